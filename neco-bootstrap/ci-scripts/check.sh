@@ -41,3 +41,25 @@ if command -v nvidia-smi >/dev/null 2>&1 && \
 else
     cargo test --workspace --offline
 fi
+
+# Check the exported Coq and Lean files of the snapshots with the provers
+# that are installed
+echo "Checking exported Coq and Lean files..."
+EXPORT_DIR="$(mktemp -d)"
+trap 'rm -rf "$EXPORT_DIR"' EXIT
+for snapshot in neco-felis-type-check/src/snapshots/*__export__*.snap; do
+    name="$(basename "$snapshot" .snap)"
+    name="${name##*__}"
+    case "$name" in
+        *_coq) prover=coqc; file="$name.v" ;;
+        *_lean) prover=lean; file="$name.lean" ;;
+        *) continue ;;
+    esac
+    if ! command -v "$prover" >/dev/null 2>&1; then
+        echo "Skipping $name: $prover is not installed"
+        continue
+    fi
+    # Drop the header of the snapshot
+    sed '1,/^---$/d' "$snapshot" > "$EXPORT_DIR/$file"
+    (cd "$EXPORT_DIR" && "$prover" "$file")
+done
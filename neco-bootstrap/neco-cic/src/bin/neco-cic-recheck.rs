@@ -0,0 +1,20 @@
+use neco_cic::{
+    global_environment::GlobalEnvironment, id::IdGenerator, serialize::EnvironmentFile,
+};
+
+/// Checks an environment file with the kernel alone, independently of the
+/// type checker that wrote it
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<_> = std::env::args().collect();
+    if args.len() != 2 {
+        eprintln!("Usage: {} <environment file>", args[0]);
+        std::process::exit(1);
+    }
+
+    let contents = std::fs::read_to_string(&args[1])?;
+    let file = EnvironmentFile::read(&contents, &mut IdGenerator::new())?;
+    let mut env = GlobalEnvironment::new();
+    file.recheck(&mut env)?;
+    println!("{}: {} items checked", args[1], file.items.len());
+    Ok(())
+}
@@ -0,0 +1,932 @@
+use std::{cmp::Ordering, rc::Rc};
+
+use crate::{
+    global_environment::GlobalEnvironment,
+    id::Id,
+    intern,
+    local_context::LocalContext,
+    nbe::{Closure, Env, Evaluator, Head, StuckMatch, Value},
+    term::{Sort, Term},
+};
+
+/// Checks if two terms are convertible (definitionally equal) in `ctx`.
+///
+/// Both terms are evaluated (see `nbe`) and their values are compared one
+/// weak head normal form at a time instead of being read back:
+/// - identical terms are accepted without any evaluation (interned terms are
+///   compared by pointer first),
+/// - β, ι and ζ redexes are contracted by evaluation,
+/// - constants are unfolded lazily: applications of the same constant compare
+///   their arguments first, otherwise the constant with the greater definition
+///   height is unfolded,
+/// - a lambda is convertible with any term `t` whose η-expansion matches it
+///   (`f ≡ λx. f x`), and so is a record built by its constructor
+///   (`r ≡ C (proj_1 r) .. (proj_n r)`),
+/// - proofs are irrelevant: two neutral values whose types are convertible
+///   propositions are convertible. Their types are inferred from their heads,
+///   typed by `ctx`, the global environment and the binders entered so far.
+pub fn is_convertible(
+    ctx: &LocalContext,
+    env: &GlobalEnvironment,
+    term1: &Term,
+    term2: &Term,
+) -> bool {
+    if std::ptr::eq(term1, term2) || term1 == term2 {
+        return true;
+    }
+    let converter = Converter {
+        evaluator: Evaluator::new(env),
+        env,
+        ctx,
+    };
+    let value1 = converter.evaluator.eval(term1, &Env::new());
+    let value2 = converter.evaluator.eval(term2, &Env::new());
+    converter.convert(value1, value2, &Levels::default())
+}
+
+/// Types of the variables introduced by the comparison under binders, by de
+/// Bruijn level. The type of a variable bound by a match branch is unknown
+/// when the type of the scrutinee cannot be inferred.
+#[derive(Clone, Default)]
+struct Levels {
+    node: Option<Rc<LevelNode>>,
+    len: usize,
+}
+
+struct LevelNode {
+    ty: Option<Rc<Value>>,
+    next: Option<Rc<LevelNode>>,
+}
+
+impl Levels {
+    /// Introduces the variable with the next level
+    fn push(&self, ty: Option<Rc<Value>>) -> Self {
+        Levels {
+            node: Some(Rc::new(LevelNode {
+                ty,
+                next: self.node.clone(),
+            })),
+            len: self.len + 1,
+        }
+    }
+
+    /// The number of variables introduced, which is the next level
+    fn depth(&self) -> usize {
+        self.len
+    }
+
+    /// The type of the variable with level `level`, if it is known
+    fn get(&self, level: usize) -> Option<Rc<Value>> {
+        let mut node = self.node.as_ref()?;
+        for _ in 0..self.len.checked_sub(level + 1)? {
+            node = node.next.as_ref()?;
+        }
+        node.ty.clone()
+    }
+}
+
+struct Converter<'a> {
+    evaluator: Evaluator<'a>,
+    env: &'a GlobalEnvironment,
+    ctx: &'a LocalContext,
+}
+
+impl Converter<'_> {
+    /// Compares two values under the binders of `levels`
+    fn convert(&self, value1: Rc<Value>, value2: Rc<Value>, levels: &Levels) -> bool {
+        let mut value1 = value1;
+        let mut value2 = value2;
+
+        loop {
+            if Rc::ptr_eq(&value1, &value2) {
+                return true;
+            }
+
+            match (value1.as_ref(), value2.as_ref()) {
+                (Value::Sort(sort1), Value::Sort(sort2)) => return sort1 == sort2,
+                (Value::Literal(a), Value::Literal(b)) => return a == b,
+                (Value::Product(binder1), Value::Product(binder2))
+                | (Value::Lambda(binder1), Value::Lambda(binder2)) => {
+                    let var = [Value::level(levels.depth())];
+                    return self.convert(binder1.domain.clone(), binder2.domain.clone(), levels)
+                        && (same_body(&binder1.closure, &binder2.closure, 1)
+                            || self.convert(
+                                self.evaluator.instantiate(&binder1.closure, &var),
+                                self.evaluator.instantiate(&binder2.closure, &var),
+                                &levels.push(Some(binder1.domain.clone())),
+                            ));
+                }
+                // η: λx. t ≡ u  iff  t ≡ u x
+                (Value::Lambda(binder), _) => {
+                    let var = Value::level(levels.depth());
+                    return self.convert(
+                        self.evaluator
+                            .instantiate(&binder.closure, std::slice::from_ref(&var)),
+                        self.evaluator.apply(value2.clone(), var),
+                        &levels.push(Some(binder.domain.clone())),
+                    );
+                }
+                (_, Value::Lambda(binder)) => {
+                    let var = Value::level(levels.depth());
+                    return self.convert(
+                        self.evaluator.apply(value1.clone(), var.clone()),
+                        self.evaluator.instantiate(&binder.closure, &[var]),
+                        &levels.push(Some(binder.domain.clone())),
+                    );
+                }
+                (Value::Delayed(c1, spine1), Value::Delayed(c2, spine2)) => {
+                    if c1 == c2 && self.convert_spines(spine1, spine2, levels) {
+                        return true;
+                    }
+                    match self
+                        .env
+                        .constant_height(*c1)
+                        .cmp(&self.env.constant_height(*c2))
+                    {
+                        Ordering::Greater => value1 = self.unfold(&value1),
+                        Ordering::Less => value2 = self.unfold(&value2),
+                        Ordering::Equal => {
+                            value1 = self.unfold(&value1);
+                            value2 = self.unfold(&value2);
+                        }
+                    }
+                }
+                (Value::Delayed(_, _), _) => value1 = self.unfold(&value1),
+                (_, Value::Delayed(_, _)) => value2 = self.unfold(&value2),
+                (Value::Neutral(head1, spine1), Value::Neutral(head2, spine2)) => {
+                    return (self.convert_heads(head1, head2, levels)
+                        && self.convert_spines(spine1, spine2, levels))
+                        || self.convert_record_eta(&value1, &value2, levels)
+                        || self.convert_record_eta(&value2, &value1, levels)
+                        || self.convert_proofs(&value1, &value2, levels);
+                }
+                _ => return false,
+            }
+        }
+    }
+
+    /// η for records: `C a_1 .. a_n ≡ r`  iff  `a_i ≡ proj_i r` for every
+    /// field, when `C` is the constructor of a record type and `r` is not a
+    /// constructor application
+    fn convert_record_eta(&self, record: &Rc<Value>, other: &Rc<Value>, levels: &Levels) -> bool {
+        let Value::Neutral(Head::Constant(constructor), fields) = record.as_ref() else {
+            return false;
+        };
+        let Some(projections) = self.env.record_projections(*constructor) else {
+            return false;
+        };
+        if fields.len() != projections.len()
+            || matches!(other.as_ref(), Value::Neutral(Head::Constant(c), _) if c == constructor)
+        {
+            return false;
+        }
+        projections.iter().zip(fields).all(|(projection, field)| {
+            let projected = Rc::new(Value::Delayed(*projection, vec![other.clone()]));
+            self.convert(field.clone(), projected, levels)
+        })
+    }
+
+    /// Proof irrelevance: two proofs of convertible propositions are
+    /// convertible. Only tried once the values differ structurally.
+    fn convert_proofs(&self, value1: &Value, value2: &Value, levels: &Levels) -> bool {
+        let Some(proposition1) = self.infer(value1, levels) else {
+            return false;
+        };
+        if !self.is_proposition(proposition1.clone(), levels) {
+            return false;
+        }
+        let Some(proposition2) = self.infer(value2, levels) else {
+            return false;
+        };
+        self.convert(proposition1, proposition2, levels)
+    }
+
+    /// Compares the arguments of two applications
+    fn convert_spines(&self, spine1: &[Rc<Value>], spine2: &[Rc<Value>], levels: &Levels) -> bool {
+        spine1.len() == spine2.len()
+            && spine1
+                .iter()
+                .zip(spine2)
+                .all(|(arg1, arg2)| self.convert(arg1.clone(), arg2.clone(), levels))
+    }
+
+    /// Variables and opaque constants (axioms, inductive types and
+    /// constructors) are rigid heads compared by identity
+    fn convert_heads(&self, head1: &Head, head2: &Head, levels: &Levels) -> bool {
+        match (head1, head2) {
+            (Head::Variable(id1), Head::Variable(id2)) => id1 == id2,
+            (Head::Level(level1), Head::Level(level2)) => level1 == level2,
+            (Head::Loose(index1), Head::Loose(index2)) => index1 == index2,
+            (Head::Constant(id1), Head::Constant(id2)) => id1 == id2,
+            (Head::Match(case1), Head::Match(case2)) => self.convert_match(case1, case2, levels),
+            (Head::Irreducible(value1), Head::Irreducible(value2)) => {
+                self.convert(value1.clone(), value2.clone(), levels)
+            }
+            _ => false,
+        }
+    }
+
+    /// Compares two stuck match expressions branch by branch
+    fn convert_match(&self, case1: &StuckMatch, case2: &StuckMatch, levels: &Levels) -> bool {
+        if case1.branches.len() != case2.branches.len()
+            || !self.convert(case1.scrutinee.clone(), case2.scrutinee.clone(), levels)
+            || !self.convert(case1.return_type.clone(), case2.return_type.clone(), levels)
+        {
+            return false;
+        }
+
+        case1.branches.iter().all(|branch1| {
+            let Some(branch2) = case2
+                .branches
+                .iter()
+                .find(|branch2| branch2.constructor_id == branch1.constructor_id)
+            else {
+                return false;
+            };
+            let count = branch1.bound_vars.len();
+            if count != branch2.bound_vars.len() {
+                return false;
+            }
+            if same_body(&branch1.closure, &branch2.closure, count) {
+                return true;
+            }
+            let depth = levels.depth();
+            let vars: Vec<_> = (depth..depth + count).map(Value::level).collect();
+            let branch_levels = self
+                .branch_types(case1, branch1.constructor_id, count, levels)
+                .into_iter()
+                .fold(levels.clone(), |levels, ty| levels.push(ty));
+            self.convert(
+                self.evaluator.instantiate(&branch1.closure, &vars),
+                self.evaluator.instantiate(&branch2.closure, &vars),
+                &branch_levels,
+            )
+        })
+    }
+
+    /// The type of a neutral value, computed from the type of its head
+    fn infer(&self, value: &Value, levels: &Levels) -> Option<Rc<Value>> {
+        let Value::Neutral(head, spine) = value else {
+            return None;
+        };
+        let ty = match head {
+            Head::Variable(id) => self.eval_closed(&*self.ctx.lookup(*id)?),
+            Head::Level(level) => levels.get(*level)?,
+            Head::Loose(index) => self.eval_closed(&*self.ctx.lookup_bound(*index)?),
+            Head::Constant(id) => self.eval_closed(&*self.env.constant_type(*id)?),
+            Head::Match(case) => case.return_type.clone(),
+            Head::Irreducible(_) => return None,
+        };
+        self.instantiate_product(ty, spine)
+    }
+
+    /// Whether `ty` is a proposition, that is a type of type Prop
+    fn is_proposition(&self, ty: Rc<Value>, levels: &Levels) -> bool {
+        let ty = self.evaluator.force(ty);
+        match ty.as_ref() {
+            Value::Product(binder) => self.is_proposition(
+                self.evaluator
+                    .instantiate(&binder.closure, &[Value::level(levels.depth())]),
+                &levels.push(Some(binder.domain.clone())),
+            ),
+            Value::Neutral(_, _) => self.infer(&ty, levels).is_some_and(|sort| {
+                matches!(self.evaluator.force(sort).as_ref(), Value::Sort(Sort::Prop))
+            }),
+            _ => false,
+        }
+    }
+
+    /// The types of the `count` variables bound by the branch of
+    /// `constructor`, from the parameters of the type of the scrutinee
+    fn branch_types(
+        &self,
+        case: &StuckMatch,
+        constructor: Id,
+        count: usize,
+        levels: &Levels,
+    ) -> Vec<Option<Rc<Value>>> {
+        let mut ty = self.constructor_type(case, constructor, levels);
+        (levels.depth()..levels.depth() + count)
+            .map(|level| {
+                let binder = ty
+                    .take()
+                    .and_then(|ty| match self.evaluator.force(ty).as_ref() {
+                        Value::Product(binder) => Some(binder.clone()),
+                        _ => None,
+                    })?;
+                ty = Some(
+                    self.evaluator
+                        .instantiate(&binder.closure, &[Value::level(level)]),
+                );
+                Some(binder.domain)
+            })
+            .collect()
+    }
+
+    /// The type of `constructor` applied to the parameters of the type of the
+    /// scrutinee of `case`
+    fn constructor_type(
+        &self,
+        case: &StuckMatch,
+        constructor: Id,
+        levels: &Levels,
+    ) -> Option<Rc<Value>> {
+        let scrutinee_type = self.evaluator.force(self.infer(&case.scrutinee, levels)?);
+        let Value::Neutral(Head::Constant(inductive), args) = scrutinee_type.as_ref() else {
+            return None;
+        };
+        let parameters = self
+            .env
+            .inductives
+            .get_inductive(*inductive)?
+            .parameters
+            .len();
+        let ty = self.eval_closed(&self.env.inductives.get_constructor(constructor)?.ty);
+        self.instantiate_product(ty, args.get(..parameters)?)
+    }
+
+    /// Instantiates the product type `ty` with `args`
+    fn instantiate_product(&self, ty: Rc<Value>, args: &[Rc<Value>]) -> Option<Rc<Value>> {
+        args.iter()
+            .try_fold(ty, |ty, arg| match self.evaluator.force(ty).as_ref() {
+                Value::Product(binder) => Some(
+                    self.evaluator
+                        .instantiate(&binder.closure, std::slice::from_ref(arg)),
+                ),
+                _ => None,
+            })
+    }
+
+    /// Evaluates a term of the local context or of the global environment
+    fn eval_closed(&self, term: &Term) -> Rc<Value> {
+        self.evaluator.eval(term, &Env::new())
+    }
+
+    /// Unfolds the head constant of a delayed value
+    fn unfold(&self, value: &Value) -> Rc<Value> {
+        self.evaluator
+            .unfold(value)
+            .expect("head constant should be unfoldable")
+    }
+}
+
+/// Two closures with the same interned body that only refers to its own
+/// `count` binders evaluate to the same value for the same arguments
+fn same_body(closure1: &Closure, closure2: &Closure, count: usize) -> bool {
+    Rc::ptr_eq(closure1.body(), closure2.body())
+        && intern::info(closure1.body()).is_some_and(|info| info.loose_bound <= count)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use crate::{
+        global_environment::GlobalEnvironment,
+        id::Id,
+        inductive::{ConstructorDefinition, InductiveDefinition},
+        local_context::LocalContext,
+        nbe::{Env, Evaluator},
+        term::{
+            Sort, Term, TermApplication, TermBound, TermConstant, TermLambda, TermMatch,
+            TermMatchBranch, TermProduct, TermSort, TermVariable,
+        },
+    };
+
+    use super::{Converter, Levels, is_convertible};
+
+    fn constant(id: Id) -> Term {
+        Term::Constant(TermConstant { id })
+    }
+
+    fn variable(id: Id) -> Term {
+        Term::Variable(TermVariable { id })
+    }
+
+    fn bound(index: usize) -> Term {
+        Term::Bound(TermBound { index })
+    }
+
+    fn apply(f: Term, args: Vec<Term>) -> Term {
+        Term::Application(TermApplication {
+            f: Rc::new(f),
+            args,
+        })
+    }
+
+    /// Builds nat with `add` defined by recursion on its first argument
+    fn nat_env() -> (GlobalEnvironment, Id, Id, Id, Id) {
+        let mut env = GlobalEnvironment::new();
+        let nat_id = Id::new();
+        let zero_id = Id::new();
+        let succ_id = Id::new();
+        env.inductives.add_nat(nat_id, zero_id, succ_id).unwrap();
+
+        let add_id = Id::new();
+        let n = Id::new();
+        let m = Id::new();
+        let p = Id::new();
+        let nat = Rc::new(constant(nat_id));
+        let add_type = Term::Product(TermProduct {
+            var: n,
+            source: nat.clone(),
+            target: Rc::new(Term::Product(TermProduct {
+                var: m,
+                source: nat.clone(),
+                target: nat.clone(),
+            })),
+        });
+        let add_body = Term::Lambda(TermLambda {
+            var: n,
+            source_ty: nat.clone(),
+            target: Rc::new(Term::Lambda(TermLambda {
+                var: m,
+                source_ty: nat.clone(),
+                target: Rc::new(Term::Match(TermMatch {
+                    scrutinee: Rc::new(bound(1)),
+                    return_type: nat,
+                    branches: vec![
+                        TermMatchBranch {
+                            constructor_id: zero_id,
+                            bound_vars: vec![],
+                            body: Rc::new(bound(0)),
+                        },
+                        TermMatchBranch {
+                            constructor_id: succ_id,
+                            bound_vars: vec![p],
+                            body: Rc::new(apply(
+                                constant(succ_id),
+                                vec![apply(constant(add_id), vec![bound(0), bound(1)])],
+                            )),
+                        },
+                    ],
+                })),
+            })),
+        });
+        env.add_definition(add_id, Rc::new(add_type), Rc::new(add_body))
+            .unwrap();
+
+        (env, nat_id, zero_id, succ_id, add_id)
+    }
+
+    fn numeral(zero_id: Id, succ_id: Id, n: usize) -> Term {
+        (0..n).fold(constant(zero_id), |acc, _| {
+            apply(constant(succ_id), vec![acc])
+        })
+    }
+
+    #[test]
+    fn test_add_computes_by_lazy_unfolding() {
+        let (env, _, zero_id, succ_id, add_id) = nat_env();
+        let lhs = apply(
+            constant(add_id),
+            vec![numeral(zero_id, succ_id, 2), numeral(zero_id, succ_id, 3)],
+        );
+        assert!(is_convertible(
+            &LocalContext::new(),
+            &env,
+            &lhs,
+            &numeral(zero_id, succ_id, 5)
+        ));
+        assert!(!is_convertible(
+            &LocalContext::new(),
+            &env,
+            &lhs,
+            &numeral(zero_id, succ_id, 4)
+        ));
+    }
+
+    /// Whether two closed terms are convertible, with the number of
+    /// constants unfolded to decide it
+    fn convert_counting(env: &GlobalEnvironment, term1: &Term, term2: &Term) -> (bool, usize) {
+        let ctx = LocalContext::new();
+        let converter = Converter {
+            evaluator: Evaluator::new(env),
+            env,
+            ctx: &ctx,
+        };
+        let value1 = converter.eval_closed(term1);
+        let value2 = converter.eval_closed(term2);
+        let convertible = converter.convert(value1, value2, &Levels::default());
+        (convertible, converter.evaluator.unfoldings())
+    }
+
+    #[test]
+    fn test_unfolds_lazily() {
+        // The arguments of add are compared before add is unfolded, while
+        // normalizing both sides unfolds add once per successor of 50
+        let (env, _, zero_id, succ_id, add_id) = nat_env();
+        let n = |k| numeral(zero_id, succ_id, k);
+        let lhs = apply(
+            constant(add_id),
+            vec![n(50), apply(constant(add_id), vec![n(1), n(1)])],
+        );
+        let rhs = apply(constant(add_id), vec![n(50), n(2)]);
+        assert_eq!(convert_counting(&env, &lhs, &rhs), (true, 2));
+
+        let evaluator = Evaluator::new(&env);
+        for term in [&lhs, &rhs] {
+            evaluator.quote(&evaluator.eval(term, &Env::new()), 0);
+        }
+        assert_eq!(evaluator.unfoldings(), 2 * 51 + 2);
+    }
+
+    #[test]
+    fn test_same_head_with_stuck_arguments() {
+        let (env, _, _, _, add_id) = nat_env();
+        let x = Id::new();
+        let y = Id::new();
+        let add_x_y = apply(constant(add_id), vec![variable(x), variable(y)]);
+        let add_y_x = apply(constant(add_id), vec![variable(y), variable(x)]);
+        assert!(is_convertible(
+            &LocalContext::new(),
+            &env,
+            &add_x_y,
+            &add_x_y.clone()
+        ));
+        assert!(!is_convertible(
+            &LocalContext::new(),
+            &env,
+            &add_x_y,
+            &add_y_x
+        ));
+    }
+
+    #[test]
+    fn test_eta_conversion() {
+        // f ≡ λx:nat. f x
+        let (env, nat_id, _, _, _) = nat_env();
+        let f = Id::new();
+        let x = Id::new();
+        let eta = Term::Lambda(TermLambda {
+            var: x,
+            source_ty: Rc::new(constant(nat_id)),
+            target: Rc::new(apply(variable(f), vec![bound(0)])),
+        });
+        assert!(is_convertible(
+            &LocalContext::new(),
+            &env,
+            &variable(f),
+            &eta
+        ));
+        assert!(is_convertible(
+            &LocalContext::new(),
+            &env,
+            &eta,
+            &variable(f)
+        ));
+    }
+
+    #[test]
+    fn test_eta_conversion_with_defined_constant() {
+        // add ≡ λx:nat. add x
+        let (env, nat_id, _, _, add_id) = nat_env();
+        let x = Id::new();
+        let eta = Term::Lambda(TermLambda {
+            var: x,
+            source_ty: Rc::new(constant(nat_id)),
+            target: Rc::new(apply(constant(add_id), vec![bound(0)])),
+        });
+        assert!(is_convertible(
+            &LocalContext::new(),
+            &env,
+            &constant(add_id),
+            &eta
+        ));
+    }
+
+    #[test]
+    fn test_alpha_equivalent_products() {
+        let env = GlobalEnvironment::new();
+        let x = Id::new();
+        let y = Id::new();
+        let set = Rc::new(Term::Sort(TermSort { sort: Sort::Set }));
+        let px = Term::Product(TermProduct {
+            var: x,
+            source: set.clone(),
+            target: Rc::new(bound(0)),
+        });
+        let py = Term::Product(TermProduct {
+            var: y,
+            source: set,
+            target: Rc::new(bound(0)),
+        });
+        assert!(is_convertible(&LocalContext::new(), &env, &px, &py));
+    }
+
+    #[test]
+    fn test_unfolds_higher_definition_first() {
+        // double n := add n n, so double (S O) ≡ add (S O) (S O)
+        let (mut env, nat_id, zero_id, succ_id, add_id) = nat_env();
+        let double_id = Id::new();
+        let n = Id::new();
+        let nat = Rc::new(constant(nat_id));
+        env.add_definition(
+            double_id,
+            Rc::new(Term::Product(TermProduct {
+                var: n,
+                source: nat.clone(),
+                target: nat.clone(),
+            })),
+            Rc::new(Term::Lambda(TermLambda {
+                var: n,
+                source_ty: nat,
+                target: Rc::new(apply(constant(add_id), vec![bound(0), bound(0)])),
+            })),
+        )
+        .unwrap();
+        assert!(env.constant_height(double_id) > env.constant_height(add_id));
+
+        let one = numeral(zero_id, succ_id, 1);
+        let lhs = apply(constant(double_id), vec![one.clone()]);
+        let rhs = apply(constant(add_id), vec![one.clone(), one]);
+        assert!(is_convertible(&LocalContext::new(), &env, &lhs, &rhs));
+        assert!(is_convertible(
+            &LocalContext::new(),
+            &env,
+            &lhs,
+            &numeral(zero_id, succ_id, 2)
+        ));
+    }
+
+    #[test]
+    fn test_opaque_constants_are_not_unfolded() {
+        // two := S (S O) as a definition and as a theorem
+        let (mut env, nat_id, zero_id, succ_id, _) = nat_env();
+        let two = numeral(zero_id, succ_id, 2);
+        let transparent = Id::new();
+        let opaque = Id::new();
+        let nat = Rc::new(constant(nat_id));
+        env.add_definition(transparent, nat.clone(), Rc::new(two.clone()))
+            .unwrap();
+        env.add_theorem(opaque, nat, Rc::new(two.clone())).unwrap();
+
+        assert!(is_convertible(
+            &LocalContext::new(),
+            &env,
+            &constant(transparent),
+            &two
+        ));
+        assert!(!is_convertible(
+            &LocalContext::new(),
+            &env,
+            &constant(opaque),
+            &two
+        ));
+        assert!(is_convertible(
+            &LocalContext::new(),
+            &env,
+            &constant(opaque),
+            &constant(opaque)
+        ));
+        assert_eq!(env.constant_height(opaque), 0);
+        assert_eq!(
+            crate::reduction::whnf_with_env(&constant(opaque), &env),
+            constant(opaque)
+        );
+    }
+
+    /// Builds `point := mk nat nat` with its projections `px` and `py`
+    fn point_env() -> (GlobalEnvironment, Id, Id, Id, Id) {
+        let (mut env, nat_id, _, _, _) = nat_env();
+        let point_id = Id::new();
+        let mk_id = Id::new();
+        let nat = Rc::new(constant(nat_id));
+        let set = Rc::new(Term::Sort(TermSort { sort: Sort::Set }));
+        let mk_type = Term::Product(TermProduct {
+            var: Id::new(),
+            source: nat.clone(),
+            target: Rc::new(Term::Product(TermProduct {
+                var: Id::new(),
+                source: nat.clone(),
+                target: Rc::new(constant(point_id)),
+            })),
+        });
+        env.inductives
+            .add_inductive(InductiveDefinition {
+                name: point_id,
+                parameters: vec![],
+                sort: set,
+                constructors: vec![ConstructorDefinition {
+                    name: mk_id,
+                    ty: Rc::new(mk_type),
+                    arity: 2,
+                }],
+            })
+            .unwrap();
+
+        let projections = [Id::new(), Id::new()];
+        for (i, projection) in projections.iter().enumerate() {
+            let body = Term::Lambda(TermLambda {
+                var: Id::new(),
+                source_ty: Rc::new(constant(point_id)),
+                target: Rc::new(Term::Match(TermMatch {
+                    scrutinee: Rc::new(bound(0)),
+                    return_type: nat.clone(),
+                    branches: vec![TermMatchBranch {
+                        constructor_id: mk_id,
+                        bound_vars: vec![Id::new(), Id::new()],
+                        body: Rc::new(bound(1 - i)),
+                    }],
+                })),
+            });
+            let ty = Term::Product(TermProduct {
+                var: Id::new(),
+                source: Rc::new(constant(point_id)),
+                target: nat.clone(),
+            });
+            env.add_definition(*projection, Rc::new(ty), Rc::new(body))
+                .unwrap();
+        }
+        env.add_record(point_id, projections.to_vec()).unwrap();
+
+        (env, point_id, mk_id, projections[0], projections[1])
+    }
+
+    #[test]
+    fn test_record_eta_conversion() {
+        // p ≡ mk (px p) (py p)
+        let (env, _, mk_id, px_id, py_id) = point_env();
+        let p = variable(Id::new());
+        let expanded = apply(
+            constant(mk_id),
+            vec![
+                apply(constant(px_id), vec![p.clone()]),
+                apply(constant(py_id), vec![p.clone()]),
+            ],
+        );
+        assert!(is_convertible(&LocalContext::new(), &env, &p, &expanded));
+        assert!(is_convertible(&LocalContext::new(), &env, &expanded, &p));
+
+        let swapped = apply(
+            constant(mk_id),
+            vec![
+                apply(constant(py_id), vec![p.clone()]),
+                apply(constant(px_id), vec![p.clone()]),
+            ],
+        );
+        assert!(!is_convertible(&LocalContext::new(), &env, &p, &swapped));
+    }
+
+    #[test]
+    fn test_add_record_checks_the_projections() {
+        let (mut env, point_id, _, px_id, py_id) = point_env();
+        assert!(env.add_record(point_id, vec![py_id, px_id]).is_err());
+        assert!(env.add_record(point_id, vec![px_id]).is_err());
+    }
+
+    fn prop() -> Rc<Term> {
+        Rc::new(Term::Sort(TermSort { sort: Sort::Prop }))
+    }
+
+    fn arrow(source: Term, target: Term) -> Term {
+        Term::Product(TermProduct {
+            var: Id::new(),
+            source: Rc::new(source),
+            target: Rc::new(target),
+        })
+    }
+
+    /// Adds `P : Prop`, its proofs `p1` and `p2` and
+    /// `g : nat -> P -> nat -> nat` to `nat_env`
+    fn proof_env() -> (GlobalEnvironment, Id, Id, Id, Id, Id, Id) {
+        let (mut env, nat_id, zero_id, _, _) = nat_env();
+        let (p, p1, p2, g) = (Id::new(), Id::new(), Id::new(), Id::new());
+        env.add_axiom(p, prop()).unwrap();
+        env.add_axiom(p1, Rc::new(constant(p))).unwrap();
+        env.add_axiom(p2, Rc::new(constant(p))).unwrap();
+        let g_ty = arrow(
+            constant(nat_id),
+            arrow(constant(p), arrow(constant(nat_id), constant(nat_id))),
+        );
+        env.add_axiom(g, Rc::new(g_ty)).unwrap();
+        (env, nat_id, zero_id, p, p1, p2, g)
+    }
+
+    #[test]
+    fn test_proofs_are_irrelevant_under_applications() {
+        // k1 := g x p1 and k2 := g x p2, compared inside `f k`
+        let (mut env, nat_id, zero_id, _, p1, p2, g) = proof_env();
+        let x = Id::new();
+        env.add_axiom(x, Rc::new(constant(nat_id))).unwrap();
+        let fun = arrow(constant(nat_id), constant(nat_id));
+        let (k1, k2) = (Id::new(), Id::new());
+        for (k, proof) in [(k1, p1), (k2, p2)] {
+            let body = apply(constant(g), vec![constant(x), constant(proof)]);
+            env.add_definition(k, Rc::new(fun.clone()), Rc::new(body))
+                .unwrap();
+        }
+        let f = Id::new();
+        env.add_axiom(f, Rc::new(arrow(fun, constant(nat_id))))
+            .unwrap();
+
+        let ctx = LocalContext::new();
+        assert!(is_convertible(&ctx, &env, &constant(p1), &constant(p2)));
+        assert!(is_convertible(&ctx, &env, &constant(k1), &constant(k2)));
+        assert!(is_convertible(
+            &ctx,
+            &env,
+            &apply(constant(f), vec![constant(k1)]),
+            &apply(constant(f), vec![constant(k2)])
+        ));
+
+        // The arguments that are not proofs are still compared
+        let one = apply(constant(g), vec![constant(zero_id), constant(p1)]);
+        assert!(!is_convertible(&ctx, &env, &constant(k1), &one));
+    }
+
+    #[test]
+    fn test_proofs_are_irrelevant_under_binders() {
+        // λh:P. g x h O ≡ λh:P. g x p1 O, and with h a variable of the context
+        let (env, nat_id, zero_id, p, p1, _, g) = proof_env();
+        let x = variable(Id::new());
+        let call = |proof: Term| apply(constant(g), vec![x.clone(), proof, constant(zero_id)]);
+        let lambda = |body: Term| {
+            Term::Lambda(TermLambda {
+                var: Id::new(),
+                source_ty: Rc::new(constant(p)),
+                target: Rc::new(body),
+            })
+        };
+        let mut ctx = LocalContext::new();
+        let h = Id::new();
+        ctx.extend(h, Rc::new(constant(p))).unwrap();
+
+        assert!(is_convertible(
+            &ctx,
+            &env,
+            &lambda(call(bound(0))),
+            &lambda(call(constant(p1)))
+        ));
+        assert!(is_convertible(
+            &ctx,
+            &env,
+            &call(variable(h)),
+            &call(constant(p1))
+        ));
+
+        // Variables of informative types are not irrelevant
+        let n = Id::new();
+        ctx.extend(n, Rc::new(constant(nat_id))).unwrap();
+        assert!(!is_convertible(
+            &ctx,
+            &env,
+            &variable(n),
+            &constant(zero_id)
+        ));
+    }
+
+    #[test]
+    fn test_proofs_are_irrelevant_in_match_branches() {
+        // box := mk : P -> box, and match b { mk h => g x h O }
+        let (mut env, nat_id, zero_id, p, p1, p2, g) = proof_env();
+        let (box_id, mk_id) = (Id::new(), Id::new());
+        env.inductives
+            .add_inductive(InductiveDefinition {
+                name: box_id,
+                parameters: vec![],
+                sort: Rc::new(Term::Sort(TermSort { sort: Sort::Set })),
+                constructors: vec![ConstructorDefinition {
+                    name: mk_id,
+                    ty: Rc::new(arrow(constant(p), constant(box_id))),
+                    arity: 1,
+                }],
+            })
+            .unwrap();
+        let b = Id::new();
+        let mut ctx = LocalContext::new();
+        ctx.extend(b, Rc::new(constant(box_id))).unwrap();
+        let x = constant(zero_id);
+        let case = |body: Term| {
+            Term::Match(TermMatch {
+                scrutinee: Rc::new(variable(b)),
+                return_type: Rc::new(constant(nat_id)),
+                branches: vec![TermMatchBranch {
+                    constructor_id: mk_id,
+                    bound_vars: vec![Id::new()],
+                    body: Rc::new(body),
+                }],
+            })
+        };
+        let call = |proof: Term| apply(constant(g), vec![x.clone(), proof, constant(zero_id)]);
+
+        assert!(is_convertible(
+            &ctx,
+            &env,
+            &case(call(constant(p1))),
+            &case(call(constant(p2)))
+        ));
+        assert!(is_convertible(
+            &ctx,
+            &env,
+            &case(call(bound(0))),
+            &case(call(constant(p1)))
+        ));
+        assert!(!is_convertible(
+            &ctx,
+            &env,
+            &case(call(bound(0))),
+            &case(x.clone())
+        ));
+    }
+}
@@ -0,0 +1,910 @@
+use std::{
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
+
+use crate::{
+    global_environment::{ConstantDefinition, GlobalEnvironment},
+    id::Id,
+    pretty::mentions_innermost,
+    substitution::{is_closed_under, lift},
+    term::{
+        Sort, Term, TermApplication, TermBound, TermLambda, TermLetIn, TermMatch, TermMatchBranch,
+        TermProduct,
+    },
+    termination::check_termination,
+    typechecker::arity_sort,
+};
+
+/// Proof assistants a checked environment can be exported to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportTarget {
+    /// A Coq `.v` file
+    Coq,
+    /// A Lean 4 `.lean` file, declaring everything in the namespace `Felis`
+    /// so that names such as `True` do not clash with the prelude
+    Lean,
+}
+
+const COQ_KEYWORDS: &[&str] = &[
+    "as", "at", "cofix", "else", "end", "exists", "exists2", "fix", "for", "forall", "fun", "if",
+    "IF", "in", "let", "match", "mod", "Prop", "return", "SProp", "Set", "then", "Type", "using",
+    "where", "with",
+];
+
+const LEAN_KEYWORDS: &[&str] = &[
+    "abbrev",
+    "at",
+    "axiom",
+    "by",
+    "calc",
+    "class",
+    "def",
+    "deriving",
+    "do",
+    "else",
+    "end",
+    "example",
+    "for",
+    "from",
+    "fun",
+    "have",
+    "if",
+    "import",
+    "in",
+    "inductive",
+    "instance",
+    "let",
+    "match",
+    "mutual",
+    "namespace",
+    "nomatch",
+    "open",
+    "Prop",
+    "section",
+    "show",
+    "Sort",
+    "structure",
+    "then",
+    "theorem",
+    "Type",
+    "universe",
+    "variable",
+    "where",
+    "with",
+];
+
+/// Prints the inductive types and constants of `env` as a source file of
+/// `target`, with the source names in `names`. Every declaration comes after
+/// the ones it mentions, and mutually recursive ones are in a single block.
+///
+/// Names that are keywords of the target, or that are already taken by
+/// another declaration, are renamed. The parameters of inductive types are
+/// printed as such, and Lean constructors are applied to them with `@`.
+/// Matches return the non-dependent type of the kernel, except that Lean
+/// definitions by recursion on a proof go through the recursor of the
+/// proposition and are `noncomputable`.
+///
+/// `Set` and `Prop` are kept. `Type i` becomes `Type` in Coq, which infers
+/// the levels, and `Type (i + 1)` in Lean, whose `Type` is `Set`. Neither
+/// target has an impredicative `Set`, so the developments relying on it do
+/// not check there. The primitive `u64` and its operations stay axioms, and
+/// literals are printed as numerals, which only check once `u64` is replaced
+/// with an integer type of the target.
+pub fn export(
+    env: &GlobalEnvironment,
+    names: &HashMap<Id, String>,
+    target: ExportTarget,
+) -> String {
+    Exporter::new(env, names, target).export()
+}
+
+struct Exporter<'a> {
+    env: &'a GlobalEnvironment,
+    names: &'a HashMap<Id, String>,
+    target: ExportTarget,
+    /// Names of the inductive types, constructors and constants
+    globals: HashMap<Id, String>,
+    /// Names of the declarations, never used for binders
+    taken: HashSet<String>,
+}
+
+/// A definition split into the parameters bound by the lambdas of its body,
+/// its return type and the rest of its body, printed under the parameters
+struct Signature {
+    parameters: Vec<(String, String)>,
+    return_type: String,
+    body: String,
+}
+
+impl<'a> Exporter<'a> {
+    fn new(
+        env: &'a GlobalEnvironment,
+        names: &'a HashMap<Id, String>,
+        target: ExportTarget,
+    ) -> Self {
+        let mut exporter = Exporter {
+            env,
+            names,
+            target,
+            globals: HashMap::new(),
+            taken: HashSet::new(),
+        };
+        let mut ids: Vec<Id> = env
+            .inductives
+            .list_inductives()
+            .flat_map(|inductive| {
+                std::iter::once(inductive.name).chain(
+                    inductive
+                        .constructors
+                        .iter()
+                        .map(|constructor| constructor.name),
+                )
+            })
+            .chain(env.constants.keys().copied())
+            .collect();
+        ids.sort();
+        for id in ids {
+            let mut name = exporter.sanitize(id, "c");
+            if exporter.taken.contains(&name) {
+                let base = name.clone();
+                name = (1..)
+                    .map(|n| format!("{base}_{n}"))
+                    .find(|name| !exporter.taken.contains(name))
+                    .unwrap();
+            }
+            exporter.taken.insert(name.clone());
+            exporter.globals.insert(id, name);
+        }
+        exporter
+    }
+
+    fn export(&self) -> String {
+        let mut out = String::new();
+        match self.target {
+            ExportTarget::Coq => out.push_str("(* Exported from Felis *)\n"),
+            ExportTarget::Lean => out.push_str("-- Exported from Felis\n\nnamespace Felis\n"),
+        }
+        let declarations = self.env.declarations();
+        for component in self.env.components(&declarations) {
+            out.push('\n');
+            if self.env.inductives.get_inductive(component[0]).is_some() {
+                self.inductives(&component, &mut out);
+            } else {
+                self.constants(&component, &mut out);
+            }
+        }
+        if self.target == ExportTarget::Lean {
+            out.push_str("\nend Felis\n");
+        }
+        out
+    }
+
+    /// The name of `id` as an identifier of the target, or `default` if it
+    /// has no source name
+    fn sanitize(&self, id: Id, default: &str) -> String {
+        let name = self.names.get(&id).map_or(default, String::as_str);
+        let mut name: String = name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        if !name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+            name.insert_str(0, default);
+        }
+        let keywords = match self.target {
+            ExportTarget::Coq => COQ_KEYWORDS,
+            ExportTarget::Lean => LEAN_KEYWORDS,
+        };
+        if name == "_" || keywords.contains(&name.as_str()) {
+            name.push('_');
+        }
+        name
+    }
+
+    /// A reference to a declaration. Lean puts constructors in the namespace
+    /// of their type.
+    fn reference(&self, id: Id) -> String {
+        let name = self
+            .globals
+            .get(&id)
+            .cloned()
+            .unwrap_or_else(|| self.sanitize(id, "c"));
+        match self.env.inductives.get_inductive_for_constructor(id) {
+            Some(inductive) if self.target == ExportTarget::Lean => {
+                format!("{}.{name}", self.globals[&inductive])
+            }
+            _ => name,
+        }
+    }
+
+    /// A name for a binder, primed while it would shadow a binder on the
+    /// stack or a declaration
+    fn binder_name(&self, var: Id, binders: &[String]) -> String {
+        let mut name = self.sanitize(var, "x");
+        while binders.contains(&name) || self.taken.contains(&name) {
+            name.push('\'');
+        }
+        name
+    }
+
+    /// A block of mutual inductive types
+    fn inductives(&self, block: &[Id], out: &mut String) {
+        let mutual = self.target == ExportTarget::Lean && block.len() > 1;
+        if mutual {
+            out.push_str("mutual\n");
+        }
+        for (i, id) in block.iter().enumerate() {
+            let inductive = self.env.inductives.get_inductive(*id).unwrap();
+            // The constructors are printed under the parameters
+            let mut binders = Vec::new();
+            let mut parameters = String::new();
+            for parameter in &inductive.parameters {
+                let name = self.binder_name(parameter.name, &binders);
+                let ty = self.print_under(&parameter.ty, &mut binders);
+                parameters.push_str(&format!(" ({name} : {ty})"));
+                binders.push(name);
+            }
+            let arity = self.print_under(&inductive.sort, &mut binders);
+            let name = &self.globals[id];
+            out.push_str(&match (self.target, i) {
+                (ExportTarget::Coq, 0) => format!("Inductive {name}{parameters} : {arity} :="),
+                (ExportTarget::Coq, _) => format!("\nwith {name}{parameters} : {arity} :="),
+                (ExportTarget::Lean, _) if inductive.constructors.is_empty() => {
+                    format!("inductive {name}{parameters} : {arity}\n")
+                }
+                (ExportTarget::Lean, _) => {
+                    format!("inductive {name}{parameters} : {arity} where\n")
+                }
+            });
+            for constructor in &inductive.constructors {
+                let mut ty = constructor.ty.as_ref();
+                for _ in &inductive.parameters {
+                    if let Term::Product(product) = ty {
+                        ty = &product.target;
+                    }
+                }
+                let ty = self.print_under(ty, &mut binders);
+                let name = &self.globals[&constructor.name];
+                match self.target {
+                    ExportTarget::Coq => out.push_str(&format!("\n  | {name} : {ty}")),
+                    ExportTarget::Lean => out.push_str(&format!("  | {name} : {ty}\n")),
+                }
+            }
+        }
+        match self.target {
+            ExportTarget::Coq
+                if self
+                    .env
+                    .inductives
+                    .get_inductive(block[block.len() - 1])
+                    .is_some_and(|inductive| inductive.constructors.is_empty()) =>
+            {
+                out.push_str(" .\n");
+            }
+            ExportTarget::Coq => out.push_str(".\n"),
+            ExportTarget::Lean if mutual => out.push_str("end\n"),
+            ExportTarget::Lean => {}
+        }
+    }
+
+    /// A constant, or a block of mutually recursive constants
+    fn constants(&self, block: &[Id], out: &mut String) {
+        let defs: Vec<&ConstantDefinition> = block
+            .iter()
+            .map(|id| self.env.get_constant(*id).unwrap())
+            .collect();
+        let recursive = block.len() > 1 || self.env.dependencies(block[0]).contains(&block[0]);
+        if !recursive {
+            self.constant(defs[0], out);
+            return;
+        }
+
+        let bodies: Vec<(Id, &Term)> = defs
+            .iter()
+            .filter_map(|def| def.body.as_deref().map(|body| (def.name, body)))
+            .collect();
+        match self.target {
+            ExportTarget::Coq => {
+                let decreasing = check_termination(self.env, &bodies).ok();
+                for (i, (id, body)) in bodies.iter().enumerate() {
+                    let ty = &self.env.get_constant(*id).unwrap().ty;
+                    let signature = self.signature(ty, body);
+                    let keyword = if i == 0 { "Fixpoint" } else { "\nwith" };
+                    let parameters = Self::parameters(&signature);
+                    let structural = decreasing
+                        .as_ref()
+                        .and_then(|decreasing| decreasing[i])
+                        .and_then(|index| signature.parameters.get(index))
+                        .map_or(String::new(), |(name, _)| format!(" {{struct {name}}}"));
+                    out.push_str(&format!(
+                        "{keyword} {}{parameters}{structural} : {} :=\n  {}",
+                        self.globals[id], signature.return_type, signature.body
+                    ));
+                }
+                out.push_str(".\n");
+            }
+            ExportTarget::Lean => {
+                if block.len() > 1 {
+                    out.push_str("mutual\n");
+                }
+                for def in defs {
+                    self.constant(def, out);
+                }
+                if block.len() > 1 {
+                    out.push_str("end\n");
+                }
+            }
+        }
+    }
+
+    /// An axiom, a theorem or a definition, which may call itself in Lean
+    fn constant(&self, def: &ConstantDefinition, out: &mut String) {
+        let name = &self.globals[&def.name];
+        let ty = self.print(&def.ty);
+        let declaration = match (self.target, &def.body) {
+            (ExportTarget::Coq, None) => format!("Axiom {name} : {ty}.\n"),
+            (ExportTarget::Lean, None) => format!("axiom {name} : {ty}\n"),
+            (ExportTarget::Coq, Some(proof)) if def.opaque => format!(
+                "Theorem {name} : {ty}.\nProof.\n  exact ({}).\nQed.\n",
+                self.print(proof)
+            ),
+            (ExportTarget::Lean, Some(proof)) if def.opaque => {
+                format!("theorem {name} : {ty} :=\n  {}\n", self.print(proof))
+            }
+            (_, Some(body)) => {
+                let signature = self.signature(&def.ty, body);
+                let parameters = Self::parameters(&signature);
+                let (return_type, body) = (signature.return_type, signature.body);
+                match self.target {
+                    ExportTarget::Coq => {
+                        format!("Definition {name}{parameters} : {return_type} :=\n  {body}.\n")
+                    }
+                    ExportTarget::Lean => {
+                        let body = self.proof_recursion(def).unwrap_or(body);
+                        // The compiler cannot run code built from axioms or
+                        // recursors
+                        let noncomputable = if self.env.axioms_of(def.name).is_empty()
+                            && !self.uses_proof_recursion(def.name)
+                        {
+                            ""
+                        } else {
+                            "noncomputable "
+                        };
+                        format!(
+                            "{noncomputable}def {name}{parameters} : {return_type} :=\n  {body}\n"
+                        )
+                    }
+                }
+            }
+        };
+        out.push_str(&declaration);
+    }
+
+    /// The body of a definition by recursion on a proof, as the recursor of
+    /// its proposition applied to the branch of the match on the proof. Lean
+    /// does not accept such a recursion written with a match.
+    ///
+    /// The definition must take the indices of the proposition, then the
+    /// proof, and match on it at once. A recursive call on a field of the
+    /// branch, possibly applied, becomes the induction hypothesis of the field
+    /// applied alike, the indices being those of the field.
+    fn proof_recursion(&self, def: &ConstantDefinition) -> Option<String> {
+        let body = def.body.as_deref()?;
+        if self.target != ExportTarget::Lean
+            || def.opaque
+            || !self.env.dependencies(def.name).contains(&def.name)
+        {
+            return None;
+        }
+        let decreasing = check_termination(self.env, &[(def.name, body)]).ok()?[0]?;
+
+        let mut parameters = Vec::new();
+        let (mut ty, mut body) = (def.ty.as_ref(), body);
+        while parameters.len() <= decreasing {
+            let (Term::Product(product), Term::Lambda(lambda)) = (ty, body) else {
+                return None;
+            };
+            parameters.push((lambda.var, product.source.as_ref()));
+            ty = &product.target;
+            body = &lambda.target;
+        }
+        let (head, args) = match parameters[decreasing].1 {
+            Term::Application(app) => (app.f.as_ref(), app.args.as_slice()),
+            ty => (ty, &[][..]),
+        };
+        let Term::Constant(head) = head else {
+            return None;
+        };
+        let inductive = self.env.inductives.get_inductive(head.id)?;
+        let [constructor] = inductive.constructors.as_slice() else {
+            return None;
+        };
+        let parameter_count = inductive.parameters.len();
+        let is_index = |(i, arg): (usize, &Term)| match arg {
+            Term::Bound(bound) => bound.index == decreasing - 1 - i,
+            _ => false,
+        };
+        if arity_sort(self.env, &inductive.get_type()) != Some(Sort::Prop)
+            || args.len() != parameter_count + decreasing
+            || !args[..parameter_count]
+                .iter()
+                .all(|arg| is_closed_under(arg, 0))
+            || !args[parameter_count..].iter().enumerate().all(is_index)
+        {
+            return None;
+        }
+        let Term::Match(case) = body else {
+            return None;
+        };
+        let [branch] = case.branches.as_slice() else {
+            return None;
+        };
+        if !matches!(case.scrutinee.as_ref(), Term::Bound(bound) if bound.index == 0) {
+            return None;
+        }
+
+        // The fields that are proofs of the proposition, possibly under
+        // products, each with an induction hypothesis after the fields
+        let mut fields = constructor.ty.as_ref();
+        for _ in 0..parameter_count {
+            let Term::Product(product) = fields else {
+                return None;
+            };
+            fields = &product.target;
+        }
+        let mut hypotheses = Vec::new();
+        let mut field = 0;
+        while let Term::Product(product) = fields {
+            let mut conclusion = product.source.as_ref();
+            while let Term::Product(product) = conclusion {
+                conclusion = &product.target;
+            }
+            let head = match conclusion {
+                Term::Application(app) => app.f.as_ref(),
+                conclusion => conclusion,
+            };
+            if matches!(head, Term::Constant(c) if c.id == inductive.name) {
+                hypotheses.push(field);
+            }
+            fields = &product.target;
+            field += 1;
+        }
+        let calls = RecursiveCalls {
+            constant: def.name,
+            decreasing,
+            fields: branch.bound_vars.len(),
+            hypotheses: &hypotheses,
+        };
+        let minor = calls.replace(&lift(&branch.body, hypotheses.len()), 0)?;
+
+        let mut binders = Vec::new();
+        for (var, _) in &parameters {
+            let name = self.binder_name(*var, &binders);
+            binders.push(name);
+        }
+        let proof = binders[decreasing].clone();
+        let mut motive = "fun".to_string();
+        for (var, ty) in &parameters {
+            let name = self.binder_name(*var, &binders);
+            let ty = self.print_under(ty, &mut binders);
+            motive.push_str(&format!(" ({name} : {ty})"));
+            binders.push(name);
+        }
+        motive.push_str(" => ");
+        self.term(ty, &mut binders, &mut motive);
+        binders.truncate(parameters.len());
+
+        let mut out = "fun".to_string();
+        for var in &branch.bound_vars {
+            let name = self.binder_name(*var, &binders);
+            out.push_str(&format!(" {name}"));
+            binders.push(name);
+        }
+        for _ in &hypotheses {
+            let mut name = "ih".to_string();
+            while binders.contains(&name) || self.taken.contains(&name) {
+                name.push('\'');
+            }
+            out.push_str(&format!(" {name}"));
+            binders.push(name);
+        }
+        out.push_str(" => ");
+        self.term(&minor, &mut binders, &mut out);
+        Some(format!(
+            "{}.rec (motive := {motive}) ({out}) {proof}",
+            self.globals[&inductive.name]
+        ))
+    }
+
+    /// Whether the definition `id`, or one it unfolds to, is defined by
+    /// recursion on a proof
+    fn uses_proof_recursion(&self, id: Id) -> bool {
+        let mut visited = HashSet::new();
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            if !visited.insert(id) {
+                continue;
+            }
+            // Proofs are erased by the compiler
+            let Some(def) = self.env.get_constant(id).filter(|def| !def.opaque) else {
+                continue;
+            };
+            if self.proof_recursion(def).is_some() {
+                return true;
+            }
+            stack.extend(self.env.dependencies(id));
+        }
+        false
+    }
+
+    fn signature(&self, ty: &Term, body: &Term) -> Signature {
+        let mut binders = Vec::new();
+        let mut parameters = Vec::new();
+        let (mut ty, mut body) = (ty, body);
+        while let (Term::Product(product), Term::Lambda(lambda)) = (ty, body) {
+            let name = self.binder_name(lambda.var, &binders);
+            parameters.push((
+                name.clone(),
+                self.print_under(&lambda.source_ty, &mut binders),
+            ));
+            binders.push(name);
+            ty = &product.target;
+            body = &lambda.target;
+        }
+        let return_type = self.print_under(ty, &mut binders);
+        let mut out = String::new();
+        match body {
+            // A match needs no parentheses as the whole body
+            Term::Match(case) => self.case(case, &mut binders, &mut out),
+            _ => self.term(body, &mut binders, &mut out),
+        }
+        Signature {
+            parameters,
+            return_type,
+            body: out,
+        }
+    }
+
+    fn parameters(signature: &Signature) -> String {
+        signature
+            .parameters
+            .iter()
+            .map(|(name, ty)| format!(" ({name} : {ty})"))
+            .collect()
+    }
+
+    fn print(&self, term: &Term) -> String {
+        self.print_under(term, &mut Vec::new())
+    }
+
+    fn print_under(&self, term: &Term, binders: &mut Vec<String>) -> String {
+        let mut out = String::new();
+        self.term(term, binders, &mut out);
+        out
+    }
+
+    fn term(&self, term: &Term, binders: &mut Vec<String>, out: &mut String) {
+        match term {
+            Term::Product(product) if mentions_innermost(&product.target, 0) => {
+                let name = self.binder_name(product.var, binders);
+                out.push_str(&match self.target {
+                    ExportTarget::Coq => format!("forall ({name} : "),
+                    ExportTarget::Lean => format!("({name} : "),
+                });
+                self.term(&product.source, binders, out);
+                out.push_str(match self.target {
+                    ExportTarget::Coq => "), ",
+                    ExportTarget::Lean => ") -> ",
+                });
+                binders.push(name);
+                self.term(&product.target, binders, out);
+                binders.pop();
+            }
+            Term::Product(product) => {
+                self.application(&product.source, binders, out);
+                out.push_str(" -> ");
+                // The binder is unused but still shifts the indices
+                binders.push("_".to_string());
+                self.term(&product.target, binders, out);
+                binders.pop();
+            }
+            Term::Lambda(lambda) => {
+                let name = self.binder_name(lambda.var, binders);
+                out.push_str(&format!("fun ({name} : "));
+                self.term(&lambda.source_ty, binders, out);
+                out.push_str(") => ");
+                binders.push(name);
+                self.term(&lambda.target, binders, out);
+                binders.pop();
+            }
+            Term::LetIn(let_in) => {
+                let name = self.binder_name(let_in.var, binders);
+                out.push_str(&format!("let {name} : "));
+                self.term(&let_in.ty, binders, out);
+                out.push_str(" := ");
+                self.term(&let_in.term, binders, out);
+                out.push_str(match self.target {
+                    ExportTarget::Coq => " in ",
+                    ExportTarget::Lean => "; ",
+                });
+                binders.push(name);
+                self.term(&let_in.body, binders, out);
+                binders.pop();
+            }
+            // Lean matches have no closing keyword
+            Term::Match(case) if self.target == ExportTarget::Lean => {
+                out.push('(');
+                self.case(case, binders, out);
+                out.push(')');
+            }
+            Term::Match(case) => self.case(case, binders, out),
+            _ => self.application(term, binders, out),
+        }
+    }
+
+    fn case(&self, case: &TermMatch, binders: &mut Vec<String>, out: &mut String) {
+        if self.target == ExportTarget::Lean && case.branches.is_empty() {
+            out.push_str("nomatch ");
+            self.atom(&case.scrutinee, binders, out);
+            return;
+        }
+        out.push_str("match ");
+        self.term(&case.scrutinee, binders, out);
+        if self.target == ExportTarget::Coq {
+            out.push_str(" return ");
+            self.atom(&case.return_type, binders, out);
+        }
+        out.push_str(" with");
+        for branch in &case.branches {
+            out.push_str(" | ");
+            out.push_str(&self.reference(branch.constructor_id));
+            let scope = binders.len();
+            for var in &branch.bound_vars {
+                let name = self.binder_name(*var, binders);
+                out.push(' ');
+                out.push_str(&name);
+                binders.push(name);
+            }
+            out.push_str(" => ");
+            self.term(&branch.body, binders, out);
+            binders.truncate(scope);
+        }
+        if self.target == ExportTarget::Coq {
+            out.push_str(" end");
+        }
+    }
+
+    fn application(&self, term: &Term, binders: &mut Vec<String>, out: &mut String) {
+        match term {
+            Term::Application(app) => {
+                self.atom(&app.f, binders, out);
+                for arg in &app.args {
+                    out.push(' ');
+                    self.atom(arg, binders, out);
+                }
+            }
+            _ => self.atom(term, binders, out),
+        }
+    }
+
+    fn atom(&self, term: &Term, binders: &mut Vec<String>, out: &mut String) {
+        match term {
+            Term::Sort(sort) => out.push_str(&match (self.target, &sort.sort) {
+                (_, Sort::Prop) => "Prop".to_string(),
+                (ExportTarget::Coq, Sort::Set) => "Set".to_string(),
+                (ExportTarget::Coq, Sort::Type(_)) => "Type".to_string(),
+                (ExportTarget::Lean, Sort::Set) => "Type".to_string(),
+                (ExportTarget::Lean, Sort::Type(level)) => format!("(Type {})", level + 1),
+            }),
+            Term::Variable(var) => out.push_str(&self.sanitize(var.id, "x")),
+            Term::Constant(const_) => {
+                // The parameters are implicit arguments of Lean constructors
+                if self.target == ExportTarget::Lean
+                    && self
+                        .env
+                        .inductives
+                        .get_inductive_for_constructor(const_.id)
+                        .and_then(|inductive| self.env.inductives.get_inductive(inductive))
+                        .is_some_and(|inductive| !inductive.parameters.is_empty())
+                {
+                    out.push('@');
+                }
+                out.push_str(&self.reference(const_.id));
+            }
+            Term::Literal(literal) => out.push_str(&literal.value.to_string()),
+            Term::Bound(bound) => match binders.len().checked_sub(bound.index + 1) {
+                Some(level) => out.push_str(&binders[level]),
+                None => out.push('_'),
+            },
+            _ => {
+                out.push('(');
+                self.term(term, binders, out);
+                out.push(')');
+            }
+        }
+    }
+}
+
+/// The recursive calls of a definition by recursion on a proof, in the
+/// branch of the match on the proof, to replace with induction hypotheses
+struct RecursiveCalls<'a> {
+    constant: Id,
+    /// The position of the proof among the arguments
+    decreasing: usize,
+    /// The number of fields of the branch
+    fields: usize,
+    /// The fields with an induction hypothesis
+    hypotheses: &'a [usize],
+}
+
+impl RecursiveCalls<'_> {
+    /// `term`, under `depth` binders of the branch lifted over the induction
+    /// hypotheses, with the calls replaced, or `None` if a call is not on a
+    /// field with a hypothesis
+    fn replace(&self, term: &Term, depth: usize) -> Option<Term> {
+        let count = self.hypotheses.len();
+        let replace_all = |terms: &[Term], depth| {
+            terms
+                .iter()
+                .map(|term| self.replace(term, depth))
+                .collect::<Option<Vec<_>>>()
+        };
+        let replace_rc = |term: &Rc<Term>, depth| self.replace(term, depth).map(Rc::new);
+        Some(match term {
+            Term::Application(app) if matches!(app.f.as_ref(), Term::Constant(c) if c.id == self.constant) =>
+            {
+                let (field, field_args) = match app.args.get(self.decreasing)? {
+                    Term::Application(field) => (field.f.as_ref(), field.args.as_slice()),
+                    field => (field, &[][..]),
+                };
+                let Term::Bound(field) = field else {
+                    return None;
+                };
+                let position = field.index.checked_sub(depth + count)?;
+                let field = self.fields.checked_sub(position + 1)?;
+                let hypothesis = self.hypotheses.iter().position(|h| *h == field)?;
+                let mut args = replace_all(field_args, depth)?;
+                args.extend(replace_all(&app.args[self.decreasing + 1..], depth)?);
+                let hypothesis = Term::Bound(TermBound {
+                    index: depth + count - 1 - hypothesis,
+                });
+                if args.is_empty() {
+                    hypothesis
+                } else {
+                    Term::Application(TermApplication {
+                        f: Rc::new(hypothesis),
+                        args,
+                    })
+                }
+            }
+            Term::Constant(c) if c.id == self.constant => return None,
+            Term::Sort(_)
+            | Term::Variable(_)
+            | Term::Bound(_)
+            | Term::Constant(_)
+            | Term::Literal(_) => term.clone(),
+            Term::Product(product) => Term::Product(TermProduct {
+                var: product.var,
+                source: replace_rc(&product.source, depth)?,
+                target: replace_rc(&product.target, depth + 1)?,
+            }),
+            Term::Lambda(lambda) => Term::Lambda(TermLambda {
+                var: lambda.var,
+                source_ty: replace_rc(&lambda.source_ty, depth)?,
+                target: replace_rc(&lambda.target, depth + 1)?,
+            }),
+            Term::Application(app) => Term::Application(TermApplication {
+                f: replace_rc(&app.f, depth)?,
+                args: replace_all(&app.args, depth)?,
+            }),
+            Term::LetIn(let_in) => Term::LetIn(TermLetIn {
+                var: let_in.var,
+                term: replace_rc(&let_in.term, depth)?,
+                ty: replace_rc(&let_in.ty, depth)?,
+                body: replace_rc(&let_in.body, depth + 1)?,
+            }),
+            Term::Match(case) => Term::Match(TermMatch {
+                scrutinee: replace_rc(&case.scrutinee, depth)?,
+                return_type: replace_rc(&case.return_type, depth)?,
+                branches: case
+                    .branches
+                    .iter()
+                    .map(|branch| {
+                        Some(TermMatchBranch {
+                            constructor_id: branch.constructor_id,
+                            bound_vars: branch.bound_vars.clone(),
+                            body: replace_rc(&branch.body, depth + branch.bound_vars.len())?,
+                        })
+                    })
+                    .collect::<Option<_>>()?,
+            }),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, rc::Rc};
+
+    use crate::{
+        global_environment::GlobalEnvironment,
+        id::Id,
+        substitution::abstract_variable,
+        term::{Term, TermApplication, TermConstant, TermLambda, TermProduct, TermVariable},
+    };
+
+    use super::{ExportTarget, export};
+
+    fn constant(id: Id) -> Term {
+        Term::Constant(TermConstant { id })
+    }
+
+    #[test]
+    fn test_names_are_sanitized_and_declarations_ordered() {
+        let mut env = GlobalEnvironment::new();
+        let [nat, zero, succ, end, fun, x] = [(); 6].map(|_| Id::new());
+        env.inductives.add_nat(nat, zero, succ).unwrap();
+        // end := fun (fun : nat) => S fun, declared before `fun := end`
+        let body = Term::Application(TermApplication {
+            f: Rc::new(constant(succ)),
+            args: vec![Term::Variable(TermVariable { id: x })],
+        });
+        let lambda = Term::Lambda(TermLambda {
+            var: x,
+            source_ty: Rc::new(constant(nat)),
+            target: Rc::new(abstract_variable(&body, x)),
+        });
+        let ty = Term::Product(TermProduct {
+            var: x,
+            source: Rc::new(constant(nat)),
+            target: Rc::new(constant(nat)),
+        });
+        env.add_definition(end, Rc::new(ty.clone()), Rc::new(lambda))
+            .unwrap();
+        env.add_definition(fun, Rc::new(ty), Rc::new(constant(end)))
+            .unwrap();
+
+        let names = HashMap::from([
+            (nat, "nat".to_string()),
+            (zero, "O".to_string()),
+            (succ, "S".to_string()),
+            (end, "end".to_string()),
+            (fun, "fun".to_string()),
+            (x, "fun".to_string()),
+        ]);
+        assert_eq!(
+            export(&env, &names, ExportTarget::Coq),
+            "(* Exported from Felis *)
+
+Inductive nat : Set :=
+  | O : nat
+  | S : nat -> nat.
+
+Definition end_ (fun_' : nat) : nat :=
+  S fun_'.
+
+Definition fun_ : nat -> nat :=
+  end_.
+"
+        );
+        assert_eq!(
+            export(&env, &names, ExportTarget::Lean),
+            "-- Exported from Felis
+
+namespace Felis
+
+inductive nat : Type where
+  | O : nat
+  | S : nat -> nat
+
+def end_ (fun_' : nat) : nat :=
+  nat.S fun_'
+
+def fun_ : nat -> nat :=
+  end_
+
+end Felis
+"
+        );
+    }
+}
@@ -0,0 +1,372 @@
+use std::{
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
+
+use crate::{
+    global_environment::GlobalEnvironment,
+    id::Id,
+    local_context::LocalContext,
+    primitive::Operation,
+    reduction::whnf_with_env,
+    term::{Sort, Term, TermMatch},
+    typechecker::{TypeError, branch_context, infer_type},
+};
+
+/// A checked definition with its types and proofs erased, as a first-order
+/// function over machine words
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Function {
+    pub name: Id,
+    /// Number of parameters that are kept, which are the locals `0..params`
+    pub params: usize,
+    /// Number of locals, parameters included
+    pub locals: usize,
+    pub body: Code,
+}
+
+/// The erased body of a function. Values of inductive types are pointers to
+/// a tag, the index of their constructor, followed by its fields.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Code {
+    /// A type or a proof, whose value is never used
+    Erased,
+    Local(usize),
+    Literal(u64),
+    /// A primitive operation on two machine words
+    Primitive(Operation, Vec<Code>),
+    /// Call of an extracted function with its kept arguments
+    Call(Id, Vec<Code>),
+    /// Allocation of a value, with all the fields of its constructor
+    Construct {
+        tag: usize,
+        fields: Vec<Code>,
+    },
+    Let {
+        local: usize,
+        value: Box<Code>,
+        body: Box<Code>,
+    },
+    /// Jumps to the branch of the tag of `scrutinee`, binding its fields
+    Switch {
+        scrutinee: Box<Code>,
+        branches: Vec<Branch>,
+    },
+    /// A match on a proof of an empty proposition
+    Unreachable,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Branch {
+    /// Locals of the fields, `None` for the erased ones
+    pub fields: Vec<Option<usize>>,
+    pub body: Code,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExtractError {
+    /// An axiom, or a definition left with holes, is needed for computation
+    Axiom(Id),
+    /// A function or constructor is not applied to all its arguments
+    PartialApplication(Id),
+    /// A function is bound, passed or returned as a value
+    HigherOrder,
+    Type(TypeError),
+}
+
+impl ExtractError {
+    /// Describes the error, naming the constant being extracted with `names`
+    pub fn describe(&self, function: Id, names: &HashMap<Id, String>) -> String {
+        let name = |id: &Id| names.get(id).cloned().unwrap_or_else(|| format!("{id:?}"));
+        let message = match self {
+            ExtractError::Axiom(id) => format!("{} has no body to compute with", name(id)),
+            ExtractError::PartialApplication(id) => {
+                format!("{} is not applied to all its arguments", name(id))
+            }
+            ExtractError::HigherOrder => "functions as values are not supported".to_string(),
+            ExtractError::Type(e) => e.describe(names),
+        };
+        format!("Cannot extract {}: {message}", name(&function))
+    }
+}
+
+impl From<TypeError> for ExtractError {
+    fn from(e: TypeError) -> Self {
+        ExtractError::Type(e)
+    }
+}
+
+/// Extracts `roots` and the definitions they call, in order of first call.
+/// The error comes with the definition it was found in.
+pub fn extract(env: &GlobalEnvironment, roots: &[Id]) -> Result<Vec<Function>, (Id, ExtractError)> {
+    let mut queued = HashSet::new();
+    let pending = roots
+        .iter()
+        .copied()
+        .filter(|id| queued.insert(*id))
+        .collect();
+    let mut extractor = Extractor {
+        env,
+        locals: Vec::new(),
+        local_count: 0,
+        pending,
+        queued,
+        signatures: HashMap::new(),
+    };
+    let mut functions = Vec::new();
+    let mut next = 0;
+    while let Some(&name) = extractor.pending.get(next) {
+        functions.push(extractor.function(name).map_err(|e| (name, e))?);
+        next += 1;
+    }
+    Ok(functions)
+}
+
+/// Whether the values of type `ty` are types or proofs
+pub fn is_logical_type(
+    ctx: &LocalContext,
+    env: &GlobalEnvironment,
+    ty: &Term,
+) -> Result<bool, TypeError> {
+    if is_arity(env, ty) {
+        return Ok(true);
+    }
+    let sort = infer_type(ctx, env, ty)?;
+    Ok(matches!(whnf_with_env(&sort, env), Term::Sort(sort) if sort.sort == Sort::Prop))
+}
+
+/// Whether `ty` is a sort or a product into a sort
+fn is_arity(env: &GlobalEnvironment, ty: &Term) -> bool {
+    match whnf_with_env(ty, env) {
+        Term::Sort(_) => true,
+        Term::Product(product) => is_arity(env, &product.target),
+        _ => false,
+    }
+}
+
+struct Extractor<'a> {
+    env: &'a GlobalEnvironment,
+    /// Locals of the binders entered so far, innermost last, `None` for the
+    /// erased ones
+    locals: Vec<Option<usize>>,
+    local_count: usize,
+    /// Definitions to extract, in order of first call
+    pending: Vec<Id>,
+    queued: HashSet<Id>,
+    /// Which parameters of each definition are kept
+    signatures: HashMap<Id, Vec<bool>>,
+}
+
+impl Extractor<'_> {
+    fn function(&mut self, name: Id) -> Result<Function, ExtractError> {
+        let body = self.body(name)?;
+        let mut ctx = LocalContext::new();
+        self.locals.clear();
+        self.local_count = 0;
+        let mut body = body.as_ref();
+        while let Term::Lambda(lambda) = body {
+            let kept = !is_logical_type(&ctx, self.env, &lambda.source_ty)?;
+            self.bind(kept);
+            ctx.push(lambda.var, lambda.source_ty.clone());
+            body = &lambda.target;
+        }
+        let params = self.local_count;
+        let body = self.code(&ctx, body)?;
+        Ok(Function {
+            name,
+            params,
+            locals: self.local_count,
+            body,
+        })
+    }
+
+    fn body(&self, name: Id) -> Result<Rc<Term>, ExtractError> {
+        let constant = self
+            .env
+            .get_constant(name)
+            .ok_or(TypeError::UnboundConstant(name))?;
+        constant.body.clone().ok_or(ExtractError::Axiom(name))
+    }
+
+    /// Which of the parameters of `name`, the leading lambdas of its body,
+    /// are kept
+    fn signature(&mut self, name: Id) -> Result<Vec<bool>, ExtractError> {
+        if let Some(signature) = self.signatures.get(&name) {
+            return Ok(signature.clone());
+        }
+        let body = self.body(name)?;
+        let mut ctx = LocalContext::new();
+        let mut signature = Vec::new();
+        let mut body = body.as_ref();
+        while let Term::Lambda(lambda) = body {
+            signature.push(!is_logical_type(&ctx, self.env, &lambda.source_ty)?);
+            ctx.push(lambda.var, lambda.source_ty.clone());
+            body = &lambda.target;
+        }
+        self.signatures.insert(name, signature.clone());
+        Ok(signature)
+    }
+
+    /// Enters a binder, allocating a local if it is kept
+    fn bind(&mut self, kept: bool) -> Option<usize> {
+        let local = kept.then(|| {
+            self.local_count += 1;
+            self.local_count - 1
+        });
+        self.locals.push(local);
+        local
+    }
+
+    fn code(&mut self, ctx: &LocalContext, term: &Term) -> Result<Code, ExtractError> {
+        let ty = infer_type(ctx, self.env, term)?;
+        if is_logical_type(ctx, self.env, &ty)? {
+            return Ok(Code::Erased);
+        }
+        match term {
+            Term::Bound(bound) => {
+                let local = self.locals[self.locals.len() - 1 - bound.index];
+                Ok(local.map_or(Code::Erased, Code::Local))
+            }
+            Term::Literal(literal) => Ok(Code::Literal(literal.value)),
+            Term::Constant(constant) => self.apply(ctx, constant.id, &[]),
+            Term::Application(app) => match app.f.as_ref() {
+                Term::Constant(constant) => self.apply(ctx, constant.id, &app.args),
+                _ => Err(ExtractError::HigherOrder),
+            },
+            Term::Lambda(_) => Err(ExtractError::HigherOrder),
+            Term::LetIn(let_in) => {
+                let value = self.code(ctx, &let_in.term)?;
+                let kept = !is_logical_type(ctx, self.env, &let_in.ty)?;
+                let local = self.bind(kept);
+                let body = self.code(&ctx.pushed(let_in.var, let_in.ty.clone()), &let_in.body);
+                self.locals.pop();
+                let body = Box::new(body?);
+                Ok(match local {
+                    Some(local) => Code::Let {
+                        local,
+                        value: Box::new(value),
+                        body,
+                    },
+                    None => *body,
+                })
+            }
+            Term::Match(case) => self.switch(ctx, case),
+            // Sorts and products are types, and the kernel definitions are
+            // closed
+            Term::Sort(_) | Term::Product(_) | Term::Variable(_) => Ok(Code::Erased),
+        }
+    }
+
+    fn apply(&mut self, ctx: &LocalContext, head: Id, args: &[Term]) -> Result<Code, ExtractError> {
+        if let Some(inductive) = self.env.inductives.get_inductive_for_constructor(head) {
+            let inductive = self.env.inductives.get_inductive(inductive).unwrap();
+            let tag = inductive
+                .constructors
+                .iter()
+                .position(|constructor| constructor.name == head)
+                .unwrap();
+            let parameters = inductive.parameters.len();
+            if args.len() != parameters + inductive.constructors[tag].arity {
+                return Err(ExtractError::PartialApplication(head));
+            }
+            let fields = args[parameters..]
+                .iter()
+                .map(|arg| self.code(ctx, arg))
+                .collect::<Result<_, _>>()?;
+            return Ok(Code::Construct { tag, fields });
+        }
+
+        if let Some(operation) = self.env.primitive_operation(head) {
+            if args.len() != 2 {
+                return Err(ExtractError::PartialApplication(head));
+            }
+            let args = args
+                .iter()
+                .map(|arg| self.code(ctx, arg))
+                .collect::<Result<_, _>>()?;
+            return Ok(Code::Primitive(operation, args));
+        }
+
+        let signature = self.signature(head)?;
+        if args.len() < signature.len() {
+            return Err(ExtractError::PartialApplication(head));
+        }
+        if args.len() > signature.len() {
+            return Err(ExtractError::HigherOrder);
+        }
+        let args = args
+            .iter()
+            .zip(signature)
+            .filter(|(_, kept)| *kept)
+            .map(|(arg, _)| self.code(ctx, arg))
+            .collect::<Result<_, _>>()?;
+        if self.queued.insert(head) {
+            self.pending.push(head);
+        }
+        Ok(Code::Call(head, args))
+    }
+
+    fn switch(&mut self, ctx: &LocalContext, case: &TermMatch) -> Result<Code, ExtractError> {
+        let scrutinee_type = infer_type(ctx, self.env, &case.scrutinee)?;
+        let scrutinee_type = whnf_with_env(&scrutinee_type, self.env);
+        let (head, scrutinee_args) = match &scrutinee_type {
+            Term::Application(app) => (app.f.as_ref(), app.args.as_slice()),
+            _ => (&scrutinee_type, &[][..]),
+        };
+        let inductive = match head {
+            Term::Constant(constant) => self.env.inductives.get_inductive(constant.id),
+            _ => None,
+        }
+        .ok_or_else(|| TypeError::NotInductive(Rc::new(scrutinee_type.clone())))?;
+
+        // Proofs only eliminate into types when they carry no information:
+        // the only branch is taken without looking at them
+        let proof = is_logical_type(ctx, self.env, &scrutinee_type)?;
+        if proof && case.branches.is_empty() {
+            return Ok(Code::Unreachable);
+        }
+        let scrutinee = self.code(ctx, &case.scrutinee)?;
+
+        let mut branches = Vec::new();
+        for constructor in &inductive.constructors {
+            let Some(branch) = case
+                .branches
+                .iter()
+                .find(|branch| branch.constructor_id == constructor.name)
+            else {
+                return Err(TypeError::BranchCount {
+                    expected: inductive.constructor_count(),
+                    found: case.branches.len(),
+                }
+                .into());
+            };
+            let branch_ctx = branch_context(
+                ctx,
+                self.env,
+                inductive,
+                constructor,
+                scrutinee_args,
+                branch,
+            )?;
+            let mut fields = Vec::new();
+            for index in (0..branch.bound_vars.len()).rev() {
+                let ty = branch_ctx.lookup_bound(index).unwrap();
+                let kept = !proof && !is_logical_type(&branch_ctx, self.env, &ty)?;
+                fields.push(self.bind(kept));
+            }
+            let body = self.code(&branch_ctx, &branch.body);
+            self.locals.truncate(self.locals.len() - fields.len());
+            branches.push(Branch {
+                fields,
+                body: body?,
+            });
+        }
+        if proof {
+            return Ok(branches.pop().unwrap().body);
+        }
+        Ok(Code::Switch {
+            scrutinee: Box::new(scrutinee),
+            branches,
+        })
+    }
+}
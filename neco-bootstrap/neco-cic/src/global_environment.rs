@@ -1,6 +1,11 @@
-use std::{collections::HashMap, rc::Rc};
+use std::{
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
 
-use crate::{id::Id, inductive::InductiveEnvironment, term::Term};
+use crate::{
+    id::Id, inductive::InductiveEnvironment, intern::intern_rc, primitive::Primitives, term::Term,
+};
 
 /// Global environment containing definitions of constants and inductive types
 #[derive(Debug, Clone)]
@@ -9,6 +14,69 @@ pub struct GlobalEnvironment {
     pub constants: HashMap<Id, ConstantDefinition>,
     /// Inductive type definitions
     pub inductives: InductiveEnvironment,
+    /// Definition heights used to decide which constant to unfold first during
+    /// conversion. A constant whose body mentions no other definition has height 1.
+    heights: HashMap<Id, usize>,
+    /// Projections of the record types, by constructor, for η in conversion
+    records: HashMap<Id, Rc<[Id]>>,
+    /// The primitive integer type and its operations
+    pub primitives: Primitives,
+}
+
+/// State of Tarjan's algorithm on the dependencies of declarations
+#[derive(Default)]
+struct Components {
+    index: HashMap<Id, usize>,
+    low: HashMap<Id, usize>,
+    stack: Vec<Id>,
+    components: Vec<Vec<Id>>,
+}
+
+/// Why `add_record` refused to declare a record type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordError {
+    UnknownInductive(Id),
+    /// An inductive type without the shape of a record, for `reason`
+    NotARecord {
+        inductive: Id,
+        reason: &'static str,
+    },
+    /// A projection not defined by matching on the record
+    NotAProjection {
+        projection: Id,
+        index: usize,
+        inductive: Id,
+    },
+}
+
+impl RecordError {
+    /// The message of the error, with the names of `names` for the ids
+    pub fn describe(&self, names: &HashMap<Id, String>) -> String {
+        let name = |id: &Id| names.get(id).cloned().unwrap_or_else(|| format!("{id:?}"));
+        match self {
+            RecordError::UnknownInductive(inductive) => {
+                format!("Unknown inductive type {}", name(inductive))
+            }
+            RecordError::NotARecord { inductive, reason } => {
+                format!("{} is not a record: {reason}", name(inductive))
+            }
+            RecordError::NotAProjection {
+                projection,
+                index,
+                inductive,
+            } => format!(
+                "{} is not the projection {index} of {}",
+                name(projection),
+                name(inductive)
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for RecordError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.describe(&HashMap::new()))
+    }
 }
 
 /// Definition of a global constant
@@ -20,6 +88,9 @@ pub struct ConstantDefinition {
     pub ty: Rc<Term>,
     /// Optional body (if this is a definition, not an axiom)
     pub body: Option<Rc<Term>>,
+    /// Opaque constants, such as theorems, are never δ-unfolded: only their
+    /// type matters to the rest of the environment
+    pub opaque: bool,
 }
 
 impl GlobalEnvironment {
@@ -28,29 +99,93 @@ impl GlobalEnvironment {
         GlobalEnvironment {
             constants: HashMap::new(),
             inductives: InductiveEnvironment::new(),
+            heights: HashMap::new(),
+            records: HashMap::new(),
+            primitives: Primitives::default(),
         }
     }
 
     /// Adds a constant definition
-    pub fn add_constant(&mut self, def: ConstantDefinition) -> Result<(), String> {
+    /// Its type and body are interned so that they are shared with the rest of
+    /// the kernel.
+    pub fn add_constant(&mut self, mut def: ConstantDefinition) -> Result<(), String> {
         if self.constants.contains_key(&def.name) {
             return Err(format!("Constant {:?} already defined", def.name));
         }
+        def.ty = intern_rc(&def.ty);
+        def.body = def.body.as_ref().map(intern_rc);
+        if let Some(body) = def.unfoldable_body() {
+            let height = 1 + self.max_height(body, def.name);
+            self.heights.insert(def.name, height);
+        }
         self.constants.insert(def.name, def);
         Ok(())
     }
 
+    /// Gets the definition height of a constant (0 for axioms and unknown constants)
+    pub fn constant_height(&self, name: Id) -> usize {
+        self.heights.get(&name).copied().unwrap_or(0)
+    }
+
+    /// Maximum height among the constants referenced by a term, ignoring `self_id`
+    /// so that recursive definitions do not depend on themselves.
+    fn max_height(&self, term: &Term, self_id: Id) -> usize {
+        match term {
+            Term::Sort(_) | Term::Variable(_) | Term::Bound(_) | Term::Literal(_) => 0,
+            Term::Constant(const_) if const_.id == self_id => 0,
+            Term::Constant(const_) => self.constant_height(const_.id),
+            Term::Product(product) => self
+                .max_height(&product.source, self_id)
+                .max(self.max_height(&product.target, self_id)),
+            Term::Lambda(lambda) => self
+                .max_height(&lambda.source_ty, self_id)
+                .max(self.max_height(&lambda.target, self_id)),
+            Term::Application(app) => app
+                .args
+                .iter()
+                .map(|arg| self.max_height(arg, self_id))
+                .fold(self.max_height(&app.f, self_id), usize::max),
+            Term::LetIn(let_in) => self
+                .max_height(&let_in.term, self_id)
+                .max(self.max_height(&let_in.ty, self_id))
+                .max(self.max_height(&let_in.body, self_id)),
+            Term::Match(case) => case
+                .branches
+                .iter()
+                .map(|branch| self.max_height(&branch.body, self_id))
+                .fold(
+                    self.max_height(&case.scrutinee, self_id)
+                        .max(self.max_height(&case.return_type, self_id)),
+                    usize::max,
+                ),
+        }
+    }
+
     /// Gets a constant definition
     pub fn get_constant(&self, name: Id) -> Option<&ConstantDefinition> {
         self.constants.get(&name)
     }
 
+    /// The type of a constant, an inductive type or a constructor
+    pub fn constant_type(&self, name: Id) -> Option<Rc<Term>> {
+        if let Some(def) = self.get_constant(name) {
+            return Some(def.ty.clone());
+        }
+        if let Some(inductive) = self.inductives.get_inductive(name) {
+            return Some(inductive.get_type());
+        }
+        self.inductives
+            .get_constructor(name)
+            .map(|constructor| constructor.ty.clone())
+    }
+
     /// Adds an axiom (constant without body)
     pub fn add_axiom(&mut self, name: Id, ty: Rc<Term>) -> Result<(), String> {
         let def = ConstantDefinition {
             name,
             ty,
             body: None,
+            opaque: false,
         };
         self.add_constant(def)
     }
@@ -61,9 +196,266 @@ impl GlobalEnvironment {
             name,
             ty,
             body: Some(body),
+            opaque: false,
         };
         self.add_constant(def)
     }
+
+    /// Adds a theorem (opaque constant with a proof)
+    pub fn add_theorem(&mut self, name: Id, ty: Rc<Term>, proof: Rc<Term>) -> Result<(), String> {
+        self.add_constant(ConstantDefinition::new_theorem(name, ty, proof))
+    }
+
+    /// Declares `inductive` a record type whose fields are read by
+    /// `projections`, so that conversion identifies any `r` of the type with
+    /// `C (proj_1 r) .. (proj_n r)`.
+    ///
+    /// A record has a single constructor without parameters or indices, whose
+    /// arguments do not mention the record type, and the projection `i` must
+    /// be defined as `fun r => match r { C x_1 .. x_n => x_i }`.
+    pub fn add_record(&mut self, inductive: Id, projections: Vec<Id>) -> Result<(), RecordError> {
+        let not_a_record = |reason| Err(RecordError::NotARecord { inductive, reason });
+        let Some(def) = self.inductives.get_inductive(inductive) else {
+            return Err(RecordError::UnknownInductive(inductive));
+        };
+        let [constructor] = def.constructors.as_slice() else {
+            return not_a_record("it does not have exactly one constructor");
+        };
+        if !def.parameters.is_empty() || !matches!(def.sort.as_ref(), Term::Sort(_)) {
+            return not_a_record("it has parameters or indices");
+        }
+        if constructor.arity != projections.len() {
+            return not_a_record("the projections do not match the fields");
+        }
+        let mut field = constructor.ty.as_ref();
+        while let Term::Product(product) = field {
+            if mentions_constant(&product.source, inductive) {
+                return not_a_record("it is recursive");
+            }
+            field = &product.target;
+        }
+
+        for (i, projection) in projections.iter().enumerate() {
+            let is_projection = self
+                .get_constant(*projection)
+                .and_then(|def| def.unfoldable_body())
+                .is_some_and(|body| match body.as_ref() {
+                    Term::Lambda(lambda) => match lambda.target.as_ref() {
+                        Term::Match(case) => {
+                            matches!(case.scrutinee.as_ref(), Term::Bound(b) if b.index == 0)
+                                && matches!(case.branches.as_slice(), [branch]
+                                    if branch.constructor_id == constructor.name
+                                        && branch.bound_vars.len() == projections.len()
+                                        && matches!(branch.body.as_ref(),
+                                            Term::Bound(b) if b.index == projections.len() - 1 - i))
+                        }
+                        _ => false,
+                    },
+                    _ => false,
+                });
+            if !is_projection {
+                return Err(RecordError::NotAProjection {
+                    projection: *projection,
+                    index: i,
+                    inductive,
+                });
+            }
+        }
+
+        self.records
+            .insert(constructor.name, projections.into_iter().collect());
+        Ok(())
+    }
+
+    /// The projections of the record type built by `constructor`
+    pub fn record_projections(&self, constructor: Id) -> Option<&[Id]> {
+        self.records
+            .get(&constructor)
+            .map(|projections| &projections[..])
+    }
+
+    /// Removes the declaration `name`, a constant, an inductive type or a
+    /// constructor, as an interactive session rolling back its last items
+    /// does. The declarations that mention it must be removed as well.
+    pub fn remove(&mut self, name: Id) {
+        self.constants.remove(&name);
+        self.heights.remove(&name);
+        self.records.remove(&name);
+        self.inductives.remove(name);
+        self.primitives.operations.remove(&name);
+        if self.primitives.u64 == Some(name) {
+            self.primitives.u64 = None;
+        }
+    }
+
+    /// Lists the axioms `name` depends on, directly or through the constants,
+    /// inductive types and constructors it mentions, in the order they are
+    /// first reached. An axiom depends on itself.
+    pub fn axioms_of(&self, name: Id) -> Vec<Id> {
+        let mut visited = HashSet::new();
+        let mut stack = vec![name];
+        let mut axioms = Vec::new();
+        while let Some(id) = stack.pop() {
+            if !visited.insert(id) {
+                continue;
+            }
+            let mut terms = Vec::new();
+            if let Some(def) = self.get_constant(id) {
+                if def.is_axiom() {
+                    axioms.push(id);
+                }
+                terms.push(def.ty.clone());
+                terms.extend(def.body.clone());
+            } else if let Some(inductive) = self.inductives.get_inductive(id).or_else(|| {
+                self.inductives
+                    .get_inductive_for_constructor(id)
+                    .and_then(|inductive| self.inductives.get_inductive(inductive))
+            }) {
+                // Constructors and their inductive type depend on each other
+                stack.push(inductive.name);
+                terms.push(inductive.get_type());
+                for constructor in &inductive.constructors {
+                    stack.push(constructor.name);
+                    terms.push(constructor.ty.clone());
+                }
+            }
+            for term in terms.iter().rev() {
+                collect_constants(term, &mut stack);
+            }
+        }
+        axioms
+    }
+
+    /// The inductive types and constants, in the order of their ids, which is
+    /// the order of the source
+    pub fn declarations(&self) -> Vec<Id> {
+        let mut declarations: Vec<Id> = self
+            .inductives
+            .list_inductives()
+            .map(|inductive| inductive.name)
+            .chain(self.constants.keys().copied())
+            .collect();
+        declarations.sort();
+        declarations
+    }
+
+    /// The declaration standing for `id`: constructors stand for their type
+    fn declaration_of(&self, id: Id) -> Option<Id> {
+        if self.get_constant(id).is_some() || self.inductives.get_inductive(id).is_some() {
+            Some(id)
+        } else {
+            self.inductives.get_inductive_for_constructor(id)
+        }
+    }
+
+    /// The declarations mentioned by the declaration `declaration`, in order
+    /// of appearance
+    pub fn dependencies(&self, declaration: Id) -> Vec<Id> {
+        let mut terms = Vec::new();
+        if let Some(def) = self.get_constant(declaration) {
+            terms.push(def.ty.clone());
+            terms.extend(def.body.clone());
+        } else if let Some(inductive) = self.inductives.get_inductive(declaration) {
+            terms.push(inductive.get_type());
+            terms.extend(inductive.constructors.iter().map(|c| c.ty.clone()));
+        }
+        let mut mentioned = Vec::new();
+        for term in &terms {
+            let mut found = Vec::new();
+            collect_constants(term, &mut found);
+            mentioned.extend(found.into_iter().rev());
+        }
+        let mut dependencies = Vec::new();
+        for id in mentioned
+            .into_iter()
+            .filter_map(|id| self.declaration_of(id))
+        {
+            if !dependencies.contains(&id) {
+                dependencies.push(id);
+            }
+        }
+        dependencies
+    }
+
+    /// Groups `declarations` into the strongly connected components of their
+    /// dependencies, the mutual blocks, each one after the components it
+    /// depends on. Dependencies outside of `declarations` are ignored.
+    pub fn components(&self, declarations: &[Id]) -> Vec<Vec<Id>> {
+        let mut state = Components::default();
+        for declaration in declarations {
+            if !state.index.contains_key(declaration) {
+                self.visit(*declaration, declarations, &mut state);
+            }
+        }
+        state.components
+    }
+
+    /// A step of Tarjan's algorithm
+    fn visit(&self, declaration: Id, declarations: &[Id], state: &mut Components) {
+        let index = state.index.len();
+        state.index.insert(declaration, index);
+        state.low.insert(declaration, index);
+        state.stack.push(declaration);
+        for dependency in self.dependencies(declaration) {
+            let low = if !declarations.contains(&dependency) {
+                continue;
+            } else if !state.index.contains_key(&dependency) {
+                self.visit(dependency, declarations, state);
+                state.low[&dependency]
+            } else if state.stack.contains(&dependency) {
+                state.index[&dependency]
+            } else {
+                continue;
+            };
+            let low = state.low[&declaration].min(low);
+            state.low.insert(declaration, low);
+        }
+        if state.low[&declaration] == index {
+            let position = state.stack.iter().rposition(|id| *id == declaration);
+            let mut component = state.stack.split_off(position.unwrap());
+            component.sort();
+            state.components.push(component);
+        }
+    }
+}
+
+/// Pushes the constants mentioned by `term` onto `out`, in reverse order of
+/// appearance so that popping visits them from left to right
+fn collect_constants(term: &Term, out: &mut Vec<Id>) {
+    let mut found = Vec::new();
+    fn go(term: &Term, found: &mut Vec<Id>) {
+        match term {
+            Term::Sort(_) | Term::Variable(_) | Term::Bound(_) | Term::Literal(_) => {}
+            Term::Constant(const_) => found.push(const_.id),
+            Term::Product(product) => {
+                go(&product.source, found);
+                go(&product.target, found);
+            }
+            Term::Lambda(lambda) => {
+                go(&lambda.source_ty, found);
+                go(&lambda.target, found);
+            }
+            Term::Application(app) => {
+                go(&app.f, found);
+                app.args.iter().for_each(|arg| go(arg, found));
+            }
+            Term::LetIn(let_in) => {
+                go(&let_in.term, found);
+                go(&let_in.ty, found);
+                go(&let_in.body, found);
+            }
+            Term::Match(case) => {
+                go(&case.scrutinee, found);
+                go(&case.return_type, found);
+                for branch in &case.branches {
+                    found.push(branch.constructor_id);
+                    go(&branch.body, found);
+                }
+            }
+        }
+    }
+    go(term, &mut found);
+    out.extend(found.into_iter().rev());
 }
 
 impl Default for GlobalEnvironment {
@@ -79,6 +471,7 @@ impl ConstantDefinition {
             name,
             ty,
             body: None,
+            opaque: false,
         }
     }
 
@@ -88,6 +481,17 @@ impl ConstantDefinition {
             name,
             ty,
             body: Some(body),
+            opaque: false,
+        }
+    }
+
+    /// Creates a new theorem, whose proof is never unfolded
+    pub fn new_theorem(name: Id, ty: Rc<Term>, proof: Rc<Term>) -> Self {
+        ConstantDefinition {
+            name,
+            ty,
+            body: Some(proof),
+            opaque: true,
         }
     }
 
@@ -100,4 +504,39 @@ impl ConstantDefinition {
     pub fn is_definition(&self) -> bool {
         self.body.is_some()
     }
+
+    /// The body that δ-reduction may substitute for the constant: none for
+    /// axioms and opaque constants
+    pub fn unfoldable_body(&self) -> Option<&Rc<Term>> {
+        self.body.as_ref().filter(|_| !self.opaque)
+    }
+}
+
+pub(crate) fn mentions_constant(term: &Term, id: Id) -> bool {
+    match term {
+        Term::Sort(_) | Term::Variable(_) | Term::Bound(_) | Term::Literal(_) => false,
+        Term::Constant(const_) => const_.id == id,
+        Term::Product(product) => {
+            mentions_constant(&product.source, id) || mentions_constant(&product.target, id)
+        }
+        Term::Lambda(lambda) => {
+            mentions_constant(&lambda.source_ty, id) || mentions_constant(&lambda.target, id)
+        }
+        Term::Application(app) => {
+            mentions_constant(&app.f, id) || app.args.iter().any(|arg| mentions_constant(arg, id))
+        }
+        Term::LetIn(let_in) => {
+            mentions_constant(&let_in.term, id)
+                || mentions_constant(&let_in.ty, id)
+                || mentions_constant(&let_in.body, id)
+        }
+        Term::Match(case) => {
+            mentions_constant(&case.scrutinee, id)
+                || mentions_constant(&case.return_type, id)
+                || case
+                    .branches
+                    .iter()
+                    .any(|branch| mentions_constant(&branch.body, id))
+        }
+    }
 }
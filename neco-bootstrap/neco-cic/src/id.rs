@@ -38,3 +38,10 @@ impl IdGenerator {
         res
     }
 }
+
+/// The number of the id, as written in environment files
+impl std::fmt::Display for Id {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
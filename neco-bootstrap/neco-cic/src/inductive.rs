@@ -2,6 +2,7 @@ use std::{collections::HashMap, rc::Rc};
 
 use crate::{
     id::{Id, IdGenerator},
+    intern::intern_rc,
     term::Term,
 };
 
@@ -20,6 +21,7 @@ pub struct InductiveDefinition {
 }
 
 /// A parameter of an inductive type
+/// `ty` lives under the binders of the previous parameters.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Parameter {
     pub name: Id,
@@ -33,6 +35,8 @@ pub struct ConstructorDefinition {
     pub name: Id,
     /// Type of the constructor (in telescope form)
     /// Example: for S : nat -> nat, this would be [nat] -> nat
+    /// The parameters of the inductive type come first, as for
+    /// cons : Π(A: Set). A -> list A -> list A
     pub ty: Rc<Term>,
     /// Number of arguments this constructor takes
     pub arity: usize,
@@ -99,6 +103,27 @@ pub struct InductiveEnvironment {
     definitions: HashMap<Id, InductiveDefinition>,
     /// Maps constructor names to their inductive type
     constructor_to_inductive: HashMap<Id, Id>,
+    /// Maps inductive type names to the types declared together with them
+    blocks: HashMap<Id, Rc<[Id]>>,
+}
+
+/// A constructor argument where `inductive`, a type of the block of the
+/// constructor, occurs other than strictly positively, e.g.
+/// `bad : (bad -> nat) -> bad`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PositivityError {
+    pub inductive: Id,
+    pub constructor: Id,
+}
+
+impl std::fmt::Display for PositivityError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Non strictly positive occurrence of {:?} in constructor {:?}",
+            self.inductive, self.constructor
+        )
+    }
 }
 
 impl InductiveEnvironment {
@@ -107,34 +132,80 @@ impl InductiveEnvironment {
         InductiveEnvironment {
             definitions: HashMap::new(),
             constructor_to_inductive: HashMap::new(),
+            blocks: HashMap::new(),
         }
     }
 
     /// Adds an inductive definition
+    /// Its types are interned so that they are shared with the rest of the kernel.
     pub fn add_inductive(&mut self, def: InductiveDefinition) -> Result<(), String> {
-        if self.definitions.contains_key(&def.name) {
-            return Err(format!("Inductive type {:?} already defined", def.name));
-        }
+        self.add_mutual_inductives(vec![def])
+    }
 
-        // Register all constructors
-        for constructor in &def.constructors {
-            if self
-                .constructor_to_inductive
-                .contains_key(&constructor.name)
-            {
-                return Err(format!(
-                    "Constructor {:?} already defined",
-                    constructor.name
-                ));
+    /// Adds inductive definitions whose constructors may refer to each other,
+    /// such as `even` and `odd`. The types of the block may only occur strictly
+    /// positively in the arguments of the constructors.
+    pub fn add_mutual_inductives(&mut self, defs: Vec<InductiveDefinition>) -> Result<(), String> {
+        let block: Rc<[Id]> = defs.iter().map(|def| def.name).collect();
+        let mut constructors = Vec::new();
+        for (i, def) in defs.iter().enumerate() {
+            if self.definitions.contains_key(&def.name) || block[..i].contains(&def.name) {
+                return Err(format!("Inductive type {:?} already defined", def.name));
+            }
+            for constructor in &def.constructors {
+                if self
+                    .constructor_to_inductive
+                    .contains_key(&constructor.name)
+                    || constructors.contains(&constructor.name)
+                {
+                    return Err(format!(
+                        "Constructor {:?} already defined",
+                        constructor.name
+                    ));
+                }
+                constructors.push(constructor.name);
             }
-            self.constructor_to_inductive
-                .insert(constructor.name, def.name);
         }
+        check_positivity(&defs).map_err(|e| e.to_string())?;
+
+        for mut def in defs {
+            // Register all constructors
+            for constructor in &def.constructors {
+                self.constructor_to_inductive
+                    .insert(constructor.name, def.name);
+            }
 
-        self.definitions.insert(def.name, def);
+            def.sort = intern_rc(&def.sort);
+            for param in &mut def.parameters {
+                param.ty = intern_rc(&param.ty);
+            }
+            for constructor in &mut def.constructors {
+                constructor.ty = intern_rc(&constructor.ty);
+            }
+
+            self.blocks.insert(def.name, block.clone());
+            self.definitions.insert(def.name, def);
+        }
         Ok(())
     }
 
+    /// Removes the inductive type or the constructor `name`. The constructors
+    /// of an inductive type are removed with it.
+    pub fn remove(&mut self, name: Id) {
+        self.constructor_to_inductive.remove(&name);
+        if let Some(def) = self.definitions.remove(&name) {
+            for constructor in &def.constructors {
+                self.constructor_to_inductive.remove(&constructor.name);
+            }
+        }
+        self.blocks.remove(&name);
+    }
+
+    /// The inductive types declared together with `name`, itself included
+    pub fn mutual_block(&self, name: Id) -> &[Id] {
+        self.blocks.get(&name).map_or(&[], |block| block)
+    }
+
     /// Gets an inductive definition by name
     pub fn get_inductive(&self, name: Id) -> Option<&InductiveDefinition> {
         self.definitions.get(&name)
@@ -158,6 +229,81 @@ impl InductiveEnvironment {
     }
 }
 
+/// Checks that the types of a block occur only strictly positively in the
+/// arguments of its constructors: an argument either does not mention them,
+/// or is a function type returning one of them whose domains do not mention
+/// them.
+pub fn check_positivity(block: &[InductiveDefinition]) -> Result<(), PositivityError> {
+    let names: Vec<Id> = block.iter().map(|def| def.name).collect();
+    for def in block {
+        for constructor in &def.constructors {
+            let mut ty = constructor.ty.as_ref();
+            while let Term::Product(product) = ty {
+                if !strictly_positive(&product.source, &names) {
+                    // The type of the block that occurs in the argument
+                    let inductive = names
+                        .iter()
+                        .find(|name| {
+                            !strictly_positive(&product.source, std::slice::from_ref(name))
+                        })
+                        .copied()
+                        .unwrap_or(def.name);
+                    return Err(PositivityError {
+                        inductive,
+                        constructor: constructor.name,
+                    });
+                }
+                ty = &product.target;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn strictly_positive(ty: &Term, names: &[Id]) -> bool {
+    match ty {
+        _ if !mentions_any(ty, names) => true,
+        Term::Product(product) => {
+            !mentions_any(&product.source, names) && strictly_positive(&product.target, names)
+        }
+        Term::Constant(_) => true,
+        Term::Application(app) => {
+            matches!(app.f.as_ref(), Term::Constant(_))
+                && app.args.iter().all(|arg| !mentions_any(arg, names))
+        }
+        _ => false,
+    }
+}
+
+fn mentions_any(term: &Term, names: &[Id]) -> bool {
+    match term {
+        Term::Sort(_) | Term::Variable(_) | Term::Bound(_) | Term::Literal(_) => false,
+        Term::Constant(c) => names.contains(&c.id),
+        Term::Product(product) => {
+            mentions_any(&product.source, names) || mentions_any(&product.target, names)
+        }
+        Term::Lambda(lambda) => {
+            mentions_any(&lambda.source_ty, names) || mentions_any(&lambda.target, names)
+        }
+        Term::Application(app) => {
+            mentions_any(&app.f, names) || app.args.iter().any(|arg| mentions_any(arg, names))
+        }
+        Term::LetIn(let_in) => {
+            mentions_any(&let_in.term, names)
+                || mentions_any(&let_in.ty, names)
+                || mentions_any(&let_in.body, names)
+        }
+        Term::Match(case) => {
+            mentions_any(&case.scrutinee, names)
+                || mentions_any(&case.return_type, names)
+                || case
+                    .branches
+                    .iter()
+                    .any(|branch| mentions_any(&branch.body, names))
+        }
+    }
+}
+
 /// Helper functions for building common inductive types
 impl InductiveEnvironment {
     /// Creates the standard Bool inductive type
@@ -220,8 +366,10 @@ mod tests {
         id::Id,
         local_context::LocalContext,
         reduction::reduce_step,
-        term::{Sort, Term, TermConstant, TermMatch, TermMatchBranch, TermSort, TermVariable},
-        typechecker::infer_type,
+        term::{
+            Sort, Term, TermBound, TermConstant, TermMatch, TermMatchBranch, TermSort, TermVariable,
+        },
+        typechecker::{TypeError, infer_type},
     };
 
     use super::{ConstructorDefinition, InductiveDefinition, InductiveEnvironment, Parameter};
@@ -461,7 +609,7 @@ mod tests {
                 TermMatchBranch {
                     constructor_id: succ_id,
                     bound_vars: vec![n],
-                    body: Rc::new(Term::Variable(TermVariable { id: n })),
+                    body: Rc::new(Term::Bound(TermBound { index: 0 })),
                 },
             ],
         });
@@ -470,6 +618,77 @@ mod tests {
         assert_eq!(reduced, one);
     }
 
+    /// `A -> B`
+    fn arrow(a: Rc<Term>, b: Rc<Term>) -> Rc<Term> {
+        Rc::new(Term::Product(crate::term::TermProduct {
+            var: Id::new(),
+            source: a,
+            target: b,
+        }))
+    }
+
+    #[test]
+    fn test_mutual_inductives_refer_to_each_other() {
+        // tree : Set { node : forest -> tree }, forest : Set { nil : forest, cons : tree -> forest -> forest }
+        let mut inductive_env = InductiveEnvironment::new();
+        let (tree_id, forest_id) = (Id::new(), Id::new());
+        let (node_id, nil_id, cons_id) = (Id::new(), Id::new(), Id::new());
+        let set = Rc::new(Term::Sort(TermSort { sort: Sort::Set }));
+        let tree = Rc::new(Term::Constant(TermConstant { id: tree_id }));
+        let forest = Rc::new(Term::Constant(TermConstant { id: forest_id }));
+
+        let tree_def = InductiveDefinition::new(
+            tree_id,
+            Vec::new(),
+            set.clone(),
+            vec![ConstructorDefinition::new(
+                node_id,
+                arrow(forest.clone(), tree.clone()),
+                1,
+            )],
+        );
+        let forest_def = InductiveDefinition::new(
+            forest_id,
+            Vec::new(),
+            set,
+            vec![
+                ConstructorDefinition::new(nil_id, forest.clone(), 0),
+                ConstructorDefinition::new(cons_id, arrow(tree, arrow(forest.clone(), forest)), 2),
+            ],
+        );
+
+        inductive_env
+            .add_mutual_inductives(vec![tree_def, forest_def])
+            .unwrap();
+        assert_eq!(inductive_env.mutual_block(tree_id), &[tree_id, forest_id]);
+        assert_eq!(
+            inductive_env.get_inductive_for_constructor(cons_id),
+            Some(forest_id)
+        );
+    }
+
+    #[test]
+    fn test_non_positive_constructor_is_rejected() {
+        // bad : Set { mk : (bad -> bad) -> bad }
+        let mut inductive_env = InductiveEnvironment::new();
+        let (bad_id, mk_id) = (Id::new(), Id::new());
+        let bad = Rc::new(Term::Constant(TermConstant { id: bad_id }));
+        let bad_def = InductiveDefinition::new(
+            bad_id,
+            Vec::new(),
+            Rc::new(Term::Sort(TermSort { sort: Sort::Set })),
+            vec![ConstructorDefinition::new(
+                mk_id,
+                arrow(arrow(bad.clone(), bad.clone()), bad),
+                1,
+            )],
+        );
+
+        assert!(inductive_env.add_inductive(bad_def).is_err());
+        assert!(inductive_env.get_inductive(bad_id).is_none());
+        assert_eq!(inductive_env.get_inductive_for_constructor(mk_id), None);
+    }
+
     #[test]
     fn test_list_inductive_definition() {
         let mut inductive_env = InductiveEnvironment::new();
@@ -598,6 +817,7 @@ mod tests {
             name: plus_id,
             ty: plus_type,
             body: None, // We'll treat it as an axiom for this test
+            opaque: false,
         })
         .unwrap();
 
@@ -610,17 +830,16 @@ mod tests {
 
         let set = Rc::new(Term::Sort(TermSort { sort: Sort::Set }));
         let prop = Rc::new(Term::Sort(TermSort { sort: Sort::Prop }));
-        let var_a = Rc::new(Term::Variable(TermVariable { id: a_param }));
 
         let eq_type = Rc::new(Term::Product(crate::term::TermProduct {
             var: a_param,
             source: set.clone(),
             target: Rc::new(Term::Product(crate::term::TermProduct {
                 var: x_param,
-                source: var_a.clone(),
+                source: Rc::new(Term::Bound(TermBound { index: 0 })),
                 target: Rc::new(Term::Product(crate::term::TermProduct {
                     var: y_param,
-                    source: var_a.clone(),
+                    source: Rc::new(Term::Bound(TermBound { index: 1 })),
                     target: prop,
                 })),
             })),
@@ -630,6 +849,7 @@ mod tests {
             name: eq_id,
             ty: eq_type,
             body: None,
+            opaque: false,
         })
         .unwrap();
 
@@ -638,8 +858,9 @@ mod tests {
         let n_var = Id::new();
         let m_var = Id::new();
 
-        let var_n = Rc::new(Term::Variable(TermVariable { id: n_var }));
-        let var_m = Rc::new(Term::Variable(TermVariable { id: m_var }));
+        // Under the binders of n and m
+        let var_n = Rc::new(Term::Bound(TermBound { index: 1 }));
+        let var_m = Rc::new(Term::Bound(TermBound { index: 0 }));
 
         // plus n m
         let plus_n_m = Rc::new(Term::Application(crate::term::TermApplication {
@@ -693,15 +914,15 @@ mod tests {
         let eq_refl_id = Id::new();
         let refl_a_param = Id::new();
         let refl_x_param = Id::new();
-        let refl_var_a = Rc::new(Term::Variable(TermVariable { id: refl_a_param }));
-        let refl_var_x = Rc::new(Term::Variable(TermVariable { id: refl_x_param }));
+        let refl_var_a = Rc::new(Term::Bound(TermBound { index: 1 }));
+        let refl_var_x = Rc::new(Term::Bound(TermBound { index: 0 }));
 
         let eq_refl_type = Rc::new(Term::Product(crate::term::TermProduct {
             var: refl_a_param,
             source: set.clone(),
             target: Rc::new(Term::Product(crate::term::TermProduct {
                 var: refl_x_param,
-                source: refl_var_a.clone(),
+                source: Rc::new(Term::Bound(TermBound { index: 0 })),
                 target: Rc::new(Term::Application(crate::term::TermApplication {
                     f: Rc::new(Term::Constant(TermConstant { id: eq_id })),
                     args: vec![
@@ -717,6 +938,7 @@ mod tests {
             name: eq_refl_id,
             ty: eq_refl_type,
             body: None, // Axiom for reflexivity
+            opaque: false,
         })
         .unwrap();
 
@@ -736,7 +958,7 @@ mod tests {
                 var: proof_m_var,
                 source_ty: nat_type.clone(),
                 target: Rc::new(Term::Match(TermMatch {
-                    scrutinee: Rc::new(Term::Variable(TermVariable { id: proof_n_var })),
+                    scrutinee: Rc::new(var_n.as_ref().clone()),
                     return_type: Rc::new(Term::Application(crate::term::TermApplication {
                         f: Rc::new(Term::Constant(TermConstant { id: eq_id })),
                         args: vec![
@@ -744,18 +966,12 @@ mod tests {
                             // plus n m
                             Term::Application(crate::term::TermApplication {
                                 f: Rc::new(Term::Constant(TermConstant { id: plus_id })),
-                                args: vec![
-                                    Term::Variable(TermVariable { id: proof_n_var }),
-                                    Term::Variable(TermVariable { id: proof_m_var }),
-                                ],
+                                args: vec![var_n.as_ref().clone(), var_m.as_ref().clone()],
                             }),
                             // plus m n
                             Term::Application(crate::term::TermApplication {
                                 f: Rc::new(Term::Constant(TermConstant { id: plus_id })),
-                                args: vec![
-                                    Term::Variable(TermVariable { id: proof_m_var }),
-                                    Term::Variable(TermVariable { id: proof_n_var }),
-                                ],
+                                args: vec![var_m.as_ref().clone(), var_n.as_ref().clone()],
                             }),
                         ],
                     })),
@@ -769,7 +985,7 @@ mod tests {
                                     f: Rc::new(Term::Constant(TermConstant { id: eq_refl_id })),
                                     args: vec![nat_type.as_ref().clone()],
                                 })),
-                                args: vec![Term::Variable(TermVariable { id: proof_m_var })],
+                                args: vec![var_m.as_ref().clone()],
                             })),
                         },
                         // Case n = S p: would need inductive hypothesis
@@ -781,7 +997,8 @@ mod tests {
                                     f: Rc::new(Term::Constant(TermConstant { id: eq_refl_id })),
                                     args: vec![nat_type.as_ref().clone()],
                                 })),
-                                args: vec![Term::Variable(TermVariable { id: proof_m_var })],
+                                // m is behind the binder of p
+                                args: vec![Term::Bound(TermBound { index: 1 })],
                             })),
                         },
                     ],
@@ -789,24 +1006,13 @@ mod tests {
             })),
         });
 
-        // Check that the proof term has the correct type
-        let proof_type = infer_type(&ctx, &env, &proof_body).unwrap();
-        assert_eq!(*proof_type, commutativity_type);
-
-        // Add the proof as a constant to the environment
-        let proof_constant_id = Id::new();
-        env.add_constant(crate::global_environment::ConstantDefinition {
-            name: proof_constant_id,
-            ty: Rc::new(commutativity_type.clone()),
-            body: Some(Rc::new(proof_body)),
-        })
-        .unwrap();
-
-        let proof_constant = Term::Constant(TermConstant {
-            id: proof_constant_id,
-        });
-        let constant_type = infer_type(&ctx, &env, &proof_constant).unwrap();
-        assert_eq!(*constant_type, commutativity_type);
+        // `plus` is an axiom, so `eq_refl nat m` does not prove `plus O m = plus m O`:
+        // the branches are checked against the return type and the proof is rejected
+        let result = infer_type(&ctx, &env, &proof_body);
+        assert!(
+            matches!(result, Err(TypeError::TypeMismatch { .. })),
+            "{result:?}"
+        );
     }
 
     #[test]
@@ -829,7 +1035,7 @@ mod tests {
         let identity_function = Term::Lambda(crate::term::TermLambda {
             var: n_var,
             source_ty: nat_type.clone(),
-            target: Rc::new(Term::Variable(TermVariable { id: n_var })),
+            target: Rc::new(Term::Bound(TermBound { index: 0 })),
         });
 
         // Check that this lambda has the right type: Nat -> Nat
@@ -873,7 +1079,7 @@ mod tests {
         let plus_zero_left = Term::Lambda(crate::term::TermLambda {
             var: n_var,
             source_ty: Rc::new(Term::Constant(TermConstant { id: nat_id })),
-            target: Rc::new(Term::Variable(TermVariable { id: n_var })),
+            target: Rc::new(Term::Bound(TermBound { index: 0 })),
         });
 
         // Test: (λn. n) 1 should reduce to 1
@@ -917,6 +1123,7 @@ mod tests {
             name: plus_id,
             ty: plus_type,
             body: None,
+            opaque: false,
         })
         .unwrap();
 
@@ -927,17 +1134,16 @@ mod tests {
 
         let set = Rc::new(Term::Sort(TermSort { sort: Sort::Set }));
         let prop = Rc::new(Term::Sort(TermSort { sort: Sort::Prop }));
-        let var_a = Rc::new(Term::Variable(TermVariable { id: a_param }));
 
         let eq_type = Rc::new(Term::Product(crate::term::TermProduct {
             var: a_param,
             source: set,
             target: Rc::new(Term::Product(crate::term::TermProduct {
                 var: x_param,
-                source: var_a.clone(),
+                source: Rc::new(Term::Bound(TermBound { index: 0 })),
                 target: Rc::new(Term::Product(crate::term::TermProduct {
                     var: y_param,
-                    source: var_a.clone(),
+                    source: Rc::new(Term::Bound(TermBound { index: 1 })),
                     target: prop,
                 })),
             })),
@@ -947,6 +1153,7 @@ mod tests {
             name: eq_id,
             ty: eq_type,
             body: None,
+            opaque: false,
         })
         .unwrap();
 
@@ -955,8 +1162,9 @@ mod tests {
         let n_var = Id::new();
         let m_var = Id::new();
 
-        let var_n = Rc::new(Term::Variable(TermVariable { id: n_var }));
-        let var_m = Rc::new(Term::Variable(TermVariable { id: m_var }));
+        // Under the binders of n and m
+        let var_n = Rc::new(Term::Bound(TermBound { index: 1 }));
+        let var_m = Rc::new(Term::Bound(TermBound { index: 0 }));
 
         // plus n m
         let plus_n_m = Rc::new(Term::Application(crate::term::TermApplication {
@@ -1010,6 +1218,7 @@ mod tests {
             name: false_proof_id,
             ty: Rc::new(false_statement_type.clone()),
             body: None, // Only as an axiom - there's no real proof!
+            opaque: false,
         })
         .unwrap();
 
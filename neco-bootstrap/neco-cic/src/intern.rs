@@ -0,0 +1,430 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    rc::{Rc, Weak},
+};
+
+use crate::{
+    id::Id,
+    term::{
+        Sort, Term, TermApplication, TermLambda, TermLetIn, TermMatch, TermMatchBranch, TermProduct,
+    },
+};
+
+// Hash-consing of kernel terms.
+//
+// Interning a term returns a shared node: structurally equal terms (with the
+// same binder names) interned on the same thread are the same allocation, so
+// `Rc` equality short-circuits on pointers and clones are free. Interned nodes
+// also carry cached `TermInfo`, which lets substitution and conversion skip
+// subterms that cannot be affected.
+//
+// Terms are `Rc`-based and cannot cross threads, so there is one table per
+// thread. The table only holds weak references: a node is freed once the terms
+// (and environments) using it are dropped. The weak references keep the
+// allocations of dead nodes, so their addresses are not reused while they are
+// in the table; dead entries are swept once the table doubles in size.
+
+/// Cached information about an interned term
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TermInfo {
+    /// Number of enclosing binders the term refers to, i.e. one more than its
+    /// greatest loose de Bruijn index (0 if it has no loose bound variable)
+    pub loose_bound: usize,
+    /// Whether the term mentions a free variable (`Term::Variable`)
+    pub has_free_vars: bool,
+}
+
+impl TermInfo {
+    /// Closed terms are unaffected by substitution and lifting
+    pub fn is_closed(&self) -> bool {
+        self.loose_bound == 0 && !self.has_free_vars
+    }
+
+    fn join(self, other: TermInfo) -> TermInfo {
+        TermInfo {
+            loose_bound: self.loose_bound.max(other.loose_bound),
+            has_free_vars: self.has_free_vars || other.has_free_vars,
+        }
+    }
+
+    /// Information about a term seen from outside `count` binders
+    fn under(self, count: usize) -> TermInfo {
+        TermInfo {
+            loose_bound: self.loose_bound.saturating_sub(count),
+            has_free_vars: self.has_free_vars,
+        }
+    }
+}
+
+/// Counters of the interning table of the current thread
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InternStats {
+    /// Number of distinct live nodes in the table
+    pub nodes: usize,
+    /// Number of times an existing node was reused
+    pub hits: usize,
+}
+
+/// Shape of a node whose children are interned, identified by address
+#[derive(Debug, PartialEq, Eq, Hash)]
+enum Key {
+    Sort(Sort),
+    Variable(Id),
+    Bound(usize),
+    Constant(Id),
+    Literal(u64),
+    Product(Id, usize, usize),
+    Lambda(Id, usize, usize),
+    Application(usize, Vec<usize>),
+    LetIn(Id, usize, usize, usize),
+    Match(usize, usize, Vec<(Id, Vec<Id>, usize)>),
+}
+
+/// Size of the table below which dead entries are not swept
+const MIN_COLLECT_AT: usize = 1024;
+
+struct TermTable {
+    /// The nodes by shape, with the interned arguments of applications, which
+    /// are stored inline and would otherwise be freed while their addresses
+    /// are part of the key
+    nodes: HashMap<Key, (Weak<Term>, Vec<Rc<Term>>)>,
+    /// Info of interned nodes, and of the arguments stored inline in interned
+    /// applications, by address, with the node that owns the address
+    infos: HashMap<*const Term, (TermInfo, Weak<Term>)>,
+    hits: usize,
+    /// Number of nodes at which dead entries are swept next
+    collect_at: usize,
+}
+
+impl Default for TermTable {
+    fn default() -> Self {
+        TermTable {
+            nodes: HashMap::new(),
+            infos: HashMap::new(),
+            hits: 0,
+            collect_at: MIN_COLLECT_AT,
+        }
+    }
+}
+
+thread_local! {
+    static TABLE: RefCell<TermTable> = RefCell::new(TermTable::default());
+}
+
+/// Returns the shared node for `term`, interning all of its subterms
+pub fn intern(term: &Term) -> Rc<Term> {
+    TABLE.with(|table| table.borrow_mut().intern(term))
+}
+
+/// Returns the shared node for `term`, reusing `term` itself if it is interned
+pub fn intern_rc(term: &Rc<Term>) -> Rc<Term> {
+    TABLE.with(|table| table.borrow_mut().intern_rc(term))
+}
+
+/// Cached information about `term`, if it is an interned node
+pub fn info(term: &Term) -> Option<TermInfo> {
+    TABLE.with(|table| table.borrow().info(term))
+}
+
+/// Checks whether `term` is an interned node
+pub fn is_interned(term: &Term) -> bool {
+    info(term).is_some()
+}
+
+/// Counters of the interning table of the current thread
+pub fn stats() -> InternStats {
+    TABLE.with(|table| {
+        let table = table.borrow();
+        InternStats {
+            nodes: table
+                .nodes
+                .values()
+                .filter(|(node, _)| node.strong_count() > 0)
+                .count(),
+            hits: table.hits,
+        }
+    })
+}
+
+fn address(term: &Rc<Term>) -> usize {
+    Rc::as_ptr(term) as usize
+}
+
+impl TermTable {
+    /// The info of `term`, if it is a live interned node or an argument of one.
+    /// An address whose owner is dead may have been reused by another term.
+    fn info(&self, term: &Term) -> Option<TermInfo> {
+        let (info, owner) = self.infos.get(&(term as *const Term))?;
+        (owner.strong_count() > 0).then_some(*info)
+    }
+
+    fn info_of(&self, term: &Term) -> TermInfo {
+        self.info(term).expect("the term should be interned")
+    }
+
+    fn intern_rc(&mut self, term: &Rc<Term>) -> Rc<Term> {
+        if self.info(term).is_some() {
+            self.hits += 1;
+            return term.clone();
+        }
+        self.intern(term)
+    }
+
+    fn intern(&mut self, term: &Term) -> Rc<Term> {
+        let mut inline_args = Vec::new();
+        let (key, node, info) = match term {
+            Term::Sort(sort) => (
+                Key::Sort(sort.sort.clone()),
+                term.clone(),
+                TermInfo {
+                    loose_bound: 0,
+                    has_free_vars: false,
+                },
+            ),
+            Term::Variable(var) => (
+                Key::Variable(var.id),
+                term.clone(),
+                TermInfo {
+                    loose_bound: 0,
+                    has_free_vars: true,
+                },
+            ),
+            Term::Bound(bound) => (
+                Key::Bound(bound.index),
+                term.clone(),
+                TermInfo {
+                    loose_bound: bound.index + 1,
+                    has_free_vars: false,
+                },
+            ),
+            Term::Constant(const_) => (
+                Key::Constant(const_.id),
+                term.clone(),
+                TermInfo {
+                    loose_bound: 0,
+                    has_free_vars: false,
+                },
+            ),
+            Term::Literal(literal) => (
+                Key::Literal(literal.value),
+                term.clone(),
+                TermInfo {
+                    loose_bound: 0,
+                    has_free_vars: false,
+                },
+            ),
+            Term::Product(product) => {
+                let source = self.intern_rc(&product.source);
+                let target = self.intern_rc(&product.target);
+                let info = self.info_of(&source).join(self.info_of(&target).under(1));
+                (
+                    Key::Product(product.var, address(&source), address(&target)),
+                    Term::Product(TermProduct {
+                        var: product.var,
+                        source,
+                        target,
+                    }),
+                    info,
+                )
+            }
+            Term::Lambda(lambda) => {
+                let source_ty = self.intern_rc(&lambda.source_ty);
+                let target = self.intern_rc(&lambda.target);
+                let info = self
+                    .info_of(&source_ty)
+                    .join(self.info_of(&target).under(1));
+                (
+                    Key::Lambda(lambda.var, address(&source_ty), address(&target)),
+                    Term::Lambda(TermLambda {
+                        var: lambda.var,
+                        source_ty,
+                        target,
+                    }),
+                    info,
+                )
+            }
+            Term::Application(app) => {
+                let f = self.intern_rc(&app.f);
+                let args: Vec<_> = app.args.iter().map(|arg| self.intern(arg)).collect();
+                let info = args
+                    .iter()
+                    .fold(self.info_of(&f), |info, arg| info.join(self.info_of(arg)));
+                let key = Key::Application(address(&f), args.iter().map(address).collect());
+                let node = Term::Application(TermApplication {
+                    f,
+                    args: args.iter().map(|arg| arg.as_ref().clone()).collect(),
+                });
+                inline_args = args;
+                (key, node, info)
+            }
+            Term::LetIn(let_in) => {
+                let value = self.intern_rc(&let_in.term);
+                let ty = self.intern_rc(&let_in.ty);
+                let body = self.intern_rc(&let_in.body);
+                let info = self
+                    .info_of(&value)
+                    .join(self.info_of(&ty))
+                    .join(self.info_of(&body).under(1));
+                (
+                    Key::LetIn(let_in.var, address(&value), address(&ty), address(&body)),
+                    Term::LetIn(TermLetIn {
+                        var: let_in.var,
+                        term: value,
+                        ty,
+                        body,
+                    }),
+                    info,
+                )
+            }
+            Term::Match(case) => {
+                let scrutinee = self.intern_rc(&case.scrutinee);
+                let return_type = self.intern_rc(&case.return_type);
+                let branches: Vec<_> = case
+                    .branches
+                    .iter()
+                    .map(|branch| TermMatchBranch {
+                        constructor_id: branch.constructor_id,
+                        bound_vars: branch.bound_vars.clone(),
+                        body: self.intern_rc(&branch.body),
+                    })
+                    .collect();
+                let info = branches.iter().fold(
+                    self.info_of(&scrutinee).join(self.info_of(&return_type)),
+                    |info, branch| {
+                        info.join(self.info_of(&branch.body).under(branch.bound_vars.len()))
+                    },
+                );
+                (
+                    Key::Match(
+                        address(&scrutinee),
+                        address(&return_type),
+                        branches
+                            .iter()
+                            .map(|branch| {
+                                (
+                                    branch.constructor_id,
+                                    branch.bound_vars.clone(),
+                                    address(&branch.body),
+                                )
+                            })
+                            .collect(),
+                    ),
+                    Term::Match(TermMatch {
+                        scrutinee,
+                        return_type,
+                        branches,
+                    }),
+                    info,
+                )
+            }
+        };
+
+        if let Some(existing) = self.nodes.get(&key).and_then(|(node, _)| node.upgrade()) {
+            self.hits += 1;
+            return existing;
+        }
+
+        let node = Rc::new(node);
+        let owner = Rc::downgrade(&node);
+        self.infos.insert(Rc::as_ptr(&node), (info, owner.clone()));
+        if let (Term::Application(app), Key::Application(_, addresses)) = (node.as_ref(), &key) {
+            for (arg, &address) in app.args.iter().zip(addresses) {
+                let arg_info = self.infos[&(address as *const Term)].0;
+                self.infos
+                    .insert(arg as *const Term, (arg_info, owner.clone()));
+            }
+        }
+        self.nodes.insert(key, (owner, inline_args));
+        if self.nodes.len() >= self.collect_at {
+            self.collect();
+        }
+        node
+    }
+
+    /// Drops the entries of dead nodes, freeing their allocations
+    fn collect(&mut self) {
+        self.nodes.retain(|_, (node, _)| node.strong_count() > 0);
+        self.infos.retain(|_, (_, owner)| owner.strong_count() > 0);
+        self.collect_at = MIN_COLLECT_AT.max(2 * self.nodes.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use crate::{
+        id::Id,
+        term::{Sort, Term, TermApplication, TermBound, TermLambda, TermSort, TermVariable},
+    };
+
+    use super::{MIN_COLLECT_AT, TABLE, info, intern, is_interned, stats};
+
+    fn set() -> Term {
+        Term::Sort(TermSort { sort: Sort::Set })
+    }
+
+    fn lambda(var: Id, body: Term) -> Term {
+        Term::Lambda(TermLambda {
+            var,
+            source_ty: Rc::new(set()),
+            target: Rc::new(body),
+        })
+    }
+
+    #[test]
+    fn test_equal_terms_are_shared() {
+        let x = Id::new();
+        let term = lambda(x, Term::Bound(TermBound { index: 0 }));
+        let first = intern(&term);
+        let second = intern(&term.clone());
+        assert!(Rc::ptr_eq(&first, &second));
+        assert!(is_interned(&first));
+        assert!(stats().hits > 0);
+
+        // Subterms are shared too
+        let Term::Lambda(lambda) = first.as_ref() else {
+            panic!("expected a lambda");
+        };
+        assert!(Rc::ptr_eq(&lambda.source_ty, &intern(&set())));
+    }
+
+    #[test]
+    fn test_info_tracks_loose_and_free_variables() {
+        let x = Id::new();
+        let closed = intern(&lambda(x, Term::Bound(TermBound { index: 0 })));
+        assert!(info(&closed).unwrap().is_closed());
+
+        let open = intern(&lambda(x, Term::Bound(TermBound { index: 2 })));
+        assert_eq!(info(&open).unwrap().loose_bound, 2);
+
+        let free = intern(&Term::Application(TermApplication {
+            f: Rc::new(Term::Variable(TermVariable { id: Id::new() })),
+            args: vec![set()],
+        }));
+        let free_info = info(&free).unwrap();
+        assert!(free_info.has_free_vars);
+        let Term::Application(app) = free.as_ref() else {
+            panic!("expected an application");
+        };
+        assert!(info(&app.args[0]).unwrap().is_closed());
+    }
+
+    #[test]
+    fn test_dead_nodes_are_collected() {
+        let term = intern(&lambda(Id::new(), Term::Bound(TermBound { index: 0 })));
+        let Term::Lambda(lambda) = term.as_ref() else {
+            panic!("expected a lambda");
+        };
+        let body = Rc::downgrade(&lambda.target);
+        drop(term);
+        assert!(body.upgrade().is_none());
+
+        for _ in 0..2 * MIN_COLLECT_AT {
+            intern(&Term::Variable(TermVariable { id: Id::new() }));
+        }
+        assert_eq!(stats().nodes, 0);
+        assert!(TABLE.with(|table| table.borrow().nodes.len()) < MIN_COLLECT_AT);
+    }
+}
@@ -1,8 +1,19 @@
+pub mod conversion;
+pub mod export;
+pub mod extract;
 pub mod global_environment;
 pub mod id;
 pub mod inductive;
+pub mod intern;
 pub mod local_context;
+pub mod nbe;
+pub mod pretty;
+pub mod primitive;
 pub mod reduction;
+pub mod serialize;
 pub mod substitution;
 pub mod term;
+pub mod termination;
 pub mod typechecker;
+pub mod u64_spec;
+pub mod well_founded;
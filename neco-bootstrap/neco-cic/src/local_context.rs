@@ -1,6 +1,6 @@
 use std::{collections::HashMap, rc::Rc};
 
-use crate::{id::Id, term::Term};
+use crate::{id::Id, substitution::lift, term::Term};
 
 /// Local context (typing context) that maps variable identifiers to their types.
 /// In CIC, this represents Γ in the judgment Γ ⊢ t : T
@@ -11,6 +11,10 @@ pub struct LocalContext {
     /// Ordered list of bindings for proper scoping
     /// This is important for dependent types where later bindings can refer to earlier ones
     order: Vec<Id>,
+    /// Types of the binders entered so far, innermost last.
+    /// `Term::Bound(i)` refers to `binders[binders.len() - 1 - i]`, and each
+    /// type lives in the context of the binders before it.
+    binders: Vec<(Id, Rc<Term>)>,
 }
 
 impl LocalContext {
@@ -19,6 +23,7 @@ impl LocalContext {
         LocalContext {
             bindings: HashMap::new(),
             order: Vec::new(),
+            binders: Vec::new(),
         }
     }
 
@@ -41,6 +46,32 @@ impl LocalContext {
         new_ctx
     }
 
+    /// Enters a binder named `var` of type `ty`
+    pub fn push(&mut self, var: Id, ty: Rc<Term>) {
+        self.binders.push((var, ty));
+    }
+
+    /// Creates a new context with an additional binder
+    pub fn pushed(&self, var: Id, ty: Rc<Term>) -> Self {
+        let mut new_ctx = self.clone();
+        new_ctx.push(var, ty);
+        new_ctx
+    }
+
+    /// Looks up the type of the bound variable with de Bruijn index `index`.
+    /// The type is lifted so that it is valid under all current binders.
+    pub fn lookup_bound(&self, index: usize) -> Option<Rc<Term>> {
+        let position = self.binders.len().checked_sub(index + 1)?;
+        let (_, ty) = &self.binders[position];
+        Some(Rc::new(lift(ty, index + 1)))
+    }
+
+    /// Returns the name of the binder with de Bruijn index `index`
+    pub fn bound_name(&self, index: usize) -> Option<Id> {
+        let position = self.binders.len().checked_sub(index + 1)?;
+        Some(self.binders[position].0)
+    }
+
     /// Looks up the type of a variable
     pub fn lookup(&self, var: Id) -> Option<Rc<Term>> {
         self.bindings.get(&var).cloned()
@@ -51,14 +82,14 @@ impl LocalContext {
         self.bindings.contains_key(&var)
     }
 
-    /// Returns the number of bindings
+    /// Returns the number of bindings, including binders
     pub fn len(&self) -> usize {
-        self.bindings.len()
+        self.bindings.len() + self.binders.len()
     }
 
     /// Checks if the context is empty
     pub fn is_empty(&self) -> bool {
-        self.bindings.is_empty()
+        self.bindings.is_empty() && self.binders.is_empty()
     }
 
     /// Removes a binding from the context
@@ -0,0 +1,683 @@
+use std::{cell::Cell, rc::Rc};
+
+use crate::{
+    global_environment::GlobalEnvironment,
+    id::Id,
+    reduction::matches_on_proof,
+    substitution::is_closed_under,
+    term::{
+        Sort, Term, TermApplication, TermBound, TermConstant, TermLambda, TermLiteral, TermMatch,
+        TermMatchBranch, TermProduct, TermSort, TermVariable,
+    },
+};
+
+// Normalization by evaluation: terms are evaluated into semantic values where
+// binders are closures, so β, ζ and ι steps cost one environment lookup instead
+// of a substitution over the whole body. Normal forms are read back from values.
+
+/// Semantic value of a term
+#[derive(Debug, Clone)]
+pub enum Value {
+    Sort(Sort),
+    Literal(u64),
+    Product(ValueBinder),
+    Lambda(ValueBinder),
+    /// A head that cannot reduce applied to arguments
+    Neutral(Head, Vec<Rc<Value>>),
+    /// A defined constant applied to arguments, unfolded only on demand
+    Delayed(Id, Vec<Rc<Value>>),
+}
+
+/// The domain and the body of a product or a lambda
+#[derive(Debug, Clone)]
+pub struct ValueBinder {
+    pub var: Id,
+    pub domain: Rc<Value>,
+    pub closure: Closure,
+}
+
+/// Head of a neutral value
+#[derive(Debug, Clone)]
+pub enum Head {
+    /// A free variable of the local context
+    Variable(Id),
+    /// A variable introduced during readback or conversion, as a de Bruijn level
+    Level(usize),
+    /// A bound variable that was already loose in the evaluated term,
+    /// counted from the outside of that term
+    Loose(usize),
+    /// An axiom, an inductive type or a constructor
+    Constant(Id),
+    /// A match whose scrutinee is not a constructor application
+    Match(Rc<StuckMatch>),
+    /// A value that cannot be applied; only produced by ill-typed terms
+    Irreducible(Rc<Value>),
+}
+
+#[derive(Debug, Clone)]
+pub struct StuckMatch {
+    pub scrutinee: Rc<Value>,
+    pub return_type: Rc<Value>,
+    pub branches: Vec<StuckBranch>,
+}
+
+#[derive(Debug, Clone)]
+pub struct StuckBranch {
+    pub constructor_id: Id,
+    pub bound_vars: Vec<Id>,
+    pub closure: Closure,
+}
+
+/// A term together with the values of its loose bound variables
+#[derive(Debug, Clone)]
+pub struct Closure {
+    env: Env,
+    body: Rc<Term>,
+}
+
+/// Values of the bound variables, innermost first
+#[derive(Debug, Clone, Default)]
+pub struct Env {
+    head: Option<Rc<EnvNode>>,
+    len: usize,
+}
+
+#[derive(Debug)]
+struct EnvNode {
+    value: Rc<Value>,
+    next: Option<Rc<EnvNode>>,
+}
+
+impl Closure {
+    /// The term evaluated when the closure is instantiated
+    pub fn body(&self) -> &Rc<Term> {
+        &self.body
+    }
+}
+
+impl Env {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Binds a new innermost variable
+    pub fn push(&self, value: Rc<Value>) -> Self {
+        Env {
+            head: Some(Rc::new(EnvNode {
+                value,
+                next: self.head.clone(),
+            })),
+            len: self.len + 1,
+        }
+    }
+
+    /// Looks up the value of the bound variable with de Bruijn index `index`
+    pub fn lookup(&self, index: usize) -> Option<Rc<Value>> {
+        let mut node = self.head.as_ref()?;
+        for _ in 0..index {
+            node = node.next.as_ref()?;
+        }
+        Some(node.value.clone())
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl Value {
+    /// The variable with de Bruijn level `level`
+    pub fn level(level: usize) -> Rc<Value> {
+        Rc::new(Value::Neutral(Head::Level(level), Vec::new()))
+    }
+}
+
+/// Evaluates terms in a global environment
+pub struct Evaluator<'a> {
+    global: &'a GlobalEnvironment,
+    /// Number of constants unfolded so far
+    unfoldings: Cell<usize>,
+}
+
+impl<'a> Evaluator<'a> {
+    pub fn new(global: &'a GlobalEnvironment) -> Self {
+        Evaluator {
+            global,
+            unfoldings: Cell::new(0),
+        }
+    }
+
+    /// The number of constants unfolded so far, which measures how lazily a
+    /// value was computed
+    pub fn unfoldings(&self) -> usize {
+        self.unfoldings.get()
+    }
+
+    /// Evaluates `term`, whose bound variables are given by `env`
+    pub fn eval(&self, term: &Term, env: &Env) -> Rc<Value> {
+        match term {
+            Term::Sort(sort) => Rc::new(Value::Sort(sort.sort.clone())),
+            Term::Variable(var) => Rc::new(Value::Neutral(Head::Variable(var.id), Vec::new())),
+            Term::Bound(bound) => env.lookup(bound.index).unwrap_or_else(|| {
+                Rc::new(Value::Neutral(
+                    Head::Loose(bound.index - env.len()),
+                    Vec::new(),
+                ))
+            }),
+            Term::Constant(const_) => {
+                let defined = self
+                    .global
+                    .get_constant(const_.id)
+                    .is_some_and(|def| def.unfoldable_body().is_some());
+                if defined {
+                    Rc::new(Value::Delayed(const_.id, Vec::new()))
+                } else {
+                    Rc::new(Value::Neutral(Head::Constant(const_.id), Vec::new()))
+                }
+            }
+            Term::Literal(literal) => Rc::new(Value::Literal(literal.value)),
+            Term::Product(product) => Rc::new(Value::Product(ValueBinder {
+                var: product.var,
+                domain: self.eval(&product.source, env),
+                closure: Closure {
+                    env: env.clone(),
+                    body: product.target.clone(),
+                },
+            })),
+            Term::Lambda(lambda) => Rc::new(Value::Lambda(ValueBinder {
+                var: lambda.var,
+                domain: self.eval(&lambda.source_ty, env),
+                closure: Closure {
+                    env: env.clone(),
+                    body: lambda.target.clone(),
+                },
+            })),
+            Term::Application(app) => app.args.iter().fold(self.eval(&app.f, env), |f, arg| {
+                self.apply(f, self.eval(arg, env))
+            }),
+            Term::LetIn(let_in) => {
+                let value = self.eval(&let_in.term, env);
+                self.eval(&let_in.body, &env.push(value))
+            }
+            Term::Match(case) => self.eval_match(case, env),
+        }
+    }
+
+    /// Applies a function value to an argument (β-reduction for lambdas)
+    pub fn apply(&self, f: Rc<Value>, arg: Rc<Value>) -> Rc<Value> {
+        match f.as_ref() {
+            Value::Lambda(binder) => self.instantiate(&binder.closure, &[arg]),
+            Value::Neutral(head, spine) => {
+                let mut spine = spine.clone();
+                spine.push(arg);
+                if let Head::Constant(id) = head
+                    && let Some(value) = self.apply_primitive(*id, &spine)
+                {
+                    return value;
+                }
+                Rc::new(Value::Neutral(head.clone(), spine))
+            }
+            Value::Delayed(id, spine) => {
+                let mut spine = spine.clone();
+                spine.push(arg);
+                Rc::new(Value::Delayed(*id, spine))
+            }
+            Value::Sort(_) | Value::Literal(_) | Value::Product(_) => {
+                Rc::new(Value::Neutral(Head::Irreducible(f.clone()), vec![arg]))
+            }
+        }
+    }
+
+    /// Computes a primitive operation applied to two literals
+    fn apply_primitive(&self, id: Id, spine: &[Rc<Value>]) -> Option<Rc<Value>> {
+        let operation = self.global.primitive_operation(id)?;
+        let [a, b] = spine else {
+            return None;
+        };
+        match (
+            self.force(a.clone()).as_ref(),
+            self.force(b.clone()).as_ref(),
+        ) {
+            (Value::Literal(a), Value::Literal(b)) => {
+                Some(Rc::new(Value::Literal(operation.apply(*a, *b))))
+            }
+            _ => None,
+        }
+    }
+
+    /// Evaluates the body of a closure binding `args.len()` variables.
+    /// `args[0]` is the outermost of them, as for constructor arguments.
+    pub fn instantiate(&self, closure: &Closure, args: &[Rc<Value>]) -> Rc<Value> {
+        let env = args
+            .iter()
+            .fold(closure.env.clone(), |env, arg| env.push(arg.clone()));
+        self.eval(&closure.body, &env)
+    }
+
+    /// Unfolds a delayed constant once (δ-reduction).
+    /// Returns None if the value is not a delayed constant.
+    pub fn unfold(&self, value: &Value) -> Option<Rc<Value>> {
+        let Value::Delayed(id, spine) = value else {
+            return None;
+        };
+        let body = self.global.get_constant(*id)?.unfoldable_body()?;
+        self.unfoldings.set(self.unfoldings.get() + 1);
+        let f = self.eval(body, &Env::new());
+        Some(spine.iter().fold(f, |f, arg| self.apply(f, arg.clone())))
+    }
+
+    /// Unfolds delayed constants until the head of the value is exposed
+    pub fn force(&self, value: Rc<Value>) -> Rc<Value> {
+        let mut value = value;
+        while let Some(unfolded) = self.unfold(&value) {
+            value = unfolded;
+        }
+        value
+    }
+
+    /// ι-reduction, or a stuck match if the scrutinee is not a constructor
+    fn eval_match(&self, case: &TermMatch, env: &Env) -> Rc<Value> {
+        let mut scrutinee = self.force(self.eval(&case.scrutinee, env));
+        loop {
+            if let Value::Neutral(Head::Constant(constructor_id), args) = scrutinee.as_ref()
+                && let Some(branch) = case
+                    .branches
+                    .iter()
+                    .find(|branch| branch.constructor_id == *constructor_id)
+                && let Some(fields) = args.len().checked_sub(branch.bound_vars.len())
+            {
+                // The parameters of the inductive type are not bound
+                let closure = Closure {
+                    env: env.clone(),
+                    body: branch.body.clone(),
+                };
+                return self.instantiate(&closure, &args[fields..]);
+            }
+            match self.unfold_closed_proof(case, &scrutinee) {
+                Some(unfolded) => scrutinee = self.force(unfolded),
+                None => break,
+            }
+        }
+
+        let stuck = StuckMatch {
+            scrutinee,
+            return_type: self.eval(&case.return_type, env),
+            branches: case
+                .branches
+                .iter()
+                .map(|branch| StuckBranch {
+                    constructor_id: branch.constructor_id,
+                    bound_vars: branch.bound_vars.clone(),
+                    closure: Closure {
+                        env: env.clone(),
+                        body: branch.body.clone(),
+                    },
+                })
+                .collect(),
+        };
+        Rc::new(Value::Neutral(Head::Match(Rc::new(stuck)), Vec::new()))
+    }
+
+    /// Unfolds the theorem at the head of a closed proof that a match on a
+    /// proposition is stuck on, as `reduction` does for well-founded recursion
+    fn unfold_closed_proof(&self, case: &TermMatch, scrutinee: &Value) -> Option<Rc<Value>> {
+        let Value::Neutral(Head::Constant(id), spine) = scrutinee else {
+            return None;
+        };
+        let definition = self.global.get_constant(*id).filter(|def| def.opaque)?;
+        let proof = definition.body.as_ref()?;
+        if !matches_on_proof(case, self.global) || !spine.iter().all(|arg| is_closed(arg)) {
+            return None;
+        }
+        let f = self.eval(proof, &Env::new());
+        Some(spine.iter().fold(f, |f, arg| self.apply(f, arg.clone())))
+    }
+
+    /// Reads a value back as a term in normal form, under `depth` binders.
+    /// All constants are unfolded.
+    pub fn quote(&self, value: &Value, depth: usize) -> Term {
+        match value {
+            Value::Sort(sort) => Term::Sort(TermSort { sort: sort.clone() }),
+            Value::Literal(value) => Term::Literal(TermLiteral { value: *value }),
+            Value::Product(binder) => Term::Product(TermProduct {
+                var: binder.var,
+                source: Rc::new(self.quote(&binder.domain, depth)),
+                target: Rc::new(self.quote_closure(&binder.closure, depth, 1)),
+            }),
+            Value::Lambda(binder) => Term::Lambda(TermLambda {
+                var: binder.var,
+                source_ty: Rc::new(self.quote(&binder.domain, depth)),
+                target: Rc::new(self.quote_closure(&binder.closure, depth, 1)),
+            }),
+            Value::Neutral(head, spine) => {
+                let head = self.quote_head(head, depth);
+                if spine.is_empty() {
+                    head
+                } else {
+                    Term::Application(TermApplication {
+                        f: Rc::new(head),
+                        args: spine.iter().map(|arg| self.quote(arg, depth)).collect(),
+                    })
+                }
+            }
+            Value::Delayed(_, _) => {
+                let unfolded = self.unfold(value).expect("delayed constant has a body");
+                self.quote(&unfolded, depth)
+            }
+        }
+    }
+
+    /// Reads back the body of a closure binding `count` variables
+    fn quote_closure(&self, closure: &Closure, depth: usize, count: usize) -> Term {
+        let args: Vec<_> = (depth..depth + count).map(Value::level).collect();
+        self.quote(&self.instantiate(closure, &args), depth + count)
+    }
+
+    fn quote_head(&self, head: &Head, depth: usize) -> Term {
+        match head {
+            Head::Variable(id) => Term::Variable(TermVariable { id: *id }),
+            Head::Level(level) => Term::Bound(TermBound {
+                index: depth - 1 - level,
+            }),
+            Head::Loose(index) => Term::Bound(TermBound {
+                index: depth + index,
+            }),
+            Head::Constant(id) => Term::Constant(TermConstant { id: *id }),
+            Head::Match(stuck) => Term::Match(TermMatch {
+                scrutinee: Rc::new(self.quote(&stuck.scrutinee, depth)),
+                return_type: Rc::new(self.quote(&stuck.return_type, depth)),
+                branches: stuck
+                    .branches
+                    .iter()
+                    .map(|branch| TermMatchBranch {
+                        constructor_id: branch.constructor_id,
+                        bound_vars: branch.bound_vars.clone(),
+                        body: Rc::new(self.quote_closure(
+                            &branch.closure,
+                            depth,
+                            branch.bound_vars.len(),
+                        )),
+                    })
+                    .collect(),
+            }),
+            Head::Irreducible(value) => self.quote(value, depth),
+        }
+    }
+}
+
+/// Whether a value mentions no variable: neither a free one nor one bound
+/// outside of it
+fn is_closed(value: &Value) -> bool {
+    match value {
+        Value::Sort(_) | Value::Literal(_) => true,
+        Value::Product(binder) | Value::Lambda(binder) => {
+            is_closed(&binder.domain) && is_closed_closure(&binder.closure, 1)
+        }
+        Value::Neutral(head, spine) => {
+            let closed_head = match head {
+                Head::Variable(_) | Head::Level(_) | Head::Loose(_) => false,
+                Head::Constant(_) => true,
+                Head::Match(stuck) => {
+                    is_closed(&stuck.scrutinee)
+                        && is_closed(&stuck.return_type)
+                        && stuck.branches.iter().all(|branch| {
+                            is_closed_closure(&branch.closure, branch.bound_vars.len())
+                        })
+                }
+                Head::Irreducible(value) => is_closed(value),
+            };
+            closed_head && spine.iter().all(|arg| is_closed(arg))
+        }
+        Value::Delayed(_, spine) => spine.iter().all(|arg| is_closed(arg)),
+    }
+}
+
+/// Whether a closure binding `count` variables is closed
+fn is_closed_closure(closure: &Closure, count: usize) -> bool {
+    let mut node = closure.env.head.as_ref();
+    while let Some(current) = node {
+        if !is_closed(&current.value) {
+            return false;
+        }
+        node = current.next.as_ref();
+    }
+    is_closed_under(&closure.body, closure.env.len() + count)
+}
+
+/// Normalizes a term by evaluating it and reading the value back
+pub fn normalize(term: &Term, global: &GlobalEnvironment) -> Term {
+    let evaluator = Evaluator::new(global);
+    evaluator.quote(&evaluator.eval(term, &Env::new()), 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use crate::{
+        global_environment::GlobalEnvironment,
+        id::Id,
+        reduction::normalize_with_env_by_steps,
+        term::{
+            Sort, Term, TermApplication, TermBound, TermConstant, TermLambda, TermLetIn, TermMatch,
+            TermMatchBranch, TermProduct, TermSort, TermVariable,
+        },
+    };
+
+    use super::normalize;
+
+    fn constant(id: Id) -> Term {
+        Term::Constant(TermConstant { id })
+    }
+
+    fn bound(index: usize) -> Term {
+        Term::Bound(TermBound { index })
+    }
+
+    fn apply(f: Term, args: Vec<Term>) -> Term {
+        Term::Application(TermApplication {
+            f: Rc::new(f),
+            args,
+        })
+    }
+
+    fn lambda(source_ty: Term, body: Term) -> Term {
+        Term::Lambda(TermLambda {
+            var: Id::new(),
+            source_ty: Rc::new(source_ty),
+            target: Rc::new(body),
+        })
+    }
+
+    struct Nat {
+        env: GlobalEnvironment,
+        nat_id: Id,
+        zero_id: Id,
+        succ_id: Id,
+        add_id: Id,
+    }
+
+    impl Nat {
+        /// Builds nat with `add` defined by recursion on its first argument
+        fn new() -> Self {
+            let mut env = GlobalEnvironment::new();
+            let nat_id = Id::new();
+            let zero_id = Id::new();
+            let succ_id = Id::new();
+            env.inductives.add_nat(nat_id, zero_id, succ_id).unwrap();
+
+            let add_id = Id::new();
+            let nat = Rc::new(constant(nat_id));
+            let add_type = Term::Product(TermProduct {
+                var: Id::new(),
+                source: nat.clone(),
+                target: Rc::new(Term::Product(TermProduct {
+                    var: Id::new(),
+                    source: nat.clone(),
+                    target: nat.clone(),
+                })),
+            });
+            let add_body = lambda(
+                constant(nat_id),
+                lambda(
+                    constant(nat_id),
+                    Term::Match(TermMatch {
+                        scrutinee: Rc::new(bound(1)),
+                        return_type: nat,
+                        branches: vec![
+                            TermMatchBranch {
+                                constructor_id: zero_id,
+                                bound_vars: vec![],
+                                body: Rc::new(bound(0)),
+                            },
+                            TermMatchBranch {
+                                constructor_id: succ_id,
+                                bound_vars: vec![Id::new()],
+                                body: Rc::new(apply(
+                                    constant(succ_id),
+                                    vec![apply(constant(add_id), vec![bound(0), bound(1)])],
+                                )),
+                            },
+                        ],
+                    }),
+                ),
+            );
+            env.add_definition(add_id, Rc::new(add_type), Rc::new(add_body))
+                .unwrap();
+
+            Nat {
+                env,
+                nat_id,
+                zero_id,
+                succ_id,
+                add_id,
+            }
+        }
+
+        fn numeral(&self, n: usize) -> Term {
+            (0..n).fold(constant(self.zero_id), |acc, _| {
+                apply(constant(self.succ_id), vec![acc])
+            })
+        }
+
+        fn add(&self, n: Term, m: Term) -> Term {
+            apply(constant(self.add_id), vec![n, m])
+        }
+    }
+
+    /// Checks the evaluator against the reduction stepper
+    fn assert_same_normal_form(env: &GlobalEnvironment, term: &Term) -> Term {
+        let expected = normalize_with_env_by_steps(term, env);
+        let actual = normalize(term, env);
+        assert_eq!(actual, expected, "normal forms differ for {term:?}");
+        actual
+    }
+
+    #[test]
+    fn test_add_on_numerals() {
+        let nat = Nat::new();
+        let term = nat.add(nat.numeral(3), nat.numeral(4));
+        let normal = assert_same_normal_form(&nat.env, &term);
+        assert_eq!(normal, nat.numeral(7));
+    }
+
+    #[test]
+    fn test_nested_add() {
+        let nat = Nat::new();
+        let term = nat.add(
+            nat.add(nat.numeral(2), nat.numeral(1)),
+            nat.add(nat.numeral(0), nat.numeral(2)),
+        );
+        let normal = assert_same_normal_form(&nat.env, &term);
+        assert_eq!(normal, nat.numeral(5));
+    }
+
+    #[test]
+    fn test_normalizes_under_binders() {
+        // λm. add (S O) m  →  λm. S m
+        let nat = Nat::new();
+        let term = lambda(constant(nat.nat_id), nat.add(nat.numeral(1), bound(0)));
+        let normal = assert_same_normal_form(&nat.env, &term);
+        assert_eq!(
+            normal,
+            lambda(
+                constant(nat.nat_id),
+                apply(constant(nat.succ_id), vec![bound(0)])
+            )
+        );
+    }
+
+    #[test]
+    fn test_stuck_match_is_read_back() {
+        // λn. match n { O => O, S p => add p (S O) } with p read back under the branch
+        let nat = Nat::new();
+        let term = lambda(
+            constant(nat.nat_id),
+            Term::Match(TermMatch {
+                scrutinee: Rc::new(bound(0)),
+                return_type: Rc::new(constant(nat.nat_id)),
+                branches: vec![
+                    TermMatchBranch {
+                        constructor_id: nat.zero_id,
+                        bound_vars: vec![],
+                        body: Rc::new(nat.numeral(0)),
+                    },
+                    TermMatchBranch {
+                        constructor_id: nat.succ_id,
+                        bound_vars: vec![Id::new()],
+                        body: Rc::new(nat.add(nat.numeral(1), bound(0))),
+                    },
+                ],
+            }),
+        );
+        assert_same_normal_form(&nat.env, &term);
+    }
+
+    #[test]
+    fn test_let_in_and_beta() {
+        // let x := S O in (λy. add y x) x  →  S (S O)
+        let nat = Nat::new();
+        let term = Term::LetIn(TermLetIn {
+            var: Id::new(),
+            term: Rc::new(nat.numeral(1)),
+            ty: Rc::new(constant(nat.nat_id)),
+            body: Rc::new(apply(
+                lambda(constant(nat.nat_id), nat.add(bound(0), bound(1))),
+                vec![bound(0)],
+            )),
+        });
+        let normal = assert_same_normal_form(&nat.env, &term);
+        assert_eq!(normal, nat.numeral(2));
+    }
+
+    #[test]
+    fn test_open_terms() {
+        // Free variables and loose bound variables are kept as they are
+        let nat = Nat::new();
+        let x = Term::Variable(TermVariable { id: Id::new() });
+        let set = Term::Sort(TermSort { sort: Sort::Set });
+
+        let identity = lambda(set.clone(), bound(0));
+        assert_same_normal_form(&nat.env, &apply(identity, vec![bound(3)]));
+
+        let term = lambda(
+            set,
+            apply(bound(2), vec![bound(0), nat.add(nat.numeral(1), x)]),
+        );
+        assert_same_normal_form(&nat.env, &term);
+    }
+
+    #[test]
+    fn test_nbe_against_stepper() {
+        let nat = Nat::new();
+        let term = nat.add(nat.numeral(40), nat.numeral(40));
+        assert_eq!(
+            normalize(&term, &nat.env),
+            normalize_with_env_by_steps(&term, &nat.env)
+        );
+    }
+}
@@ -0,0 +1,352 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+};
+
+use crate::{
+    id::Id,
+    term::{Sort, Term},
+};
+
+/// Prints terms in Felis syntax, using source names for constants, variables
+/// and binders. Binders without a name get the first of `x`, `x0`, `x1`, ...
+/// that is not the name of an enclosing binder or of a constant or variable of
+/// the term. Other ids without a name are printed as `Id(n)`.
+pub struct Printer<'a> {
+    names: &'a HashMap<Id, String>,
+    implicit_binders: Option<&'a HashSet<Id>>,
+    /// The names of the constants and variables of the term being printed
+    free_names: RefCell<HashSet<String>>,
+}
+
+impl<'a> Printer<'a> {
+    pub fn new(names: &'a HashMap<Id, String>) -> Self {
+        Self {
+            names,
+            implicit_binders: None,
+            free_names: RefCell::new(HashSet::new()),
+        }
+    }
+
+    /// Prints the products binding `implicit_binders` as `{x : A} -> B`
+    pub fn with_implicit_binders(mut self, implicit_binders: &'a HashSet<Id>) -> Self {
+        self.implicit_binders = Some(implicit_binders);
+        self
+    }
+
+    pub fn print(&self, term: &Term) -> String {
+        let mut free_names = HashSet::new();
+        self.collect_free_names(term, &mut free_names);
+        *self.free_names.borrow_mut() = free_names;
+        let mut binders = Vec::new();
+        let mut out = String::new();
+        self.term(term, &mut binders, &mut out);
+        out
+    }
+
+    fn name(&self, id: Id) -> String {
+        self.names
+            .get(&id)
+            .cloned()
+            .unwrap_or_else(|| format!("{id:?}"))
+    }
+
+    /// Binder names on the stack, innermost last. Shadowed names are primed so
+    /// that the printed term still refers to the right binder.
+    fn binder_name(&self, var: Id, binders: &[String]) -> String {
+        let Some(name) = self.names.get(&var) else {
+            let free_names = self.free_names.borrow();
+            return std::iter::once("x".to_string())
+                .chain((0..).map(|i| format!("x{i}")))
+                .find(|name| !binders.contains(name) && !free_names.contains(name))
+                .unwrap();
+        };
+        let mut name = name.clone();
+        while binders.contains(&name) {
+            name.push('\'');
+        }
+        name
+    }
+
+    fn collect_free_names(&self, term: &Term, free_names: &mut HashSet<String>) {
+        match term {
+            Term::Variable(var) => free_names.extend(self.names.get(&var.id).cloned()),
+            Term::Constant(const_) => free_names.extend(self.names.get(&const_.id).cloned()),
+            Term::Sort(_) | Term::Bound(_) | Term::Literal(_) => {}
+            Term::Product(product) => {
+                self.collect_free_names(&product.source, free_names);
+                self.collect_free_names(&product.target, free_names);
+            }
+            Term::Lambda(lambda) => {
+                self.collect_free_names(&lambda.source_ty, free_names);
+                self.collect_free_names(&lambda.target, free_names);
+            }
+            Term::Application(app) => {
+                self.collect_free_names(&app.f, free_names);
+                for arg in &app.args {
+                    self.collect_free_names(arg, free_names);
+                }
+            }
+            Term::LetIn(let_in) => {
+                self.collect_free_names(&let_in.term, free_names);
+                self.collect_free_names(&let_in.ty, free_names);
+                self.collect_free_names(&let_in.body, free_names);
+            }
+            Term::Match(case) => {
+                self.collect_free_names(&case.scrutinee, free_names);
+                self.collect_free_names(&case.return_type, free_names);
+                for branch in &case.branches {
+                    free_names.extend(self.names.get(&branch.constructor_id).cloned());
+                    self.collect_free_names(&branch.body, free_names);
+                }
+            }
+        }
+    }
+
+    fn term(&self, term: &Term, binders: &mut Vec<String>, out: &mut String) {
+        match term {
+            Term::Product(product) => {
+                let implicit = self
+                    .implicit_binders
+                    .is_some_and(|implicit_binders| implicit_binders.contains(&product.var));
+                if implicit || mentions_innermost(&product.target, 0) {
+                    let name = self.binder_name(product.var, binders);
+                    let (open, close) = if implicit { ("{", "}") } else { ("(", ")") };
+                    out.push_str(&format!("{open}{name} : "));
+                    self.term(&product.source, binders, out);
+                    out.push_str(&format!("{close} -> "));
+                    binders.push(name);
+                    self.term(&product.target, binders, out);
+                    binders.pop();
+                } else {
+                    self.application(&product.source, binders, out);
+                    out.push_str(" -> ");
+                    // The binder is unused but still shifts the indices
+                    binders.push("_".to_string());
+                    self.term(&product.target, binders, out);
+                    binders.pop();
+                }
+            }
+            Term::Lambda(lambda) => {
+                let name = self.binder_name(lambda.var, binders);
+                out.push_str(&format!("fun ({name} : "));
+                self.term(&lambda.source_ty, binders, out);
+                out.push_str(") => ");
+                binders.push(name);
+                self.term(&lambda.target, binders, out);
+                binders.pop();
+            }
+            Term::LetIn(let_in) => {
+                let name = self.binder_name(let_in.var, binders);
+                out.push_str(&format!("#let {name} : "));
+                self.term(&let_in.ty, binders, out);
+                out.push_str(" = ");
+                self.term(&let_in.term, binders, out);
+                out.push_str("; ");
+                binders.push(name);
+                self.term(&let_in.body, binders, out);
+                binders.pop();
+            }
+            Term::Match(case) => {
+                out.push_str("#match ");
+                self.atom(&case.scrutinee, binders, out);
+                out.push_str(" {");
+                for (i, branch) in case.branches.iter().enumerate() {
+                    out.push_str(if i == 0 { " " } else { ", " });
+                    out.push_str(&self.name(branch.constructor_id));
+                    let scope = binders.len();
+                    for var in &branch.bound_vars {
+                        let name = self.binder_name(*var, binders);
+                        out.push(' ');
+                        out.push_str(&name);
+                        binders.push(name);
+                    }
+                    out.push_str(" => ");
+                    self.term(&branch.body, binders, out);
+                    binders.truncate(scope);
+                }
+                out.push_str(" }");
+            }
+            _ => self.application(term, binders, out),
+        }
+    }
+
+    fn application(&self, term: &Term, binders: &mut Vec<String>, out: &mut String) {
+        match term {
+            Term::Application(app) => {
+                self.atom(&app.f, binders, out);
+                for arg in &app.args {
+                    out.push(' ');
+                    self.atom(arg, binders, out);
+                }
+            }
+            _ => self.atom(term, binders, out),
+        }
+    }
+
+    fn atom(&self, term: &Term, binders: &mut Vec<String>, out: &mut String) {
+        match term {
+            Term::Sort(sort) => out.push_str(&match sort.sort {
+                Sort::Set => "Set".to_string(),
+                Sort::Prop => "Prop".to_string(),
+                Sort::Type(0) => "Type".to_string(),
+                Sort::Type(level) => format!("Type{level}"),
+            }),
+            Term::Variable(var) => out.push_str(&self.name(var.id)),
+            Term::Constant(const_) => out.push_str(&self.name(const_.id)),
+            Term::Literal(literal) => out.push_str(&literal.value.to_string()),
+            Term::Bound(bound) => match binders.len().checked_sub(bound.index + 1) {
+                Some(level) => out.push_str(&binders[level]),
+                None => out.push_str(&format!("#{}", bound.index)),
+            },
+            _ => {
+                out.push('(');
+                self.term(term, binders, out);
+                out.push(')');
+            }
+        }
+    }
+}
+
+/// Checks whether `term` refers to the binder `depth` levels above it
+pub(crate) fn mentions_innermost(term: &Term, depth: usize) -> bool {
+    match term {
+        Term::Bound(bound) => bound.index == depth,
+        Term::Sort(_) | Term::Variable(_) | Term::Constant(_) | Term::Literal(_) => false,
+        Term::Product(product) => {
+            mentions_innermost(&product.source, depth)
+                || mentions_innermost(&product.target, depth + 1)
+        }
+        Term::Lambda(lambda) => {
+            mentions_innermost(&lambda.source_ty, depth)
+                || mentions_innermost(&lambda.target, depth + 1)
+        }
+        Term::Application(app) => {
+            mentions_innermost(&app.f, depth)
+                || app.args.iter().any(|arg| mentions_innermost(arg, depth))
+        }
+        Term::LetIn(let_in) => {
+            mentions_innermost(&let_in.term, depth)
+                || mentions_innermost(&let_in.ty, depth)
+                || mentions_innermost(&let_in.body, depth + 1)
+        }
+        Term::Match(case) => {
+            mentions_innermost(&case.scrutinee, depth)
+                || mentions_innermost(&case.return_type, depth)
+                || case
+                    .branches
+                    .iter()
+                    .any(|branch| mentions_innermost(&branch.body, depth + branch.bound_vars.len()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{HashMap, HashSet},
+        rc::Rc,
+    };
+
+    use crate::{
+        id::Id,
+        term::{
+            Sort, Term, TermApplication, TermBound, TermConstant, TermLambda, TermProduct, TermSort,
+        },
+    };
+
+    use super::Printer;
+
+    #[test]
+    fn test_print_dependent_and_plain_arrows() {
+        let a = Id::new();
+        let x = Id::new();
+        let eq = Id::new();
+        let names = HashMap::from([
+            (a, "A".to_string()),
+            (x, "x".to_string()),
+            (eq, "eq".to_string()),
+        ]);
+
+        // (A : Set) -> A -> eq A #0 #0
+        let term = Term::Product(TermProduct {
+            var: a,
+            source: Rc::new(Term::Sort(TermSort { sort: Sort::Set })),
+            target: Rc::new(Term::Product(TermProduct {
+                var: x,
+                source: Rc::new(Term::Bound(TermBound { index: 0 })),
+                target: Rc::new(Term::Application(TermApplication {
+                    f: Rc::new(Term::Constant(TermConstant { id: eq })),
+                    args: vec![
+                        Term::Bound(TermBound { index: 1 }),
+                        Term::Bound(TermBound { index: 0 }),
+                        Term::Bound(TermBound { index: 0 }),
+                    ],
+                })),
+            })),
+        });
+        assert_eq!(
+            Printer::new(&names).print(&term),
+            "(A : Set) -> (x : A) -> eq A x x"
+        );
+
+        let implicit_binders = HashSet::from([a]);
+        assert_eq!(
+            Printer::new(&names)
+                .with_implicit_binders(&implicit_binders)
+                .print(&term),
+            "{A : Set} -> (x : A) -> eq A x x"
+        );
+    }
+
+    #[test]
+    fn test_print_primes_shadowed_binders() {
+        let x = Id::new();
+        let y = Id::new();
+        let names = HashMap::from([(x, "x".to_string()), (y, "x".to_string())]);
+
+        let set = Rc::new(Term::Sort(TermSort { sort: Sort::Set }));
+        let term = Term::Lambda(TermLambda {
+            var: x,
+            source_ty: set.clone(),
+            target: Rc::new(Term::Lambda(TermLambda {
+                var: y,
+                source_ty: set,
+                target: Rc::new(Term::Bound(TermBound { index: 1 })),
+            })),
+        });
+        assert_eq!(
+            Printer::new(&names).print(&term),
+            "fun (x : Set) => fun (x' : Set) => x"
+        );
+    }
+
+    #[test]
+    fn test_print_fresh_names_for_unnamed_binders() {
+        let (x, y, z) = (Id::new(), Id::new(), Id::new());
+        let f = Id::new();
+        let names = HashMap::from([(y, "x".to_string()), (f, "x0".to_string())]);
+
+        // fun (_ : Set) => fun (x : Set) => fun (_ : Set) => x0 #2 #1 #0
+        let set = Rc::new(Term::Sort(TermSort { sort: Sort::Set }));
+        let lambda = |var, target| {
+            Term::Lambda(TermLambda {
+                var,
+                source_ty: set.clone(),
+                target: Rc::new(target),
+            })
+        };
+        let body = Term::Application(TermApplication {
+            f: Rc::new(Term::Constant(TermConstant { id: f })),
+            args: (0..3)
+                .rev()
+                .map(|index| Term::Bound(TermBound { index }))
+                .collect(),
+        });
+        let term = lambda(x, lambda(y, lambda(z, body)));
+        assert_eq!(
+            Printer::new(&names).print(&term),
+            "fun (x : Set) => fun (x' : Set) => fun (x1 : Set) => x0 x x' x1"
+        );
+    }
+}
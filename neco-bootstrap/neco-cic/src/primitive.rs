@@ -0,0 +1,231 @@
+use std::{collections::HashMap, rc::Rc};
+
+use crate::{
+    global_environment::GlobalEnvironment,
+    id::{Id, IdGenerator},
+    term::{Sort, Term, TermConstant, TermLiteral, TermProduct, TermSort},
+};
+
+/// Arithmetic on the primitive 64-bit unsigned integers, computed on literals
+/// by the kernel as the x86-64 backend computes it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Operation {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    /// 1 if the integers are equal, 0 otherwise
+    Eq,
+}
+
+impl Operation {
+    /// The operation declared by `#use_builtin` under `builtin`
+    pub fn from_builtin(builtin: &str) -> Option<Self> {
+        match builtin {
+            "u64_add" => Some(Operation::Add),
+            "u64_sub" => Some(Operation::Sub),
+            "u64_mul" => Some(Operation::Mul),
+            "u64_div" => Some(Operation::Div),
+            "u64_mod" => Some(Operation::Mod),
+            "u64_eq" => Some(Operation::Eq),
+            _ => None,
+        }
+    }
+
+    pub fn builtin(self) -> &'static str {
+        match self {
+            Operation::Add => "u64_add",
+            Operation::Sub => "u64_sub",
+            Operation::Mul => "u64_mul",
+            Operation::Div => "u64_div",
+            Operation::Mod => "u64_mod",
+            Operation::Eq => "u64_eq",
+        }
+    }
+
+    /// Computes the operation, wrapping around on overflow. Division by zero
+    /// is 0 and leaves the dividend as remainder, so that every operation is
+    /// total and `(a / b) * b + a % b = a`. The backends compute the same
+    /// instead of trapping on x86-64 or giving an unspecified value in PTX.
+    pub fn apply(self, a: u64, b: u64) -> u64 {
+        match self {
+            Operation::Add => a.wrapping_add(b),
+            Operation::Sub => a.wrapping_sub(b),
+            Operation::Mul => a.wrapping_mul(b),
+            Operation::Div => a.checked_div(b).unwrap_or(0),
+            Operation::Mod => a.checked_rem(b).unwrap_or(a),
+            Operation::Eq => u64::from(a == b),
+        }
+    }
+}
+
+/// The primitive type and operations declared in a global environment. They
+/// are axioms for the rest of the kernel, except that operations applied to
+/// two literals reduce to a literal.
+#[derive(Debug, Clone, Default)]
+pub struct Primitives {
+    /// The type of the literals
+    pub u64: Option<Id>,
+    pub operations: HashMap<Id, Operation>,
+}
+
+impl GlobalEnvironment {
+    /// Adds the axiom `u64 : Set`, the type of the literals
+    pub fn add_u64(&mut self, id: Id) -> Result<(), String> {
+        if self.primitives.u64.is_some() {
+            return Err("u64 is already declared".to_string());
+        }
+        self.add_axiom(id, Rc::new(Term::Sort(TermSort { sort: Sort::Set })))?;
+        self.primitives.u64 = Some(id);
+        Ok(())
+    }
+
+    /// Adds the axiom `id : u64 -> u64 -> u64` computing `operation`
+    pub fn add_u64_operation(
+        &mut self,
+        id: Id,
+        operation: Operation,
+        id_gen: &mut IdGenerator,
+    ) -> Result<(), String> {
+        let u64 = self
+            .primitives
+            .u64
+            .ok_or_else(|| format!("{} needs u64 to be declared first", operation.builtin()))?;
+        let u64 = Rc::new(Term::Constant(TermConstant { id: u64 }));
+        let ty = Term::Product(TermProduct {
+            var: id_gen.generate_id(),
+            source: u64.clone(),
+            target: Rc::new(Term::Product(TermProduct {
+                var: id_gen.generate_id(),
+                source: u64.clone(),
+                target: u64,
+            })),
+        });
+        self.add_axiom(id, Rc::new(ty))?;
+        self.primitives.operations.insert(id, operation);
+        Ok(())
+    }
+
+    /// Marks the axiom `id` of an environment file as the primitive `builtin`,
+    /// returning whether `builtin` is a primitive. The axiom must have the
+    /// type `add_u64` or `add_u64_operation` would have given it.
+    pub fn register_primitive(&mut self, builtin: &str, id: Id) -> Result<bool, String> {
+        let operation = Operation::from_builtin(builtin);
+        if builtin != "u64" && operation.is_none() {
+            return Ok(false);
+        }
+        let constant = self
+            .get_constant(id)
+            .filter(|constant| constant.body.is_none())
+            .ok_or_else(|| format!("{builtin} is not an axiom"))?;
+        let u64 = |term: &Term| matches!(term, Term::Constant(constant) if Some(constant.id) == self.primitives.u64);
+        let well_typed = match (operation, constant.ty.as_ref()) {
+            (None, Term::Sort(sort)) => sort.sort == Sort::Set,
+            (Some(_), Term::Product(first)) => {
+                u64(&first.source)
+                    && matches!(first.target.as_ref(), Term::Product(second)
+                        if u64(&second.source) && u64(&second.target))
+            }
+            _ => false,
+        };
+        if !well_typed {
+            return Err(format!("{builtin} does not have the type of the primitive"));
+        }
+        match operation {
+            Some(operation) => {
+                self.primitives.operations.insert(id, operation);
+            }
+            None => self.primitives.u64 = Some(id),
+        }
+        Ok(true)
+    }
+
+    /// The operation computed by the constant `id`, if any
+    pub fn primitive_operation(&self, id: Id) -> Option<Operation> {
+        self.primitives.operations.get(&id).copied()
+    }
+}
+
+/// Computes an operation applied to two literals
+pub(crate) fn reduce(env: &GlobalEnvironment, f: &Term, args: &[Term]) -> Option<Term> {
+    let Term::Constant(constant) = f else {
+        return None;
+    };
+    let operation = env.primitive_operation(constant.id)?;
+    match args {
+        [Term::Literal(a), Term::Literal(b)] => Some(Term::Literal(TermLiteral {
+            value: operation.apply(a.value, b.value),
+        })),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        conversion::is_convertible,
+        global_environment::GlobalEnvironment,
+        id::{Id, IdGenerator},
+        local_context::LocalContext,
+        reduction::whnf_with_env,
+        term::{Term, TermApplication, TermConstant, TermLiteral},
+        typechecker::infer_type,
+    };
+
+    use super::Operation;
+
+    fn literal(value: u64) -> Term {
+        Term::Literal(TermLiteral { value })
+    }
+
+    fn apply(f: Id, args: Vec<Term>) -> Term {
+        Term::Application(TermApplication {
+            f: std::rc::Rc::new(Term::Constant(TermConstant { id: f })),
+            args,
+        })
+    }
+
+    #[test]
+    fn test_operations_wrap_like_the_backend() {
+        assert_eq!(Operation::Add.apply(u64::MAX, 2), 1);
+        assert_eq!(Operation::Sub.apply(0, 1), u64::MAX);
+        assert_eq!(Operation::Mul.apply(1 << 63, 2), 0);
+        assert_eq!(Operation::Div.apply(84, 2), 42);
+        assert_eq!(Operation::Div.apply(1, 0), 0);
+        assert_eq!(Operation::Mod.apply(142, 100), 42);
+        assert_eq!(Operation::Mod.apply(1, 0), 1);
+        assert_eq!(Operation::Eq.apply(3, 3), 1);
+        assert_eq!(Operation::Eq.apply(3, 4), 0);
+    }
+
+    #[test]
+    fn test_operations_reduce_on_literals() {
+        let mut env = GlobalEnvironment::new();
+        let mut id_gen = IdGenerator::new();
+        let [u64, add, mul] = [(); 3].map(|_| Id::new());
+        assert!(
+            env.add_u64_operation(add, Operation::Add, &mut id_gen)
+                .is_err()
+        );
+        env.add_u64(u64).unwrap();
+        env.add_u64_operation(add, Operation::Add, &mut id_gen)
+            .unwrap();
+        env.add_u64_operation(mul, Operation::Mul, &mut id_gen)
+            .unwrap();
+
+        // 2 * 20 + 2
+        let term = apply(
+            add,
+            vec![apply(mul, vec![literal(2), literal(20)]), literal(2)],
+        );
+        let ctx = LocalContext::new();
+        assert_eq!(
+            infer_type(&ctx, &env, &term).unwrap().as_ref(),
+            &Term::Constant(TermConstant { id: u64 })
+        );
+        assert_eq!(whnf_with_env(&term, &env), literal(42));
+        assert!(is_convertible(&ctx, &env, &term, &literal(42)));
+        assert!(!is_convertible(&ctx, &env, &term, &literal(41)));
+    }
+}
@@ -2,8 +2,12 @@ use std::rc::Rc;
 
 use crate::{
     global_environment::GlobalEnvironment,
-    substitution::{Substitution, substitute},
-    term::{Term, TermApplication, TermConstant, TermLambda, TermLetIn, TermMatch, TermProduct},
+    nbe, primitive,
+    substitution::{instantiate, instantiate_many, is_closed_under},
+    term::{
+        Sort, Term, TermApplication, TermConstant, TermLambda, TermLetIn, TermMatch, TermProduct,
+    },
+    typechecker::arity_sort,
 };
 
 /// Performs one step of reduction on a term.
@@ -12,7 +16,9 @@ pub fn reduce_step(term: &Term) -> Option<Term> {
     match term {
         Term::Sort(_) => None,
         Term::Variable(_) => None,
+        Term::Bound(_) => None,
         Term::Constant(_) => None,
+        Term::Literal(_) => None,
         Term::Product(product) => reduce_product(product),
         Term::Lambda(lambda) => reduce_lambda(lambda),
         Term::Application(app) => reduce_application(app),
@@ -36,7 +42,9 @@ pub fn reduce_step_with_env(term: &Term, env: &GlobalEnvironment) -> Option<Term
     match term {
         Term::Sort(_) => None,
         Term::Variable(_) => None,
+        Term::Bound(_) => None,
         Term::Constant(const_) => reduce_constant(const_, env),
+        Term::Literal(_) => None,
         Term::Product(product) => reduce_product_with_env(product, env),
         Term::Lambda(lambda) => reduce_lambda_with_env(lambda, env),
         Term::Application(app) => reduce_application_with_env(app, env),
@@ -46,7 +54,15 @@ pub fn reduce_step_with_env(term: &Term, env: &GlobalEnvironment) -> Option<Term
 }
 
 /// Reduces a term to its normal form with access to global environment.
+/// See `nbe` for the evaluator.
 pub fn normalize_with_env(term: &Term, env: &GlobalEnvironment) -> Term {
+    nbe::normalize(term, env)
+}
+
+/// Reduces a term to its normal form by repeated `reduce_step_with_env`.
+/// This is much slower than `normalize_with_env` and is kept as a reference
+/// implementation to test the evaluator against.
+pub fn normalize_with_env_by_steps(term: &Term, env: &GlobalEnvironment) -> Term {
     let mut current = term.clone();
     while let Some(reduced) = reduce_step_with_env(&current, env) {
         current = reduced;
@@ -60,7 +76,7 @@ pub fn whnf_with_env(term: &Term, env: &GlobalEnvironment) -> Term {
         Term::Constant(const_) => {
             // δ-reduction: unfold constant if it has a body
             if let Some(const_def) = env.get_constant(const_.id)
-                && let Some(body) = &const_def.body
+                && let Some(body) = const_def.unfoldable_body()
             {
                 return whnf_with_env(body, env);
             }
@@ -73,9 +89,7 @@ pub fn whnf_with_env(term: &Term, env: &GlobalEnvironment) -> Term {
             // If it's a lambda, perform ONE β-reduction step
             if let Term::Lambda(lambda) = &f_whnf {
                 if !app.args.is_empty() {
-                    let mut subst = Substitution::new();
-                    subst.add(lambda.var, Rc::new(app.args[0].clone()));
-                    let reduced_body = substitute(&lambda.target, &subst);
+                    let reduced_body = instantiate(&lambda.target, &app.args[0]);
 
                     if app.args.len() > 1 {
                         // Create new application with remaining arguments
@@ -87,8 +101,7 @@ pub fn whnf_with_env(term: &Term, env: &GlobalEnvironment) -> Term {
                             env,
                         )
                     } else {
-                        // Return the substituted body as-is (don't reduce further)
-                        reduced_body
+                        whnf_with_env(&reduced_body, env)
                     }
                 } else {
                     Term::Application(TermApplication {
@@ -97,7 +110,16 @@ pub fn whnf_with_env(term: &Term, env: &GlobalEnvironment) -> Term {
                     })
                 }
             } else {
-                // Function is not a lambda after WHNF
+                // Function is not a lambda after WHNF, it may be a primitive
+                // operation on literals
+                if let Term::Constant(const_) = &f_whnf
+                    && env.primitive_operation(const_.id).is_some()
+                {
+                    let args: Vec<_> = app.args.iter().map(|arg| whnf_with_env(arg, env)).collect();
+                    if let Some(literal) = primitive::reduce(env, &f_whnf, &args) {
+                        return literal;
+                    }
+                }
                 Term::Application(TermApplication {
                     f: Rc::new(f_whnf),
                     args: app.args.clone(),
@@ -106,13 +128,11 @@ pub fn whnf_with_env(term: &Term, env: &GlobalEnvironment) -> Term {
         }
         Term::LetIn(let_in) => {
             // ζ-reduction: immediately substitute
-            let mut subst = Substitution::new();
-            subst.add(let_in.var, let_in.term.clone());
-            substitute(&let_in.body, &subst)
+            instantiate(&let_in.body, &let_in.term)
         }
         Term::Match(case) => {
             // Try to reduce the case expression
-            if let Some(reduced_case) = reduce_case_with_env(case, env) {
+            if let Some(reduced_case) = reduce_iota_with_env(case, env) {
                 whnf_with_env(&reduced_case, env)
             } else {
                 term.clone()
@@ -173,9 +193,7 @@ fn reduce_application(app: &TermApplication) -> Option<Term> {
         && !app.args.is_empty()
     {
         // Apply the first argument
-        let mut subst = Substitution::new();
-        subst.add(lambda.var, Rc::new(app.args[0].clone()));
-        let reduced_body = substitute(&lambda.target, &subst);
+        let reduced_body = instantiate(&lambda.target, &app.args[0]);
 
         // If there are more arguments, create a new application
         if app.args.len() > 1 {
@@ -213,9 +231,7 @@ fn reduce_application(app: &TermApplication) -> Option<Term> {
 
 fn reduce_let_in(let_in: &TermLetIn) -> Option<Term> {
     // ζ-reduction: let x = t : T in u → u[x := t]
-    let mut subst = Substitution::new();
-    subst.add(let_in.var, let_in.term.clone());
-    Some(substitute(&let_in.body, &subst))
+    Some(instantiate(&let_in.body, &let_in.term))
 }
 
 /// Performs weak head normal form reduction (WHNF).
@@ -231,9 +247,7 @@ pub fn whnf(term: &Term) -> Term {
             // If it's a lambda, perform ONE β-reduction step
             if let Term::Lambda(lambda) = &f_whnf {
                 if !app.args.is_empty() {
-                    let mut subst = Substitution::new();
-                    subst.add(lambda.var, Rc::new(app.args[0].clone()));
-                    let reduced_body = substitute(&lambda.target, &subst);
+                    let reduced_body = instantiate(&lambda.target, &app.args[0]);
 
                     if app.args.len() > 1 {
                         // Create new application with remaining arguments
@@ -261,13 +275,11 @@ pub fn whnf(term: &Term) -> Term {
         }
         Term::LetIn(let_in) => {
             // ζ-reduction: immediately substitute
-            let mut subst = Substitution::new();
-            subst.add(let_in.var, let_in.term.clone());
-            substitute(&let_in.body, &subst)
+            instantiate(&let_in.body, &let_in.term)
         }
         Term::Match(case) => {
             // Try to reduce the case expression
-            if let Some(reduced_case) = reduce_case(case) {
+            if let Some(reduced_case) = reduce_iota(case) {
                 whnf(&reduced_case)
             } else {
                 term.clone()
@@ -281,47 +293,8 @@ pub fn whnf(term: &Term) -> Term {
 /// Reduces a case expression (ι-reduction)
 /// match (C a₁ ... aₙ) with | C x₁ ... xₙ => t | ... => t[x₁ := a₁, ..., xₙ := aₙ]
 fn reduce_case(case: &TermMatch) -> Option<Term> {
-    // First reduce the scrutinee to WHNF
-    let scrutinee_whnf = whnf(&case.scrutinee);
-
-    // Check if the scrutinee is a constructor application
-    // Constructor applications are represented as Application(Constant(constructor_id), args)
-    match &scrutinee_whnf {
-        Term::Constant(const_) => {
-            // Zero-argument constructor
-            for branch in &case.branches {
-                if branch.constructor_id == const_.id {
-                    if !branch.bound_vars.is_empty() {
-                        // This should not happen if type checking is correct
-                        return None;
-                    }
-                    return Some(branch.body.as_ref().clone());
-                }
-            }
-        }
-        Term::Application(app) => {
-            if let Term::Constant(const_) = app.f.as_ref() {
-                // Constructor with arguments
-                for branch in &case.branches {
-                    if branch.constructor_id == const_.id {
-                        // Check that the number of bound variables matches the constructor arguments
-                        if branch.bound_vars.len() != app.args.len() {
-                            // This should not happen if type checking is correct
-                            return None;
-                        }
-
-                        // Apply substitutions: substitute constructor arguments for bound variables
-                        let mut subst = Substitution::new();
-                        for (bound_var, arg) in branch.bound_vars.iter().zip(&app.args) {
-                            subst.add(*bound_var, Rc::new(arg.clone()));
-                        }
-
-                        return Some(substitute(&branch.body, &subst));
-                    }
-                }
-            }
-        }
-        _ => {}
+    if let Some(reduced) = reduce_iota(case) {
+        return Some(reduced);
     }
 
     // If the scrutinee is not a constructor, try to reduce it
@@ -365,9 +338,8 @@ fn reduce_case(case: &TermMatch) -> Option<Term> {
 /// δ-reduction: unfold constant definition
 fn reduce_constant(const_: &TermConstant, env: &GlobalEnvironment) -> Option<Term> {
     if let Some(const_def) = env.get_constant(const_.id)
-        && let Some(body) = &const_def.body
+        && let Some(body) = const_def.unfoldable_body()
     {
-        eprintln!("δ-reducing constant {:?} to {:?}", const_.id, body);
         return Some(body.as_ref().clone());
     }
     None
@@ -423,9 +395,7 @@ fn reduce_application_with_env(app: &TermApplication, env: &GlobalEnvironment) -
         && !app.args.is_empty()
     {
         // Apply the first argument
-        let mut subst = Substitution::new();
-        subst.add(lambda.var, Rc::new(app.args[0].clone()));
-        let reduced_body = substitute(&lambda.target, &subst);
+        let reduced_body = instantiate(&lambda.target, &app.args[0]);
 
         // If there are more arguments, create a new application
         if app.args.len() > 1 {
@@ -458,58 +428,18 @@ fn reduce_application_with_env(app: &TermApplication, env: &GlobalEnvironment) -
         }
     }
 
-    None
+    // Primitive operations compute once their arguments are literals
+    primitive::reduce(env, &app.f, &app.args)
 }
 
 fn reduce_let_in_with_env(let_in: &TermLetIn, _env: &GlobalEnvironment) -> Option<Term> {
     // ζ-reduction: let x = t : T in u → u[x := t]
-    let mut subst = Substitution::new();
-    subst.add(let_in.var, let_in.term.clone());
-    Some(substitute(&let_in.body, &subst))
+    Some(instantiate(&let_in.body, &let_in.term))
 }
 
 fn reduce_case_with_env(case: &TermMatch, env: &GlobalEnvironment) -> Option<Term> {
-    // First reduce the scrutinee to WHNF
-    let scrutinee_whnf = whnf_with_env(&case.scrutinee, env);
-
-    // Check if the scrutinee is a constructor application
-    // Constructor applications are represented as Application(Constant(constructor_id), args)
-    match &scrutinee_whnf {
-        Term::Constant(const_) => {
-            // Zero-argument constructor
-            for branch in &case.branches {
-                if branch.constructor_id == const_.id {
-                    if !branch.bound_vars.is_empty() {
-                        // This should not happen if type checking is correct
-                        return None;
-                    }
-                    return Some(branch.body.as_ref().clone());
-                }
-            }
-        }
-        Term::Application(app) => {
-            if let Term::Constant(const_) = app.f.as_ref() {
-                // Constructor with arguments
-                for branch in &case.branches {
-                    if branch.constructor_id == const_.id {
-                        // Check that the number of bound variables matches the constructor arguments
-                        if branch.bound_vars.len() != app.args.len() {
-                            // This should not happen if type checking is correct
-                            return None;
-                        }
-
-                        // Apply substitutions: substitute constructor arguments for bound variables
-                        let mut subst = Substitution::new();
-                        for (bound_var, arg) in branch.bound_vars.iter().zip(&app.args) {
-                            subst.add(*bound_var, Rc::new(arg.clone()));
-                        }
-
-                        return Some(substitute(&branch.body, &subst));
-                    }
-                }
-            }
-        }
-        _ => {}
+    if let Some(reduced) = reduce_iota_with_env(case, env) {
+        return Some(reduced);
     }
 
     // If the scrutinee is not a constructor, try to reduce it
@@ -550,13 +480,102 @@ fn reduce_case_with_env(case: &TermMatch, env: &GlobalEnvironment) -> Option<Ter
     None
 }
 
+/// Reduces a case expression whose scrutinee reduces to a constructor,
+/// without reducing under it otherwise
+fn reduce_iota(case: &TermMatch) -> Option<Term> {
+    // First reduce the scrutinee to WHNF
+    select_branch(case, &whnf(&case.scrutinee))
+}
+
+/// Reduces a case expression whose scrutinee reduces to a constructor,
+/// without reducing under it otherwise
+fn reduce_iota_with_env(case: &TermMatch, env: &GlobalEnvironment) -> Option<Term> {
+    let mut scrutinee = whnf_with_env(&case.scrutinee, env);
+    loop {
+        if let Some(reduced) = select_branch(case, &scrutinee) {
+            return Some(reduced);
+        }
+        scrutinee = unfold_closed_proof(case, &scrutinee, env)?;
+    }
+}
+
+/// The branch of `case` for a scrutinee in WHNF, with the constructor
+/// arguments substituted, if the scrutinee is a constructor application
+fn select_branch(case: &TermMatch, scrutinee: &Term) -> Option<Term> {
+    // Constructor applications are represented as Application(Constant(constructor_id), args)
+    let (constructor_id, args) = match scrutinee {
+        Term::Constant(const_) => (const_.id, &[][..]),
+        Term::Application(app) => match app.f.as_ref() {
+            Term::Constant(const_) => (const_.id, app.args.as_slice()),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    let branch = case
+        .branches
+        .iter()
+        .find(|branch| branch.constructor_id == constructor_id)?;
+    // The application carries the parameters of the inductive type first,
+    // which the branch does not bind. The arities differ otherwise only if
+    // type checking is incorrect.
+    let fields = args.len().checked_sub(branch.bound_vars.len())?;
+    Some(instantiate_many(&branch.body, &args[fields..]))
+}
+
+/// Unfolds the theorem at the head of a closed proof that a match on a
+/// proposition is stuck on, and reduces it to WHNF.
+///
+/// Theorems are otherwise opaque, but a closed proof built without axioms
+/// reduces to a constructor this way. This is what makes the functions defined
+/// by well-founded recursion compute: they match on accessibility proofs.
+fn unfold_closed_proof(
+    case: &TermMatch,
+    scrutinee: &Term,
+    env: &GlobalEnvironment,
+) -> Option<Term> {
+    if !matches_on_proof(case, env) || !is_closed_under(scrutinee, 0) {
+        return None;
+    }
+    let (head, args) = match scrutinee {
+        Term::Application(app) => (app.f.as_ref(), app.args.as_slice()),
+        term => (term, &[][..]),
+    };
+    let Term::Constant(const_) = head else {
+        return None;
+    };
+    let definition = env.get_constant(const_.id).filter(|def| def.opaque)?;
+    let proof = definition.body.as_ref()?;
+    let unfolded = if args.is_empty() {
+        proof.as_ref().clone()
+    } else {
+        Term::Application(TermApplication {
+            f: proof.clone(),
+            args: args.to_vec(),
+        })
+    };
+    Some(whnf_with_env(&unfolded, env))
+}
+
+/// Whether the branches of `case` are those of an inductive proposition
+pub(crate) fn matches_on_proof(case: &TermMatch, env: &GlobalEnvironment) -> bool {
+    case.branches.first().is_some_and(|branch| {
+        env.inductives
+            .get_inductive_for_constructor(branch.constructor_id)
+            .and_then(|inductive| env.inductives.get_inductive(inductive))
+            .is_some_and(|inductive| arity_sort(env, &inductive.get_type()) == Some(Sort::Prop))
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use std::rc::Rc;
 
     use crate::{
         id::Id,
-        term::{Sort, Term, TermApplication, TermLambda, TermLetIn, TermSort, TermVariable},
+        term::{
+            Sort, Term, TermApplication, TermBound, TermConstant, TermLambda, TermLetIn, TermMatch,
+            TermMatchBranch, TermSort, TermVariable,
+        },
     };
 
     use super::{normalize, reduce_step, whnf};
@@ -570,7 +589,7 @@ mod tests {
         let lambda = Term::Lambda(TermLambda {
             var: x,
             source_ty: Rc::new(Term::Sort(TermSort { sort: Sort::Set })),
-            target: Rc::new(Term::Variable(TermVariable { id: x })),
+            target: Rc::new(Term::Bound(TermBound { index: 0 })),
         });
 
         let app = Term::Application(TermApplication {
@@ -594,7 +613,7 @@ mod tests {
         let inner_lambda = Term::Lambda(TermLambda {
             var: y,
             source_ty: Rc::new(Term::Sort(TermSort { sort: Sort::Set })),
-            target: Rc::new(Term::Variable(TermVariable { id: x })),
+            target: Rc::new(Term::Bound(TermBound { index: 1 })),
         });
 
         let outer_lambda = Term::Lambda(TermLambda {
@@ -628,7 +647,7 @@ mod tests {
         let inner_inner_lambda = Term::Lambda(TermLambda {
             var: z,
             source_ty: Rc::new(Term::Sort(TermSort { sort: Sort::Set })),
-            target: Rc::new(Term::Variable(TermVariable { id: x })),
+            target: Rc::new(Term::Bound(TermBound { index: 2 })),
         });
 
         let inner_lambda = Term::Lambda(TermLambda {
@@ -666,7 +685,7 @@ mod tests {
             var: x,
             term: Rc::new(Term::Variable(TermVariable { id: a })),
             ty: Rc::new(Term::Sort(TermSort { sort: Sort::Set })),
-            body: Rc::new(Term::Variable(TermVariable { id: x })),
+            body: Rc::new(Term::Bound(TermBound { index: 0 })),
         });
 
         let reduced = reduce_step(&let_in);
@@ -709,12 +728,12 @@ mod tests {
         let inner_lambda = Term::Lambda(TermLambda {
             var: y,
             source_ty: Rc::new(Term::Sort(TermSort { sort: Sort::Set })),
-            target: Rc::new(Term::Variable(TermVariable { id: y })),
+            target: Rc::new(Term::Bound(TermBound { index: 0 })),
         });
 
         let inner_app = Term::Application(TermApplication {
             f: Rc::new(inner_lambda),
-            args: vec![Term::Variable(TermVariable { id: x })],
+            args: vec![Term::Bound(TermBound { index: 0 })],
         });
 
         let outer_lambda = Term::Lambda(TermLambda {
@@ -740,6 +759,52 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_whnf_keeps_stuck_match() {
+        // match x return ((λy. y) Set) with C => (λy. y) x: the scrutinee is
+        // not a constructor, so nothing under the match is reduced
+        let x = Id::new();
+        let identity = Rc::new(Term::Lambda(TermLambda {
+            var: Id::new(),
+            source_ty: Rc::new(Term::Sort(TermSort { sort: Sort::Set })),
+            target: Rc::new(Term::Bound(TermBound { index: 0 })),
+        }));
+        let case = Term::Match(TermMatch {
+            scrutinee: Rc::new(Term::Variable(TermVariable { id: x })),
+            return_type: Rc::new(Term::Application(TermApplication {
+                f: identity.clone(),
+                args: vec![Term::Sort(TermSort { sort: Sort::Set })],
+            })),
+            branches: vec![TermMatchBranch {
+                constructor_id: Id::new(),
+                bound_vars: Vec::new(),
+                body: Rc::new(Term::Application(TermApplication {
+                    f: identity,
+                    args: vec![Term::Variable(TermVariable { id: x })],
+                })),
+            }],
+        });
+
+        assert_eq!(whnf(&case), case);
+        assert!(reduce_step(&case).is_some());
+
+        // It reduces once the scrutinee is a constructor
+        let constructor = Term::Constant(TermConstant {
+            id: match &case {
+                Term::Match(case) => case.branches[0].constructor_id,
+                _ => unreachable!(),
+            },
+        });
+        let Term::Match(mut reducible) = case else {
+            unreachable!()
+        };
+        reducible.scrutinee = Rc::new(constructor);
+        assert_eq!(
+            whnf(&Term::Match(reducible)),
+            Term::Variable(TermVariable { id: x })
+        );
+    }
+
     #[test]
     fn test_normalize_reduces_completely() {
         // Test that normalize reduces nested applications completely
@@ -748,7 +813,7 @@ mod tests {
         let lambda = Term::Lambda(TermLambda {
             var: x,
             source_ty: Rc::new(Term::Sort(TermSort { sort: Sort::Set })),
-            target: Rc::new(Term::Variable(TermVariable { id: x })),
+            target: Rc::new(Term::Bound(TermBound { index: 0 })),
         });
 
         let app = Term::Application(TermApplication {
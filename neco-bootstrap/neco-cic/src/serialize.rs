@@ -0,0 +1,906 @@
+use std::{
+    collections::{HashMap, HashSet},
+    iter::Peekable,
+    rc::Rc,
+    str::SplitWhitespace,
+};
+
+use crate::{
+    global_environment::{ConstantDefinition, GlobalEnvironment, mentions_constant},
+    id::{Id, IdGenerator},
+    inductive::{ConstructorDefinition, InductiveDefinition, Parameter},
+    local_context::LocalContext,
+    term::{
+        Sort, Term, TermApplication, TermBound, TermConstant, TermLambda, TermLetIn, TermLiteral,
+        TermMatch, TermMatchBranch, TermProduct, TermSort, TermVariable,
+    },
+    termination::check_termination,
+    typechecker::{arity_sort, check_type, ensure_sort_with_env, infer_type},
+};
+
+// Environment files are text: a header line, then words separated by
+// whitespace. Each item starts with `item` and the hash of its source, followed
+// by the names of its ids and its declarations, one per line, with terms in
+// prefix notation:
+//
+// ```text
+// neco-cic-environment 1
+// item 5d1c6f4a0e3b2a19
+// name 2 nat
+// inductives 1
+// inductive 2 0 Set 2 3 0 const 2 4 1 pi 5 const 2 const 2
+// constants 1
+// definition 6 pi 7 const 2 const 2 lam 8 const 2 app 1 const 4 bound 0
+// ```
+//
+// Ids are renumbered when a file is read, so that they do not collide with the
+// ids of the reader.
+
+const HEADER: &str = "neco-cic-environment 1";
+
+/// Hashes the source of an item with 64-bit FNV-1a, which is the same across
+/// runs and platforms
+pub fn content_hash(source: &str) -> u64 {
+    source.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// A declaration of an environment file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Declaration {
+    /// A block of mutual inductive types
+    Inductives(Vec<InductiveDefinition>),
+    /// A constant, or a block of mutually recursive constants
+    Constants(Vec<ConstantDefinition>),
+    /// The projections of a record type, for η in conversion
+    Record { inductive: Id, projections: Vec<Id> },
+}
+
+/// An item of the source with the declarations it added, in an order in
+/// which they can be added again
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FileItem {
+    /// Hash of the source of the item, see `content_hash`
+    pub hash: u64,
+    pub declarations: Vec<Declaration>,
+    /// Source names of the declarations and of the binders of their terms,
+    /// each a single word
+    pub names: Vec<(Id, String)>,
+    /// Binders of the products written as implicit arrows
+    pub implicit_binders: Vec<Id>,
+    /// Library roles of the declarations, such as `Acc`
+    pub builtins: Vec<(String, Id)>,
+}
+
+/// A checked environment as written to a file, item by item
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct EnvironmentFile {
+    pub items: Vec<FileItem>,
+}
+
+impl FileItem {
+    /// The item of the source hashed to `hash` that declared `ids` in `env`.
+    /// Names and implicit binders are kept for the ids of the item, the
+    /// constructors of its inductive types and the binders of its terms.
+    pub fn new(
+        env: &GlobalEnvironment,
+        hash: u64,
+        ids: &[Id],
+        names: &HashMap<Id, String>,
+        implicit_binders: &HashSet<Id>,
+    ) -> Self {
+        let declarations: Vec<Id> = ids
+            .iter()
+            .copied()
+            .filter(|id| {
+                env.get_constant(*id).is_some() || env.inductives.get_inductive(*id).is_some()
+            })
+            .collect();
+        let mut item = FileItem {
+            hash,
+            ..FileItem::default()
+        };
+        let mut added: Vec<Id> = Vec::new();
+        let mut binders = Vec::new();
+        for component in env.components(&declarations) {
+            if added.contains(&component[0]) {
+                continue;
+            }
+            if env.inductives.get_inductive(component[0]).is_some() {
+                let block = env.inductives.mutual_block(component[0]);
+                let defs: Vec<InductiveDefinition> = block
+                    .iter()
+                    .map(|id| env.inductives.get_inductive(*id).unwrap().clone())
+                    .collect();
+                for def in &defs {
+                    binders.extend(def.constructors.iter().map(|c| c.name));
+                    collect_binders(&def.get_type(), &mut binders);
+                    for constructor in &def.constructors {
+                        collect_binders(&constructor.ty, &mut binders);
+                    }
+                }
+                added.extend(block);
+                item.declarations.push(Declaration::Inductives(defs));
+            } else {
+                let defs: Vec<ConstantDefinition> = component
+                    .iter()
+                    .map(|id| env.get_constant(*id).unwrap().clone())
+                    .collect();
+                for def in &defs {
+                    collect_binders(&def.ty, &mut binders);
+                    if let Some(body) = &def.body {
+                        collect_binders(body, &mut binders);
+                    }
+                }
+                added.extend(&component);
+                item.declarations.push(Declaration::Constants(defs));
+            }
+
+            // Records come once their projections are defined
+            for id in &declarations {
+                let Some(inductive) = env.inductives.get_inductive(*id) else {
+                    continue;
+                };
+                let Some(projections) = inductive
+                    .constructors
+                    .first()
+                    .and_then(|constructor| env.record_projections(constructor.name))
+                else {
+                    continue;
+                };
+                let recorded = item.declarations.iter().any(|declaration| {
+                    matches!(declaration, Declaration::Record { inductive, .. } if inductive == id)
+                });
+                if !recorded && projections.iter().all(|p| added.contains(p)) {
+                    item.declarations.push(Declaration::Record {
+                        inductive: *id,
+                        projections: projections.to_vec(),
+                    });
+                }
+            }
+        }
+
+        let mut named: Vec<Id> = ids.iter().copied().chain(binders).collect();
+        named.sort();
+        named.dedup();
+        item.names = named
+            .iter()
+            .filter_map(|id| names.get(id).map(|name| (*id, name.clone())))
+            .collect();
+        item.implicit_binders = named
+            .into_iter()
+            .filter(|id| implicit_binders.contains(id))
+            .collect();
+        item
+    }
+
+    /// Adds the declarations to `env` without checking them
+    pub fn load(&self, env: &mut GlobalEnvironment) -> Result<(), String> {
+        for declaration in &self.declarations {
+            match declaration {
+                Declaration::Inductives(defs) => {
+                    env.inductives.add_mutual_inductives(defs.clone())?;
+                }
+                Declaration::Constants(defs) => {
+                    for def in defs {
+                        env.add_constant(def.clone())?;
+                    }
+                }
+                Declaration::Record {
+                    inductive,
+                    projections,
+                } => env
+                    .add_record(*inductive, projections.clone())
+                    .map_err(|e| e.to_string())?,
+            }
+        }
+        self.register_primitives(env)
+    }
+
+    /// Checks the declarations with the kernel alone, and adds them to `env`.
+    /// Errors print terms with `names`. On error, `env` may hold some of the
+    /// declarations of the item.
+    pub fn recheck(
+        &self,
+        env: &mut GlobalEnvironment,
+        names: &HashMap<Id, String>,
+    ) -> Result<(), String> {
+        let name = |id: &Id| names.get(id).cloned().unwrap_or_else(|| format!("{id:?}"));
+        let ctx = LocalContext::new();
+        let is_type = |env: &GlobalEnvironment, id: &Id, ty: &Term| {
+            let sort = infer_type(&ctx, env, ty)
+                .map_err(|e| format!("{}: {}", name(id), e.describe(names)))?;
+            ensure_sort_with_env(ty, &sort, env)
+                .map_err(|e| format!("{}: {}", name(id), e.describe(names)))
+        };
+
+        for declaration in &self.declarations {
+            match declaration {
+                Declaration::Inductives(defs) => {
+                    for def in defs {
+                        is_type(env, &def.name, &def.get_type())?;
+                        if arity_sort(env, &def.get_type()).is_none() {
+                            return Err(format!("{}: its type is not an arity", name(&def.name)));
+                        }
+                    }
+                    env.inductives.add_mutual_inductives(defs.clone())?;
+                    for def in defs {
+                        for constructor in &def.constructors {
+                            is_type(env, &constructor.name, &constructor.ty)?;
+                            check_constructor(def, constructor)
+                                .map_err(|e| format!("{}: {e}", name(&constructor.name)))?;
+                        }
+                    }
+                }
+                Declaration::Constants(defs) => {
+                    for def in defs {
+                        is_type(env, &def.name, &def.ty)?;
+                    }
+                    let block: Vec<Id> = defs.iter().map(|def| def.name).collect();
+                    let bodies: Vec<(Id, &Term)> = defs
+                        .iter()
+                        .filter_map(|def| def.body.as_deref().map(|body| (def.name, body)))
+                        .collect();
+
+                    // Recursive calls are checked against the declared types
+                    let recursive = bodies
+                        .iter()
+                        .any(|(_, body)| block.iter().any(|id| mentions_constant(body, *id)));
+                    let mut recursive_env;
+                    let checking_env = if recursive {
+                        check_termination(env, &bodies).map_err(|e| {
+                            let names: Vec<String> = e.constants.iter().map(name).collect();
+                            format!("{}: no decreasing arguments", names.join(", "))
+                        })?;
+                        recursive_env = env.clone();
+                        for def in defs {
+                            recursive_env.add_axiom(def.name, def.ty.clone())?;
+                        }
+                        &recursive_env
+                    } else {
+                        &*env
+                    };
+                    for def in defs {
+                        if let Some(body) = &def.body {
+                            check_type(&ctx, checking_env, body, &def.ty).map_err(|e| {
+                                format!("{}: {}", name(&def.name), e.describe(names))
+                            })?;
+                        }
+                    }
+                    for def in defs {
+                        env.add_constant(def.clone())?;
+                    }
+                }
+                Declaration::Record {
+                    inductive,
+                    projections,
+                } => env
+                    .add_record(*inductive, projections.clone())
+                    .map_err(|e| e.describe(names))?,
+            }
+        }
+        self.register_primitives(env)
+    }
+}
+
+impl FileItem {
+    fn register_primitives(&self, env: &mut GlobalEnvironment) -> Result<(), String> {
+        // The operations on u64 are checked against the type
+        let mut builtins: Vec<_> = self.builtins.iter().collect();
+        builtins.sort_by_key(|(builtin, _)| builtin != "u64");
+        for (builtin, id) in builtins {
+            env.register_primitive(builtin, *id)?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks that a constructor takes the parameters and `arity` arguments and
+/// returns its inductive type applied to as many arguments as the arity of
+/// the type has
+fn check_constructor(
+    def: &InductiveDefinition,
+    constructor: &ConstructorDefinition,
+) -> Result<(), String> {
+    let (mut conclusion, mut arguments) = (constructor.ty.as_ref(), 0);
+    while let Term::Product(product) = conclusion {
+        conclusion = &product.target;
+        arguments += 1;
+    }
+    let expected = def.parameters.len() + constructor.arity;
+    if arguments != expected {
+        return Err(format!("it takes {arguments} arguments, not {expected}"));
+    }
+    let (mut arity, mut indices) = (def.get_type(), 0);
+    while let Term::Product(product) = arity.as_ref() {
+        arity = product.target.clone();
+        indices += 1;
+    }
+    let (head, args) = match conclusion {
+        Term::Application(app) => (app.f.as_ref(), app.args.len()),
+        _ => (conclusion, 0),
+    };
+    match head {
+        Term::Constant(constant) if constant.id == def.name && args == indices => Ok(()),
+        _ => Err("it does not return its inductive type".to_string()),
+    }
+}
+
+/// Pushes the binder ids of `term` onto `out`
+fn collect_binders(term: &Term, out: &mut Vec<Id>) {
+    match term {
+        Term::Sort(_)
+        | Term::Variable(_)
+        | Term::Bound(_)
+        | Term::Constant(_)
+        | Term::Literal(_) => {}
+        Term::Product(product) => {
+            out.push(product.var);
+            collect_binders(&product.source, out);
+            collect_binders(&product.target, out);
+        }
+        Term::Lambda(lambda) => {
+            out.push(lambda.var);
+            collect_binders(&lambda.source_ty, out);
+            collect_binders(&lambda.target, out);
+        }
+        Term::Application(app) => {
+            collect_binders(&app.f, out);
+            app.args.iter().for_each(|arg| collect_binders(arg, out));
+        }
+        Term::LetIn(let_in) => {
+            out.push(let_in.var);
+            collect_binders(&let_in.term, out);
+            collect_binders(&let_in.ty, out);
+            collect_binders(&let_in.body, out);
+        }
+        Term::Match(case) => {
+            collect_binders(&case.scrutinee, out);
+            collect_binders(&case.return_type, out);
+            for branch in &case.branches {
+                out.extend(&branch.bound_vars);
+                collect_binders(&branch.body, out);
+            }
+        }
+    }
+}
+
+impl EnvironmentFile {
+    /// The source names of all the items
+    pub fn names(&self) -> HashMap<Id, String> {
+        self.items
+            .iter()
+            .flat_map(|item| item.names.iter().cloned())
+            .collect()
+    }
+
+    /// Adds the declarations of all the items to `env` without checking them
+    pub fn load(&self, env: &mut GlobalEnvironment) -> Result<(), String> {
+        self.items.iter().try_for_each(|item| item.load(env))
+    }
+
+    /// Checks the declarations of all the items with the kernel alone, and
+    /// adds them to `env`
+    pub fn recheck(&self, env: &mut GlobalEnvironment) -> Result<(), String> {
+        let names = self.names();
+        self.items
+            .iter()
+            .try_for_each(|item| item.recheck(env, &names))
+    }
+
+    pub fn write(&self) -> String {
+        let mut out = format!("{HEADER}\n");
+        for item in &self.items {
+            out.push_str(&format!("item {:016x}\n", item.hash));
+            for (id, name) in &item.names {
+                out.push_str(&format!("name {id} {name}\n"));
+            }
+            for id in &item.implicit_binders {
+                out.push_str(&format!("implicit {id}\n"));
+            }
+            for (builtin, id) in &item.builtins {
+                out.push_str(&format!("builtin {builtin} {id}\n"));
+            }
+            for declaration in &item.declarations {
+                write_declaration(declaration, &mut out);
+            }
+        }
+        out
+    }
+
+    /// Reads a file written by `write`, with fresh ids from `id_gen`
+    pub fn read(contents: &str, id_gen: &mut IdGenerator) -> Result<Self, String> {
+        let mut reader = Reader {
+            words: contents.split_whitespace().peekable(),
+            ids: HashMap::new(),
+            id_gen,
+        };
+        for word in HEADER.split_whitespace() {
+            reader.expect(word)?;
+        }
+        let mut file = EnvironmentFile::default();
+        while reader.words.peek().is_some() {
+            reader.expect("item")?;
+            let hash = reader.next()?;
+            let hash = u64::from_str_radix(hash, 16).map_err(|_| format!("Invalid hash {hash}"))?;
+            let mut item = FileItem {
+                hash,
+                ..FileItem::default()
+            };
+            while let Some(&word) = reader.words.peek() {
+                if word == "item" {
+                    break;
+                }
+                reader.next()?;
+                match word {
+                    "name" => {
+                        let id = reader.id()?;
+                        item.names.push((id, reader.next()?.to_string()));
+                    }
+                    "implicit" => item.implicit_binders.push(reader.id()?),
+                    "builtin" => {
+                        let builtin = reader.next()?.to_string();
+                        item.builtins.push((builtin, reader.id()?));
+                    }
+                    "inductives" => {
+                        let count = reader.number()?;
+                        let defs = (0..count)
+                            .map(|_| reader.inductive())
+                            .collect::<Result<_, _>>()?;
+                        item.declarations.push(Declaration::Inductives(defs));
+                    }
+                    "constants" => {
+                        let count = reader.number()?;
+                        let defs = (0..count)
+                            .map(|_| reader.constant())
+                            .collect::<Result<_, _>>()?;
+                        item.declarations.push(Declaration::Constants(defs));
+                    }
+                    "record" => {
+                        let inductive = reader.id()?;
+                        let count = reader.number()?;
+                        let projections =
+                            (0..count).map(|_| reader.id()).collect::<Result<_, _>>()?;
+                        item.declarations.push(Declaration::Record {
+                            inductive,
+                            projections,
+                        });
+                    }
+                    _ => return Err(format!("Unexpected `{word}`")),
+                }
+            }
+            file.items.push(item);
+        }
+        Ok(file)
+    }
+}
+
+fn write_declaration(declaration: &Declaration, out: &mut String) {
+    match declaration {
+        Declaration::Inductives(defs) => {
+            out.push_str(&format!("inductives {}\n", defs.len()));
+            for def in defs {
+                out.push_str(&format!("inductive {} {}", def.name, def.parameters.len()));
+                for parameter in &def.parameters {
+                    out.push_str(&format!(" {}", parameter.name));
+                    write_term(&parameter.ty, out);
+                }
+                write_term(&def.sort, out);
+                out.push_str(&format!(" {}", def.constructors.len()));
+                for constructor in &def.constructors {
+                    out.push_str(&format!(" {} {}", constructor.name, constructor.arity));
+                    write_term(&constructor.ty, out);
+                }
+                out.push('\n');
+            }
+        }
+        Declaration::Constants(defs) => {
+            out.push_str(&format!("constants {}\n", defs.len()));
+            for def in defs {
+                let kind = match (&def.body, def.opaque) {
+                    (None, _) => "axiom",
+                    (Some(_), false) => "definition",
+                    (Some(_), true) => "theorem",
+                };
+                out.push_str(&format!("{kind} {}", def.name));
+                write_term(&def.ty, out);
+                if let Some(body) = &def.body {
+                    write_term(body, out);
+                }
+                out.push('\n');
+            }
+        }
+        Declaration::Record {
+            inductive,
+            projections,
+        } => {
+            out.push_str(&format!("record {inductive} {}", projections.len()));
+            for projection in projections {
+                out.push_str(&format!(" {projection}"));
+            }
+            out.push('\n');
+        }
+    }
+}
+
+/// Writes a term in prefix notation, each word preceded by a space
+fn write_term(term: &Term, out: &mut String) {
+    match term {
+        Term::Sort(sort) => match sort.sort {
+            Sort::Set => out.push_str(" Set"),
+            Sort::Prop => out.push_str(" Prop"),
+            Sort::Type(level) => out.push_str(&format!(" Type {level}")),
+        },
+        Term::Variable(var) => out.push_str(&format!(" var {}", var.id)),
+        Term::Bound(bound) => out.push_str(&format!(" bound {}", bound.index)),
+        Term::Constant(constant) => out.push_str(&format!(" const {}", constant.id)),
+        Term::Literal(literal) => out.push_str(&format!(" lit {}", literal.value)),
+        Term::Product(product) => {
+            out.push_str(&format!(" pi {}", product.var));
+            write_term(&product.source, out);
+            write_term(&product.target, out);
+        }
+        Term::Lambda(lambda) => {
+            out.push_str(&format!(" lam {}", lambda.var));
+            write_term(&lambda.source_ty, out);
+            write_term(&lambda.target, out);
+        }
+        Term::Application(app) => {
+            out.push_str(&format!(" app {}", app.args.len()));
+            write_term(&app.f, out);
+            app.args.iter().for_each(|arg| write_term(arg, out));
+        }
+        Term::LetIn(let_in) => {
+            out.push_str(&format!(" let {}", let_in.var));
+            write_term(&let_in.term, out);
+            write_term(&let_in.ty, out);
+            write_term(&let_in.body, out);
+        }
+        Term::Match(case) => {
+            out.push_str(" match");
+            write_term(&case.scrutinee, out);
+            write_term(&case.return_type, out);
+            out.push_str(&format!(" {}", case.branches.len()));
+            for branch in &case.branches {
+                out.push_str(&format!(
+                    " {} {}",
+                    branch.constructor_id,
+                    branch.bound_vars.len()
+                ));
+                for var in &branch.bound_vars {
+                    out.push_str(&format!(" {var}"));
+                }
+                write_term(&branch.body, out);
+            }
+        }
+    }
+}
+
+struct Reader<'a> {
+    words: Peekable<SplitWhitespace<'a>>,
+    /// Fresh ids of the numbers of the file
+    ids: HashMap<&'a str, Id>,
+    id_gen: &'a mut IdGenerator,
+}
+
+impl<'a> Reader<'a> {
+    fn next(&mut self) -> Result<&'a str, String> {
+        self.words
+            .next()
+            .ok_or_else(|| "Unexpected end of file".to_string())
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), String> {
+        match self.next()? {
+            word if word == expected => Ok(()),
+            word => Err(format!("Expected `{expected}`, found `{word}`")),
+        }
+    }
+
+    fn number(&mut self) -> Result<usize, String> {
+        let word = self.next()?;
+        word.parse().map_err(|_| format!("Invalid number `{word}`"))
+    }
+
+    fn id(&mut self) -> Result<Id, String> {
+        let word = self.next()?;
+        if word.parse::<usize>().is_err() {
+            return Err(format!("Invalid id `{word}`"));
+        }
+        let id_gen = &mut self.id_gen;
+        Ok(*self.ids.entry(word).or_insert_with(|| id_gen.generate_id()))
+    }
+
+    fn inductive(&mut self) -> Result<InductiveDefinition, String> {
+        self.expect("inductive")?;
+        let name = self.id()?;
+        let count = self.number()?;
+        let mut parameters = Vec::new();
+        for _ in 0..count {
+            let name = self.id()?;
+            parameters.push(Parameter {
+                name,
+                ty: Rc::new(self.term()?),
+            });
+        }
+        let sort = Rc::new(self.term()?);
+        let count = self.number()?;
+        let mut constructors = Vec::new();
+        for _ in 0..count {
+            let name = self.id()?;
+            let arity = self.number()?;
+            constructors.push(ConstructorDefinition::new(
+                name,
+                Rc::new(self.term()?),
+                arity,
+            ));
+        }
+        Ok(InductiveDefinition::new(
+            name,
+            parameters,
+            sort,
+            constructors,
+        ))
+    }
+
+    fn constant(&mut self) -> Result<ConstantDefinition, String> {
+        let kind = self.next()?;
+        let name = self.id()?;
+        let ty = Rc::new(self.term()?);
+        match kind {
+            "axiom" => Ok(ConstantDefinition::new_axiom(name, ty)),
+            "definition" => Ok(ConstantDefinition::new_definition(
+                name,
+                ty,
+                Rc::new(self.term()?),
+            )),
+            "theorem" => Ok(ConstantDefinition::new_theorem(
+                name,
+                ty,
+                Rc::new(self.term()?),
+            )),
+            _ => Err(format!("Unexpected `{kind}`")),
+        }
+    }
+
+    fn term(&mut self) -> Result<Term, String> {
+        let term = match self.next()? {
+            "Set" => Term::Sort(TermSort { sort: Sort::Set }),
+            "Prop" => Term::Sort(TermSort { sort: Sort::Prop }),
+            "Type" => Term::Sort(TermSort {
+                sort: Sort::Type(self.number()?),
+            }),
+            "var" => Term::Variable(TermVariable { id: self.id()? }),
+            "bound" => Term::Bound(TermBound {
+                index: self.number()?,
+            }),
+            "const" => Term::Constant(TermConstant { id: self.id()? }),
+            "lit" => {
+                let word = self.next()?;
+                let value = word
+                    .parse()
+                    .map_err(|_| format!("Invalid literal `{word}`"))?;
+                Term::Literal(TermLiteral { value })
+            }
+            "pi" => Term::Product(TermProduct {
+                var: self.id()?,
+                source: Rc::new(self.term()?),
+                target: Rc::new(self.term()?),
+            }),
+            "lam" => Term::Lambda(TermLambda {
+                var: self.id()?,
+                source_ty: Rc::new(self.term()?),
+                target: Rc::new(self.term()?),
+            }),
+            "app" => {
+                let count = self.number()?;
+                let f = Rc::new(self.term()?);
+                let args = (0..count).map(|_| self.term()).collect::<Result<_, _>>()?;
+                Term::Application(TermApplication { f, args })
+            }
+            "let" => Term::LetIn(TermLetIn {
+                var: self.id()?,
+                term: Rc::new(self.term()?),
+                ty: Rc::new(self.term()?),
+                body: Rc::new(self.term()?),
+            }),
+            "match" => {
+                let scrutinee = Rc::new(self.term()?);
+                let return_type = Rc::new(self.term()?);
+                let count = self.number()?;
+                let mut branches = Vec::new();
+                for _ in 0..count {
+                    let constructor_id = self.id()?;
+                    let arity = self.number()?;
+                    let bound_vars = (0..arity).map(|_| self.id()).collect::<Result<_, _>>()?;
+                    branches.push(TermMatchBranch {
+                        constructor_id,
+                        bound_vars,
+                        body: Rc::new(self.term()?),
+                    });
+                }
+                Term::Match(TermMatch {
+                    scrutinee,
+                    return_type,
+                    branches,
+                })
+            }
+            word => return Err(format!("Unexpected `{word}` in a term")),
+        };
+        Ok(term)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{HashMap, HashSet},
+        rc::Rc,
+    };
+
+    use crate::{
+        global_environment::{ConstantDefinition, GlobalEnvironment},
+        id::{Id, IdGenerator},
+        reduction::normalize_with_env,
+        substitution::abstract_variable,
+        term::{
+            Sort, Term, TermApplication, TermBound, TermConstant, TermLambda, TermMatch,
+            TermMatchBranch, TermProduct, TermSort, TermVariable,
+        },
+    };
+
+    use super::{Declaration, EnvironmentFile, FileItem, content_hash};
+
+    fn constant(id: Id) -> Term {
+        Term::Constant(TermConstant { id })
+    }
+
+    fn apply(f: Id, args: Vec<Term>) -> Term {
+        Term::Application(TermApplication {
+            f: Rc::new(constant(f)),
+            args,
+        })
+    }
+
+    /// `nat` with `double : nat -> nat`, by recursion
+    fn nat_and_double() -> (GlobalEnvironment, HashMap<Id, String>, [Id; 4]) {
+        let mut env = GlobalEnvironment::new();
+        let [nat, zero, succ, double, n, p] = [(); 6].map(|_| Id::new());
+        env.inductives.add_nat(nat, zero, succ).unwrap();
+        let case = Term::Match(TermMatch {
+            scrutinee: Rc::new(Term::Variable(TermVariable { id: n })),
+            return_type: Rc::new(constant(nat)),
+            branches: vec![
+                TermMatchBranch {
+                    constructor_id: zero,
+                    bound_vars: Vec::new(),
+                    body: Rc::new(constant(zero)),
+                },
+                TermMatchBranch {
+                    constructor_id: succ,
+                    bound_vars: vec![p],
+                    body: Rc::new(apply(
+                        succ,
+                        vec![apply(
+                            succ,
+                            vec![apply(double, vec![Term::Bound(TermBound { index: 0 })])],
+                        )],
+                    )),
+                },
+            ],
+        });
+        let body = Term::Lambda(TermLambda {
+            var: n,
+            source_ty: Rc::new(constant(nat)),
+            target: Rc::new(abstract_variable(&case, n)),
+        });
+        let ty = Term::Product(TermProduct {
+            var: n,
+            source: Rc::new(constant(nat)),
+            target: Rc::new(constant(nat)),
+        });
+        env.add_definition(double, Rc::new(ty), Rc::new(body))
+            .unwrap();
+        let names = HashMap::from([
+            (nat, "nat".to_string()),
+            (zero, "O".to_string()),
+            (succ, "S".to_string()),
+            (double, "double".to_string()),
+            (n, "n".to_string()),
+            (p, "p".to_string()),
+        ]);
+        (env, names, [nat, zero, succ, double])
+    }
+
+    #[test]
+    fn test_environment_files_round_trip() {
+        let (env, names, [nat, _, _, double]) = nat_and_double();
+        let file = EnvironmentFile {
+            items: vec![
+                FileItem::new(&env, content_hash("nat"), &[nat], &names, &HashSet::new()),
+                FileItem::new(
+                    &env,
+                    content_hash("double"),
+                    &[double],
+                    &names,
+                    &HashSet::new(),
+                ),
+            ],
+        };
+        let written = file.write();
+        let read = EnvironmentFile::read(&written, &mut IdGenerator::new()).unwrap();
+        assert_eq!(read.items.len(), 2);
+        assert_eq!(read.items[1].hash, content_hash("double"));
+        assert_eq!(read.names().len(), names.len());
+
+        // Ids are renumbered in order of appearance, so that writing again
+        // gives back the same file
+        let rewritten = read.write();
+        let reread = EnvironmentFile::read(&rewritten, &mut IdGenerator::new()).unwrap();
+        assert_eq!(reread.write(), rewritten);
+
+        // The loaded and the rechecked environments compute
+        let mut loaded = GlobalEnvironment::new();
+        read.load(&mut loaded).unwrap();
+        let mut rechecked = GlobalEnvironment::new();
+        read.recheck(&mut rechecked).unwrap();
+        let id = |name: &str| read.names().into_iter().find(|(_, n)| n == name).unwrap().0;
+        let [succ, zero, double] = [id("S"), id("O"), id("double")];
+        let two = apply(double, vec![apply(succ, vec![constant(zero)])]);
+        let expected = apply(succ, vec![apply(succ, vec![constant(zero)])]);
+        assert_eq!(normalize_with_env(&two, &loaded), expected);
+        assert_eq!(normalize_with_env(&two, &rechecked), expected);
+    }
+
+    #[test]
+    fn test_recheck_rejects_ill_typed_declarations() {
+        let (env, names, [nat, ..]) = nat_and_double();
+        let bad = Id::new();
+        let set = Rc::new(Term::Sort(TermSort { sort: Sort::Set }));
+        let mut item = FileItem::new(&env, 0, &[nat], &names, &HashSet::new());
+        item.declarations.push(Declaration::Constants(vec![
+            ConstantDefinition::new_definition(bad, Rc::new(constant(nat)), set),
+        ]));
+        item.names.push((bad, "bad".to_string()));
+        let file = EnvironmentFile { items: vec![item] };
+        let read = EnvironmentFile::read(&file.write(), &mut IdGenerator::new()).unwrap();
+
+        let mut loaded = GlobalEnvironment::new();
+        assert_eq!(read.load(&mut loaded), Ok(()));
+        let mut rechecked = GlobalEnvironment::new();
+        assert_eq!(
+            read.recheck(&mut rechecked),
+            Err("bad: Type mismatch: expected `nat`, found `Type` for `Set`".to_string())
+        );
+    }
+
+    #[test]
+    fn test_recheck_rejects_branches_of_the_wrong_type() {
+        // `f : bool -> False`, whose branches return `I : True`
+        let contents =
+            std::fs::read_to_string("../../testcases/environment/false_by_true.env").unwrap();
+        let read = EnvironmentFile::read(&contents, &mut IdGenerator::new()).unwrap();
+        let mut rechecked = GlobalEnvironment::new();
+        assert_eq!(
+            read.recheck(&mut rechecked),
+            Err("f: Type mismatch: expected `False`, found `True` for `I`".to_string())
+        );
+    }
+
+    #[test]
+    fn test_reading_reports_malformed_files() {
+        let mut id_gen = IdGenerator::new();
+        assert_eq!(
+            EnvironmentFile::read("neco-cic-environment 2", &mut id_gen),
+            Err("Expected `1`, found `2`".to_string())
+        );
+        assert_eq!(
+            EnvironmentFile::read(
+                "neco-cic-environment 1 item 0 constants 1 axiom 0 pi",
+                &mut id_gen
+            ),
+            Err("Unexpected end of file".to_string())
+        );
+    }
+}
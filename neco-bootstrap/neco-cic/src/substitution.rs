@@ -2,16 +2,19 @@ use std::{collections::HashMap, rc::Rc};
 
 use crate::{
     id::Id,
+    intern::{self, TermInfo},
     term::{
-        Term, TermApplication, TermConstant, TermLambda, TermLetIn, TermMatch, TermMatchBranch,
-        TermProduct, TermSort, TermVariable,
+        Term, TermApplication, TermBound, TermLambda, TermLetIn, TermMatch, TermMatchBranch,
+        TermProduct, TermVariable,
     },
 };
 
-// Note: Variable capture is not a concern in this implementation because each binding
-// (in Lambda, Product, and LetIn) is assigned a unique ID when constructed.
-// This means that bound variables will always have different IDs from any variables
-// being substituted, preventing capture issues.
+// Bound variables are de Bruijn indices and free variables are named by `Id`
+// (locally nameless). Substituting a free variable can never capture a bound
+// one, and the primitives below keep indices consistent when a term is moved
+// under or out of binders.
+// Interned subterms carry cached `TermInfo`: subterms that a primitive cannot
+// change are shared with the input instead of being rebuilt.
 
 pub struct Substitution {
     subst: HashMap<Id, Rc<Term>>,
@@ -39,107 +42,368 @@ impl Substitution {
     }
 }
 
+/// Replaces free variables according to `subst`.
+/// Replacements are lifted when they are moved under binders.
 pub fn substitute(term: &Term, subst: &Substitution) -> Term {
-    match term {
-        Term::Sort(term_sort) => substitute_sort(term_sort, subst),
-        Term::Variable(term_variable) => substitute_variable(term_variable, subst),
-        Term::Constant(term_constant) => substitute_constant(term_constant, subst),
-        Term::Product(term_product) => substitute_product(term_product, subst),
-        Term::Lambda(term_lambda) => substitute_lambda(term_lambda, subst),
-        Term::Application(term_application) => substitute_application(term_application, subst),
-        Term::LetIn(term_let_in) => substitute_let_in(term_let_in, subst),
-        Term::Match(term_case) => substitute_case(term_case, subst),
-    }
+    let unaffected = |info: TermInfo, _| !info.has_free_vars;
+    map_leaves(term, 0, &unaffected, &|leaf, depth| match leaf {
+        Term::Variable(var) => subst.get(var.id).map(|u| lift(&u, depth)),
+        _ => None,
+    })
 }
 
-fn substitute_sort(term_sort: &TermSort, _subst: &Substitution) -> Term {
-    Term::Sort(term_sort.clone())
+/// Shifts every loose bound variable by `amount`
+pub fn lift(term: &Term, amount: usize) -> Term {
+    lift_from(term, amount, 0)
 }
 
-fn substitute_variable(term_variable: &TermVariable, subst: &Substitution) -> Term {
-    if let Some(u) = subst.get(term_variable.id) {
-        u.as_ref().clone()
-    } else {
-        Term::Variable(term_variable.clone())
+/// Shifts loose bound variables with index >= `cutoff` by `amount`
+pub fn lift_from(term: &Term, amount: usize, cutoff: usize) -> Term {
+    if amount == 0 {
+        return term.clone();
     }
+    let unaffected = |info: TermInfo, depth| info.loose_bound <= depth + cutoff;
+    map_leaves(term, 0, &unaffected, &|leaf, depth| match leaf {
+        Term::Bound(bound) if bound.index >= depth + cutoff => Some(Term::Bound(TermBound {
+            index: bound.index + amount,
+        })),
+        _ => None,
+    })
 }
 
-fn substitute_constant(term_constant: &TermConstant, subst: &Substitution) -> Term {
-    // Check if this constant should be substituted as if it were a variable
-    if let Some(replacement) = subst.get(term_constant.id) {
-        replacement.as_ref().clone()
-    } else {
-        Term::Constant(term_constant.clone())
-    }
+/// Instantiates the innermost binder of `term` (index 0) with `value`.
+/// Used for β-reduction: (λx. t) u → t[0 := u]
+pub fn instantiate(term: &Term, value: &Term) -> Term {
+    instantiate_many(term, std::slice::from_ref(value))
 }
 
-fn substitute_product(term_product: &TermProduct, subst: &Substitution) -> Term {
-    let source = substitute(&term_product.source, subst);
-    let target = substitute(&term_product.target, subst);
-    Term::Product(TermProduct {
-        var: term_product.var,
-        source: Rc::new(source),
-        target: Rc::new(target),
+/// Instantiates the `values.len()` innermost binders of `term`.
+/// `values[0]` corresponds to the outermost of these binders and the last
+/// value to index 0, matching the order of constructor arguments in a match.
+pub fn instantiate_many(term: &Term, values: &[Term]) -> Term {
+    let n = values.len();
+    if n == 0 {
+        return term.clone();
+    }
+    let unaffected = |info: TermInfo, depth| info.loose_bound <= depth;
+    map_leaves(term, 0, &unaffected, &|leaf, depth| match leaf {
+        Term::Bound(bound) if bound.index >= depth => {
+            let k = bound.index - depth;
+            if k < n {
+                Some(lift(&values[n - 1 - k], depth))
+            } else {
+                Some(Term::Bound(TermBound {
+                    index: bound.index - n,
+                }))
+            }
+        }
+        _ => None,
     })
 }
 
-fn substitute_lambda(term_lambda: &TermLambda, subst: &Substitution) -> Term {
-    let source_ty = substitute(&term_lambda.source_ty, subst);
-    let target = substitute(&term_lambda.target, subst);
-    Term::Lambda(TermLambda {
-        var: term_lambda.var,
-        source_ty: Rc::new(source_ty),
-        target: Rc::new(target),
-    })
+/// Turns the free variable `id` into the bound variable of a new binder
+/// placed around `term`. This is the inverse of instantiating with a variable.
+pub fn abstract_variable(term: &Term, id: Id) -> Term {
+    abstract_variables(term, &[id])
 }
 
-fn substitute_application(term_application: &TermApplication, subst: &Substitution) -> Term {
-    let f = substitute(&term_application.f, subst);
-    let args: Vec<_> = term_application
-        .args
-        .iter()
-        .map(|t| substitute(t, subst))
-        .collect();
-    Term::Application(TermApplication {
-        f: Rc::new(f),
-        args,
+/// Turns the free variables `ids` into bound variables of `ids.len()` new
+/// binders around `term`. The last id becomes index 0.
+pub fn abstract_variables(term: &Term, ids: &[Id]) -> Term {
+    let n = ids.len();
+    if n == 0 {
+        return term.clone();
+    }
+    let unaffected = |info: TermInfo, depth| !info.has_free_vars && info.loose_bound <= depth;
+    map_leaves(term, 0, &unaffected, &|leaf, depth| match leaf {
+        Term::Variable(var) => ids.iter().position(|id| *id == var.id).map(|i| {
+            Term::Bound(TermBound {
+                index: depth + n - 1 - i,
+            })
+        }),
+        Term::Bound(bound) if bound.index >= depth => Some(Term::Bound(TermBound {
+            index: bound.index + n,
+        })),
+        _ => None,
     })
 }
 
-fn substitute_let_in(term_let_in: &TermLetIn, subst: &Substitution) -> Term {
-    let term = substitute(&term_let_in.term, subst);
-    let ty = substitute(&term_let_in.ty, subst);
-    let body = substitute(&term_let_in.body, subst);
-    Term::LetIn(TermLetIn {
-        var: term_let_in.var,
-        term: Rc::new(term),
-        ty: Rc::new(ty),
-        body: Rc::new(body),
+/// Turns the occurrences of `leaves` that are free variables or loose bound
+/// variables into bound variables of `leaves.len()` new binders around
+/// `term`, as `abstract_variables` does. The last leaf becomes index 0, and
+/// the other leaves are left alone.
+pub fn abstract_leaves(term: &Term, leaves: &[Term]) -> Term {
+    let n = leaves.len();
+    if n == 0 {
+        return term.clone();
+    }
+    let unaffected = |info: TermInfo, depth| !info.has_free_vars && info.loose_bound <= depth;
+    let bound = |i: usize, depth| {
+        Term::Bound(TermBound {
+            index: depth + n - 1 - i,
+        })
+    };
+    map_leaves(term, 0, &unaffected, &|leaf, depth| match leaf {
+        Term::Variable(var) => leaves
+            .iter()
+            .position(|leaf| matches!(leaf, Term::Variable(v) if v.id == var.id))
+            .map(|i| bound(i, depth)),
+        Term::Bound(b) if b.index >= depth => Some(
+            leaves
+                .iter()
+                .position(|leaf| matches!(leaf, Term::Bound(l) if l.index == b.index - depth))
+                .map_or(Term::Bound(TermBound { index: b.index + n }), |i| {
+                    bound(i, depth)
+                }),
+        ),
+        _ => None,
     })
 }
 
-fn substitute_case(term_case: &TermMatch, subst: &Substitution) -> Term {
-    let scrutinee = substitute(&term_case.scrutinee, subst);
-    let return_type = substitute(&term_case.return_type, subst);
-    let branches: Vec<_> = term_case
-        .branches
-        .iter()
-        .map(|branch| substitute_case_branch(branch, subst))
-        .collect();
-    Term::Match(TermMatch {
-        scrutinee: Rc::new(scrutinee),
-        return_type: Rc::new(return_type),
-        branches,
-    })
+/// Opens the innermost binder of `term` with the free variable `id`
+pub fn open(term: &Term, id: Id) -> Term {
+    instantiate(term, &Term::Variable(TermVariable { id }))
+}
+
+/// Checks whether `term` contains a bound variable pointing outside of it
+pub fn has_loose_bound(term: &Term) -> bool {
+    fn go(term: &Term, depth: usize) -> bool {
+        match term {
+            Term::Bound(bound) => bound.index >= depth,
+            Term::Sort(_) | Term::Variable(_) | Term::Constant(_) | Term::Literal(_) => false,
+            Term::Product(product) => go(&product.source, depth) || go(&product.target, depth + 1),
+            Term::Lambda(lambda) => go(&lambda.source_ty, depth) || go(&lambda.target, depth + 1),
+            Term::Application(app) => go(&app.f, depth) || app.args.iter().any(|a| go(a, depth)),
+            Term::LetIn(let_in) => {
+                go(&let_in.term, depth) || go(&let_in.ty, depth) || go(&let_in.body, depth + 1)
+            }
+            Term::Match(case) => {
+                go(&case.scrutinee, depth)
+                    || go(&case.return_type, depth)
+                    || case
+                        .branches
+                        .iter()
+                        .any(|branch| go(&branch.body, depth + branch.bound_vars.len()))
+            }
+        }
+    }
+    go(term, 0)
+}
+
+/// Checks whether `term` mentions no free variable and no bound variable
+/// pointing outside of `depth` enclosing binders
+pub fn is_closed_under(term: &Term, depth: usize) -> bool {
+    if let Some(info) = intern::info(term) {
+        return info.loose_bound <= depth && !info.has_free_vars;
+    }
+    match term {
+        Term::Bound(bound) => bound.index < depth,
+        Term::Variable(_) => false,
+        Term::Sort(_) | Term::Constant(_) | Term::Literal(_) => true,
+        Term::Product(product) => {
+            is_closed_under(&product.source, depth) && is_closed_under(&product.target, depth + 1)
+        }
+        Term::Lambda(lambda) => {
+            is_closed_under(&lambda.source_ty, depth) && is_closed_under(&lambda.target, depth + 1)
+        }
+        Term::Application(app) => {
+            is_closed_under(&app.f, depth) && app.args.iter().all(|a| is_closed_under(a, depth))
+        }
+        Term::LetIn(let_in) => {
+            is_closed_under(&let_in.term, depth)
+                && is_closed_under(&let_in.ty, depth)
+                && is_closed_under(&let_in.body, depth + 1)
+        }
+        Term::Match(case) => {
+            is_closed_under(&case.scrutinee, depth)
+                && is_closed_under(&case.return_type, depth)
+                && case
+                    .branches
+                    .iter()
+                    .all(|branch| is_closed_under(&branch.body, depth + branch.bound_vars.len()))
+        }
+    }
+}
+
+/// Rebuilds `term`, replacing the leaves (variables, bound variables,
+/// constants and sorts) for which `f` returns a term. `f` also receives the
+/// number of binders crossed so far. Interned subterms for which `unaffected`
+/// holds are shared as they are.
+fn map_leaves<U, F>(term: &Term, depth: usize, unaffected: &U, f: &F) -> Term
+where
+    U: Fn(TermInfo, usize) -> bool,
+    F: Fn(&Term, usize) -> Option<Term>,
+{
+    if let Some(info) = intern::info(term)
+        && unaffected(info, depth)
+    {
+        return term.clone();
+    }
+    let map_child = |child: &Rc<Term>, depth| match intern::info(child) {
+        Some(info) if unaffected(info, depth) => child.clone(),
+        _ => Rc::new(map_leaves(child, depth, unaffected, f)),
+    };
+    match term {
+        Term::Sort(_) | Term::Variable(_) | Term::Bound(_) | Term::Constant(_) => {
+            f(term, depth).unwrap_or_else(|| term.clone())
+        }
+        Term::Literal(_) => term.clone(),
+        Term::Product(product) => Term::Product(TermProduct {
+            var: product.var,
+            source: map_child(&product.source, depth),
+            target: map_child(&product.target, depth + 1),
+        }),
+        Term::Lambda(lambda) => Term::Lambda(TermLambda {
+            var: lambda.var,
+            source_ty: map_child(&lambda.source_ty, depth),
+            target: map_child(&lambda.target, depth + 1),
+        }),
+        Term::Application(app) => Term::Application(TermApplication {
+            f: map_child(&app.f, depth),
+            args: app
+                .args
+                .iter()
+                .map(|t| map_leaves(t, depth, unaffected, f))
+                .collect(),
+        }),
+        Term::LetIn(let_in) => Term::LetIn(TermLetIn {
+            var: let_in.var,
+            term: map_child(&let_in.term, depth),
+            ty: map_child(&let_in.ty, depth),
+            body: map_child(&let_in.body, depth + 1),
+        }),
+        Term::Match(case) => Term::Match(TermMatch {
+            scrutinee: map_child(&case.scrutinee, depth),
+            return_type: map_child(&case.return_type, depth),
+            branches: case
+                .branches
+                .iter()
+                .map(|branch| TermMatchBranch {
+                    constructor_id: branch.constructor_id,
+                    bound_vars: branch.bound_vars.clone(),
+                    body: map_child(&branch.body, depth + branch.bound_vars.len()),
+                })
+                .collect(),
+        }),
+    }
 }
 
-fn substitute_case_branch(branch: &TermMatchBranch, subst: &Substitution) -> TermMatchBranch {
-    // For simplicity, don't handle bound variable capture for now
-    // TODO: Properly handle bound variable capture
-    let body = substitute(&branch.body, subst);
-    TermMatchBranch {
-        constructor_id: branch.constructor_id,
-        bound_vars: branch.bound_vars.clone(),
-        body: Rc::new(body),
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use crate::{
+        id::Id,
+        intern::intern,
+        term::{
+            Sort, Term, TermApplication, TermBound, TermLambda, TermProduct, TermSort, TermVariable,
+        },
+    };
+
+    use super::{
+        Substitution, abstract_variable, has_loose_bound, instantiate, instantiate_many, lift,
+        substitute,
+    };
+
+    fn bound(index: usize) -> Term {
+        Term::Bound(TermBound { index })
+    }
+
+    fn variable(id: Id) -> Term {
+        Term::Variable(TermVariable { id })
+    }
+
+    fn lambda(body: Term) -> Term {
+        Term::Lambda(TermLambda {
+            var: Id::new(),
+            source_ty: Rc::new(Term::Sort(TermSort { sort: Sort::Set })),
+            target: Rc::new(body),
+        })
+    }
+
+    #[test]
+    fn test_lift_only_shifts_loose_indices() {
+        // λ. #0 #1  lifted by 2  →  λ. #0 #3
+        let term = lambda(Term::Application(TermApplication {
+            f: Rc::new(bound(0)),
+            args: vec![bound(1)],
+        }));
+        let expected = lambda(Term::Application(TermApplication {
+            f: Rc::new(bound(0)),
+            args: vec![bound(3)],
+        }));
+        assert_eq!(lift(&term, 2), expected);
+    }
+
+    #[test]
+    fn test_instantiate_lifts_value_under_binders() {
+        // (λ. #1)[#0 := #0]  →  λ. #1
+        let term = lambda(bound(1));
+        assert_eq!(instantiate(&term, &bound(0)), lambda(bound(1)));
+    }
+
+    #[test]
+    fn test_instantiate_many_uses_outermost_first_order() {
+        let a = Id::new();
+        let b = Id::new();
+        let term = Term::Application(TermApplication {
+            f: Rc::new(bound(1)),
+            args: vec![bound(0), bound(2)],
+        });
+        let expected = Term::Application(TermApplication {
+            f: Rc::new(variable(a)),
+            args: vec![variable(b), bound(0)],
+        });
+        assert_eq!(
+            instantiate_many(&term, &[variable(a), variable(b)]),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_abstract_is_inverse_of_instantiate() {
+        let x = Id::new();
+        let body = Term::Application(TermApplication {
+            f: Rc::new(variable(x)),
+            args: vec![lambda(variable(x))],
+        });
+        let closed = abstract_variable(&body, x);
+        assert!(has_loose_bound(&closed));
+        assert_eq!(instantiate(&closed, &variable(x)), body);
+    }
+
+    #[test]
+    fn test_substitution_does_not_capture() {
+        // (λy. x)[x := #0] must not bind the replacement to y
+        let x = Id::new();
+        let term = lambda(variable(x));
+        let mut subst = Substitution::new();
+        subst.add(x, Rc::new(bound(0)));
+        assert_eq!(substitute(&term, &subst), lambda(bound(1)));
+    }
+
+    #[test]
+    fn test_unaffected_interned_subterms_are_shared() {
+        // Π(_ : λ. #0). #1  instantiated with x keeps the closed source
+        let x = Id::new();
+        let closed = intern(&lambda(bound(0)));
+        let term = intern(&Term::Product(TermProduct {
+            var: Id::new(),
+            source: closed.clone(),
+            target: Rc::new(bound(1)),
+        }));
+
+        let Term::Product(result) = instantiate(&term, &variable(x)) else {
+            panic!("expected a product");
+        };
+        assert!(Rc::ptr_eq(&result.source, &closed));
+        assert_eq!(*result.target, variable(x));
+
+        // Lifting only rebuilds the parts with loose bound variables
+        let Term::Product(lifted) = lift(&term, 3) else {
+            panic!("expected a product");
+        };
+        assert!(Rc::ptr_eq(&lifted.source, &closed));
+        assert_eq!(*lifted.target, bound(4));
     }
 }
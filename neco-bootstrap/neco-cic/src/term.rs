@@ -2,7 +2,7 @@ use std::rc::Rc;
 
 use crate::id::*;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Sort {
     Set,
     Prop,
@@ -14,24 +14,36 @@ pub struct TermSort {
     pub sort: Sort,
 }
 
+/// A free variable, looked up by name in the local context
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TermVariable {
     pub id: Id,
 }
 
+/// A bound variable as a de Bruijn index: 0 refers to the innermost binder
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TermBound {
+    pub index: usize,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TermConstant {
     pub id: Id,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Dependent product Πx:A.B
+/// `var` is the binder name and is only kept for printing: `target` refers to
+/// the bound variable as `TermBound { index: 0 }`.
+#[derive(Debug, Clone, Eq)]
 pub struct TermProduct {
     pub var: Id,
     pub source: Rc<Term>,
     pub target: Rc<Term>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Lambda abstraction λx:A.t
+/// As for products, `var` is only a name and `target` uses de Bruijn indices.
+#[derive(Debug, Clone, Eq)]
 pub struct TermLambda {
     pub var: Id,
     pub source_ty: Rc<Term>,
@@ -44,7 +56,8 @@ pub struct TermApplication {
     pub args: Vec<Term>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Local definition `let x : T = t in u`, where `body` binds one variable
+#[derive(Debug, Clone, Eq)]
 pub struct TermLetIn {
     pub var: Id,
     pub term: Rc<Term>,
@@ -62,21 +75,59 @@ pub struct TermMatch {
 }
 
 /// A branch in a match expression
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// `body` is under `bound_vars.len()` binders: the last bound variable is index 0.
+#[derive(Debug, Clone, Eq)]
 pub struct TermMatchBranch {
     pub constructor_id: Id,
     pub bound_vars: Vec<Id>,
     pub body: Rc<Term>,
 }
 
+/// A literal of the primitive type of 64-bit unsigned integers, see
+/// `primitive`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TermLiteral {
+    pub value: u64,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Term {
     Sort(TermSort),
     Variable(TermVariable),
+    Bound(TermBound),
     Constant(TermConstant),
     Product(TermProduct),
     Lambda(TermLambda),
     Application(TermApplication),
     LetIn(TermLetIn),
     Match(TermMatch),
+    Literal(TermLiteral),
+}
+
+// Binder names do not take part in equality, so that α-equivalent terms are equal.
+
+impl PartialEq for TermProduct {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source && self.target == other.target
+    }
+}
+
+impl PartialEq for TermLambda {
+    fn eq(&self, other: &Self) -> bool {
+        self.source_ty == other.source_ty && self.target == other.target
+    }
+}
+
+impl PartialEq for TermLetIn {
+    fn eq(&self, other: &Self) -> bool {
+        self.term == other.term && self.ty == other.ty && self.body == other.body
+    }
+}
+
+impl PartialEq for TermMatchBranch {
+    fn eq(&self, other: &Self) -> bool {
+        self.constructor_id == other.constructor_id
+            && self.bound_vars.len() == other.bound_vars.len()
+            && self.body == other.body
+    }
 }
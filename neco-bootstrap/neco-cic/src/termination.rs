@@ -0,0 +1,361 @@
+use std::collections::HashSet;
+
+use crate::{
+    global_environment::GlobalEnvironment,
+    id::Id,
+    reduction::whnf_with_env,
+    substitution::{instantiate_many, open},
+    term::{Term, TermVariable},
+};
+
+/// Recursive calls of a block of constants that are not all on structurally
+/// smaller arguments
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TerminationError {
+    /// The constants of the block
+    pub constants: Vec<Id>,
+}
+
+impl std::fmt::Display for TerminationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Cannot find decreasing arguments for the recursive calls of {:?}",
+            self.constants
+        )
+    }
+}
+
+/// Checks that the constants of a block, given with their bodies, only call
+/// each other on arguments structurally smaller than a parameter of inductive
+/// type. Returns the index of the decreasing parameter of each constant, or
+/// `None` for a constant that calls no constant of the block.
+///
+/// A variable bound by a branch of a match on the decreasing parameter, or on
+/// such a variable, is smaller than the parameter. Every call to a constant of
+/// the block must have such a variable as its decreasing argument, or such a
+/// variable applied to arguments: a function bound by a branch, like the one
+/// of an accessibility proof, returns smaller values.
+pub fn check_termination(
+    env: &GlobalEnvironment,
+    block: &[(Id, &Term)],
+) -> Result<Vec<Option<usize>>, TerminationError> {
+    let constants: Vec<Id> = block.iter().map(|(id, _)| *id).collect();
+    let error = || TerminationError {
+        constants: constants.clone(),
+    };
+
+    // Open the parameters of each body
+    let mut bodies = Vec::new();
+    let mut candidates = Vec::new();
+    for (_, body) in block {
+        let mut body = (*body).clone();
+        let mut parameters = Vec::new();
+        let mut inductive_parameters = Vec::new();
+        while let Term::Lambda(lambda) = body {
+            if is_inductive_type(env, &lambda.source_ty) {
+                inductive_parameters.push(Some(parameters.len()));
+            }
+            parameters.push(lambda.var);
+            body = open(&lambda.target, lambda.var);
+        }
+        let calls_block = mentions(&body, &constants);
+        candidates.push(if calls_block {
+            inductive_parameters
+        } else {
+            vec![None]
+        });
+        bodies.push((parameters, body));
+    }
+
+    // Try every choice of decreasing parameters, the blocks being small
+    let mut choice = vec![0; block.len()];
+    loop {
+        let decreasing: Vec<Option<usize>> = candidates
+            .iter()
+            .zip(&choice)
+            .map(|(candidates, i)| candidates.get(*i).copied().flatten())
+            .collect();
+        let guarded = candidates.iter().all(|candidates| !candidates.is_empty())
+            && bodies
+                .iter()
+                .zip(&decreasing)
+                .all(|((parameters, body), d)| {
+                    let checker = Guard {
+                        constants: &constants,
+                        decreasing: &decreasing,
+                    };
+                    let mut smaller = HashSet::new();
+                    let parameter = d.map(|d| parameters[d]);
+                    checker.check(body, parameter, &mut smaller)
+                });
+        if guarded {
+            return Ok(decreasing);
+        }
+
+        // Next choice
+        let mut k = 0;
+        loop {
+            if k == choice.len() {
+                return Err(error());
+            }
+            choice[k] += 1;
+            if choice[k] < candidates[k].len() {
+                break;
+            }
+            choice[k] = 0;
+            k += 1;
+        }
+    }
+}
+
+fn is_inductive_type(env: &GlobalEnvironment, ty: &Term) -> bool {
+    let ty = whnf_with_env(ty, env);
+    let head = match &ty {
+        Term::Application(app) => app.f.as_ref(),
+        ty => ty,
+    };
+    matches!(head, Term::Constant(c) if env.inductives.get_inductive(c.id).is_some())
+}
+
+fn mentions(term: &Term, constants: &[Id]) -> bool {
+    match term {
+        Term::Sort(_) | Term::Variable(_) | Term::Bound(_) | Term::Literal(_) => false,
+        Term::Constant(c) => constants.contains(&c.id),
+        Term::Product(product) => {
+            mentions(&product.source, constants) || mentions(&product.target, constants)
+        }
+        Term::Lambda(lambda) => {
+            mentions(&lambda.source_ty, constants) || mentions(&lambda.target, constants)
+        }
+        Term::Application(app) => {
+            mentions(&app.f, constants) || app.args.iter().any(|arg| mentions(arg, constants))
+        }
+        Term::LetIn(let_in) => {
+            mentions(&let_in.term, constants)
+                || mentions(&let_in.ty, constants)
+                || mentions(&let_in.body, constants)
+        }
+        Term::Match(case) => {
+            mentions(&case.scrutinee, constants)
+                || mentions(&case.return_type, constants)
+                || case
+                    .branches
+                    .iter()
+                    .any(|branch| mentions(&branch.body, constants))
+        }
+    }
+}
+
+/// Whether `arg` is a smaller variable, possibly applied to arguments
+fn is_smaller(arg: &Term, smaller: &HashSet<Id>) -> bool {
+    let head = match arg {
+        Term::Application(app) => app.f.as_ref(),
+        arg => arg,
+    };
+    matches!(head, Term::Variable(v) if smaller.contains(&v.id))
+}
+
+struct Guard<'a> {
+    constants: &'a [Id],
+    decreasing: &'a [Option<usize>],
+}
+
+impl Guard<'_> {
+    /// Checks the calls in `term`, where `smaller` are the variables smaller
+    /// than the decreasing `parameter`
+    fn check(&self, term: &Term, parameter: Option<Id>, smaller: &mut HashSet<Id>) -> bool {
+        match term {
+            Term::Sort(_) | Term::Variable(_) | Term::Bound(_) | Term::Literal(_) => true,
+            // A constant of the block that is not called
+            Term::Constant(c) => !self.constants.contains(&c.id),
+            Term::Application(app) => {
+                let args_guarded = app
+                    .args
+                    .iter()
+                    .all(|arg| self.check(arg, parameter, smaller));
+                let call_guarded = match app.f.as_ref() {
+                    Term::Constant(c) => match self.constants.iter().position(|id| *id == c.id) {
+                        Some(index) => self.decreasing[index].is_some_and(|d| {
+                            app.args.get(d).is_some_and(|arg| is_smaller(arg, smaller))
+                        }),
+                        None => true,
+                    },
+                    f => self.check(f, parameter, smaller),
+                };
+                args_guarded && call_guarded
+            }
+            Term::Product(product) => {
+                self.check(&product.source, parameter, smaller)
+                    && self.check_binder(&product.target, product.var, parameter, smaller)
+            }
+            Term::Lambda(lambda) => {
+                self.check(&lambda.source_ty, parameter, smaller)
+                    && self.check_binder(&lambda.target, lambda.var, parameter, smaller)
+            }
+            Term::LetIn(let_in) => {
+                self.check(&let_in.term, parameter, smaller)
+                    && self.check(&let_in.ty, parameter, smaller)
+                    && self.check_binder(&let_in.body, let_in.var, parameter, smaller)
+            }
+            Term::Match(case) => {
+                let on_smaller = match case.scrutinee.as_ref() {
+                    Term::Variable(v) => Some(v.id) == parameter || smaller.contains(&v.id),
+                    _ => false,
+                };
+                self.check(&case.scrutinee, parameter, smaller)
+                    && self.check(&case.return_type, parameter, smaller)
+                    && case.branches.iter().all(|branch| {
+                        let vars: Vec<Term> = branch
+                            .bound_vars
+                            .iter()
+                            .map(|id| Term::Variable(TermVariable { id: *id }))
+                            .collect();
+                        let body = instantiate_many(&branch.body, &vars);
+                        let mut smaller = smaller.clone();
+                        for id in &branch.bound_vars {
+                            if on_smaller {
+                                smaller.insert(*id);
+                            } else {
+                                smaller.remove(id);
+                            }
+                        }
+                        self.check(&body, parameter, &mut smaller)
+                    })
+            }
+        }
+    }
+
+    /// Checks the body of a binder, whose variable is not smaller than the
+    /// parameter even if an outer variable had the same id
+    fn check_binder(
+        &self,
+        body: &Term,
+        var: Id,
+        parameter: Option<Id>,
+        smaller: &mut HashSet<Id>,
+    ) -> bool {
+        let was_smaller = smaller.remove(&var);
+        let guarded = self.check(&open(body, var), parameter, smaller);
+        if was_smaller {
+            smaller.insert(var);
+        }
+        guarded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use crate::{
+        global_environment::GlobalEnvironment,
+        id::Id,
+        substitution::{abstract_variable, abstract_variables},
+        term::{
+            Term, TermApplication, TermConstant, TermLambda, TermMatch, TermMatchBranch,
+            TermVariable,
+        },
+    };
+
+    use super::check_termination;
+
+    fn var(id: Id) -> Term {
+        Term::Variable(TermVariable { id })
+    }
+
+    fn constant(id: Id) -> Term {
+        Term::Constant(TermConstant { id })
+    }
+
+    fn call(f: Id, arg: Term) -> Term {
+        Term::Application(TermApplication {
+            f: Rc::new(constant(f)),
+            args: vec![arg],
+        })
+    }
+
+    /// `fun n : nat => match n { O => O, S p => body p n }`
+    fn recursive_body(nat: Id, zero: Id, succ: Id, body: impl Fn(Id, Id) -> Term) -> Term {
+        let n = Id::new();
+        let p = Id::new();
+        let case = Term::Match(TermMatch {
+            scrutinee: Rc::new(var(n)),
+            return_type: Rc::new(constant(nat)),
+            branches: vec![
+                TermMatchBranch {
+                    constructor_id: zero,
+                    bound_vars: Vec::new(),
+                    body: Rc::new(constant(zero)),
+                },
+                TermMatchBranch {
+                    constructor_id: succ,
+                    bound_vars: vec![p],
+                    body: Rc::new(abstract_variables(&body(p, n), &[p])),
+                },
+            ],
+        });
+        Term::Lambda(TermLambda {
+            var: n,
+            source_ty: Rc::new(constant(nat)),
+            target: Rc::new(abstract_variable(&case, n)),
+        })
+    }
+
+    fn nat_env() -> (GlobalEnvironment, Id, Id, Id) {
+        let (nat, zero, succ) = (Id::new(), Id::new(), Id::new());
+        let mut env = GlobalEnvironment::new();
+        env.inductives.add_nat(nat, zero, succ).unwrap();
+        (env, nat, zero, succ)
+    }
+
+    #[test]
+    fn test_mutual_calls_on_subterms_terminate() {
+        let (env, nat, zero, succ) = nat_env();
+        let (even, odd) = (Id::new(), Id::new());
+        let even_body = recursive_body(nat, zero, succ, |p, _| call(odd, var(p)));
+        let odd_body = recursive_body(nat, zero, succ, |p, _| call(even, var(p)));
+
+        assert_eq!(
+            check_termination(&env, &[(even, &even_body), (odd, &odd_body)]),
+            Ok(vec![Some(0), Some(0)])
+        );
+    }
+
+    #[test]
+    fn test_call_on_the_parameter_itself_is_rejected() {
+        let (env, nat, zero, succ) = nat_env();
+        let (even, odd) = (Id::new(), Id::new());
+        let even_body = recursive_body(nat, zero, succ, |p, _| call(odd, var(p)));
+        let odd_body = recursive_body(nat, zero, succ, |_, n| call(even, var(n)));
+
+        assert!(check_termination(&env, &[(even, &even_body), (odd, &odd_body)]).is_err());
+    }
+
+    #[test]
+    fn test_calls_on_applied_subterms_terminate() {
+        let (env, nat, zero, succ) = nat_env();
+        let f = Id::new();
+        // As `h y r` for the function `h` of an accessibility proof
+        let applied = |f_: Id, arg: Id| {
+            Term::Application(TermApplication {
+                f: Rc::new(var(f_)),
+                args: vec![var(arg)],
+            })
+        };
+        let smaller = recursive_body(nat, zero, succ, |p, n| call(f, applied(p, n)));
+        let not_smaller = recursive_body(nat, zero, succ, |p, n| call(f, applied(n, p)));
+
+        assert_eq!(check_termination(&env, &[(f, &smaller)]), Ok(vec![Some(0)]));
+        assert!(check_termination(&env, &[(f, &not_smaller)]).is_err());
+    }
+
+    #[test]
+    fn test_non_recursive_constant_needs_no_decreasing_parameter() {
+        let (env, nat, zero, succ) = nat_env();
+        let f = Id::new();
+        let body = recursive_body(nat, zero, succ, |p, _| call(succ, var(p)));
+
+        assert_eq!(check_termination(&env, &[(f, &body)]), Ok(vec![None]));
+    }
+}
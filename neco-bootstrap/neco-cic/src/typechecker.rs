@@ -1,53 +1,130 @@
-use std::rc::Rc;
+use std::{collections::HashMap, rc::Rc};
 
 use crate::{
+    conversion::is_convertible,
     global_environment::GlobalEnvironment,
+    id::Id,
+    inductive::{ConstructorDefinition, InductiveDefinition},
     local_context::LocalContext,
-    reduction::{normalize_with_env, whnf_with_env},
+    pretty::Printer,
+    reduction::whnf_with_env,
+    substitution::{abstract_leaves, instantiate, instantiate_many, lift, lift_from},
     term::{
-        Sort, Term, TermApplication, TermConstant, TermLambda, TermLetIn, TermMatch, TermProduct,
-        TermSort, TermVariable,
+        Sort, Term, TermApplication, TermBound, TermConstant, TermLambda, TermLetIn, TermLiteral,
+        TermMatch, TermMatchBranch, TermProduct, TermSort, TermVariable,
     },
 };
 
 /// Type checking errors
+///
+/// Errors refer to the terms and constants involved rather than to strings, so
+/// that a frontend can print them with source names (see `TypeError::describe`).
 #[derive(Debug, Clone, PartialEq)]
 pub enum TypeError {
-    UnboundVariable(String),
-    UnboundConstant(String),
-    NotAFunction(String),
-    TypeMismatch { expected: String, found: String },
-    NotAType(String),
-    InvalidApplication(String),
-    InvalidProductSort(String),
-    UniverseInconsistency(String),
-    UnknownInductive(String),
-    UnknownConstructor(String),
-    InvalidConstructor(String),
-    InvalidCase(String),
+    /// A free variable that is not in the local context
+    UnboundVariable(Id),
+    /// A de Bruijn index pointing outside of the enclosing binders
+    LooseBound(usize),
+    /// A constant that is not in the global environment
+    UnboundConstant(Id),
+    /// `term` is applied to arguments but has type `ty`, which is not a product
+    NotAFunction { term: Rc<Term>, ty: Rc<Term> },
+    /// `term` has type `found` where `expected` is required
+    TypeMismatch {
+        term: Rc<Term>,
+        expected: Rc<Term>,
+        found: Rc<Term>,
+    },
+    /// `term` is used as a type but its type `ty` is not a sort
+    NotAType { term: Rc<Term>, ty: Rc<Term> },
+    /// A match on a scrutinee whose type is not an inductive type
+    NotInductive(Rc<Term>),
+    /// A match whose branches do not cover the constructors one to one
+    BranchCount { expected: usize, found: usize },
+    /// A constructor of a match branch that does not belong to the inductive
+    UnknownConstructor(Id),
+    /// A match branch binding the wrong number of constructor arguments
+    BranchArity {
+        constructor: Id,
+        expected: usize,
+        found: usize,
+    },
+    /// A constructor type that does not take the parameters of its inductive
+    InvalidConstructor(Id),
+    /// A match on a proof of the proposition `inductive` whose return type
+    /// lives in `sort`, which only empty and singleton propositions allow
+    IllegalElimination { inductive: Id, sort: Sort },
+    /// A literal in an environment where the primitive u64 is not declared
+    UndeclaredLiteralType(u64),
 }
 
-impl std::fmt::Display for TypeError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl TypeError {
+    /// Describes the error, printing terms and constants with `names`
+    pub fn describe(&self, names: &HashMap<Id, String>) -> String {
+        let printer = Printer::new(names);
+        let name = |id: &Id| names.get(id).cloned().unwrap_or_else(|| format!("{id:?}"));
         match self {
-            TypeError::UnboundVariable(msg) => write!(f, "Unbound variable: {msg}"),
-            TypeError::UnboundConstant(msg) => write!(f, "Unbound constant: {msg}"),
-            TypeError::NotAFunction(msg) => write!(f, "Not a function: {msg}"),
-            TypeError::TypeMismatch { expected, found } => {
-                write!(f, "Type mismatch: expected {expected}, found {found}")
+            TypeError::UnboundVariable(id) => format!("Unbound variable: {}", name(id)),
+            TypeError::LooseBound(index) => format!("Unbound variable: #{index}"),
+            TypeError::UnboundConstant(id) => format!("Unbound constant: {}", name(id)),
+            TypeError::NotAFunction { term, ty } => format!(
+                "Not a function: `{}` of type `{}`",
+                printer.print(term),
+                printer.print(ty)
+            ),
+            TypeError::TypeMismatch {
+                term,
+                expected,
+                found,
+            } => format!(
+                "Type mismatch: expected `{}`, found `{}` for `{}`",
+                printer.print(expected),
+                printer.print(found),
+                printer.print(term)
+            ),
+            TypeError::NotAType { term, ty } => format!(
+                "Not a type: `{}` of type `{}`",
+                printer.print(term),
+                printer.print(ty)
+            ),
+            TypeError::NotInductive(ty) => format!(
+                "Invalid case expression: `{}` is not an inductive type",
+                printer.print(ty)
+            ),
+            TypeError::BranchCount { expected, found } => {
+                format!("Invalid case expression: {found} branches for {expected} constructors")
+            }
+            TypeError::UnknownConstructor(id) => format!("Unknown constructor: {}", name(id)),
+            TypeError::BranchArity {
+                constructor,
+                expected,
+                found,
+            } => format!(
+                "Invalid case expression: branch for {} binds {found} variables but the constructor takes {expected} arguments",
+                name(constructor)
+            ),
+            TypeError::InvalidConstructor(id) => format!(
+                "Invalid constructor: {} does not take the parameters of its inductive type",
+                name(id)
+            ),
+            TypeError::IllegalElimination { inductive, sort } => format!(
+                "Cannot eliminate a proof of {} into {}: only empty and singleton propositions eliminate into Set and Type",
+                name(inductive),
+                printer.print(&Term::Sort(TermSort { sort: sort.clone() }))
+            ),
+            TypeError::UndeclaredLiteralType(value) => {
+                format!("Literal {value} needs the primitive type u64 to be declared")
             }
-            TypeError::NotAType(msg) => write!(f, "Not a type: {msg}"),
-            TypeError::InvalidApplication(msg) => write!(f, "Invalid application: {msg}"),
-            TypeError::InvalidProductSort(msg) => write!(f, "Invalid product sort: {msg}"),
-            TypeError::UniverseInconsistency(msg) => write!(f, "Universe inconsistency: {msg}"),
-            TypeError::UnknownInductive(msg) => write!(f, "Unknown inductive type: {msg}"),
-            TypeError::UnknownConstructor(msg) => write!(f, "Unknown constructor: {msg}"),
-            TypeError::InvalidConstructor(msg) => write!(f, "Invalid constructor: {msg}"),
-            TypeError::InvalidCase(msg) => write!(f, "Invalid case expression: {msg}"),
         }
     }
 }
 
+impl std::fmt::Display for TypeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.describe(&HashMap::new()))
+    }
+}
+
 impl std::error::Error for TypeError {}
 
 type TypeResult = Result<Rc<Term>, TypeError>;
@@ -58,7 +135,9 @@ pub fn infer_type(ctx: &LocalContext, env: &GlobalEnvironment, term: &Term) -> T
     match term {
         Term::Sort(sort) => infer_sort_type(sort),
         Term::Variable(var) => infer_variable_type(ctx, var),
+        Term::Bound(bound) => infer_bound_type(ctx, bound),
         Term::Constant(const_) => infer_constant_type(env, const_),
+        Term::Literal(literal) => infer_literal_type(env, literal),
         Term::Product(product) => infer_product_type(ctx, env, product),
         Term::Lambda(lambda) => infer_lambda_type(ctx, env, lambda),
         Term::Application(app) => infer_application_type(ctx, env, app),
@@ -68,33 +147,110 @@ pub fn infer_type(ctx: &LocalContext, env: &GlobalEnvironment, term: &Term) -> T
 }
 
 /// Type checking: verify that a term has a given type
+///
+/// Lambdas checked against a product are checked through the binder, so a
+/// mismatch is reported at the subterm that causes it. Other terms have their
+/// type inferred and compared with the expected one.
 pub fn check_type(
     ctx: &LocalContext,
     env: &GlobalEnvironment,
     term: &Term,
     expected_type: &Term,
 ) -> Result<(), TypeError> {
+    if let Term::Lambda(lambda) = term
+        && let Term::Product(product) = whnf_with_env(expected_type, env)
+        && is_convertible(ctx, env, &lambda.source_ty, &product.source)
+    {
+        let source_type = infer_type(ctx, env, &lambda.source_ty)?;
+        ensure_sort_with_env(&lambda.source_ty, &source_type, env)?;
+        let extended_ctx = ctx.pushed(lambda.var, lambda.source_ty.clone());
+        return check_type(&extended_ctx, env, &lambda.target, &product.target);
+    }
+
     let inferred_type = infer_type(ctx, env, term)?;
     if !is_convertible(ctx, env, &inferred_type, expected_type) {
         return Err(TypeError::TypeMismatch {
-            expected: format!("{expected_type:?}"),
-            found: format!("{inferred_type:?}"),
+            term: Rc::new(term.clone()),
+            expected: Rc::new(expected_type.clone()),
+            found: inferred_type,
         });
     }
     Ok(())
 }
 
-/// Checks if two terms are convertible (definitionally equal)
-pub fn is_convertible(
-    _ctx: &LocalContext,
+/// Checks that a match on a value of `inductive` may return a type of sort
+/// `sort`.
+///
+/// Proofs are erased by extraction, so a match on a proof may only compute
+/// data when the proof carries no information: the proposition has no
+/// constructor, or a single one whose arguments are proofs or appear in the
+/// indices of its type, as for `eq_refl x : eq x x`.
+pub fn check_elimination(
     env: &GlobalEnvironment,
-    term1: &Term,
-    term2: &Term,
-) -> bool {
-    // Use normalization with environment to handle δ-reduction
-    let norm1 = normalize_with_env(term1, env);
-    let norm2 = normalize_with_env(term2, env);
-    norm1 == norm2
+    inductive: Id,
+    sort: &Sort,
+) -> Result<(), TypeError> {
+    let error = || TypeError::IllegalElimination {
+        inductive,
+        sort: sort.clone(),
+    };
+    let inductive_def = env
+        .inductives
+        .get_inductive(inductive)
+        .ok_or(TypeError::UnboundConstant(inductive))?;
+    if *sort == Sort::Prop || arity_sort(env, &inductive_def.sort) != Some(Sort::Prop) {
+        return Ok(());
+    }
+    let constructor = match inductive_def.constructors.as_slice() {
+        [] => return Ok(()),
+        [constructor] => constructor,
+        _ => return Err(error()),
+    };
+
+    // Go through the arguments, noting those that are neither proofs nor
+    // parameters by their position
+    let mut ctx = LocalContext::new();
+    let mut ty = constructor.ty.clone();
+    let mut informative = Vec::new();
+    let mut count = 0;
+    while let Term::Product(product) = whnf_with_env(&ty, env) {
+        if count >= inductive_def.parameters.len() {
+            let source_sort = infer_type(&ctx, env, &product.source)
+                .map(|source_type| whnf_with_env(&source_type, env));
+            if !matches!(source_sort, Ok(Term::Sort(TermSort { sort: Sort::Prop }))) {
+                informative.push(count);
+            }
+        }
+        ctx.push(product.var, product.source.clone());
+        ty = product.target.clone();
+        count += 1;
+    }
+
+    // In the type of the constructor, argument `i` is the bound variable
+    // `count - 1 - i`
+    let indices = match ty.as_ref() {
+        Term::Application(app) => &app.args[..],
+        _ => &[],
+    };
+    let determined = |i: &usize| {
+        indices
+            .iter()
+            .any(|index| matches!(index, Term::Bound(b) if b.index == count - 1 - i))
+    };
+    if informative.iter().all(determined) {
+        Ok(())
+    } else {
+        Err(error())
+    }
+}
+
+/// The sort at the end of the arity of an inductive type
+pub(crate) fn arity_sort(env: &GlobalEnvironment, arity: &Term) -> Option<Sort> {
+    match whnf_with_env(arity, env) {
+        Term::Sort(sort) => Some(sort.sort),
+        Term::Product(product) => arity_sort(env, &product.target),
+        _ => None,
+    }
 }
 
 /// Infers the type of a sort
@@ -115,28 +271,27 @@ fn infer_sort_type(sort: &TermSort) -> TypeResult {
 
 /// Infers the type of a variable by looking it up in the context
 fn infer_variable_type(ctx: &LocalContext, var: &TermVariable) -> TypeResult {
-    ctx.lookup(var.id)
-        .ok_or_else(|| TypeError::UnboundVariable(format!("{:?}", var.id)))
+    ctx.lookup(var.id).ok_or(TypeError::UnboundVariable(var.id))
 }
 
-/// Infers the type of a constant by looking it up in the global environment
-fn infer_constant_type(env: &GlobalEnvironment, const_: &TermConstant) -> TypeResult {
-    // First check if it's a regular constant
-    if let Some(const_def) = env.get_constant(const_.id) {
-        return Ok(const_def.ty.clone());
-    }
-
-    // Then check if it's an inductive type
-    if let Some(inductive_def) = env.inductives.get_inductive(const_.id) {
-        return Ok(inductive_def.get_type());
-    }
+/// Infers the type of a bound variable from the binders of the context
+fn infer_bound_type(ctx: &LocalContext, bound: &TermBound) -> TypeResult {
+    ctx.lookup_bound(bound.index)
+        .ok_or(TypeError::LooseBound(bound.index))
+}
 
-    // Finally check if it's a constructor
-    if let Some(constructor_def) = env.inductives.get_constructor(const_.id) {
-        return Ok(constructor_def.ty.clone());
-    }
+/// Infers the type of a constant by looking it up in the global environment
+fn infer_literal_type(env: &GlobalEnvironment, literal: &TermLiteral) -> TypeResult {
+    let id = env
+        .primitives
+        .u64
+        .ok_or(TypeError::UndeclaredLiteralType(literal.value))?;
+    Ok(Rc::new(Term::Constant(TermConstant { id })))
+}
 
-    Err(TypeError::UnboundConstant(format!("{:?}", const_.id)))
+fn infer_constant_type(env: &GlobalEnvironment, const_: &TermConstant) -> TypeResult {
+    env.constant_type(const_.id)
+        .ok_or(TypeError::UnboundConstant(const_.id))
 }
 
 /// Infers the type of a product (Π-type)
@@ -150,12 +305,12 @@ fn infer_product_type(
 ) -> TypeResult {
     // Check that the source type is a valid type
     let source_type = infer_type(ctx, env, &product.source)?;
-    let source_sort = ensure_sort_with_env(&source_type, env)?;
+    let source_sort = ensure_sort_with_env(&product.source, &source_type, env)?;
 
     // Check the target in the extended context
-    let extended_ctx = ctx.with(product.var, product.source.clone());
+    let extended_ctx = ctx.pushed(product.var, product.source.clone());
     let target_type = infer_type(&extended_ctx, env, &product.target)?;
-    let target_sort = ensure_sort_with_env(&target_type, env)?;
+    let target_sort = ensure_sort_with_env(&product.target, &target_type, env)?;
 
     // Apply sort rule
     let result_sort = sort_rule(&source_sort, &target_sort)?;
@@ -173,10 +328,10 @@ fn infer_lambda_type(
 ) -> TypeResult {
     // Check that the source type is valid
     let source_type = infer_type(ctx, env, &lambda.source_ty)?;
-    ensure_sort_with_env(&source_type, env)?;
+    ensure_sort_with_env(&lambda.source_ty, &source_type, env)?;
 
     // Infer the type of the body in the extended context
-    let extended_ctx = ctx.with(lambda.var, lambda.source_ty.clone());
+    let extended_ctx = ctx.pushed(lambda.var, lambda.source_ty.clone());
     let body_type = infer_type(&extended_ctx, env, &lambda.target)?;
 
     // The type of the lambda is a product type
@@ -216,15 +371,13 @@ fn infer_application_type(
                 check_type(ctx, env, arg, &product.source)?;
 
                 // Substitute the argument in the return type
-                let mut subst = crate::substitution::Substitution::new();
-                subst.add(product.var, Rc::new(arg.clone()));
-                let substituted_target = crate::substitution::substitute(&product.target, &subst);
-                result_type = Rc::new(substituted_target);
+                result_type = Rc::new(instantiate(&product.target, arg));
             }
             _ => {
-                return Err(TypeError::NotAFunction(format!(
-                    "Expected function type, got {fun_whnf:?}"
-                )));
+                return Err(TypeError::NotAFunction {
+                    term: Rc::new(app.f.as_ref().clone()),
+                    ty: Rc::new(fun_whnf),
+                });
             }
         }
     }
@@ -246,30 +399,35 @@ fn infer_let_in_type(
 
     // Check that the type is valid
     let ty_type = infer_type(ctx, env, &let_in.ty)?;
-    ensure_sort_with_env(&ty_type, env)?;
+    ensure_sort_with_env(&let_in.ty, &ty_type, env)?;
 
     // Infer the type of the body in the extended context
-    let extended_ctx = ctx.with(let_in.var, let_in.ty.clone());
+    let extended_ctx = ctx.pushed(let_in.var, let_in.ty.clone());
     let body_type = infer_type(&extended_ctx, env, &let_in.body)?;
 
     // Substitute the term in the body type
-    let mut subst = crate::substitution::Substitution::new();
-    subst.add(let_in.var, let_in.term.clone());
-    Ok(Rc::new(crate::substitution::substitute(&body_type, &subst)))
+    Ok(Rc::new(instantiate(&body_type, &let_in.term)))
 }
 
-/// Ensures that a term is a sort and returns it (with environment)
-fn ensure_sort_with_env(term: &Term, env: &GlobalEnvironment) -> Result<Sort, TypeError> {
-    let whnf_term = whnf_with_env(term, env);
-    match &whnf_term {
+/// Ensures that `ty`, the type of `term`, is a sort and returns it (with environment)
+pub(crate) fn ensure_sort_with_env(
+    term: &Term,
+    ty: &Rc<Term>,
+    env: &GlobalEnvironment,
+) -> Result<Sort, TypeError> {
+    let whnf_ty = whnf_with_env(ty, env);
+    match &whnf_ty {
         Term::Sort(sort) => Ok(sort.sort.clone()),
-        _ => Err(TypeError::NotAType(format!("{term:?}"))),
+        _ => Err(TypeError::NotAType {
+            term: Rc::new(term.clone()),
+            ty: ty.clone(),
+        }),
     }
 }
 
 /// Sort rule for CIC
 /// This implements the allowed product formations
-fn sort_rule(s1: &Sort, s2: &Sort) -> Result<Sort, TypeError> {
+pub fn sort_rule(s1: &Sort, s2: &Sort) -> Result<Sort, TypeError> {
     match (s1, s2) {
         // (Set, Set) → Set
         (Sort::Set, Sort::Set) => Ok(Sort::Set),
@@ -294,78 +452,179 @@ fn sort_rule(s1: &Sort, s2: &Sort) -> Result<Sort, TypeError> {
 fn infer_case_type(ctx: &LocalContext, env: &GlobalEnvironment, case: &TermMatch) -> TypeResult {
     // Infer the type of the scrutinee
     let scrutinee_type = infer_type(ctx, env, &case.scrutinee)?;
-
-    // The scrutinee should have an inductive type
-    let inductive_id = match scrutinee_type.as_ref() {
-        Term::Constant(const_) => {
-            // Check that this is indeed an inductive type
-            if env.inductives.get_inductive(const_.id).is_some() {
-                const_.id
-            } else {
-                return Err(TypeError::InvalidCase(format!(
-                    "Case scrutinee type {:?} is not an inductive type",
-                    const_.id
-                )));
-            }
-        }
-        _ => {
-            return Err(TypeError::InvalidCase(format!(
-                "Case scrutinee must have inductive type, got {scrutinee_type:?}"
-            )));
-        }
+    let scrutinee_type = whnf_with_env(&scrutinee_type, env);
+    let (scrutinee_head, scrutinee_args) = match &scrutinee_type {
+        Term::Application(app) => (app.f.as_ref(), app.args.as_slice()),
+        _ => (&scrutinee_type, &[][..]),
     };
 
-    let inductive_def = env.inductives.get_inductive(inductive_id).unwrap();
+    // The scrutinee should have an inductive type
+    let inductive_def = match scrutinee_head {
+        Term::Constant(const_) => env.inductives.get_inductive(const_.id),
+        _ => None,
+    }
+    .ok_or_else(|| TypeError::NotInductive(Rc::new(scrutinee_type.clone())))?;
 
     // Check that we have the right number of branches
     if case.branches.len() != inductive_def.constructor_count() {
-        return Err(TypeError::InvalidCase(format!(
-            "Case has {} branches but inductive type has {} constructors",
-            case.branches.len(),
-            inductive_def.constructor_count()
-        )));
+        return Err(TypeError::BranchCount {
+            expected: inductive_def.constructor_count(),
+            found: case.branches.len(),
+        });
     }
 
+    // Proofs only eliminate into Prop, unless they carry no information
+    let return_sort = infer_type(ctx, env, &case.return_type)?;
+    let return_sort = ensure_sort_with_env(&case.return_type, &return_sort, env)?;
+    check_elimination(env, inductive_def.name, &return_sort)?;
+
+    // The return type as a function of the indices and of the scrutinee,
+    // where they are variables, to instantiate for each branch
+    let parameter_count = inductive_def.parameters.len();
+    let mut refined = scrutinee_args
+        .get(parameter_count..)
+        .unwrap_or_default()
+        .to_vec();
+    refined.push(case.scrutinee.as_ref().clone());
+    let motive = abstract_leaves(&case.return_type, &refined);
+    let parameters_of_scrutinee = scrutinee_args
+        .get(..parameter_count)
+        .ok_or_else(|| TypeError::NotInductive(Rc::new(scrutinee_type.clone())))?;
+
     // Type check each branch
     for branch in &case.branches {
         let constructor_def = inductive_def
             .find_constructor(branch.constructor_id)
-            .ok_or_else(|| TypeError::UnknownConstructor(format!("{:?}", branch.constructor_id)))?;
+            .ok_or(TypeError::UnknownConstructor(branch.constructor_id))?;
 
         // Check that the number of bound variables matches the constructor arity
         if branch.bound_vars.len() != constructor_def.arity {
-            return Err(TypeError::InvalidCase(format!(
-                "Branch for constructor {:?} binds {} variables but constructor has arity {}",
-                branch.constructor_id,
-                branch.bound_vars.len(),
-                constructor_def.arity
-            )));
+            return Err(TypeError::BranchArity {
+                constructor: branch.constructor_id,
+                expected: constructor_def.arity,
+                found: branch.bound_vars.len(),
+            });
         }
 
-        // TODO: Add the bound variables to the context with their proper types
-        // and type check the branch body
-        let _branch_type = infer_type(ctx, env, &branch.body)?;
-
-        // TODO: Check that all branches have compatible types with the return type
+        let branch_ctx = branch_context(
+            ctx,
+            env,
+            inductive_def,
+            constructor_def,
+            scrutinee_args,
+            branch,
+        )?;
+
+        // The branch returns the return type for the constructor applied to
+        // the bound variables
+        let count = branch.bound_vars.len();
+        let parameters = parameters_of_scrutinee
+            .iter()
+            .map(|param| lift(param, count));
+        let fields = (0..count)
+            .rev()
+            .map(|index| Term::Bound(TermBound { index }));
+        let value = Term::Application(TermApplication {
+            f: Rc::new(Term::Constant(TermConstant {
+                id: branch.constructor_id,
+            })),
+            args: parameters.chain(fields).collect(),
+        });
+        let mut values = constructor_indices(env, constructor_def, parameters_of_scrutinee, count)?;
+        values.push(value);
+        if values.len() != refined.len() {
+            return Err(TypeError::InvalidConstructor(branch.constructor_id));
+        }
+        let expected = instantiate_many(&lift_from(&motive, count, values.len()), &values);
+        check_type(&branch_ctx, env, &branch.body, &expected)?;
     }
 
-    // For now, return the declared return type
-    // TODO: Apply proper substitutions
     Ok(case.return_type.clone())
 }
 
+/// The indices of the type of a constructor applied to `parameters` and to
+/// `count` bound variables
+fn constructor_indices(
+    env: &GlobalEnvironment,
+    constructor_def: &ConstructorDefinition,
+    parameters: &[Term],
+    count: usize,
+) -> Result<Vec<Term>, TypeError> {
+    let invalid = || TypeError::InvalidConstructor(constructor_def.name);
+    let mut ty = constructor_def.ty.as_ref().clone();
+    for param in parameters {
+        let Term::Product(product) = whnf_with_env(&ty, env) else {
+            return Err(invalid());
+        };
+        ty = instantiate(&product.target, param);
+    }
+    for _ in 0..count {
+        let Term::Product(product) = whnf_with_env(&ty, env) else {
+            return Err(invalid());
+        };
+        ty = product.target.as_ref().clone();
+    }
+    Ok(match whnf_with_env(&ty, env) {
+        Term::Application(app) => app
+            .args
+            .get(parameters.len()..)
+            .ok_or_else(invalid)?
+            .to_vec(),
+        _ => Vec::new(),
+    })
+}
+
+/// Extends `ctx` with the bound variables of `branch`, typed by the arguments
+/// of its constructor, the parameters being those of the scrutinee type
+pub(crate) fn branch_context(
+    ctx: &LocalContext,
+    env: &GlobalEnvironment,
+    inductive_def: &InductiveDefinition,
+    constructor_def: &ConstructorDefinition,
+    scrutinee_args: &[Term],
+    branch: &TermMatchBranch,
+) -> Result<LocalContext, TypeError> {
+    let mut branch_ctx = ctx.clone();
+    let mut constructor_ty = constructor_def.ty.as_ref().clone();
+    let parameter_count = inductive_def.parameters.len();
+    for param in scrutinee_args.iter().take(parameter_count) {
+        let Term::Product(product) = whnf_with_env(&constructor_ty, env) else {
+            return Err(TypeError::InvalidConstructor(branch.constructor_id));
+        };
+        constructor_ty = instantiate(&product.target, param);
+    }
+    for var in &branch.bound_vars {
+        let Term::Product(product) = whnf_with_env(&constructor_ty, env) else {
+            return Err(TypeError::BranchArity {
+                constructor: branch.constructor_id,
+                expected: constructor_def.arity,
+                found: branch.bound_vars.len(),
+            });
+        };
+        branch_ctx.push(*var, product.source.clone());
+        constructor_ty = product.target.as_ref().clone();
+    }
+
+    Ok(branch_ctx)
+}
+
 #[cfg(test)]
 mod tests {
-    use std::rc::Rc;
+    use std::{collections::HashMap, rc::Rc};
 
     use crate::{
+        conversion::is_convertible,
         global_environment::GlobalEnvironment,
         id::Id,
+        inductive::{ConstructorDefinition, InductiveDefinition},
         local_context::LocalContext,
-        term::{Sort, Term, TermApplication, TermLambda, TermProduct, TermSort, TermVariable},
+        term::{
+            Sort, Term, TermApplication, TermBound, TermConstant, TermLambda, TermMatch,
+            TermMatchBranch, TermProduct, TermSort, TermVariable,
+        },
     };
 
-    use super::{TypeError, check_type, infer_type};
+    use super::{TypeError, check_elimination, check_type, infer_type};
 
     #[test]
     fn test_set_has_type_type0() {
@@ -451,7 +710,7 @@ mod tests {
         let lambda = Term::Lambda(TermLambda {
             var: x,
             source_ty: Rc::new(set.clone()),
-            target: Rc::new(Term::Variable(TermVariable { id: x })),
+            target: Rc::new(Term::Bound(TermBound { index: 0 })),
         });
 
         let ty = infer_type(&ctx, &env, &lambda).unwrap();
@@ -475,7 +734,7 @@ mod tests {
         let lambda = Term::Lambda(TermLambda {
             var: x,
             source_ty: Rc::new(set.clone()),
-            target: Rc::new(Term::Variable(TermVariable { id: x })),
+            target: Rc::new(Term::Bound(TermBound { index: 0 })),
         });
 
         // Apply to a variable of type Set
@@ -524,7 +783,7 @@ mod tests {
         let id_fun = Term::Lambda(TermLambda {
             var: x,
             source_ty: Rc::new(set.clone()),
-            target: Rc::new(Term::Variable(TermVariable { id: x })),
+            target: Rc::new(Term::Bound(TermBound { index: 0 })),
         });
 
         // Expected type: Set → Set
@@ -548,13 +807,178 @@ mod tests {
         let id_fun = Term::Lambda(TermLambda {
             var: x,
             source_ty: Rc::new(set.clone()),
-            target: Rc::new(Term::Variable(TermVariable { id: x })),
+            target: Rc::new(Term::Bound(TermBound { index: 0 })),
         });
 
         // Wrong type should fail
         assert!(check_type(&ctx, &env, &id_fun, &set).is_err());
     }
 
+    #[test]
+    fn test_lambda_is_checked_through_its_binder() {
+        let ctx = LocalContext::new();
+        let env = GlobalEnvironment::new();
+
+        // λx:Set. x against Set -> Prop fails at the body
+        let set = Rc::new(Term::Sort(TermSort { sort: Sort::Set }));
+        let prop = Rc::new(Term::Sort(TermSort { sort: Sort::Prop }));
+        let body = Rc::new(Term::Bound(TermBound { index: 0 }));
+        let id_fun = Term::Lambda(TermLambda {
+            var: Id::new(),
+            source_ty: set.clone(),
+            target: body.clone(),
+        });
+        let expected_type = Term::Product(TermProduct {
+            var: Id::new(),
+            source: set.clone(),
+            target: prop.clone(),
+        });
+
+        assert_eq!(
+            check_type(&ctx, &env, &id_fun, &expected_type),
+            Err(TypeError::TypeMismatch {
+                term: body,
+                expected: prop,
+                found: set,
+            })
+        );
+    }
+
+    #[test]
+    fn test_type_mismatch_is_described_with_names() {
+        let ctx = LocalContext::new();
+        let env = GlobalEnvironment::new();
+        let x = Id::new();
+
+        let set = Term::Sort(TermSort { sort: Sort::Set });
+        let id_fun = Term::Lambda(TermLambda {
+            var: x,
+            source_ty: Rc::new(set.clone()),
+            target: Rc::new(Term::Bound(TermBound { index: 0 })),
+        });
+
+        let error = check_type(&ctx, &env, &id_fun, &set).unwrap_err();
+        assert!(matches!(error, TypeError::TypeMismatch { .. }));
+        let names = HashMap::from([(x, "x".to_string())]);
+        assert_eq!(
+            error.describe(&names),
+            "Type mismatch: expected `Set`, found `Set -> Set` for `fun (x : Set) => x`"
+        );
+    }
+
+    #[test]
+    fn test_match_branch_binds_constructor_arguments() {
+        let ctx = LocalContext::new();
+        let mut env = GlobalEnvironment::new();
+        let nat_id = Id::new();
+        let zero_id = Id::new();
+        let succ_id = Id::new();
+        env.inductives.add_nat(nat_id, zero_id, succ_id).unwrap();
+
+        // Create predecessor: λn:nat. match n { O => O, S p => p }
+        let nat = Rc::new(Term::Constant(TermConstant { id: nat_id }));
+        let pred = Term::Lambda(TermLambda {
+            var: Id::new(),
+            source_ty: nat.clone(),
+            target: Rc::new(Term::Match(TermMatch {
+                scrutinee: Rc::new(Term::Bound(TermBound { index: 0 })),
+                return_type: nat.clone(),
+                branches: vec![
+                    TermMatchBranch {
+                        constructor_id: zero_id,
+                        bound_vars: vec![],
+                        body: Rc::new(Term::Constant(TermConstant { id: zero_id })),
+                    },
+                    TermMatchBranch {
+                        constructor_id: succ_id,
+                        bound_vars: vec![Id::new()],
+                        body: Rc::new(Term::Bound(TermBound { index: 0 })),
+                    },
+                ],
+            })),
+        });
+
+        let expected_type = Term::Product(TermProduct {
+            var: Id::new(),
+            source: nat.clone(),
+            target: nat,
+        });
+        assert!(check_type(&ctx, &env, &pred, &expected_type).is_ok());
+    }
+
+    #[test]
+    fn test_match_branches_are_checked_against_the_refined_return_type() {
+        // P : bool -> Prop, pt : P true, pf : P false
+        let ctx = LocalContext::new();
+        let mut env = GlobalEnvironment::new();
+        let (bool_id, true_id, false_id) = (Id::new(), Id::new(), Id::new());
+        env.inductives.add_bool(bool_id, true_id, false_id).unwrap();
+        let (p_id, pt_id, pf_id) = (Id::new(), Id::new(), Id::new());
+        let p_of = |b: Id| {
+            Rc::new(Term::Application(TermApplication {
+                f: constant(p_id),
+                args: vec![constant(b).as_ref().clone()],
+            }))
+        };
+        env.inductives
+            .add_inductive(InductiveDefinition::new(
+                p_id,
+                vec![],
+                Rc::new(Term::Product(TermProduct {
+                    var: Id::new(),
+                    source: constant(bool_id),
+                    target: prop(),
+                })),
+                vec![
+                    ConstructorDefinition {
+                        name: pt_id,
+                        ty: p_of(true_id),
+                        arity: 0,
+                    },
+                    ConstructorDefinition {
+                        name: pf_id,
+                        ty: p_of(false_id),
+                        arity: 0,
+                    },
+                ],
+            ))
+            .unwrap();
+
+        // λb:bool. match b return P b { true => on_true, false => on_false }
+        let proof = |on_true: Id, on_false: Id| {
+            Term::Lambda(TermLambda {
+                var: Id::new(),
+                source_ty: constant(bool_id),
+                target: Rc::new(Term::Match(TermMatch {
+                    scrutinee: Rc::new(Term::Bound(TermBound { index: 0 })),
+                    return_type: Rc::new(Term::Application(TermApplication {
+                        f: constant(p_id),
+                        args: vec![Term::Bound(TermBound { index: 0 })],
+                    })),
+                    branches: vec![
+                        TermMatchBranch {
+                            constructor_id: true_id,
+                            bound_vars: vec![],
+                            body: constant(on_true),
+                        },
+                        TermMatchBranch {
+                            constructor_id: false_id,
+                            bound_vars: vec![],
+                            body: constant(on_false),
+                        },
+                    ],
+                })),
+            })
+        };
+
+        assert!(infer_type(&ctx, &env, &proof(pt_id, pf_id)).is_ok());
+        let result = infer_type(&ctx, &env, &proof(pf_id, pt_id));
+        assert!(
+            matches!(result, Err(TypeError::TypeMismatch { .. })),
+            "{result:?}"
+        );
+    }
+
     #[test]
     fn test_dependent_product_type_inference() {
         let ctx = LocalContext::new();
@@ -566,12 +990,10 @@ mod tests {
         let type0 = Term::Sort(TermSort {
             sort: Sort::Type(0),
         });
-        let var_a = Term::Variable(TermVariable { id: a });
-
         let inner_product = Term::Product(TermProduct {
             var: x,
-            source: Rc::new(var_a.clone()),
-            target: Rc::new(var_a),
+            source: Rc::new(Term::Bound(TermBound { index: 0 })),
+            target: Rc::new(Term::Bound(TermBound { index: 1 })),
         });
 
         let outer_product = Term::Product(TermProduct {
@@ -588,4 +1010,205 @@ mod tests {
             })
         );
     }
+
+    fn prop() -> Rc<Term> {
+        Rc::new(Term::Sort(TermSort { sort: Sort::Prop }))
+    }
+
+    fn constant(id: Id) -> Rc<Term> {
+        Rc::new(Term::Constant(TermConstant { id }))
+    }
+
+    /// A proposition `id : Prop` with constructors of the types built by
+    /// `constructors` from `id`
+    fn add_proposition(
+        env: &mut GlobalEnvironment,
+        constructors: impl Fn(Id) -> Vec<Rc<Term>>,
+    ) -> Id {
+        let id = Id::new();
+        let constructors: Vec<_> = constructors(id)
+            .into_iter()
+            .map(|ty| {
+                let mut arity = 0;
+                let mut target = ty.as_ref();
+                while let Term::Product(product) = target {
+                    arity += 1;
+                    target = &product.target;
+                }
+                ConstructorDefinition {
+                    name: Id::new(),
+                    ty,
+                    arity,
+                }
+            })
+            .collect();
+        env.inductives
+            .add_inductive(InductiveDefinition::new(id, vec![], prop(), constructors))
+            .unwrap();
+        id
+    }
+
+    /// `match h return nat { c_1 args => O, .. }` with `h` a variable of type `ty`
+    fn match_into_nat(
+        env: &GlobalEnvironment,
+        ty: Id,
+        nat_id: Id,
+        zero_id: Id,
+    ) -> (LocalContext, Term) {
+        let mut ctx = LocalContext::new();
+        let h = Id::new();
+        ctx.extend(h, constant(ty)).unwrap();
+        let branches = env
+            .inductives
+            .get_inductive(ty)
+            .unwrap()
+            .constructors
+            .iter()
+            .map(|constructor| TermMatchBranch {
+                constructor_id: constructor.name,
+                bound_vars: (0..constructor.arity).map(|_| Id::new()).collect(),
+                body: constant(zero_id),
+            })
+            .collect();
+        let case = Term::Match(TermMatch {
+            scrutinee: Rc::new(Term::Variable(TermVariable { id: h })),
+            return_type: constant(nat_id),
+            branches,
+        });
+        (ctx, case)
+    }
+
+    #[test]
+    fn test_informative_propositions_do_not_eliminate_into_set() {
+        let mut env = GlobalEnvironment::new();
+        let (nat_id, zero_id, succ_id) = (Id::new(), Id::new(), Id::new());
+        env.inductives.add_nat(nat_id, zero_id, succ_id).unwrap();
+
+        // Two constructors
+        let or_id = add_proposition(&mut env, |id| vec![constant(id), constant(id)]);
+        // One constructor hiding a number
+        let ex_id = add_proposition(&mut env, |id| {
+            vec![Rc::new(Term::Product(TermProduct {
+                var: Id::new(),
+                source: constant(nat_id),
+                target: constant(id),
+            }))]
+        });
+        // No constructor
+        let false_id = add_proposition(&mut env, |_| vec![]);
+
+        for (id, allowed) in [(or_id, false), (ex_id, false), (false_id, true)] {
+            let (ctx, case) = match_into_nat(&env, id, nat_id, zero_id);
+            let result = infer_type(&ctx, &env, &case);
+            if allowed {
+                assert!(result.is_ok(), "{result:?}");
+            } else {
+                assert_eq!(
+                    result,
+                    Err(TypeError::IllegalElimination {
+                        inductive: id,
+                        sort: Sort::Set
+                    })
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_equality_eliminates_into_set() {
+        // eq : nat -> nat -> Prop, refl : (n : nat) -> eq n n
+        let mut env = GlobalEnvironment::new();
+        let (nat_id, zero_id, succ_id) = (Id::new(), Id::new(), Id::new());
+        env.inductives.add_nat(nat_id, zero_id, succ_id).unwrap();
+        let eq_id = Id::new();
+        let refl_id = Id::new();
+        let arrow = |source: Rc<Term>, target: Rc<Term>| {
+            Rc::new(Term::Product(TermProduct {
+                var: Id::new(),
+                source,
+                target,
+            }))
+        };
+        let refl_ty = arrow(
+            constant(nat_id),
+            Rc::new(Term::Application(TermApplication {
+                f: constant(eq_id),
+                args: vec![
+                    Term::Bound(TermBound { index: 0 }),
+                    Term::Bound(TermBound { index: 0 }),
+                ],
+            })),
+        );
+        env.inductives
+            .add_inductive(InductiveDefinition::new(
+                eq_id,
+                vec![],
+                arrow(constant(nat_id), arrow(constant(nat_id), prop())),
+                vec![ConstructorDefinition {
+                    name: refl_id,
+                    ty: refl_ty,
+                    arity: 1,
+                }],
+            ))
+            .unwrap();
+
+        assert_eq!(check_elimination(&env, eq_id, &Sort::Set), Ok(()));
+        assert_eq!(check_elimination(&env, eq_id, &Sort::Type(0)), Ok(()));
+    }
+
+    #[test]
+    fn test_proofs_of_the_same_proposition_are_convertible() {
+        let mut env = GlobalEnvironment::new();
+        let (nat_id, zero_id, succ_id) = (Id::new(), Id::new(), Id::new());
+        env.inductives.add_nat(nat_id, zero_id, succ_id).unwrap();
+        let p = Id::new();
+        env.add_axiom(p, prop()).unwrap();
+        let (proof1, proof2, n1, n2, f) = (Id::new(), Id::new(), Id::new(), Id::new(), Id::new());
+        env.add_axiom(proof1, constant(p)).unwrap();
+        env.add_axiom(proof2, constant(p)).unwrap();
+        env.add_axiom(n1, constant(nat_id)).unwrap();
+        env.add_axiom(n2, constant(nat_id)).unwrap();
+        // f : nat -> p -> nat
+        let f_ty = Term::Product(TermProduct {
+            var: Id::new(),
+            source: constant(nat_id),
+            target: Rc::new(Term::Product(TermProduct {
+                var: Id::new(),
+                source: constant(p),
+                target: constant(nat_id),
+            })),
+        });
+        env.add_axiom(f, Rc::new(f_ty)).unwrap();
+
+        let ctx = LocalContext::new();
+        assert!(is_convertible(
+            &ctx,
+            &env,
+            &constant(proof1),
+            &constant(proof2)
+        ));
+        assert!(!is_convertible(&ctx, &env, &constant(n1), &constant(n2)));
+
+        let call = |n: Id, proof: Id| {
+            Term::Application(TermApplication {
+                f: constant(f),
+                args: vec![
+                    constant(n).as_ref().clone(),
+                    constant(proof).as_ref().clone(),
+                ],
+            })
+        };
+        assert!(is_convertible(
+            &ctx,
+            &env,
+            &call(n1, proof1),
+            &call(n1, proof2)
+        ));
+        assert!(!is_convertible(
+            &ctx,
+            &env,
+            &call(n1, proof1),
+            &call(n2, proof2)
+        ));
+    }
 }
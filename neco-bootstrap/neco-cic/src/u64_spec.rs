@@ -0,0 +1,334 @@
+use std::{collections::HashMap, rc::Rc};
+
+use crate::{
+    global_environment::GlobalEnvironment,
+    id::{Id, IdGenerator},
+    primitive::Operation,
+    substitution::abstract_variables,
+    term::{Term, TermLiteral, TermMatch, TermMatchBranch},
+    well_founded::{apply, constant, lambda, pi, prop, var},
+};
+
+// The operations on u64 compute on literals only. Their specification states
+// what they compute on any integer through `of_nat n`, the natural number `n`
+// modulo 2^64, so that proofs about them go by induction on `nat`. The axioms
+// state Leibniz equality, `a = b` being `(P : u64 -> Prop) -> P a -> P b`, so
+// that they hold for whatever equality type the user declares.
+
+/// The natural numbers, with addition and multiplication by recursion on their
+/// first argument
+#[derive(Debug, Clone, Copy)]
+pub struct Nat {
+    pub nat: Id,
+    pub zero: Id,
+    pub succ: Id,
+    pub add: Id,
+    pub mul: Id,
+}
+
+/// Adds the natural numbers:
+///
+/// ```text
+/// nat : Set := O : nat | S : nat -> nat
+/// add := fun n m => match n { O => m, S p => S (add p m) }
+/// mul := fun n m => match n { O => O, S p => add m (mul p m) }
+/// ```
+pub fn add_nat(
+    env: &mut GlobalEnvironment,
+    nat: Nat,
+    id_gen: &mut IdGenerator,
+) -> Result<(), String> {
+    env.inductives.add_nat(nat.nat, nat.zero, nat.succ)?;
+    let [n, m, p] = [(); 3].map(|_| id_gen.generate_id());
+    let succ = apply(
+        constant(nat.succ),
+        vec![apply(constant(nat.add), vec![var(p), var(m)])],
+    );
+    let add = by_recursion(nat, [n, m, p], var(m), succ);
+    env.add_definition(nat.add, Rc::new(binary(id_gen, nat.nat)), Rc::new(add))?;
+
+    let [n, m, p] = [(); 3].map(|_| id_gen.generate_id());
+    let succ = apply(
+        constant(nat.add),
+        vec![var(m), apply(constant(nat.mul), vec![var(p), var(m)])],
+    );
+    let mul = by_recursion(nat, [n, m, p], constant(nat.zero), succ);
+    env.add_definition(nat.mul, Rc::new(binary(id_gen, nat.nat)), Rc::new(mul))
+}
+
+/// `fun n m => match n { O => zero, S p => succ }`
+fn by_recursion(nat: Nat, [n, m, p]: [Id; 3], zero: Term, succ: Term) -> Term {
+    let case = Term::Match(TermMatch {
+        scrutinee: Rc::new(var(n)),
+        return_type: Rc::new(constant(nat.nat)),
+        branches: vec![
+            TermMatchBranch {
+                constructor_id: nat.zero,
+                bound_vars: Vec::new(),
+                body: Rc::new(zero),
+            },
+            TermMatchBranch {
+                constructor_id: nat.succ,
+                bound_vars: vec![p],
+                body: Rc::new(abstract_variables(&succ, &[p])),
+            },
+        ],
+    });
+    lambda(n, constant(nat.nat), lambda(m, constant(nat.nat), case))
+}
+
+/// The declarations the specification of the operations is stated with
+#[derive(Debug, Clone)]
+pub struct U64Spec {
+    pub u64: Id,
+    pub nat: Option<Nat>,
+    /// `of_nat : nat -> u64`, added by `add_of_nat`
+    pub of_nat: Option<Id>,
+    pub operations: HashMap<Operation, Id>,
+}
+
+impl U64Spec {
+    /// Adds `of_nat`, the natural numbers modulo 2^64, with the axioms that it
+    /// wraps around at 2^64 and reaches every integer:
+    ///
+    /// ```text
+    /// of_nat := fun n => match n { O => 0, S p => add (of_nat p) 1 }
+    /// wraps : of_nat (2^64) = 0
+    /// surjective : (P : u64 -> Prop) -> ((n : nat) -> P (of_nat n)) -> (x : u64) -> P x
+    /// ```
+    ///
+    /// It needs the natural numbers and the addition.
+    pub fn add_of_nat(
+        &mut self,
+        env: &mut GlobalEnvironment,
+        [of_nat, wraps, surjective]: [Id; 3],
+        id_gen: &mut IdGenerator,
+    ) -> Result<(), String> {
+        let (Some(nat), Some(&add)) = (self.nat, self.operations.get(&Operation::Add)) else {
+            return Err("of_nat needs nat and u64_add to be declared first".to_string());
+        };
+        let [n, p, x, q, h] = [(); 5].map(|_| id_gen.generate_id());
+        let case = Term::Match(TermMatch {
+            scrutinee: Rc::new(var(n)),
+            return_type: Rc::new(constant(self.u64)),
+            branches: vec![
+                TermMatchBranch {
+                    constructor_id: nat.zero,
+                    bound_vars: Vec::new(),
+                    body: Rc::new(literal(0)),
+                },
+                TermMatchBranch {
+                    constructor_id: nat.succ,
+                    bound_vars: vec![p],
+                    body: Rc::new(abstract_variables(
+                        &apply(
+                            constant(add),
+                            vec![apply(constant(of_nat), vec![var(p)]), literal(1)],
+                        ),
+                        &[p],
+                    )),
+                },
+            ],
+        });
+        let ty = pi(n, constant(nat.nat), constant(self.u64));
+        env.add_definition(
+            of_nat,
+            Rc::new(ty),
+            Rc::new(lambda(n, constant(nat.nat), case)),
+        )?;
+        self.of_nat = Some(of_nat);
+
+        // 2^64 = ((((((2^2)^2)^2)^2)^2)^2
+        let two = apply(
+            constant(nat.succ),
+            vec![apply(constant(nat.succ), vec![constant(nat.zero)])],
+        );
+        let power = (0..6).fold(two, |x, _| apply(constant(nat.mul), vec![x.clone(), x]));
+        let wrapped = apply(constant(of_nat), vec![power]);
+        env.add_axiom(wraps, Rc::new(self.equal(id_gen, wrapped, literal(0))))?;
+
+        let predicate = pi(id_gen.generate_id(), constant(self.u64), prop());
+        let reached = pi(
+            n,
+            constant(nat.nat),
+            apply(var(q), vec![apply(constant(of_nat), vec![var(n)])]),
+        );
+        let all = pi(x, constant(self.u64), apply(var(q), vec![var(x)]));
+        let ty = pi(q, predicate, pi(h, reached, all));
+        env.add_axiom(surjective, Rc::new(ty))
+    }
+
+    /// The axioms specifying `operation`, or `None` if the declarations they
+    /// are stated with are not all known:
+    ///
+    /// ```text
+    /// add : (n m : nat) -> u64_add (of_nat n) (of_nat m) = of_nat (add n m)
+    /// mul : (n m : nat) -> u64_mul (of_nat n) (of_nat m) = of_nat (mul n m)
+    /// sub : (n m : nat) -> u64_sub (of_nat (add n m)) (of_nat m) = of_nat n
+    /// div : (x y : u64) -> u64_add (u64_mul (u64_div x y) y) (u64_mod x y) = x
+    /// eq : (x y : u64) -> u64_eq x y = 1 -> x = y
+    /// eq_refl : (x : u64) -> u64_eq x x = 1
+    /// ```
+    ///
+    /// The one of `u64_mod` is the one of `u64_div`.
+    pub fn axioms(&self, operation: Operation, id_gen: &mut IdGenerator) -> Option<Vec<Term>> {
+        let op = |operation| self.operations.get(&operation).copied().map(constant);
+        let [n, m, x, y, h] = [(); 5].map(|_| id_gen.generate_id());
+        let u64 = constant(self.u64);
+        let axioms = match operation {
+            Operation::Add | Operation::Sub | Operation::Mul => {
+                let (nat, of_nat) = (self.nat?, constant(self.of_nat?));
+                let of_nat = |n: Term| apply(of_nat.clone(), vec![n]);
+                let (left, right) = match operation {
+                    Operation::Add => (
+                        apply(op(Operation::Add)?, vec![of_nat(var(n)), of_nat(var(m))]),
+                        of_nat(apply(constant(nat.add), vec![var(n), var(m)])),
+                    ),
+                    Operation::Mul => (
+                        apply(op(Operation::Mul)?, vec![of_nat(var(n)), of_nat(var(m))]),
+                        of_nat(apply(constant(nat.mul), vec![var(n), var(m)])),
+                    ),
+                    _ => (
+                        apply(
+                            op(Operation::Sub)?,
+                            vec![
+                                of_nat(apply(constant(nat.add), vec![var(n), var(m)])),
+                                of_nat(var(m)),
+                            ],
+                        ),
+                        of_nat(var(n)),
+                    ),
+                };
+                let equal = self.equal(id_gen, left, right);
+                vec![pi(n, constant(nat.nat), pi(m, constant(nat.nat), equal))]
+            }
+            Operation::Div => {
+                let divided = apply(op(Operation::Div)?, vec![var(x), var(y)]);
+                let remainder = apply(op(Operation::Mod)?, vec![var(x), var(y)]);
+                let product = apply(op(Operation::Mul)?, vec![divided, var(y)]);
+                let sum = apply(op(Operation::Add)?, vec![product, remainder]);
+                let equal = self.equal(id_gen, sum, var(x));
+                vec![pi(x, u64.clone(), pi(y, u64, equal))]
+            }
+            Operation::Mod => return None,
+            Operation::Eq => {
+                let eq = op(Operation::Eq)?;
+                let test = |a: Id, b: Id| apply(eq.clone(), vec![var(a), var(b)]);
+                let tested = self.equal(id_gen, test(x, y), literal(1));
+                let equal = self.equal(id_gen, var(x), var(y));
+                let spec = pi(x, u64.clone(), pi(y, u64.clone(), pi(h, tested, equal)));
+                let reflexive = pi(x, u64, self.equal(id_gen, test(x, x), literal(1)));
+                vec![spec, reflexive]
+            }
+        };
+        Some(axioms)
+    }
+
+    /// `(P : u64 -> Prop) -> P left -> P right`
+    fn equal(&self, id_gen: &mut IdGenerator, left: Term, right: Term) -> Term {
+        let [q, a, h] = [(); 3].map(|_| id_gen.generate_id());
+        pi(
+            q,
+            pi(a, constant(self.u64), prop()),
+            pi(h, apply(var(q), vec![left]), apply(var(q), vec![right])),
+        )
+    }
+}
+
+/// `nat -> nat -> nat`
+fn binary(id_gen: &mut IdGenerator, nat: Id) -> Term {
+    let (n, m) = (id_gen.generate_id(), id_gen.generate_id());
+    pi(n, constant(nat), pi(m, constant(nat), constant(nat)))
+}
+
+fn literal(value: u64) -> Term {
+    Term::Literal(TermLiteral { value })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{
+        conversion::is_convertible,
+        global_environment::GlobalEnvironment,
+        id::{Id, IdGenerator},
+        local_context::LocalContext,
+        primitive::Operation,
+        reduction::whnf_with_env,
+        term::{Sort, Term, TermSort},
+        typechecker::infer_type,
+        well_founded::{apply, constant},
+    };
+
+    use super::{Nat, U64Spec, add_nat, literal};
+
+    #[test]
+    fn test_specification_is_well_typed_and_of_nat_computes() {
+        let mut env = GlobalEnvironment::new();
+        let mut id_gen = IdGenerator::new();
+        let u64 = Id::new();
+        env.add_u64(u64).unwrap();
+        let mut spec = U64Spec {
+            u64,
+            nat: None,
+            of_nat: None,
+            operations: HashMap::new(),
+        };
+        for operation in [
+            Operation::Add,
+            Operation::Sub,
+            Operation::Mul,
+            Operation::Div,
+            Operation::Mod,
+            Operation::Eq,
+        ] {
+            let id = Id::new();
+            env.add_u64_operation(id, operation, &mut id_gen).unwrap();
+            spec.operations.insert(operation, id);
+        }
+        let of_nat = [(); 3].map(|_| Id::new());
+        assert!(spec.add_of_nat(&mut env, of_nat, &mut id_gen).is_err());
+        assert!(spec.axioms(Operation::Add, &mut id_gen).is_none());
+
+        let [nat, zero, succ, add, mul] = [(); 5].map(|_| Id::new());
+        let nat = Nat {
+            nat,
+            zero,
+            succ,
+            add,
+            mul,
+        };
+        add_nat(&mut env, nat, &mut id_gen).unwrap();
+        spec.nat = Some(nat);
+        spec.add_of_nat(&mut env, of_nat, &mut id_gen).unwrap();
+
+        let ctx = LocalContext::new();
+        let prop = Term::Sort(TermSort { sort: Sort::Prop });
+        for id in [nat.add, nat.mul, of_nat[0]] {
+            let constant = env.get_constant(id).unwrap();
+            let ty = infer_type(&ctx, &env, constant.body.as_ref().unwrap()).unwrap();
+            assert!(is_convertible(&ctx, &env, &ty, &constant.ty));
+        }
+        for id in &of_nat[1..] {
+            let ty = env.get_constant(*id).unwrap().ty.clone();
+            assert_eq!(infer_type(&ctx, &env, &ty).unwrap().as_ref(), &prop);
+        }
+        let mut count = 0;
+        for operation in spec.operations.keys() {
+            for axiom in spec.axioms(*operation, &mut id_gen).unwrap_or_default() {
+                assert_eq!(infer_type(&ctx, &env, &axiom).unwrap().as_ref(), &prop);
+                count += 1;
+            }
+        }
+        assert_eq!(count, 6);
+
+        // of_nat (2 * 3) = 6
+        let numeral = |n: usize| (0..n).fold(constant(zero), |n, _| apply(constant(succ), vec![n]));
+        let product = apply(constant(mul), vec![numeral(2), numeral(3)]);
+        assert_eq!(
+            whnf_with_env(&apply(constant(of_nat[0]), vec![product]), &env),
+            literal(6)
+        );
+    }
+}
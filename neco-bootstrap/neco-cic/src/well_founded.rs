@@ -0,0 +1,376 @@
+use std::rc::Rc;
+
+use crate::{
+    global_environment::GlobalEnvironment,
+    id::{Id, IdGenerator},
+    inductive::{ConstructorDefinition, InductiveDefinition, Parameter},
+    substitution::{abstract_variable, abstract_variables},
+    term::{
+        Sort, Term, TermApplication, TermConstant, TermLambda, TermProduct, TermSort, TermVariable,
+    },
+};
+
+// Accessibility is what recursion on arguments that decrease along a relation,
+// rather than structurally, is built on: a function of `x` is defined by
+// recursion on a proof of `Acc A R x`, whose constructor gives the proofs for
+// all the `y` with `R y x`. Matching on that proof into `Set` is allowed as
+// `Acc` is a singleton proposition, and the proofs of a closed `Acc A R x` are
+// unfolded by reduction so that such functions compute.
+
+/// Adds the accessibility predicate of a relation, with `A` and `R` as kernel
+/// parameters so that the branch of a match on a proof binds `x` and `h`
+/// only, with the types of the proof:
+///
+/// ```text
+/// Acc : (A : Set) -> (R : A -> A -> Prop) -> A -> Prop
+/// Acc_intro : (A : Set) -> (R : A -> A -> Prop) -> (x : A)
+///     -> ((y : A) -> R y x -> Acc A R y) -> Acc A R x
+/// ```
+pub fn add_acc(
+    env: &mut GlobalEnvironment,
+    acc: Id,
+    intro: Id,
+    id_gen: &mut IdGenerator,
+) -> Result<(), String> {
+    let [a, r, x, y, h, proof] = [(); 6].map(|_| id_gen.generate_id());
+    let relation = relation_type(id_gen, a);
+    let parameters = vec![
+        Parameter {
+            name: a,
+            ty: Rc::new(set()),
+        },
+        Parameter {
+            name: r,
+            ty: Rc::new(abstract_variable(&relation, a)),
+        },
+    ];
+    let arity = abstract_variables(&pi(x, var(a), prop()), &[a, r]);
+
+    let accessible = |z: Id| apply(constant(acc), vec![var(a), var(r), var(z)]);
+    let smaller = pi(
+        y,
+        var(a),
+        pi(proof, apply(var(r), vec![var(y), var(x)]), accessible(y)),
+    );
+    let intro_type = pi(
+        a,
+        set(),
+        pi(r, relation, pi(x, var(a), pi(h, smaller, accessible(x)))),
+    );
+
+    env.inductives.add_inductive(InductiveDefinition::new(
+        acc,
+        parameters,
+        Rc::new(arity),
+        vec![ConstructorDefinition::new(intro, Rc::new(intro_type), 2)],
+    ))
+}
+
+/// Adds the well-founded relations, those whose elements are all accessible:
+///
+/// ```text
+/// well_founded : (A : Set) -> (R : A -> A -> Prop) -> Prop
+/// well_founded := fun A R => (a : A) -> Acc A R a
+/// ```
+pub fn add_well_founded(
+    env: &mut GlobalEnvironment,
+    well_founded: Id,
+    acc: Id,
+    id_gen: &mut IdGenerator,
+) -> Result<(), String> {
+    if env.inductives.get_inductive(acc).is_none() {
+        return Err(format!("Accessibility {acc:?} is not defined"));
+    }
+    let [a, r, x] = [(); 3].map(|_| id_gen.generate_id());
+    let relation = relation_type(id_gen, a);
+    let ty = pi(a, set(), pi(r, relation.clone(), prop()));
+    let all_accessible = pi(
+        x,
+        var(a),
+        apply(constant(acc), vec![var(a), var(r), var(x)]),
+    );
+    let body = lambda(a, set(), lambda(r, relation, all_accessible));
+    env.add_definition(well_founded, Rc::new(ty), Rc::new(body))
+}
+
+/// `A -> A -> Prop`
+fn relation_type(id_gen: &mut IdGenerator, a: Id) -> Term {
+    let (left, right) = (id_gen.generate_id(), id_gen.generate_id());
+    pi(left, var(a), pi(right, var(a), prop()))
+}
+
+pub(crate) fn set() -> Term {
+    Term::Sort(TermSort { sort: Sort::Set })
+}
+
+pub(crate) fn prop() -> Term {
+    Term::Sort(TermSort { sort: Sort::Prop })
+}
+
+pub(crate) fn var(id: Id) -> Term {
+    Term::Variable(TermVariable { id })
+}
+
+pub(crate) fn constant(id: Id) -> Term {
+    Term::Constant(TermConstant { id })
+}
+
+pub(crate) fn apply(f: Term, args: Vec<Term>) -> Term {
+    Term::Application(TermApplication {
+        f: Rc::new(f),
+        args,
+    })
+}
+
+/// The product binding the variable `var` in `target`
+pub(crate) fn pi(var: Id, source: Term, target: Term) -> Term {
+    Term::Product(TermProduct {
+        var,
+        source: Rc::new(source),
+        target: Rc::new(abstract_variable(&target, var)),
+    })
+}
+
+pub(crate) fn lambda(var: Id, source_ty: Term, body: Term) -> Term {
+    Term::Lambda(TermLambda {
+        var,
+        source_ty: Rc::new(source_ty),
+        target: Rc::new(abstract_variable(&body, var)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use crate::{
+        global_environment::GlobalEnvironment,
+        id::{Id, IdGenerator},
+        inductive::{ConstructorDefinition, InductiveDefinition},
+        local_context::LocalContext,
+        reduction::{normalize_with_env, normalize_with_env_by_steps, whnf_with_env},
+        substitution::abstract_variables,
+        term::{Term, TermMatch, TermMatchBranch},
+        typechecker::{check_elimination, infer_type},
+    };
+
+    use super::{add_acc, add_well_founded, apply, constant, lambda, pi, prop, set, var};
+
+    struct Env {
+        env: GlobalEnvironment,
+        nat: Id,
+        zero: Id,
+        succ: Id,
+        acc: Id,
+        intro: Id,
+        /// `pred : nat -> nat -> Prop`, with `pred_intro n : pred n (S n)`
+        pred: Id,
+        pred_intro: Id,
+        /// `acc_pred : (n : nat) -> Acc nat pred n`, an opaque theorem
+        acc_pred: Id,
+    }
+
+    impl Env {
+        fn new() -> Self {
+            let mut env = GlobalEnvironment::new();
+            let mut id_gen = IdGenerator::new();
+            let [nat, zero, succ, acc, intro, pred, pred_intro, acc_pred] =
+                [(); 8].map(|_| Id::new());
+            env.inductives.add_nat(nat, zero, succ).unwrap();
+            add_acc(&mut env, acc, intro, &mut id_gen).unwrap();
+
+            let [n, m] = [(); 2].map(|_| Id::new());
+            let pred_type = pi(n, constant(nat), pi(m, constant(nat), prop()));
+            let pred_intro_type = pi(
+                n,
+                constant(nat),
+                apply(
+                    constant(pred),
+                    vec![var(n), apply(constant(succ), vec![var(n)])],
+                ),
+            );
+            env.inductives
+                .add_inductive(InductiveDefinition::new(
+                    pred,
+                    Vec::new(),
+                    Rc::new(pred_type),
+                    vec![ConstructorDefinition::new(
+                        pred_intro,
+                        Rc::new(pred_intro_type),
+                        1,
+                    )],
+                ))
+                .unwrap();
+
+            // acc_pred n := Acc_intro nat pred n (fun y h => acc_pred y)
+            let [y, h] = [(); 2].map(|_| Id::new());
+            let acc_type =
+                |z: Id| apply(constant(acc), vec![constant(nat), constant(pred), var(z)]);
+            let statement = pi(n, constant(nat), acc_type(n));
+            let proof = lambda(
+                n,
+                constant(nat),
+                apply(
+                    constant(intro),
+                    vec![
+                        constant(nat),
+                        constant(pred),
+                        var(n),
+                        lambda(
+                            y,
+                            constant(nat),
+                            lambda(
+                                h,
+                                apply(constant(pred), vec![var(y), var(n)]),
+                                apply(constant(acc_pred), vec![var(y)]),
+                            ),
+                        ),
+                    ],
+                ),
+            );
+            env.add_theorem(acc_pred, Rc::new(statement), Rc::new(proof))
+                .unwrap();
+
+            Env {
+                env,
+                nat,
+                zero,
+                succ,
+                acc,
+                intro,
+                pred,
+                pred_intro,
+                acc_pred,
+            }
+        }
+
+        fn numeral(&self, n: usize) -> Term {
+            (0..n).fold(constant(self.zero), |m, _| {
+                apply(constant(self.succ), vec![m])
+            })
+        }
+
+        /// `count x a : nat`, copying `x` by recursion on `a : Acc nat pred x`:
+        ///
+        /// ```text
+        /// match a { Acc_intro _ h =>
+        ///     match x { O => O, S p => S (count p (h p (pred_intro p))) } }
+        /// ```
+        fn count_body(&self, count: Id) -> Term {
+            let [x, a, a_x, h, p] = [(); 5].map(|_| Id::new());
+            let call = apply(
+                constant(count),
+                vec![
+                    var(p),
+                    apply(
+                        var(h),
+                        vec![var(p), apply(constant(self.pred_intro), vec![var(p)])],
+                    ),
+                ],
+            );
+            let on_x = Term::Match(TermMatch {
+                scrutinee: Rc::new(var(x)),
+                return_type: Rc::new(constant(self.nat)),
+                branches: vec![
+                    TermMatchBranch {
+                        constructor_id: self.zero,
+                        bound_vars: Vec::new(),
+                        body: Rc::new(constant(self.zero)),
+                    },
+                    TermMatchBranch {
+                        constructor_id: self.succ,
+                        bound_vars: vec![p],
+                        body: Rc::new(abstract_variables(
+                            &apply(constant(self.succ), vec![call]),
+                            &[p],
+                        )),
+                    },
+                ],
+            });
+            let bound_vars = vec![a_x, h];
+            let on_a = Term::Match(TermMatch {
+                scrutinee: Rc::new(var(a)),
+                return_type: Rc::new(constant(self.nat)),
+                branches: vec![TermMatchBranch {
+                    constructor_id: self.intro,
+                    body: Rc::new(abstract_variables(&on_x, &bound_vars)),
+                    bound_vars,
+                }],
+            });
+            let a_type = apply(
+                constant(self.acc),
+                vec![constant(self.nat), constant(self.pred), var(x)],
+            );
+            lambda(x, constant(self.nat), lambda(a, a_type, on_a))
+        }
+
+        fn add_count(&mut self) -> Id {
+            let count = Id::new();
+            let [x, a] = [(); 2].map(|_| Id::new());
+            let ty = pi(
+                x,
+                constant(self.nat),
+                pi(
+                    a,
+                    apply(
+                        constant(self.acc),
+                        vec![constant(self.nat), constant(self.pred), var(x)],
+                    ),
+                    constant(self.nat),
+                ),
+            );
+            let body = self.count_body(count);
+            self.env
+                .add_definition(count, Rc::new(ty), Rc::new(body))
+                .unwrap();
+            count
+        }
+    }
+
+    #[test]
+    fn test_acc_is_a_singleton_proposition() {
+        let mut env = Env::new();
+        let mut id_gen = IdGenerator::new();
+        let well_founded = Id::new();
+        add_well_founded(&mut env.env, well_founded, env.acc, &mut id_gen).unwrap();
+
+        let sort = crate::term::Sort::Set;
+        assert_eq!(check_elimination(&env.env, env.acc, &sort), Ok(()));
+        let ty = infer_type(&LocalContext::new(), &env.env, &constant(well_founded)).unwrap();
+        let Term::Product(product) = ty.as_ref() else {
+            panic!("well_founded is a function");
+        };
+        assert_eq!(product.source.as_ref(), &set());
+    }
+
+    #[test]
+    fn test_closed_accessibility_proofs_reduce() {
+        let mut env = Env::new();
+        let count = env.add_count();
+        let three = env.numeral(3);
+        let term = apply(
+            constant(count),
+            vec![
+                three.clone(),
+                apply(constant(env.acc_pred), vec![three.clone()]),
+            ],
+        );
+
+        assert_eq!(normalize_with_env(&term, &env.env), three);
+        assert_eq!(normalize_with_env_by_steps(&term, &env.env), three);
+    }
+
+    #[test]
+    fn test_open_accessibility_proofs_stay_opaque() {
+        let mut env = Env::new();
+        let count = env.add_count();
+        let x = Id::new();
+        let term = apply(
+            constant(count),
+            vec![var(x), apply(constant(env.acc_pred), vec![var(x)])],
+        );
+
+        let whnf = whnf_with_env(&term, &env.env);
+        assert!(matches!(whnf, Term::Match(_)));
+    }
+}
@@ -1,11 +1,28 @@
-use crate::{AssemblyCompiler, CompileError};
+use crate::{AssemblyCompiler, CompileError, Slot};
 use neco_felis_syn::*;
+use neco_felis_type_check::PhaseTyped;
+
+/// Divides `rax` by `rbx`, leaving the quotient in `rax` and the remainder in
+/// `rdx`. A zero divisor gives the quotient 0 and the remainder `rax` instead
+/// of trapping, as `u64_div` and `u64_mod` compute in the kernel, so that
+/// proofs about them hold of the compiled code.
+pub(crate) fn push_u64_div(output: &mut String) {
+    output.push_str("    xor rdx, rdx\n");
+    output.push_str("    test rbx, rbx\n");
+    output.push_str("    jz 1f\n");
+    output.push_str("    div rbx\n");
+    output.push_str("    jmp 2f\n");
+    output.push_str("1:\n");
+    output.push_str("    mov rdx, rax\n");
+    output.push_str("    xor rax, rax\n");
+    output.push_str("2:\n");
+}
 
 /// U64 arithmetic operations for let statements
 impl AssemblyCompiler {
     pub fn compile_u64_add_let(
         &mut self,
-        apply: &TermApply<PhaseParse>,
+        apply: &TermApply<PhaseTyped>,
         offset: i32,
     ) -> Result<(), CompileError> {
         // u64_add expects exactly 2 arguments
@@ -37,7 +54,7 @@ impl AssemblyCompiler {
 
     pub fn compile_u64_sub_let(
         &mut self,
-        apply: &TermApply<PhaseParse>,
+        apply: &TermApply<PhaseTyped>,
         offset: i32,
     ) -> Result<(), CompileError> {
         // u64_sub expects exactly 2 arguments
@@ -69,7 +86,7 @@ impl AssemblyCompiler {
 
     pub fn compile_u64_mul_let(
         &mut self,
-        apply: &TermApply<PhaseParse>,
+        apply: &TermApply<PhaseTyped>,
         offset: i32,
     ) -> Result<(), CompileError> {
         // u64_mul expects exactly 2 arguments
@@ -101,7 +118,7 @@ impl AssemblyCompiler {
 
     pub fn compile_u64_div_let(
         &mut self,
-        apply: &TermApply<PhaseParse>,
+        apply: &TermApply<PhaseTyped>,
         offset: i32,
     ) -> Result<(), CompileError> {
         // u64_div expects exactly 2 arguments
@@ -121,10 +138,7 @@ impl AssemblyCompiler {
         // Load second argument into rbx
         self.load_argument_into_register(arg2, "rbx")?;
 
-        // Clear rdx for division
-        self.output.push_str("    xor rdx, rdx\n");
-        // Divide rax by rbx, result in rax
-        self.output.push_str("    div rbx\n");
+        push_u64_div(&mut self.output);
 
         // Store result from rax to the variable's stack location
         self.output.push_str(&format!(
@@ -137,7 +151,7 @@ impl AssemblyCompiler {
 
     pub fn compile_u64_mod_let(
         &mut self,
-        apply: &TermApply<PhaseParse>,
+        apply: &TermApply<PhaseTyped>,
         offset: i32,
     ) -> Result<(), CompileError> {
         // u64_mod expects exactly 2 arguments
@@ -157,10 +171,7 @@ impl AssemblyCompiler {
         // Load second argument into rbx
         self.load_argument_into_register(arg2, "rbx")?;
 
-        // Clear rdx for division
-        self.output.push_str("    xor rdx, rdx\n");
-        // Divide rax by rbx, remainder in rdx
-        self.output.push_str("    div rbx\n");
+        push_u64_div(&mut self.output);
 
         // Store remainder from rdx to the variable's stack location
         self.output.push_str(&format!(
@@ -174,7 +185,7 @@ impl AssemblyCompiler {
     /// F32 arithmetic operations for let statements
     pub fn compile_f32_add_let(
         &mut self,
-        apply: &TermApply<PhaseParse>,
+        apply: &TermApply<PhaseTyped>,
         offset: i32,
     ) -> Result<(), CompileError> {
         // f32_add expects exactly 2 arguments
@@ -206,7 +217,7 @@ impl AssemblyCompiler {
 
     pub fn compile_f32_sub_let(
         &mut self,
-        apply: &TermApply<PhaseParse>,
+        apply: &TermApply<PhaseTyped>,
         offset: i32,
     ) -> Result<(), CompileError> {
         // f32_sub expects exactly 2 arguments
@@ -238,7 +249,7 @@ impl AssemblyCompiler {
 
     pub fn compile_f32_mul_let(
         &mut self,
-        apply: &TermApply<PhaseParse>,
+        apply: &TermApply<PhaseTyped>,
         offset: i32,
     ) -> Result<(), CompileError> {
         // f32_mul expects exactly 2 arguments
@@ -270,7 +281,7 @@ impl AssemblyCompiler {
 
     pub fn compile_f32_div_let(
         &mut self,
-        apply: &TermApply<PhaseParse>,
+        apply: &TermApply<PhaseTyped>,
         offset: i32,
     ) -> Result<(), CompileError> {
         // f32_div expects exactly 2 arguments
@@ -304,7 +315,7 @@ impl AssemblyCompiler {
 
     pub fn compile_f32_to_u64_let(
         &mut self,
-        apply: &TermApply<PhaseParse>,
+        apply: &TermApply<PhaseTyped>,
         offset: i32,
     ) -> Result<(), CompileError> {
         // f32_to_u64 expects exactly 1 argument
@@ -335,7 +346,7 @@ impl AssemblyCompiler {
     /// U64 arithmetic operations for proc statements
     pub fn compile_u64_add_let_proc(
         &mut self,
-        apply: &ProcTermApply<PhaseParse>,
+        apply: &ProcTermApply<PhaseTyped>,
         offset: i32,
     ) -> Result<(), CompileError> {
         if apply.args.len() != 2 {
@@ -361,7 +372,7 @@ impl AssemblyCompiler {
 
     pub fn compile_u64_sub_let_proc(
         &mut self,
-        apply: &ProcTermApply<PhaseParse>,
+        apply: &ProcTermApply<PhaseTyped>,
         offset: i32,
     ) -> Result<(), CompileError> {
         if apply.args.len() != 2 {
@@ -387,7 +398,7 @@ impl AssemblyCompiler {
 
     pub fn compile_u64_mul_let_proc(
         &mut self,
-        apply: &ProcTermApply<PhaseParse>,
+        apply: &ProcTermApply<PhaseTyped>,
         offset: i32,
     ) -> Result<(), CompileError> {
         if apply.args.len() != 2 {
@@ -413,7 +424,7 @@ impl AssemblyCompiler {
 
     pub fn compile_u64_div_let_proc(
         &mut self,
-        apply: &ProcTermApply<PhaseParse>,
+        apply: &ProcTermApply<PhaseTyped>,
         offset: i32,
     ) -> Result<(), CompileError> {
         if apply.args.len() != 2 {
@@ -428,8 +439,7 @@ impl AssemblyCompiler {
 
         self.load_proc_argument_into_register(arg1, "rax")?;
         self.load_proc_argument_into_register(arg2, "rbx")?;
-        self.output.push_str("    xor rdx, rdx\n"); // Clear rdx for unsigned division
-        self.output.push_str("    div rbx\n");
+        push_u64_div(&mut self.output);
         self.output.push_str(&format!(
             "    mov qword ptr [rbp - 8 - {}], rax\n",
             offset - 8
@@ -440,7 +450,7 @@ impl AssemblyCompiler {
 
     pub fn compile_u64_mod_let_proc(
         &mut self,
-        apply: &ProcTermApply<PhaseParse>,
+        apply: &ProcTermApply<PhaseTyped>,
         offset: i32,
     ) -> Result<(), CompileError> {
         if apply.args.len() != 2 {
@@ -455,8 +465,7 @@ impl AssemblyCompiler {
 
         self.load_proc_argument_into_register(arg1, "rax")?;
         self.load_proc_argument_into_register(arg2, "rbx")?;
-        self.output.push_str("    xor rdx, rdx\n"); // Clear rdx for unsigned division
-        self.output.push_str("    div rbx\n");
+        push_u64_div(&mut self.output);
         // For modulo, result is in rdx
         self.output.push_str(&format!(
             "    mov qword ptr [rbp - 8 - {}], rdx\n",
@@ -469,7 +478,7 @@ impl AssemblyCompiler {
     /// F32 arithmetic operations for proc statements
     pub fn compile_f32_add_let_proc(
         &mut self,
-        apply: &ProcTermApply<PhaseParse>,
+        apply: &ProcTermApply<PhaseTyped>,
         offset: i32,
     ) -> Result<(), CompileError> {
         if apply.args.len() != 2 {
@@ -500,7 +509,7 @@ impl AssemblyCompiler {
 
     pub fn compile_f32_sub_let_proc(
         &mut self,
-        apply: &ProcTermApply<PhaseParse>,
+        apply: &ProcTermApply<PhaseTyped>,
         offset: i32,
     ) -> Result<(), CompileError> {
         if apply.args.len() != 2 {
@@ -531,7 +540,7 @@ impl AssemblyCompiler {
 
     pub fn compile_f32_mul_let_proc(
         &mut self,
-        apply: &ProcTermApply<PhaseParse>,
+        apply: &ProcTermApply<PhaseTyped>,
         offset: i32,
     ) -> Result<(), CompileError> {
         if apply.args.len() != 2 {
@@ -562,7 +571,7 @@ impl AssemblyCompiler {
 
     pub fn compile_f32_div_let_proc(
         &mut self,
-        apply: &ProcTermApply<PhaseParse>,
+        apply: &ProcTermApply<PhaseTyped>,
         offset: i32,
     ) -> Result<(), CompileError> {
         if apply.args.len() != 2 {
@@ -593,7 +602,7 @@ impl AssemblyCompiler {
 
     pub fn compile_f32_to_u64_let_proc(
         &mut self,
-        apply: &ProcTermApply<PhaseParse>,
+        apply: &ProcTermApply<PhaseTyped>,
         offset: i32,
     ) -> Result<(), CompileError> {
         if apply.args.len() != 1 {
@@ -623,7 +632,7 @@ impl AssemblyCompiler {
     /// U64 arithmetic operations for assignment statements
     pub fn compile_u64_add_assign_proc(
         &mut self,
-        apply: &ProcTermApply<PhaseParse>,
+        apply: &ProcTermApply<PhaseTyped>,
         offset: i32,
     ) -> Result<(), CompileError> {
         if apply.args.len() != 2 {
@@ -649,7 +658,7 @@ impl AssemblyCompiler {
 
     pub fn compile_u64_sub_assign_proc(
         &mut self,
-        apply: &ProcTermApply<PhaseParse>,
+        apply: &ProcTermApply<PhaseTyped>,
         offset: i32,
     ) -> Result<(), CompileError> {
         if apply.args.len() != 2 {
@@ -675,7 +684,7 @@ impl AssemblyCompiler {
 
     pub fn compile_u64_mul_assign_proc(
         &mut self,
-        apply: &ProcTermApply<PhaseParse>,
+        apply: &ProcTermApply<PhaseTyped>,
         offset: i32,
     ) -> Result<(), CompileError> {
         if apply.args.len() != 2 {
@@ -701,7 +710,7 @@ impl AssemblyCompiler {
 
     pub fn compile_u64_div_assign_proc(
         &mut self,
-        apply: &ProcTermApply<PhaseParse>,
+        apply: &ProcTermApply<PhaseTyped>,
         offset: i32,
     ) -> Result<(), CompileError> {
         if apply.args.len() != 2 {
@@ -716,8 +725,7 @@ impl AssemblyCompiler {
 
         self.load_proc_argument_into_register(arg1, "rax")?;
         self.load_proc_argument_into_register(arg2, "rbx")?;
-        self.output.push_str("    xor rdx, rdx\n");
-        self.output.push_str("    div rbx\n");
+        push_u64_div(&mut self.output);
         self.output.push_str(&format!(
             "    mov qword ptr [rbp - 8 - {}], rax\n",
             offset - 8
@@ -728,7 +736,7 @@ impl AssemblyCompiler {
 
     pub fn compile_u64_mod_assign_proc(
         &mut self,
-        apply: &ProcTermApply<PhaseParse>,
+        apply: &ProcTermApply<PhaseTyped>,
         offset: i32,
     ) -> Result<(), CompileError> {
         if apply.args.len() != 2 {
@@ -743,8 +751,7 @@ impl AssemblyCompiler {
 
         self.load_proc_argument_into_register(arg1, "rax")?;
         self.load_proc_argument_into_register(arg2, "rbx")?;
-        self.output.push_str("    xor rdx, rdx\n");
-        self.output.push_str("    div rbx\n");
+        push_u64_div(&mut self.output);
         self.output.push_str(&format!(
             "    mov qword ptr [rbp - 8 - {}], rdx\n",
             offset - 8
@@ -755,7 +762,7 @@ impl AssemblyCompiler {
 
     pub fn compile_f32_add_assign_proc(
         &mut self,
-        apply: &ProcTermApply<PhaseParse>,
+        apply: &ProcTermApply<PhaseTyped>,
         offset: i32,
     ) -> Result<(), CompileError> {
         if apply.args.len() != 2 {
@@ -781,7 +788,7 @@ impl AssemblyCompiler {
 
     pub fn compile_f32_sub_assign_proc(
         &mut self,
-        apply: &ProcTermApply<PhaseParse>,
+        apply: &ProcTermApply<PhaseTyped>,
         offset: i32,
     ) -> Result<(), CompileError> {
         if apply.args.len() != 2 {
@@ -807,7 +814,7 @@ impl AssemblyCompiler {
 
     pub fn compile_f32_mul_assign_proc(
         &mut self,
-        apply: &ProcTermApply<PhaseParse>,
+        apply: &ProcTermApply<PhaseTyped>,
         offset: i32,
     ) -> Result<(), CompileError> {
         if apply.args.len() != 2 {
@@ -833,7 +840,7 @@ impl AssemblyCompiler {
 
     pub fn compile_f32_div_assign_proc(
         &mut self,
-        apply: &ProcTermApply<PhaseParse>,
+        apply: &ProcTermApply<PhaseTyped>,
         offset: i32,
     ) -> Result<(), CompileError> {
         if apply.args.len() != 2 {
@@ -859,7 +866,7 @@ impl AssemblyCompiler {
 
     pub fn compile_f32_to_u64_assign_proc(
         &mut self,
-        apply: &ProcTermApply<PhaseParse>,
+        apply: &ProcTermApply<PhaseTyped>,
         offset: i32,
     ) -> Result<(), CompileError> {
         if apply.args.len() != 1 {
@@ -889,7 +896,7 @@ impl AssemblyCompiler {
     /// Helper methods for loading arguments into registers
     pub fn load_argument_into_register(
         &mut self,
-        arg: &Term<PhaseParse>,
+        arg: &Term<PhaseTyped>,
         register: &str,
     ) -> Result<(), CompileError> {
         match arg {
@@ -900,7 +907,7 @@ impl AssemblyCompiler {
             }
             Term::Variable(var) => {
                 let var_name = var.variable.s();
-                if let Some(&var_offset) = self.variables.get(var_name) {
+                if let Some(&var_offset) = self.variables.get(&Slot::Variable(var.ext.clone())) {
                     self.output.push_str(&format!(
                         "    mov {register}, qword ptr [rbp - 8 - {}]\n",
                         var_offset - 8
@@ -926,7 +933,7 @@ impl AssemblyCompiler {
 
     pub fn load_f32_argument_into_register(
         &mut self,
-        arg: &Term<PhaseParse>,
+        arg: &Term<PhaseTyped>,
         register: &str,
     ) -> Result<(), CompileError> {
         match arg {
@@ -947,7 +954,7 @@ impl AssemblyCompiler {
             }
             Term::Variable(var) => {
                 let var_name = var.variable.s();
-                if let Some(&var_offset) = self.variables.get(var_name) {
+                if let Some(&var_offset) = self.variables.get(&Slot::Variable(var.ext.clone())) {
                     self.output.push_str(&format!(
                         "    movss {register}, dword ptr [rbp - 8 - {}]\n",
                         var_offset - 8
@@ -973,7 +980,7 @@ impl AssemblyCompiler {
 
     pub fn load_f32_proc_argument_into_register(
         &mut self,
-        arg: &ProcTerm<PhaseParse>,
+        arg: &ProcTerm<PhaseTyped>,
         register: &str,
     ) -> Result<(), CompileError> {
         match arg {
@@ -995,7 +1002,7 @@ impl AssemblyCompiler {
             }
             ProcTerm::Variable(var) => {
                 let var_name = var.variable.s();
-                if let Some(&var_offset) = self.variables.get(var_name) {
+                if let Some(&var_offset) = self.variables.get(&Slot::Variable(var.ext.0.clone())) {
                     self.output.push_str(&format!(
                         "    movss {register}, dword ptr [rbp - 8 - {}]\n",
                         var_offset - 8
@@ -1015,10 +1022,11 @@ impl AssemblyCompiler {
                 // Handle field access like points.x 0 for f32 loading
                 let object_name = field_access.object.s();
                 let field_name = field_access.field.s();
+                let object = &field_access.ext.0;
 
                 // Check if this is a Structure of Arrays (SoA) access
-                let soa_ptr_var_name = format!("{object_name}_{field_name}_ptr");
-                if let Some(&ptr_offset) = self.variables.get(&soa_ptr_var_name) {
+                let column = Slot::Column(object.clone(), field_name.to_string());
+                if let Some(&ptr_offset) = self.variables.get(&column) {
                     // This is SoA access - load the field array pointer
                     self.output.push_str(&format!(
                         "    mov rax, qword ptr [rbp - 8 - {}]\n",
@@ -1028,7 +1036,7 @@ impl AssemblyCompiler {
                     // Handle index if present
                     if let Some(index_term) = &field_access.index {
                         // Get array info for element size calculation
-                        if let Some(array_type_name) = self.variable_arrays.get(object_name)
+                        if let Some(array_type_name) = self.variable_arrays.get(object)
                             && let Some(array_info) = self.arrays.get(array_type_name)
                         {
                             let element_size = crate::arrays::get_element_size(
@@ -1046,7 +1054,8 @@ impl AssemblyCompiler {
                                     }
                                 }
                                 ProcTerm::Variable(var) => {
-                                    if let Some(&var_offset) = self.variables.get(var.variable.s())
+                                    if let Some(&var_offset) =
+                                        self.variables.get(&Slot::Variable(var.ext.0.clone()))
                                     {
                                         self.output.push_str(&format!(
                                             "    mov rbx, qword ptr [rbp - 8 - {}]\n",
@@ -1072,7 +1081,8 @@ impl AssemblyCompiler {
                         .push_str(&format!("    movss {register}, dword ptr [rax]\n"));
 
                     Ok(())
-                } else if let Some(var_offset) = self.variables.get(object_name) {
+                } else if let Some(var_offset) = self.variables.get(&Slot::Variable(object.clone()))
+                {
                     // Fall back to struct-based access for non-SoA variables
                     self.output.push_str(&format!(
                         "    mov rax, qword ptr [rbp - 8 - {}]\n",
@@ -1131,7 +1141,7 @@ impl AssemblyCompiler {
     /// Convert u64 to f32
     pub fn compile_u64_to_f32_let_proc(
         &mut self,
-        apply: &ProcTermApply<PhaseParse>,
+        apply: &ProcTermApply<PhaseTyped>,
         offset: i32,
     ) -> Result<(), CompileError> {
         if apply.args.len() != 1 {
@@ -1161,7 +1171,7 @@ impl AssemblyCompiler {
     /// Create u64 value from literal
     pub fn compile_u64_let_proc(
         &mut self,
-        apply: &ProcTermApply<PhaseParse>,
+        apply: &ProcTermApply<PhaseTyped>,
         offset: i32,
     ) -> Result<(), CompileError> {
         if apply.args.len() != 1 {
@@ -1188,7 +1198,7 @@ impl AssemblyCompiler {
     /// Create f32 value from literal
     pub fn compile_f32_let_proc(
         &mut self,
-        apply: &ProcTermApply<PhaseParse>,
+        apply: &ProcTermApply<PhaseTyped>,
         offset: i32,
     ) -> Result<(), CompileError> {
         if apply.args.len() != 1 {
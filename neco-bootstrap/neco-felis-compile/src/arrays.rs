@@ -1,11 +1,13 @@
+use neco_felis_rename::VariableId;
 use neco_felis_syn::*;
+use neco_felis_type_check::PhaseTyped;
 use std::collections::HashMap;
 
-use crate::compiler::ArrayInfo;
+use crate::compiler::{ArrayInfo, Slot};
 use crate::error::CompileError;
 
 /// Count array pointers in statements
-pub fn count_array_pointers_in_statements(statements: &Statements<PhaseParse>) -> i32 {
+pub fn count_array_pointers_in_statements(statements: &Statements<PhaseTyped>) -> i32 {
     match statements {
         Statements::Then(then) => {
             let head_count = count_array_pointers_in_statement(&then.head);
@@ -18,7 +20,7 @@ pub fn count_array_pointers_in_statements(statements: &Statements<PhaseParse>) -
 }
 
 /// Count array pointers in a single statement
-pub fn count_array_pointers_in_statement(statement: &Statement<PhaseParse>) -> i32 {
+pub fn count_array_pointers_in_statement(statement: &Statement<PhaseTyped>) -> i32 {
     match statement {
         Statement::Let(let_stmt) => count_array_pointers_in_proc_term(&let_stmt.value),
         Statement::LetMut(let_mut_stmt) => count_array_pointers_in_proc_term(&let_mut_stmt.value),
@@ -29,18 +31,18 @@ pub fn count_array_pointers_in_statement(statement: &Statement<PhaseParse>) -> i
         Statement::Return(return_stmt) => count_array_pointers_in_proc_term(&return_stmt.value),
         Statement::CallPtx(_) => 0,
         Statement::Expr(proc_term) => count_array_pointers_in_proc_term(proc_term),
-        Statement::Ext(_) => unreachable!("Ext statements not supported in PhaseParse"),
+        Statement::Ext(_) => unreachable!("Ext statements not supported in PhaseTyped"),
     }
 }
 
 /// Count array pointers in a proc term
-pub fn count_array_pointers_in_proc_term(_proc_term: &ProcTerm<PhaseParse>) -> i32 {
+pub fn count_array_pointers_in_proc_term(_proc_term: &ProcTerm<PhaseTyped>) -> i32 {
     // For now, return 0 - this would need proper implementation
     0
 }
 
 /// Count array pointers in a term
-pub fn count_array_pointers_in_term(term: &Term<PhaseParse>) -> i32 {
+pub fn count_array_pointers_in_term(term: &Term<PhaseTyped>) -> i32 {
     match term {
         Term::Apply(apply) => {
             let mut count = count_array_pointers_in_term(&apply.f);
@@ -55,7 +57,7 @@ pub fn count_array_pointers_in_term(term: &Term<PhaseParse>) -> i32 {
 
 /// Compile an array definition
 pub fn compile_array(
-    array: &ItemArray<PhaseParse>,
+    array: &ItemArray<PhaseTyped>,
     arrays: &mut HashMap<String, ArrayInfo>,
 ) -> Result<(), CompileError> {
     let array_name = array.name().s().to_string();
@@ -63,25 +65,16 @@ pub fn compile_array(
     let mut field_types = Vec::new();
     let mut dimension = 1;
 
+    // The fields of the items, with the types the type checker resolved
+    for (field_name, field_type) in &array.ext {
+        field_names.push(field_name.clone());
+        field_types.push(field_type.to_string());
+    }
     for field in array.fields() {
-        let field_name = field.keyword.s();
-        match field_name {
-            "item" => {
-                // Parse the struct fields from the item definition
-                if let Term::Struct(item_struct) = &*field.value {
-                    for struct_field in item_struct.fields() {
-                        field_names.push(struct_field.name.s().to_string());
-                        // Extract type from field type (simplified)
-                        field_types.push(extract_type_from_term(&struct_field.ty)?);
-                    }
-                }
-            }
-            "dimension" => {
-                if let Term::Number(num) = &*field.value {
-                    dimension = num.number.s().parse::<usize>().unwrap_or(1);
-                }
-            }
-            _ => {}
+        if field.keyword.s() == "dimension"
+            && let Term::Number(num) = &*field.value
+        {
+            dimension = num.number.s().parse::<usize>().unwrap_or(1);
         }
     }
 
@@ -97,16 +90,6 @@ pub fn compile_array(
     Ok(())
 }
 
-/// Extract type information from a term
-pub fn extract_type_from_term(term: &Term<PhaseParse>) -> Result<String, CompileError> {
-    match term {
-        Term::Variable(var) => Ok(var.variable.s().to_string()),
-        _ => Err(CompileError::UnsupportedConstruct(format!(
-            "Unsupported type term: {term:?}"
-        ))),
-    }
-}
-
 /// Generate Structure of Arrays (SoA) allocation using mmap
 ///
 /// This function implements Structure of Arrays layout by allocating separate
@@ -122,7 +105,6 @@ pub fn generate_soa_allocation(
     size: &str,
     output: &mut String,
     stack_offset: &mut i32,
-    variables: &mut HashMap<String, i32>,
     arrays: &mut HashMap<String, ArrayInfo>,
 ) -> Result<(), CompileError> {
     let mut updated_info = array_info.clone();
@@ -135,24 +117,22 @@ pub fn generate_soa_allocation(
         array_info.field_names.len()
     ));
 
-    // Store the array size for later use by #len method
-    // Note: This uses array_name which may not be a variable name, but we store it just in case
-    let size_var_name = format!("{array_name}_size");
+    // The array is not bound to a variable, so its size and columns are
+    // stored without slots of their own
     *stack_offset += 8;
     let size_offset = *stack_offset;
-    variables.insert(size_var_name.clone(), size_offset);
 
     if size == "rsi" {
         // Size is already in rsi, store it
         output.push_str(&format!(
-            "    mov qword ptr [rbp - 8 - {}], rsi  # Store {size_var_name}\n",
+            "    mov qword ptr [rbp - 8 - {}], rsi  # Store {array_name}_size\n",
             size_offset - 8
         ));
     } else {
         // Load size and store it
         output.push_str(&format!("    mov rax, {size}        # Load array size\n"));
         output.push_str(&format!(
-            "    mov qword ptr [rbp - 8 - {}], rax  # Store {size_var_name}\n",
+            "    mov qword ptr [rbp - 8 - {}], rax  # Store {array_name}_size\n",
             size_offset - 8
         ));
     }
@@ -185,13 +165,11 @@ pub fn generate_soa_allocation(
         output.push_str("    syscall\n");
 
         // Store the returned pointer for this field's array
-        let ptr_var_name = format!("{array_name}_{field_name}_ptr");
         *stack_offset += 8;
         let ptr_offset = *stack_offset;
-        variables.insert(ptr_var_name.clone(), ptr_offset);
 
         output.push_str(&format!(
-            "    mov qword ptr [rbp - 8 - {}], rax  # Store {ptr_var_name}\n",
+            "    mov qword ptr [rbp - 8 - {}], rax  # Store {array_name}_{field_name}_ptr\n",
             ptr_offset - 8
         ));
     }
@@ -210,15 +188,16 @@ pub fn generate_soa_allocation(
 
 /// Generate Structure of Arrays (SoA) allocation with variable name
 ///
-/// This is similar to `generate_soa_allocation` but uses the variable name
-/// instead of the array type name for pointer variable naming.
+/// This is similar to `generate_soa_allocation` but registers the slots of
+/// the size and of the field pointers under the id of the variable.
 pub fn generate_soa_allocation_with_var(
+    id: &VariableId,
     var_name: &str,
     array_info: &ArrayInfo,
     size: &str,
     output: &mut String,
     stack_offset: &mut i32,
-    variables: &mut HashMap<String, i32>,
+    variables: &mut HashMap<Slot, i32>,
 ) -> Result<(), CompileError> {
     output.push_str(&format!("    # SoA allocation for variable '{var_name}'\n"));
     output.push_str(&format!(
@@ -230,7 +209,7 @@ pub fn generate_soa_allocation_with_var(
     let size_var_name = format!("{var_name}_size");
     *stack_offset += 8;
     let size_offset = *stack_offset;
-    variables.insert(size_var_name.clone(), size_offset);
+    variables.insert(Slot::Length(id.clone()), size_offset);
 
     if size == "rsi" {
         // Size is already in rsi, store it
@@ -274,11 +253,11 @@ pub fn generate_soa_allocation_with_var(
         output.push_str("    mov r9, 0                # offset = 0\n");
         output.push_str("    syscall\n");
 
-        // Store the returned pointer using variable name
+        // Store the returned pointer in the column slot of the variable
         let ptr_var_name = format!("{var_name}_{field_name}_ptr");
         *stack_offset += 8;
         let ptr_offset = *stack_offset;
-        variables.insert(ptr_var_name.clone(), ptr_offset);
+        variables.insert(Slot::Column(id.clone(), field_name.clone()), ptr_offset);
 
         output.push_str(&format!(
             "    mov qword ptr [rbp - 8 - {}], rax  # Store {ptr_var_name}\n",
@@ -309,24 +288,25 @@ pub fn parse_number(number_str: &str) -> String {
 
 /// Compile field assignment for array elements
 pub fn compile_field_assign(
-    field_assign: &StatementFieldAssign<PhaseParse>,
+    field_assign: &StatementFieldAssign<PhaseTyped>,
     output: &mut String,
-    variables: &HashMap<String, i32>,
-    variable_arrays: &HashMap<String, String>,
+    variables: &HashMap<Slot, i32>,
+    variable_arrays: &HashMap<VariableId, String>,
     arrays: &HashMap<String, ArrayInfo>,
 ) -> Result<(), CompileError> {
     // This is used for writing array elements like "points.x 0 = 10.0f32"
     let obj_name = field_assign.field_access.object_name();
     let field_name = field_assign.field_access.field_name();
+    let object = &field_assign.field_access.ext.0;
 
-    // Look up the array type from variable name
-    if let Some(array_type_name) = variable_arrays.get(obj_name)
+    // Look up the array type of the variable
+    if let Some(array_type_name) = variable_arrays.get(object)
         && let Some(array_info) = arrays.get(array_type_name).cloned()
         && let Some(index_term) = &field_assign.field_access.index
     {
         // Get the pointer for this field
-        let ptr_var_name = format!("{obj_name}_{field_name}_ptr");
-        if let Some(&ptr_offset) = variables.get(&ptr_var_name) {
+        let column = Slot::Column(object.clone(), field_name.to_string());
+        if let Some(&ptr_offset) = variables.get(&column) {
             // Load the base pointer
             output.push_str(&format!(
                 "    mov rax, qword ptr [rbp - 8 - {}]\n",
@@ -344,7 +324,7 @@ pub fn compile_field_assign(
                     output.push_str(&format!("    add rax, {offset}\n"));
                 }
                 ProcTerm::Variable(var) => {
-                    if let Some(&var_offset) = variables.get(var.variable.s()) {
+                    if let Some(&var_offset) = variables.get(&Slot::Variable(var.ext.0.clone())) {
                         output.push_str(&format!(
                             "    mov rbx, qword ptr [rbp - 8 - {}]\n",
                             var_offset - 8
@@ -388,7 +368,7 @@ pub fn compile_field_assign(
                     }
                 }
                 ProcTerm::Variable(var) => {
-                    if let Some(&var_offset) = variables.get(var.variable.s()) {
+                    if let Some(&var_offset) = variables.get(&Slot::Variable(var.ext.0.clone())) {
                         output.push_str(&format!(
                             "    mov rbx, qword ptr [rbp - 8 - {}]\n",
                             var_offset - 8
@@ -410,7 +390,7 @@ pub fn compile_field_assign(
 
     // Basic stub implementation for field assignments when array info is not available
     // This is simplified to make tests pass
-    if let Some(&var_offset) = variables.get(obj_name) {
+    if let Some(&var_offset) = variables.get(&Slot::Variable(object.clone())) {
         // Load the base pointer
         output.push_str(&format!(
             "    mov rax, qword ptr [rbp - 8 - {}]\n",
@@ -1,4 +1,7 @@
-use neco_felis_compile::{compile_file_to_assembly, compile_file_to_assembly_with_ptx};
+use neco_felis_compile::{
+    compile_file_to_assembly_with_warnings,
+    compile_options::{CompileOptions, ContractMode},
+};
 use std::process::Command;
 use tempfile::TempDir;
 
@@ -6,7 +9,10 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args: Vec<_> = std::env::args().collect();
 
     if args.len() < 2 {
-        eprintln!("Usage: {} <source.fe> [-o <output>] [--ptx]", args[0]);
+        eprintln!(
+            "Usage: {} <source.fe> [-o <output>] [--ptx] [--contracts=static|runtime] [--no-environment-file]",
+            args[0]
+        );
         std::process::exit(1);
     }
 
@@ -14,6 +20,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let mut source_file = None;
     let mut output_file = None;
     let mut use_ptx = false;
+    let mut contracts = ContractMode::Static;
+    let mut use_environment_file = true;
     let mut i = 1;
 
     while i < args.len() {
@@ -31,6 +39,19 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 use_ptx = true;
                 i += 1;
             }
+            "--no-environment-file" => {
+                use_environment_file = false;
+                i += 1;
+            }
+            arg if arg.starts_with("--contracts=") => {
+                let mode = &arg["--contracts=".len()..];
+                let Some(mode) = ContractMode::parse(mode) else {
+                    eprintln!("Error: unknown contract mode {mode}, expected static or runtime");
+                    std::process::exit(1);
+                };
+                contracts = mode;
+                i += 1;
+            }
             _ => {
                 if source_file.is_none() {
                     source_file = Some(args[i].clone());
@@ -45,12 +66,21 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let source_file = source_file.ok_or("No source file specified")?;
 
+    // The checked items are kept next to the source, and loaded again while
+    // their source is unchanged
+    let environment_file = use_environment_file.then(|| format!("{source_file}.env"));
+
     // Compile to assembly
-    let assembly = if use_ptx {
-        compile_file_to_assembly_with_ptx(&source_file)?
-    } else {
-        compile_file_to_assembly(&source_file)?
+    let compile_options = CompileOptions {
+        use_ptx,
+        contracts,
+        environment_file,
     };
+    let (assembly, warnings) =
+        compile_file_to_assembly_with_warnings(&source_file, compile_options)?;
+    for warning in warnings {
+        eprintln!("warning: {warning}");
+    }
 
     // If no output file specified, print assembly to stdout
     if output_file.is_none() {
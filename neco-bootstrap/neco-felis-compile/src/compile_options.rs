@@ -1,3 +1,30 @@
 pub struct CompileOptions {
     pub use_ptx: bool,
+    pub contracts: ContractMode,
+    /// The environment file the unchanged items of the source are loaded
+    /// from, written back with the items checked
+    pub environment_file: Option<String>,
+}
+
+/// What the compiler does with the `#requires` and `#ensures` contracts of
+/// procedures, which are type checked in both modes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ContractMode {
+    /// Contracts are only checked statically
+    #[default]
+    Static,
+    /// Contracts are also checked on every call, aborting the program with a
+    /// message naming the violated contract
+    Runtime,
+}
+
+impl ContractMode {
+    /// Parses the value of `--contracts=`
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "static" => Some(ContractMode::Static),
+            "runtime" => Some(ContractMode::Runtime),
+            _ => None,
+        }
+    }
 }
@@ -2,9 +2,28 @@ use crate::{
     compile_options::CompileOptions, error::CompileError, ptx::PtxCompiler,
     statement::StatementCompiler,
 };
+use neco_felis_rename::{VariableId, Warning};
 use neco_felis_syn::*;
+use neco_felis_type_check::{PhaseTyped, TypeChecker};
 use std::collections::HashMap;
 
+/// Registers of the first arguments of procedures, extracted functions and
+/// contract functions, in the order of the System V calling convention
+pub const ARGUMENT_REGISTERS: [&str; 6] = ["rdi", "rsi", "rdx", "rcx", "r8", "r9"];
+
+/// A stack slot of a procedure, named by the ids the renamer gave its
+/// variables, so that a shadowing `#let` gets its own slot
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Slot {
+    Variable(VariableId),
+    /// A field of a struct local
+    Field(VariableId, String),
+    /// The number of elements of an array local
+    Length(VariableId),
+    /// The pointer to the column of a field of an array local
+    Column(VariableId, String),
+}
+
 #[derive(Debug, Clone)]
 pub struct ArrayInfo {
     #[allow(dead_code)]
@@ -20,20 +39,29 @@ pub struct ArrayInfo {
 pub struct AssemblyCompiler {
     pub output: String,
     pub entrypoint: Option<String>,
-    pub builtins: HashMap<String, String>,
-    pub variables: HashMap<String, i32>,
-    pub reference_variables: HashMap<String, String>, // Maps reference var -> original var
+    pub builtins: HashMap<VariableId, String>,
+    pub variables: HashMap<Slot, i32>,
+    pub reference_variables: HashMap<VariableId, VariableId>, // Maps reference var -> original var
     pub stack_offset: i32,
     pub arrays: HashMap<String, ArrayInfo>,
-    pub variable_arrays: HashMap<String, String>,
+    pub variable_arrays: HashMap<VariableId, String>,
     pub loop_stack: Vec<String>,
     pub compile_options: CompileOptions,
     pub ptx_output: String,
     pub ptx_functions: Vec<String>,
-    pub ptx_registers: HashMap<String, String>, // Maps variable names to PTX registers
+    pub ptx_registers: HashMap<VariableId, String>, // Maps variables to PTX registers
     pub ptx_next_u64_reg: usize,
     pub ptx_next_u32_reg: usize,
     pub ptx_next_f32_reg: usize,
+    /// The checker of the definitions and contracts, if the file has any
+    pub type_checker: Option<TypeChecker>,
+    /// Messages printed when a contract checked at runtime is violated
+    pub contract_messages: Vec<String>,
+    /// The text of the file, to load its unchanged items from the
+    /// environment file of the options
+    pub source: Option<(String, FileId)>,
+    /// The lint warnings of the compiled file, for the caller to report
+    pub warnings: Vec<Warning>,
 }
 
 impl AssemblyCompiler {
@@ -55,6 +83,10 @@ impl AssemblyCompiler {
             ptx_next_u64_reg: 4, // Start from %rd4 (1-3 are for params)
             ptx_next_u32_reg: 1,
             ptx_next_f32_reg: 1,
+            type_checker: None,
+            contract_messages: Vec::new(),
+            source: None,
+            warnings: Vec::new(),
         }
     }
 
@@ -64,9 +96,12 @@ impl AssemblyCompiler {
             self.output.push_str(".section .text\n");
             self.output.push_str(".globl _start\n\n");
 
+            let file = &self.check_definitions(file)?;
             for item in file.items() {
                 self.compile_item(item)?;
             }
+            self.compile_definitions(file)?;
+            self.compile_contract_messages();
 
             if let Some(entrypoint) = &self.entrypoint {
                 self.output.push_str("_start:\n");
@@ -122,9 +157,12 @@ __cu_device_ptr:
             self.output.push_str(".section .text\n");
             self.output.push_str(".globl main\n\n");
 
+            let file = &self.check_definitions(file)?;
             for item in file.items() {
                 self.compile_item(item)?;
             }
+            self.compile_definitions(file)?;
+            self.compile_contract_messages();
 
             if let Some(entrypoint) = &self.entrypoint {
                 self.output.push_str("main:\n");
@@ -248,7 +286,7 @@ cuda_context_ok:
         }
     }
 
-    pub fn compile_item(&mut self, item: &Item<PhaseParse>) -> Result<(), CompileError> {
+    pub fn compile_item(&mut self, item: &Item<PhaseTyped>) -> Result<(), CompileError> {
         match item {
             Item::Entrypoint(entrypoint) => {
                 self.entrypoint = Some(entrypoint.name.s().to_string());
@@ -256,18 +294,24 @@ cuda_context_ok:
             }
             Item::UseBuiltin(use_builtin) => {
                 self.builtins.insert(
-                    use_builtin.name.s().to_string(),
+                    use_builtin.ext.clone(),
                     use_builtin.builtin_name.s().to_string(),
                 );
                 Ok(())
             }
             Item::Proc(proc) => self.compile_proc(proc),
             Item::Array(array) => crate::arrays::compile_array(array, &mut self.arrays),
+            // Struct locals are laid out field by field where they are bound
+            Item::Struct(_) => Ok(()),
+            // Lints are checked by the renamer
+            Item::Allow(_) => Ok(()),
+            // Checked and extracted by `compile_definitions`
+            _ if crate::extraction::is_checked_item(item) => Ok(()),
             _ => Err(CompileError::UnsupportedConstruct(format!("{item:?}"))),
         }
     }
 
-    pub fn compile_proc(&mut self, proc: &ItemProc<PhaseParse>) -> Result<(), CompileError> {
+    pub fn compile_proc(&mut self, proc: &ItemProc<PhaseTyped>) -> Result<(), CompileError> {
         // Check if this is a PTX procedure
         if proc.ptx_modifier.is_some() {
             // eprintln!("DEBUG: Compiling PTX procedure: {}", proc.name.s());
@@ -275,9 +319,9 @@ cuda_context_ok:
         }
         eprintln!("DEBUG: Compiling regular procedure: {}", proc.name.s());
 
-        // Extract parameter names from the function type
-        let param_names = self.extract_proc_parameters(&proc.ty);
-        let param_count = param_names.len();
+        // Extract the parameters from the function type
+        let params = self.extract_proc_parameters(&proc.ty);
+        let param_count = params.len();
         let let_count = self.count_let_variables_in_proc_block(&proc.proc_block);
         let has_ptx_calls = self.has_ptx_calls_in_proc_block(&proc.proc_block);
 
@@ -325,31 +369,30 @@ cuda_context_ok:
         self.stack_offset = 0;
 
         // Store parameters from registers to stack and register them as variables
-        for (i, param_name) in param_names.iter().enumerate() {
+        let mut param_offsets = Vec::new();
+        for (i, param) in params.iter().enumerate() {
             self.stack_offset += 8;
             let offset = self.stack_offset;
-            self.variables.insert(param_name.clone(), offset);
+            param_offsets.push(offset);
+            if let Some(id) = param {
+                self.variables.insert(Slot::Variable(id.clone()), offset);
+            }
 
             // Store parameter from register to stack
-            match i {
-                0 => self.output.push_str(&format!(
-                    "    mov qword ptr [rbp - 8 - {}], rdi\n",
-                    offset - 8
-                )),
-                1 => self.output.push_str(&format!(
-                    "    mov qword ptr [rbp - 8 - {}], rsi\n",
-                    offset - 8
-                )),
-                // For now, only support up to 2 parameters
-                _ => {
-                    return Err(CompileError::UnsupportedConstruct(
-                        "More than 2 parameters not supported".to_string(),
-                    ));
-                }
-            }
+            let Some(register) = ARGUMENT_REGISTERS.get(i) else {
+                return Err(CompileError::UnsupportedConstruct(
+                    "More than 6 parameters not supported".to_string(),
+                ));
+            };
+            self.output.push_str(&format!(
+                "    mov qword ptr [rbp - 8 - {}], {register}\n",
+                offset - 8
+            ));
         }
 
+        self.compile_contract_checks(proc, &param_offsets, false)?;
         self.compile_proc_block(&proc.proc_block)?;
+        self.compile_contract_checks(proc, &param_offsets, true)?;
 
         if total_stack_space > 0 {
             self.output
@@ -365,19 +408,20 @@ cuda_context_ok:
         Ok(())
     }
 
-    /// Extract parameter names from a procedure type signature
-    fn extract_proc_parameters(&self, ty: &Term<PhaseParse>) -> Vec<String> {
+    /// Extract the ids of the parameters from a procedure type signature, or
+    /// `None` for the parameters without a name
+    fn extract_proc_parameters(&self, ty: &Term<PhaseTyped>) -> Vec<Option<VariableId>> {
         let mut params = Vec::new();
         Self::extract_params_recursive(ty, &mut params);
         params
     }
 
     /// Recursively extract parameters from dependent arrow types
-    fn extract_params_recursive(term: &Term<PhaseParse>, params: &mut Vec<String>) {
+    fn extract_params_recursive(term: &Term<PhaseTyped>, params: &mut Vec<Option<VariableId>>) {
         match term {
             Term::ArrowDep(arrow_dep) => {
-                // Extract parameter name from dependent arrow (x : A) -> B
-                params.push(arrow_dep.from.variable.s().to_string());
+                // Extract the parameter from dependent arrow (x : A) -> B
+                params.push(Some(arrow_dep.from.ext.clone()));
                 // Continue with the return type to find more parameters
                 Self::extract_params_recursive(&arrow_dep.to, params);
             }
@@ -389,7 +433,7 @@ cuda_context_ok:
                 } else {
                     // For non-dependent arrows A -> B, we don't have parameter names
                     // This shouldn't happen in well-formed procedure signatures, but handle it gracefully
-                    params.push(format!("param_{}", params.len()));
+                    params.push(None);
                     // Continue with the return type
                     Self::extract_params_recursive(&arrow_nodep.to, params);
                 }
@@ -401,14 +445,14 @@ cuda_context_ok:
 
     pub fn compile_proc_block(
         &mut self,
-        block: &ItemProcBlock<PhaseParse>,
+        block: &ItemProcBlock<PhaseTyped>,
     ) -> Result<(), CompileError> {
         self.compile_statements(&block.statements)
     }
 
     pub fn compile_statements(
         &mut self,
-        statements: &Statements<PhaseParse>,
+        statements: &Statements<PhaseTyped>,
     ) -> Result<(), CompileError> {
         match statements {
             Statements::Then(then) => {
@@ -455,7 +499,7 @@ cuda_context_ok:
 
     pub fn compile_proc_term(
         &mut self,
-        proc_term: &ProcTerm<PhaseParse>,
+        proc_term: &ProcTerm<PhaseTyped>,
     ) -> Result<(), CompileError> {
         crate::statement::expressions::compile_proc_term(
             proc_term,
@@ -470,14 +514,14 @@ cuda_context_ok:
 
     pub fn compile_proc_variable(
         &mut self,
-        var: &ProcTermVariable<PhaseParse>,
+        var: &ProcTermVariable<PhaseTyped>,
     ) -> Result<(), CompileError> {
         crate::statement::expressions::compile_proc_variable(var, &self.variables, &mut self.output)
     }
 
     pub fn compile_proc_apply(
         &mut self,
-        apply: &ProcTermApply<PhaseParse>,
+        apply: &ProcTermApply<PhaseTyped>,
     ) -> Result<(), CompileError> {
         crate::statement::expressions::compile_proc_apply(
             apply,
@@ -491,7 +535,7 @@ cuda_context_ok:
 
     pub fn compile_proc_field_access(
         &mut self,
-        field_access: &ProcTermFieldAccess<PhaseParse>,
+        field_access: &ProcTermFieldAccess<PhaseTyped>,
     ) -> Result<(), CompileError> {
         crate::statement::memory::compile_proc_field_access(
             field_access,
@@ -504,7 +548,7 @@ cuda_context_ok:
 
     pub fn compile_proc_dereference(
         &mut self,
-        dereference: &ProcTermDereference<PhaseParse>,
+        dereference: &ProcTermDereference<PhaseTyped>,
     ) -> Result<(), CompileError> {
         crate::statement::memory::compile_proc_dereference(
             dereference,
@@ -519,11 +563,13 @@ cuda_context_ok:
 
     pub fn compile_proc_constructor_call_with_var(
         &mut self,
-        constructor_call: &ProcTermConstructorCall<PhaseParse>,
+        constructor_call: &ProcTermConstructorCall<PhaseTyped>,
+        id: &VariableId,
         var_name: &str,
     ) -> Result<(), CompileError> {
         crate::statement::constructors::compile_proc_constructor_call_with_var(
             constructor_call,
+            id,
             var_name,
             &self.arrays,
             &mut self.output,
@@ -535,20 +581,20 @@ cuda_context_ok:
 
     pub fn compile_proc_constructor_call(
         &mut self,
-        constructor_call: &ProcTermConstructorCall<PhaseParse>,
+        constructor_call: &ProcTermConstructorCall<PhaseTyped>,
     ) -> Result<(), CompileError> {
         crate::statement::constructors::compile_proc_constructor_call(
             constructor_call,
             &self.arrays,
             &mut self.output,
             &mut self.stack_offset,
-            &mut self.variables,
+            &self.variables,
         )
     }
 
     pub fn compile_proc_syscall(
         &mut self,
-        args: &[ProcTerm<PhaseParse>],
+        args: &[ProcTerm<PhaseTyped>],
     ) -> Result<(), CompileError> {
         crate::syscall::SyscallCompiler::compile_proc_syscall(
             args,
@@ -567,7 +613,7 @@ cuda_context_ok:
 
     pub fn load_proc_argument_into_register(
         &mut self,
-        arg: &ProcTerm<PhaseParse>,
+        arg: &ProcTerm<PhaseTyped>,
         register: &str,
     ) -> Result<(), CompileError> {
         crate::syscall::SyscallCompiler::load_proc_argument_into_register(
@@ -578,15 +624,15 @@ cuda_context_ok:
         )
     }
 
-    pub fn count_let_variables_in_proc_block(&self, block: &ItemProcBlock<PhaseParse>) -> i32 {
-        crate::statement::utils::count_let_variables_in_statements(&block.statements)
+    pub fn count_let_variables_in_proc_block(&self, block: &ItemProcBlock<PhaseTyped>) -> i32 {
+        crate::statement::utils::count_let_variables_in_statements(&block.statements, &self.arrays)
     }
 
-    pub fn has_ptx_calls_in_proc_block(&self, block: &ItemProcBlock<PhaseParse>) -> bool {
+    pub fn has_ptx_calls_in_proc_block(&self, block: &ItemProcBlock<PhaseTyped>) -> bool {
         crate::statement::utils::has_ptx_calls_in_statements(&block.statements)
     }
 
-    pub fn compile_ptx_proc(&mut self, proc: &ItemProc<PhaseParse>) -> Result<(), CompileError> {
+    pub fn compile_ptx_proc(&mut self, proc: &ItemProc<PhaseTyped>) -> Result<(), CompileError> {
         eprintln!(
             "DEBUG: Starting PTX compilation for function: {}",
             proc.name.s()
@@ -626,7 +672,7 @@ cuda_context_ok:
     /// Compile a #call_ptx statement
     pub fn compile_call_ptx(
         &mut self,
-        call_ptx: &StatementCallPtx<PhaseParse>,
+        call_ptx: &StatementCallPtx<PhaseTyped>,
     ) -> Result<(), CompileError> {
         let function_name = call_ptx.function_name.s();
 
@@ -638,10 +684,10 @@ cuda_context_ok:
         }
 
         // Handle arguments
-        let (has_array, array_var_name, array_info) = if !call_ptx.args.is_empty() {
-            // Extract array name from the argument
-            let array_var_name = match &call_ptx.args[0] {
-                ProcTerm::Variable(var) => var.variable.s(),
+        let (has_array, array_var, array_info) = if !call_ptx.args.is_empty() {
+            // Extract the array variable from the argument
+            let array_var = match &call_ptx.args[0] {
+                ProcTerm::Variable(var) => var,
                 _ => {
                     return Err(CompileError::UnsupportedConstruct(
                         "call_ptx expects array variable as argument".to_string(),
@@ -650,9 +696,10 @@ cuda_context_ok:
             };
 
             // Get array info
-            let array_type_name = self.variable_arrays.get(array_var_name).ok_or_else(|| {
+            let array_type_name = self.variable_arrays.get(&array_var.ext.0).ok_or_else(|| {
                 CompileError::UnsupportedConstruct(format!(
-                    "Unknown array variable: {array_var_name}"
+                    "Unknown array variable: {}",
+                    array_var.variable.s()
                 ))
             })?;
 
@@ -660,12 +707,12 @@ cuda_context_ok:
                 CompileError::UnsupportedConstruct(format!("Unknown array type: {array_type_name}"))
             })?;
 
-            (true, array_var_name, array_info.clone())
+            (true, Some(array_var.ext.0.clone()), array_info.clone())
         } else {
             // No arguments - create dummy info
             (
                 false,
-                "",
+                None,
                 ArrayInfo {
                     element_type: String::new(),
                     field_names: vec![],
@@ -756,8 +803,11 @@ cuda_context_ok:
                 ));
 
                 // Get host pointer for this field
-                let field_ptr_var = format!("{array_var_name}_{field_name}_ptr");
-                if let Some(&offset) = self.variables.get(&field_ptr_var) {
+                if let Some(array_var) = &array_var
+                    && let Some(&offset) = self
+                        .variables
+                        .get(&Slot::Column(array_var.clone(), field_name.clone()))
+                {
                     self.output.push_str(&format!(
                         "    mov rsi, QWORD PTR [rbp - 8 - {}]\n",
                         offset - 8
@@ -865,8 +915,11 @@ cuda_context_ok:
                     .push_str(&format!("    # Copy {field_name} data back from device\n"));
 
                 // Get host pointer for this field
-                let field_ptr_var = format!("{array_var_name}_{field_name}_ptr");
-                if let Some(&offset) = self.variables.get(&field_ptr_var) {
+                if let Some(array_var) = &array_var
+                    && let Some(&offset) = self
+                        .variables
+                        .get(&Slot::Column(array_var.clone(), field_name.clone()))
+                {
                     self.output.push_str(&format!(
                         "    mov rdi, QWORD PTR [rbp - 8 - {}]\n",
                         offset - 8
@@ -0,0 +1,120 @@
+use crate::{
+    compile_options::ContractMode,
+    compiler::{ARGUMENT_REGISTERS, AssemblyCompiler},
+    error::CompileError,
+};
+use neco_felis_syn::*;
+use neco_felis_type_check::PhaseTyped;
+
+/// Exit code of a program aborted by a violated contract
+const CONTRACT_EXIT_CODE: u64 = 134;
+
+impl AssemblyCompiler {
+    /// In `ContractMode::Runtime`, calls the functions of the preconditions
+    /// of `proc`, or of its postconditions with the result in rax, and aborts
+    /// the program if one of them returns 0. The functions take the
+    /// parameters, read from their stack slots at `param_offsets`, then the
+    /// result, and rax is preserved. A contract that has no function cannot
+    /// be checked at runtime.
+    pub fn compile_contract_checks(
+        &mut self,
+        proc: &ItemProc<PhaseTyped>,
+        param_offsets: &[i32],
+        ensures: bool,
+    ) -> Result<(), CompileError> {
+        if self.compile_options.contracts != ContractMode::Runtime {
+            return Ok(());
+        }
+        let Some(type_checker) = &self.type_checker else {
+            return Ok(());
+        };
+        let contracts: Vec<_> = type_checker
+            .contracts(proc.name.s())
+            .iter()
+            .filter(|contract| contract.ensures == ensures)
+            .cloned()
+            .collect();
+        for contract in contracts {
+            let keyword = if ensures { "ensures" } else { "requires" };
+            let Some(function) = &contract.function else {
+                return Err(CompileError::UnsupportedConstruct(format!(
+                    "{}: #{keyword} {} cannot be decided at runtime",
+                    proc.name.s(),
+                    contract.condition
+                )));
+            };
+            if param_offsets.len() + usize::from(ensures) > ARGUMENT_REGISTERS.len() {
+                return Err(CompileError::UnsupportedConstruct(format!(
+                    "{}: #{keyword} {} has more than 6 arguments",
+                    proc.name.s(),
+                    contract.condition
+                )));
+            }
+            let index = self.contract_messages.len();
+            self.contract_messages.push(format!(
+                "{}: #{keyword} {} violated at {}\n",
+                proc.name.s(),
+                contract.condition,
+                contract.pos
+            ));
+
+            self.output
+                .push_str(&format!("    # #{keyword} {}\n", contract.condition));
+            if ensures {
+                self.output.push_str("    push rax\n");
+                self.output.push_str(&format!(
+                    "    mov {}, rax\n",
+                    ARGUMENT_REGISTERS[param_offsets.len()]
+                ));
+            }
+            for (register, offset) in ARGUMENT_REGISTERS.iter().zip(param_offsets) {
+                self.output.push_str(&format!(
+                    "    mov {register}, qword ptr [rbp - 8 - {}]\n",
+                    offset - 8
+                ));
+            }
+            self.output.push_str(&format!("    call {function}\n"));
+            self.output.push_str("    test rax, rax\n");
+            if ensures {
+                self.output.push_str("    pop rax\n");
+            }
+            self.output
+                .push_str(&format!("    jnz contract_ok_{index}\n"));
+
+            // write(2, message, len), then exit_group
+            self.output.push_str("    mov rax, 1\n");
+            self.output.push_str("    mov rdi, 2\n");
+            self.output
+                .push_str(&format!("    lea rsi, [rip + contract_message_{index}]\n"));
+            self.output.push_str(&format!(
+                "    mov rdx, {}\n",
+                self.contract_messages[index].len()
+            ));
+            self.output.push_str("    syscall\n");
+            self.output.push_str("    mov rax, 231\n");
+            self.output
+                .push_str(&format!("    mov rdi, {CONTRACT_EXIT_CODE}\n"));
+            self.output.push_str("    syscall\n");
+            self.output.push_str(&format!("contract_ok_{index}:\n"));
+        }
+        Ok(())
+    }
+
+    /// Emits the messages of the contracts checked at runtime
+    pub fn compile_contract_messages(&mut self) {
+        if self.contract_messages.is_empty() {
+            return;
+        }
+        self.output.push_str(".section .rodata\n");
+        for (index, message) in self.contract_messages.iter().enumerate() {
+            let escaped = message
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+            self.output
+                .push_str(&format!("contract_message_{index}:\n"));
+            self.output.push_str(&format!("    .ascii \"{escaped}\"\n"));
+        }
+        self.output.push_str(".section .text\n\n");
+    }
+}
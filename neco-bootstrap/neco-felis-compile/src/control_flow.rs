@@ -1,10 +1,11 @@
 use crate::{AssemblyCompiler, CompileError};
 use neco_felis_syn::*;
+use neco_felis_type_check::PhaseTyped;
 
 /// Compile an if statement with condition checking and branching
 pub fn compile_proc_if(
     compiler: &mut AssemblyCompiler,
-    if_expr: &ProcTermIf<PhaseParse>,
+    if_expr: &ProcTermIf<PhaseTyped>,
 ) -> Result<(), CompileError> {
     static mut LABEL_COUNTER: u32 = 0;
     let label_id = unsafe {
@@ -25,7 +26,7 @@ pub fn compile_proc_if(
             };
             // Handle builtin equality checks like __u64_eq
             if let ProcTerm::Variable(var) = &*apply.f
-                && let Some(builtin) = compiler.builtins.get(var.variable.s())
+                && let Some(builtin) = compiler.builtins.get(&var.ext.0)
                 && builtin == "u64_eq"
             {
                 if apply.args.len() != 2 {
@@ -89,7 +90,7 @@ pub fn compile_proc_if(
 /// Compile a loop statement with start and end labels
 pub fn compile_loop(
     compiler: &mut AssemblyCompiler,
-    loop_stmt: &StatementLoop<PhaseParse>,
+    loop_stmt: &StatementLoop<PhaseTyped>,
 ) -> Result<(), CompileError> {
     static mut LOOP_COUNTER: u32 = 0;
     let loop_id = unsafe {
@@ -126,7 +127,7 @@ pub fn compile_loop(
 /// Compile a break statement that jumps to the innermost loop's end label
 pub fn compile_break(
     compiler: &mut AssemblyCompiler,
-    _break_stmt: &StatementBreak<PhaseParse>,
+    _break_stmt: &StatementBreak<PhaseTyped>,
 ) -> Result<(), CompileError> {
     // Get the innermost loop's end label
     if let Some(loop_end_label) = compiler.loop_stack.last() {
@@ -1,8 +1,17 @@
+use neco_felis_rename::RenameError;
+
 #[derive(Debug, Clone)]
 pub enum CompileError {
     UnsupportedConstruct(String),
     EntrypointNotFound,
     InvalidSyscall,
+    /// The inductive types and definitions do not type check, or a definition
+    /// called from a procedure cannot be extracted
+    TypeCheck(String),
+    /// A name in the file is unbound or bound twice
+    Rename(RenameError),
+    /// The environment file of the checked items cannot be written
+    EnvironmentFile(String),
 }
 
 impl std::fmt::Display for CompileError {
@@ -11,6 +20,9 @@ impl std::fmt::Display for CompileError {
             CompileError::UnsupportedConstruct(msg) => write!(f, "Unsupported construct: {msg}"),
             CompileError::EntrypointNotFound => write!(f, "Entrypoint not found"),
             CompileError::InvalidSyscall => write!(f, "Invalid syscall"),
+            CompileError::TypeCheck(msg) => write!(f, "{msg}"),
+            CompileError::Rename(e) => write!(f, "{e}"),
+            CompileError::EnvironmentFile(msg) => write!(f, "{msg}"),
         }
     }
 }
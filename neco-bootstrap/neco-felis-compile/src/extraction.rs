@@ -0,0 +1,368 @@
+use crate::{
+    compile_options::ContractMode,
+    compiler::{ARGUMENT_REGISTERS, AssemblyCompiler},
+    error::CompileError,
+};
+use neco_felis_rename::rename_file_with_warnings;
+use neco_felis_syn::*;
+use neco_felis_type_check::{Code, Function, Operation, PhaseTyped, TypeCheckError, TypeChecker};
+
+/// Size of the heap the values of inductive types are allocated in. There
+/// is no garbage collector: allocation bumps `extracted_heap_next`.
+const HEAP_SIZE: usize = 16 * 1024 * 1024;
+
+/// Exit code of a program that runs out of heap
+const HEAP_EXHAUSTED_EXIT_CODE: u64 = 134;
+
+/// Message of a program that runs out of heap, printed on a line of its own
+const HEAP_EXHAUSTED_MESSAGE: &str = "extracted heap exhausted";
+
+impl AssemblyCompiler {
+    /// Resolves the names of `file` and type checks it, its procedures
+    /// included, returning it with the types its procedures are compiled by.
+    /// The lint warnings of the names are kept in `warnings`.
+    pub fn check_definitions(
+        &mut self,
+        file: &File<PhaseParse>,
+    ) -> Result<File<PhaseTyped>, CompileError> {
+        let (renamed, warnings) = rename_file_with_warnings(file).map_err(CompileError::Rename)?;
+        self.warnings.extend(warnings);
+        let mut type_checker = TypeChecker::new();
+        let type_error = |e: TypeCheckError| CompileError::TypeCheck(e.to_string());
+        let file = match (&self.source, &self.compile_options.environment_file) {
+            (Some((source, file_id)), Some(path)) => {
+                let cache = std::fs::read_to_string(path).ok();
+                let file = type_checker
+                    .type_source(source, *file_id, cache.as_deref())
+                    .map_err(type_error)?;
+                std::fs::write(path, type_checker.environment_file().write())
+                    .map_err(|e| CompileError::EnvironmentFile(format!("{path}: {e}")))?;
+                file
+            }
+            _ => type_checker.type_file(file, &renamed).map_err(type_error)?,
+        };
+        self.type_checker = Some(type_checker);
+        Ok(file)
+    }
+
+    /// Compiles the definitions called from the procedures of `file`, and
+    /// the functions of the contracts checked at runtime, to functions that
+    /// take and return machine words. Types and proofs are erased, and the
+    /// values of inductive types are pointers to their constructor index
+    /// followed by their fields.
+    pub fn compile_definitions(&mut self, file: &File<PhaseTyped>) -> Result<(), CompileError> {
+        let Some(type_checker) = &self.type_checker else {
+            return Ok(());
+        };
+
+        let mut called = Vec::new();
+        for item in file.items() {
+            if let Item::Proc(proc) = item {
+                called_in_statements(&proc.proc_block.statements, &mut called);
+                if self.compile_options.contracts == ContractMode::Runtime {
+                    called.extend(
+                        type_checker
+                            .contracts(proc.name.s())
+                            .iter()
+                            .filter_map(|contract| contract.function.as_deref()),
+                    );
+                }
+            }
+        }
+        let functions = type_checker
+            .extract(&called)
+            .map_err(|e| CompileError::TypeCheck(e.to_string()))?;
+        if functions.is_empty() {
+            return Ok(());
+        }
+
+        let mut compiler = FunctionCompiler {
+            type_checker,
+            output: String::new(),
+            label_count: 0,
+        };
+        for function in &functions {
+            compiler.compile_function(function)?;
+        }
+        self.output.push_str(&compiler.output);
+
+        // write(2, message, len), then exit_group
+        self.output.push_str("extracted_heap_exhausted:\n");
+        self.output.push_str("    mov rax, 1\n");
+        self.output.push_str("    mov rdi, 2\n");
+        self.output
+            .push_str("    lea rsi, [rip + extracted_heap_exhausted_message]\n");
+        self.output.push_str(&format!(
+            "    mov rdx, {}\n",
+            HEAP_EXHAUSTED_MESSAGE.len() + 1
+        ));
+        self.output.push_str("    syscall\n");
+        self.output.push_str("    mov rax, 231\n");
+        self.output
+            .push_str(&format!("    mov rdi, {HEAP_EXHAUSTED_EXIT_CODE}\n"));
+        self.output.push_str("    syscall\n");
+        self.output.push_str(".section .rodata\n");
+        self.output.push_str("extracted_heap_exhausted_message:\n");
+        self.output
+            .push_str(&format!("    .ascii \"{HEAP_EXHAUSTED_MESSAGE}\\n\"\n"));
+        self.output.push_str(".section .data\n");
+        self.output.push_str("extracted_heap_next:\n");
+        self.output.push_str("    .quad extracted_heap\n");
+        self.output.push_str("extracted_heap_end:\n");
+        self.output
+            .push_str(&format!("    .quad extracted_heap + {HEAP_SIZE}\n"));
+        self.output.push_str(".section .bss\n");
+        self.output.push_str("extracted_heap:\n");
+        self.output.push_str(&format!("    .zero {HEAP_SIZE}\n"));
+        self.output.push_str(".section .text\n\n");
+        Ok(())
+    }
+}
+
+/// Items that are type checked rather than compiled
+pub fn is_checked_item(item: &Item<PhaseTyped>) -> bool {
+    matches!(
+        item,
+        Item::Inductive(_)
+            | Item::Definition(_)
+            | Item::Theorem(_)
+            | Item::Axiom(_)
+            | Item::Mutual(_)
+            | Item::Structure(_)
+            | Item::Check(_)
+            | Item::Eval(_)
+            | Item::Print(_)
+            | Item::PrintAxioms(_)
+    )
+}
+
+/// Collects the names of the functions applied in `statements`
+fn called_in_statements<'a>(statements: &'a Statements<PhaseTyped>, called: &mut Vec<&'a str>) {
+    match statements {
+        Statements::Then(then) => {
+            called_in_statement(&then.head, called);
+            called_in_statements(&then.tail, called);
+        }
+        Statements::Statement(statement) => called_in_statement(statement, called),
+        Statements::Nil => {}
+    }
+}
+
+fn called_in_statement<'a>(statement: &'a Statement<PhaseTyped>, called: &mut Vec<&'a str>) {
+    match statement {
+        Statement::Let(let_stmt) => called_in_proc_term(&let_stmt.value, called),
+        Statement::LetMut(let_mut_stmt) => called_in_proc_term(&let_mut_stmt.value, called),
+        Statement::Assign(assign_stmt) => called_in_proc_term(&assign_stmt.value, called),
+        Statement::FieldAssign(field_assign_stmt) => {
+            called_in_proc_term(&field_assign_stmt.value, called)
+        }
+        Statement::Loop(loop_stmt) => called_in_statements(&loop_stmt.body, called),
+        Statement::Return(return_stmt) => called_in_proc_term(&return_stmt.value, called),
+        Statement::Expr(proc_term) => called_in_proc_term(proc_term, called),
+        Statement::Break(_) | Statement::CallPtx(_) | Statement::Ext(_) => {}
+    }
+}
+
+fn called_in_proc_term<'a>(proc_term: &'a ProcTerm<PhaseTyped>, called: &mut Vec<&'a str>) {
+    match proc_term {
+        ProcTerm::Apply(apply) => {
+            if let ProcTerm::Variable(var) = &*apply.f {
+                called.push(var.variable.s());
+            }
+            for arg in &apply.args {
+                called_in_proc_term(arg, called);
+            }
+        }
+        ProcTerm::Paren(paren) => called_in_proc_term(&paren.proc_term, called),
+        ProcTerm::Dereference(dereference) => called_in_proc_term(&dereference.term, called),
+        ProcTerm::If(if_expr) => {
+            called_in_statements(&if_expr.condition, called);
+            called_in_statements(&if_expr.then_body, called);
+            if let Some(else_clause) = &if_expr.else_clause {
+                called_in_statements(&else_clause.else_body, called);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Compiles extracted functions, leaving the value of each code in rax
+struct FunctionCompiler<'a> {
+    type_checker: &'a TypeChecker,
+    output: String,
+    /// Number of switches and primitive operations with labels so far
+    label_count: usize,
+}
+
+impl FunctionCompiler<'_> {
+    fn compile_function(&mut self, function: &Function) -> Result<(), CompileError> {
+        let name = self.type_checker.name(function.name);
+        if function.params > ARGUMENT_REGISTERS.len() {
+            return Err(CompileError::UnsupportedConstruct(format!(
+                "{name} has more than 6 parameters"
+            )));
+        }
+        // Locals are stored below rbp, keeping the stack aligned to 16 bytes
+        let stack_space = (function.locals * 8).div_ceil(16) * 16;
+
+        self.output.push_str(&format!("{name}:\n"));
+        self.output.push_str("    push rbp\n");
+        self.output.push_str("    mov rbp, rsp\n");
+        if stack_space > 0 {
+            self.output
+                .push_str(&format!("    sub rsp, {stack_space}\n"));
+        }
+        for (local, register) in ARGUMENT_REGISTERS.iter().take(function.params).enumerate() {
+            self.output
+                .push_str(&format!("    mov qword ptr {}, {register}\n", slot(local)));
+        }
+        self.compile_code(&function.body)?;
+        self.output.push_str("    mov rsp, rbp\n");
+        self.output.push_str("    pop rbp\n");
+        self.output.push_str("    ret\n\n");
+        Ok(())
+    }
+
+    fn compile_code(&mut self, code: &Code) -> Result<(), CompileError> {
+        match code {
+            Code::Erased => self.output.push_str("    mov rax, 0\n"),
+            Code::Local(local) => self
+                .output
+                .push_str(&format!("    mov rax, qword ptr {}\n", slot(*local))),
+            Code::Literal(value) => self.output.push_str(&format!("    mov rax, {value}\n")),
+            Code::Primitive(operation, args) => {
+                let [a, b] = args.as_slice() else {
+                    unreachable!("primitive operations take two arguments");
+                };
+                self.compile_code(a)?;
+                self.output.push_str("    push rax\n");
+                self.compile_code(b)?;
+                self.output.push_str("    mov rcx, rax\n");
+                self.output.push_str("    pop rax\n");
+                self.compile_operation(*operation);
+            }
+            Code::Call(function, args) => {
+                if args.len() > ARGUMENT_REGISTERS.len() {
+                    return Err(CompileError::UnsupportedConstruct(
+                        "More than 6 arguments not supported".to_string(),
+                    ));
+                }
+                for arg in args {
+                    self.compile_code(arg)?;
+                    self.output.push_str("    push rax\n");
+                }
+                for register in ARGUMENT_REGISTERS[..args.len()].iter().rev() {
+                    self.output.push_str(&format!("    pop {register}\n"));
+                }
+                let name = self.type_checker.name(*function);
+                self.output.push_str(&format!("    call {name}\n"));
+            }
+            Code::Construct { tag, fields } => {
+                for field in fields {
+                    self.compile_code(field)?;
+                    self.output.push_str("    push rax\n");
+                }
+                self.output
+                    .push_str("    mov rax, qword ptr extracted_heap_next[rip]\n");
+                self.output.push_str(&format!(
+                    "    lea rcx, [rax + {}]\n",
+                    8 * (fields.len() + 1)
+                ));
+                self.output
+                    .push_str("    cmp rcx, qword ptr extracted_heap_end[rip]\n");
+                self.output.push_str("    ja extracted_heap_exhausted\n");
+                self.output
+                    .push_str("    mov qword ptr extracted_heap_next[rip], rcx\n");
+                self.output
+                    .push_str(&format!("    mov qword ptr [rax], {tag}\n"));
+                for i in (0..fields.len()).rev() {
+                    self.output.push_str("    pop rcx\n");
+                    self.output
+                        .push_str(&format!("    mov qword ptr [rax + {}], rcx\n", 8 * (i + 1)));
+                }
+            }
+            Code::Let { local, value, body } => {
+                self.compile_code(value)?;
+                self.output
+                    .push_str(&format!("    mov qword ptr {}, rax\n", slot(*local)));
+                self.compile_code(body)?;
+            }
+            Code::Switch {
+                scrutinee,
+                branches,
+            } => {
+                let switch_id = self.label_count;
+                self.label_count += 1;
+                self.compile_code(scrutinee)?;
+                self.output.push_str("    mov rcx, qword ptr [rax]\n");
+                for tag in 0..branches.len() {
+                    self.output.push_str(&format!("    cmp rcx, {tag}\n"));
+                    self.output
+                        .push_str(&format!("    je extracted_case_{switch_id}_{tag}\n"));
+                }
+                self.output.push_str("    ud2\n");
+                for (tag, branch) in branches.iter().enumerate() {
+                    self.output
+                        .push_str(&format!("extracted_case_{switch_id}_{tag}:\n"));
+                    for (i, field) in branch.fields.iter().enumerate() {
+                        if let Some(local) = field {
+                            self.output.push_str(&format!(
+                                "    mov rcx, qword ptr [rax + {}]\n",
+                                8 * (i + 1)
+                            ));
+                            self.output
+                                .push_str(&format!("    mov qword ptr {}, rcx\n", slot(*local)));
+                        }
+                    }
+                    self.compile_code(&branch.body)?;
+                    self.output
+                        .push_str(&format!("    jmp extracted_end_{switch_id}\n"));
+                }
+                self.output
+                    .push_str(&format!("extracted_end_{switch_id}:\n"));
+            }
+            Code::Unreachable => self.output.push_str("    ud2\n"),
+        }
+        Ok(())
+    }
+
+    /// Computes `rax op rcx` into rax as the kernel does: division by zero
+    /// gives 0 and leaves the dividend as remainder instead of trapping
+    fn compile_operation(&mut self, operation: Operation) {
+        match operation {
+            Operation::Add => self.output.push_str("    add rax, rcx\n"),
+            Operation::Sub => self.output.push_str("    sub rax, rcx\n"),
+            Operation::Mul => self.output.push_str("    imul rax, rcx\n"),
+            Operation::Div | Operation::Mod => {
+                let label_id = self.label_count;
+                self.label_count += 1;
+                if operation == Operation::Div {
+                    self.output.push_str("    mov rdx, 0\n");
+                } else {
+                    self.output.push_str("    mov rdx, rax\n");
+                }
+                self.output.push_str("    test rcx, rcx\n");
+                self.output
+                    .push_str(&format!("    jz extracted_by_zero_{label_id}\n"));
+                self.output.push_str("    xor edx, edx\n");
+                self.output.push_str("    div rcx\n");
+                if operation == Operation::Div {
+                    self.output.push_str("    mov rdx, rax\n");
+                }
+                self.output
+                    .push_str(&format!("extracted_by_zero_{label_id}:\n"));
+                self.output.push_str("    mov rax, rdx\n");
+            }
+            Operation::Eq => {
+                self.output.push_str("    cmp rax, rcx\n");
+                self.output.push_str("    sete al\n");
+                self.output.push_str("    movzx rax, al\n");
+            }
+        }
+    }
+}
+
+/// The stack slot of a local
+fn slot(local: usize) -> String {
+    format!("[rbp - {}]", 8 * (local + 1))
+}
@@ -1,21 +1,25 @@
+use neco_felis_rename::Warning;
 use neco_felis_syn::*;
+use neco_felis_type_check::PhaseTyped;
 
 // Module declarations
 pub mod arithmetic;
 pub mod arrays;
 pub mod compile_options;
 pub mod compiler;
+pub mod contract;
 pub mod control_flow;
 pub mod error;
+pub mod extraction;
 pub mod ptx;
 pub mod statement;
 pub mod syscall;
 
 // Re-exports
-pub use compiler::{ArrayInfo, AssemblyCompiler};
+pub use compiler::{ArrayInfo, AssemblyCompiler, Slot};
 pub use error::CompileError;
 
-use crate::compile_options::CompileOptions;
+use crate::compile_options::{CompileOptions, ContractMode};
 
 /// Main public API function to compile a file to assembly
 pub fn compile_to_assembly(
@@ -27,29 +31,41 @@ pub fn compile_to_assembly(
 }
 
 pub fn compile_file_to_assembly(file_path: &str) -> Result<String, Box<dyn std::error::Error>> {
-    let mut file_id_generator = FileIdGenerator::new();
-    let file_id = file_id_generator.generate_file_id();
-    let source = std::fs::read_to_string(file_path)?;
-    let tokens = Token::lex(&source, file_id);
-
-    let mut i = 0;
-    let file = File::parse(&tokens, &mut i)?.ok_or("Failed to parse file")?;
-    if i != tokens.len() {
-        return Err(format!("Failed to parse file. token at {} / {}", i, tokens.len()).into());
-    }
-
-    let compile_options = CompileOptions { use_ptx: false };
-    let assembly = compile_to_assembly(&file, compile_options)?;
-    Ok(assembly)
+    let compile_options = CompileOptions {
+        use_ptx: false,
+        contracts: ContractMode::Static,
+        environment_file: None,
+    };
+    compile_file_to_assembly_with_options(file_path, compile_options)
 }
 
 pub fn compile_file_to_assembly_with_ptx(
     file_path: &str,
 ) -> Result<String, Box<dyn std::error::Error>> {
+    let compile_options = CompileOptions {
+        use_ptx: true,
+        contracts: ContractMode::Static,
+        environment_file: None,
+    };
+    compile_file_to_assembly_with_options(file_path, compile_options)
+}
+
+pub fn compile_file_to_assembly_with_options(
+    file_path: &str,
+    compile_options: CompileOptions,
+) -> Result<String, Box<dyn std::error::Error>> {
+    compile_file_to_assembly_with_warnings(file_path, compile_options).map(|(assembly, _)| assembly)
+}
+
+/// Compiles a file to assembly, with the lint warnings of the file
+pub fn compile_file_to_assembly_with_warnings(
+    file_path: &str,
+    compile_options: CompileOptions,
+) -> Result<(String, Vec<Warning>), Box<dyn std::error::Error>> {
     let mut file_id_generator = FileIdGenerator::new();
     let file_id = file_id_generator.generate_file_id();
     let source = std::fs::read_to_string(file_path)?;
-    let tokens = Token::lex(&source, file_id);
+    let tokens = Token::try_lex(&source, file_id)?;
 
     let mut i = 0;
     let file = File::parse(&tokens, &mut i)?.ok_or("Failed to parse file")?;
@@ -57,16 +73,17 @@ pub fn compile_file_to_assembly_with_ptx(
         return Err(format!("Failed to parse file. token at {} / {}", i, tokens.len()).into());
     }
 
-    let compile_options = CompileOptions { use_ptx: true };
-    let assembly = compile_to_assembly(&file, compile_options)?;
-    Ok(assembly)
+    let mut compiler = AssemblyCompiler::new(compile_options);
+    compiler.source = Some((source, file_id));
+    let assembly = compiler.compile_file(&file)?;
+    Ok((assembly, compiler.warnings))
 }
 
 impl AssemblyCompiler {
     /// Compile a return statement
     pub fn compile_return(
         &mut self,
-        return_stmt: &StatementReturn<PhaseParse>,
+        return_stmt: &StatementReturn<PhaseTyped>,
     ) -> Result<(), CompileError> {
         // Compile the return value expression
         self.compile_proc_term(&return_stmt.value)?;
@@ -1,16 +1,18 @@
-use crate::error::CompileError;
+use crate::{Slot, error::CompileError};
+use neco_felis_rename::VariableId;
 use neco_felis_syn::*;
+use neco_felis_type_check::{PhaseTyped, ProcType};
 use std::collections::HashMap;
 
 pub struct PtxCompiler {
     pub ptx_output: String,
     pub ptx_functions: Vec<String>,
-    pub ptx_registers: HashMap<String, String>, // Maps variable names to PTX registers
+    pub ptx_registers: HashMap<VariableId, String>, // Maps variable names to PTX registers
     pub ptx_next_u64_reg: usize,
     pub ptx_next_u32_reg: usize,
     pub ptx_next_f32_reg: usize,
-    pub variables: HashMap<String, i32>,
-    pub builtins: HashMap<String, String>,
+    pub variables: HashMap<Slot, i32>,
+    pub builtins: HashMap<VariableId, String>,
 }
 
 impl Default for PtxCompiler {
@@ -33,7 +35,7 @@ impl PtxCompiler {
         Self::default()
     }
 
-    pub fn compile_ptx_proc(&mut self, proc: &ItemProc<PhaseParse>) -> Result<(), CompileError> {
+    pub fn compile_ptx_proc(&mut self, proc: &ItemProc<PhaseTyped>) -> Result<(), CompileError> {
         // Reset PTX state for this function
         self.ptx_registers.clear();
         self.ptx_next_u64_reg = 4; // Start from %rd4 (1-3 are for params)
@@ -80,14 +82,14 @@ impl PtxCompiler {
     }
 
     /// Extract parameter names from a procedure type signature
-    fn extract_proc_parameters(&self, ty: &Term<PhaseParse>) -> Vec<String> {
+    fn extract_proc_parameters(&self, ty: &Term<PhaseTyped>) -> Vec<String> {
         let mut params = Vec::new();
         Self::extract_params_recursive(ty, &mut params);
         params
     }
 
     /// Recursively extract parameters from dependent arrow types
-    fn extract_params_recursive(term: &Term<PhaseParse>, params: &mut Vec<String>) {
+    fn extract_params_recursive(term: &Term<PhaseTyped>, params: &mut Vec<String>) {
         match term {
             Term::ArrowDep(arrow_dep) => {
                 // Extract parameter name from dependent arrow (x : A) -> B
@@ -115,13 +117,14 @@ impl PtxCompiler {
 
     pub fn compile_ptx_proc_block(
         &mut self,
-        block: &ItemProcBlock<PhaseParse>,
+        block: &ItemProcBlock<PhaseTyped>,
         has_params: bool,
     ) -> Result<(), CompileError> {
         // Initialize PTX registers for parameters with higher limit
         self.ptx_output.push_str("    .reg .b64 %rd<100>;\n");
         self.ptx_output.push_str("    .reg .b32 %r<100>;\n");
         self.ptx_output.push_str("    .reg .b32 %f<100>;\n");
+        self.ptx_output.push_str("    .reg .pred %p<2>;\n");
         self.ptx_output.push('\n');
 
         // Load parameters only if there are any
@@ -146,7 +149,7 @@ impl PtxCompiler {
 
     pub fn compile_ptx_statements(
         &mut self,
-        statements: &Statements<PhaseParse>,
+        statements: &Statements<PhaseTyped>,
     ) -> Result<(), CompileError> {
         match statements {
             Statements::Then(then) => {
@@ -163,17 +166,18 @@ impl PtxCompiler {
 
     pub fn compile_ptx_statement(
         &mut self,
-        statement: &Statement<PhaseParse>,
+        statement: &Statement<PhaseTyped>,
     ) -> Result<(), CompileError> {
         match statement {
             Statement::Let(let_stmt) => {
                 // Compile the let statement for PTX
-                let var_name = let_stmt.variable_name().to_string();
+                let (id, _) = &let_stmt.ext;
                 let result_reg = self.compile_ptx_proc_term(&let_stmt.value)?;
 
                 // Store the register mapping
-                self.ptx_registers.insert(var_name.clone(), result_reg);
-                self.variables.insert(var_name, self.variables.len() as i32);
+                self.ptx_registers.insert(id.clone(), result_reg);
+                self.variables
+                    .insert(Slot::Variable(id.clone()), self.variables.len() as i32);
                 Ok(())
             }
             Statement::FieldAssign(field_assign) => {
@@ -205,21 +209,23 @@ impl PtxCompiler {
                     }
                 };
 
-                // Calculate address and store
-                // mul.lo.u64 %rd_temp, %index_reg, 8;  // index * sizeof(u64)
-                // add.u64 %rd_addr, %field_ptr, %rd_temp;
-                // st.global.u64 [%rd_addr], %value_reg;
+                // The stride and the store follow the type of the field
+                let (size, ty) = match &field_assign.field_access.ext.1 {
+                    ProcType::Reference(ty) if **ty == ProcType::F32 => (4, "f32"),
+                    _ => (8, "u64"),
+                };
 
                 let temp_reg = self.allocate_ptx_u64_register();
                 let addr_reg = self.allocate_ptx_u64_register();
 
-                self.ptx_output
-                    .push_str(&format!("    mul.lo.u64 {temp_reg}, {index_reg}, 8;\n"));
+                self.ptx_output.push_str(&format!(
+                    "    mul.lo.u64 {temp_reg}, {index_reg}, {size};\n"
+                ));
                 self.ptx_output.push_str(&format!(
                     "    add.u64 {addr_reg}, {field_ptr}, {temp_reg};\n"
                 ));
                 self.ptx_output
-                    .push_str(&format!("    st.global.u64 [{addr_reg}], {value_reg};\n"));
+                    .push_str(&format!("    st.global.{ty} [{addr_reg}], {value_reg};\n"));
 
                 Ok(())
             }
@@ -229,6 +235,16 @@ impl PtxCompiler {
         }
     }
 
+    /// Replaces the unspecified result of a division by zero with the one of
+    /// the kernel, `fallback` being the quotient 0 or the dividend
+    fn push_ptx_zero_divisor(&mut self, divisor: &str, fallback: &str, result_reg: &str) {
+        self.ptx_output
+            .push_str(&format!("    setp.eq.u64 %p1, {divisor}, 0;\n"));
+        self.ptx_output.push_str(&format!(
+            "    selp.u64 {result_reg}, {fallback}, {result_reg}, %p1;\n"
+        ));
+    }
+
     // Helper methods for PTX register allocation
     pub fn allocate_ptx_u64_register(&mut self) -> String {
         let reg = format!("%rd{}", self.ptx_next_u64_reg);
@@ -251,15 +267,27 @@ impl PtxCompiler {
     // Compile a ProcTerm to PTX and return the result register
     pub fn compile_ptx_proc_term(
         &mut self,
-        proc_term: &ProcTerm<PhaseParse>,
+        proc_term: &ProcTerm<PhaseTyped>,
     ) -> Result<String, CompileError> {
         use neco_felis_syn::ProcTerm;
 
         match proc_term {
-            ProcTerm::Variable(var) => self.compile_ptx_variable(var.variable.s()),
+            ProcTerm::Variable(var) => self.compile_ptx_variable(&var.ext.0, var.variable.s()),
             ProcTerm::Number(num) => {
-                // Handle integer literals
                 let value_str = num.number.s();
+                let value_str = value_str
+                    .strip_suffix("u64")
+                    .or_else(|| value_str.strip_suffix("f32"))
+                    .unwrap_or(value_str);
+                if num.ext == ProcType::F32 {
+                    let value: f32 = value_str.parse().map_err(|_| {
+                        CompileError::UnsupportedConstruct(format!("Invalid number: {value_str}"))
+                    })?;
+                    let reg = self.allocate_ptx_f32_register();
+                    self.ptx_output
+                        .push_str(&format!("    mov.b32 {reg}, 0x{:08x};\n", value.to_bits()));
+                    return Ok(reg);
+                }
                 let value: u64 = value_str.parse().map_err(|_| {
                     CompileError::UnsupportedConstruct(format!("Invalid number: {value_str}"))
                 })?;
@@ -280,9 +308,13 @@ impl PtxCompiler {
     }
 
     // Helper to compile a variable reference
-    pub fn compile_ptx_variable(&mut self, var_name: &str) -> Result<String, CompileError> {
+    pub fn compile_ptx_variable(
+        &mut self,
+        id: &VariableId,
+        var_name: &str,
+    ) -> Result<String, CompileError> {
         // Check if this is a PTX builtin function
-        if let Some(builtin) = self.builtins.get(var_name) {
+        if let Some(builtin) = self.builtins.get(id) {
             match builtin.as_str() {
                 "ctaid_x" => {
                     let reg = self.allocate_ptx_u32_register();
@@ -316,7 +348,7 @@ impl PtxCompiler {
                 }
                 _ => {
                     // Check if it's a variable
-                    if let Some(reg) = self.ptx_registers.get(var_name) {
+                    if let Some(reg) = self.ptx_registers.get(id) {
                         Ok(reg.clone())
                     } else {
                         Err(CompileError::UnsupportedConstruct(format!(
@@ -325,7 +357,7 @@ impl PtxCompiler {
                     }
                 }
             }
-        } else if let Some(reg) = self.ptx_registers.get(var_name) {
+        } else if let Some(reg) = self.ptx_registers.get(id) {
             Ok(reg.clone())
         } else {
             Err(CompileError::UnsupportedConstruct(format!(
@@ -335,13 +367,13 @@ impl PtxCompiler {
     }
 
     // Compile a term to PTX and return the result register
-    pub fn compile_ptx_term(&mut self, term: &Term<PhaseParse>) -> Result<String, CompileError> {
+    pub fn compile_ptx_term(&mut self, term: &Term<PhaseTyped>) -> Result<String, CompileError> {
         match term {
             Term::Variable(var) => {
-                let var_name = var.variable.s();
+                let (id, var_name) = (&var.ext, var.variable.s());
 
                 // Check if this is a PTX builtin function
-                if let Some(builtin) = self.builtins.get(var_name) {
+                if let Some(builtin) = self.builtins.get(id) {
                     match builtin.as_str() {
                         "ctaid_x" => {
                             let reg = self.allocate_ptx_u32_register();
@@ -375,7 +407,7 @@ impl PtxCompiler {
                         }
                         _ => {
                             // Check if it's a variable
-                            if let Some(reg) = self.ptx_registers.get(var_name) {
+                            if let Some(reg) = self.ptx_registers.get(id) {
                                 Ok(reg.clone())
                             } else {
                                 Err(CompileError::UnsupportedConstruct(format!(
@@ -384,7 +416,7 @@ impl PtxCompiler {
                             }
                         }
                     }
-                } else if let Some(reg) = self.ptx_registers.get(var_name) {
+                } else if let Some(reg) = self.ptx_registers.get(id) {
                     Ok(reg.clone())
                 } else {
                     Err(CompileError::UnsupportedConstruct(format!(
@@ -419,14 +451,12 @@ impl PtxCompiler {
 
     pub fn compile_ptx_apply(
         &mut self,
-        apply: &TermApply<PhaseParse>,
+        apply: &TermApply<PhaseTyped>,
     ) -> Result<String, CompileError> {
         // Handle PTX builtin function applications
         match &*apply.f {
             Term::Variable(var) => {
-                let func_name = var.variable.s();
-
-                if let Some(builtin) = self.builtins.get(func_name) {
+                if let Some(builtin) = self.builtins.get(&var.ext) {
                     match builtin.as_str() {
                         "u64_add" => {
                             // Expect two arguments
@@ -482,6 +512,7 @@ impl PtxCompiler {
                                 self.ptx_output.push_str(&format!(
                                     "    div.u64 {result_reg}, {arg1_reg}, {arg2_reg};\n"
                                 ));
+                                self.push_ptx_zero_divisor(&arg2_reg, "0", &result_reg);
                                 Ok(result_reg)
                             } else {
                                 Err(CompileError::UnsupportedConstruct(
@@ -497,6 +528,7 @@ impl PtxCompiler {
                                 self.ptx_output.push_str(&format!(
                                     "    rem.u64 {result_reg}, {arg1_reg}, {arg2_reg};\n"
                                 ));
+                                self.push_ptx_zero_divisor(&arg2_reg, &arg1_reg, &result_reg);
                                 Ok(result_reg)
                             } else {
                                 Err(CompileError::UnsupportedConstruct(
@@ -623,16 +655,14 @@ impl PtxCompiler {
 
     pub fn compile_ptx_proc_apply(
         &mut self,
-        apply: &ProcTermApply<PhaseParse>,
+        apply: &ProcTermApply<PhaseTyped>,
     ) -> Result<String, CompileError> {
         use neco_felis_syn::ProcTerm;
 
         // Handle PTX builtin function applications
         match &*apply.f {
             ProcTerm::Variable(var) => {
-                let func_name = var.variable.s();
-
-                if let Some(builtin) = self.builtins.get(func_name) {
+                if let Some(builtin) = self.builtins.get(&var.ext.0) {
                     match builtin.as_str() {
                         "u64_add" => {
                             // Expect two arguments
@@ -688,6 +718,7 @@ impl PtxCompiler {
                                 self.ptx_output.push_str(&format!(
                                     "    div.u64 {result_reg}, {arg1_reg}, {arg2_reg};\n"
                                 ));
+                                self.push_ptx_zero_divisor(&arg2_reg, "0", &result_reg);
                                 Ok(result_reg)
                             } else {
                                 Err(CompileError::UnsupportedConstruct(
@@ -703,6 +734,7 @@ impl PtxCompiler {
                                 self.ptx_output.push_str(&format!(
                                     "    rem.u64 {result_reg}, {arg1_reg}, {arg2_reg};\n"
                                 ));
+                                self.push_ptx_zero_divisor(&arg2_reg, &arg1_reg, &result_reg);
                                 Ok(result_reg)
                             } else {
                                 Err(CompileError::UnsupportedConstruct(
@@ -738,36 +770,13 @@ impl PtxCompiler {
                                 ))
                             }
                         }
-                        "f32" => {
-                            // Handle float literal
-                            if apply.args.len() == 1 {
-                                if let ProcTerm::Number(num) = &apply.args[0] {
-                                    let value_str = num.number.s();
-                                    // Try to parse as float directly
-                                    let float_val: f32 = value_str.parse().unwrap_or_else(|_| {
-                                        // If parsing fails, try as integer
-                                        let int_val: u64 = value_str.parse().unwrap_or(0);
-                                        int_val as f32
-                                    });
-                                    let result_reg = self.allocate_ptx_f32_register();
-
-                                    // Convert float to its bit representation
-                                    let bits = float_val.to_bits();
-                                    self.ptx_output.push_str(&format!(
-                                        "    mov.b32 {result_reg}, 0x{bits:08x};\n"
-                                    ));
-                                    Ok(result_reg)
-                                } else {
-                                    Err(CompileError::UnsupportedConstruct(
-                                        "f32 requires a number literal".to_string(),
-                                    ))
-                                }
-                            } else {
-                                Err(CompileError::UnsupportedConstruct(
-                                    "f32 requires one argument".to_string(),
-                                ))
-                            }
+                        // The literal is typed as f32
+                        "f32" if apply.args.len() == 1 => {
+                            self.compile_ptx_proc_term(&apply.args[0])
                         }
+                        "f32" => Err(CompileError::UnsupportedConstruct(
+                            "f32 requires one argument".to_string(),
+                        )),
                         "f32_mul" => {
                             if apply.args.len() == 2 {
                                 let arg1_reg = self.compile_ptx_proc_term(&apply.args[0])?;
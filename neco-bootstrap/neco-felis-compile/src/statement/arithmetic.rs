@@ -1,13 +1,18 @@
-use crate::{ArrayInfo, error::CompileError};
+use crate::{ArrayInfo, Slot, error::CompileError};
+use neco_felis_rename::VariableId;
 use neco_felis_syn::*;
+use neco_felis_type_check::PhaseTyped;
 use std::collections::HashMap;
 
 use super::memory;
 
 // Direct arithmetic compilation functions that return results in rax
 pub fn compile_u64_add_direct(
-    apply: &ProcTermApply<PhaseParse>,
-    variables: &HashMap<String, i32>,
+    apply: &ProcTermApply<PhaseTyped>,
+    variables: &HashMap<Slot, i32>,
+    builtins: &HashMap<VariableId, String>,
+    arrays: &HashMap<String, ArrayInfo>,
+    variable_arrays: &HashMap<VariableId, String>,
     output: &mut String,
 ) -> Result<(), CompileError> {
     if apply.args.len() != 2 {
@@ -21,17 +26,36 @@ pub fn compile_u64_add_direct(
     let arg2 = &apply.args[1];
 
     // Load first argument into rax
-    memory::load_proc_argument_into_register(arg1, "rax", variables, output)?;
+    memory::load_proc_argument_into_register(
+        arg1,
+        "rax",
+        variables,
+        builtins,
+        arrays,
+        variable_arrays,
+        output,
+    )?;
     // Load second argument into rbx and add to rax
-    memory::load_proc_argument_into_register(arg2, "rbx", variables, output)?;
+    memory::load_proc_argument_into_register(
+        arg2,
+        "rbx",
+        variables,
+        builtins,
+        arrays,
+        variable_arrays,
+        output,
+    )?;
     output.push_str("    add rax, rbx\n");
 
     Ok(())
 }
 
 pub fn compile_u64_sub_direct(
-    apply: &ProcTermApply<PhaseParse>,
-    variables: &HashMap<String, i32>,
+    apply: &ProcTermApply<PhaseTyped>,
+    variables: &HashMap<Slot, i32>,
+    builtins: &HashMap<VariableId, String>,
+    arrays: &HashMap<String, ArrayInfo>,
+    variable_arrays: &HashMap<VariableId, String>,
     output: &mut String,
 ) -> Result<(), CompileError> {
     if apply.args.len() != 2 {
@@ -45,17 +69,36 @@ pub fn compile_u64_sub_direct(
     let arg2 = &apply.args[1];
 
     // Load first argument into rax
-    memory::load_proc_argument_into_register(arg1, "rax", variables, output)?;
+    memory::load_proc_argument_into_register(
+        arg1,
+        "rax",
+        variables,
+        builtins,
+        arrays,
+        variable_arrays,
+        output,
+    )?;
     // Load second argument into rbx and subtract from rax
-    memory::load_proc_argument_into_register(arg2, "rbx", variables, output)?;
+    memory::load_proc_argument_into_register(
+        arg2,
+        "rbx",
+        variables,
+        builtins,
+        arrays,
+        variable_arrays,
+        output,
+    )?;
     output.push_str("    sub rax, rbx\n");
 
     Ok(())
 }
 
 pub fn compile_u64_mul_direct(
-    apply: &ProcTermApply<PhaseParse>,
-    variables: &HashMap<String, i32>,
+    apply: &ProcTermApply<PhaseTyped>,
+    variables: &HashMap<Slot, i32>,
+    builtins: &HashMap<VariableId, String>,
+    arrays: &HashMap<String, ArrayInfo>,
+    variable_arrays: &HashMap<VariableId, String>,
     output: &mut String,
 ) -> Result<(), CompileError> {
     if apply.args.len() != 2 {
@@ -69,17 +112,36 @@ pub fn compile_u64_mul_direct(
     let arg2 = &apply.args[1];
 
     // Load first argument into rax
-    memory::load_proc_argument_into_register(arg1, "rax", variables, output)?;
+    memory::load_proc_argument_into_register(
+        arg1,
+        "rax",
+        variables,
+        builtins,
+        arrays,
+        variable_arrays,
+        output,
+    )?;
     // Load second argument into rbx and multiply with rax
-    memory::load_proc_argument_into_register(arg2, "rbx", variables, output)?;
+    memory::load_proc_argument_into_register(
+        arg2,
+        "rbx",
+        variables,
+        builtins,
+        arrays,
+        variable_arrays,
+        output,
+    )?;
     output.push_str("    imul rax, rbx\n");
 
     Ok(())
 }
 
 pub fn compile_u64_div_direct(
-    apply: &ProcTermApply<PhaseParse>,
-    variables: &HashMap<String, i32>,
+    apply: &ProcTermApply<PhaseTyped>,
+    variables: &HashMap<Slot, i32>,
+    builtins: &HashMap<VariableId, String>,
+    arrays: &HashMap<String, ArrayInfo>,
+    variable_arrays: &HashMap<VariableId, String>,
     output: &mut String,
 ) -> Result<(), CompileError> {
     if apply.args.len() != 2 {
@@ -93,20 +155,36 @@ pub fn compile_u64_div_direct(
     let arg2 = &apply.args[1];
 
     // Load first argument into rax
-    memory::load_proc_argument_into_register(arg1, "rax", variables, output)?;
+    memory::load_proc_argument_into_register(
+        arg1,
+        "rax",
+        variables,
+        builtins,
+        arrays,
+        variable_arrays,
+        output,
+    )?;
     // Load second argument into rbx
-    memory::load_proc_argument_into_register(arg2, "rbx", variables, output)?;
-    // Clear rdx for division
-    output.push_str("    xor rdx, rdx\n");
-    // Divide rax by rbx, result in rax
-    output.push_str("    div rbx\n");
+    memory::load_proc_argument_into_register(
+        arg2,
+        "rbx",
+        variables,
+        builtins,
+        arrays,
+        variable_arrays,
+        output,
+    )?;
+    crate::arithmetic::push_u64_div(output);
 
     Ok(())
 }
 
 pub fn compile_u64_mod_direct(
-    apply: &ProcTermApply<PhaseParse>,
-    variables: &HashMap<String, i32>,
+    apply: &ProcTermApply<PhaseTyped>,
+    variables: &HashMap<Slot, i32>,
+    builtins: &HashMap<VariableId, String>,
+    arrays: &HashMap<String, ArrayInfo>,
+    variable_arrays: &HashMap<VariableId, String>,
     output: &mut String,
 ) -> Result<(), CompileError> {
     if apply.args.len() != 2 {
@@ -120,13 +198,26 @@ pub fn compile_u64_mod_direct(
     let arg2 = &apply.args[1];
 
     // Load first argument into rax
-    memory::load_proc_argument_into_register(arg1, "rax", variables, output)?;
+    memory::load_proc_argument_into_register(
+        arg1,
+        "rax",
+        variables,
+        builtins,
+        arrays,
+        variable_arrays,
+        output,
+    )?;
     // Load second argument into rbx
-    memory::load_proc_argument_into_register(arg2, "rbx", variables, output)?;
-    // Clear rdx for division
-    output.push_str("    xor rdx, rdx\n");
-    // Divide rax by rbx, remainder in rdx
-    output.push_str("    div rbx\n");
+    memory::load_proc_argument_into_register(
+        arg2,
+        "rbx",
+        variables,
+        builtins,
+        arrays,
+        variable_arrays,
+        output,
+    )?;
+    crate::arithmetic::push_u64_div(output);
     // For modulo, result is in rdx, move to rax
     output.push_str("    mov rax, rdx\n");
 
@@ -134,8 +225,11 @@ pub fn compile_u64_mod_direct(
 }
 
 pub fn compile_u64_eq_direct(
-    apply: &ProcTermApply<PhaseParse>,
-    variables: &HashMap<String, i32>,
+    apply: &ProcTermApply<PhaseTyped>,
+    variables: &HashMap<Slot, i32>,
+    builtins: &HashMap<VariableId, String>,
+    arrays: &HashMap<String, ArrayInfo>,
+    variable_arrays: &HashMap<VariableId, String>,
     output: &mut String,
 ) -> Result<(), CompileError> {
     if apply.args.len() != 2 {
@@ -149,9 +243,25 @@ pub fn compile_u64_eq_direct(
     let arg2 = &apply.args[1];
 
     // Load first argument into rax
-    memory::load_proc_argument_into_register(arg1, "rax", variables, output)?;
+    memory::load_proc_argument_into_register(
+        arg1,
+        "rax",
+        variables,
+        builtins,
+        arrays,
+        variable_arrays,
+        output,
+    )?;
     // Load second argument into rbx
-    memory::load_proc_argument_into_register(arg2, "rbx", variables, output)?;
+    memory::load_proc_argument_into_register(
+        arg2,
+        "rbx",
+        variables,
+        builtins,
+        arrays,
+        variable_arrays,
+        output,
+    )?;
     // Compare the values
     output.push_str("    cmp rax, rbx\n");
     // Set rax to 1 if equal, 0 if not equal
@@ -162,10 +272,11 @@ pub fn compile_u64_eq_direct(
 }
 
 pub fn compile_f32_add_direct(
-    apply: &ProcTermApply<PhaseParse>,
-    variables: &HashMap<String, i32>,
+    apply: &ProcTermApply<PhaseTyped>,
+    variables: &HashMap<Slot, i32>,
+    builtins: &HashMap<VariableId, String>,
     arrays: &HashMap<String, ArrayInfo>,
-    variable_arrays: &HashMap<String, String>,
+    variable_arrays: &HashMap<VariableId, String>,
     output: &mut String,
 ) -> Result<(), CompileError> {
     if apply.args.len() != 2 {
@@ -183,6 +294,7 @@ pub fn compile_f32_add_direct(
         arg1,
         "xmm0",
         variables,
+        builtins,
         arrays,
         variable_arrays,
         output,
@@ -192,6 +304,7 @@ pub fn compile_f32_add_direct(
         arg2,
         "xmm1",
         variables,
+        builtins,
         arrays,
         variable_arrays,
         output,
@@ -205,10 +318,11 @@ pub fn compile_f32_add_direct(
 }
 
 pub fn compile_f32_sub_direct(
-    apply: &ProcTermApply<PhaseParse>,
-    variables: &HashMap<String, i32>,
+    apply: &ProcTermApply<PhaseTyped>,
+    variables: &HashMap<Slot, i32>,
+    builtins: &HashMap<VariableId, String>,
     arrays: &HashMap<String, ArrayInfo>,
-    variable_arrays: &HashMap<String, String>,
+    variable_arrays: &HashMap<VariableId, String>,
     output: &mut String,
 ) -> Result<(), CompileError> {
     if apply.args.len() != 2 {
@@ -226,6 +340,7 @@ pub fn compile_f32_sub_direct(
         arg1,
         "xmm0",
         variables,
+        builtins,
         arrays,
         variable_arrays,
         output,
@@ -235,6 +350,7 @@ pub fn compile_f32_sub_direct(
         arg2,
         "xmm1",
         variables,
+        builtins,
         arrays,
         variable_arrays,
         output,
@@ -248,10 +364,11 @@ pub fn compile_f32_sub_direct(
 }
 
 pub fn compile_f32_mul_direct(
-    apply: &ProcTermApply<PhaseParse>,
-    variables: &HashMap<String, i32>,
+    apply: &ProcTermApply<PhaseTyped>,
+    variables: &HashMap<Slot, i32>,
+    builtins: &HashMap<VariableId, String>,
     arrays: &HashMap<String, ArrayInfo>,
-    variable_arrays: &HashMap<String, String>,
+    variable_arrays: &HashMap<VariableId, String>,
     output: &mut String,
 ) -> Result<(), CompileError> {
     if apply.args.len() != 2 {
@@ -269,6 +386,7 @@ pub fn compile_f32_mul_direct(
         arg1,
         "xmm0",
         variables,
+        builtins,
         arrays,
         variable_arrays,
         output,
@@ -278,6 +396,7 @@ pub fn compile_f32_mul_direct(
         arg2,
         "xmm1",
         variables,
+        builtins,
         arrays,
         variable_arrays,
         output,
@@ -291,10 +410,11 @@ pub fn compile_f32_mul_direct(
 }
 
 pub fn compile_f32_div_direct(
-    apply: &ProcTermApply<PhaseParse>,
-    variables: &HashMap<String, i32>,
+    apply: &ProcTermApply<PhaseTyped>,
+    variables: &HashMap<Slot, i32>,
+    builtins: &HashMap<VariableId, String>,
     arrays: &HashMap<String, ArrayInfo>,
-    variable_arrays: &HashMap<String, String>,
+    variable_arrays: &HashMap<VariableId, String>,
     output: &mut String,
 ) -> Result<(), CompileError> {
     if apply.args.len() != 2 {
@@ -312,6 +432,7 @@ pub fn compile_f32_div_direct(
         arg1,
         "xmm0",
         variables,
+        builtins,
         arrays,
         variable_arrays,
         output,
@@ -321,6 +442,7 @@ pub fn compile_f32_div_direct(
         arg2,
         "xmm1",
         variables,
+        builtins,
         arrays,
         variable_arrays,
         output,
@@ -334,10 +456,11 @@ pub fn compile_f32_div_direct(
 }
 
 pub fn compile_f32_to_u64_direct(
-    apply: &ProcTermApply<PhaseParse>,
-    variables: &HashMap<String, i32>,
+    apply: &ProcTermApply<PhaseTyped>,
+    variables: &HashMap<Slot, i32>,
+    builtins: &HashMap<VariableId, String>,
     arrays: &HashMap<String, ArrayInfo>,
-    variable_arrays: &HashMap<String, String>,
+    variable_arrays: &HashMap<VariableId, String>,
     output: &mut String,
 ) -> Result<(), CompileError> {
     if apply.args.len() != 1 {
@@ -354,6 +477,7 @@ pub fn compile_f32_to_u64_direct(
         arg,
         "xmm0",
         variables,
+        builtins,
         arrays,
         variable_arrays,
         output,
@@ -366,10 +490,11 @@ pub fn compile_f32_to_u64_direct(
 }
 
 pub fn compile_u64_to_f32_direct(
-    apply: &ProcTermApply<PhaseParse>,
-    variables: &HashMap<String, i32>,
-    _arrays: &HashMap<String, ArrayInfo>,
-    _variable_arrays: &HashMap<String, String>,
+    apply: &ProcTermApply<PhaseTyped>,
+    variables: &HashMap<Slot, i32>,
+    builtins: &HashMap<VariableId, String>,
+    arrays: &HashMap<String, ArrayInfo>,
+    variable_arrays: &HashMap<VariableId, String>,
     output: &mut String,
 ) -> Result<(), CompileError> {
     if apply.args.len() != 1 {
@@ -382,7 +507,15 @@ pub fn compile_u64_to_f32_direct(
     let arg = &apply.args[0];
 
     // Load u64 argument into rax
-    memory::load_proc_argument_into_register(arg, "rax", variables, output)?;
+    memory::load_proc_argument_into_register(
+        arg,
+        "rax",
+        variables,
+        builtins,
+        arrays,
+        variable_arrays,
+        output,
+    )?;
 
     // Convert u64 to f32
     output.push_str("    cvtsi2ss xmm0, rax\n");
@@ -394,8 +527,11 @@ pub fn compile_u64_to_f32_direct(
 }
 
 pub fn compile_u64_direct(
-    apply: &ProcTermApply<PhaseParse>,
-    variables: &HashMap<String, i32>,
+    apply: &ProcTermApply<PhaseTyped>,
+    variables: &HashMap<Slot, i32>,
+    builtins: &HashMap<VariableId, String>,
+    arrays: &HashMap<String, ArrayInfo>,
+    variable_arrays: &HashMap<VariableId, String>,
     output: &mut String,
 ) -> Result<(), CompileError> {
     if apply.args.len() != 1 {
@@ -408,14 +544,25 @@ pub fn compile_u64_direct(
     let arg = &apply.args[0];
 
     // Load argument value
-    memory::load_proc_argument_into_register(arg, "rax", variables, output)?;
+    memory::load_proc_argument_into_register(
+        arg,
+        "rax",
+        variables,
+        builtins,
+        arrays,
+        variable_arrays,
+        output,
+    )?;
 
     Ok(())
 }
 
 pub fn compile_f32_direct(
-    apply: &ProcTermApply<PhaseParse>,
-    _variables: &HashMap<String, i32>,
+    apply: &ProcTermApply<PhaseTyped>,
+    variables: &HashMap<Slot, i32>,
+    builtins: &HashMap<VariableId, String>,
+    arrays: &HashMap<String, ArrayInfo>,
+    variable_arrays: &HashMap<VariableId, String>,
     output: &mut String,
 ) -> Result<(), CompileError> {
     if apply.args.len() != 1 {
@@ -427,113 +574,16 @@ pub fn compile_f32_direct(
 
     let arg = &apply.args[0];
 
-    // Load f32 argument
-    match arg {
-        ProcTerm::Number(num) => {
-            let number_str = num.number.s();
-            let float_value = number_str.parse::<f32>().unwrap_or(0.0);
-            output.push_str(&format!("    mov eax, 0x{:08x}\n", float_value.to_bits()));
-            output.push_str("    mov rax, rax\n"); // Clear upper 32 bits
-        }
-        _ => {
-            return Err(CompileError::UnsupportedConstruct(format!(
-                "f32 expects numeric literal, got: {arg:?}"
-            )));
-        }
-    }
-
-    Ok(())
-}
-
-pub fn compile_f32_arithmetic_for_let(
-    apply: &ProcTermApply<PhaseParse>,
-    builtin: &str,
-    variables: &HashMap<String, i32>,
-    arrays: &HashMap<String, ArrayInfo>,
-    variable_arrays: &HashMap<String, String>,
-    output: &mut String,
-    offset: i32,
-) -> Result<(), CompileError> {
-    if apply.args.len() != 2 {
-        return Err(CompileError::UnsupportedConstruct(format!(
-            "{builtin} expects 2 arguments, got {}",
-            apply.args.len()
-        )));
-    }
-
-    let arg1 = &apply.args[0];
-    let arg2 = &apply.args[1];
-
-    // Load first argument into xmm0
-    memory::load_f32_proc_argument_into_register(
-        arg1,
-        "xmm0",
-        variables,
-        arrays,
-        variable_arrays,
-        output,
-    )?;
-    // Load second argument into xmm1
-    memory::load_f32_proc_argument_into_register(
-        arg2,
-        "xmm1",
+    // The literal is already encoded as f32
+    memory::load_proc_argument_into_register(
+        arg,
+        "rax",
         variables,
+        builtins,
         arrays,
         variable_arrays,
         output,
     )?;
 
-    // Perform the operation
-    match builtin {
-        "f32_add" => output.push_str("    addss xmm0, xmm1\n"),
-        "f32_sub" => output.push_str("    subss xmm0, xmm1\n"),
-        "f32_mul" => output.push_str("    mulss xmm0, xmm1\n"),
-        "f32_div" => output.push_str("    divss xmm0, xmm1\n"),
-        _ => {
-            return Err(CompileError::UnsupportedConstruct(format!(
-                "Unknown f32 operation: {builtin}"
-            )));
-        }
-    }
-
-    // Store result from xmm0 to the variable's stack location as f32
-    output.push_str(&format!(
-        "    movss dword ptr [rbp - 8 - {}], xmm0\n",
-        offset - 8
-    ));
-
-    Ok(())
-}
-
-pub fn compile_u64_mod_for_let(
-    apply: &ProcTermApply<PhaseParse>,
-    variables: &HashMap<String, i32>,
-    output: &mut String,
-    offset: i32,
-) -> Result<(), CompileError> {
-    if apply.args.len() != 2 {
-        return Err(CompileError::UnsupportedConstruct(format!(
-            "u64_mod expects 2 arguments, got {}",
-            apply.args.len()
-        )));
-    }
-
-    let arg1 = &apply.args[0];
-    let arg2 = &apply.args[1];
-
-    // Load first argument into rax
-    memory::load_proc_argument_into_register(arg1, "rax", variables, output)?;
-    // Load second argument into rbx
-    memory::load_proc_argument_into_register(arg2, "rbx", variables, output)?;
-    // Clear rdx for division
-    output.push_str("    xor rdx, rdx\n");
-    // Divide rax by rbx, remainder in rdx
-    output.push_str("    div rbx\n");
-    // Store remainder from rdx directly to the variable's stack location
-    output.push_str(&format!(
-        "    mov qword ptr [rbp - 8 - {}], rdx\n",
-        offset - 8
-    ));
-
     Ok(())
 }
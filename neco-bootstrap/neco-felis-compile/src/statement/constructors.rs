@@ -1,15 +1,19 @@
-use crate::{ArrayInfo, error::CompileError};
+use crate::{ArrayInfo, Slot, error::CompileError};
+use neco_felis_rename::VariableId;
 use neco_felis_syn::*;
+use neco_felis_type_check::PhaseTyped;
 use std::collections::HashMap;
 
+#[allow(clippy::too_many_arguments)]
 pub fn compile_proc_constructor_call_with_var(
-    constructor_call: &ProcTermConstructorCall<PhaseParse>,
+    constructor_call: &ProcTermConstructorCall<PhaseTyped>,
+    id: &VariableId,
     var_name: &str,
     arrays: &HashMap<String, ArrayInfo>,
     output: &mut String,
     stack_offset: &mut i32,
-    variables: &mut HashMap<String, i32>,
-    variable_arrays: &mut HashMap<String, String>,
+    variables: &mut HashMap<Slot, i32>,
+    variable_arrays: &mut HashMap<VariableId, String>,
 ) -> Result<(), CompileError> {
     let type_name = constructor_call.type_name.s();
     let method_name = constructor_call.method.s();
@@ -19,7 +23,7 @@ pub fn compile_proc_constructor_call_with_var(
         // Look up array information
         if let Some(array_info) = arrays.get(type_name).cloned() {
             // Register the variable to array type mapping
-            variable_arrays.insert(var_name.to_string(), type_name.to_string());
+            variable_arrays.insert(id.clone(), type_name.to_string());
             // Get the size argument
             let size_arg = if !constructor_call.args.is_empty()
                 && let Some(arg) = constructor_call.args.first()
@@ -27,7 +31,7 @@ pub fn compile_proc_constructor_call_with_var(
                 match arg {
                     ProcTerm::Number(num) => num.number.s().to_string(),
                     ProcTerm::Variable(var) => {
-                        if let Some(&offset) = variables.get(var.variable.s()) {
+                        if let Some(&offset) = variables.get(&Slot::Variable(var.ext.0.clone())) {
                             // Load variable value into rsi for use by SoA allocation
                             output.push_str(&format!(
                                 "    mov rsi, qword ptr [rbp - 8 - {}]\n",
@@ -53,8 +57,9 @@ pub fn compile_proc_constructor_call_with_var(
                 ));
             };
 
-            // Generate Structure of Arrays allocation using variable name
+            // Generate Structure of Arrays allocation for the variable
             crate::arrays::generate_soa_allocation_with_var(
+                id,
                 var_name,
                 &array_info,
                 &size_arg,
@@ -77,11 +82,11 @@ pub fn compile_proc_constructor_call_with_var(
 }
 
 pub fn compile_proc_constructor_call(
-    constructor_call: &ProcTermConstructorCall<PhaseParse>,
+    constructor_call: &ProcTermConstructorCall<PhaseTyped>,
     arrays: &HashMap<String, ArrayInfo>,
     output: &mut String,
     stack_offset: &mut i32,
-    variables: &mut HashMap<String, i32>,
+    variables: &HashMap<Slot, i32>,
 ) -> Result<(), CompileError> {
     let type_name = constructor_call.type_name.s();
     let method_name = constructor_call.method.s();
@@ -97,7 +102,7 @@ pub fn compile_proc_constructor_call(
                 match arg {
                     ProcTerm::Number(num) => num.number.s().to_string(),
                     ProcTerm::Variable(var) => {
-                        if let Some(&offset) = variables.get(var.variable.s()) {
+                        if let Some(&offset) = variables.get(&Slot::Variable(var.ext.0.clone())) {
                             // Load variable value into rsi for use by SoA allocation
                             output.push_str(&format!(
                                 "    mov rsi, qword ptr [rbp - 8 - {}]\n",
@@ -130,7 +135,6 @@ pub fn compile_proc_constructor_call(
                 &size_arg,
                 output,
                 stack_offset,
-                variables,
                 &mut HashMap::new(),
             )?;
 
@@ -1,17 +1,19 @@
-use crate::{ArrayInfo, error::CompileError};
+use crate::{ArrayInfo, Slot, error::CompileError};
+use neco_felis_rename::VariableId;
 use neco_felis_syn::*;
+use neco_felis_type_check::PhaseTyped;
 use std::collections::HashMap;
 
 use super::StatementCompiler;
 
 #[allow(clippy::too_many_arguments)]
 pub fn compile_proc_if(
-    if_expr: &ProcTermIf<PhaseParse>,
-    variables: &HashMap<String, i32>,
-    reference_variables: &HashMap<String, String>,
-    builtins: &HashMap<String, String>,
+    if_expr: &ProcTermIf<PhaseTyped>,
+    variables: &HashMap<Slot, i32>,
+    reference_variables: &HashMap<VariableId, VariableId>,
+    builtins: &HashMap<VariableId, String>,
     arrays: &HashMap<String, ArrayInfo>,
-    variable_arrays: &mut HashMap<String, String>,
+    variable_arrays: &mut HashMap<VariableId, String>,
     output: &mut String,
 ) -> Result<(), CompileError> {
     // Generate unique labels for this if statement
@@ -89,12 +91,12 @@ pub fn compile_proc_if(
 
 #[allow(clippy::too_many_arguments)]
 pub fn compile_loop_statement(
-    loop_stmt: &StatementLoop<PhaseParse>,
-    variables: &mut HashMap<String, i32>,
-    reference_variables: &mut HashMap<String, String>,
-    builtins: &HashMap<String, String>,
+    loop_stmt: &StatementLoop<PhaseTyped>,
+    variables: &mut HashMap<Slot, i32>,
+    reference_variables: &mut HashMap<VariableId, VariableId>,
+    builtins: &HashMap<VariableId, String>,
     arrays: &HashMap<String, ArrayInfo>,
-    variable_arrays: &mut HashMap<String, String>,
+    variable_arrays: &mut HashMap<VariableId, String>,
     stack_offset: &mut i32,
     output: &mut String,
 ) -> Result<(), CompileError> {
@@ -131,12 +133,12 @@ pub fn compile_loop_statement(
 
 #[allow(clippy::too_many_arguments)]
 fn compile_proc_if_with_break(
-    if_expr: &ProcTermIf<PhaseParse>,
-    variables: &HashMap<String, i32>,
-    reference_variables: &HashMap<String, String>,
-    builtins: &HashMap<String, String>,
+    if_expr: &ProcTermIf<PhaseTyped>,
+    variables: &HashMap<Slot, i32>,
+    reference_variables: &HashMap<VariableId, VariableId>,
+    builtins: &HashMap<VariableId, String>,
     arrays: &HashMap<String, ArrayInfo>,
-    variable_arrays: &mut HashMap<String, String>,
+    variable_arrays: &mut HashMap<VariableId, String>,
     break_label: &str,
     output: &mut String,
 ) -> Result<(), CompileError> {
@@ -218,12 +220,12 @@ fn compile_proc_if_with_break(
 
 #[allow(clippy::too_many_arguments)]
 fn compile_statements_with_break(
-    statements: &Statements<PhaseParse>,
-    variables: &mut HashMap<String, i32>,
-    reference_variables: &mut HashMap<String, String>,
-    builtins: &HashMap<String, String>,
+    statements: &Statements<PhaseTyped>,
+    variables: &mut HashMap<Slot, i32>,
+    reference_variables: &mut HashMap<VariableId, VariableId>,
+    builtins: &HashMap<VariableId, String>,
     arrays: &HashMap<String, ArrayInfo>,
-    variable_arrays: &mut HashMap<String, String>,
+    variable_arrays: &mut HashMap<VariableId, String>,
     stack_offset: &mut i32,
     break_label: &str,
     output: &mut String,
@@ -273,12 +275,12 @@ fn compile_statements_with_break(
 
 #[allow(clippy::too_many_arguments)]
 fn compile_statement_with_break(
-    statement: &Statement<PhaseParse>,
-    variables: &mut HashMap<String, i32>,
-    reference_variables: &mut HashMap<String, String>,
-    builtins: &HashMap<String, String>,
+    statement: &Statement<PhaseTyped>,
+    variables: &mut HashMap<Slot, i32>,
+    reference_variables: &mut HashMap<VariableId, VariableId>,
+    builtins: &HashMap<VariableId, String>,
     arrays: &HashMap<String, ArrayInfo>,
-    variable_arrays: &mut HashMap<String, String>,
+    variable_arrays: &mut HashMap<VariableId, String>,
     stack_offset: &mut i32,
     break_label: &str,
     output: &mut String,
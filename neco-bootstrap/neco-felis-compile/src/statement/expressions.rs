@@ -1,5 +1,9 @@
-use crate::{ArrayInfo, error::CompileError, syscall::SyscallCompiler};
+use crate::{
+    ArrayInfo, Slot, compiler::ARGUMENT_REGISTERS, error::CompileError, syscall::SyscallCompiler,
+};
+use neco_felis_rename::VariableId;
 use neco_felis_syn::*;
+use neco_felis_type_check::{PhaseTyped, ProcType};
 use std::collections::HashMap;
 
 use super::arithmetic;
@@ -8,12 +12,12 @@ use super::control_flow;
 use super::memory;
 
 pub fn compile_proc_term(
-    proc_term: &ProcTerm<PhaseParse>,
-    variables: &HashMap<String, i32>,
-    reference_variables: &HashMap<String, String>,
-    builtins: &HashMap<String, String>,
+    proc_term: &ProcTerm<PhaseTyped>,
+    variables: &HashMap<Slot, i32>,
+    reference_variables: &HashMap<VariableId, VariableId>,
+    builtins: &HashMap<VariableId, String>,
     arrays: &HashMap<String, ArrayInfo>,
-    variable_arrays: &mut HashMap<String, String>,
+    variable_arrays: &mut HashMap<VariableId, String>,
     output: &mut String,
 ) -> Result<(), CompileError> {
     match proc_term {
@@ -34,7 +38,7 @@ pub fn compile_proc_term(
             arrays,
             output,
             &mut 0,
-            &mut HashMap::new(),
+            variables,
         ),
         ProcTerm::Dereference(dereference) => memory::compile_proc_dereference(
             dereference,
@@ -68,14 +72,14 @@ pub fn compile_proc_term(
 }
 
 pub fn compile_proc_variable(
-    var: &ProcTermVariable<PhaseParse>,
-    variables: &HashMap<String, i32>,
+    var: &ProcTermVariable<PhaseTyped>,
+    variables: &HashMap<Slot, i32>,
     output: &mut String,
 ) -> Result<(), CompileError> {
     let var_name = var.variable.s();
 
     // Check if the variable exists in our variable map
-    if let Some(&offset) = variables.get(var_name) {
+    if let Some(&offset) = variables.get(&Slot::Variable(var.ext.0.clone())) {
         // Load the variable value from its stack location into rax
         output.push_str(&format!(
             "    mov rax, qword ptr [rbp - 8 - {}]\n",
@@ -90,37 +94,37 @@ pub fn compile_proc_variable(
 }
 
 pub fn compile_proc_number(
-    num: &ProcTermNumber<PhaseParse>,
+    num: &ProcTermNumber<PhaseTyped>,
     output: &mut String,
 ) -> Result<(), CompileError> {
-    let number_value = parse_number(num.number.s());
+    let number_value = number_value(num);
     output.push_str(&format!("    mov rax, {number_value}\n"));
     Ok(())
 }
 
-fn parse_number(number_str: &str) -> String {
-    if number_str.ends_with("u64") {
-        number_str.trim_end_matches("u64").to_string()
-    } else if number_str.ends_with("f32") {
-        // For f32 numbers, we need to handle them specially
-        let float_value = number_str.trim_end_matches("f32");
-        // Convert to u32 representation for storage
-        if let Ok(f) = float_value.parse::<f32>() {
+/// The immediate of a number literal: its bits for `f32`, whether or not it
+/// has a suffix
+pub fn number_value(num: &ProcTermNumber<PhaseTyped>) -> String {
+    let number_str = num.number.s();
+    let number_str = number_str
+        .strip_suffix("u64")
+        .or_else(|| number_str.strip_suffix("f32"))
+        .unwrap_or(number_str);
+    match num.ext {
+        ProcType::F32 => {
+            let f = number_str.parse::<f32>().unwrap_or(0.0);
             format!("0x{:08x}", f.to_bits())
-        } else {
-            float_value.to_string()
         }
-    } else {
-        number_str.to_string()
+        _ => number_str.to_string(),
     }
 }
 
 pub fn compile_proc_apply(
-    apply: &ProcTermApply<PhaseParse>,
-    variables: &HashMap<String, i32>,
-    builtins: &HashMap<String, String>,
+    apply: &ProcTermApply<PhaseTyped>,
+    variables: &HashMap<Slot, i32>,
+    builtins: &HashMap<VariableId, String>,
     arrays: &HashMap<String, ArrayInfo>,
-    variable_arrays: &HashMap<String, String>,
+    variable_arrays: &HashMap<VariableId, String>,
     output: &mut String,
 ) -> Result<(), CompileError> {
     // Handle field access apply (e.g., points.#len ())
@@ -139,21 +143,11 @@ pub fn compile_proc_apply(
 
             // Generate code to get array length
             let array_name = field_access.object.s();
-            if let Some(array_info) = arrays.get(array_name) {
-                // Static array - use compile-time size if available
-                if let Some(size) = array_info.size {
-                    output.push_str(&format!("    mov rax, {size}\n"));
-                    return Ok(());
-                } else {
-                    return Err(CompileError::UnsupportedConstruct(format!(
-                        "Static array {array_name} has no size information"
-                    )));
-                }
-            } else if let Some(_array_type) = variable_arrays.get(array_name) {
-                // This is a variable that holds a dynamically allocated array
-                // The size is stored in a variable named "{array_name}_size"
-                let size_var_name = format!("{array_name}_size");
-                if let Some(&size_offset) = variables.get(&size_var_name) {
+            let object = &field_access.ext.0;
+            if variable_arrays.contains_key(object) {
+                // This is a variable that holds a dynamically allocated array,
+                // whose size has a slot of its own
+                if let Some(&size_offset) = variables.get(&Slot::Length(object.clone())) {
                     // Load the array size from memory into rax
                     output.push_str(&format!(
                         "    mov rax, qword ptr [rbp - 8 - {}]\n",
@@ -165,6 +159,16 @@ pub fn compile_proc_apply(
                         "Size variable not found for array: {array_name}"
                     )));
                 }
+            } else if let Some(array_info) = arrays.get(array_name) {
+                // Static array - use compile-time size if available
+                if let Some(size) = array_info.size {
+                    output.push_str(&format!("    mov rax, {size}\n"));
+                    return Ok(());
+                } else {
+                    return Err(CompileError::UnsupportedConstruct(format!(
+                        "Static array {array_name} has no size information"
+                    )));
+                }
             } else {
                 return Err(CompileError::UnsupportedConstruct(format!(
                     "Unknown array variable: {array_name}"
@@ -179,112 +183,82 @@ pub fn compile_proc_apply(
     }
 
     if let ProcTerm::Variable(var) = &*apply.f {
-        if let Some(builtin) = builtins.get(var.variable.s()) {
-            match builtin.as_str() {
+        if let Some(builtin) = builtins.get(&var.ext.0) {
+            let compile_builtin = match builtin.as_str() {
                 "syscall" => {
                     return SyscallCompiler::compile_proc_syscall(&apply.args, variables, output);
                 }
-                "u64_add" => return arithmetic::compile_u64_add_direct(apply, variables, output),
-                "u64_sub" => return arithmetic::compile_u64_sub_direct(apply, variables, output),
-                "u64_mul" => return arithmetic::compile_u64_mul_direct(apply, variables, output),
-                "u64_div" => return arithmetic::compile_u64_div_direct(apply, variables, output),
-                "u64_mod" => return arithmetic::compile_u64_mod_direct(apply, variables, output),
-                "u64_eq" => return arithmetic::compile_u64_eq_direct(apply, variables, output),
-                "f32_add" => {
-                    return arithmetic::compile_f32_add_direct(
-                        apply,
-                        variables,
-                        arrays,
-                        variable_arrays,
-                        output,
-                    );
-                }
-                "f32_sub" => {
-                    return arithmetic::compile_f32_sub_direct(
-                        apply,
-                        variables,
-                        arrays,
-                        variable_arrays,
-                        output,
-                    );
-                }
-                "f32_mul" => {
-                    return arithmetic::compile_f32_mul_direct(
-                        apply,
-                        variables,
-                        arrays,
-                        variable_arrays,
-                        output,
-                    );
-                }
-                "f32_div" => {
-                    return arithmetic::compile_f32_div_direct(
-                        apply,
-                        variables,
-                        arrays,
-                        variable_arrays,
-                        output,
-                    );
-                }
-                "f32_to_u64" => {
-                    return arithmetic::compile_f32_to_u64_direct(
-                        apply,
-                        variables,
-                        arrays,
-                        variable_arrays,
-                        output,
-                    );
-                }
-                "u64_to_f32" => {
-                    return arithmetic::compile_u64_to_f32_direct(
-                        apply,
-                        variables,
-                        arrays,
-                        variable_arrays,
-                        output,
-                    );
-                }
-                "u64" => return arithmetic::compile_u64_direct(apply, variables, output),
-                "f32" => return arithmetic::compile_f32_direct(apply, variables, output),
-                _ => {}
-            }
-        } else {
-            // This is a call to a user-defined procedure
-            let proc_name = var.variable.s();
-
-            // Set up arguments in registers (following System V ABI)
-            for (i, arg) in apply.args.iter().enumerate() {
-                match i {
-                    0 => SyscallCompiler::load_proc_argument_into_register(
-                        arg, "rdi", variables, output,
-                    )?,
-                    1 => SyscallCompiler::load_proc_argument_into_register(
-                        arg, "rsi", variables, output,
-                    )?,
-                    2 => SyscallCompiler::load_proc_argument_into_register(
-                        arg, "rdx", variables, output,
-                    )?,
-                    3 => SyscallCompiler::load_proc_argument_into_register(
-                        arg, "rcx", variables, output,
-                    )?,
-                    4 => SyscallCompiler::load_proc_argument_into_register(
-                        arg, "r8", variables, output,
-                    )?,
-                    5 => SyscallCompiler::load_proc_argument_into_register(
-                        arg, "r9", variables, output,
-                    )?,
-                    _ => {
-                        return Err(CompileError::UnsupportedConstruct(
-                            "More than 6 arguments not supported".to_string(),
-                        ));
-                    }
+                "u64_add" => arithmetic::compile_u64_add_direct,
+                "u64_sub" => arithmetic::compile_u64_sub_direct,
+                "u64_mul" => arithmetic::compile_u64_mul_direct,
+                "u64_div" => arithmetic::compile_u64_div_direct,
+                "u64_mod" => arithmetic::compile_u64_mod_direct,
+                "u64_eq" => arithmetic::compile_u64_eq_direct,
+                "f32_add" => arithmetic::compile_f32_add_direct,
+                "f32_sub" => arithmetic::compile_f32_sub_direct,
+                "f32_mul" => arithmetic::compile_f32_mul_direct,
+                "f32_div" => arithmetic::compile_f32_div_direct,
+                "f32_to_u64" => arithmetic::compile_f32_to_u64_direct,
+                "u64_to_f32" => arithmetic::compile_u64_to_f32_direct,
+                "u64" => arithmetic::compile_u64_direct,
+                "f32" => arithmetic::compile_f32_direct,
+                _ => {
+                    return Err(CompileError::UnsupportedConstruct(format!(
+                        "Unsupported builtin: {builtin}"
+                    )));
                 }
-            }
+            };
+            return compile_builtin(apply, variables, builtins, arrays, variable_arrays, output);
+        }
 
-            // Call the user-defined procedure
-            output.push_str(&format!("    call {proc_name}\n"));
-            return Ok(());
+        // A call to a procedure or an extracted definition. Every argument,
+        // `f32` ones included, is a word. Computed arguments are pushed
+        // first, then popped into their registers, and literals and
+        // variables are loaded last.
+        let args: Vec<_> = apply
+            .args
+            .iter()
+            .filter(|arg| !matches!(arg, ProcTerm::Unit(_)))
+            .collect();
+        if args.len() > ARGUMENT_REGISTERS.len() {
+            return Err(CompileError::UnsupportedConstruct(
+                "More than 6 arguments not supported".to_string(),
+            ));
+        }
+        let args: Vec<_> = args.into_iter().zip(ARGUMENT_REGISTERS).collect();
+        let is_loaded = |arg: &ProcTerm<PhaseTyped>| {
+            matches!(arg, ProcTerm::Number(_))
+                || matches!(arg, ProcTerm::Variable(var) if variables.contains_key(&Slot::Variable(var.ext.0.clone())))
+        };
+        let computed: Vec<_> = args.iter().filter(|(arg, _)| !is_loaded(arg)).collect();
+        for (arg, _) in &computed {
+            compile_proc_term(
+                arg,
+                variables,
+                &HashMap::new(),
+                builtins,
+                arrays,
+                &mut variable_arrays.clone(),
+                output,
+            )?;
+            output.push_str("    push rax\n");
+        }
+        for (_, register) in computed.iter().rev() {
+            output.push_str(&format!("    pop {register}\n"));
+        }
+        for (arg, register) in args.iter().filter(|(arg, _)| is_loaded(arg)) {
+            memory::load_proc_argument_into_register(
+                arg,
+                register,
+                variables,
+                builtins,
+                arrays,
+                variable_arrays,
+                output,
+            )?;
         }
+        output.push_str(&format!("    call {}\n", var.variable.s()));
+        return Ok(());
     }
     Err(CompileError::UnsupportedConstruct(format!("{apply:?}")))
 }
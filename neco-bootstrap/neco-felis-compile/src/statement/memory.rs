@@ -1,98 +1,92 @@
-use crate::{ArrayInfo, error::CompileError};
+use crate::{ArrayInfo, Slot, error::CompileError};
+use neco_felis_rename::VariableId;
 use neco_felis_syn::*;
+use neco_felis_type_check::{PhaseTyped, ProcType};
 use std::collections::HashMap;
 
 use super::expressions;
 
 pub fn load_proc_argument_into_register(
-    arg: &ProcTerm<PhaseParse>,
+    arg: &ProcTerm<PhaseTyped>,
     register: &str,
-    variables: &HashMap<String, i32>,
+    variables: &HashMap<Slot, i32>,
+    builtins: &HashMap<VariableId, String>,
+    arrays: &HashMap<String, ArrayInfo>,
+    variable_arrays: &HashMap<VariableId, String>,
     output: &mut String,
 ) -> Result<(), CompileError> {
     match arg {
         ProcTerm::Number(num) => {
-            let number_value = parse_number(num.number.s());
+            let number_value = expressions::number_value(num);
             output.push_str(&format!("    mov {register}, {number_value}\n"));
         }
-        ProcTerm::Variable(var) => {
-            let var_name = var.variable.s();
-            if let Some(&var_offset) = variables.get(var_name) {
-                output.push_str(&format!(
-                    "    mov {register}, qword ptr [rbp - 8 - {}]\n",
-                    var_offset - 8
-                ));
-            } else {
-                return Err(CompileError::UnsupportedConstruct(format!(
-                    "Unknown variable: {var_name}"
-                )));
-            }
+        ProcTerm::Variable(var) if variables.contains_key(&Slot::Variable(var.ext.0.clone())) => {
+            let var_offset = variables[&Slot::Variable(var.ext.0.clone())];
+            output.push_str(&format!(
+                "    mov {register}, qword ptr [rbp - 8 - {}]\n",
+                var_offset - 8
+            ));
         }
         ProcTerm::Paren(paren) => {
             // Handle parenthesized expressions by delegating to the inner term
-            load_proc_argument_into_register(&paren.proc_term, register, variables, output)?;
+            load_proc_argument_into_register(
+                &paren.proc_term,
+                register,
+                variables,
+                builtins,
+                arrays,
+                variable_arrays,
+                output,
+            )?;
         }
-        ProcTerm::Dereference(dereference) => {
-            // Handle dereference operation: expr.*
-            // First, compile the term that produces a reference/address
+        _ => {
+            // Any other value is computed into rax, keeping the first operand
+            // of a binary operation, which is already in rax
+            if register != "rax" {
+                output.push_str("    push rax\n");
+            }
             expressions::compile_proc_term(
-                &dereference.term,
+                arg,
                 variables,
                 &HashMap::new(),
-                &HashMap::new(),
-                &HashMap::new(),
-                &mut HashMap::new(),
+                builtins,
+                arrays,
+                &mut variable_arrays.clone(),
                 output,
             )?;
-
-            // Then dereference it - rax contains the address, we need to load the value
-            output.push_str(&format!("    mov {register}, qword ptr [rax]\n"));
-        }
-        _ => {
-            return Err(CompileError::UnsupportedConstruct(format!(
-                "Unsupported argument type: {arg:?}"
-            )));
+            if register != "rax" {
+                output.push_str(&format!("    mov {register}, rax\n"));
+                output.push_str("    pop rax\n");
+            }
         }
     }
     Ok(())
 }
 
+/// Loads an `f32` into the low lane of an XMM register
 pub fn load_f32_proc_argument_into_register(
-    arg: &ProcTerm<PhaseParse>,
+    arg: &ProcTerm<PhaseTyped>,
     register: &str,
-    variables: &HashMap<String, i32>,
+    variables: &HashMap<Slot, i32>,
+    builtins: &HashMap<VariableId, String>,
     arrays: &HashMap<String, ArrayInfo>,
-    variable_arrays: &HashMap<String, String>,
+    variable_arrays: &HashMap<VariableId, String>,
     output: &mut String,
 ) -> Result<(), CompileError> {
     match arg {
         ProcTerm::Number(num) => {
-            let number_str = num.number.s();
-            if let Some(float_value) = number_str.strip_suffix("f32") {
-                // Use direct encoding
-                let f = float_value.parse::<f32>().unwrap_or(0.0);
-                output.push_str(&format!("    mov eax, 0x{:08x}\n", f.to_bits()));
-                output.push_str(&format!("    movd {register}, eax\n"));
-            } else {
-                return Err(CompileError::UnsupportedConstruct(format!(
-                    "Expected f32 number, got: {number_str}"
-                )));
-            }
+            output.push_str(&format!(
+                "    mov eax, {}\n",
+                expressions::number_value(num)
+            ));
+            output.push_str(&format!("    movd {register}, eax\n"));
         }
-        ProcTerm::Variable(var) => {
-            let var_name = var.variable.s();
-            if let Some(&var_offset) = variables.get(var_name) {
-                // Load f32 value from memory - this handles both f32 variables stored as f32
-                // and u64 variables that need to be interpreted as f32
-                output.push_str(&format!(
-                    "    movss {register}, dword ptr [rbp - 8 - {}]\n",
-                    var_offset - 8
-                ));
-            } else {
-                return Err(CompileError::UnsupportedConstruct(format!(
-                    "Unknown variable: {var_name}"
-                )));
-            }
+        ProcTerm::Variable(var) if variables.contains_key(&Slot::Variable(var.ext.0.clone())) => {
+            let var_offset = variables[&Slot::Variable(var.ext.0.clone())];
+            output.push_str(&format!(
+                "    movss {register}, dword ptr [rbp - 8 - {}]\n",
+                var_offset - 8
+            ));
         }
         ProcTerm::Paren(paren) => {
             // Handle parenthesized expressions by delegating to the inner term
@@ -100,52 +94,67 @@ pub fn load_f32_proc_argument_into_register(
                 &paren.proc_term,
                 register,
                 variables,
+                builtins,
                 arrays,
                 variable_arrays,
                 output,
             )?;
         }
         ProcTerm::Dereference(dereference) => {
-            // Handle dereference operation: expr.*
-            // First, compile the term that produces a reference/address
+            // The address is a reference local or an array element, which
+            // leaves the XMM registers alone
             expressions::compile_proc_term(
                 &dereference.term,
                 variables,
                 &HashMap::new(),
-                &HashMap::new(),
+                builtins,
                 arrays,
                 &mut variable_arrays.clone(),
                 output,
             )?;
-
-            // At this point, rax should contain the address of the f32 value
-            // Load the f32 value from that address into the XMM register
             output.push_str(&format!("    movss {register}, dword ptr [rax]\n"));
         }
         _ => {
-            return Err(CompileError::UnsupportedConstruct(format!(
-                "Unsupported f32 argument type: {arg:?}"
-            )));
+            // Any other value is computed into eax, keeping the first operand
+            // of a binary operation, which is already in xmm0
+            if register != "xmm0" {
+                output.push_str("    sub rsp, 16\n");
+                output.push_str("    movss dword ptr [rsp], xmm0\n");
+            }
+            expressions::compile_proc_term(
+                arg,
+                variables,
+                &HashMap::new(),
+                builtins,
+                arrays,
+                &mut variable_arrays.clone(),
+                output,
+            )?;
+            output.push_str(&format!("    movd {register}, eax\n"));
+            if register != "xmm0" {
+                output.push_str("    movss xmm0, dword ptr [rsp]\n");
+                output.push_str("    add rsp, 16\n");
+            }
         }
     }
     Ok(())
 }
 
 pub fn compile_proc_field_access(
-    field_access: &ProcTermFieldAccess<PhaseParse>,
-    variables: &HashMap<String, i32>,
+    field_access: &ProcTermFieldAccess<PhaseTyped>,
+    variables: &HashMap<Slot, i32>,
     arrays: &HashMap<String, ArrayInfo>,
-    variable_arrays: &HashMap<String, String>,
+    variable_arrays: &HashMap<VariableId, String>,
     output: &mut String,
 ) -> Result<(), CompileError> {
     let object_name = field_access.object.s();
     let field_name = field_access.field.s();
+    let (object, ty) = &field_access.ext;
 
     // Check if this is the #len method for an array
     if field_name == "#len" {
-        // Look up the array size variable
-        let size_var_name = format!("{object_name}_size");
-        if let Some(&size_offset) = variables.get(&size_var_name) {
+        // Look up the array size slot
+        if let Some(&size_offset) = variables.get(&Slot::Length(object.clone())) {
             // Load the array size
             output.push_str(&format!(
                 "    mov rax, qword ptr [rbp - 8 - {}]\n",
@@ -159,9 +168,19 @@ pub fn compile_proc_field_access(
         }
     }
 
+    // A field of a struct local has its own slot
+    if let Some(&field_offset) = variables.get(&Slot::Field(object.clone(), field_name.to_string()))
+    {
+        output.push_str(&format!(
+            "    mov rax, qword ptr [rbp - 8 - {}]\n",
+            field_offset - 8
+        ));
+        return Ok(());
+    }
+
     // Check if this is a Structure of Arrays (SoA) access
-    let soa_ptr_var_name = format!("{object_name}_{field_name}_ptr");
-    if let Some(&ptr_offset) = variables.get(&soa_ptr_var_name) {
+    let column = Slot::Column(object.clone(), field_name.to_string());
+    if let Some(&ptr_offset) = variables.get(&column) {
         // This is SoA access - load the field array pointer
         output.push_str(&format!(
             "    mov rax, qword ptr [rbp - 8 - {}]\n",
@@ -170,41 +189,33 @@ pub fn compile_proc_field_access(
 
         // Handle index if present
         if let Some(index_term) = &field_access.index {
-            // Get array info for element size calculation
-            if let Some(array_type_name) = variable_arrays.get(object_name)
-                && let Some(array_info) = arrays.get(array_type_name)
-            {
-                let element_size = crate::arrays::get_element_size(
-                    &array_info.field_types,
-                    &array_info.field_names,
-                    field_name,
-                )?;
+            let element_size = match ty {
+                ProcType::Reference(ty) => crate::arrays::get_type_size(&ty.to_string()),
+                _ => 8,
+            };
 
-                match &**index_term {
-                    ProcTerm::Number(num) => {
-                        let index = crate::arrays::parse_number(num.number.s());
-                        let offset = index.parse::<usize>().unwrap_or(0) * element_size;
-                        if offset > 0 {
-                            output.push_str(&format!("    add rax, {offset}\n"));
-                        }
-                    }
-                    ProcTerm::Variable(var) => {
-                        if let Some(&var_offset) = variables.get(var.variable.s()) {
-                            output.push_str(&format!(
-                                "    mov rbx, qword ptr [rbp - 8 - {}]\n",
-                                var_offset - 8
-                            ));
-                            output.push_str(&format!("    mov rcx, {element_size}\n"));
-                            output.push_str("    imul rbx, rcx\n");
-                            output.push_str("    add rax, rbx\n");
-                        }
-                    }
-                    _ => {
-                        return Err(CompileError::UnsupportedConstruct(format!(
-                            "Unsupported index type in field access: {index_term:?}"
-                        )));
+            match &**index_term {
+                ProcTerm::Number(num) => {
+                    let index = crate::arrays::parse_number(num.number.s());
+                    let offset = index.parse::<usize>().unwrap_or(0) * element_size;
+                    if offset > 0 {
+                        output.push_str(&format!("    add rax, {offset}\n"));
                     }
                 }
+                _ => {
+                    load_proc_argument_into_register(
+                        index_term,
+                        "rbx",
+                        variables,
+                        &HashMap::new(),
+                        arrays,
+                        variable_arrays,
+                        output,
+                    )?;
+                    output.push_str(&format!("    mov rcx, {element_size}\n"));
+                    output.push_str("    imul rbx, rcx\n");
+                    output.push_str("    add rax, rbx\n");
+                }
             }
         }
         // rax now contains the address of the field element in the SoA
@@ -217,12 +228,12 @@ pub fn compile_proc_field_access(
 }
 
 pub fn compile_proc_dereference(
-    dereference: &ProcTermDereference<PhaseParse>,
-    variables: &HashMap<String, i32>,
-    reference_variables: &HashMap<String, String>,
-    builtins: &HashMap<String, String>,
+    dereference: &ProcTermDereference<PhaseTyped>,
+    variables: &HashMap<Slot, i32>,
+    reference_variables: &HashMap<VariableId, VariableId>,
+    builtins: &HashMap<VariableId, String>,
     arrays: &HashMap<String, ArrayInfo>,
-    variable_arrays: &mut HashMap<String, String>,
+    variable_arrays: &mut HashMap<VariableId, String>,
     output: &mut String,
 ) -> Result<(), CompileError> {
     // First compile the term that produces a reference
@@ -236,74 +247,10 @@ pub fn compile_proc_dereference(
         output,
     )?;
 
-    // Determine the type being dereferenced to use the correct instruction
-    // Check if this is a field access that we can determine the type for
-    if let ProcTerm::FieldAccess(field_access) = &*dereference.term {
-        let object_name = field_access.object.s();
-        let field_name = field_access.field.s();
-
-        // Try to get type information from arrays
-        if let Some(array_type_name) = variable_arrays.get(object_name)
-            && let Some(array_info) = arrays.get(array_type_name)
-            && let Ok(field_type) = crate::arrays::get_field_type(
-                &array_info.field_types,
-                &array_info.field_names,
-                field_name,
-            )
-        {
-            match field_type.as_str() {
-                "f32" => {
-                    // For f32, load 4 bytes and zero-extend to 8 bytes in rax
-                    output.push_str("    mov eax, dword ptr [rax]\n");
-                    return Ok(());
-                }
-                "f64" => {
-                    output.push_str("    mov rax, qword ptr [rax]\n");
-                    return Ok(());
-                }
-                "u64" | "i64" => {
-                    output.push_str("    mov rax, qword ptr [rax]\n");
-                    return Ok(());
-                }
-                "u32" | "i32" => {
-                    output.push_str("    mov eax, dword ptr [rax]\n");
-                    return Ok(());
-                }
-                "u16" | "i16" => {
-                    output.push_str("    movzx rax, word ptr [rax]\n");
-                    return Ok(());
-                }
-                "u8" | "i8" => {
-                    output.push_str("    movzx rax, byte ptr [rax]\n");
-                    return Ok(());
-                }
-                _ => {
-                    // Default case - assume 8 bytes
-                    output.push_str("    mov rax, qword ptr [rax]\n");
-                    return Ok(());
-                }
-            }
-        }
+    // The width of the load is the size of the value
+    match dereference.ext {
+        ProcType::F32 => output.push_str("    mov eax, dword ptr [rax]\n"),
+        _ => output.push_str("    mov rax, qword ptr [rax]\n"),
     }
-
-    // Default case - assume 8 bytes for unknown types
-    output.push_str("    mov rax, qword ptr [rax]\n");
     Ok(())
 }
-
-fn parse_number(number_str: &str) -> String {
-    if number_str.ends_with("u64") {
-        number_str.trim_end_matches("u64").to_string()
-    } else if number_str.ends_with("f32") {
-        // For f32 numbers, we need to handle them specially
-        let float_value = number_str.trim_end_matches("f32");
-        // Convert to u32 representation for storage
-        if let Ok(f) = float_value.parse::<f32>() {
-            format!("0x{:08x}", f.to_bits())
-        } else {
-            float_value.to_string()
-        }
-    } else {
-        number_str.to_string()
-    }
-}
@@ -6,8 +6,10 @@ pub mod memory;
 pub mod utils;
 pub mod variables;
 
-use crate::{ArrayInfo, error::CompileError};
+use crate::{ArrayInfo, Slot, error::CompileError};
+use neco_felis_rename::VariableId;
 use neco_felis_syn::*;
+use neco_felis_type_check::PhaseTyped;
 use std::collections::HashMap;
 
 pub struct StatementCompiler;
@@ -15,12 +17,12 @@ pub struct StatementCompiler;
 impl StatementCompiler {
     #[allow(clippy::too_many_arguments)]
     pub fn compile_statement(
-        statement: &Statement<PhaseParse>,
-        variables: &mut HashMap<String, i32>,
-        reference_variables: &mut HashMap<String, String>,
-        builtins: &HashMap<String, String>,
+        statement: &Statement<PhaseTyped>,
+        variables: &mut HashMap<Slot, i32>,
+        reference_variables: &mut HashMap<VariableId, VariableId>,
+        builtins: &HashMap<VariableId, String>,
         arrays: &HashMap<String, ArrayInfo>,
-        variable_arrays: &mut HashMap<String, String>,
+        variable_arrays: &mut HashMap<VariableId, String>,
         stack_offset: &mut i32,
         output: &mut String,
     ) -> Result<(), CompileError> {
@@ -107,12 +109,12 @@ impl StatementCompiler {
 
     #[allow(clippy::too_many_arguments)]
     pub fn compile_statements(
-        statements: &Statements<PhaseParse>,
-        variables: &mut HashMap<String, i32>,
-        reference_variables: &mut HashMap<String, String>,
-        builtins: &HashMap<String, String>,
+        statements: &Statements<PhaseTyped>,
+        variables: &mut HashMap<Slot, i32>,
+        reference_variables: &mut HashMap<VariableId, VariableId>,
+        builtins: &HashMap<VariableId, String>,
         arrays: &HashMap<String, ArrayInfo>,
-        variable_arrays: &mut HashMap<String, String>,
+        variable_arrays: &mut HashMap<VariableId, String>,
         stack_offset: &mut i32,
         output: &mut String,
     ) -> Result<(), CompileError> {
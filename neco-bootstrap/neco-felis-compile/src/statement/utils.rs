@@ -1,30 +1,47 @@
+use crate::ArrayInfo;
 use neco_felis_syn::*;
+use neco_felis_type_check::{PhaseTyped, ProcType};
+use std::collections::HashMap;
 
-pub fn count_let_variables_in_statements(statements: &Statements<PhaseParse>) -> i32 {
+pub fn count_let_variables_in_statements(
+    statements: &Statements<PhaseTyped>,
+    arrays: &HashMap<String, ArrayInfo>,
+) -> i32 {
     match statements {
         Statements::Then(then) => {
-            count_let_variables_in_statement(&then.head)
-                + count_let_variables_in_statements(&then.tail)
+            count_let_variables_in_statement(&then.head, arrays)
+                + count_let_variables_in_statements(&then.tail, arrays)
         }
-        Statements::Statement(statement) => count_let_variables_in_statement(statement),
+        Statements::Statement(statement) => count_let_variables_in_statement(statement, arrays),
         Statements::Nil => 0,
     }
 }
 
-pub fn count_let_variables_in_statement(statement: &Statement<PhaseParse>) -> i32 {
+pub fn count_let_variables_in_statement(
+    statement: &Statement<PhaseTyped>,
+    arrays: &HashMap<String, ArrayInfo>,
+) -> i32 {
     match statement {
-        Statement::Let(_) => 1,
+        // A struct local takes a slot for each field
+        Statement::Let(let_stmt) => match (&let_stmt.ext.1, &*let_stmt.value) {
+            (_, ProcTerm::StructValue(struct_value)) => struct_value.fields.len().max(1) as i32,
+            // An array local also holds its size and a pointer per field
+            (ProcType::Array(name), ProcTerm::ConstructorCall(_)) => arrays
+                .get(name)
+                .map_or(1, |array_info| 2 + array_info.field_names.len() as i32),
+            _ => 1,
+        },
         Statement::LetMut(_) => 2, // let mut uses 2 stack slots: one for value, one for reference
         Statement::Expr(proc_term) => count_let_variables_in_proc_term(proc_term),
         _ => 0,
     }
 }
 
-pub fn count_let_variables_in_proc_term(_proc_term: &ProcTerm<PhaseParse>) -> i32 {
+pub fn count_let_variables_in_proc_term(_proc_term: &ProcTerm<PhaseTyped>) -> i32 {
     0
 }
 
-pub fn has_ptx_calls_in_statements(statements: &Statements<PhaseParse>) -> bool {
+pub fn has_ptx_calls_in_statements(statements: &Statements<PhaseTyped>) -> bool {
     match statements {
         Statements::Then(then) => {
             has_ptx_calls_in_statement(&then.head) || has_ptx_calls_in_statements(&then.tail)
@@ -34,7 +51,7 @@ pub fn has_ptx_calls_in_statements(statements: &Statements<PhaseParse>) -> bool
     }
 }
 
-pub fn has_ptx_calls_in_statement(statement: &Statement<PhaseParse>) -> bool {
+pub fn has_ptx_calls_in_statement(statement: &Statement<PhaseTyped>) -> bool {
     match statement {
         Statement::CallPtx(_) => true,
         Statement::Expr(proc_term) => has_ptx_calls_in_proc_term(proc_term),
@@ -42,7 +59,7 @@ pub fn has_ptx_calls_in_statement(statement: &Statement<PhaseParse>) -> bool {
     }
 }
 
-pub fn has_ptx_calls_in_proc_term(proc_term: &ProcTerm<PhaseParse>) -> bool {
+pub fn has_ptx_calls_in_proc_term(proc_term: &ProcTerm<PhaseTyped>) -> bool {
     match proc_term {
         ProcTerm::If(if_expr) => {
             has_ptx_calls_in_statements(&if_expr.then_body)
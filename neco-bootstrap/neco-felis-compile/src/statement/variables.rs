@@ -1,30 +1,32 @@
-use crate::{ArrayInfo, error::CompileError};
+use crate::{ArrayInfo, Slot, error::CompileError};
+use neco_felis_rename::VariableId;
 use neco_felis_syn::*;
+use neco_felis_type_check::{PhaseTyped, ProcType};
 use std::collections::HashMap;
 
-use super::arithmetic;
 use super::expressions;
 
 #[allow(clippy::too_many_arguments)]
 pub fn compile_let_statement(
-    let_stmt: &StatementLet<PhaseParse>,
-    variables: &mut HashMap<String, i32>,
-    reference_variables: &HashMap<String, String>,
-    builtins: &HashMap<String, String>,
+    let_stmt: &StatementLet<PhaseTyped>,
+    variables: &mut HashMap<Slot, i32>,
+    reference_variables: &HashMap<VariableId, VariableId>,
+    builtins: &HashMap<VariableId, String>,
     arrays: &HashMap<String, ArrayInfo>,
-    variable_arrays: &mut HashMap<String, String>,
+    variable_arrays: &mut HashMap<VariableId, String>,
     stack_offset: &mut i32,
     output: &mut String,
 ) -> Result<(), CompileError> {
-    let var_name = let_stmt.variable_name().to_string();
+    let (id, ty) = &let_stmt.ext;
     *stack_offset += 8;
     let offset = *stack_offset;
 
-    match &*let_stmt.value {
-        ProcTerm::ConstructorCall(constructor_call) => {
+    match (ty, &*let_stmt.value) {
+        (ProcType::Array(_), ProcTerm::ConstructorCall(constructor_call)) => {
             super::constructors::compile_proc_constructor_call_with_var(
                 constructor_call,
-                &var_name,
+                id,
+                let_stmt.variable_name(),
                 arrays,
                 output,
                 stack_offset,
@@ -35,69 +37,49 @@ pub fn compile_let_statement(
             // The constructor call handles the variable registration internally
             Ok(())
         }
-        ProcTerm::Apply(apply) => {
-            // Check if this is a f32 arithmetic operation that should store f32 result
-            if let ProcTerm::Variable(var) = &*apply.f
-                && let Some(builtin) = builtins.get(var.variable.s())
-            {
-                match builtin.as_str() {
-                    "f32_add" | "f32_sub" | "f32_mul" | "f32_div" => {
-                        // These operations produce f32 results that should be stored as f32
-                        arithmetic::compile_f32_arithmetic_for_let(
-                            apply,
-                            builtin,
-                            variables,
-                            arrays,
-                            variable_arrays,
-                            output,
-                            offset,
-                        )?;
-                        variables.insert(var_name, offset);
-                        return Ok(());
-                    }
-                    "u64_mod" => {
-                        // Modulo operation stores remainder from rdx directly
-                        arithmetic::compile_u64_mod_for_let(apply, variables, output, offset)?;
-                        variables.insert(var_name, offset);
-                        return Ok(());
-                    }
-                    _ => {}
+        (ProcType::Struct(_), ProcTerm::StructValue(struct_value)) => {
+            // Each field of a struct local has its own slot
+            for (i, field) in struct_value.fields.iter().enumerate() {
+                if i > 0 {
+                    *stack_offset += 8;
                 }
+                let field_offset = *stack_offset;
+                expressions::compile_proc_term(
+                    &field.value,
+                    variables,
+                    reference_variables,
+                    builtins,
+                    arrays,
+                    variable_arrays,
+                    output,
+                )?;
+                output.push_str(&format!(
+                    "    mov qword ptr [rbp - 8 - {}], rax\n",
+                    field_offset - 8
+                ));
+                variables.insert(
+                    Slot::Field(id.clone(), field.name.s().to_string()),
+                    field_offset,
+                );
             }
-
-            // Compile other expressions normally
-            expressions::compile_proc_term(
-                &let_stmt.value,
-                variables,
-                reference_variables,
-                builtins,
-                arrays,
-                variable_arrays,
-                output,
-            )?;
-
-            // Store the result from rax to the variable's stack location
-            output.push_str(&format!(
-                "    mov qword ptr [rbp - 8 - {}], rax\n",
-                offset - 8
-            ));
-
-            variables.insert(var_name, offset);
             Ok(())
         }
-        ProcTerm::Number(num) => {
+        (ProcType::Struct(name), _) => Err(CompileError::UnsupportedConstruct(format!(
+            "Struct {name} values other than struct literals"
+        ))),
+        (ProcType::U64, ProcTerm::Number(num)) => {
             // Direct number assignment - store immediately without going through rax
-            let number_value = parse_number(num.number.s());
+            let number_value = expressions::number_value(num);
             output.push_str(&format!(
                 "    mov qword ptr [rbp - 8 - {}], {}\n",
                 offset - 8,
                 number_value
             ));
-            variables.insert(var_name, offset);
+            variables.insert(Slot::Variable(id.clone()), offset);
             Ok(())
         }
         _ => {
-            // Compile the expression
+            // Compile the expression. An `f32` is in the low half of rax.
             expressions::compile_proc_term(
                 &let_stmt.value,
                 variables,
@@ -114,7 +96,7 @@ pub fn compile_let_statement(
                 offset - 8
             ));
 
-            variables.insert(var_name, offset);
+            variables.insert(Slot::Variable(id.clone()), offset);
             Ok(())
         }
     }
@@ -122,17 +104,16 @@ pub fn compile_let_statement(
 
 #[allow(clippy::too_many_arguments)]
 pub fn compile_let_mut_statement(
-    let_mut_stmt: &StatementLetMut<PhaseParse>,
-    variables: &mut HashMap<String, i32>,
-    reference_variables: &mut HashMap<String, String>,
-    builtins: &HashMap<String, String>,
+    let_mut_stmt: &StatementLetMut<PhaseTyped>,
+    variables: &mut HashMap<Slot, i32>,
+    reference_variables: &mut HashMap<VariableId, VariableId>,
+    builtins: &HashMap<VariableId, String>,
     arrays: &HashMap<String, ArrayInfo>,
-    variable_arrays: &mut HashMap<String, String>,
+    variable_arrays: &mut HashMap<VariableId, String>,
     stack_offset: &mut i32,
     output: &mut String,
 ) -> Result<(), CompileError> {
-    let var_name = let_mut_stmt.variable_name().to_string();
-    let ref_var_name = let_mut_stmt.reference_variable_name().to_string();
+    let (id, reference_id, _) = &let_mut_stmt.ext;
 
     // Allocate space for the value (8 bytes)
     *stack_offset += 8;
@@ -170,28 +151,28 @@ pub fn compile_let_mut_statement(
     ));
 
     // Register both variables
-    variables.insert(var_name.clone(), value_offset);
-    variables.insert(ref_var_name.clone(), ref_offset);
+    variables.insert(Slot::Variable(id.clone()), value_offset);
+    variables.insert(Slot::Variable(reference_id.clone()), ref_offset);
 
-    // Track that ref_var_name is a reference to var_name
-    reference_variables.insert(ref_var_name, var_name);
+    // Track that the reference refers to the value
+    reference_variables.insert(reference_id.clone(), id.clone());
 
     Ok(())
 }
 
 pub fn compile_assign_statement(
-    assign_stmt: &StatementAssign<PhaseParse>,
-    variables: &mut HashMap<String, i32>,
-    reference_variables: &HashMap<String, String>,
-    builtins: &HashMap<String, String>,
+    assign_stmt: &StatementAssign<PhaseTyped>,
+    variables: &mut HashMap<Slot, i32>,
+    reference_variables: &HashMap<VariableId, VariableId>,
+    builtins: &HashMap<VariableId, String>,
     arrays: &HashMap<String, ArrayInfo>,
-    variable_arrays: &mut HashMap<String, String>,
+    variable_arrays: &mut HashMap<VariableId, String>,
     output: &mut String,
 ) -> Result<(), CompileError> {
     let var_name = assign_stmt.variable.s();
 
     // Check if this is a mutable variable (reference)
-    if let Some(&ref_offset) = variables.get(var_name) {
+    if let Some(&ref_offset) = variables.get(&Slot::Variable(assign_stmt.ext.clone())) {
         // Compile the value to assign
         expressions::compile_proc_term(
             &assign_stmt.value,
@@ -221,12 +202,12 @@ pub fn compile_assign_statement(
 }
 
 pub fn compile_field_assign_statement(
-    field_assign_stmt: &StatementFieldAssign<PhaseParse>,
-    variables: &mut HashMap<String, i32>,
-    reference_variables: &HashMap<String, String>,
-    builtins: &HashMap<String, String>,
+    field_assign_stmt: &StatementFieldAssign<PhaseTyped>,
+    variables: &mut HashMap<Slot, i32>,
+    reference_variables: &HashMap<VariableId, VariableId>,
+    builtins: &HashMap<VariableId, String>,
     arrays: &HashMap<String, ArrayInfo>,
-    variable_arrays: &mut HashMap<String, String>,
+    variable_arrays: &mut HashMap<VariableId, String>,
     output: &mut String,
 ) -> Result<(), CompileError> {
     // Compile the field access to get the address
@@ -238,8 +219,8 @@ pub fn compile_field_assign_statement(
         output,
     )?;
 
-    // Save the address in rbx
-    output.push_str("    mov rbx, rax\n");
+    // Keep the address while the value is computed
+    output.push_str("    push rax\n");
 
     // Compile the value to assign
     expressions::compile_proc_term(
@@ -252,20 +233,26 @@ pub fn compile_field_assign_statement(
         output,
     )?;
 
-    // Store the value at the field address
-    output.push_str("    mov qword ptr [rbx], rax\n");
+    // Store the value at the field address, with the width of the field
+    output.push_str("    pop rbx\n");
+    match &field_assign_stmt.field_access.ext.1 {
+        ProcType::Reference(ty) if **ty == ProcType::F32 => {
+            output.push_str("    mov dword ptr [rbx], eax\n")
+        }
+        _ => output.push_str("    mov qword ptr [rbx], rax\n"),
+    }
 
     Ok(())
 }
 
 #[allow(clippy::too_many_arguments)]
 pub fn compile_return_statement(
-    return_stmt: &StatementReturn<PhaseParse>,
-    variables: &HashMap<String, i32>,
-    reference_variables: &HashMap<String, String>,
-    builtins: &HashMap<String, String>,
+    return_stmt: &StatementReturn<PhaseTyped>,
+    variables: &HashMap<Slot, i32>,
+    reference_variables: &HashMap<VariableId, VariableId>,
+    builtins: &HashMap<VariableId, String>,
     arrays: &HashMap<String, ArrayInfo>,
-    variable_arrays: &mut HashMap<String, String>,
+    variable_arrays: &mut HashMap<VariableId, String>,
     output: &mut String,
 ) -> Result<(), CompileError> {
     // Compile the return value expression
@@ -283,20 +270,3 @@ pub fn compile_return_statement(
     // The compile_proc_term already puts the result in rax, so no additional work needed
     Ok(())
 }
-
-fn parse_number(number_str: &str) -> String {
-    if number_str.ends_with("u64") {
-        number_str.trim_end_matches("u64").to_string()
-    } else if number_str.ends_with("f32") {
-        // For f32 numbers, we need to handle them specially
-        let float_value = number_str.trim_end_matches("f32");
-        // Convert to u32 representation for storage
-        if let Ok(f) = float_value.parse::<f32>() {
-            format!("0x{:08x}", f.to_bits())
-        } else {
-            float_value.to_string()
-        }
-    } else {
-        number_str.to_string()
-    }
-}
@@ -1,13 +1,14 @@
-use crate::error::CompileError;
+use crate::{Slot, error::CompileError};
 use neco_felis_syn::*;
+use neco_felis_type_check::PhaseTyped;
 use std::collections::HashMap;
 
 pub struct SyscallCompiler;
 
 impl SyscallCompiler {
     pub fn compile_proc_syscall(
-        args: &[ProcTerm<PhaseParse>],
-        variables: &HashMap<String, i32>,
+        args: &[ProcTerm<PhaseTyped>],
+        variables: &HashMap<Slot, i32>,
         output: &mut String,
     ) -> Result<(), CompileError> {
         if args.len() != 6 {
@@ -24,7 +25,7 @@ impl SyscallCompiler {
                 }
                 ProcTerm::Variable(var) => {
                     let var_name = var.variable.s();
-                    if let Some(&offset) = variables.get(var_name) {
+                    if let Some(&offset) = variables.get(&Slot::Variable(var.ext.0.clone())) {
                         // Load value from stack into register
                         output.push_str(&format!(
                             "    mov {}, qword ptr [rbp - 8 - {}]\n",
@@ -56,9 +57,9 @@ impl SyscallCompiler {
     }
 
     pub fn load_proc_argument_into_register(
-        arg: &ProcTerm<PhaseParse>,
+        arg: &ProcTerm<PhaseTyped>,
         register: &str,
-        variables: &HashMap<String, i32>,
+        variables: &HashMap<Slot, i32>,
         output: &mut String,
     ) -> Result<(), CompileError> {
         match arg {
@@ -68,7 +69,7 @@ impl SyscallCompiler {
                 Ok(())
             }
             ProcTerm::Variable(var) => {
-                if let Some(offset) = variables.get(var.variable.s()) {
+                if let Some(offset) = variables.get(&Slot::Variable(var.ext.0.clone())) {
                     output.push_str(&format!(
                         "    mov {register}, qword ptr [rbp - 8 - {}]\n",
                         offset - 8
@@ -78,6 +79,8 @@ impl SyscallCompiler {
                 }
                 Ok(())
             }
+            // A unit argument, as in `f ()`, passes nothing
+            ProcTerm::Unit(_) => Ok(()),
             _ => Err(CompileError::UnsupportedConstruct(format!(
                 "Unsupported argument type: {arg:?}"
             ))),
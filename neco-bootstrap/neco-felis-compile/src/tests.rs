@@ -1,4 +1,5 @@
 use super::*;
+use crate::compile_options::{CompileOptions, ContractMode};
 use std::process::{Command, Stdio};
 use tempfile::TempDir;
 
@@ -79,7 +80,8 @@ fn test_compile_mod() {
     assert!(assembly.contains("mov rbx, 100"));
     assert!(assembly.contains("xor rdx, rdx"));
     assert!(assembly.contains("div rbx"));
-    assert!(assembly.contains("mov qword ptr [rbp - 8 - 8], rdx"));
+    assert!(assembly.contains("mov rax, rdx"));
+    assert!(assembly.contains("mov qword ptr [rbp - 8 - 8], rax"));
     assert!(assembly.contains("syscall"));
     assert!(assembly.contains("main:"));
     assert!(assembly.contains("_start:"));
@@ -320,6 +322,14 @@ fn test_let_integration() {
     }
 }
 
+#[test]
+fn test_let_shadow_integration() {
+    // The shadowing `#let` gets a slot of its own, so the assignment through
+    // `error_code_ref` still writes to the mutable `error_code`
+    let status = compile_and_execute("../../testcases/felis/single/let_shadow.fe").unwrap();
+    assert_eq!(status.code(), Some(42), "Program should exit with code 42");
+}
+
 #[test]
 fn test_add_integration() {
     let result = compile_and_execute("../../testcases/felis/single/add.fe");
@@ -415,6 +425,13 @@ fn test_mod_integration() {
     }
 }
 
+#[test]
+fn test_division_by_zero_computes_as_the_kernel() {
+    // 84 / 0 + 42 % 0 = 0 + 42, where x86 `div` would trap
+    let status = compile_and_execute("../../testcases/felis/single/div_by_zero.fe").unwrap();
+    assert_eq!(status.code(), Some(42));
+}
+
 #[test]
 fn test_compile_add_f32() {
     let assembly = compile_file_to_assembly("../../testcases/felis/single/add_f32.fe").unwrap();
@@ -424,7 +441,9 @@ fn test_compile_add_f32() {
     assert!(assembly.contains("mov eax, 0x40000000")); // 2.0f32
     assert!(assembly.contains("movd xmm1, eax"));
     assert!(assembly.contains("addss xmm0, xmm1"));
-    assert!(assembly.contains("movss dword ptr [rbp - 8 - 8], xmm0"));
+    // The f32 result is stored as a word, its bits in the low half
+    assert!(assembly.contains("movd eax, xmm0"));
+    assert!(assembly.contains("mov qword ptr [rbp - 8 - 8], rax"));
     assert!(assembly.contains("cvttss2si rax, xmm0"));
     assert!(assembly.contains("syscall"));
     assert!(assembly.contains("main:"));
@@ -440,7 +459,9 @@ fn test_compile_sub_f32() {
     assert!(assembly.contains("mov eax, 0x41000000")); // 8.0f32
     assert!(assembly.contains("movd xmm1, eax"));
     assert!(assembly.contains("subss xmm0, xmm1"));
-    assert!(assembly.contains("movss dword ptr [rbp - 8 - 8], xmm0"));
+    // The f32 result is stored as a word, its bits in the low half
+    assert!(assembly.contains("movd eax, xmm0"));
+    assert!(assembly.contains("mov qword ptr [rbp - 8 - 8], rax"));
     assert!(assembly.contains("cvttss2si rax, xmm0"));
     assert!(assembly.contains("syscall"));
     assert!(assembly.contains("main:"));
@@ -456,7 +477,9 @@ fn test_compile_mul_f32() {
     assert!(assembly.contains("mov eax, 0x40e00000")); // 7.0f32
     assert!(assembly.contains("movd xmm1, eax"));
     assert!(assembly.contains("mulss xmm0, xmm1"));
-    assert!(assembly.contains("movss dword ptr [rbp - 8 - 8], xmm0"));
+    // The f32 result is stored as a word, its bits in the low half
+    assert!(assembly.contains("movd eax, xmm0"));
+    assert!(assembly.contains("mov qword ptr [rbp - 8 - 8], rax"));
     assert!(assembly.contains("cvttss2si rax, xmm0"));
     assert!(assembly.contains("syscall"));
     assert!(assembly.contains("main:"));
@@ -472,7 +495,9 @@ fn test_compile_div_f32() {
     assert!(assembly.contains("mov eax, 0x40000000")); // 2.0f32
     assert!(assembly.contains("movd xmm1, eax"));
     assert!(assembly.contains("divss xmm0, xmm1"));
-    assert!(assembly.contains("movss dword ptr [rbp - 8 - 8], xmm0"));
+    // The f32 result is stored as a word, its bits in the low half
+    assert!(assembly.contains("movd eax, xmm0"));
+    assert!(assembly.contains("mov qword ptr [rbp - 8 - 8], rax"));
     assert!(assembly.contains("cvttss2si rax, xmm0"));
     assert!(assembly.contains("syscall"));
     assert!(assembly.contains("main:"));
@@ -611,7 +636,8 @@ fn test_compile_array() {
 
     // Check for array field assignments
     assert!(assembly.contains("mov rax, 0x41200000")); // 10.0f32
-    assert!(assembly.contains("mov qword ptr [rbx], rax"));
+    assert!(assembly.contains("mov dword ptr [rbx], eax")); // f32 fields are 4 bytes
+    assert!(assembly.contains("mov rax, 4")); // element size of the f32 fields
 
     // Check for field access in builtin calls
     assert!(assembly.contains("movss xmm0, dword ptr [rax]"));
@@ -842,6 +868,291 @@ fn test_proc_call_integration() {
     }
 }
 
+#[test]
+fn test_mixed_f32_integration() {
+    let assembly = compile_file_to_assembly("../../testcases/felis/single/mixed_f32.fe").unwrap();
+    // Literals without a suffix are encoded by their type
+    assert!(assembly.contains("mov rax, 0x3fc00000")); // 1.5
+    // The f32 argument of `scale` is passed in rdi as a word
+    assert!(assembly.contains("pop rdi"));
+    assert!(assembly.contains("mov dword ptr [rbx], eax"));
+
+    // 3.5 * 12 + 0
+    let status = compile_and_execute("../../testcases/felis/single/mixed_f32.fe").unwrap();
+    assert_eq!(status.code(), Some(42));
+}
+
+#[test]
+fn test_compile_extracted_definitions() {
+    let assembly = compile_file_to_assembly("../../testcases/felis/single/extract_nat.fe").unwrap();
+    // Definitions called from procedures, and the ones they call, are compiled
+    assert!(assembly.contains("six:"));
+    assert!(assembly.contains("mul:"));
+    assert!(assembly.contains("add:"));
+    assert!(assembly.contains("call six"));
+    // Theorems are only type checked
+    assert!(!assembly.contains("mul_1_2_eq_add_1_1"));
+    // Constructors allocate, and matches jump on the constructor index
+    assert!(assembly.contains("mov qword ptr extracted_heap_next[rip], rcx"));
+    assert!(assembly.contains("mov rcx, qword ptr [rax]"));
+    assert!(assembly.contains("je extracted_case_0_1"));
+}
+
+#[test]
+fn test_extracted_definitions_integration() {
+    let result = compile_and_execute("../../testcases/felis/single/extract_nat.fe");
+
+    match result {
+        Ok(status) => {
+            // extract_nat.fe counts the successors of six, 7 at a time
+            assert_eq!(status.code(), Some(42), "Program should exit with code 42");
+        }
+        Err(e) => {
+            panic!("extract_nat.fe integration test failed: {e}");
+        }
+    }
+}
+
+#[test]
+fn test_extracted_primitives_integration() {
+    let assembly = compile_file_to_assembly("../../testcases/felis/single/extract_u64.fe").unwrap();
+    assert!(assembly.contains("answer:"));
+    assert!(assembly.contains("of_nat:"));
+    assert!(assembly.contains("jz extracted_by_zero_"));
+
+    let result = compile_and_execute("../../testcases/felis/single/extract_u64.fe");
+    match result {
+        Ok(status) => {
+            // Division by zero computes as in the kernel, where answer 40 is 42
+            assert_eq!(status.code(), Some(42), "Program should exit with code 42");
+        }
+        Err(e) => {
+            panic!("extract_u64.fe integration test failed: {e}");
+        }
+    }
+}
+
+#[test]
+fn test_extracted_heap_exhaustion_aborts() {
+    let assembly =
+        compile_file_to_assembly("../../testcases/felis/single/extract_heap.fe").unwrap();
+    // `six` is called twice but compiled once
+    assert_eq!(assembly.matches("\nsix:\n").count(), 1);
+    assert!(assembly.contains("cmp rcx, qword ptr extracted_heap_end[rip]"));
+
+    // Each iteration allocates a new six, which is never freed
+    let output = execute_assembly(&assembly).unwrap();
+    assert_eq!(output.status.code(), Some(134));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "extracted heap exhausted\n"
+    );
+}
+
+#[test]
+fn test_compile_rejects_ill_typed_definitions() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("ill_typed.fe");
+    let source = std::fs::read_to_string("../../testcases/felis/single/extract_nat.fe")
+        .unwrap()
+        .replace("S p => add m (mul p m)", "S p => add m p m");
+    std::fs::write(&path, source).unwrap();
+    let error = compile_file_to_assembly(&path.to_string_lossy()).unwrap_err();
+    assert!(error.to_string().starts_with("Not a function"), "{error}");
+}
+
+#[test]
+fn test_compile_returns_the_lint_warnings() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("unused.fe");
+    let source = std::fs::read_to_string("../../testcases/felis/single/exit_42.fe")
+        .unwrap()
+        .replace("{\n", "{\n    #let code = 0;\n");
+    std::fs::write(&path, source).unwrap();
+    let compile_options = CompileOptions {
+        use_ptx: false,
+        contracts: ContractMode::Static,
+        environment_file: None,
+    };
+    let (_, warnings) =
+        compile_file_to_assembly_with_warnings(&path.to_string_lossy(), compile_options).unwrap();
+    let warnings: Vec<String> = warnings.iter().map(|w| w.to_string()).collect();
+    assert_eq!(
+        warnings,
+        ["Unused variable: code at 6:10 (unused_variables)".to_string()]
+    );
+}
+
+/// Helper function to assemble, link, and execute generated assembly with output capture
+fn execute_assembly(assembly: &str) -> Result<std::process::Output, Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let asm_file = temp_dir.path().join("program.s");
+    let obj_file = temp_dir.path().join("program.o");
+    let exe_file = temp_dir.path().join("program");
+    std::fs::write(&asm_file, assembly)?;
+
+    let as_status = Command::new("as")
+        .args([
+            "--64",
+            &asm_file.to_string_lossy(),
+            "-o",
+            &obj_file.to_string_lossy(),
+        ])
+        .status()?;
+    if !as_status.success() {
+        return Err("Assembly failed".into());
+    }
+
+    let ld_status = Command::new("ld")
+        .args([
+            obj_file.to_string_lossy().as_ref(),
+            "-o",
+            &exe_file.to_string_lossy(),
+        ])
+        .status()?;
+    if !ld_status.success() {
+        return Err("Linking failed".into());
+    }
+
+    Ok(Command::new(&exe_file).output()?)
+}
+
+fn compile_with_runtime_contracts(path: &str) -> String {
+    let compile_options = CompileOptions {
+        use_ptx: false,
+        contracts: ContractMode::Runtime,
+        environment_file: None,
+    };
+    compile_file_to_assembly_with_options(path, compile_options).unwrap()
+}
+
+#[test]
+fn test_compile_contracts() {
+    // Contracts are only type checked by default
+    let assembly = compile_file_to_assembly("../../testcases/felis/single/contracts.fe").unwrap();
+    assert!(!assembly.contains("div_safe.requires.0"));
+    assert!(!assembly.contains("contract_message_"));
+
+    let assembly = compile_with_runtime_contracts("../../testcases/felis/single/contracts.fe");
+    assert!(assembly.contains("call div_safe.requires.0"));
+    assert!(assembly.contains("call div_safe.ensures.1"));
+    assert!(assembly.contains("div_safe.requires.0:"));
+    // The result of a procedure with three parameters is the fourth argument
+    assert!(assembly.contains("call mul_add.ensures.0"));
+    assert!(assembly.contains("    mov rcx, rax\n"));
+    assert!(assembly.contains("mov rdi, 134"));
+
+    let output = execute_assembly(&assembly).unwrap();
+    assert_eq!(
+        output.status.code(),
+        Some(42),
+        "Program should exit with code 42"
+    );
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn test_violated_contracts_abort() {
+    let source = std::fs::read_to_string("../../testcases/felis/single/contracts.fe").unwrap();
+    let temp_dir = TempDir::new().unwrap();
+
+    let path = temp_dir.path().join("requires.fe");
+    std::fs::write(
+        &path,
+        source.replace("div_safe 84u64 2u64", "div_safe 84u64 0u64"),
+    )
+    .unwrap();
+    let assembly = compile_with_runtime_contracts(&path.to_string_lossy());
+    let output = execute_assembly(&assembly).unwrap();
+    assert_eq!(output.status.code(), Some(134));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "div_safe: #requires not (eq __u64 b 0) violated at 21:5\n"
+    );
+
+    let path = temp_dir.path().join("ensures.fe");
+    std::fs::write(
+        &path,
+        source.replace("div_safe 84u64 2u64", "div_safe 1u64 2u64"),
+    )
+    .unwrap();
+    let assembly = compile_with_runtime_contracts(&path.to_string_lossy());
+    let output = execute_assembly(&assembly).unwrap();
+    assert_eq!(output.status.code(), Some(134));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "div_safe: #ensures eq __u64 (__u64_mul result b) a violated at 22:5\n"
+    );
+}
+
+#[test]
+fn test_compile_rejects_ill_typed_contracts() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("ill_typed.fe");
+    let source = std::fs::read_to_string("../../testcases/felis/single/contracts.fe")
+        .unwrap()
+        .replace("#requires not (eq b 0)", "#requires not (eq c 0)");
+    std::fs::write(&path, source).unwrap();
+    let error = compile_file_to_assembly(&path.to_string_lossy()).unwrap_err();
+    assert!(error.to_string().starts_with("Unknown variable"), "{error}");
+}
+
+#[test]
+fn test_undecidable_contracts_are_only_checked_statically() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("undecidable.fe");
+    let source = std::fs::read_to_string("../../testcases/felis/single/contracts.fe")
+        .unwrap()
+        .replace(
+            "#requires not (eq b 0)",
+            "#requires (x : __u64) -> not (eq (__u64_mul x b) a)",
+        );
+    std::fs::write(&path, source).unwrap();
+    assert!(compile_file_to_assembly(&path.to_string_lossy()).is_ok());
+
+    let compile_options = CompileOptions {
+        use_ptx: false,
+        contracts: ContractMode::Runtime,
+        environment_file: None,
+    };
+    let error = compile_file_to_assembly_with_options(&path.to_string_lossy(), compile_options)
+        .unwrap_err();
+    assert!(
+        error.to_string().ends_with("cannot be decided at runtime"),
+        "{error}"
+    );
+}
+
+#[test]
+fn test_compile_rejects_ill_typed_procedures() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("ill_typed.fe");
+    let source = std::fs::read_to_string("../../testcases/felis/single/let_mut.fe")
+        .unwrap()
+        .replace("error_code_ref <- 42u64;", "error_code <- 42u64;");
+    std::fs::write(&path, source).unwrap();
+    let error = compile_file_to_assembly(&path.to_string_lossy()).unwrap_err();
+    assert!(error.to_string().starts_with("Not a reference"), "{error}");
+}
+
+#[test]
+fn test_compile_rejects_unbound_names() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("unbound.fe");
+    let source = std::fs::read_to_string("../../testcases/felis/single/let_mut.fe")
+        .unwrap()
+        .replace("error_code_ref <- 42u64;", "missing_ref <- 42u64;");
+    std::fs::write(&path, source).unwrap();
+    let error = compile_file_to_assembly(&path.to_string_lossy()).unwrap_err();
+    assert_eq!(error.to_string(), "Unbound variable: missing_ref at 8:5");
+}
+
+#[test]
+fn test_allow_integration() {
+    let status = compile_and_execute("../../testcases/felis/single/allow.fe").unwrap();
+    assert_eq!(status.code(), Some(42), "Program should exit with code 42");
+}
+
 #[test]
 fn test_print_c_integration() {
     let result = compile_and_execute_with_output("../../testcases/felis/single/print_c.fe");
@@ -1039,3 +1350,43 @@ fn test_ptx_4() {
         }
     }
 }
+
+#[test]
+fn test_environment_file_is_written_and_loaded_again() {
+    let temp_dir = TempDir::new().unwrap();
+    let environment_file = temp_dir.path().join("contracts.fe.env");
+    let compile = || {
+        let compile_options = CompileOptions {
+            use_ptx: false,
+            contracts: ContractMode::Runtime,
+            environment_file: Some(environment_file.to_string_lossy().to_string()),
+        };
+        compile_file_to_assembly_with_options(
+            "../../testcases/felis/single/contracts.fe",
+            compile_options,
+        )
+        .unwrap()
+    };
+    let expected = compile_with_runtime_contracts("../../testcases/felis/single/contracts.fe");
+
+    assert_eq!(compile(), expected);
+    let items = || {
+        let written = std::fs::read_to_string(&environment_file).unwrap();
+        assert!(written.starts_with("neco-cic-environment"));
+        written
+            .lines()
+            .filter(|line| line.starts_with("item "))
+            .count()
+    };
+    let written = items();
+    assert!(written > 0);
+
+    // The unchanged items are loaded from the file, and written back
+    assert_eq!(compile(), expected);
+    assert_eq!(items(), written);
+
+    // A file that cannot be read is checked again and replaced
+    std::fs::write(&environment_file, "garbage").unwrap();
+    assert_eq!(compile(), expected);
+    assert_eq!(items(), written);
+}
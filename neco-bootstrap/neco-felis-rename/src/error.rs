@@ -0,0 +1,48 @@
+use neco_felis_syn::Pos;
+
+/// What went wrong while resolving the names of a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RenameErrorKind {
+    /// A name used in a procedure that nothing binds
+    UnboundVariable(String),
+    /// A name bound twice where it must be unique: an item, a parameter of a
+    /// procedure or a field
+    DuplicateName(String),
+    /// A field that the struct or array of the object does not declare
+    UnknownField { field: String, ty: String },
+    /// An `#allow` of a lint that does not exist
+    UnknownLint(String),
+}
+
+impl std::fmt::Display for RenameErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RenameErrorKind::UnboundVariable(name) => write!(f, "Unbound variable: {name}"),
+            RenameErrorKind::DuplicateName(name) => write!(f, "Duplicate name: {name}"),
+            RenameErrorKind::UnknownLint(name) => write!(f, "Unknown lint: {name}"),
+            RenameErrorKind::UnknownField { field, ty } => {
+                write!(f, "No field {field} in `{ty}`")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenameError {
+    pub kind: RenameErrorKind,
+    pub pos: Pos,
+}
+
+impl RenameError {
+    pub fn new(kind: RenameErrorKind, pos: Pos) -> Self {
+        Self { kind, pos }
+    }
+}
+
+impl std::fmt::Display for RenameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at {}", self.kind, self.pos)
+    }
+}
+
+impl std::error::Error for RenameError {}
@@ -1,22 +1,63 @@
+use std::collections::HashMap;
+
 use neco_felis_syn::{
-    File, Item, ItemDefinition, ItemInductive, ItemInductiveBranch, ItemTheorem, Pattern,
-    PhaseParse, Term, TermApply, TermArrowDep, TermArrowNodep, TermMatch, TermMatchBranch,
-    TermNumber, TermParen, TermUnit, TermVariable,
+    DefinitionDecreasingBy, File, Item, ItemAllow, ItemAxiom, ItemCheck, ItemDefinition,
+    ItemEntrypoint, ItemEval, ItemInductive, ItemInductiveBranch, ItemMutual, ItemPrint,
+    ItemPrintAxioms, ItemStructField, ItemStructure, ItemTheorem, ItemUseBuiltin, Pattern,
+    PhaseParse, Pos, Term, TermApply, TermArrowDep, TermArrowImplicit, TermArrowNodep, TermBy,
+    TermByTactic, TermFieldAccess, TermHole, TermMatch, TermMatchBranch, TermNumber, TermParen,
+    TermUnit, TermVariable, token::TokenVariable,
 };
 use neco_scope::ScopeStack;
 
 use crate::phase_renamed::PhaseRenamed;
 
+mod error;
+mod lint;
 pub mod phase_renamed;
+mod procedure;
+
+pub use error::{RenameError, RenameErrorKind};
+pub use lint::{Lint, Warning};
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 // (file_id, variable_id_in_the_file)
 pub struct VariableId(pub usize, pub usize);
 
+/// A name in the source that declares or refers to a binding
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Occurrence {
+    pub id: VariableId,
+    pub name: String,
+    pub pos: Pos,
+    pub declaration: bool,
+}
+
+/// A renamed file, with the warnings of its lints and the names of its
+/// declarations and references
+#[derive(Debug, Clone)]
+pub struct RenamedFile {
+    pub file: File<PhaseRenamed>,
+    pub warnings: Vec<Warning>,
+    pub occurrences: Vec<Occurrence>,
+}
+
 struct RenameContext {
     file_id: usize,
     next_variable_id: usize,
-    scope: ScopeStack<String, VariableId>,
+    scope: ScopeStack<String, VariableId, Pos>,
+    /// The name and the field names of each `#struct` and `#array`
+    fields: HashMap<VariableId, (String, Vec<String>)>,
+    /// The `#struct` or `#array` type of the locals whose type is known
+    local_types: HashMap<VariableId, VariableId>,
+    /// The lints allowed for each item of the file
+    allows: Vec<Vec<Lint>>,
+    /// The index of the item being renamed
+    current_item: usize,
+    /// The items checked by `unused_items`, with their index
+    lint_items: HashMap<VariableId, usize>,
+    warnings: Vec<Warning>,
+    occurrences: Vec<Occurrence>,
 }
 
 impl RenameContext {
@@ -24,7 +65,14 @@ impl RenameContext {
         Self {
             file_id,
             next_variable_id: 0,
-            scope: ScopeStack::new(),
+            scope: ScopeStack::default(),
+            fields: HashMap::new(),
+            local_types: HashMap::new(),
+            allows: Vec::new(),
+            current_item: 0,
+            lint_items: HashMap::new(),
+            warnings: Vec::new(),
+            occurrences: Vec::new(),
         }
     }
 
@@ -40,10 +88,48 @@ impl RenameContext {
         id
     }
 
+    /// Binds a name declared in the source, whose uses are counted
+    fn declare_variable(&mut self, name: &TokenVariable) -> VariableId {
+        let id = self.generate_variable_id();
+        self.scope
+            .declare(name.s().to_string(), id.clone(), name.pos().clone());
+        self.record(name, &id, true);
+        id
+    }
+
+    fn record(&mut self, name: &TokenVariable, id: &VariableId, declaration: bool) {
+        self.occurrences.push(Occurrence {
+            id: id.clone(),
+            name: name.s().to_string(),
+            pos: name.pos().clone(),
+            declaration,
+        });
+    }
+
     fn lookup_variable(&self, name: &str) -> Option<VariableId> {
         self.scope.get(&name.to_string()).cloned()
     }
 
+    /// Looks a name up for a use of it
+    fn use_variable(&mut self, name: &TokenVariable) -> Option<VariableId> {
+        let id = self.scope.use_key(&name.s().to_string()).cloned()?;
+        self.record(name, &id, false);
+        Some(id)
+    }
+
+    /// A name that must be bound
+    fn resolve(&mut self, name: &TokenVariable) -> Result<VariableId, RenameError> {
+        self.use_variable(name)
+            .ok_or_else(|| unbound_variable(name))
+    }
+
+    /// The id of an item bound by `bind_item_names`. Defining an item is not
+    /// a use of it.
+    fn item_id(&self, name: &TokenVariable) -> Result<VariableId, RenameError> {
+        self.lookup_variable(name.s())
+            .ok_or_else(|| unbound_variable(name))
+    }
+
     fn enter_scope(&mut self) {
         self.scope.enter_scope();
     }
@@ -51,21 +137,140 @@ impl RenameContext {
     fn leave_scope(&mut self) {
         self.scope.leave_scope();
     }
+
+    /// Leaves the scope of a procedure or a block, warning of its unused
+    /// locals. Names starting with `_` may be unused.
+    fn leave_local_scope(&mut self) {
+        let scope = self.scope.leave_scope();
+        for (name, _, declaration) in scope.bindings() {
+            if let Some(pos) = &declaration.declared_at
+                && declaration.uses == 0
+                && !name.starts_with('_')
+            {
+                self.warn(
+                    Lint::UnusedVariables,
+                    format!("Unused variable: {name}"),
+                    pos.clone(),
+                    self.current_item,
+                );
+            }
+        }
+    }
+
+    fn warn(&mut self, lint: Lint, message: String, pos: Pos, item: usize) {
+        if !self.allows[item].contains(&lint) {
+            self.warnings.push(Warning { lint, message, pos });
+        }
+    }
 }
 
-fn rename_item(context: &mut RenameContext, item: &Item<PhaseParse>) -> Item<PhaseRenamed> {
+fn unbound_variable(name: &TokenVariable) -> RenameError {
+    RenameError::new(
+        RenameErrorKind::UnboundVariable(name.s().to_string()),
+        name.pos().clone(),
+    )
+}
+
+/// Binds the name of an item, which must be unique in the file
+fn bind_item_name(
+    context: &mut RenameContext,
+    bound: &mut HashMap<String, Option<String>>,
+    name: &TokenVariable,
+) -> Result<VariableId, RenameError> {
+    if bound.insert(name.s().to_string(), None).is_some() {
+        return Err(RenameError::new(
+            RenameErrorKind::DuplicateName(name.s().to_string()),
+            name.pos().clone(),
+        ));
+    }
+    Ok(context.declare_variable(name))
+}
+
+/// Binds the names of the items of a file, and the constructors of its
+/// inductive types, before any item is renamed, so that items may refer to
+/// later ones
+fn bind_item_names(
+    context: &mut RenameContext,
+    item: &Item<PhaseParse>,
+    bound: &mut HashMap<String, Option<String>>,
+) -> Result<(), RenameError> {
+    let item_index = context.current_item;
+    let bind = |context: &mut RenameContext, bound: &mut HashMap<_, _>, name| {
+        bind_item_name(context, bound, name).map(drop)
+    };
+    let bind_checked = |context: &mut RenameContext, bound: &mut HashMap<_, _>, name| {
+        let id = bind_item_name(context, bound, name)?;
+        context.lint_items.insert(id, item_index);
+        Ok(())
+    };
     match item {
+        Item::Definition(definition) => bind(context, bound, &definition.name),
+        Item::Inductive(inductive) => {
+            bind(context, bound, &inductive.name)?;
+            for branch in inductive.branches() {
+                bind(context, bound, &branch.name)?;
+            }
+            Ok(())
+        }
+        Item::Theorem(theorem) => bind(context, bound, &theorem.name),
+        Item::Axiom(axiom) => bind(context, bound, &axiom.name),
+        Item::Structure(structure) => bind(context, bound, &structure.name),
+        Item::UseBuiltin(use_builtin) => {
+            // The same import again is only a warning
+            let name = use_builtin.name.s();
+            let builtin = use_builtin.builtin_name.s();
+            if let Some(Some(imported)) = bound.get(name)
+                && imported == builtin
+            {
+                context.warn(
+                    Lint::DuplicateBuiltins,
+                    format!("Duplicate import of builtin {builtin} as {name}"),
+                    use_builtin.name.pos().clone(),
+                    item_index,
+                );
+                return Ok(());
+            }
+            bind_checked(context, bound, &use_builtin.name)?;
+            bound.insert(name.to_string(), Some(builtin.to_string()));
+            Ok(())
+        }
+        Item::Proc(proc) => bind_checked(context, bound, &proc.name),
+        Item::Array(array) => bind_checked(context, bound, &array.name),
+        Item::Struct(item_struct) => bind_checked(context, bound, &item_struct.name),
+        Item::Mutual(mutual) => {
+            for item in mutual.items() {
+                bind_item_names(context, item, bound)?;
+            }
+            Ok(())
+        }
+        Item::PrintAxioms(_)
+        | Item::Check(_)
+        | Item::Eval(_)
+        | Item::Print(_)
+        | Item::Entrypoint(_)
+        | Item::Allow(_) => Ok(()),
+    }
+}
+
+fn rename_item(
+    context: &mut RenameContext,
+    item: &Item<PhaseParse>,
+) -> Result<Item<PhaseRenamed>, RenameError> {
+    let item = match item {
         Item::Definition(definition) => {
             context.enter_scope();
 
-            // Bind the definition name in the current scope
-            let _def_id = context.bind_variable(definition.name().s());
-
             let renamed_definition = ItemDefinition {
                 keyword_definition: definition.keyword_definition.clone(),
                 name: definition.name.clone(),
                 colon: definition.colon.clone(),
                 type_: Box::new(rename_term(context, definition.type_())),
+                decreasing_by: definition.decreasing_by().map(|decreasing_by| {
+                    DefinitionDecreasingBy {
+                        keyword_decreasing_by: decreasing_by.keyword_decreasing_by.clone(),
+                        proof: Box::new(rename_term(context, decreasing_by.proof())),
+                    }
+                }),
                 brace_l: definition.brace_l.clone(),
                 body: Box::new(rename_term(context, definition.body())),
                 brace_r: definition.brace_r.clone(),
@@ -78,9 +283,6 @@ fn rename_item(context: &mut RenameContext, item: &Item<PhaseParse>) -> Item<Pha
         Item::Inductive(inductive) => {
             context.enter_scope();
 
-            // Bind the inductive type name
-            let _ind_id = context.bind_variable(inductive.name().s());
-
             let renamed_inductive = ItemInductive {
                 keyword_inductive: inductive.keyword_inductive.clone(),
                 name: inductive.name.clone(),
@@ -102,9 +304,6 @@ fn rename_item(context: &mut RenameContext, item: &Item<PhaseParse>) -> Item<Pha
         Item::Theorem(theorem) => {
             context.enter_scope();
 
-            // Bind the theorem name
-            let _thm_id = context.bind_variable(theorem.name().s());
-
             let renamed_theorem = ItemTheorem {
                 keyword_theorem: theorem.keyword_theorem.clone(),
                 name: theorem.name.clone(),
@@ -119,27 +318,115 @@ fn rename_item(context: &mut RenameContext, item: &Item<PhaseParse>) -> Item<Pha
             context.leave_scope();
             Item::Theorem(renamed_theorem)
         }
-        Item::Entrypoint(_) => {
-            // For now, just return the item unchanged since we don't handle entrypoints in renaming
-            unreachable!("Entrypoint items are not yet supported in renaming")
-        }
-        Item::UseBuiltin(_) => {
-            // For now, just return the item unchanged since we don't handle builtins in renaming
-            unreachable!("UseBuiltin items are not yet supported in renaming")
-        }
-        Item::Proc(_) => {
-            // For now, just return the item unchanged since we don't handle builtins in renaming
-            unreachable!("Proc items are not yet supported in renaming")
+        Item::Axiom(axiom) => {
+            context.enter_scope();
+
+            let renamed_axiom = ItemAxiom {
+                keyword_axiom: axiom.keyword_axiom.clone(),
+                name: axiom.name.clone(),
+                colon: axiom.colon.clone(),
+                type_: Box::new(rename_term(context, axiom.type_())),
+                semicolon: axiom.semicolon.clone(),
+                ext: (),
+            };
+
+            context.leave_scope();
+            Item::Axiom(renamed_axiom)
         }
-        Item::Array(_) => {
-            // For now, just return the item unchanged since we don't handle arrays in renaming
-            unreachable!("Array items are not yet supported in renaming")
+        Item::PrintAxioms(print_axioms) => Item::PrintAxioms(ItemPrintAxioms {
+            keyword_print_axioms: print_axioms.keyword_print_axioms.clone(),
+            name: print_axioms.name.clone(),
+            ext: (),
+        }),
+        Item::Check(check) => Item::Check(ItemCheck {
+            keyword_check: check.keyword_check.clone(),
+            term: Box::new(rename_term(context, check.term())),
+            semicolon: check.semicolon.clone(),
+            ext: (),
+        }),
+        Item::Eval(eval) => Item::Eval(ItemEval {
+            keyword_eval: eval.keyword_eval.clone(),
+            term: Box::new(rename_term(context, eval.term())),
+            semicolon: eval.semicolon.clone(),
+            ext: (),
+        }),
+        Item::Print(print) => Item::Print(ItemPrint {
+            keyword_print: print.keyword_print.clone(),
+            name: print.name.clone(),
+            ext: (),
+        }),
+        Item::Mutual(mutual) => Item::Mutual(ItemMutual {
+            keyword_mutual: mutual.keyword_mutual.clone(),
+            brace_l: mutual.brace_l.clone(),
+            items: mutual
+                .items()
+                .iter()
+                .map(|item| rename_item(context, item))
+                .collect::<Result<_, _>>()?,
+            brace_r: mutual.brace_r.clone(),
+            ext: (),
+        }),
+        Item::Structure(structure) => {
+            context.enter_scope();
+
+            let ty = Box::new(rename_term(context, structure.ty()));
+
+            // Each field is in scope in the types of the next ones
+            let fields = structure
+                .fields()
+                .iter()
+                .map(|field| {
+                    let renamed_field = ItemStructField {
+                        name: field.name.clone(),
+                        colon: field.colon.clone(),
+                        ty: Box::new(rename_term(context, &field.ty)),
+                        comma: field.comma.clone(),
+                    };
+                    context.bind_variable(field.name.s());
+                    renamed_field
+                })
+                .collect();
+
+            context.leave_scope();
+            Item::Structure(ItemStructure {
+                keyword_structure: structure.keyword_structure.clone(),
+                name: structure.name.clone(),
+                colon: structure.colon.clone(),
+                ty,
+                brace_l: structure.brace_l.clone(),
+                fields,
+                brace_r: structure.brace_r.clone(),
+                ext: (),
+            })
         }
-        Item::Struct(_) => {
-            // For now, just return the item unchanged since we don't handle structs in renaming
-            unreachable!("Struct items are not yet supported in renaming")
+        Item::Entrypoint(entrypoint) => Item::Entrypoint(ItemEntrypoint {
+            keyword_entrypoint: entrypoint.keyword_entrypoint.clone(),
+            name: entrypoint.name.clone(),
+            semicolon: entrypoint.semicolon.clone(),
+            ext: context.resolve(&entrypoint.name)?,
+        }),
+        Item::UseBuiltin(use_builtin) => Item::UseBuiltin(ItemUseBuiltin {
+            keyword_use_builtin: use_builtin.keyword_use_builtin.clone(),
+            builtin_name: use_builtin.builtin_name.clone(),
+            keyword_as: use_builtin.keyword_as.clone(),
+            name: use_builtin.name.clone(),
+            semicolon: use_builtin.semicolon.clone(),
+            ext: context.item_id(&use_builtin.name)?,
+        }),
+        Item::Allow(allow) => Item::Allow(ItemAllow {
+            keyword_allow: allow.keyword_allow.clone(),
+            lint: allow.lint.clone(),
+            semicolon: allow.semicolon.clone(),
+            ext: (),
+        }),
+        Item::Proc(proc) => Item::Proc(Box::new(procedure::rename_proc(context, proc)?)),
+        Item::Array(array) => Item::Array(procedure::rename_array(context, array)?),
+        Item::Struct(item_struct) => {
+            let id = context.item_id(&item_struct.name)?;
+            Item::Struct(procedure::rename_struct(context, item_struct, id)?)
         }
-    }
+    };
+    Ok(item)
 }
 
 fn rename_inductive_branch(
@@ -148,9 +435,6 @@ fn rename_inductive_branch(
 ) -> ItemInductiveBranch<PhaseRenamed> {
     context.enter_scope();
 
-    // Bind the constructor name
-    let _ctor_id = context.bind_variable(branch.name().s());
-
     let renamed_branch = ItemInductiveBranch {
         name: branch.name.clone(),
         colon: branch.colon.clone(),
@@ -166,8 +450,7 @@ fn rename_inductive_branch(
 fn rename_term(context: &mut RenameContext, term: &Term<PhaseParse>) -> Term<PhaseRenamed> {
     match term {
         Term::Variable(var) => {
-            let variable_name = var.variable().s();
-            let variable_id = context.lookup_variable(variable_name).unwrap_or_else(|| {
+            let variable_id = context.use_variable(var.variable()).unwrap_or_else(|| {
                 // If variable not found, create a new ID (this might be an error case)
                 context.generate_variable_id()
             });
@@ -211,6 +494,31 @@ fn rename_term(context: &mut RenameContext, term: &Term<PhaseParse>) -> Term<Pha
             context.leave_scope();
             Term::ArrowDep(renamed_arrow)
         }
+        Term::ArrowImplicit(arrow) => {
+            context.enter_scope();
+
+            // Bind the parameter name
+            context.bind_variable(arrow.from().variable().s());
+
+            let renamed_arrow = TermArrowImplicit {
+                brace_l: arrow.brace_l.clone(),
+                from: TermVariable {
+                    variable: arrow.from.variable.clone(),
+                    ext: context
+                        .lookup_variable(arrow.from().variable().s())
+                        .unwrap(),
+                },
+                colon: arrow.colon.clone(),
+                from_ty: Box::new(rename_term(context, arrow.from_ty())),
+                brace_r: arrow.brace_r.clone(),
+                arrow: arrow.arrow.clone(),
+                to: Box::new(rename_term(context, arrow.to())),
+                ext: (),
+            };
+
+            context.leave_scope();
+            Term::ArrowImplicit(renamed_arrow)
+        }
         Term::ArrowNodep(arrow) => Term::ArrowNodep(TermArrowNodep {
             from: Box::new(rename_term(context, arrow.from())),
             arrow: arrow.arrow.clone(),
@@ -247,9 +555,47 @@ fn rename_term(context: &mut RenameContext, term: &Term<PhaseParse>) -> Term<Pha
             number: number.number.clone(),
             ext: (),
         }),
-        Term::Struct(_) => {
-            // For now, struct expressions are not supported in renaming
-            unreachable!("Struct terms are not yet supported in renaming")
+        Term::By(by) => {
+            context.enter_scope();
+
+            // Names introduced by `intro` are in scope in the later tactics
+            let tactics = by
+                .tactics()
+                .iter()
+                .map(|tactic| {
+                    if tactic.name().s() == "intro" {
+                        bind_intro_names(context, tactic.arg());
+                    }
+                    TermByTactic {
+                        name: tactic.name.clone(),
+                        arg: tactic.arg().map(|arg| Box::new(rename_term(context, arg))),
+                        semicolon: tactic.semicolon.clone(),
+                        ext: (),
+                    }
+                })
+                .collect();
+
+            context.leave_scope();
+            Term::By(TermBy {
+                keyword_by: by.keyword_by.clone(),
+                brace_l: by.brace_l.clone(),
+                tactics,
+                brace_r: by.brace_r.clone(),
+                ext: (),
+            })
+        }
+        Term::Hole(hole) => Term::Hole(TermHole {
+            hole: hole.hole.clone(),
+            ext: (),
+        }),
+        Term::FieldAccess(field_access) => Term::FieldAccess(TermFieldAccess {
+            object: Box::new(rename_term(context, field_access.object())),
+            dot: field_access.dot.clone(),
+            field: field_access.field.clone(),
+            ext: (),
+        }),
+        Term::Struct(term_struct) => {
+            Term::Struct(procedure::rename_term_struct(context, term_struct))
         }
     }
 }
@@ -287,16 +633,100 @@ fn rename_pattern_bindings(context: &mut RenameContext, pattern: &Pattern) {
     }
 }
 
-pub fn rename_file(file: File<PhaseParse>) -> File<PhaseRenamed> {
+/// Binds the names of `intro x y`, whose argument parses as an application
+fn bind_intro_names(context: &mut RenameContext, arg: Option<&Term<PhaseParse>>) {
+    match arg {
+        Some(Term::Variable(var)) => {
+            context.bind_variable(var.variable().s());
+        }
+        Some(Term::Apply(apply)) => {
+            bind_intro_names(context, Some(apply.f()));
+            for arg in apply.args() {
+                bind_intro_names(context, Some(arg));
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Resolves the names of a file. A name in a procedure must be bound, and
+/// the names of items, of the parameters of a procedure and of fields must
+/// be unique.
+pub fn rename_file(file: &File<PhaseParse>) -> Result<File<PhaseRenamed>, RenameError> {
+    rename_file_with_warnings(file).map(|(file, _)| file)
+}
+
+/// Resolves the names of a file as `rename_file`, with the warnings of the
+/// lints not allowed, in the order of the source
+pub fn rename_file_with_warnings(
+    file: &File<PhaseParse>,
+) -> Result<(File<PhaseRenamed>, Vec<Warning>), RenameError> {
+    rename_file_indexed(file).map(|renamed| (renamed.file, renamed.warnings))
+}
+
+/// Resolves the names of a file as `rename_file_with_warnings`, with the
+/// occurrences of the names bound in the source, in the order of the source
+pub fn rename_file_indexed(file: &File<PhaseParse>) -> Result<RenamedFile, RenameError> {
     let mut context = RenameContext::new(0); // TODO: Get file_id from somewhere
+    context.allows = allowed_lints(file)?;
 
-    let items = file
-        .items()
-        .iter()
-        .map(|item| rename_item(&mut context, item))
-        .collect();
+    // The scope of the items of the file
+    context.enter_scope();
+    let mut bound = HashMap::new();
+    for (index, item) in file.items().iter().enumerate() {
+        context.current_item = index;
+        bind_item_names(&mut context, item, &mut bound)?;
+    }
+    let mut items = Vec::new();
+    for (index, item) in file.items().iter().enumerate() {
+        context.current_item = index;
+        items.push(rename_item(&mut context, item)?);
+    }
 
-    File { items, ext: () }
+    let scope = context.scope.leave_scope();
+    for (name, id, declaration) in scope.bindings() {
+        if let (Some(&item), Some(pos)) = (context.lint_items.get(id), &declaration.declared_at)
+            && declaration.uses == 0
+        {
+            context.warn(
+                Lint::UnusedItems,
+                format!("Unused item: {name}"),
+                pos.clone(),
+                item,
+            );
+        }
+    }
+
+    let mut warnings = context.warnings;
+    warnings.sort_by(|a, b| a.pos.cmp(&b.pos));
+    let mut occurrences = context.occurrences;
+    occurrences.sort_by(|a, b| a.pos.cmp(&b.pos));
+    Ok(RenamedFile {
+        file: File { items, ext: () },
+        warnings,
+        occurrences,
+    })
+}
+
+/// The lints allowed for each item by the `#allow`s right before it
+fn allowed_lints(file: &File<PhaseParse>) -> Result<Vec<Vec<Lint>>, RenameError> {
+    let mut allows = Vec::new();
+    let mut pending = Vec::new();
+    for item in file.items() {
+        if let Item::Allow(allow) = item {
+            let lint = Lint::from_name(allow.lint().s()).ok_or_else(|| {
+                RenameError::new(
+                    RenameErrorKind::UnknownLint(allow.lint().s().to_string()),
+                    allow.lint().pos().clone(),
+                )
+            })?;
+            pending.push(lint);
+            allows.push(Vec::new());
+        } else {
+            allows.push(std::mem::take(&mut pending));
+        }
+    }
+    Ok(allows)
 }
 
 #[cfg(test)]
@@ -317,7 +747,7 @@ mod tests {
         let mut i = 0;
         let parsed_file = File::parse(&tokens, &mut i).unwrap().unwrap();
 
-        let renamed_file = rename_file(parsed_file);
+        let renamed_file = rename_file(&parsed_file).unwrap();
 
         // Verify that we have one item
         assert_eq!(renamed_file.items.len(), 1);
@@ -354,7 +784,7 @@ mod tests {
         let mut i = 0;
         let parsed_file = File::parse(&tokens, &mut i).unwrap().unwrap();
 
-        let renamed_file = rename_file(parsed_file);
+        let renamed_file = rename_file(&parsed_file).unwrap();
 
         // Just verify that renaming doesn't crash and produces output
         assert_eq!(renamed_file.items.len(), 1);
@@ -373,9 +803,58 @@ mod tests {
         let mut i = 0;
         let parsed_file = File::parse(&tokens, &mut i).unwrap().unwrap();
 
-        let renamed_file = rename_file(parsed_file);
+        let renamed_file = rename_file(&parsed_file).unwrap();
 
         // Just verify that renaming doesn't crash and produces output
         assert_eq!(renamed_file.items.len(), 1);
     }
+
+    #[test]
+    fn test_rename_occurrences() {
+        let mut file_id_generator = FileIdGenerator::new();
+        let file_id = file_id_generator.generate_file_id();
+
+        let source = "#use_builtin \"u64\" #as __u64;
+#proc f : (x : __u64) -> __u64 {
+    #let y = x;
+    y
+}
+#proc g : (y : __u64) -> __u64 {
+    f y
+}
+";
+        let tokens = Token::lex(source, file_id);
+        let mut i = 0;
+        let parsed_file = File::parse(&tokens, &mut i).unwrap().unwrap();
+        let renamed = rename_file_indexed(&parsed_file).unwrap();
+
+        // The names with the same id as the occurrence at `line:column`
+        let same = |line: usize, column: usize| {
+            let occurrence = renamed
+                .occurrences
+                .iter()
+                .find(|o| o.pos.line() == line && o.pos.column() == column)
+                .unwrap();
+            renamed
+                .occurrences
+                .iter()
+                .filter(|o| o.id == occurrence.id)
+                .map(|o| (o.pos.to_string(), o.declaration))
+                .collect::<Vec<_>>()
+        };
+        let at = |pos: &str, declaration| (pos.to_string(), declaration);
+        assert_eq!(same(2, 7), vec![at("2:7", true), at("7:5", false)]);
+        assert_eq!(same(4, 5), vec![at("3:10", true), at("4:5", false)]);
+        assert_eq!(same(7, 7), vec![at("6:12", true), at("7:7", false)]);
+        assert_eq!(
+            same(1, 24),
+            vec![
+                at("1:24", true),
+                at("2:16", false),
+                at("2:26", false),
+                at("6:16", false),
+                at("6:26", false),
+            ]
+        );
+    }
 }
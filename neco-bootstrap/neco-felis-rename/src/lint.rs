@@ -0,0 +1,251 @@
+use neco_felis_syn::Pos;
+
+/// The lints of the renamer. `#allow name;` before an item silences one for
+/// the item.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Lint {
+    /// A parameter or a local of a procedure that is never used
+    UnusedVariables,
+    /// A procedure, an array, a struct or a builtin that is never used
+    UnusedItems,
+    /// A local of a procedure that shadows a binding of an enclosing scope
+    ShadowedNames,
+    /// A `#use_builtin` repeated with the same builtin and name
+    DuplicateBuiltins,
+}
+
+impl Lint {
+    pub const ALL: [Lint; 4] = [
+        Lint::UnusedVariables,
+        Lint::UnusedItems,
+        Lint::ShadowedNames,
+        Lint::DuplicateBuiltins,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Lint::UnusedVariables => "unused_variables",
+            Lint::UnusedItems => "unused_items",
+            Lint::ShadowedNames => "shadowed_names",
+            Lint::DuplicateBuiltins => "duplicate_builtins",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Lint> {
+        Lint::ALL.into_iter().find(|lint| lint.name() == name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    pub lint: Lint,
+    pub message: String,
+    pub pos: Pos,
+}
+
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at {} ({})", self.message, self.pos, self.lint.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use neco_felis_syn::{File, FileIdGenerator, Parse, token::Token};
+
+    use crate::{Lint, RenameErrorKind, rename_file_with_warnings};
+
+    fn parse(source: &str) -> File<neco_felis_syn::PhaseParse> {
+        let mut file_id_generator = FileIdGenerator::new();
+        let file_id = file_id_generator.generate_file_id();
+        let tokens = Token::lex(source, file_id);
+        let mut i = 0;
+        File::parse(&tokens, &mut i).unwrap().unwrap()
+    }
+
+    fn warnings(source: &str) -> Vec<(Lint, String)> {
+        let (_, warnings) = rename_file_with_warnings(&parse(source)).unwrap();
+        warnings
+            .into_iter()
+            .map(|warning| (warning.lint, warning.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_lint_names() {
+        for lint in Lint::ALL {
+            assert_eq!(Lint::from_name(lint.name()), Some(lint));
+        }
+        assert_eq!(Lint::from_name("unused"), None);
+    }
+
+    #[test]
+    fn test_used_bindings_have_no_warnings() {
+        let source = "
+#entrypoint main;
+#use_builtin \"syscall\" #as __syscall;
+#proc main : () -> () {
+    #let code = 0;
+    #let #mut _unused @ code_ref = code;
+    code_ref <- 42;
+    __syscall 60 code 0 0 0 0;
+}
+";
+        assert_eq!(warnings(source), vec![]);
+    }
+
+    #[test]
+    fn test_unused_variables() {
+        let source = "
+#entrypoint main;
+#use_builtin \"syscall\" #as __syscall;
+#proc main : (x : ()) -> () {
+    #let code = 0;
+    #loop {
+        #let inner = 1;
+        #break;
+    };
+    __syscall 60 0 0 0 0 0;
+}
+";
+        assert_eq!(
+            warnings(source),
+            vec![
+                (
+                    Lint::UnusedVariables,
+                    "Unused variable: x at 4:15 (unused_variables)".to_string()
+                ),
+                (
+                    Lint::UnusedVariables,
+                    "Unused variable: code at 5:10 (unused_variables)".to_string()
+                ),
+                (
+                    Lint::UnusedVariables,
+                    "Unused variable: inner at 7:14 (unused_variables)".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unused_items() {
+        let source = "
+#entrypoint main;
+#use_builtin \"syscall\" #as __syscall;
+#use_builtin \"u64\" #as __u64;
+#proc helper : () -> () {
+    __syscall 60 0 0 0 0 0;
+}
+#proc main : () -> () {
+    __syscall 60 0 0 0 0 0;
+}
+";
+        assert_eq!(
+            warnings(source),
+            vec![
+                (
+                    Lint::UnusedItems,
+                    "Unused item: __u64 at 4:24 (unused_items)".to_string()
+                ),
+                (
+                    Lint::UnusedItems,
+                    "Unused item: helper at 5:7 (unused_items)".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_shadowed_names() {
+        let source = "
+#entrypoint main;
+#use_builtin \"syscall\" #as __syscall;
+#proc main : (code : ()) -> () {
+    #loop {
+        #let code = 1;
+        __syscall 60 code 0 0 0 0;
+        #break;
+    };
+    __syscall 60 code 0 0 0 0;
+}
+";
+        assert_eq!(
+            warnings(source),
+            vec![(
+                Lint::ShadowedNames,
+                "code shadows the binding at 4:15 at 6:14 (shadowed_names)".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_builtins() {
+        let source = "
+#entrypoint main;
+#use_builtin \"syscall\" #as __syscall;
+#use_builtin \"syscall\" #as __syscall;
+#proc main : () -> () {
+    __syscall 60 0 0 0 0 0;
+}
+";
+        assert_eq!(
+            warnings(source),
+            vec![(
+                Lint::DuplicateBuiltins,
+                "Duplicate import of builtin syscall as __syscall at 4:28 (duplicate_builtins)"
+                    .to_string()
+            )]
+        );
+
+        // Another builtin under the same name is still an error
+        let source = "
+#use_builtin \"syscall\" #as __syscall;
+#use_builtin \"u64\" #as __syscall;
+";
+        let error = rename_file_with_warnings(&parse(source)).unwrap_err();
+        assert_eq!(
+            error.kind,
+            RenameErrorKind::DuplicateName("__syscall".to_string())
+        );
+    }
+
+    #[test]
+    fn test_allow() {
+        let source = "
+#entrypoint main;
+#use_builtin \"syscall\" #as __syscall;
+#allow unused_items;
+#proc helper : () -> () {
+    #let unused = 0;
+    __syscall 60 0 0 0 0 0;
+}
+#allow unused_variables;
+#allow shadowed_names;
+#proc main : (code : ()) -> () {
+    #let code = 0;
+    __syscall 60 0 0 0 0 0;
+}
+";
+        assert_eq!(
+            warnings(source),
+            vec![(
+                Lint::UnusedVariables,
+                "Unused variable: unused at 6:10 (unused_variables)".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_unknown_lint() {
+        let source = "
+#allow unused_things;
+#use_builtin \"syscall\" #as __syscall;
+";
+        let error = rename_file_with_warnings(&parse(source)).unwrap_err();
+        assert_eq!(
+            error.kind,
+            RenameErrorKind::UnknownLint("unused_things".to_string())
+        );
+        assert_eq!(error.to_string(), "Unknown lint: unused_things at 2:8");
+    }
+}
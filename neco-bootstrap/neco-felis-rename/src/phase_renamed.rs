@@ -11,11 +11,23 @@ impl Phase for PhaseRenamed {
     type ItemInductiveExt = ();
     type ItemInductiveBranchExt = ();
     type ItemTheoremExt = ();
-    type ItemEntrypointExt = ();
-    type ItemBuiltinExt = ();
+    type ItemAxiomExt = ();
+    type ItemPrintAxiomsExt = ();
+    type ItemCheckExt = ();
+    type ItemEvalExt = ();
+    type ItemPrintExt = ();
+    type ItemMutualExt = ();
+    /// The procedure run by the entrypoint
+    type ItemEntrypointExt = VariableId;
+    type ItemAllowExt = ();
+    type ItemBuiltinExt = VariableId;
     type TermApplyExt = ();
     type TermArrowDepExt = ();
+    type TermArrowImplicitExt = ();
     type TermArrowNodepExt = ();
+    type TermByExt = ();
+    type TermByTacticExt = ();
+    type TermHoleExt = ();
     type TermMatchExt = ();
     type TermMatchBranchExt = ();
     type TermParenExt = ();
@@ -24,32 +36,37 @@ impl Phase for PhaseRenamed {
     type StatementsExt = ();
     type StatementsThenExt = ();
     type ItemProcBlockExt = ();
-    type ItemProcExt = ();
+    type ItemProcExt = VariableId;
     type TermUnitExt = ();
     type TermNumberExt = ();
-    type ItemArrayExt = ();
-    type ItemStructExt = ();
+    type ItemArrayExt = VariableId;
+    type ItemStructExt = VariableId;
+    type ItemStructureExt = ();
     type TermStructExt = ();
     type TermFieldAccessExt = ();
     type TermConstructorCallExt = ();
     type StatementExt = ();
     type StatementLoopExt = ();
     type StatementBreakExt = ();
-    type StatementAssignExt = ();
+    /// The reference assigned through
+    type StatementAssignExt = VariableId;
     type StatementFieldAssignExt = ();
-    type StatementLetExt = ();
-    type StatementLetMutExt = ();
+    type StatementLetExt = VariableId;
+    /// The value and the reference
+    type StatementLetMutExt = (VariableId, VariableId);
     type ProcTermExt = ();
     type ProcTermApplyExt = ();
     type ProcTermVariableExt = VariableId;
     type ProcTermParenExt = ();
     type ProcTermUnitExt = ();
     type ProcTermNumberExt = ();
-    type ProcTermFieldAccessExt = ();
-    type ProcTermConstructorCallExt = ();
+    /// The object whose field is accessed
+    type ProcTermFieldAccessExt = VariableId;
+    /// The array type constructed
+    type ProcTermConstructorCallExt = VariableId;
     type ProcTermDereferenceExt = ();
     type ProcTermIfExt = ();
-    type ProcTermStructValueExt = ();
+    type ProcTermStructValueExt = VariableId;
     type StatementReturnExt = ();
-    type StatementCallPtxExt = ();
+    type StatementCallPtxExt = VariableId;
 }
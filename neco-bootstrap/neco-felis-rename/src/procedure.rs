@@ -0,0 +1,736 @@
+use neco_felis_syn::{
+    ItemArray, ItemArrayField, ItemProc, ItemProcBlock, ItemStruct, ItemStructField, PhaseParse,
+    ProcContract, ProcTerm, ProcTermApply, ProcTermConstructorCall, ProcTermDereference,
+    ProcTermFieldAccess, ProcTermIf, ProcTermIfElse, ProcTermNumber, ProcTermParen,
+    ProcTermStructField, ProcTermStructValue, ProcTermUnit, ProcTermVariable, Statement,
+    StatementAssign, StatementBreak, StatementCallPtx, StatementFieldAssign, StatementLet,
+    StatementLetMut, StatementLoop, StatementReturn, Statements, StatementsThen, Term,
+    TermArrowDep, TermArrowImplicit, TermArrowNodep, TermStruct, TermStructField, TermVariable,
+    token::TokenVariable,
+};
+
+use crate::{
+    Lint, RenameContext, RenameError, RenameErrorKind, VariableId, phase_renamed::PhaseRenamed,
+    rename_term,
+};
+
+/// A procedure. Its parameters are in scope in its contracts and its body,
+/// and `result` in its `#ensures`.
+pub(crate) fn rename_proc(
+    context: &mut RenameContext,
+    proc: &ItemProc<PhaseParse>,
+) -> Result<ItemProc<PhaseRenamed>, RenameError> {
+    let id = context.item_id(&proc.name)?;
+    context.enter_scope();
+
+    let mut params = Vec::new();
+    let ty = rename_signature(context, &proc.ty, &mut params)?;
+    let contracts = proc
+        .contracts
+        .iter()
+        .map(|contract| {
+            context.enter_scope();
+            if contract.is_ensures() {
+                context.bind_variable("result");
+            }
+            let condition = rename_term(context, &contract.condition);
+            context.leave_scope();
+            ProcContract {
+                keyword: contract.keyword.clone(),
+                condition: Box::new(condition),
+            }
+        })
+        .collect();
+    let statements = rename_statements(context, &proc.proc_block.statements)?;
+
+    context.leave_local_scope();
+    Ok(ItemProc {
+        ptx_modifier: proc.ptx_modifier.clone(),
+        keyword_proc: proc.keyword_proc.clone(),
+        name: proc.name.clone(),
+        colon: proc.colon.clone(),
+        ty: Box::new(ty),
+        contracts,
+        proc_block: ItemProcBlock {
+            brace_l: proc.proc_block.brace_l.clone(),
+            statements,
+            brace_r: proc.proc_block.brace_r.clone(),
+            ext: (),
+        },
+        ext: id,
+    })
+}
+
+/// The type of a procedure, binding its parameters in the current scope
+fn rename_signature(
+    context: &mut RenameContext,
+    ty: &Term<PhaseParse>,
+    params: &mut Vec<String>,
+) -> Result<Term<PhaseRenamed>, RenameError> {
+    match ty {
+        Term::ArrowDep(arrow) => {
+            let from_ty = rename_term(context, arrow.from_ty());
+            let from = bind_param(context, arrow.from(), arrow.from_ty(), params)?;
+            Ok(Term::ArrowDep(TermArrowDep {
+                paren_l: arrow.paren_l.clone(),
+                from,
+                colon: arrow.colon.clone(),
+                from_ty: Box::new(from_ty),
+                paren_r: arrow.paren_r.clone(),
+                arrow: arrow.arrow.clone(),
+                to: Box::new(rename_signature(context, arrow.to(), params)?),
+                ext: (),
+            }))
+        }
+        Term::ArrowImplicit(arrow) => {
+            let from_ty = rename_term(context, arrow.from_ty());
+            let from = bind_param(context, arrow.from(), arrow.from_ty(), params)?;
+            Ok(Term::ArrowImplicit(TermArrowImplicit {
+                brace_l: arrow.brace_l.clone(),
+                from,
+                colon: arrow.colon.clone(),
+                from_ty: Box::new(from_ty),
+                brace_r: arrow.brace_r.clone(),
+                arrow: arrow.arrow.clone(),
+                to: Box::new(rename_signature(context, arrow.to(), params)?),
+                ext: (),
+            }))
+        }
+        Term::ArrowNodep(arrow) => Ok(Term::ArrowNodep(TermArrowNodep {
+            from: Box::new(rename_term(context, arrow.from())),
+            arrow: arrow.arrow.clone(),
+            to: Box::new(rename_signature(context, arrow.to(), params)?),
+            ext: (),
+        })),
+        ty => Ok(rename_term(context, ty)),
+    }
+}
+
+fn bind_param(
+    context: &mut RenameContext,
+    from: &TermVariable<PhaseParse>,
+    from_ty: &Term<PhaseParse>,
+    params: &mut Vec<String>,
+) -> Result<TermVariable<PhaseRenamed>, RenameError> {
+    let name = from.variable().s();
+    if params.iter().any(|param| param == name) {
+        return Err(RenameError::new(
+            RenameErrorKind::DuplicateName(name.to_string()),
+            from.variable().pos().clone(),
+        ));
+    }
+    params.push(name.to_string());
+
+    let id = declare_local(context, from.variable());
+    // A parameter of a struct or array type has its fields
+    if let Term::Variable(ty) = from_ty
+        && let Some(ty_id) = context.lookup_variable(ty.variable().s())
+        && context.fields.contains_key(&ty_id)
+    {
+        context.local_types.insert(id.clone(), ty_id);
+    }
+    Ok(TermVariable {
+        variable: from.variable.clone(),
+        ext: id,
+    })
+}
+
+/// Declares a parameter or a local, warning if it shadows a binding of an
+/// enclosing scope
+fn declare_local(context: &mut RenameContext, name: &TokenVariable) -> VariableId {
+    if let Some(shadowed) = context.scope.shadowed(&name.s().to_string())
+        && let Some(declared_at) = shadowed.declared_at.clone()
+    {
+        context.warn(
+            Lint::ShadowedNames,
+            format!("{} shadows the binding at {declared_at}", name.s()),
+            name.pos().clone(),
+            context.current_item,
+        );
+    }
+    context.declare_variable(name)
+}
+
+/// A `#struct`, whose fields are unique
+pub(crate) fn rename_struct(
+    context: &mut RenameContext,
+    item: &ItemStruct<PhaseParse>,
+    id: VariableId,
+) -> Result<ItemStruct<PhaseRenamed>, RenameError> {
+    let names = unique_fields(item.fields.iter().map(|field| &field.name))?;
+    context
+        .fields
+        .insert(id.clone(), (item.name.s().to_string(), names));
+    Ok(ItemStruct {
+        keyword_struct: item.keyword_struct.clone(),
+        name: item.name.clone(),
+        brace_l: item.brace_l.clone(),
+        fields: item
+            .fields
+            .iter()
+            .map(|field| ItemStructField {
+                name: field.name.clone(),
+                colon: field.colon.clone(),
+                ty: Box::new(rename_term(context, &field.ty)),
+                comma: field.comma.clone(),
+            })
+            .collect(),
+        brace_r: item.brace_r.clone(),
+        ext: id,
+    })
+}
+
+/// An `#array`, whose fields are those of its `#item` struct
+pub(crate) fn rename_array(
+    context: &mut RenameContext,
+    item: &ItemArray<PhaseParse>,
+) -> Result<ItemArray<PhaseRenamed>, RenameError> {
+    let id = context.item_id(&item.name)?;
+    let mut names = Vec::new();
+    for field in &item.fields {
+        if field.keyword.s() == "item"
+            && let Term::Struct(item_struct) = field.value.as_ref()
+        {
+            names = unique_fields(item_struct.fields().iter().map(|field| &field.name))?;
+        }
+    }
+    context
+        .fields
+        .insert(id.clone(), (item.name.s().to_string(), names));
+    Ok(ItemArray {
+        keyword_array: item.keyword_array.clone(),
+        name: item.name.clone(),
+        brace_l: item.brace_l.clone(),
+        fields: item
+            .fields
+            .iter()
+            .map(|field| ItemArrayField {
+                keyword: field.keyword.clone(),
+                colon: field.colon.clone(),
+                value: Box::new(rename_term(context, &field.value)),
+                comma: field.comma.clone(),
+            })
+            .collect(),
+        brace_r: item.brace_r.clone(),
+        ext: id,
+    })
+}
+
+/// The fields of a `#struct { .. }` term
+pub(crate) fn rename_term_struct(
+    context: &mut RenameContext,
+    term_struct: &TermStruct<PhaseParse>,
+) -> TermStruct<PhaseRenamed> {
+    TermStruct {
+        keyword_struct: term_struct.keyword_struct.clone(),
+        brace_l: term_struct.brace_l.clone(),
+        fields: term_struct
+            .fields()
+            .iter()
+            .map(|field| TermStructField {
+                name: field.name.clone(),
+                colon: field.colon.clone(),
+                ty: Box::new(rename_term(context, &field.ty)),
+                comma: field.comma.clone(),
+            })
+            .collect(),
+        brace_r: term_struct.brace_r.clone(),
+        ext: (),
+    }
+}
+
+fn unique_fields<'a>(
+    fields: impl Iterator<Item = &'a TokenVariable>,
+) -> Result<Vec<String>, RenameError> {
+    let mut names: Vec<String> = Vec::new();
+    for field in fields {
+        if names.iter().any(|name| name == field.s()) {
+            return Err(RenameError::new(
+                RenameErrorKind::DuplicateName(field.s().to_string()),
+                field.pos().clone(),
+            ));
+        }
+        names.push(field.s().to_string());
+    }
+    Ok(names)
+}
+
+/// A block, whose bindings are not in scope after it
+fn rename_block(
+    context: &mut RenameContext,
+    statements: &Statements<PhaseParse>,
+) -> Result<Statements<PhaseRenamed>, RenameError> {
+    context.enter_scope();
+    let block = rename_statements(context, statements);
+    context.leave_local_scope();
+    block
+}
+
+fn rename_statements(
+    context: &mut RenameContext,
+    statements: &Statements<PhaseParse>,
+) -> Result<Statements<PhaseRenamed>, RenameError> {
+    match statements {
+        Statements::Then(then) => Ok(Statements::Then(StatementsThen {
+            head: Box::new(rename_statement(context, &then.head)?),
+            semicolon: then.semicolon.clone(),
+            tail: Box::new(rename_statements(context, &then.tail)?),
+            ext: (),
+        })),
+        Statements::Statement(statement) => Ok(Statements::Statement(Box::new(rename_statement(
+            context, statement,
+        )?))),
+        Statements::Nil => Ok(Statements::Nil),
+    }
+}
+
+/// A statement. A `#let` binds its name after its value, so it may shadow
+/// an earlier binding it uses.
+fn rename_statement(
+    context: &mut RenameContext,
+    statement: &Statement<PhaseParse>,
+) -> Result<Statement<PhaseRenamed>, RenameError> {
+    let statement = match statement {
+        Statement::Let(let_stmt) => {
+            let value = rename_proc_term(context, &let_stmt.value)?;
+            let id = declare_local(context, &let_stmt.variable);
+            bind_local_type(context, &id, &value);
+            Statement::Let(StatementLet {
+                let_keyword: let_stmt.let_keyword.clone(),
+                variable: let_stmt.variable.clone(),
+                equals: let_stmt.equals.clone(),
+                value: Box::new(value),
+                ext: id,
+            })
+        }
+        Statement::LetMut(let_mut) => {
+            if let_mut.variable.s() == let_mut.reference_variable.s() {
+                return Err(RenameError::new(
+                    RenameErrorKind::DuplicateName(let_mut.reference_variable.s().to_string()),
+                    let_mut.reference_variable.pos().clone(),
+                ));
+            }
+            let value = rename_proc_term(context, &let_mut.value)?;
+            let id = declare_local(context, &let_mut.variable);
+            let reference_id = declare_local(context, &let_mut.reference_variable);
+            bind_local_type(context, &id, &value);
+            Statement::LetMut(StatementLetMut {
+                let_keyword: let_mut.let_keyword.clone(),
+                mut_keyword: let_mut.mut_keyword.clone(),
+                variable: let_mut.variable.clone(),
+                at_operator: let_mut.at_operator.clone(),
+                reference_variable: let_mut.reference_variable.clone(),
+                equals: let_mut.equals.clone(),
+                value: Box::new(value),
+                ext: (id, reference_id),
+            })
+        }
+        Statement::Assign(assign) => Statement::Assign(StatementAssign {
+            variable: assign.variable.clone(),
+            equals: assign.equals.clone(),
+            value: Box::new(rename_proc_term(context, &assign.value)?),
+            ext: context.resolve(&assign.variable)?,
+        }),
+        Statement::FieldAssign(assign) => Statement::FieldAssign(StatementFieldAssign {
+            field_access: rename_field_access(context, &assign.field_access)?,
+            equals: assign.equals.clone(),
+            value: Box::new(rename_proc_term(context, &assign.value)?),
+            ext: (),
+        }),
+        Statement::Loop(loop_stmt) => Statement::Loop(StatementLoop {
+            keyword_loop: loop_stmt.keyword_loop.clone(),
+            brace_l: loop_stmt.brace_l.clone(),
+            body: Box::new(rename_block(context, &loop_stmt.body)?),
+            brace_r: loop_stmt.brace_r.clone(),
+            ext: (),
+        }),
+        Statement::Break(break_stmt) => Statement::Break(StatementBreak {
+            keyword_break: break_stmt.keyword_break.clone(),
+            semicolon: break_stmt.semicolon.clone(),
+            ext: (),
+        }),
+        Statement::Return(return_stmt) => Statement::Return(StatementReturn {
+            keyword_return: return_stmt.keyword_return.clone(),
+            value: Box::new(rename_proc_term(context, &return_stmt.value)?),
+            semicolon: return_stmt.semicolon.clone(),
+            ext: (),
+        }),
+        Statement::CallPtx(call_ptx) => Statement::CallPtx(StatementCallPtx {
+            keyword_call_ptx: call_ptx.keyword_call_ptx.clone(),
+            function_name: call_ptx.function_name.clone(),
+            args: rename_proc_terms(context, &call_ptx.args)?,
+            grid_dim_x: call_ptx.grid_dim_x.clone(),
+            grid_dim_y: call_ptx.grid_dim_y.clone(),
+            grid_dim_z: call_ptx.grid_dim_z.clone(),
+            block_dim_x: call_ptx.block_dim_x.clone(),
+            block_dim_y: call_ptx.block_dim_y.clone(),
+            block_dim_z: call_ptx.block_dim_z.clone(),
+            ext: context.resolve(&call_ptx.function_name)?,
+        }),
+        Statement::Expr(term) => Statement::Expr(rename_proc_term(context, term)?),
+        Statement::Ext(()) => Statement::Ext(()),
+    };
+    Ok(statement)
+}
+
+/// Records the struct or array type of a local bound to a literal or an
+/// allocation, for the fields accessed on it
+fn bind_local_type(context: &mut RenameContext, id: &VariableId, value: &ProcTerm<PhaseRenamed>) {
+    let ty = match value {
+        ProcTerm::ConstructorCall(call) => call.ext.clone(),
+        ProcTerm::StructValue(struct_value) => struct_value.ext.clone(),
+        _ => return,
+    };
+    context.local_types.insert(id.clone(), ty);
+}
+
+fn rename_proc_terms(
+    context: &mut RenameContext,
+    terms: &[ProcTerm<PhaseParse>],
+) -> Result<Vec<ProcTerm<PhaseRenamed>>, RenameError> {
+    terms
+        .iter()
+        .map(|term| rename_proc_term(context, term))
+        .collect()
+}
+
+fn rename_proc_term(
+    context: &mut RenameContext,
+    term: &ProcTerm<PhaseParse>,
+) -> Result<ProcTerm<PhaseRenamed>, RenameError> {
+    let term = match term {
+        ProcTerm::Paren(paren) => ProcTerm::Paren(ProcTermParen {
+            paren_l: paren.paren_l.clone(),
+            proc_term: Box::new(rename_proc_term(context, &paren.proc_term)?),
+            paren_r: paren.paren_r.clone(),
+            ext: (),
+        }),
+        ProcTerm::Apply(apply) => ProcTerm::Apply(ProcTermApply {
+            f: Box::new(rename_proc_term(context, &apply.f)?),
+            args: rename_proc_terms(context, &apply.args)?,
+            ext: (),
+        }),
+        ProcTerm::Variable(var) => ProcTerm::Variable(ProcTermVariable {
+            variable: var.variable.clone(),
+            ext: context.resolve(&var.variable)?,
+        }),
+        ProcTerm::Unit(unit) => ProcTerm::Unit(ProcTermUnit {
+            paren_l: unit.paren_l.clone(),
+            paren_r: unit.paren_r.clone(),
+            ext: (),
+        }),
+        ProcTerm::Number(number) => ProcTerm::Number(ProcTermNumber {
+            number: number.number.clone(),
+            ext: (),
+        }),
+        ProcTerm::FieldAccess(access) => {
+            ProcTerm::FieldAccess(rename_field_access(context, access)?)
+        }
+        ProcTerm::ConstructorCall(call) => ProcTerm::ConstructorCall(ProcTermConstructorCall {
+            type_name: call.type_name.clone(),
+            colon2: call.colon2.clone(),
+            method: call.method.clone(),
+            args: rename_proc_terms(context, &call.args)?,
+            ext: context.resolve(&call.type_name)?,
+        }),
+        ProcTerm::Struct(item) => {
+            let id = context.generate_variable_id();
+            ProcTerm::Struct(rename_struct(context, item, id)?)
+        }
+        ProcTerm::StructValue(value) => ProcTerm::StructValue(rename_struct_value(context, value)?),
+        ProcTerm::If(if_expr) => ProcTerm::If(ProcTermIf {
+            keyword_if: if_expr.keyword_if.clone(),
+            condition: Box::new(rename_block(context, &if_expr.condition)?),
+            brace_l: if_expr.brace_l.clone(),
+            then_body: Box::new(rename_block(context, &if_expr.then_body)?),
+            brace_r: if_expr.brace_r.clone(),
+            else_clause: match &if_expr.else_clause {
+                Some(else_clause) => Some(ProcTermIfElse {
+                    keyword_else: else_clause.keyword_else.clone(),
+                    brace_l: else_clause.brace_l.clone(),
+                    else_body: Box::new(rename_block(context, &else_clause.else_body)?),
+                    brace_r: else_clause.brace_r.clone(),
+                }),
+                None => None,
+            },
+            ext: (),
+        }),
+        ProcTerm::Dereference(dereference) => ProcTerm::Dereference(ProcTermDereference {
+            term: Box::new(rename_proc_term(context, &dereference.term)?),
+            dot_star: dereference.dot_star.clone(),
+            ext: (),
+        }),
+        ProcTerm::Ext(()) => ProcTerm::Ext(()),
+    };
+    Ok(term)
+}
+
+/// `x.f` or `xs.f i`. The field is checked when the type of the object is
+/// known here, and is otherwise left to the type checker.
+fn rename_field_access(
+    context: &mut RenameContext,
+    access: &ProcTermFieldAccess<PhaseParse>,
+) -> Result<ProcTermFieldAccess<PhaseRenamed>, RenameError> {
+    let object = context.resolve(&access.object)?;
+    if let Some((ty, fields)) = context
+        .local_types
+        .get(&object)
+        .and_then(|ty| context.fields.get(ty))
+        && access.field_name() != "#len"
+        && !fields.iter().any(|field| field == access.field_name())
+    {
+        return Err(RenameError::new(
+            RenameErrorKind::UnknownField {
+                field: access.field_name().to_string(),
+                ty: ty.clone(),
+            },
+            access.field.pos().clone(),
+        ));
+    }
+    Ok(ProcTermFieldAccess {
+        object: access.object.clone(),
+        dot: access.dot.clone(),
+        field: access.field.clone(),
+        index: match &access.index {
+            Some(index) => Some(Box::new(rename_proc_term(context, index)?)),
+            None => None,
+        },
+        ext: object,
+    })
+}
+
+/// `S { x: a, y: b }`, whose fields are fields of `S`, each given once
+fn rename_struct_value(
+    context: &mut RenameContext,
+    value: &ProcTermStructValue<PhaseParse>,
+) -> Result<ProcTermStructValue<PhaseRenamed>, RenameError> {
+    let id = context.resolve(&value.struct_name)?;
+    unique_fields(value.fields.iter().map(|field| &field.name))?;
+    if let Some((ty, fields)) = context.fields.get(&id)
+        && let Some(field) = value
+            .fields
+            .iter()
+            .find(|field| !fields.iter().any(|name| name == field.name.s()))
+    {
+        return Err(RenameError::new(
+            RenameErrorKind::UnknownField {
+                field: field.name.s().to_string(),
+                ty: ty.clone(),
+            },
+            field.name.pos().clone(),
+        ));
+    }
+    let mut fields = Vec::new();
+    for field in &value.fields {
+        fields.push(ProcTermStructField {
+            name: field.name.clone(),
+            colon: field.colon.clone(),
+            value: Box::new(rename_proc_term(context, &field.value)?),
+            comma: field.comma.clone(),
+        });
+    }
+    Ok(ProcTermStructValue {
+        struct_name: value.struct_name.clone(),
+        brace_l: value.brace_l.clone(),
+        fields,
+        brace_r: value.brace_r.clone(),
+        ext: id,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use neco_felis_syn::{File, FileIdGenerator, Item, Parse, token::Token};
+
+    use crate::{RenameError, RenameErrorKind, VariableId, rename_file};
+
+    const PRELUDE: &str = "
+#use_builtin \"syscall\" #as __syscall;
+#use_builtin \"u64\" #as __u64;
+#use_builtin \"u64_add\" #as __u64_add;
+
+#struct Vec2 {
+    x: __u64,
+    y: __u64,
+}
+
+#array Points {
+    #item: #struct {
+        x: __u64,
+        y: __u64,
+    },
+    #dimension: 1,
+}
+";
+
+    fn parse(source: &str) -> File<neco_felis_syn::PhaseParse> {
+        let mut file_id_generator = FileIdGenerator::new();
+        let file_id = file_id_generator.generate_file_id();
+        let tokens = Token::lex(source, file_id);
+        let mut i = 0;
+        File::parse(&tokens, &mut i).unwrap().unwrap()
+    }
+
+    fn rename_main(body: &str) -> Result<crate::File<crate::PhaseRenamed>, RenameError> {
+        let source = format!("{PRELUDE}\n#proc main : () -> () {{\n{body}\n}}\n");
+        rename_file(&parse(&source))
+    }
+
+    fn kind(body: &str) -> RenameErrorKind {
+        rename_main(body).unwrap_err().kind
+    }
+
+    #[test]
+    fn test_procedure_testcases_rename() {
+        for entry in std::fs::read_dir("../../testcases/felis/single").unwrap() {
+            let path = entry.unwrap().path();
+            let source = std::fs::read_to_string(&path).unwrap();
+            let result = rename_file(&parse(&source));
+            assert!(result.is_ok(), "{}: {:?}", path.display(), result.err());
+        }
+    }
+
+    #[test]
+    fn test_locals_are_resolved_to_their_bindings() {
+        let file = rename_main(
+            "    #let a = 1;
+    #let a = __u64_add a 2;
+    __syscall 231 a 0 0 0 0;",
+        )
+        .unwrap();
+        let Some(Item::Proc(proc)) = file.items.last() else {
+            panic!("expected the procedure");
+        };
+        let neco_felis_syn::Statements::Then(first) = &proc.proc_block.statements else {
+            panic!("expected a statement sequence");
+        };
+        let neco_felis_syn::Statements::Then(second) = first.tail.as_ref() else {
+            panic!("expected a statement sequence");
+        };
+        let (neco_felis_syn::Statement::Let(a1), neco_felis_syn::Statement::Let(a2)) =
+            (first.head.as_ref(), second.head.as_ref())
+        else {
+            panic!("expected two `#let`s");
+        };
+        assert_ne!(a1.ext, a2.ext);
+
+        // The second `a` uses the first
+        let neco_felis_syn::ProcTerm::Apply(apply) = a2.value.as_ref() else {
+            panic!("expected an application");
+        };
+        let neco_felis_syn::ProcTerm::Variable(used) = &apply.args[0] else {
+            panic!("expected a variable");
+        };
+        assert_eq!(used.ext, a1.ext);
+        let VariableId(file_id, _) = used.ext;
+        assert_eq!(file_id, 0);
+    }
+
+    #[test]
+    fn test_procedures_may_call_later_procedures() {
+        let source = format!(
+            "{PRELUDE}
+#proc main : () -> () {{
+    __syscall 231 (f 1) 0 0 0 0;
+}}
+
+#proc f : (n : __u64) -> __u64 {{
+    __u64_add n 1
+}}
+"
+        );
+        rename_file(&parse(&source)).unwrap();
+    }
+
+    #[test]
+    fn test_unbound_variable() {
+        let error = rename_main("    __syscall 231 b 0 0 0 0;").unwrap_err();
+        assert_eq!(
+            error.kind,
+            RenameErrorKind::UnboundVariable("b".to_string())
+        );
+        assert!(error.to_string().starts_with("Unbound variable: b at "));
+    }
+
+    #[test]
+    fn test_let_is_not_in_scope_after_its_block() {
+        assert_eq!(
+            kind(
+                "    #loop {
+        #let inner = 1;
+        #break;
+    };
+    __syscall 231 inner 0 0 0 0;"
+            ),
+            RenameErrorKind::UnboundVariable("inner".to_string())
+        );
+    }
+
+    #[test]
+    fn test_unbound_reference_and_array_type() {
+        assert_eq!(
+            kind("    r <- 1;"),
+            RenameErrorKind::UnboundVariable("r".to_string())
+        );
+        assert_eq!(
+            kind("    #let ps = Missing::#new_with_size 2;"),
+            RenameErrorKind::UnboundVariable("Missing".to_string())
+        );
+    }
+
+    #[test]
+    fn test_duplicate_names() {
+        assert_eq!(
+            kind("    #let #mut n @ n = 0;"),
+            RenameErrorKind::DuplicateName("n".to_string())
+        );
+        assert_eq!(
+            kind("    #let v = Vec2 { x: 1, x: 2 };"),
+            RenameErrorKind::DuplicateName("x".to_string())
+        );
+
+        let source = format!(
+            "{PRELUDE}
+#proc f : (a : __u64) -> (a : __u64) -> __u64 {{
+    a
+}}
+"
+        );
+        let error = rename_file(&parse(&source)).unwrap_err();
+        assert_eq!(error.kind, RenameErrorKind::DuplicateName("a".to_string()));
+
+        let source = format!("{PRELUDE}\n#use_builtin \"u64_add\" #as __u64;\n");
+        let error = rename_file(&parse(&source)).unwrap_err();
+        assert_eq!(
+            error.kind,
+            RenameErrorKind::DuplicateName("__u64".to_string())
+        );
+    }
+
+    #[test]
+    fn test_unknown_fields() {
+        assert_eq!(
+            kind("    #let v = Vec2 { x: 1, z: 2 };"),
+            RenameErrorKind::UnknownField {
+                field: "z".to_string(),
+                ty: "Vec2".to_string(),
+            }
+        );
+        assert_eq!(
+            kind(
+                "    #let ps = Points::#new_with_size 2;
+    ps.z 0 <- 1;"
+            ),
+            RenameErrorKind::UnknownField {
+                field: "z".to_string(),
+                ty: "Points".to_string(),
+            }
+        );
+        rename_main(
+            "    #let ps = Points::#new_with_size 2;
+    #let n = ps.#len ();
+    ps.y 0 <- n;",
+        )
+        .unwrap();
+    }
+}
@@ -1,6 +1,7 @@
 use crate::{
-    ItemArray, ItemDefinition, ItemEntrypoint, ItemInductive, ItemProc, ItemStruct, ItemTheorem,
-    ItemUseBuiltin, Parse, ParseError, Phase, PhaseParse, token::Token,
+    ItemAllow, ItemArray, ItemAxiom, ItemCheck, ItemDefinition, ItemEntrypoint, ItemEval,
+    ItemInductive, ItemMutual, ItemPrint, ItemPrintAxioms, ItemProc, ItemStruct, ItemStructure,
+    ItemTheorem, ItemUseBuiltin, Parse, ParseError, Phase, PhaseParse, token::Token,
 };
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -8,17 +9,27 @@ pub enum Item<P: Phase> {
     Inductive(ItemInductive<P>),
     Definition(ItemDefinition<P>),
     Theorem(ItemTheorem<P>),
+    Axiom(ItemAxiom<P>),
+    PrintAxioms(ItemPrintAxioms<P>),
+    Check(ItemCheck<P>),
+    Eval(ItemEval<P>),
+    Print(ItemPrint<P>),
+    Mutual(ItemMutual<P>),
     Entrypoint(ItemEntrypoint<P>),
     UseBuiltin(ItemUseBuiltin<P>),
     Proc(Box<ItemProc<P>>),
     Array(ItemArray<P>),
     Struct(ItemStruct<P>),
+    Structure(ItemStructure<P>),
+    Allow(ItemAllow<P>),
 }
 
 impl Parse for Item<PhaseParse> {
     fn parse(tokens: &[Token], i: &mut usize) -> Result<Option<Self>, ParseError> {
         if let Some(entrypoint) = ItemEntrypoint::parse(tokens, i)? {
             Ok(Some(Item::Entrypoint(entrypoint)))
+        } else if let Some(allow) = ItemAllow::parse(tokens, i)? {
+            Ok(Some(Item::Allow(allow)))
         } else if let Some(use_builtin) = ItemUseBuiltin::parse(tokens, i)? {
             Ok(Some(Item::UseBuiltin(use_builtin)))
         } else if let Some(inductive) = ItemInductive::parse(tokens, i)? {
@@ -27,10 +38,24 @@ impl Parse for Item<PhaseParse> {
             Ok(Some(Item::Definition(definition)))
         } else if let Some(theorem) = ItemTheorem::parse(tokens, i)? {
             Ok(Some(Item::Theorem(theorem)))
+        } else if let Some(axiom) = ItemAxiom::parse(tokens, i)? {
+            Ok(Some(Item::Axiom(axiom)))
+        } else if let Some(print_axioms) = ItemPrintAxioms::parse(tokens, i)? {
+            Ok(Some(Item::PrintAxioms(print_axioms)))
+        } else if let Some(check) = ItemCheck::parse(tokens, i)? {
+            Ok(Some(Item::Check(check)))
+        } else if let Some(eval) = ItemEval::parse(tokens, i)? {
+            Ok(Some(Item::Eval(eval)))
+        } else if let Some(print) = ItemPrint::parse(tokens, i)? {
+            Ok(Some(Item::Print(print)))
+        } else if let Some(mutual) = ItemMutual::parse(tokens, i)? {
+            Ok(Some(Item::Mutual(mutual)))
         } else if let Some(proc) = ItemProc::parse(tokens, i)? {
             Ok(Some(Item::Proc(Box::new(proc))))
         } else if let Some(array) = ItemArray::parse(tokens, i)? {
             Ok(Some(Item::Array(array)))
+        } else if let Some(structure) = ItemStructure::parse(tokens, i)? {
+            Ok(Some(Item::Structure(structure)))
         } else if let Some(struct_) = ItemStruct::parse(tokens, i)? {
             Ok(Some(Item::Struct(struct_)))
         } else {
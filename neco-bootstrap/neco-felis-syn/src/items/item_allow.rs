@@ -0,0 +1,67 @@
+use crate::{
+    Parse, ParseError, Phase, PhaseParse,
+    token::{TokenKeyword, TokenSemicolon, TokenVariable},
+};
+
+/// `#allow lint;` silences a lint for the item that follows it
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ItemAllow<P: Phase> {
+    pub keyword_allow: TokenKeyword,
+    pub lint: TokenVariable,
+    pub semicolon: TokenSemicolon,
+    pub ext: P::ItemAllowExt,
+}
+
+impl<P: Phase> ItemAllow<P> {
+    pub fn lint(&self) -> &TokenVariable {
+        &self.lint
+    }
+}
+
+impl Parse for ItemAllow<PhaseParse> {
+    fn parse(
+        tokens: &[crate::token::Token],
+        i: &mut usize,
+    ) -> Result<Option<Self>, crate::ParseError> {
+        let mut k = *i;
+
+        let Some(keyword_allow) = TokenKeyword::parse_keyword(tokens, &mut k, "allow")? else {
+            return Ok(None);
+        };
+
+        let Some(lint) = TokenVariable::parse(tokens, &mut k)? else {
+            return Err(ParseError::Unknown("expected TokenVariable"));
+        };
+
+        let Some(semicolon) = TokenSemicolon::parse(tokens, &mut k)? else {
+            return Err(ParseError::Unknown("expected TokenSemicolon"));
+        };
+
+        *i = k;
+        Ok(Some(ItemAllow {
+            keyword_allow,
+            lint,
+            semicolon,
+            ext: (),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FileIdGenerator, token::Token};
+
+    #[test]
+    fn test_parse_allow() {
+        let mut file_id_generator = FileIdGenerator::new();
+        let file_id = file_id_generator.generate_file_id();
+        let s = "#allow unused_variables;";
+        let tokens = Token::lex(s, file_id);
+
+        let mut i = 0;
+        let allow = ItemAllow::parse(&tokens, &mut i).unwrap().unwrap();
+        assert_eq!(i, tokens.len());
+        assert_eq!(allow.lint().s(), "unused_variables");
+    }
+}
@@ -0,0 +1,99 @@
+use crate::{
+    Parse, ParseError, Phase, PhaseParse, Term,
+    token::{Token, TokenColon, TokenKeyword, TokenSemicolon, TokenVariable},
+};
+
+/// `#axiom name : T;`, a constant assumed without proof
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ItemAxiom<P: Phase> {
+    pub keyword_axiom: TokenKeyword,
+    pub name: TokenVariable,
+    pub colon: TokenColon,
+    pub type_: Box<Term<P>>,
+    pub semicolon: TokenSemicolon,
+    pub ext: P::ItemAxiomExt,
+}
+
+impl<P: Phase> ItemAxiom<P> {
+    pub fn name(&self) -> &TokenVariable {
+        &self.name
+    }
+
+    pub fn type_(&self) -> &Term<P> {
+        &self.type_
+    }
+}
+
+impl Parse for ItemAxiom<PhaseParse> {
+    fn parse(tokens: &[Token], i: &mut usize) -> Result<Option<Self>, ParseError> {
+        let mut k = *i;
+
+        // Parse #axiom keyword
+        let Some(keyword_axiom) = TokenKeyword::parse_keyword(tokens, &mut k, "axiom")? else {
+            return Ok(None);
+        };
+
+        // Parse name
+        let Some(name) = TokenVariable::parse(tokens, &mut k)? else {
+            return Err(ParseError::Unknown("expected name after #axiom"));
+        };
+
+        // Parse colon
+        let Some(colon) = TokenColon::parse(tokens, &mut k)? else {
+            return Err(ParseError::Unknown("expected : after axiom name"));
+        };
+
+        // Parse type
+        let Some(type_) = Term::parse(tokens, &mut k)? else {
+            return Err(ParseError::Unknown("expected type after :"));
+        };
+
+        // Parse semicolon
+        let Some(semicolon) = TokenSemicolon::parse(tokens, &mut k)? else {
+            return Err(ParseError::Unknown("expected ; after axiom type"));
+        };
+
+        let axiom = ItemAxiom {
+            keyword_axiom,
+            name,
+            colon,
+            type_: Box::new(type_),
+            semicolon,
+            ext: (),
+        };
+
+        *i = k;
+        Ok(Some(axiom))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FileIdGenerator;
+
+    #[test]
+    fn test_parse_axiom() {
+        let mut file_id_generator = FileIdGenerator::new();
+        let file_id = file_id_generator.generate_file_id();
+        let s = "#axiom excluded_middle : (P : Prop) -> or P (not P);";
+        let tokens = Token::lex(s, file_id);
+
+        let mut i = 0;
+        let axiom = ItemAxiom::parse(&tokens, &mut i).unwrap().unwrap();
+        assert_eq!(i, tokens.len());
+        assert_eq!(axiom.name().s(), "excluded_middle");
+        assert!(matches!(axiom.type_(), Term::ArrowDep(_)));
+    }
+
+    #[test]
+    fn test_parse_axiom_requires_semicolon() {
+        let mut file_id_generator = FileIdGenerator::new();
+        let file_id = file_id_generator.generate_file_id();
+        let s = "#axiom a : Prop #axiom b : Prop;";
+        let tokens = Token::lex(s, file_id);
+
+        let mut i = 0;
+        assert!(ItemAxiom::parse(&tokens, &mut i).is_err());
+    }
+}
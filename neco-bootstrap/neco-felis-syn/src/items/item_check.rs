@@ -0,0 +1,66 @@
+use crate::{
+    Parse, ParseError, Phase, PhaseParse, Term,
+    token::{Token, TokenKeyword, TokenSemicolon},
+};
+
+/// `#check term;`, reporting the type of a term
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ItemCheck<P: Phase> {
+    pub keyword_check: TokenKeyword,
+    pub term: Box<Term<P>>,
+    pub semicolon: TokenSemicolon,
+    pub ext: P::ItemCheckExt,
+}
+
+impl<P: Phase> ItemCheck<P> {
+    pub fn term(&self) -> &Term<P> {
+        &self.term
+    }
+}
+
+impl Parse for ItemCheck<PhaseParse> {
+    fn parse(tokens: &[Token], i: &mut usize) -> Result<Option<Self>, ParseError> {
+        let mut k = *i;
+
+        let Some(keyword_check) = TokenKeyword::parse_keyword(tokens, &mut k, "check")? else {
+            return Ok(None);
+        };
+
+        let Some(term) = Term::parse(tokens, &mut k)? else {
+            return Err(ParseError::Unknown("expected term after #check"));
+        };
+
+        let Some(semicolon) = TokenSemicolon::parse(tokens, &mut k)? else {
+            return Err(ParseError::Unknown("expected ; after #check term"));
+        };
+
+        let check = ItemCheck {
+            keyword_check,
+            term: Box::new(term),
+            semicolon,
+            ext: (),
+        };
+
+        *i = k;
+        Ok(Some(check))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FileIdGenerator;
+
+    #[test]
+    fn test_parse_check() {
+        let mut file_id_generator = FileIdGenerator::new();
+        let file_id = file_id_generator.generate_file_id();
+        let s = "#check add O (S O);";
+        let tokens = Token::lex(s, file_id);
+
+        let mut i = 0;
+        let check = ItemCheck::parse(&tokens, &mut i).unwrap().unwrap();
+        assert_eq!(i, tokens.len());
+        assert!(matches!(check.term(), Term::Apply(_)));
+    }
+}
@@ -9,12 +9,22 @@ pub struct ItemDefinition<P: Phase> {
     pub name: TokenVariable,
     pub colon: TokenColon,
     pub type_: Box<Term<P>>,
+    pub decreasing_by: Option<DefinitionDecreasingBy<P>>,
     pub brace_l: TokenBraceL,
     pub body: Box<Term<P>>,
     pub brace_r: TokenBraceR,
     pub ext: P::ItemDefinitionExt,
 }
 
+/// `#decreasing_by wf` after the type of a definition by well-founded
+/// recursion, where `wf` proves that the relation the first parameter
+/// decreases along is well-founded
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DefinitionDecreasingBy<P: Phase> {
+    pub keyword_decreasing_by: TokenKeyword,
+    pub proof: Box<Term<P>>,
+}
+
 impl<P: Phase> ItemDefinition<P> {
     pub fn name(&self) -> &TokenVariable {
         &self.name
@@ -24,11 +34,25 @@ impl<P: Phase> ItemDefinition<P> {
         &self.type_
     }
 
+    pub fn decreasing_by(&self) -> Option<&DefinitionDecreasingBy<P>> {
+        self.decreasing_by.as_ref()
+    }
+
     pub fn body(&self) -> &Term<P> {
         &self.body
     }
 }
 
+impl<P: Phase> DefinitionDecreasingBy<P> {
+    pub fn keyword_decreasing_by(&self) -> &TokenKeyword {
+        &self.keyword_decreasing_by
+    }
+
+    pub fn proof(&self) -> &Term<P> {
+        &self.proof
+    }
+}
+
 impl Parse for ItemDefinition<PhaseParse> {
     fn parse(tokens: &[Token], i: &mut usize) -> Result<Option<Self>, ParseError> {
         let mut k = *i;
@@ -54,6 +78,20 @@ impl Parse for ItemDefinition<PhaseParse> {
             return Err(ParseError::Unknown("expected type after :"));
         };
 
+        // Parse the well-founded relation of the recursive calls, if any
+        let decreasing_by = match TokenKeyword::parse_keyword(tokens, &mut k, "decreasing_by")? {
+            Some(keyword_decreasing_by) => {
+                let Some(proof) = Term::parse(tokens, &mut k)? else {
+                    return Err(ParseError::Unknown("expected proof after #decreasing_by"));
+                };
+                Some(DefinitionDecreasingBy {
+                    keyword_decreasing_by,
+                    proof: Box::new(proof),
+                })
+            }
+            None => None,
+        };
+
         // Parse opening brace
         let Some(brace_l) = TokenBraceL::parse(tokens, &mut k)? else {
             return Err(ParseError::Unknown("expected { after definition type"));
@@ -74,6 +112,7 @@ impl Parse for ItemDefinition<PhaseParse> {
             name,
             colon,
             type_: Box::new(type_),
+            decreasing_by,
             brace_l,
             body: Box::new(body),
             brace_r,
@@ -84,3 +123,25 @@ impl Parse for ItemDefinition<PhaseParse> {
         Ok(Some(definition))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FileIdGenerator;
+
+    #[test]
+    fn test_parse_definition_decreasing_by() {
+        let mut file_id_generator = FileIdGenerator::new();
+        let file_id = file_id_generator.generate_file_id();
+        let s = "#definition div : (n : nat) -> (m : nat) -> nat #decreasing_by lt_wf {
+    n
+}";
+        let tokens = Token::lex(s, file_id);
+
+        let mut i = 0;
+        let definition = ItemDefinition::parse(&tokens, &mut i).unwrap().unwrap();
+        assert_eq!(i, tokens.len());
+        let decreasing_by = definition.decreasing_by().unwrap();
+        assert!(matches!(decreasing_by.proof(), Term::Variable(v) if v.variable.s() == "lt_wf"));
+    }
+}
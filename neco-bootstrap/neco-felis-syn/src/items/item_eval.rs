@@ -0,0 +1,64 @@
+use crate::{
+    Parse, ParseError, Phase, PhaseParse, Term,
+    token::{Token, TokenKeyword, TokenSemicolon},
+};
+
+/// `#eval term;`, reporting the normal form of a term
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ItemEval<P: Phase> {
+    pub keyword_eval: TokenKeyword,
+    pub term: Box<Term<P>>,
+    pub semicolon: TokenSemicolon,
+    pub ext: P::ItemEvalExt,
+}
+
+impl<P: Phase> ItemEval<P> {
+    pub fn term(&self) -> &Term<P> {
+        &self.term
+    }
+}
+
+impl Parse for ItemEval<PhaseParse> {
+    fn parse(tokens: &[Token], i: &mut usize) -> Result<Option<Self>, ParseError> {
+        let mut k = *i;
+
+        let Some(keyword_eval) = TokenKeyword::parse_keyword(tokens, &mut k, "eval")? else {
+            return Ok(None);
+        };
+
+        let Some(term) = Term::parse(tokens, &mut k)? else {
+            return Err(ParseError::Unknown("expected term after #eval"));
+        };
+
+        let Some(semicolon) = TokenSemicolon::parse(tokens, &mut k)? else {
+            return Err(ParseError::Unknown("expected ; after #eval term"));
+        };
+
+        let eval = ItemEval {
+            keyword_eval,
+            term: Box::new(term),
+            semicolon,
+            ext: (),
+        };
+
+        *i = k;
+        Ok(Some(eval))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FileIdGenerator;
+
+    #[test]
+    fn test_parse_eval_requires_semicolon() {
+        let mut file_id_generator = FileIdGenerator::new();
+        let file_id = file_id_generator.generate_file_id();
+        let s = "#eval add O O #eval O;";
+        let tokens = Token::lex(s, file_id);
+
+        let mut i = 0;
+        assert!(ItemEval::parse(&tokens, &mut i).is_err());
+    }
+}
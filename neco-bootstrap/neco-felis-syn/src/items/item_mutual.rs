@@ -0,0 +1,107 @@
+use crate::{
+    Item, Parse, ParseError, Phase, PhaseParse,
+    token::{Token, TokenBraceL, TokenBraceR, TokenKeyword},
+};
+
+/// `#mutual { items }`, inductive types or definitions that refer to each
+/// other
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ItemMutual<P: Phase> {
+    pub keyword_mutual: TokenKeyword,
+    pub brace_l: TokenBraceL,
+    pub items: Vec<Item<P>>,
+    pub brace_r: TokenBraceR,
+    pub ext: P::ItemMutualExt,
+}
+
+impl<P: Phase> ItemMutual<P> {
+    pub fn keyword_mutual(&self) -> &TokenKeyword {
+        &self.keyword_mutual
+    }
+
+    pub fn items(&self) -> &[Item<P>] {
+        &self.items
+    }
+}
+
+impl Parse for ItemMutual<PhaseParse> {
+    fn parse(tokens: &[Token], i: &mut usize) -> Result<Option<Self>, ParseError> {
+        let mut k = *i;
+
+        let Some(keyword_mutual) = TokenKeyword::parse_keyword(tokens, &mut k, "mutual")? else {
+            return Ok(None);
+        };
+
+        let Some(brace_l) = TokenBraceL::parse(tokens, &mut k)? else {
+            return Err(ParseError::Unknown("expected { after #mutual"));
+        };
+
+        let mut items = vec![];
+        while k < tokens.len()
+            && let Some(item) = Item::parse(tokens, &mut k)?
+        {
+            items.push(item);
+        }
+
+        if k >= tokens.len() {
+            return Err(ParseError::Unknown("expected } after #mutual items"));
+        }
+        let Some(brace_r) = TokenBraceR::parse(tokens, &mut k)? else {
+            return Err(ParseError::Unknown("expected } after #mutual items"));
+        };
+
+        let mutual = ItemMutual {
+            keyword_mutual,
+            brace_l,
+            items,
+            brace_r,
+            ext: (),
+        };
+
+        *i = k;
+        Ok(Some(mutual))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FileIdGenerator;
+
+    #[test]
+    fn test_parse_mutual() {
+        let mut file_id_generator = FileIdGenerator::new();
+        let file_id = file_id_generator.generate_file_id();
+        let s = "#mutual {
+    #inductive even : nat -> Prop {
+        even_O : even O,
+        even_S : (n : nat) -> (odd n) -> even (S n),
+    }
+    #inductive odd : nat -> Prop {
+        odd_S : (n : nat) -> (even n) -> odd (S n),
+    }
+}";
+        let tokens = Token::lex(s, file_id);
+
+        let mut i = 0;
+        let mutual = ItemMutual::parse(&tokens, &mut i).unwrap().unwrap();
+        assert_eq!(i, tokens.len());
+        assert_eq!(mutual.items().len(), 2);
+        assert!(
+            mutual
+                .items()
+                .iter()
+                .all(|item| matches!(item, Item::Inductive(_)))
+        );
+    }
+
+    #[test]
+    fn test_parse_unclosed_mutual() {
+        let mut file_id_generator = FileIdGenerator::new();
+        let file_id = file_id_generator.generate_file_id();
+        let tokens = Token::lex("#mutual {", file_id);
+
+        let mut i = 0;
+        assert!(ItemMutual::parse(&tokens, &mut i).is_err());
+    }
+}
@@ -0,0 +1,41 @@
+use crate::{
+    Parse, ParseError, Phase, PhaseParse,
+    token::{Token, TokenKeyword, TokenVariable},
+};
+
+/// `#print name`, showing the type and body of a global name
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ItemPrint<P: Phase> {
+    pub keyword_print: TokenKeyword,
+    pub name: TokenVariable,
+    pub ext: P::ItemPrintExt,
+}
+
+impl<P: Phase> ItemPrint<P> {
+    pub fn name(&self) -> &TokenVariable {
+        &self.name
+    }
+}
+
+impl Parse for ItemPrint<PhaseParse> {
+    fn parse(tokens: &[Token], i: &mut usize) -> Result<Option<Self>, ParseError> {
+        let mut k = *i;
+
+        let Some(keyword_print) = TokenKeyword::parse_keyword(tokens, &mut k, "print")? else {
+            return Ok(None);
+        };
+
+        let Some(name) = TokenVariable::parse(tokens, &mut k)? else {
+            return Err(ParseError::Unknown("expected name after #print"));
+        };
+
+        let print = ItemPrint {
+            keyword_print,
+            name,
+            ext: (),
+        };
+
+        *i = k;
+        Ok(Some(print))
+    }
+}
@@ -0,0 +1,43 @@
+use crate::{
+    Parse, ParseError, Phase, PhaseParse,
+    token::{Token, TokenKeyword, TokenVariable},
+};
+
+/// `#print_axioms name`, listing the axioms a constant depends on
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ItemPrintAxioms<P: Phase> {
+    pub keyword_print_axioms: TokenKeyword,
+    pub name: TokenVariable,
+    pub ext: P::ItemPrintAxiomsExt,
+}
+
+impl<P: Phase> ItemPrintAxioms<P> {
+    pub fn name(&self) -> &TokenVariable {
+        &self.name
+    }
+}
+
+impl Parse for ItemPrintAxioms<PhaseParse> {
+    fn parse(tokens: &[Token], i: &mut usize) -> Result<Option<Self>, ParseError> {
+        let mut k = *i;
+
+        let Some(keyword_print_axioms) =
+            TokenKeyword::parse_keyword(tokens, &mut k, "print_axioms")?
+        else {
+            return Ok(None);
+        };
+
+        let Some(name) = TokenVariable::parse(tokens, &mut k)? else {
+            return Err(ParseError::Unknown("expected name after #print_axioms"));
+        };
+
+        let print_axioms = ItemPrintAxioms {
+            keyword_print_axioms,
+            name,
+            ext: (),
+        };
+
+        *i = k;
+        Ok(Some(print_axioms))
+    }
+}
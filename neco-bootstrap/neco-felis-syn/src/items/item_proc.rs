@@ -10,10 +10,35 @@ pub struct ItemProc<P: Phase> {
     pub name: TokenVariable,
     pub colon: TokenColon,
     pub ty: Box<Term<P>>,
+    pub contracts: Vec<ProcContract<P>>,
     pub proc_block: ItemProcBlock<P>,
     pub ext: P::ItemProcExt,
 }
 
+/// `#requires cond` or `#ensures cond` after the type of a procedure, where
+/// `cond` is a Felis proposition on its parameters, and on `result` for
+/// `#ensures`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ProcContract<P: Phase> {
+    pub keyword: TokenKeyword,
+    pub condition: Box<Term<P>>,
+}
+
+impl<P: Phase> ProcContract<P> {
+    pub fn keyword(&self) -> &TokenKeyword {
+        &self.keyword
+    }
+
+    /// Whether the contract is a postcondition
+    pub fn is_ensures(&self) -> bool {
+        self.keyword.s() == "ensures"
+    }
+
+    pub fn condition(&self) -> &Term<P> {
+        &self.condition
+    }
+}
+
 impl Parse for ItemProc<PhaseParse> {
     fn parse(tokens: &[Token], i: &mut usize) -> Result<Option<Self>, ParseError> {
         let mut k = *i;
@@ -37,6 +62,27 @@ impl Parse for ItemProc<PhaseParse> {
             return Ok(None);
         };
 
+        // Parse the contracts, if any
+        let mut contracts = Vec::new();
+        loop {
+            let keyword = match TokenKeyword::parse_keyword(tokens, &mut k, "requires")? {
+                Some(keyword) => keyword,
+                None => match TokenKeyword::parse_keyword(tokens, &mut k, "ensures")? {
+                    Some(keyword) => keyword,
+                    None => break,
+                },
+            };
+            let Some(condition) = Term::parse(tokens, &mut k)? else {
+                return Err(ParseError::Unknown(
+                    "expected condition after contract keyword",
+                ));
+            };
+            contracts.push(ProcContract {
+                keyword,
+                condition: Box::new(condition),
+            });
+        }
+
         let Some(proc_block) = ItemProcBlock::parse(tokens, &mut k)? else {
             return Ok(None);
         };
@@ -47,6 +93,7 @@ impl Parse for ItemProc<PhaseParse> {
             name,
             colon,
             ty: Box::new(ty),
+            contracts,
             proc_block,
             ext: (),
         };
@@ -54,3 +101,30 @@ impl Parse for ItemProc<PhaseParse> {
         Ok(Some(item_proc))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FileIdGenerator;
+
+    #[test]
+    fn test_parse_proc_contracts() {
+        let mut file_id_generator = FileIdGenerator::new();
+        let file_id = file_id_generator.generate_file_id();
+        let s = "#proc div_safe : (a : __u64) -> (b : __u64) -> __u64
+    #requires not (eq b 0)
+    #ensures le result a
+{
+    __u64_div a b
+}";
+        let tokens = Token::lex(s, file_id);
+
+        let mut i = 0;
+        let proc = ItemProc::parse(&tokens, &mut i).unwrap().unwrap();
+        assert_eq!(i, tokens.len());
+        assert_eq!(proc.contracts.len(), 2);
+        assert!(!proc.contracts[0].is_ensures());
+        assert!(proc.contracts[1].is_ensures());
+        assert!(matches!(proc.contracts[0].condition(), Term::Apply(_)));
+    }
+}
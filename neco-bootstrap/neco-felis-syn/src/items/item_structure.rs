@@ -0,0 +1,109 @@
+use crate::{
+    ItemStructField, Parse, ParseError, Phase, PhaseParse, Term,
+    token::{Token, TokenBraceL, TokenBraceR, TokenColon, TokenKeyword, TokenVariable},
+};
+
+/// A record type for proofs, `#structure Point : Set { x : nat, y : nat }`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ItemStructure<P: Phase> {
+    pub keyword_structure: TokenKeyword,
+    pub name: TokenVariable,
+    pub colon: TokenColon,
+    pub ty: Box<Term<P>>,
+    pub brace_l: TokenBraceL,
+    pub fields: Vec<ItemStructField<P>>,
+    pub brace_r: TokenBraceR,
+    pub ext: P::ItemStructureExt,
+}
+
+impl<P: Phase> ItemStructure<P> {
+    pub fn keyword_structure(&self) -> &TokenKeyword {
+        &self.keyword_structure
+    }
+
+    pub fn name(&self) -> &TokenVariable {
+        &self.name
+    }
+
+    pub fn ty(&self) -> &Term<P> {
+        &self.ty
+    }
+
+    pub fn fields(&self) -> &[ItemStructField<P>] {
+        &self.fields
+    }
+}
+
+impl Parse for ItemStructure<PhaseParse> {
+    fn parse(tokens: &[Token], i: &mut usize) -> Result<Option<Self>, ParseError> {
+        let mut k = *i;
+
+        let Some(keyword_structure) = TokenKeyword::parse_keyword(tokens, &mut k, "structure")?
+        else {
+            return Ok(None);
+        };
+
+        let Some(name) = TokenVariable::parse(tokens, &mut k)? else {
+            return Err(ParseError::Unknown("item_structure_1"));
+        };
+
+        let Some(colon) = TokenColon::parse(tokens, &mut k)? else {
+            return Err(ParseError::Unknown("item_structure_2"));
+        };
+
+        let Some(ty) = Term::parse(tokens, &mut k)? else {
+            return Err(ParseError::Unknown("item_structure_3"));
+        };
+
+        let Some(brace_l) = TokenBraceL::parse(tokens, &mut k)? else {
+            return Err(ParseError::Unknown("item_structure_4"));
+        };
+
+        let mut fields = vec![];
+        while let Some(field) = ItemStructField::parse(tokens, &mut k)? {
+            fields.push(field);
+        }
+
+        let Some(brace_r) = TokenBraceR::parse(tokens, &mut k)? else {
+            return Err(ParseError::Unknown("item_structure_5"));
+        };
+
+        let item_structure = ItemStructure {
+            keyword_structure,
+            name,
+            colon,
+            ty: Box::new(ty),
+            brace_l,
+            fields,
+            brace_r,
+            ext: (),
+        };
+
+        *i = k;
+        Ok(Some(item_structure))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FileIdGenerator;
+
+    #[test]
+    fn test_parse_structure() {
+        let mut file_id_generator = FileIdGenerator::new();
+        let file_id = file_id_generator.generate_file_id();
+        let s = "#structure Point : Set {
+    x : nat,
+    y : nat,
+}";
+        let tokens = Token::lex(s, file_id);
+
+        let mut i = 0;
+        let structure = ItemStructure::parse(&tokens, &mut i).unwrap().unwrap();
+        assert_eq!(i, tokens.len());
+        assert_eq!(structure.name().s(), "Point");
+        let fields: Vec<_> = structure.fields().iter().map(|f| f.name.s()).collect();
+        assert_eq!(fields, ["x", "y"]);
+    }
+}
@@ -8,14 +8,22 @@ pub mod position {
 // Items (top-level language constructs)
 pub mod items {
     pub mod item;
+    pub mod item_allow;
     pub mod item_array;
+    pub mod item_axiom;
+    pub mod item_check;
     pub mod item_definition;
     pub mod item_entrypoint;
+    pub mod item_eval;
     pub mod item_inductive;
     pub mod item_inductive_branch;
+    pub mod item_mutual;
+    pub mod item_print;
+    pub mod item_print_axioms;
     pub mod item_proc;
     pub mod item_proc_block;
     pub mod item_struct;
+    pub mod item_structure;
     pub mod item_theorem;
     pub mod item_use_builtin;
 }
@@ -25,7 +33,12 @@ pub mod terms {
     pub mod term;
     pub mod term_apply;
     pub mod term_arrow_dep;
+    pub mod term_arrow_implicit;
     pub mod term_arrow_nodep;
+    pub mod term_by;
+    pub mod term_by_tactic;
+    pub mod term_field_access;
+    pub mod term_hole;
     pub mod term_match;
     pub mod term_match_branch;
     pub mod term_number;
@@ -78,21 +91,34 @@ pub use position::file_id::*;
 pub use position::pos::*;
 
 pub use items::item::*;
+pub use items::item_allow::*;
 pub use items::item_array::*;
+pub use items::item_axiom::*;
+pub use items::item_check::*;
 pub use items::item_definition::*;
 pub use items::item_entrypoint::*;
+pub use items::item_eval::*;
 pub use items::item_inductive::*;
 pub use items::item_inductive_branch::*;
+pub use items::item_mutual::*;
+pub use items::item_print::*;
+pub use items::item_print_axioms::*;
 pub use items::item_proc::*;
 pub use items::item_proc_block::*;
 pub use items::item_struct::*;
+pub use items::item_structure::*;
 pub use items::item_theorem::*;
 pub use items::item_use_builtin::*;
 
 pub use terms::term::*;
 pub use terms::term_apply::*;
 pub use terms::term_arrow_dep::*;
+pub use terms::term_arrow_implicit::*;
 pub use terms::term_arrow_nodep::*;
+pub use terms::term_by::*;
+pub use terms::term_by_tactic::*;
+pub use terms::term_field_access::*;
+pub use terms::term_hole::*;
 pub use terms::term_match::*;
 pub use terms::term_match_branch::*;
 pub use terms::term_number::*;
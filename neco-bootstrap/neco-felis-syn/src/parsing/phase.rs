@@ -21,6 +21,24 @@ pub trait Phase {
         + PartialOrd
         + Ord
         + std::hash::Hash;
+    type ItemAxiomExt: std::fmt::Debug + Clone + PartialEq + Eq + PartialOrd + Ord + std::hash::Hash;
+    type ItemPrintAxiomsExt: std::fmt::Debug
+        + Clone
+        + PartialEq
+        + Eq
+        + PartialOrd
+        + Ord
+        + std::hash::Hash;
+    type ItemCheckExt: std::fmt::Debug + Clone + PartialEq + Eq + PartialOrd + Ord + std::hash::Hash;
+    type ItemEvalExt: std::fmt::Debug + Clone + PartialEq + Eq + PartialOrd + Ord + std::hash::Hash;
+    type ItemPrintExt: std::fmt::Debug + Clone + PartialEq + Eq + PartialOrd + Ord + std::hash::Hash;
+    type ItemMutualExt: std::fmt::Debug
+        + Clone
+        + PartialEq
+        + Eq
+        + PartialOrd
+        + Ord
+        + std::hash::Hash;
     type ItemTheoremExt: std::fmt::Debug
         + Clone
         + PartialEq
@@ -35,6 +53,7 @@ pub trait Phase {
         + PartialOrd
         + Ord
         + std::hash::Hash;
+    type ItemAllowExt: std::fmt::Debug + Clone + PartialEq + Eq + PartialOrd + Ord + std::hash::Hash;
     type ItemBuiltinExt: std::fmt::Debug
         + Clone
         + PartialEq
@@ -50,6 +69,13 @@ pub trait Phase {
         + PartialOrd
         + Ord
         + std::hash::Hash;
+    type TermArrowImplicitExt: std::fmt::Debug
+        + Clone
+        + PartialEq
+        + Eq
+        + PartialOrd
+        + Ord
+        + std::hash::Hash;
     type TermArrowNodepExt: std::fmt::Debug
         + Clone
         + PartialEq
@@ -57,6 +83,15 @@ pub trait Phase {
         + PartialOrd
         + Ord
         + std::hash::Hash;
+    type TermByExt: std::fmt::Debug + Clone + PartialEq + Eq + PartialOrd + Ord + std::hash::Hash;
+    type TermByTacticExt: std::fmt::Debug
+        + Clone
+        + PartialEq
+        + Eq
+        + PartialOrd
+        + Ord
+        + std::hash::Hash;
+    type TermHoleExt: std::fmt::Debug + Clone + PartialEq + Eq + PartialOrd + Ord + std::hash::Hash;
     type TermMatchExt: std::fmt::Debug + Clone + PartialEq + Eq + PartialOrd + Ord + std::hash::Hash;
     type TermMatchBranchExt: std::fmt::Debug
         + Clone
@@ -118,6 +153,13 @@ pub trait Phase {
         + PartialOrd
         + Ord
         + std::hash::Hash;
+    type ItemStructureExt: std::fmt::Debug
+        + Clone
+        + PartialEq
+        + Eq
+        + PartialOrd
+        + Ord
+        + std::hash::Hash;
     type TermStructExt: std::fmt::Debug
         + Clone
         + PartialEq
@@ -278,11 +320,22 @@ impl Phase for PhaseParse {
     type ItemInductiveExt = ();
     type ItemInductiveBranchExt = ();
     type ItemTheoremExt = ();
+    type ItemAxiomExt = ();
+    type ItemPrintAxiomsExt = ();
+    type ItemCheckExt = ();
+    type ItemEvalExt = ();
+    type ItemPrintExt = ();
+    type ItemMutualExt = ();
     type ItemEntrypointExt = ();
+    type ItemAllowExt = ();
     type ItemBuiltinExt = ();
     type TermApplyExt = ();
     type TermArrowDepExt = ();
+    type TermArrowImplicitExt = ();
     type TermArrowNodepExt = ();
+    type TermByExt = ();
+    type TermByTacticExt = ();
+    type TermHoleExt = ();
     type TermMatchExt = ();
     type TermMatchBranchExt = ();
     type TermParenExt = ();
@@ -296,6 +349,7 @@ impl Phase for PhaseParse {
     type TermNumberExt = ();
     type ItemArrayExt = ();
     type ItemStructExt = ();
+    type ItemStructureExt = ();
     type TermStructExt = ();
     type TermFieldAccessExt = ();
     type TermConstructorCallExt = ();
@@ -20,11 +20,7 @@ impl TokenKeyword {
         i: &mut usize,
         s: &str,
     ) -> Result<Option<Self>, ParseError> {
-        if *i >= tokens.len() {
-            return Ok(None);
-        }
-
-        if let Token::Keyword(token_keyword) = &tokens[*i]
+        if let Some(Token::Keyword(token_keyword)) = tokens.get(*i)
             && token_keyword.s == s
         {
             *i += 1;
@@ -49,11 +45,15 @@ impl TokenVariable {
     pub fn new(pos: Pos, s: String) -> Self {
         Self { pos, s }
     }
+
+    pub fn pos(&self) -> &Pos {
+        &self.pos
+    }
 }
 
 impl Parse for TokenVariable {
     fn parse(tokens: &[Token], i: &mut usize) -> Result<Option<Self>, ParseError> {
-        if let Token::Variable(variable) = &tokens[*i] {
+        if let Some(Token::Variable(variable)) = tokens.get(*i) {
             *i += 1;
             Ok(Some(variable.clone()))
         } else {
@@ -75,7 +75,7 @@ impl TokenOperator {
         i: &mut usize,
         s: &str,
     ) -> Result<Option<Self>, ParseError> {
-        if let Token::Operator(token_operator) = &tokens[*i]
+        if let Some(Token::Operator(token_operator)) = tokens.get(*i)
             && token_operator.s == s
         {
             *i += 1;
@@ -90,7 +90,7 @@ impl TokenOperator {
         i: &mut usize,
         s: &str,
     ) -> Result<Option<Self>, ParseError> {
-        if let Token::Operator(token_operator) = &tokens[*i]
+        if let Some(Token::Operator(token_operator)) = tokens.get(*i)
             && token_operator.s == s
             && token_operator.after_whitespace
         {
@@ -106,7 +106,7 @@ impl TokenOperator {
         i: &mut usize,
         s: &str,
     ) -> Result<Option<Self>, ParseError> {
-        if let Token::Operator(token_operator) = &tokens[*i]
+        if let Some(Token::Operator(token_operator)) = tokens.get(*i)
             && token_operator.s == s
             && !token_operator.after_whitespace
         {
@@ -128,11 +128,15 @@ impl TokenNumber {
     pub fn s(&self) -> &str {
         &self.s
     }
+
+    pub fn pos(&self) -> &Pos {
+        &self.pos
+    }
 }
 
 impl Parse for TokenNumber {
     fn parse(tokens: &[Token], i: &mut usize) -> Result<Option<Self>, ParseError> {
-        if let Token::Number(number) = &tokens[*i] {
+        if let Some(Token::Number(number)) = tokens.get(*i) {
             *i += 1;
             Ok(Some(number.clone()))
         } else {
@@ -155,7 +159,7 @@ impl TokenString {
 
 impl Parse for TokenString {
     fn parse(tokens: &[Token], i: &mut usize) -> Result<Option<Self>, ParseError> {
-        if let Token::String(string) = &tokens[*i] {
+        if let Some(Token::String(string)) = tokens.get(*i) {
             *i += 1;
             Ok(Some(string.clone()))
         } else {
@@ -171,7 +175,7 @@ pub struct TokenParenL {
 
 impl Parse for TokenParenL {
     fn parse(tokens: &[Token], i: &mut usize) -> Result<Option<Self>, ParseError> {
-        if let Token::ParenL(paren_l) = &tokens[*i] {
+        if let Some(Token::ParenL(paren_l)) = tokens.get(*i) {
             *i += 1;
             Ok(Some(paren_l.clone()))
         } else {
@@ -187,7 +191,7 @@ pub struct TokenParenR {
 
 impl Parse for TokenParenR {
     fn parse(tokens: &[Token], i: &mut usize) -> Result<Option<Self>, ParseError> {
-        if let Token::ParenR(paren_r) = &tokens[*i] {
+        if let Some(Token::ParenR(paren_r)) = tokens.get(*i) {
             *i += 1;
             Ok(Some(paren_r.clone()))
         } else {
@@ -213,7 +217,7 @@ pub struct TokenBraceL {
 
 impl Parse for TokenBraceL {
     fn parse(tokens: &[Token], i: &mut usize) -> Result<Option<Self>, ParseError> {
-        if let Token::BraceL(brace_l) = &tokens[*i] {
+        if let Some(Token::BraceL(brace_l)) = tokens.get(*i) {
             *i += 1;
             Ok(Some(brace_l.clone()))
         } else {
@@ -229,7 +233,7 @@ pub struct TokenBraceR {
 
 impl Parse for TokenBraceR {
     fn parse(tokens: &[Token], i: &mut usize) -> Result<Option<Self>, ParseError> {
-        if let Token::BraceR(brace_r) = &tokens[*i] {
+        if let Some(Token::BraceR(brace_r)) = tokens.get(*i) {
             *i += 1;
             Ok(Some(brace_r.clone()))
         } else {
@@ -245,7 +249,7 @@ pub struct TokenComma {
 
 impl Parse for TokenComma {
     fn parse(tokens: &[Token], i: &mut usize) -> Result<Option<Self>, ParseError> {
-        if let Token::Comma(comma) = &tokens[*i] {
+        if let Some(Token::Comma(comma)) = tokens.get(*i) {
             *i += 1;
             Ok(Some(comma.clone()))
         } else {
@@ -261,7 +265,7 @@ pub struct TokenColon {
 
 impl Parse for TokenColon {
     fn parse(tokens: &[Token], i: &mut usize) -> Result<Option<Self>, ParseError> {
-        if let Token::Colon(colon) = &tokens[*i] {
+        if let Some(Token::Colon(colon)) = tokens.get(*i) {
             *i += 1;
             Ok(Some(colon.clone()))
         } else {
@@ -277,7 +281,7 @@ pub struct TokenColon2 {
 
 impl Parse for TokenColon2 {
     fn parse(tokens: &[Token], i: &mut usize) -> Result<Option<Self>, ParseError> {
-        if let Token::Colon2(colon2) = &tokens[*i] {
+        if let Some(Token::Colon2(colon2)) = tokens.get(*i) {
             *i += 1;
             Ok(Some(colon2.clone()))
         } else {
@@ -293,7 +297,7 @@ pub struct TokenSemicolon {
 
 impl Parse for TokenSemicolon {
     fn parse(tokens: &[Token], i: &mut usize) -> Result<Option<TokenSemicolon>, ParseError> {
-        if let Token::Semicolon(semicolon) = &tokens[*i] {
+        if let Some(Token::Semicolon(semicolon)) = tokens.get(*i) {
             *i += 1;
             Ok(Some(semicolon.clone()))
         } else {
@@ -302,6 +306,49 @@ impl Parse for TokenSemicolon {
     }
 }
 
+/// `?name`, a hole left in a term. Anonymous holes `?` have an empty name.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TokenHole {
+    pos: Pos,
+    s: String,
+}
+
+impl TokenHole {
+    pub fn s(&self) -> &str {
+        &self.s
+    }
+
+    pub fn pos(&self) -> &Pos {
+        &self.pos
+    }
+}
+
+impl Parse for TokenHole {
+    fn parse(tokens: &[Token], i: &mut usize) -> Result<Option<Self>, ParseError> {
+        if let Some(Token::Hole(hole)) = tokens.get(*i) {
+            *i += 1;
+            Ok(Some(hole.clone()))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// A character that starts no token
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LexError {
+    pub pos: Pos,
+    pub c: char,
+}
+
+impl std::fmt::Display for LexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Unexpected character `{}` at {}", self.c, self.pos)
+    }
+}
+
+impl std::error::Error for LexError {}
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Token {
     Keyword(TokenKeyword),
@@ -319,6 +366,7 @@ pub enum Token {
     Colon2(TokenColon2),
     Semicolon(TokenSemicolon),
     String(TokenString),
+    Hole(TokenHole),
 }
 
 fn is_operator_char(c: char) -> bool {
@@ -330,7 +378,34 @@ fn is_reserved_keyword(_s: &str) -> bool {
 }
 
 impl Token {
+    pub fn pos(&self) -> &Pos {
+        match self {
+            Token::Keyword(token) => &token.pos,
+            Token::Variable(token) => &token.pos,
+            Token::Operator(token) => &token.pos,
+            Token::Number(token) => &token.pos,
+            Token::ParenL(token) => &token.pos,
+            Token::ParenR(token) => &token.pos,
+            Token::BracketL(token) => &token.pos,
+            Token::BracketR(token) => &token.pos,
+            Token::BraceL(token) => &token.pos,
+            Token::BraceR(token) => &token.pos,
+            Token::Comma(token) => &token.pos,
+            Token::Colon(token) => &token.pos,
+            Token::Colon2(token) => &token.pos,
+            Token::Semicolon(token) => &token.pos,
+            Token::String(token) => &token.pos,
+            Token::Hole(token) => &token.pos,
+        }
+    }
+
+    /// Lexes `s`, panicking on a character that starts no token. Input that
+    /// may be malformed goes through `try_lex`.
     pub fn lex(s: &str, file_id: FileId) -> Vec<Token> {
+        Self::try_lex(s, file_id).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    pub fn try_lex(s: &str, file_id: FileId) -> Result<Vec<Token>, LexError> {
         let cs: Vec<_> = s.chars().collect();
 
         let mut tokens = vec![];
@@ -346,10 +421,12 @@ impl Token {
                 continue;
             }
 
-            while cs[i].is_whitespace() {
+            while i < cs.len() && cs[i].is_whitespace() {
                 i += 1;
                 column += 1;
-                continue;
+            }
+            if i == cs.len() {
+                break;
             }
 
             // TokenString
@@ -366,7 +443,9 @@ impl Token {
                     if cs[i] == '\\' {
                         i += 1;
                         column += 1;
-                        buf.push(cs[i]);
+                        if let Some(&c) = cs.get(i) {
+                            buf.push(c);
+                        }
                         i += 1;
                         column += 1;
                     } else {
@@ -560,6 +639,28 @@ impl Token {
                 continue;
             }
 
+            // TokenHole
+            if cs[i] == '?' {
+                let token_line = line;
+                let token_column = column;
+                i += 1;
+                column += 1;
+                let mut buf = String::new();
+                while i < cs.len() && (cs[i].is_ascii_alphanumeric() || cs[i] == '_') {
+                    buf.push(cs[i]);
+                    i += 1;
+                    column += 1;
+                }
+
+                let token = Token::Hole(TokenHole {
+                    pos: Pos::new(file_id, token_line, token_column),
+                    s: buf,
+                });
+
+                tokens.push(token);
+                continue;
+            }
+
             // TokenNumber
             if cs[i].is_numeric() {
                 let token_line = line;
@@ -631,10 +732,13 @@ impl Token {
                 continue;
             }
 
-            panic!("line = {line}, column = {column}");
+            return Err(LexError {
+                pos: Pos::new(file_id, line, column),
+                c: cs[i],
+            });
         }
 
-        tokens
+        Ok(tokens)
     }
 }
 
@@ -665,6 +769,26 @@ mod test {
         assert_debug_snapshot!(tokens);
     }
 
+    #[test]
+    fn test_lex_reports_unexpected_characters() {
+        let mut file_id_generator = FileIdGenerator::new();
+        let file_id = file_id_generator.generate_file_id();
+        for (s, c, column) in [("#definition x : nat { ! }", '!', 23), ("x → y", '→', 3)] {
+            assert_eq!(
+                Token::try_lex(s, file_id),
+                Err(LexError {
+                    pos: Pos::new(file_id, 1, column),
+                    c
+                }),
+                "{s}"
+            );
+        }
+        assert_eq!(
+            Token::try_lex("a\nλx", file_id).unwrap_err().to_string(),
+            "Unexpected character `λ` at 2:1"
+        );
+    }
+
     #[test]
     fn test_mut_keyword() {
         let mut file_id_generator = FileIdGenerator::new();
@@ -680,4 +804,38 @@ mod test {
             _ => panic!("Expected #mut to be tokenized as a keyword"),
         }
     }
+    #[test]
+    fn test_lex_holes() {
+        let mut file_id_generator = FileIdGenerator::new();
+        let file_id = file_id_generator.generate_file_id();
+        let s = "f ?goal ?";
+        let tokens = Token::lex(s, file_id);
+
+        assert_eq!(tokens.len(), 3);
+        let Token::Hole(hole) = &tokens[1] else {
+            panic!("Expected ?goal to be tokenized as a hole");
+        };
+        assert_eq!(hole.s(), "goal");
+        assert_eq!(hole.pos().column(), 3);
+        assert!(matches!(&tokens[2], Token::Hole(hole) if hole.s().is_empty()));
+    }
+
+    #[test]
+    fn test_lex_truncated() {
+        let mut file_id_generator = FileIdGenerator::new();
+        let file_id = file_id_generator.generate_file_id();
+        let s = "#use_builtin \"a\\\"b\" #as x;  \n  #proc f : ";
+        let cs: Vec<_> = s.chars().collect();
+        for end in 0..=cs.len() {
+            let prefix: String = cs[..end].iter().collect();
+            Token::lex(&prefix, file_id);
+        }
+
+        let tokens = Token::lex(s, file_id);
+        let Token::String(string) = &tokens[1] else {
+            panic!("Expected a string");
+        };
+        assert_eq!(string.s(), "a\"b");
+        assert_eq!(tokens.len(), 8);
+    }
 }
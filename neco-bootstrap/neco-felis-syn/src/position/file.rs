@@ -251,6 +251,26 @@ mod test {
         assert!(found_proc, "main proc not found");
     }
 
+    #[test]
+    fn test_parse_truncated_files() {
+        // Every prefix of a file either parses or is an error, without
+        // reading past the last token
+        let mut file_id_generator = FileIdGenerator::new();
+        for entry in std::fs::read_dir("../../testcases/felis/single").unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_none_or(|extension| extension != "fe") {
+                continue;
+            }
+            let s = std::fs::read_to_string(&path).unwrap();
+            let tokens = Token::lex(&s, file_id_generator.generate_file_id());
+            for end in 0..tokens.len() {
+                let mut i = 0;
+                let _ = File::parse(&tokens[..end], &mut i);
+                assert!(i <= end, "{}: read past token {end}", path.display());
+            }
+        }
+    }
+
     #[test]
     fn test_parse_array_len() {
         let mut file_id_generator = FileIdGenerator::new();
@@ -1,6 +1,7 @@
 use crate::{
-    Parse, ParseError, Phase, PhaseParse, TermApply, TermArrowDep, TermArrowNodep, TermMatch,
-    TermNumber, TermParen, TermStruct, TermUnit, TermVariable, token::Token,
+    Parse, ParseError, Phase, PhaseParse, TermApply, TermArrowDep, TermArrowImplicit,
+    TermArrowNodep, TermBy, TermFieldAccess, TermHole, TermMatch, TermNumber, TermParen,
+    TermStruct, TermUnit, TermVariable, token::Token,
 };
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -8,12 +9,16 @@ pub enum Term<P: Phase> {
     Paren(TermParen<P>),
     ArrowNodep(TermArrowNodep<P>),
     ArrowDep(TermArrowDep<P>),
+    ArrowImplicit(TermArrowImplicit<P>),
     Apply(TermApply<P>),
     Variable(TermVariable<P>),
     Match(TermMatch<P>),
     Unit(TermUnit<P>),
     Number(TermNumber<P>),
     Struct(TermStruct<P>),
+    Hole(TermHole<P>),
+    By(TermBy<P>),
+    FieldAccess(TermFieldAccess<P>),
 }
 
 impl Parse for Term<PhaseParse> {
@@ -22,10 +27,18 @@ impl Parse for Term<PhaseParse> {
             return Ok(Some(Term::Match(term_match)));
         }
 
+        if let Some(term_by) = TermBy::parse(tokens, i)? {
+            return Ok(Some(Term::By(term_by)));
+        }
+
         if let Some(term_arrow_dep) = TermArrowDep::parse(tokens, i)? {
             return Ok(Some(Term::ArrowDep(term_arrow_dep)));
         }
 
+        if let Some(term_arrow_implicit) = TermArrowImplicit::parse(tokens, i)? {
+            return Ok(Some(Term::ArrowImplicit(term_arrow_implicit)));
+        }
+
         if let Some(term_arrow_nodep) = TermArrowNodep::parse(tokens, i)? {
             return Ok(Some(Term::ArrowNodep(term_arrow_nodep)));
         }
@@ -34,6 +47,10 @@ impl Parse for Term<PhaseParse> {
             return Ok(Some(Term::Apply(term_apply)));
         }
 
+        if let Some(term_field_access) = TermFieldAccess::parse(tokens, i)? {
+            return Ok(Some(Term::FieldAccess(term_field_access)));
+        }
+
         if let Some(term_struct) = TermStruct::parse(tokens, i)? {
             return Ok(Some(Term::Struct(term_struct)));
         }
@@ -46,6 +63,10 @@ impl Parse for Term<PhaseParse> {
             return Ok(Some(Term::Number(term_number)));
         }
 
+        if let Some(term_hole) = TermHole::parse(tokens, i)? {
+            return Ok(Some(Term::Hole(term_hole)));
+        }
+
         if let Some(term_unit) = TermUnit::parse(tokens, i)? {
             return Ok(Some(Term::Unit(term_unit)));
         }
@@ -1,6 +1,6 @@
 use crate::{
-    Parse, ParseError, Phase, PhaseParse, Term, TermNumber, TermParen, TermUnit, TermVariable,
-    token::Token,
+    Parse, ParseError, Phase, PhaseParse, Term, TermFieldAccess, TermHole, TermNumber, TermParen,
+    TermUnit, TermVariable, token::Token,
 };
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -53,6 +53,8 @@ enum TermForApplyElem {
     Variable(TermVariable<PhaseParse>),
     Unit(TermUnit<PhaseParse>),
     Number(TermNumber<PhaseParse>),
+    Hole(TermHole<PhaseParse>),
+    FieldAccess(TermFieldAccess<PhaseParse>),
 }
 
 impl From<TermForApplyElem> for Term<PhaseParse> {
@@ -62,6 +64,10 @@ impl From<TermForApplyElem> for Term<PhaseParse> {
             TermForApplyElem::Variable(term_variable) => Term::Variable(term_variable),
             TermForApplyElem::Unit(term_unit) => Term::Unit(term_unit),
             TermForApplyElem::Number(term_number) => Term::Number(term_number),
+            TermForApplyElem::Hole(term_hole) => Term::Hole(term_hole),
+            TermForApplyElem::FieldAccess(term_field_access) => {
+                Term::FieldAccess(term_field_access)
+            }
         }
     }
 }
@@ -72,6 +78,10 @@ impl Parse for TermForApplyElem {
             return Ok(Some(TermForApplyElem::Unit(term_unit)));
         }
 
+        if let Some(term_field_access) = TermFieldAccess::parse(tokens, i)? {
+            return Ok(Some(TermForApplyElem::FieldAccess(term_field_access)));
+        }
+
         if let Some(term_paren) = TermParen::parse(tokens, i)? {
             return Ok(Some(TermForApplyElem::Paren(term_paren)));
         }
@@ -84,6 +94,10 @@ impl Parse for TermForApplyElem {
             return Ok(Some(TermForApplyElem::Number(term_number)));
         }
 
+        if let Some(term_hole) = TermHole::parse(tokens, i)? {
+            return Ok(Some(TermForApplyElem::Hole(term_hole)));
+        }
+
         Ok(None)
     }
 }
@@ -0,0 +1,118 @@
+use crate::{
+    Parse, ParseError, Phase, PhaseParse, Term, TermVariable,
+    token::{Token, TokenBraceL, TokenBraceR, TokenColon, TokenOperator},
+};
+
+/// Dependent arrow with an implicit binder: `{A : Set} -> T`
+///
+/// Arguments for implicit binders are omitted at application sites and
+/// inferred by the elaborator.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TermArrowImplicit<P: Phase> {
+    pub brace_l: TokenBraceL,
+    pub from: TermVariable<P>,
+    pub colon: TokenColon,
+    pub from_ty: Box<Term<P>>,
+    pub brace_r: TokenBraceR,
+    pub arrow: TokenOperator,
+    pub to: Box<Term<P>>,
+    pub ext: P::TermArrowImplicitExt,
+}
+
+impl<P: Phase> TermArrowImplicit<P> {
+    pub fn from(&self) -> &TermVariable<P> {
+        &self.from
+    }
+
+    pub fn from_ty(&self) -> &Term<P> {
+        &self.from_ty
+    }
+
+    pub fn to(&self) -> &Term<P> {
+        &self.to
+    }
+}
+
+impl Parse for TermArrowImplicit<PhaseParse> {
+    fn parse(tokens: &[Token], i: &mut usize) -> Result<Option<Self>, ParseError> {
+        let mut k = *i;
+
+        let Some(brace_l) = TokenBraceL::parse(tokens, &mut k)? else {
+            return Ok(None);
+        };
+
+        let Some(from) = TermVariable::parse(tokens, &mut k)? else {
+            return Ok(None);
+        };
+
+        let Some(colon) = TokenColon::parse(tokens, &mut k)? else {
+            return Ok(None);
+        };
+
+        let Some(from_ty) = Term::parse(tokens, &mut k)? else {
+            return Ok(None);
+        };
+
+        let Some(brace_r) = TokenBraceR::parse(tokens, &mut k)? else {
+            return Ok(None);
+        };
+
+        let Some(arrow) = TokenOperator::parse_operator(tokens, &mut k, "->")? else {
+            return Ok(None);
+        };
+
+        let Some(to) = Term::parse(tokens, &mut k)? else {
+            return Err(ParseError::Unknown("term_arrow_implicit_1"));
+        };
+
+        let term_arrow_implicit = TermArrowImplicit {
+            brace_l,
+            from,
+            colon,
+            from_ty: Box::new(from_ty),
+            brace_r,
+            arrow,
+            to: Box::new(to),
+            ext: (),
+        };
+
+        *i = k;
+        Ok(Some(term_arrow_implicit))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FileIdGenerator;
+
+    #[test]
+    fn test_parse_implicit_arrow() {
+        let mut file_id_generator = FileIdGenerator::new();
+        let file_id = file_id_generator.generate_file_id();
+        // As in a definition, the type is followed by the brace of the body
+        let s = "{A : Set} -> (x : A) -> eq x x {";
+        let tokens = Token::lex(s, file_id);
+
+        let mut i = 0;
+        let Some(Term::ArrowImplicit(arrow)) = Term::parse(&tokens, &mut i).unwrap() else {
+            panic!("expected an implicit arrow");
+        };
+        assert_eq!(i, tokens.len() - 1);
+        assert_eq!(arrow.from().variable().s(), "A");
+        assert!(matches!(arrow.to(), Term::ArrowDep(_)));
+    }
+
+    #[test]
+    fn test_braces_without_binder_are_not_an_implicit_arrow() {
+        let mut file_id_generator = FileIdGenerator::new();
+        let file_id = file_id_generator.generate_file_id();
+        let s = "{ x }";
+        let tokens = Token::lex(s, file_id);
+
+        let mut i = 0;
+        let result = TermArrowImplicit::parse(&tokens, &mut i).unwrap();
+        assert!(result.is_none());
+        assert_eq!(i, 0);
+    }
+}
@@ -0,0 +1,105 @@
+use crate::{
+    Parse, ParseError, Phase, PhaseParse, TermByTactic,
+    token::{Token, TokenBraceL, TokenBraceR, TokenKeyword},
+};
+
+/// `#by { tactic; ... }`, a proof built by running tactics on its goal
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TermBy<P: Phase> {
+    pub keyword_by: TokenKeyword,
+    pub brace_l: TokenBraceL,
+    pub tactics: Vec<TermByTactic<P>>,
+    pub brace_r: TokenBraceR,
+    pub ext: P::TermByExt,
+}
+
+impl<P: Phase> TermBy<P> {
+    pub fn keyword_by(&self) -> &TokenKeyword {
+        &self.keyword_by
+    }
+
+    pub fn tactics(&self) -> &[TermByTactic<P>] {
+        &self.tactics
+    }
+}
+
+impl Parse for TermBy<PhaseParse> {
+    fn parse(tokens: &[Token], i: &mut usize) -> Result<Option<Self>, ParseError> {
+        let mut k = *i;
+
+        let Some(keyword_by) = TokenKeyword::parse_keyword(tokens, &mut k, "by")? else {
+            return Ok(None);
+        };
+
+        let Some(brace_l) = TokenBraceL::parse(tokens, &mut k)? else {
+            return Err(ParseError::Unknown("expected { after #by"));
+        };
+
+        // Tactics are separated by semicolons, the last one may omit it
+        let mut tactics = vec![];
+        while let Some(tactic) = TermByTactic::parse(tokens, &mut k)? {
+            let last = tactic.semicolon.is_none();
+            tactics.push(tactic);
+            if last {
+                break;
+            }
+        }
+
+        let Some(brace_r) = TokenBraceR::parse(tokens, &mut k)? else {
+            return Err(ParseError::Unknown("expected ; or } after tactic"));
+        };
+
+        let term_by = TermBy {
+            keyword_by,
+            brace_l,
+            tactics,
+            brace_r,
+            ext: (),
+        };
+
+        *i = k;
+        Ok(Some(term_by))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FileIdGenerator, Term};
+
+    #[test]
+    fn test_parse_by() {
+        let mut file_id_generator = FileIdGenerator::new();
+        let file_id = file_id_generator.generate_file_id();
+        let s = "#by { intro n m; induction n; simpl; rewrite add_zero m; reflexivity; }";
+        let tokens = Token::lex(s, file_id);
+
+        let mut i = 0;
+        let by = TermBy::parse(&tokens, &mut i).unwrap().unwrap();
+        assert_eq!(i, tokens.len());
+        let names: Vec<_> = by
+            .tactics()
+            .iter()
+            .map(|tactic| tactic.name().s())
+            .collect();
+        assert_eq!(
+            names,
+            ["intro", "induction", "simpl", "rewrite", "reflexivity"]
+        );
+        assert!(matches!(by.tactics()[0].arg(), Some(Term::Apply(_))));
+        assert!(by.tactics()[2].arg().is_none());
+    }
+
+    #[test]
+    fn test_parse_by_without_trailing_semicolon() {
+        let mut file_id_generator = FileIdGenerator::new();
+        let file_id = file_id_generator.generate_file_id();
+        let s = "#by { exact eq_refl O }";
+        let tokens = Token::lex(s, file_id);
+
+        let mut i = 0;
+        let by = TermBy::parse(&tokens, &mut i).unwrap().unwrap();
+        assert_eq!(i, tokens.len());
+        assert_eq!(by.tactics().len(), 1);
+    }
+}
@@ -0,0 +1,60 @@
+use crate::{
+    Parse, ParseError, Phase, PhaseParse, Term,
+    token::{Token, TokenSemicolon, TokenVariable},
+};
+
+/// A tactic of a `#by` block: its name and an optional term argument, as in
+/// `intro n`, `rewrite H` or `simpl`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TermByTactic<P: Phase> {
+    pub name: TokenVariable,
+    pub arg: Option<Box<Term<P>>>,
+    pub semicolon: Option<TokenSemicolon>,
+    pub ext: P::TermByTacticExt,
+}
+
+impl<P: Phase> TermByTactic<P> {
+    pub fn name(&self) -> &TokenVariable {
+        &self.name
+    }
+
+    pub fn arg(&self) -> Option<&Term<P>> {
+        self.arg.as_deref()
+    }
+}
+
+impl Parse for TermByTactic<PhaseParse> {
+    fn parse(tokens: &[Token], i: &mut usize) -> Result<Option<Self>, ParseError> {
+        let mut k = *i;
+
+        let Some(name) = TokenVariable::parse(tokens, &mut k)? else {
+            return Ok(None);
+        };
+
+        let arg = match tokens.get(k) {
+            Some(Token::Semicolon(_) | Token::BraceR(_)) | None => None,
+            Some(_) => {
+                let Some(arg) = Term::parse(tokens, &mut k)? else {
+                    return Err(ParseError::Unknown("expected term as tactic argument"));
+                };
+                Some(Box::new(arg))
+            }
+        };
+
+        let semicolon = if k < tokens.len() {
+            TokenSemicolon::parse(tokens, &mut k)?
+        } else {
+            None
+        };
+
+        let tactic = TermByTactic {
+            name,
+            arg,
+            semicolon,
+            ext: (),
+        };
+
+        *i = k;
+        Ok(Some(tactic))
+    }
+}
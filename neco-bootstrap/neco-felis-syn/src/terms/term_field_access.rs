@@ -0,0 +1,106 @@
+use crate::{
+    Parse, ParseError, Phase, PhaseParse, Term, TermVariable,
+    token::{Token, TokenOperator, TokenVariable},
+};
+
+/// The projection `object.field` of a field of a `#structure` value
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TermFieldAccess<P: Phase> {
+    pub object: Box<Term<P>>,
+    pub dot: TokenOperator,
+    pub field: TokenVariable,
+    pub ext: P::TermFieldAccessExt,
+}
+
+impl<P: Phase> TermFieldAccess<P> {
+    pub fn object(&self) -> &Term<P> {
+        &self.object
+    }
+
+    pub fn field(&self) -> &TokenVariable {
+        &self.field
+    }
+}
+
+impl Parse for TermFieldAccess<PhaseParse> {
+    fn parse(tokens: &[Token], i: &mut usize) -> Result<Option<Self>, ParseError> {
+        let mut k = *i;
+
+        // Only variables, so that parenthesized terms are not parsed twice
+        let Some(term_variable) = TermVariable::parse(tokens, &mut k)? else {
+            return Ok(None);
+        };
+        let mut object = Term::Variable(term_variable);
+
+        let mut accesses = vec![];
+        while k < tokens.len() {
+            let Some(dot) =
+                TokenOperator::parse_operator_after_non_whitespace(tokens, &mut k, ".")?
+            else {
+                break;
+            };
+            if k >= tokens.len() {
+                return Err(ParseError::Unknown("term_field_access_1"));
+            }
+            let Some(field) = TokenVariable::parse(tokens, &mut k)? else {
+                return Err(ParseError::Unknown("term_field_access_1"));
+            };
+            accesses.push((dot, field));
+        }
+
+        // `p.x.y` is `(p.x).y`
+        let Some((dot, field)) = accesses.pop() else {
+            return Ok(None);
+        };
+        for (dot, field) in accesses {
+            object = Term::FieldAccess(TermFieldAccess {
+                object: Box::new(object),
+                dot,
+                field,
+                ext: (),
+            });
+        }
+        let term_field_access = TermFieldAccess {
+            object: Box::new(object),
+            dot,
+            field,
+            ext: (),
+        };
+
+        *i = k;
+        Ok(Some(term_field_access))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FileIdGenerator;
+
+    #[test]
+    fn test_parse_nested_field_access() {
+        let mut file_id_generator = FileIdGenerator::new();
+        let file_id = file_id_generator.generate_file_id();
+        let tokens = Token::lex("s.start.x", file_id);
+
+        let mut i = 0;
+        let access = TermFieldAccess::parse(&tokens, &mut i).unwrap().unwrap();
+        assert_eq!(i, tokens.len());
+        assert_eq!(access.field().s(), "x");
+        let Term::FieldAccess(inner) = access.object() else {
+            panic!("expected a field access, found {:?}", access.object());
+        };
+        assert_eq!(inner.field().s(), "start");
+    }
+
+    #[test]
+    fn test_variable_alone_is_not_a_field_access() {
+        let mut file_id_generator = FileIdGenerator::new();
+        let file_id = file_id_generator.generate_file_id();
+        let tokens = Token::lex("p", file_id);
+
+        let mut i = 0;
+        assert!(TermFieldAccess::parse(&tokens, &mut i).unwrap().is_none());
+        assert_eq!(i, 0);
+    }
+}
@@ -0,0 +1,36 @@
+use crate::{
+    Parse, ParseError, Phase, PhaseParse, Pos,
+    token::{Token, TokenHole},
+};
+
+/// `?name`, a placeholder whose expected type is reported as a goal
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TermHole<P: Phase> {
+    pub hole: TokenHole,
+    pub ext: P::TermHoleExt,
+}
+
+impl<P: Phase> TermHole<P> {
+    pub fn name(&self) -> &str {
+        self.hole.s()
+    }
+
+    pub fn pos(&self) -> &Pos {
+        self.hole.pos()
+    }
+}
+
+impl Parse for TermHole<PhaseParse> {
+    fn parse(tokens: &[Token], i: &mut usize) -> Result<Option<Self>, ParseError> {
+        let mut k = *i;
+
+        let Some(hole) = TokenHole::parse(tokens, &mut k)? else {
+            return Ok(None);
+        };
+
+        let term_hole = TermHole { hole, ext: () };
+
+        *i = k;
+        Ok(Some(term_hole))
+    }
+}
@@ -44,7 +44,8 @@ impl Parse for TermMatch<PhaseParse> {
             return Err(ParseError::Unknown("expected { after match scrutinee"));
         };
 
-        // Parse branches
+        // Parse branches, none for a match on an empty type such as a proof
+        // of False
         let mut branches = vec![];
         while let Some(branch) = TermMatchBranch::parse(tokens, &mut k)? {
             branches.push(branch);
@@ -55,12 +56,6 @@ impl Parse for TermMatch<PhaseParse> {
             }
         }
 
-        if branches.is_empty() {
-            return Err(ParseError::Unknown(
-                "match expression must have at least one branch",
-            ));
-        }
-
         // Parse closing brace
         let Some(brace_r) = TokenBraceR::parse(tokens, &mut k)? else {
             return Err(ParseError::Unknown("expected } to close match expression"));
@@ -0,0 +1,23 @@
+use neco_felis_type_check::Repl;
+
+/// `neco repl [files]` starts an interactive session, after loading the
+/// items of the files
+fn main() -> std::io::Result<()> {
+    let args: Vec<_> = std::env::args().collect();
+    if args.get(1).map(String::as_str) != Some("repl") {
+        eprintln!("Usage: {} repl [files]", args[0]);
+        std::process::exit(1);
+    }
+
+    let mut repl = Repl::new();
+    for path in &args[2..] {
+        match repl.eval(&format!(":load {path}")) {
+            Ok(message) => println!("{message}"),
+            Err(message) => eprintln!("error: {message}"),
+        }
+    }
+    println!("Type :help for the commands");
+    let stdin = std::io::stdin();
+    let stdout = std::io::stdout();
+    repl.run(stdin.lock(), stdout.lock())
+}
@@ -0,0 +1,99 @@
+use neco_cic::{
+    id::Id,
+    term::Term,
+    u64_spec::{self, Nat},
+    well_founded,
+};
+use neco_felis_syn::{ItemUseBuiltin, PhaseParse, Pos};
+
+use crate::{TypeCheckError, TypeCheckErrorKind, TypeChecker};
+
+impl TypeChecker {
+    /// `#use_builtin "Acc" #as Acc;` declares the accessibility predicate
+    /// `Acc` with its constructor `Acc_intro`, and `#use_builtin
+    /// "well_founded" #as well_founded;` the well-founded relations, both with
+    /// their type argument implicit. `#use_builtin "nat" #as nat;` declares
+    /// the natural numbers `nat` with the constructors `O` and `S` and their
+    /// addition `nat_add` and multiplication `nat_mul`, which the
+    /// specification of the u64 operations is stated with. The primitives of
+    /// the kernel are declared by `process_primitive`, and the other builtins
+    /// are primitives of the compiler.
+    pub(crate) fn process_use_builtin(
+        &mut self,
+        use_builtin: &ItemUseBuiltin<PhaseParse>,
+    ) -> Result<(), TypeCheckError> {
+        let name = use_builtin.name.s();
+        let pos = use_builtin.name.pos();
+        self.procedures
+            .add_builtin(name, use_builtin.builtin_name.s());
+        match use_builtin.builtin_name.s() {
+            "Acc" => {
+                let acc = self.declare(name);
+                let intro = self.declare(&format!("{name}_intro"));
+                well_founded::add_acc(&mut self.global_env, acc, intro, &mut self.id_gen)
+                    .map_err(|e| Self::environment_error(e, pos))?;
+                let inductive = self.global_env.inductives.get_inductive(acc).unwrap();
+                let (arity, intro_type) =
+                    (inductive.get_type(), inductive.constructors[0].ty.clone());
+                self.name_binders(&arity, &["A", "R", "x"], 1);
+                self.name_binders(&intro_type, &["A", "R", "x", "h"], 1);
+                self.builtins.insert("Acc", acc);
+            }
+            "well_founded" => {
+                let acc = self.builtin("Acc", pos)?;
+                let id = self.declare(name);
+                well_founded::add_well_founded(&mut self.global_env, id, acc, &mut self.id_gen)
+                    .map_err(|e| Self::environment_error(e, pos))?;
+                let ty = self.global_env.get_constant(id).unwrap().ty.clone();
+                self.name_binders(&ty, &["A", "R"], 1);
+            }
+            "nat" => {
+                let nat = Nat {
+                    nat: self.declare(name),
+                    zero: self.declare("O"),
+                    succ: self.declare("S"),
+                    add: self.declare(&format!("{name}_add")),
+                    mul: self.declare(&format!("{name}_mul")),
+                };
+                u64_spec::add_nat(&mut self.global_env, nat, &mut self.id_gen)
+                    .map_err(|e| Self::environment_error(e, pos))?;
+                for id in [nat.add, nat.mul] {
+                    let ty = self.global_env.get_constant(id).unwrap().ty.clone();
+                    self.name_binders(&ty, &["n", "m"], 0);
+                }
+                self.builtins.extend([
+                    ("nat", nat.nat),
+                    ("nat_zero", nat.zero),
+                    ("nat_succ", nat.succ),
+                    ("nat_add", nat.add),
+                    ("nat_mul", nat.mul),
+                ]);
+                self.declare_u64_spec(pos)?;
+            }
+            builtin => self.process_primitive(builtin, name, pos)?,
+        }
+        Ok(())
+    }
+
+    /// Names the binders of a builtin type, the first `implicit` ones being
+    /// implicit
+    pub(crate) fn name_binders(&mut self, ty: &Term, names: &[&str], implicit: usize) {
+        let mut ty = ty;
+        for (i, name) in names.iter().enumerate() {
+            let Term::Product(product) = ty else {
+                break;
+            };
+            self.names.insert(product.var, name.to_string());
+            if i < implicit {
+                self.implicit_binders.insert(product.var);
+            }
+            ty = &product.target;
+        }
+    }
+
+    pub(crate) fn builtin(&self, name: &'static str, pos: &Pos) -> Result<Id, TypeCheckError> {
+        self.builtins.get(name).copied().ok_or_else(|| {
+            TypeCheckError::new(TypeCheckErrorKind::MissingBuiltin(name), Some(pos.clone()))
+        })
+    }
+}
@@ -0,0 +1,378 @@
+use std::rc::Rc;
+
+use neco_cic::{
+    global_environment::ConstantDefinition,
+    id::Id,
+    inductive::ConstructorDefinition,
+    primitive::Operation,
+    reduction::whnf_with_env,
+    substitution::{abstract_variables, has_loose_bound, open},
+    term::{
+        Sort, Term, TermApplication, TermBound, TermConstant, TermLiteral, TermProduct, TermSort,
+    },
+};
+use neco_felis_syn::{ItemProc, PhaseParse, Pos};
+
+use crate::{TypeCheckError, TypeChecker, elaborate::Elaborator};
+
+/// A `#requires` or `#ensures` condition of a procedure, a proposition on
+/// the parameters, and on the result for `#ensures`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Contract {
+    /// The function `{proc}.requires.{k}` or `{proc}.ensures.{k}` of the
+    /// parameters, and of the result for `#ensures`, returning a nonzero
+    /// `u64` when the condition holds. Only the conditions decided by
+    /// comparing integers have one, and can be checked at runtime.
+    pub function: Option<String>,
+    pub ensures: bool,
+    /// The elaborated condition, printed with source names
+    pub condition: String,
+    pub pos: Pos,
+}
+
+impl TypeChecker {
+    /// The contracts of the procedure `proc`, in source order
+    pub fn contracts(&self, proc: &str) -> &[Contract] {
+        self.contracts.get(proc).map_or(&[], Vec::as_slice)
+    }
+
+    /// Checks the contracts of a procedure against its signature, elaborated
+    /// as a Felis type when the procedure has contracts, and defines the
+    /// functions deciding them
+    pub(crate) fn process_contracts(
+        &mut self,
+        proc: &ItemProc<PhaseParse>,
+    ) -> Result<(), TypeCheckError> {
+        let mut contracts = Vec::new();
+        for (k, contract) in proc.contracts.iter().enumerate() {
+            let pos = contract.keyword().pos();
+            let ensures = contract.is_ensures();
+            let result = self.id_gen.generate_id();
+
+            let mut elab = Elaborator::new(self);
+            let proc_type = elab.elaborate_type(&proc.ty)?;
+            let mut parameter_ids = Vec::new();
+            let mut result_type = proc_type.clone();
+            for name in &Self::parameter_names(&proc.ty) {
+                let Term::Product(product) = &result_type else {
+                    break;
+                };
+                elab.push_local(name.as_deref(), product.var, product.source.clone());
+                parameter_ids.push(product.var);
+                result_type = open(&product.target, product.var);
+            }
+            if ensures {
+                elab.push_local(Some("result"), result, Rc::new(result_type));
+                parameter_ids.push(result);
+            }
+            let prop = Term::Sort(TermSort { sort: Sort::Prop });
+            let condition = elab.check(contract.condition(), &prop)?;
+            elab.finish()?;
+            let condition = elab.zonk(&condition);
+            let proc_type = elab.zonk(&proc_type);
+
+            let function = format!(
+                "{}.{}.{k}",
+                proc.name.s(),
+                if ensures { "ensures" } else { "requires" }
+            );
+            let decided = match (self.decide(&condition), self.global_env.primitives.u64) {
+                (Some(decision), Some(u64)) => {
+                    let u64 = Term::Constant(TermConstant { id: u64 });
+                    // The function takes the parameters of the procedure,
+                    // then its result, for a postcondition
+                    let parameters = parameter_ids.len() - usize::from(ensures);
+                    let ty = Self::replace_codomain(&proc_type, parameters, &|codomain| {
+                        if ensures {
+                            Term::Product(TermProduct {
+                                var: result,
+                                source: Rc::new(codomain.clone()),
+                                target: Rc::new(u64.clone()),
+                            })
+                        } else {
+                            u64.clone()
+                        }
+                    });
+                    let body = Self::create_lambda_from_product(
+                        &ty,
+                        abstract_variables(&decision, &parameter_ids),
+                        parameter_ids.len(),
+                    );
+                    let id = self.declare(&function);
+                    self.global_env
+                        .add_constant(ConstantDefinition {
+                            name: id,
+                            body: Some(Rc::new(body)),
+                            ty: Rc::new(ty),
+                            opaque: false,
+                        })
+                        .map_err(|e| Self::environment_error(e, pos))?;
+                    Some(function)
+                }
+                _ => None,
+            };
+            contracts.push(Contract {
+                function: decided,
+                ensures,
+                condition: self.print(&condition),
+                pos: pos.clone(),
+            });
+        }
+        if !contracts.is_empty() {
+            self.contracts.insert(proc.name.s().to_string(), contracts);
+        }
+        Ok(())
+    }
+
+    /// A `u64` term that is nonzero exactly when the proposition `prop`
+    /// holds, if `prop` unfolds to an equality of `u64` values, to an empty
+    /// or a trivial proposition such as `False` and `True`, or to an
+    /// implication between such propositions, negations included
+    fn decide(&mut self, prop: &Term) -> Option<Term> {
+        let prop = whnf_with_env(prop, &self.global_env);
+        if let Term::Product(product) = &prop
+            && !has_loose_bound(&product.target)
+        {
+            // `P -> Q` holds unless `P` holds and `Q` does not
+            let premise = self.decide(&product.source)?;
+            let conclusion = self.decide(&product.target)?;
+            let sub = self.operation(Operation::Sub)?;
+            let mul = self.operation(Operation::Mul)?;
+            let not = |term| apply(&sub, vec![literal(1), term]);
+            return Some(not(apply(&mul, vec![premise, not(conclusion)])));
+        }
+        let (head, args) = match &prop {
+            Term::Application(app) => (app.f.as_ref(), app.args.as_slice()),
+            prop => (prop, &[][..]),
+        };
+        let Term::Constant(constant) = head else {
+            return None;
+        };
+        let inductive = self.global_env.inductives.get_inductive(constant.id)?;
+        match (inductive.constructors.as_slice(), args) {
+            ([], []) => Some(literal(0)),
+            ([constructor], []) if constructor.arity == 0 => Some(literal(1)),
+            ([constructor], [.., ty, x, y]) if is_equality(constant.id, constructor) => {
+                let u64 = self.global_env.primitives.u64?;
+                if !matches!(whnf_with_env(ty, &self.global_env),
+                    Term::Constant(ty) if ty.id == u64)
+                {
+                    return None;
+                }
+                let eq = self.operation(Operation::Eq)?;
+                Some(apply(&eq, vec![x.clone(), y.clone()]))
+            }
+            _ => None,
+        }
+    }
+
+    /// The constant computing `operation`, declared for the decisions of the
+    /// contracts if no `#use_builtin` declared it
+    fn operation(&mut self, operation: Operation) -> Option<Term> {
+        let id = match self.builtins.get(operation.builtin()) {
+            Some(id) => *id,
+            None => {
+                self.global_env.primitives.u64?;
+                let id = self.declare(&format!("contract.{}", operation.builtin()));
+                self.global_env
+                    .add_u64_operation(id, operation, &mut self.id_gen)
+                    .ok()?;
+                self.builtins.insert(operation.builtin(), id);
+                id
+            }
+        };
+        Some(Term::Constant(TermConstant { id }))
+    }
+
+    /// Replaces what is left of a product type after `count` parameters
+    fn replace_codomain(ty: &Term, count: usize, replace: &dyn Fn(&Term) -> Term) -> Term {
+        match ty {
+            Term::Product(product) if count > 0 => Term::Product(TermProduct {
+                var: product.var,
+                source: product.source.clone(),
+                target: Rc::new(Self::replace_codomain(&product.target, count - 1, replace)),
+            }),
+            _ => replace(ty),
+        }
+    }
+}
+
+/// Whether `constructor` is the reflexivity of an equality `inductive`,
+/// `(A : Set) -> (x : A) -> inductive A x x`, possibly with more indices
+/// before the type
+fn is_equality(inductive: Id, constructor: &ConstructorDefinition) -> bool {
+    let bound = |index| Term::Bound(TermBound { index });
+    let mut conclusion = constructor.ty.as_ref();
+    let mut binders = 0;
+    // The type of `x` is the binder before it
+    let mut typed = false;
+    while let Term::Product(product) = conclusion {
+        typed = *product.source == bound(0);
+        conclusion = &product.target;
+        binders += 1;
+    }
+    let Term::Application(app) = conclusion else {
+        return false;
+    };
+    binders >= 2
+        && typed
+        && matches!(app.f.as_ref(), Term::Constant(c) if c.id == inductive)
+        && app.args.len() == binders + 1
+        && app.args[..binders]
+            .iter()
+            .enumerate()
+            .all(|(i, arg)| *arg == bound(binders - 1 - i))
+        && app.args[binders] == bound(0)
+}
+
+fn apply(f: &Term, args: Vec<Term>) -> Term {
+    Term::Application(TermApplication {
+        f: Rc::new(f.clone()),
+        args,
+    })
+}
+
+fn literal(value: u64) -> Term {
+    Term::Literal(TermLiteral { value })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use neco_cic::{
+        nbe::normalize,
+        term::{Term, TermApplication, TermConstant},
+    };
+
+    use super::literal;
+    use crate::{TypeCheckErrorKind, TypeChecker};
+
+    const PRELUDE: &str = "
+#use_builtin \"u64\" #as __u64;
+#use_builtin \"u64_div\" #as __u64_div;
+#use_builtin \"u64_mul\" #as __u64_mul;
+
+#inductive eq : {A : Set} -> (x : A) -> A -> Prop {
+    eq_refl: {A : Set} -> (x : A) -> eq x x,
+}
+
+#inductive False : Prop {
+}
+
+#definition not : (P : Prop) -> Prop {
+    P -> False
+}
+";
+
+    /// Applies the function of a contract to literals
+    fn decide(type_checker: &TypeChecker, function: &str, args: &[u64]) -> Term {
+        let term = Term::Application(TermApplication {
+            f: Rc::new(Term::Constant(TermConstant {
+                id: type_checker.name_to_id[function],
+            })),
+            args: args.iter().map(|arg| literal(*arg)).collect(),
+        });
+        normalize(&term, &type_checker.global_env)
+    }
+
+    #[test]
+    fn test_contracts_are_checked_against_the_signature() {
+        let source = format!(
+            "{PRELUDE}
+#proc div_safe : (a : __u64) -> (b : __u64) -> __u64
+    #requires not (eq b 0)
+    #ensures eq (__u64_mul result b) a
+{{
+    __u64_div a b
+}}
+"
+        );
+        let type_checker = crate::tests::check_source(&source).unwrap();
+        let contracts = type_checker.contracts("div_safe");
+        assert_eq!(contracts.len(), 2);
+        assert_eq!(
+            contracts[0].function.as_deref(),
+            Some("div_safe.requires.0")
+        );
+        assert_eq!(contracts[0].condition, "not (eq __u64 b 0)");
+        assert!(contracts[1].ensures);
+        assert_eq!(contracts[1].function.as_deref(), Some("div_safe.ensures.1"));
+        assert!(type_checker.contracts("main").is_empty());
+
+        // The functions decide the propositions
+        assert_eq!(
+            decide(&type_checker, "div_safe.requires.0", &[84, 2]),
+            literal(1)
+        );
+        assert_eq!(
+            decide(&type_checker, "div_safe.requires.0", &[84, 0]),
+            literal(0)
+        );
+        assert_eq!(
+            decide(&type_checker, "div_safe.ensures.1", &[84, 2, 42]),
+            literal(1)
+        );
+        assert_eq!(
+            decide(&type_checker, "div_safe.ensures.1", &[1, 2, 0]),
+            literal(0)
+        );
+    }
+
+    #[test]
+    fn test_undecidable_contracts_are_only_checked_statically() {
+        let source = format!(
+            "{PRELUDE}
+#proc f : (a : __u64) -> __u64 #requires (x : __u64) -> eq x a {{
+    a
+}}
+"
+        );
+        let type_checker = crate::tests::check_source(&source).unwrap();
+        let contracts = type_checker.contracts("f");
+        assert_eq!(contracts[0].condition, "(x : __u64) -> eq __u64 x a");
+        assert_eq!(contracts[0].function, None);
+    }
+
+    #[test]
+    fn test_ill_typed_contracts_are_rejected() {
+        let source = format!(
+            "{PRELUDE}
+#proc f : (a : __u64) -> __u64 #requires not (eq c 0) {{
+    a
+}}
+"
+        );
+        assert_eq!(
+            crate::tests::check_source(&source)
+                .err()
+                .map(|e| e.kind().clone()),
+            Some(TypeCheckErrorKind::UnknownVariable("c".to_string()))
+        );
+
+        // Conditions are propositions, not integers
+        let source = format!(
+            "{PRELUDE}
+#proc f : (a : __u64) -> __u64 #requires a {{
+    a
+}}
+"
+        );
+        assert!(matches!(
+            crate::tests::check_source(&source)
+                .err()
+                .map(|e| e.kind().clone()),
+            Some(TypeCheckErrorKind::TypeMismatch { .. })
+        ));
+
+        // `result` is only bound in postconditions
+        let source = format!(
+            "{PRELUDE}
+#proc f : (a : __u64) -> __u64 #requires eq result a {{
+    a
+}}
+"
+        );
+        assert!(crate::tests::check_source(&source).is_err());
+    }
+}
@@ -0,0 +1,1278 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use neco_cic::{
+    conversion,
+    id::Id,
+    local_context::LocalContext,
+    reduction,
+    substitution::{
+        Substitution, abstract_variable, abstract_variables, instantiate, instantiate_many, open,
+        substitute,
+    },
+    term::{
+        Sort, Term, TermApplication, TermConstant, TermLambda, TermLetIn, TermLiteral, TermMatch,
+        TermMatchBranch, TermProduct, TermSort, TermVariable,
+    },
+    typechecker,
+};
+use neco_felis_syn::{
+    Pattern, PhaseParse, Pos, Term as FTerm, TermFieldAccess as FTermFieldAccess,
+    TermHole as FTermHole, TermMatch as FTermMatch,
+    token::{TokenNumber, TokenVariable},
+};
+
+use neco_cic::pretty::Printer;
+
+mod tactic;
+
+use crate::{
+    TypeChecker,
+    error::{TypeCheckError, TypeCheckErrorKind},
+    goal::Goal,
+};
+
+// Elaboration turns surface terms into kernel terms, filling in what the
+// user left out. Omitted arguments for implicit binders `{A : Set}` become
+// metavariables, which are solved by unification while the term is checked
+// against its expected type.
+//
+// A metavariable created under local variables `x1 .. xn` stands for a
+// function of them and appears applied to them, `?m x1 .. xn`. Equations
+// `?m y1 .. yk = t` where the `yi` are distinct local variables (Miller's
+// pattern fragment) have the unique solution `?m := fun y1 .. yk => t`.
+// Other equations are postponed until more metavariables are known.
+//
+// Metavariables are free variables of the kernel. Once an item is elaborated
+// they are all substituted away, and the kernel never sees them.
+
+/// A metavariable of the item being elaborated
+struct Meta {
+    /// Expected type, in terms of the local variables in scope where the
+    /// metavariable was created
+    ty: Term,
+    /// Position of the term that left the metavariable behind
+    pos: Option<Pos>,
+    solution: Option<Rc<Term>>,
+    hole: Option<Hole>,
+}
+
+/// A hole `?name` written in the source. Holes are reported as goals rather
+/// than as unsolved metavariables.
+struct Hole {
+    name: String,
+    /// Local variables in scope at the hole
+    locals: Vec<Local>,
+}
+
+/// A local variable in scope, `None` for variables that cannot be named
+#[derive(Clone)]
+struct Local {
+    name: Option<String>,
+    id: Id,
+}
+
+/// Elaborator for the terms of a single item
+pub(crate) struct Elaborator<'a> {
+    checker: &'a mut TypeChecker,
+    /// Types of the constants of the item itself, which are not in the
+    /// environment yet
+    pending: HashMap<Id, Rc<Term>>,
+    locals: Vec<Local>,
+    /// Types of the local variables, including those introduced by unification
+    var_types: HashMap<Id, Rc<Term>>,
+    metas: Vec<Meta>,
+    meta_index: HashMap<Id, usize>,
+    /// Equations outside of the pattern fragment, retried at the end
+    postponed: Vec<(Term, Term, Option<Pos>)>,
+    /// The constant being defined, which tactic proofs may recurse on
+    item: Option<Id>,
+    /// Whether a tactic proof used the item to prove induction hypotheses
+    recursive: bool,
+}
+
+impl<'a> Elaborator<'a> {
+    pub(crate) fn new(checker: &'a mut TypeChecker) -> Self {
+        Self {
+            checker,
+            pending: HashMap::new(),
+            locals: Vec::new(),
+            var_types: HashMap::new(),
+            metas: Vec::new(),
+            meta_index: HashMap::new(),
+            postponed: Vec::new(),
+            item: None,
+            recursive: false,
+        }
+    }
+
+    /// Makes the constant being defined available to its own body, for
+    /// recursive calls and induction hypotheses
+    pub(crate) fn set_item(&mut self, id: Id, ty: Rc<Term>) {
+        self.item = Some(id);
+        self.add_pending(id, ty);
+    }
+
+    /// Whether the elaborated term refers to the item through induction
+    pub(crate) fn is_recursive(&self) -> bool {
+        self.recursive
+    }
+
+    /// Makes a constant of the item available before it is added to the
+    /// environment, e.g. for recursive calls
+    pub(crate) fn add_pending(&mut self, id: Id, ty: Rc<Term>) {
+        self.pending.insert(id, ty);
+    }
+
+    /// Brings a local variable into scope
+    pub(crate) fn push_local(&mut self, name: Option<&str>, id: Id, ty: Rc<Term>) {
+        if let Some(name) = name {
+            self.checker.names.insert(id, name.to_string());
+        }
+        self.var_types.insert(id, ty);
+        self.locals.push(Local {
+            name: name.map(str::to_string),
+            id,
+        });
+    }
+
+    /// Drops the local variables pushed after the scope had `len` of them
+    pub(crate) fn truncate_locals(&mut self, len: usize) {
+        self.locals.truncate(len);
+    }
+
+    /// Elaborates a term that must be a type
+    pub(crate) fn elaborate_type(
+        &mut self,
+        term: &FTerm<PhaseParse>,
+    ) -> Result<Term, TypeCheckError> {
+        Ok(self.infer_sort(term)?.0)
+    }
+
+    /// Elaborates a term against an expected type
+    pub(crate) fn check(
+        &mut self,
+        term: &FTerm<PhaseParse>,
+        expected: &Term,
+    ) -> Result<Term, TypeCheckError> {
+        match term {
+            FTerm::Paren(paren) => self.check(paren.term(), expected),
+            FTerm::Match(match_expr) => self.elaborate_match(match_expr, expected),
+            FTerm::Hole(hole) => Ok(self.new_hole(hole, expected.clone())),
+            FTerm::By(by) => self.elaborate_by(by, expected),
+            _ => {
+                let (elaborated, ty) = self.infer(term)?;
+                let pos = term_pos(term);
+                if !self.unify(&ty, expected, &pos) {
+                    return Err(self.mismatch(expected, &ty, &pos));
+                }
+                Ok(elaborated)
+            }
+        }
+    }
+
+    /// Infers the type of a term. Unlike in applications, a name standing
+    /// alone keeps its implicit arguments abstract.
+    pub(crate) fn infer_term(
+        &mut self,
+        term: &FTerm<PhaseParse>,
+    ) -> Result<(Term, Term), TypeCheckError> {
+        match term {
+            FTerm::Variable(var) => self.infer_variable(var.variable()),
+            FTerm::Paren(paren) => self.infer_term(paren.term()),
+            _ => self.infer(term),
+        }
+    }
+
+    /// Elaborates the proof `wf : well_founded R` of a definition of type
+    /// `(x : A) -> T` by well-founded recursion, returning it with the
+    /// relation `R : A -> A -> Prop` the first parameter decreases along
+    pub(crate) fn elaborate_well_founded(
+        &mut self,
+        proof: &FTerm<PhaseParse>,
+        name: &str,
+        ty: &Term,
+        acc: Id,
+    ) -> Result<(Term, Term), TypeCheckError> {
+        let pos = term_pos(proof);
+        let Term::Product(product) = ty else {
+            return Err(TypeCheckError::new(
+                TypeCheckErrorKind::NotAFunction {
+                    term: name.to_string(),
+                    ty: self.print(ty),
+                },
+                pos,
+            ));
+        };
+        let domain = product.source.clone();
+        let [left, right, a] = [(); 3].map(|_| self.checker.id_gen.generate_id());
+        let relation_ty = Term::Product(TermProduct {
+            var: left,
+            source: domain.clone(),
+            target: Rc::new(Term::Product(TermProduct {
+                var: right,
+                source: domain.clone(),
+                target: Rc::new(Term::Sort(TermSort { sort: Sort::Prop })),
+            })),
+        });
+        let relation = self.new_meta(relation_ty, pos);
+
+        // well_founded R unfolds to (a : A) -> Acc A R a
+        let accessible = apply(
+            Term::Constant(TermConstant { id: acc }),
+            vec![
+                domain.as_ref().clone(),
+                relation.clone(),
+                Term::Variable(TermVariable { id: a }),
+            ],
+        );
+        let expected = Term::Product(TermProduct {
+            var: a,
+            source: domain,
+            target: Rc::new(abstract_variable(&accessible, a)),
+        });
+        let proof = self.check(proof, &expected)?;
+        Ok((proof, relation))
+    }
+
+    /// Solves the postponed equations and reports unsolved metavariables and
+    /// holes as errors
+    pub(crate) fn finish(&mut self) -> Result<(), TypeCheckError> {
+        match self.finish_with_goals()?.into_iter().next() {
+            Some(goal) => Err(TypeCheckError::new(
+                TypeCheckErrorKind::UnfilledHole {
+                    name: goal.name,
+                    ty: goal.target,
+                },
+                Some(goal.pos),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Solves the postponed equations and reports unsolved metavariables,
+    /// returning the goals of the holes
+    pub(crate) fn finish_with_goals(&mut self) -> Result<Vec<Goal>, TypeCheckError> {
+        loop {
+            let solved = self.solved_count();
+            for (a, b, pos) in std::mem::take(&mut self.postponed) {
+                if !self.unify(&a, &b, &pos) {
+                    return Err(self.mismatch(&a, &b, &pos));
+                }
+            }
+            if self.postponed.is_empty() || self.solved_count() == solved {
+                break;
+            }
+        }
+
+        if let Some((a, b, pos)) = self.postponed.first() {
+            return Err(TypeCheckError::new(
+                TypeCheckErrorKind::CannotSolve {
+                    lhs: self.print(a),
+                    rhs: self.print(b),
+                },
+                pos.clone(),
+            ));
+        }
+
+        // The first unsolved metavariable is reported, later ones often
+        // depend on it
+        if let Some((index, meta)) = self
+            .metas
+            .iter()
+            .enumerate()
+            .find(|(_, meta)| meta.solution.is_none() && meta.hole.is_none())
+        {
+            return Err(TypeCheckError::new(
+                TypeCheckErrorKind::UnsolvedMeta {
+                    index,
+                    ty: self.print(&meta.ty),
+                },
+                meta.pos.clone(),
+            ));
+        }
+        Ok(self.goals())
+    }
+
+    fn goals(&self) -> Vec<Goal> {
+        self.metas
+            .iter()
+            .enumerate()
+            .filter_map(|(index, meta)| {
+                let hole = meta.hole.as_ref()?;
+                let name = if hole.name.is_empty() {
+                    index.to_string()
+                } else {
+                    hole.name.clone()
+                };
+                Some(Goal {
+                    name,
+                    pos: meta.pos.clone()?,
+                    hypotheses: self.hypotheses(&hole.locals),
+                    target: self.print(&meta.ty),
+                })
+            })
+            .collect()
+    }
+
+    /// The named local variables of a goal with their printed types
+    fn hypotheses(&self, locals: &[Local]) -> Vec<(String, String)> {
+        locals
+            .iter()
+            .filter_map(|local| {
+                let ty = self.print(&self.var_types[&local.id]);
+                Some((local.name.clone()?, ty))
+            })
+            .collect()
+    }
+
+    /// Substitutes the solved metavariables of `term`
+    pub(crate) fn zonk(&self, term: &Term) -> Term {
+        match term {
+            Term::Variable(var) => match self.solution(var.id) {
+                Some(solution) => self.zonk(&solution),
+                None => term.clone(),
+            },
+            Term::Sort(_) | Term::Bound(_) | Term::Constant(_) | Term::Literal(_) => term.clone(),
+            Term::Product(product) => Term::Product(TermProduct {
+                var: product.var,
+                source: Rc::new(self.zonk(&product.source)),
+                target: Rc::new(self.zonk(&product.target)),
+            }),
+            Term::Lambda(lambda) => Term::Lambda(TermLambda {
+                var: lambda.var,
+                source_ty: Rc::new(self.zonk(&lambda.source_ty)),
+                target: Rc::new(self.zonk(&lambda.target)),
+            }),
+            Term::Application(app) => {
+                let f = self.zonk(&app.f);
+                let args = app.args.iter().map(|arg| self.zonk(arg)).collect();
+                // Only the solutions of metavariables are β-reduced: a λ
+                // applied in the term itself is kept for the kernel
+                if matches!(app.f.as_ref(), Term::Lambda(_)) {
+                    return Term::Application(TermApplication {
+                        f: Rc::new(f),
+                        args,
+                    });
+                }
+                apply(f, args)
+            }
+            Term::LetIn(let_in) => Term::LetIn(TermLetIn {
+                var: let_in.var,
+                term: Rc::new(self.zonk(&let_in.term)),
+                ty: Rc::new(self.zonk(&let_in.ty)),
+                body: Rc::new(self.zonk(&let_in.body)),
+            }),
+            Term::Match(case) => Term::Match(TermMatch {
+                scrutinee: Rc::new(self.zonk(&case.scrutinee)),
+                return_type: Rc::new(self.zonk(&case.return_type)),
+                branches: case
+                    .branches
+                    .iter()
+                    .map(|branch| TermMatchBranch {
+                        constructor_id: branch.constructor_id,
+                        bound_vars: branch.bound_vars.clone(),
+                        body: Rc::new(self.zonk(&branch.body)),
+                    })
+                    .collect(),
+            }),
+        }
+    }
+
+    /// A number literal, such as `42` or `42u64`, is a literal of the
+    /// primitive type declared by `#use_builtin "u64"`
+    fn infer_number(&self, number: &TokenNumber) -> Result<(Term, Term), TypeCheckError> {
+        let digits = number.s().strip_suffix("u64").unwrap_or(number.s());
+        let value = digits.replace('_', "").parse().map_err(|_| {
+            TypeCheckError::new(
+                TypeCheckErrorKind::InvalidLiteral(number.s().to_string()),
+                Some(number.pos().clone()),
+            )
+        })?;
+        let u64 = self.checker.builtin("u64", number.pos())?;
+        Ok((
+            Term::Literal(TermLiteral { value }),
+            Term::Constant(TermConstant { id: u64 }),
+        ))
+    }
+
+    pub(crate) fn infer(
+        &mut self,
+        term: &FTerm<PhaseParse>,
+    ) -> Result<(Term, Term), TypeCheckError> {
+        match term {
+            FTerm::Variable(var) => {
+                let (head, ty) = self.infer_variable(var.variable())?;
+                let mut args = Vec::new();
+                let ty = self.insert_implicits(&mut args, ty, &term_pos(term));
+                Ok((apply(head, args), ty))
+            }
+            FTerm::Apply(apply_expr) => {
+                let pos = term_pos(apply_expr.f());
+                let (f, mut ty) = self.infer(apply_expr.f())?;
+                let (head, mut args) = match f {
+                    Term::Application(app) => (app.f.as_ref().clone(), app.args),
+                    f => (f, Vec::new()),
+                };
+                for arg in apply_expr.args() {
+                    ty = self.insert_implicits(&mut args, ty, &pos);
+                    let Term::Product(product) = self.whnf(&ty) else {
+                        return Err(TypeCheckError::new(
+                            TypeCheckErrorKind::NotAFunction {
+                                term: self.print(&apply(head, args)),
+                                ty: self.print(&ty),
+                            },
+                            pos,
+                        ));
+                    };
+                    let arg = self.check(arg, &product.source)?;
+                    ty = instantiate(&product.target, &arg);
+                    args.push(arg);
+                }
+                let ty = self.insert_implicits(&mut args, ty, &pos);
+                Ok((apply(head, args), ty))
+            }
+            FTerm::ArrowDep(arrow) => {
+                self.infer_product(arrow.from().variable(), arrow.from_ty(), arrow.to(), false)
+            }
+            FTerm::ArrowImplicit(arrow) => {
+                self.infer_product(arrow.from().variable(), arrow.from_ty(), arrow.to(), true)
+            }
+            FTerm::ArrowNodep(arrow) => {
+                let dummy_var = self.checker.id_gen.generate_id();
+                let (source, source_sort) = self.infer_sort(arrow.from())?;
+                let (target, target_sort) = self.infer_sort(arrow.to())?;
+                let sort = typechecker::sort_rule(&source_sort, &target_sort)
+                    .map_err(|e| self.kernel_error(e, term_pos(term)))?;
+                Ok((
+                    Term::Product(TermProduct {
+                        var: dummy_var,
+                        source: Rc::new(source),
+                        target: Rc::new(target),
+                    }),
+                    Term::Sort(TermSort { sort }),
+                ))
+            }
+            FTerm::Paren(paren) => self.infer(paren.term()),
+            FTerm::FieldAccess(access) => self.infer_field_access(access),
+            FTerm::Hole(hole) => Err(TypeCheckError::new(
+                TypeCheckErrorKind::CannotInferHole(hole.name().to_string()),
+                Some(hole.pos().clone()),
+            )),
+            FTerm::By(by) => Err(TypeCheckError::new(
+                TypeCheckErrorKind::CannotInferBy,
+                Some(by.keyword_by().pos().clone()),
+            )),
+            FTerm::Match(match_expr) => Err(TypeCheckError::new(
+                TypeCheckErrorKind::CannotInferMatch,
+                Some(match_expr.scrutinee().pos().clone()),
+            )),
+            FTerm::Unit(_term_unit) => Err(TypeCheckError::new(
+                TypeCheckErrorKind::Unsupported("Unit terms"),
+                None,
+            )),
+            FTerm::Number(term_number) => self.infer_number(&term_number.number),
+            FTerm::Struct(_item_struct) => {
+                // For now, struct expressions are not supported in the type checker
+                // They should be handled at the compilation level
+                Err(TypeCheckError::new(
+                    TypeCheckErrorKind::Unsupported("Struct expressions"),
+                    None,
+                ))
+            }
+        }
+    }
+
+    /// `p.x` is the projection `S_x p` of the structure `S` of `p`
+    fn infer_field_access(
+        &mut self,
+        access: &FTermFieldAccess<PhaseParse>,
+    ) -> Result<(Term, Term), TypeCheckError> {
+        let (object, object_ty) = self.infer(access.object())?;
+        let object_ty = self.whnf(&object_ty);
+        let field = access.field();
+        let projection = match &object_ty {
+            Term::Constant(const_) => self
+                .checker
+                .structures
+                .get(&const_.id)
+                .and_then(|fields| fields.iter().find(|(name, _)| name == field.s()))
+                .map(|(_, projection)| *projection),
+            _ => None,
+        };
+        let projection_ty = projection.and_then(|projection| self.constant_type(projection));
+        let (Some(projection), Some(projection_ty)) = (projection, projection_ty) else {
+            return Err(TypeCheckError::new(
+                TypeCheckErrorKind::UnknownField {
+                    field: field.s().to_string(),
+                    ty: self.print(&object_ty),
+                },
+                Some(field.pos().clone()),
+            ));
+        };
+        let Term::Product(product) = projection_ty.as_ref() else {
+            unreachable!("projections are functions of the structure");
+        };
+        let ty = instantiate(&product.target, &object);
+        Ok((
+            apply(
+                Term::Constant(TermConstant { id: projection }),
+                vec![object],
+            ),
+            ty,
+        ))
+    }
+
+    fn infer_variable(&mut self, var: &TokenVariable) -> Result<(Term, Term), TypeCheckError> {
+        let name = var.s();
+
+        // Handle built-in types
+        let sort = match name {
+            "Type" => Some(Sort::Type(0)),
+            "Set" => Some(Sort::Set),
+            "Prop" => Some(Sort::Prop),
+            _ => None,
+        };
+        if let Some(sort) = sort {
+            let term = Term::Sort(TermSort { sort });
+            let ty = typechecker::infer_type(&LocalContext::new(), &self.checker.global_env, &term)
+                .map_err(|e| self.kernel_error(e, Some(var.pos().clone())))?;
+            return Ok((term, ty.as_ref().clone()));
+        }
+
+        self.resolve(name).ok_or_else(|| {
+            TypeCheckError::new(
+                TypeCheckErrorKind::UnknownVariable(name.to_string()),
+                Some(var.pos().clone()),
+            )
+        })
+    }
+
+    /// Resolves a name to a local variable or a global constant, with its type
+    fn resolve(&self, name: &str) -> Option<(Term, Term)> {
+        if let Some(local) = self
+            .locals
+            .iter()
+            .rev()
+            .find(|local| local.name.as_deref() == Some(name))
+        {
+            let ty = self.var_types[&local.id].as_ref().clone();
+            return Some((Term::Variable(TermVariable { id: local.id }), ty));
+        }
+        let id = *self.checker.name_to_id.get(name)?;
+        let ty = self.constant_type(id)?;
+        Some((Term::Constant(TermConstant { id }), ty.as_ref().clone()))
+    }
+
+    fn constant_type(&self, id: Id) -> Option<Rc<Term>> {
+        if let Some(ty) = self.pending.get(&id) {
+            return Some(ty.clone());
+        }
+        typechecker::infer_type(
+            &LocalContext::new(),
+            &self.checker.global_env,
+            &Term::Constant(TermConstant { id }),
+        )
+        .ok()
+    }
+
+    fn infer_product(
+        &mut self,
+        from: &TokenVariable,
+        from_ty: &FTerm<PhaseParse>,
+        to: &FTerm<PhaseParse>,
+        implicit: bool,
+    ) -> Result<(Term, Term), TypeCheckError> {
+        let var_id = self.checker.id_gen.generate_id();
+        if implicit {
+            self.checker.implicit_binders.insert(var_id);
+        }
+
+        let (source, source_sort) = self.infer_sort(from_ty)?;
+        let scope = self.locals.len();
+        self.push_local(Some(from.s()), var_id, Rc::new(source.clone()));
+        let target = self.infer_sort(to);
+        self.truncate_locals(scope);
+        let (target, target_sort) = target?;
+
+        let sort = typechecker::sort_rule(&source_sort, &target_sort)
+            .map_err(|e| self.kernel_error(e, Some(from.pos().clone())))?;
+        Ok((
+            Term::Product(TermProduct {
+                var: var_id,
+                source: Rc::new(source),
+                target: Rc::new(abstract_variable(&target, var_id)),
+            }),
+            Term::Sort(TermSort { sort }),
+        ))
+    }
+
+    fn infer_sort(&mut self, term: &FTerm<PhaseParse>) -> Result<(Term, Sort), TypeCheckError> {
+        let (elaborated, ty) = self.infer(term)?;
+        match self.whnf(&ty) {
+            Term::Sort(sort) => Ok((elaborated, sort.sort)),
+            ty => Err(TypeCheckError::new(
+                TypeCheckErrorKind::NotAType {
+                    term: self.print(&elaborated),
+                    ty: self.print(&ty),
+                },
+                term_pos(term),
+            )),
+        }
+    }
+
+    /// Applies `args` to metavariables for the implicit binders at the head
+    /// of `ty`, returning the type of the application
+    fn insert_implicits(&mut self, args: &mut Vec<Term>, ty: Term, pos: &Option<Pos>) -> Term {
+        let mut ty = self.zonk(&ty);
+        while let Term::Product(product) = &ty
+            && self.checker.implicit_binders.contains(&product.var)
+        {
+            let meta = self.new_meta(product.source.as_ref().clone(), pos.clone());
+            ty = instantiate(&product.target, &meta);
+            args.push(meta);
+        }
+        ty
+    }
+
+    fn elaborate_match(
+        &mut self,
+        match_expr: &FTermMatch<PhaseParse>,
+        expected: &Term,
+    ) -> Result<Term, TypeCheckError> {
+        let scrutinee_name = match_expr.scrutinee().s();
+        let pos = Some(match_expr.scrutinee().pos().clone());
+        let (scrutinee, scrutinee_ty) = self.resolve(scrutinee_name).ok_or_else(|| {
+            TypeCheckError::new(
+                TypeCheckErrorKind::UnknownVariable(scrutinee_name.to_string()),
+                pos.clone(),
+            )
+        })?;
+
+        let scrutinee_ty = self.whnf(&scrutinee_ty);
+        let (head, ty_args) = match &scrutinee_ty {
+            Term::Application(app) => (app.f.as_ref(), app.args.as_slice()),
+            ty => (ty, &[][..]),
+        };
+        let inductive = match head {
+            Term::Constant(const_) => self
+                .checker
+                .global_env
+                .inductives
+                .get_inductive(const_.id)
+                .map(|inductive| (inductive.name, inductive.parameters.len())),
+            _ => None,
+        };
+        let (inductive, parameter_count) = inductive.ok_or_else(|| {
+            TypeCheckError::new(
+                TypeCheckErrorKind::NotInductive {
+                    scrutinee: scrutinee_name.to_string(),
+                    ty: self.print(&scrutinee_ty),
+                },
+                pos.clone(),
+            )
+        })?;
+        let parameters = &ty_args[..parameter_count.min(ty_args.len())];
+
+        // The sort of the expected type is only known once its metavariables
+        // are solved; the kernel checks the others
+        if let Some(sort) = self.sort_of(expected) {
+            typechecker::check_elimination(&self.checker.global_env, inductive, &sort).map_err(
+                |_| {
+                    TypeCheckError::new(
+                        TypeCheckErrorKind::IllegalElimination {
+                            inductive: self.print(head),
+                            sort: self.print(&Term::Sort(TermSort { sort })),
+                        },
+                        pos.clone(),
+                    )
+                },
+            )?;
+        }
+
+        let mut branches = Vec::new();
+        for branch in match_expr.branches() {
+            let (constructor, arg_names) = match branch.pattern() {
+                Pattern::Variable(var) => (var, &[][..]),
+                Pattern::Constructor(constructor, args) => (constructor, args.as_slice()),
+            };
+            let constructor_name = constructor.s();
+            let unknown = || {
+                TypeCheckError::new(
+                    TypeCheckErrorKind::UnknownConstructor(constructor_name.to_string()),
+                    Some(constructor.pos().clone()),
+                )
+            };
+            let constructor_id = *self
+                .checker
+                .name_to_id
+                .get(constructor_name)
+                .ok_or_else(unknown)?;
+            let mut ty = self
+                .constant_type(constructor_id)
+                .ok_or_else(unknown)?
+                .as_ref()
+                .clone();
+            for parameter in parameters {
+                if let Term::Product(product) = &ty {
+                    ty = instantiate(&product.target, parameter);
+                }
+            }
+
+            let explicit_arity = self.explicit_arity(&ty);
+            if arg_names.len() != explicit_arity {
+                return Err(TypeCheckError::new(
+                    TypeCheckErrorKind::ConstructorArity {
+                        constructor: constructor_name.to_string(),
+                        expected: explicit_arity,
+                        found: arg_names.len(),
+                    },
+                    Some(constructor.pos().clone()),
+                ));
+            }
+
+            // Explicit constructor arguments are named by the pattern, implicit
+            // ones are bound without a name
+            let scope = self.locals.len();
+            let mut names = arg_names.iter();
+            let mut bound_vars = Vec::new();
+            while let Term::Product(product) = &ty {
+                let name = if self.checker.implicit_binders.contains(&product.var) {
+                    None
+                } else {
+                    names.next().map(TokenVariable::s)
+                };
+                let arg_id = self.checker.id_gen.generate_id();
+                self.push_local(name, arg_id, product.source.clone());
+                bound_vars.push(arg_id);
+                ty = open(&product.target, arg_id);
+            }
+
+            // A match on a local variable refines it to the pattern in the
+            // branch, in its expected type and in the types of the locals
+            let refined = self.refine(&scrutinee, || {
+                let args = parameters
+                    .iter()
+                    .cloned()
+                    .chain(
+                        bound_vars
+                            .iter()
+                            .map(|id| Term::Variable(TermVariable { id: *id })),
+                    )
+                    .collect();
+                apply(Term::Constant(TermConstant { id: constructor_id }), args)
+            });
+            let body = match &refined {
+                Some((substitution, _)) => {
+                    self.check(branch.body(), &self.refined(expected, substitution))
+                }
+                None => self.check(branch.body(), expected),
+            };
+            if let Some((_, types)) = refined {
+                self.var_types.extend(types);
+            }
+            self.truncate_locals(scope);
+            branches.push(TermMatchBranch {
+                constructor_id,
+                body: Rc::new(abstract_variables(&body?, &bound_vars)),
+                bound_vars,
+            });
+        }
+
+        Ok(Term::Match(TermMatch {
+            scrutinee: Rc::new(scrutinee),
+            return_type: Rc::new(expected.clone()),
+            branches,
+        }))
+    }
+
+    /// Substitutes `value` for `scrutinee` if it is a local variable, in the
+    /// types of the locals in scope. Returns the substitution and the types
+    /// it replaced, to be restored after the branch.
+    #[allow(clippy::type_complexity)]
+    fn refine(
+        &mut self,
+        scrutinee: &Term,
+        value: impl FnOnce() -> Term,
+    ) -> Option<(Substitution, Vec<(Id, Rc<Term>)>)> {
+        let Term::Variable(var) = scrutinee else {
+            return None;
+        };
+        if !self.locals.iter().any(|local| local.id == var.id) {
+            return None;
+        }
+        let mut substitution = Substitution::new();
+        substitution.add(var.id, Rc::new(value()));
+
+        let mut replaced = Vec::new();
+        for local in &self.locals {
+            let ty = &self.var_types[&local.id];
+            let refined = self.refined(ty, &substitution);
+            if refined != *ty.as_ref() {
+                replaced.push((local.id, ty.clone()));
+            }
+        }
+        for (id, ty) in &replaced {
+            let refined = self.refined(ty, &substitution);
+            self.var_types.insert(*id, Rc::new(refined));
+        }
+        Some((substitution, replaced))
+    }
+
+    /// A type with a refined variable substituted, unless it has unsolved
+    /// metavariables: those are applied to the variable, and substituting it
+    /// would take their equations out of the pattern fragment
+    fn refined(&self, ty: &Term, substitution: &Substitution) -> Term {
+        let ty = self.zonk(ty);
+        let is_meta = |id| self.is_meta(id);
+        if any_variable(&ty, &is_meta) {
+            return ty;
+        }
+        substitute(&ty, substitution)
+    }
+
+    /// Number of explicit binders of a constructor type
+    fn explicit_arity(&self, ty: &Term) -> usize {
+        match ty {
+            Term::Product(product) => {
+                let explicit = !self.checker.implicit_binders.contains(&product.var);
+                usize::from(explicit) + self.explicit_arity(&product.target)
+            }
+            _ => 0,
+        }
+    }
+
+    /// Creates a metavariable of type `ty`, applied to the local variables
+    fn new_meta(&mut self, ty: Term, pos: Option<Pos>) -> Term {
+        let id = self.checker.id_gen.generate_id();
+        let index = self.metas.len();
+        self.checker.names.insert(id, format!("?{index}"));
+        self.meta_index.insert(id, index);
+        let term = apply(
+            Term::Variable(TermVariable { id }),
+            self.locals
+                .iter()
+                .map(|local| Term::Variable(TermVariable { id: local.id }))
+                .collect(),
+        );
+        self.metas.push(Meta {
+            ty,
+            pos,
+            solution: None,
+            hole: None,
+        });
+        term
+    }
+
+    /// Creates the metavariable of a hole of type `ty`
+    fn new_hole(&mut self, hole: &FTermHole<PhaseParse>, ty: Term) -> Term {
+        let term = self.new_meta(ty, Some(hole.pos().clone()));
+        let (id, _) = self.meta_spine(&term).unwrap();
+        if !hole.name().is_empty() {
+            self.checker.names.insert(id, format!("?{}", hole.name()));
+        }
+        let index = self.meta_index[&id];
+        self.metas[index].hole = Some(Hole {
+            name: hole.name().to_string(),
+            locals: self.locals.clone(),
+        });
+        term
+    }
+
+    fn solution(&self, id: Id) -> Option<Rc<Term>> {
+        let index = self.meta_index.get(&id)?;
+        self.metas[*index].solution.clone()
+    }
+
+    fn solved_count(&self) -> usize {
+        self.metas
+            .iter()
+            .filter(|meta| meta.solution.is_some())
+            .count()
+    }
+
+    fn is_meta(&self, id: Id) -> bool {
+        self.meta_index.contains_key(&id)
+    }
+
+    /// Splits `?m args` into the unsolved metavariable and its arguments
+    fn meta_spine<'t>(&self, term: &'t Term) -> Option<(Id, &'t [Term])> {
+        match term {
+            Term::Variable(var) if self.is_meta(var.id) => Some((var.id, &[])),
+            Term::Application(app) => match app.f.as_ref() {
+                Term::Variable(var) if self.is_meta(var.id) => Some((var.id, &app.args)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn whnf(&self, term: &Term) -> Term {
+        let mut current = self.zonk(term);
+        loop {
+            let next = reduction::whnf_with_env(&current, &self.checker.global_env);
+            if next == current {
+                return current;
+            }
+            current = next;
+        }
+    }
+
+    /// The local variables with known types, for the kernel
+    fn local_context(&self) -> LocalContext {
+        let mut ctx = LocalContext::new();
+        for (id, ty) in &self.var_types {
+            ctx.extend(*id, ty.clone())
+                .expect("the local variables are distinct");
+        }
+        ctx
+    }
+
+    /// The sort of a type, if it has no unsolved metavariables
+    fn sort_of(&self, ty: &Term) -> Option<Sort> {
+        let ty = self.zonk(ty);
+        let is_meta = |id| self.is_meta(id);
+        if any_variable(&ty, &is_meta) {
+            return None;
+        }
+        let sort =
+            typechecker::infer_type(&self.local_context(), &self.checker.global_env, &ty).ok()?;
+        match self.whnf(&sort) {
+            Term::Sort(sort) => Some(sort.sort),
+            _ => None,
+        }
+    }
+
+    /// Creates a variable to compare the bodies of binders. Variables bound by
+    /// match branches have no known type.
+    fn fresh_variable(&mut self, ty: Option<Rc<Term>>) -> Id {
+        let id = self.checker.id_gen.generate_id();
+        if let Some(ty) = ty {
+            self.var_types.insert(id, ty);
+        }
+        id
+    }
+
+    /// Unifies two terms, solving metavariables. Equations that cannot be
+    /// solved yet are postponed; `false` means the terms cannot be equal.
+    fn unify(&mut self, a: &Term, b: &Term, pos: &Option<Pos>) -> bool {
+        let a = self.zonk(a);
+        let b = self.zonk(b);
+        if a == b {
+            return true;
+        }
+        let is_meta = |id| self.is_meta(id);
+        if !any_variable(&a, &is_meta) && !any_variable(&b, &is_meta) {
+            return conversion::is_convertible(
+                &self.local_context(),
+                &self.checker.global_env,
+                &a,
+                &b,
+            );
+        }
+
+        let a = self.whnf(&a);
+        let b = self.whnf(&b);
+        if let Some((meta, args)) = self.meta_spine(&a) {
+            return self.solve(meta, args, &a, &b, pos);
+        }
+        if let Some((meta, args)) = self.meta_spine(&b) {
+            return self.solve(meta, args, &b, &a, pos);
+        }
+
+        match (&a, &b) {
+            (Term::Sort(s1), Term::Sort(s2)) => s1 == s2,
+            (Term::Variable(v1), Term::Variable(v2)) => v1.id == v2.id,
+            (Term::Constant(c1), Term::Constant(c2)) => c1.id == c2.id,
+            (Term::Product(p1), Term::Product(p2)) => {
+                self.unify(&p1.source, &p2.source, pos)
+                    && self.unify_binders(&p1.source, &p1.target, &p2.target, pos)
+            }
+            (Term::Lambda(l1), Term::Lambda(l2)) => {
+                self.unify(&l1.source_ty, &l2.source_ty, pos)
+                    && self.unify_binders(&l1.source_ty, &l1.target, &l2.target, pos)
+            }
+            (Term::Lambda(lambda), other) | (other, Term::Lambda(lambda)) => {
+                // η: f = fun x => f x
+                let x = self.fresh_variable(Some(lambda.source_ty.clone()));
+                let var = Term::Variable(TermVariable { id: x });
+                self.unify(
+                    &open(&lambda.target, x),
+                    &apply(other.clone(), vec![var]),
+                    pos,
+                )
+            }
+            (Term::Application(app1), Term::Application(app2)) => {
+                app1.args.len() == app2.args.len()
+                    && self.unify(&app1.f, &app2.f, pos)
+                    && app1
+                        .args
+                        .iter()
+                        .zip(&app2.args)
+                        .all(|(arg1, arg2)| self.unify(arg1, arg2, pos))
+            }
+            (Term::Match(m1), Term::Match(m2)) => {
+                m1.branches.len() == m2.branches.len()
+                    && self.unify(&m1.scrutinee, &m2.scrutinee, pos)
+                    && m1.branches.iter().zip(&m2.branches).all(|(b1, b2)| {
+                        b1.constructor_id == b2.constructor_id
+                            && b1.bound_vars.len() == b2.bound_vars.len()
+                            && {
+                                let vars: Vec<Term> = b1
+                                    .bound_vars
+                                    .iter()
+                                    .map(|_| {
+                                        let id = self.fresh_variable(None);
+                                        Term::Variable(TermVariable { id })
+                                    })
+                                    .collect();
+                                let body1 = instantiate_many(&b1.body, &vars);
+                                let body2 = instantiate_many(&b2.body, &vars);
+                                self.unify(&body1, &body2, pos)
+                            }
+                    })
+            }
+            _ => false,
+        }
+    }
+
+    /// Unifies the bodies of two binders of type `ty`
+    fn unify_binders(
+        &mut self,
+        ty: &Rc<Term>,
+        body1: &Term,
+        body2: &Term,
+        pos: &Option<Pos>,
+    ) -> bool {
+        let x = self.fresh_variable(Some(ty.clone()));
+        self.unify(&open(body1, x), &open(body2, x), pos)
+    }
+
+    /// Solves `?meta args = rhs`, where `lhs` is the left-hand side
+    fn solve(
+        &mut self,
+        meta: Id,
+        args: &[Term],
+        lhs: &Term,
+        rhs: &Term,
+        pos: &Option<Pos>,
+    ) -> bool {
+        // The arguments must be distinct local variables
+        let mut vars = Vec::new();
+        for arg in args {
+            match arg {
+                Term::Variable(var) if !self.is_meta(var.id) && !vars.contains(&var.id) => {
+                    vars.push(var.id)
+                }
+                _ => {
+                    self.postponed.push((lhs.clone(), rhs.clone(), pos.clone()));
+                    return true;
+                }
+            }
+        }
+
+        // Occurs check, and every variable of the solution must be bound by it
+        if any_variable(rhs, &|id| id == meta) {
+            return false;
+        }
+        let escapes = |id| !self.is_meta(id) && !vars.contains(&id);
+        if any_variable(rhs, &escapes) {
+            return false;
+        }
+
+        let Some(types) = vars
+            .iter()
+            .map(|var| self.var_types.get(var).cloned())
+            .collect::<Option<Vec<_>>>()
+        else {
+            self.postponed.push((lhs.clone(), rhs.clone(), pos.clone()));
+            return true;
+        };
+        let mut solution = abstract_variables(rhs, &vars);
+        for (i, (var, ty)) in vars.iter().zip(types).enumerate().rev() {
+            solution = Term::Lambda(TermLambda {
+                var: *var,
+                source_ty: Rc::new(abstract_variables(&ty, &vars[..i])),
+                target: Rc::new(solution),
+            });
+        }
+        let index = self.meta_index[&meta];
+        self.metas[index].solution = Some(Rc::new(solution));
+        true
+    }
+
+    fn print(&self, term: &Term) -> String {
+        Printer::new(&self.checker.names).print(&self.zonk(term))
+    }
+
+    fn mismatch(&self, expected: &Term, found: &Term, pos: &Option<Pos>) -> TypeCheckError {
+        TypeCheckError::new(
+            TypeCheckErrorKind::TypeMismatch {
+                expected: self.print(expected),
+                found: self.print(found),
+            },
+            pos.clone(),
+        )
+    }
+
+    fn kernel_error(&self, error: typechecker::TypeError, pos: Option<Pos>) -> TypeCheckError {
+        TypeCheckError::new(
+            TypeCheckErrorKind::Kernel(error.describe(&self.checker.names)),
+            pos,
+        )
+    }
+}
+
+/// Applies `f` to `args`, β-reducing the solutions of metavariables
+fn apply(f: Term, args: Vec<Term>) -> Term {
+    let mut f = f;
+    let mut args = args.into_iter();
+    while let Term::Lambda(lambda) = &f
+        && let Some(arg) = args.next()
+    {
+        f = instantiate(&lambda.target, &arg);
+    }
+    let rest: Vec<Term> = args.collect();
+    if rest.is_empty() {
+        return f;
+    }
+    match f {
+        Term::Application(app) => {
+            let mut all = app.args;
+            all.extend(rest);
+            Term::Application(TermApplication {
+                f: app.f,
+                args: all,
+            })
+        }
+        f => Term::Application(TermApplication {
+            f: Rc::new(f),
+            args: rest,
+        }),
+    }
+}
+
+/// Checks whether `term` mentions a free variable satisfying `pred`
+fn any_variable(term: &Term, pred: &impl Fn(Id) -> bool) -> bool {
+    match term {
+        Term::Variable(var) => pred(var.id),
+        Term::Sort(_) | Term::Bound(_) | Term::Constant(_) | Term::Literal(_) => false,
+        Term::Product(product) => {
+            any_variable(&product.source, pred) || any_variable(&product.target, pred)
+        }
+        Term::Lambda(lambda) => {
+            any_variable(&lambda.source_ty, pred) || any_variable(&lambda.target, pred)
+        }
+        Term::Application(app) => {
+            any_variable(&app.f, pred) || app.args.iter().any(|arg| any_variable(arg, pred))
+        }
+        Term::LetIn(let_in) => {
+            any_variable(&let_in.term, pred)
+                || any_variable(&let_in.ty, pred)
+                || any_variable(&let_in.body, pred)
+        }
+        Term::Match(case) => {
+            any_variable(&case.scrutinee, pred)
+                || any_variable(&case.return_type, pred)
+                || case
+                    .branches
+                    .iter()
+                    .any(|branch| any_variable(&branch.body, pred))
+        }
+    }
+}
+
+/// Position of the first token of a term, if it has one
+pub(crate) fn term_pos(term: &FTerm<PhaseParse>) -> Option<Pos> {
+    match term {
+        FTerm::Variable(var) => Some(var.variable().pos().clone()),
+        FTerm::Apply(apply) => term_pos(apply.f()),
+        FTerm::Paren(paren) => term_pos(paren.term()),
+        FTerm::ArrowDep(arrow) => Some(arrow.from().variable().pos().clone()),
+        FTerm::ArrowImplicit(arrow) => Some(arrow.from().variable().pos().clone()),
+        FTerm::ArrowNodep(arrow) => term_pos(arrow.from()),
+        FTerm::Match(match_expr) => Some(match_expr.scrutinee().pos().clone()),
+        FTerm::Hole(hole) => Some(hole.pos().clone()),
+        FTerm::By(by) => Some(by.keyword_by().pos().clone()),
+        FTerm::FieldAccess(access) => term_pos(access.object()),
+        FTerm::Number(number) => Some(number.number.pos().clone()),
+        FTerm::Unit(_) | FTerm::Struct(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set() -> Rc<Term> {
+        Rc::new(Term::Sort(TermSort { sort: Sort::Set }))
+    }
+
+    fn var(id: Id) -> Term {
+        Term::Variable(TermVariable { id })
+    }
+
+    #[test]
+    fn test_pattern_equation_is_solved_by_abstraction() {
+        let mut checker = TypeChecker::new();
+        let f = checker.declare("f");
+        let mut elab = Elaborator::new(&mut checker);
+        let x = elab.checker.id_gen.generate_id();
+        let y = elab.checker.id_gen.generate_id();
+        elab.push_local(Some("x"), x, set());
+        elab.push_local(Some("y"), y, set());
+
+        // ?m x y = f y x
+        let meta = elab.new_meta(set().as_ref().clone(), None);
+        let rhs = apply(Term::Constant(TermConstant { id: f }), vec![var(y), var(x)]);
+        assert!(elab.unify(&meta, &rhs, &None));
+        assert!(elab.finish().is_ok());
+        assert_eq!(elab.zonk(&meta), rhs);
+
+        // ?m := fun x y => f y x
+        let Term::Application(app) = &meta else {
+            panic!("expected an applied metavariable");
+        };
+        assert_eq!(
+            elab.print(&app.f),
+            "fun (x : Set) => fun (y : Set) => f y x"
+        );
+    }
+
+    #[test]
+    fn test_solution_cannot_mention_variables_out_of_scope() {
+        let mut checker = TypeChecker::new();
+        let mut elab = Elaborator::new(&mut checker);
+        let x = elab.checker.id_gen.generate_id();
+        let y = elab.checker.id_gen.generate_id();
+        elab.push_local(Some("x"), x, set());
+        let meta = elab.new_meta(set().as_ref().clone(), None);
+        elab.push_local(Some("y"), y, set());
+
+        // ?m x = y has no solution, as ?m was created before y
+        assert!(!elab.unify(&meta, &var(y), &None));
+        // ?m x = ?m x applied to itself fails the occurs check
+        let cyclic = apply(var(x), vec![meta.clone()]);
+        assert!(!elab.unify(&meta, &cyclic, &None));
+    }
+
+    #[test]
+    fn test_non_pattern_equations_are_postponed() {
+        let mut checker = TypeChecker::new();
+        let mut elab = Elaborator::new(&mut checker);
+        let x = elab.checker.id_gen.generate_id();
+        elab.push_local(Some("x"), x, set());
+        let meta = elab.new_meta(set().as_ref().clone(), None);
+        let Term::Application(app) = &meta else {
+            panic!("expected an applied metavariable");
+        };
+
+        // ?m x x is not a pattern
+        let repeated = apply(app.f.as_ref().clone(), vec![var(x), var(x)]);
+        assert!(elab.unify(&repeated, &var(x), &None));
+        assert_eq!(
+            elab.finish().map_err(|e| e.to_string()),
+            Err("Cannot solve `?0 x x` = `x`".to_string())
+        );
+    }
+}
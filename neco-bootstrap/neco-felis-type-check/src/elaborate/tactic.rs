@@ -0,0 +1,856 @@
+use std::rc::Rc;
+
+use neco_cic::{
+    id::Id,
+    substitution::{
+        Substitution, abstract_variable, abstract_variables, instantiate, instantiate_many, open,
+        substitute,
+    },
+    term::{
+        Sort, Term, TermApplication, TermConstant, TermLambda, TermLetIn, TermMatch,
+        TermMatchBranch, TermProduct, TermSort, TermVariable,
+    },
+};
+use neco_felis_syn::{PhaseParse, Pos, Term as FTerm, TermBy, TermByTactic};
+
+use super::{Elaborator, Local, apply};
+use crate::{
+    error::{TypeCheckError, TypeCheckErrorKind},
+    goal::Goal,
+};
+
+// A `#by` block proves its expected type by running tactics. Each open goal
+// is a metavariable applied to the local variables of the goal, like the
+// metavariables of implicit arguments. A tactic solves the first goal with a
+// term that may mention new goals for what is left to prove, and the proof
+// of the block is the first goal once every metavariable is substituted.
+//
+// `induction n` proves the induction hypotheses by calling the item being
+// defined on the arguments of the constructors. This requires the goal to be
+// the statement of the item, instantiated with the variables introduced by
+// `intro`.
+
+/// An open goal of a `#by` block
+#[derive(Clone)]
+struct TacticGoal {
+    name: String,
+    /// `?m x1 .. xn`, which the proof of the goal is substituted for
+    term: Term,
+    locals: Vec<Local>,
+    target: Term,
+    /// Variables introduced from the statement of the item, in order
+    intros: Vec<Id>,
+    pos: Pos,
+}
+
+/// The state of the metavariables, to undo the unifications of a failed
+/// attempt
+struct Snapshot {
+    solutions: Vec<Option<Rc<Term>>>,
+    postponed: usize,
+}
+
+impl Elaborator<'_> {
+    pub(super) fn elaborate_by(
+        &mut self,
+        by: &TermBy<PhaseParse>,
+        expected: &Term,
+    ) -> Result<Term, TypeCheckError> {
+        let pos = by.keyword_by().pos().clone();
+        let intros = self.locals.iter().map(|local| local.id).collect();
+        let root = self.new_goal("goal".to_string(), expected.clone(), intros, &pos);
+        let proof = root.term.clone();
+
+        let scope = self.locals.clone();
+        let mut goals = vec![root];
+        for tactic in by.tactics() {
+            let result = match goals.first() {
+                Some(goal) => {
+                    let goal = goal.clone();
+                    self.locals = goal.locals.clone();
+                    self.run_tactic(tactic, &goal)
+                }
+                None => Err("no goals left".to_string()),
+            };
+            self.locals = scope.clone();
+            match result {
+                Ok(new_goals) => {
+                    goals.splice(..1, new_goals);
+                }
+                Err(message) => {
+                    return Err(TypeCheckError::new(
+                        TypeCheckErrorKind::Tactic {
+                            tactic: tactic.name().s().to_string(),
+                            message,
+                            goals: self.display_goals(&goals),
+                        },
+                        Some(tactic.name().pos().clone()),
+                    ));
+                }
+            }
+        }
+
+        if !goals.is_empty() {
+            return Err(TypeCheckError::new(
+                TypeCheckErrorKind::UnsolvedGoals(self.display_goals(&goals)),
+                Some(pos),
+            ));
+        }
+        Ok(proof)
+    }
+
+    /// Runs a tactic on `goal`, whose local variables are in scope, and
+    /// returns the goals replacing it
+    fn run_tactic(
+        &mut self,
+        tactic: &TermByTactic<PhaseParse>,
+        goal: &TacticGoal,
+    ) -> Result<Vec<TacticGoal>, String> {
+        let pos = tactic.name().pos();
+        match (tactic.name().s(), tactic.arg()) {
+            ("intro", arg) => {
+                let names = match arg {
+                    Some(arg) => intro_names(arg)
+                        .ok_or("intro expects the names of the variables to introduce")?,
+                    None => vec![None],
+                };
+                self.intro(goal, &names, pos)
+            }
+            ("exact", Some(term)) => {
+                let proof = self
+                    .check(term, &goal.target)
+                    .map_err(|e| e.kind().to_string())?;
+                self.close(goal, &proof)?;
+                Ok(Vec::new())
+            }
+            ("apply", Some(term)) => {
+                let (head, ty) = self.infer_term(term).map_err(|e| e.kind().to_string())?;
+                let ty = self.zonk(&ty);
+                self.apply_lemma(goal, head.clone(), ty.clone(), pos)
+                    .ok_or_else(|| {
+                        format!(
+                            "`{}` of type `{}` does not prove the goal",
+                            self.print(&head),
+                            self.print(&ty)
+                        )
+                    })
+            }
+            ("reflexivity", None) => self.reflexivity(goal, pos),
+            ("simpl", None) => {
+                let target = self.simpl(&goal.target);
+                Ok(vec![TacticGoal {
+                    target,
+                    ..goal.clone()
+                }])
+            }
+            ("rewrite", Some(term)) => self.rewrite(goal, term, pos),
+            ("induction", Some(FTerm::Variable(var))) => {
+                self.induction(goal, var.variable().s(), pos)
+            }
+            ("induction", _) => Err("induction expects a variable".to_string()),
+            (name @ ("exact" | "apply" | "rewrite"), None) => Err(format!("{name} expects a term")),
+            (name @ ("reflexivity" | "simpl"), Some(_)) => Err(format!("{name} takes no argument")),
+            (name, _) => Err(format!("unknown tactic {name}")),
+        }
+    }
+
+    /// Creates a goal under the local variables in scope
+    fn new_goal(&mut self, name: String, target: Term, intros: Vec<Id>, pos: &Pos) -> TacticGoal {
+        let term = self.new_meta(target.clone(), Some(pos.clone()));
+        TacticGoal {
+            name,
+            term,
+            locals: self.locals.clone(),
+            target,
+            intros,
+            pos: pos.clone(),
+        }
+    }
+
+    /// Solves `goal` with `proof`
+    fn close(&mut self, goal: &TacticGoal, proof: &Term) -> Result<(), String> {
+        // The goal is solved with the proof as it is, without the weak head
+        // normalization of unification, which would reduce the redexes of
+        // `rewrite`
+        let pos = Some(goal.pos.clone());
+        let solved = match self.meta_spine(&goal.term) {
+            Some((meta, args)) if self.solution(meta).is_none() => {
+                let args = args.to_vec();
+                self.solve(meta, &args, &goal.term, proof, &pos)
+            }
+            _ => false,
+        };
+        if solved || self.unify(&goal.term, proof, &pos) {
+            Ok(())
+        } else {
+            Err(format!(
+                "cannot prove the goal with `{}`",
+                self.print(proof)
+            ))
+        }
+    }
+
+    fn intro(
+        &mut self,
+        goal: &TacticGoal,
+        names: &[Option<String>],
+        pos: &Pos,
+    ) -> Result<Vec<TacticGoal>, String> {
+        let mut target = goal.target.clone();
+        let mut introduced = Vec::new();
+        let mut intros = goal.intros.clone();
+        for name in names {
+            let Term::Product(product) = self.whnf(&target) else {
+                return Err(format!(
+                    "the goal `{}` is not a function type",
+                    self.print(&target)
+                ));
+            };
+            let name = name
+                .clone()
+                .or_else(|| self.checker.names.get(&product.var).cloned())
+                .unwrap_or_else(|| "x".to_string());
+            let id = self.checker.id_gen.generate_id();
+            self.push_local(Some(&name), id, product.source.clone());
+            introduced.push((id, product.source.clone()));
+            intros.push(id);
+            target = open(&product.target, id);
+        }
+
+        let new_goal = self.new_goal(goal.name.clone(), target, intros, pos);
+        let mut proof = new_goal.term.clone();
+        for (id, ty) in introduced.iter().rev() {
+            proof = Term::Lambda(TermLambda {
+                var: *id,
+                source_ty: ty.clone(),
+                target: Rc::new(abstract_variable(&proof, *id)),
+            });
+        }
+        self.close(goal, &proof)?;
+        Ok(vec![new_goal])
+    }
+
+    /// Proves `goal` by applying `head` of type `ty` to as many arguments as
+    /// needed for its type to match the goal. Arguments that unification
+    /// does not find become new goals.
+    fn apply_lemma(
+        &mut self,
+        goal: &TacticGoal,
+        head: Term,
+        ty: Term,
+        pos: &Pos,
+    ) -> Option<Vec<TacticGoal>> {
+        let mut arity = 0;
+        let mut current = self.whnf(&ty);
+        while let Term::Product(product) = current {
+            arity += 1;
+            current = self.whnf(&open(&product.target, product.var));
+        }
+
+        for count in (0..=arity).rev() {
+            let snapshot = self.snapshot();
+            let mut ty = ty.clone();
+            let mut args = Vec::new();
+            let mut arg_goals = Vec::new();
+            for _ in 0..count {
+                let Term::Product(product) = self.whnf(&ty) else {
+                    break;
+                };
+                let name = self
+                    .checker
+                    .names
+                    .get(&product.var)
+                    .cloned()
+                    .unwrap_or_else(|| goal.name.clone());
+                let arg_goal = self.new_goal(
+                    name,
+                    product.source.as_ref().clone(),
+                    goal.intros.clone(),
+                    pos,
+                );
+                ty = instantiate(&product.target, &arg_goal.term);
+                args.push(arg_goal.term.clone());
+                arg_goals.push(arg_goal);
+            }
+            if args.len() == count
+                && self.unify(&ty, &goal.target, &Some(pos.clone()))
+                && self.close(goal, &apply(head.clone(), args)).is_ok()
+            {
+                let open_goals = arg_goals
+                    .into_iter()
+                    .filter(|arg_goal| self.meta_spine(&self.zonk(&arg_goal.term)).is_some())
+                    .map(|arg_goal| TacticGoal {
+                        target: self.zonk(&arg_goal.target),
+                        ..arg_goal
+                    })
+                    .collect();
+                return Some(open_goals);
+            }
+            self.restore(snapshot);
+        }
+        None
+    }
+
+    /// Proves `goal` by a constructor of its inductive type that needs no
+    /// further goals, such as `eq_refl` for `eq x x`
+    fn reflexivity(&mut self, goal: &TacticGoal, pos: &Pos) -> Result<Vec<TacticGoal>, String> {
+        let target = self.whnf(&goal.target);
+        let head = match &target {
+            Term::Application(app) => app.f.as_ref(),
+            target => target,
+        };
+        let constructors: Vec<Id> = match head {
+            Term::Constant(const_) => self
+                .checker
+                .global_env
+                .inductives
+                .get_inductive(const_.id)
+                .map(|inductive| inductive.constructors.iter().map(|c| c.name).collect()),
+            _ => None,
+        }
+        .ok_or_else(|| {
+            format!(
+                "the goal `{}` is not an inductive type",
+                self.print(&target)
+            )
+        })?;
+
+        for constructor in constructors {
+            let Some(ty) = self.constant_type(constructor) else {
+                continue;
+            };
+            let snapshot = self.snapshot();
+            let head = Term::Constant(TermConstant { id: constructor });
+            match self.apply_lemma(goal, head, ty.as_ref().clone(), pos) {
+                Some(goals) if goals.is_empty() => return Ok(goals),
+                _ => self.restore(snapshot),
+            }
+        }
+        Err(format!(
+            "no constructor proves `{}`",
+            self.print(&goal.target)
+        ))
+    }
+
+    /// Replaces `x` by `y` in the goal, given a proof of `eq x y` or of any
+    /// inductive type with a single constructor and `x` and `y` as its last
+    /// indices, which the constructor makes equal
+    fn rewrite(
+        &mut self,
+        goal: &TacticGoal,
+        term: &FTerm<PhaseParse>,
+        pos: &Pos,
+    ) -> Result<Vec<TacticGoal>, String> {
+        let (proof, ty) = self.infer(term).map_err(|e| e.kind().to_string())?;
+        let ty = self.whnf(&ty);
+        let message = format!("`{}` is not an equation", self.print(&ty));
+        let not_an_equation = || message.clone();
+        let Term::Application(app) = &ty else {
+            return Err(not_an_equation());
+        };
+        let inductive = match app.f.as_ref() {
+            Term::Constant(const_) => self
+                .checker
+                .global_env
+                .inductives
+                .get_inductive(const_.id)
+                .filter(|inductive| inductive.constructors.len() == 1)
+                .cloned(),
+            _ => None,
+        }
+        .ok_or_else(not_an_equation)?;
+        let parameter_count = inductive.parameters.len();
+        let args: Vec<Term> = app.args.iter().map(|arg| self.zonk(arg)).collect();
+        if args.len() < parameter_count + 2 {
+            return Err(not_an_equation());
+        }
+        let (parameters, indices) = args.split_at(parameter_count);
+
+        // The indices are generalized into variables, so that the match
+        // refines them: `(fun i.. (e : eq i..) => #match e { refl .. => .. })`
+        let mut arity = instantiate_many(&inductive.sort, parameters);
+        let mut generalized = Vec::new();
+        for _ in indices {
+            let Term::Product(product) = self.whnf(&arity) else {
+                return Err(not_an_equation());
+            };
+            let id = self.fresh_binder(product.var, "i");
+            generalized.push((id, product.source.as_ref().clone()));
+            arity = open(&product.target, id);
+        }
+        let [.., (lhs_var, lhs_ty), (rhs_var, rhs_ty)] = generalized.as_slice() else {
+            return Err(not_an_equation());
+        };
+        if lhs_ty != rhs_ty {
+            return Err(not_an_equation());
+        }
+        let (lhs_var, rhs_var, domain) = (*lhs_var, *rhs_var, rhs_ty.clone());
+
+        // The constructor relates its last two indices, which are equal
+        let constructor = &inductive.constructors[0];
+        let mut ty = constructor.ty.as_ref().clone();
+        for parameter in parameters {
+            if let Term::Product(product) = &ty {
+                ty = instantiate(&product.target, parameter);
+            }
+        }
+        let mut fields = Vec::new();
+        while let Term::Product(product) = self.whnf(&ty) {
+            let id = self.fresh_binder(product.var, "x");
+            fields.push(id);
+            ty = open(&product.target, id);
+        }
+        let constructor_indices = match self.whnf(&ty) {
+            Term::Application(app) if app.args.len() == args.len() => {
+                app.args[parameter_count..].to_vec()
+            }
+            _ => return Err(not_an_equation()),
+        };
+        let [.., refl_lhs, refl_rhs] = constructor_indices.as_slice() else {
+            return Err(not_an_equation());
+        };
+        if refl_lhs != refl_rhs {
+            return Err(not_an_equation());
+        }
+
+        let lhs = indices[indices.len() - 2].clone();
+        let rhs = indices[indices.len() - 1].clone();
+        let target = self.zonk(&goal.target);
+        let rewritten = replace(&target, &lhs, &rhs);
+        if rewritten == target {
+            return Err(format!(
+                "`{}` does not occur in the goal `{}`",
+                self.print(&lhs),
+                self.print(&target)
+            ));
+        }
+        let new_goal = self.new_goal(goal.name.clone(), rewritten, goal.intros.clone(), pos);
+
+        // The match proves `forall (P : T -> Prop), P rhs -> P lhs`
+        let [predicate, hypothesis, element] = ["P", "H", "x"].map(|name| self.named_binder(name));
+        let predicate_ty = |domain: Term| pi(element, domain, prop());
+        let return_type = pi(
+            predicate,
+            predicate_ty(domain.clone()),
+            pi(
+                hypothesis,
+                application(var(predicate), vec![var(rhs_var)]),
+                application(var(predicate), vec![var(lhs_var)]),
+            ),
+        );
+        let mut refine = Substitution::new();
+        for ((id, _), index) in generalized.iter().zip(&constructor_indices) {
+            refine.add(*id, Rc::new(index.clone()));
+        }
+        let body = lambda(
+            predicate,
+            predicate_ty(substitute(&domain, &refine)),
+            lambda(
+                hypothesis,
+                application(var(predicate), vec![refl_rhs.clone()]),
+                var(hypothesis),
+            ),
+        );
+        let equation = self.named_binder("e");
+        let mut transport = Term::Match(TermMatch {
+            scrutinee: Rc::new(var(equation)),
+            return_type: Rc::new(return_type),
+            branches: vec![TermMatchBranch {
+                constructor_id: constructor.name,
+                body: Rc::new(abstract_variables(&body, &fields)),
+                bound_vars: fields,
+            }],
+        });
+        let generalized_ty = application(
+            app.f.as_ref().clone(),
+            parameters
+                .iter()
+                .cloned()
+                .chain(generalized.iter().map(|(id, _)| var(*id)))
+                .collect(),
+        );
+        transport = lambda(equation, generalized_ty, transport);
+        for (id, ty) in generalized.iter().rev() {
+            transport = lambda(*id, ty.clone(), transport);
+        }
+
+        let mut instantiate_indices = Substitution::new();
+        for ((id, _), index) in generalized.iter().zip(indices) {
+            instantiate_indices.add(*id, Rc::new(index.clone()));
+        }
+        let occurrence = self.named_binder("t");
+        let motive = lambda(
+            occurrence,
+            substitute(&domain, &instantiate_indices),
+            replace(&target, &lhs, &var(occurrence)),
+        );
+        let proof = application(
+            transport,
+            indices
+                .iter()
+                .cloned()
+                .chain([proof, motive, new_goal.term.clone()])
+                .collect(),
+        );
+        self.close(goal, &proof)?;
+        Ok(vec![new_goal])
+    }
+
+    /// A new binder named `name`
+    fn named_binder(&mut self, name: &str) -> Id {
+        let id = self.checker.id_gen.generate_id();
+        self.checker.names.insert(id, name.to_string());
+        id
+    }
+
+    /// A new binder named as the binder `var` of the goal or of a type, or
+    /// `fallback` if it has no name
+    fn fresh_binder(&mut self, var: Id, fallback: &str) -> Id {
+        let name = self.checker.names.get(&var).cloned();
+        self.named_binder(name.as_deref().unwrap_or(fallback))
+    }
+
+    /// Case analysis on the local variable `name`, with induction hypotheses
+    /// for the arguments of the constructors of the same type
+    fn induction(
+        &mut self,
+        goal: &TacticGoal,
+        name: &str,
+        pos: &Pos,
+    ) -> Result<Vec<TacticGoal>, String> {
+        let local = goal
+            .locals
+            .iter()
+            .rev()
+            .find(|local| local.name.as_deref() == Some(name))
+            .ok_or_else(|| format!("unknown variable {name}"))?;
+        let n = local.id;
+        let n_ty = self.whnf(&self.var_types[&n]);
+        let (head, ty_args) = match &n_ty {
+            Term::Application(app) => (app.f.as_ref(), app.args.as_slice()),
+            ty => (ty, &[][..]),
+        };
+        let inductive = match head {
+            Term::Constant(const_) => self.checker.global_env.inductives.get_inductive(const_.id),
+            _ => None,
+        }
+        .ok_or_else(|| {
+            format!(
+                "`{name}` of type `{}` is not of an inductive type",
+                self.print(&n_ty)
+            )
+        })?
+        .clone();
+        let parameters = &ty_args[..inductive.parameters.len().min(ty_args.len())];
+
+        let target = self.zonk(&goal.target);
+        let motive = abstract_variable(&target, n);
+        let mut branches = Vec::new();
+        let mut new_goals = Vec::new();
+        for constructor in &inductive.constructors {
+            self.locals = goal.locals.clone();
+            let mut ty = constructor.ty.as_ref().clone();
+            for parameter in parameters {
+                if let Term::Product(product) = &ty {
+                    ty = instantiate(&product.target, parameter);
+                }
+            }
+
+            // Constructor arguments, then the hypotheses of the recursive ones
+            let mut arg_ids = Vec::new();
+            let mut hypotheses = Vec::new();
+            while let Term::Product(product) = self.whnf(&ty) {
+                let arg_name = format!("{name}{}", arg_ids.len());
+                let arg_id = self.checker.id_gen.generate_id();
+                self.push_local(Some(&arg_name), arg_id, product.source.clone());
+                if self.is_recursive_argument(&product.source, inductive.name) {
+                    hypotheses.push((format!("IH{arg_name}"), arg_id));
+                }
+                arg_ids.push(arg_id);
+                ty = open(&product.target, arg_id);
+            }
+            let mut hypothesis_proofs = Vec::new();
+            for (hypothesis_name, arg_id) in hypotheses {
+                let arg = Term::Variable(TermVariable { id: arg_id });
+                let proof = self.induction_hypothesis(goal, n, &arg, &target)?;
+                let hypothesis_id = self.checker.id_gen.generate_id();
+                let hypothesis_ty = instantiate(&motive, &arg);
+                self.push_local(
+                    Some(&hypothesis_name),
+                    hypothesis_id,
+                    Rc::new(hypothesis_ty),
+                );
+                hypothesis_proofs.push(proof);
+            }
+
+            let value = apply(
+                Term::Constant(TermConstant {
+                    id: constructor.name,
+                }),
+                parameters
+                    .iter()
+                    .cloned()
+                    .chain(
+                        arg_ids
+                            .iter()
+                            .map(|id| Term::Variable(TermVariable { id: *id })),
+                    )
+                    .collect(),
+            );
+            let constructor_name = self.checker.names[&constructor.name].clone();
+            let branch_goal = self.new_goal(
+                constructor_name,
+                instantiate(&motive, &value),
+                goal.intros.clone(),
+                pos,
+            );
+
+            // The branch proves the goal with the hypotheses substituted
+            let Term::Application(app) = &branch_goal.term else {
+                unreachable!("goals are applied to the local variables of the branch");
+            };
+            let scope = app.args.len() - hypothesis_proofs.len();
+            let mut args = app.args[..scope].to_vec();
+            args.extend(hypothesis_proofs);
+            let body = apply(app.f.as_ref().clone(), args);
+            branches.push(TermMatchBranch {
+                constructor_id: constructor.name,
+                body: Rc::new(abstract_variables(&body, &arg_ids)),
+                bound_vars: arg_ids,
+            });
+            new_goals.push(branch_goal);
+        }
+        self.locals = goal.locals.clone();
+
+        let proof = Term::Match(TermMatch {
+            scrutinee: Rc::new(Term::Variable(TermVariable { id: n })),
+            return_type: Rc::new(target),
+            branches,
+        });
+        self.close(goal, &proof)?;
+        Ok(new_goals)
+    }
+
+    /// Computes `term` where it reduces to constructors, keeping the calls
+    /// stuck on variables folded: `add (S n) O` becomes `S (add n O)`
+    fn simpl(&self, term: &Term) -> Term {
+        let term = self.zonk(term);
+        let go = |term: &Rc<Term>| Rc::new(self.simpl(term));
+        match &term {
+            Term::Application(_) | Term::Constant(_) => {
+                let reduced = self.whnf(&term);
+                let head = match &reduced {
+                    Term::Application(app) => app.f.as_ref(),
+                    reduced => reduced,
+                };
+                let progress = match head {
+                    Term::Constant(const_) => self
+                        .checker
+                        .global_env
+                        .inductives
+                        .get_inductive_for_constructor(const_.id)
+                        .is_some(),
+                    Term::Sort(_) | Term::Product(_) => true,
+                    _ => false,
+                };
+                let folded = if progress { &reduced } else { &term };
+                match folded {
+                    Term::Application(app) => apply(
+                        self.simpl(&app.f),
+                        app.args.iter().map(|arg| self.simpl(arg)).collect(),
+                    ),
+                    Term::Product(_) if progress => self.simpl(folded),
+                    folded => folded.clone(),
+                }
+            }
+            Term::Product(product) => Term::Product(TermProduct {
+                var: product.var,
+                source: go(&product.source),
+                target: go(&product.target),
+            }),
+            _ => term.clone(),
+        }
+    }
+
+    fn is_recursive_argument(&self, ty: &Term, inductive: Id) -> bool {
+        let ty = self.whnf(ty);
+        let head = match &ty {
+            Term::Application(app) => app.f.as_ref(),
+            ty => ty,
+        };
+        matches!(head, Term::Constant(const_) if const_.id == inductive)
+    }
+
+    /// Proves the goal for `arg` instead of `n` by calling the item on the
+    /// introduced variables, `arg` replacing `n`
+    fn induction_hypothesis(
+        &mut self,
+        goal: &TacticGoal,
+        n: Id,
+        arg: &Term,
+        target: &Term,
+    ) -> Result<Term, String> {
+        let cannot_recurse = || {
+            "induction hypotheses need the goal to be the statement of the item, with the variable introduced from it".to_string()
+        };
+        let item = self.item.ok_or_else(cannot_recurse)?;
+        let mut statement = self
+            .constant_type(item)
+            .ok_or_else(cannot_recurse)?
+            .as_ref()
+            .clone();
+        if !goal.intros.contains(&n) {
+            return Err(cannot_recurse());
+        }
+        for id in &goal.intros {
+            let Term::Product(product) = self.whnf(&statement) else {
+                return Err(cannot_recurse());
+            };
+            statement = open(&product.target, *id);
+        }
+        if !self.unify(&statement, target, &Some(goal.pos.clone())) {
+            return Err(cannot_recurse());
+        }
+
+        self.recursive = true;
+        let args = goal
+            .intros
+            .iter()
+            .map(|id| match *id == n {
+                true => arg.clone(),
+                false => Term::Variable(TermVariable { id: *id }),
+            })
+            .collect();
+        Ok(apply(Term::Constant(TermConstant { id: item }), args))
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            solutions: self
+                .metas
+                .iter()
+                .map(|meta| meta.solution.clone())
+                .collect(),
+            postponed: self.postponed.len(),
+        }
+    }
+
+    /// Forgets the unifications and metavariables since `snapshot`
+    fn restore(&mut self, snapshot: Snapshot) {
+        let count = snapshot.solutions.len();
+        self.metas.truncate(count);
+        self.meta_index.retain(|_, index| *index < count);
+        for (meta, solution) in self.metas.iter_mut().zip(snapshot.solutions) {
+            meta.solution = solution;
+        }
+        self.postponed.truncate(snapshot.postponed);
+    }
+
+    fn display_goals(&self, goals: &[TacticGoal]) -> Vec<Goal> {
+        goals
+            .iter()
+            .map(|goal| Goal {
+                name: goal.name.clone(),
+                pos: goal.pos.clone(),
+                hypotheses: self.hypotheses(&goal.locals),
+                target: self.print(&goal.target),
+            })
+            .collect()
+    }
+}
+
+/// The names of `intro x y`, whose argument parses as an application
+fn var(id: Id) -> Term {
+    Term::Variable(TermVariable { id })
+}
+
+fn prop() -> Term {
+    Term::Sort(TermSort { sort: Sort::Prop })
+}
+
+fn application(f: Term, args: Vec<Term>) -> Term {
+    Term::Application(TermApplication {
+        f: Rc::new(f),
+        args,
+    })
+}
+
+/// The product binding the variable `var` in `target`
+fn pi(var: Id, source: Term, target: Term) -> Term {
+    Term::Product(TermProduct {
+        var,
+        source: Rc::new(source),
+        target: Rc::new(abstract_variable(&target, var)),
+    })
+}
+
+fn lambda(var: Id, source_ty: Term, body: Term) -> Term {
+    Term::Lambda(TermLambda {
+        var,
+        source_ty: Rc::new(source_ty),
+        target: Rc::new(abstract_variable(&body, var)),
+    })
+}
+
+fn intro_names(arg: &FTerm<PhaseParse>) -> Option<Vec<Option<String>>> {
+    match arg {
+        FTerm::Variable(var) => Some(vec![Some(var.variable().s().to_string())]),
+        FTerm::Apply(apply) => {
+            let mut names = intro_names(apply.f())?;
+            for arg in apply.args() {
+                names.extend(intro_names(arg)?);
+            }
+            Some(names)
+        }
+        _ => None,
+    }
+}
+
+/// Replaces the occurrences of `from` in `term` by `to`. `from` mentions no
+/// bound variable, so it can be compared under binders as it is.
+fn replace(term: &Term, from: &Term, to: &Term) -> Term {
+    if term == from {
+        return to.clone();
+    }
+    let go = |term: &Rc<Term>| Rc::new(replace(term, from, to));
+    match term {
+        Term::Sort(_)
+        | Term::Variable(_)
+        | Term::Bound(_)
+        | Term::Constant(_)
+        | Term::Literal(_) => term.clone(),
+        Term::Product(product) => Term::Product(TermProduct {
+            var: product.var,
+            source: go(&product.source),
+            target: go(&product.target),
+        }),
+        Term::Lambda(lambda) => Term::Lambda(TermLambda {
+            var: lambda.var,
+            source_ty: go(&lambda.source_ty),
+            target: go(&lambda.target),
+        }),
+        Term::Application(app) => apply(
+            replace(&app.f, from, to),
+            app.args.iter().map(|arg| replace(arg, from, to)).collect(),
+        ),
+        Term::LetIn(let_in) => Term::LetIn(TermLetIn {
+            var: let_in.var,
+            term: go(&let_in.term),
+            ty: go(&let_in.ty),
+            body: go(&let_in.body),
+        }),
+        Term::Match(case) => Term::Match(TermMatch {
+            scrutinee: go(&case.scrutinee),
+            return_type: go(&case.return_type),
+            branches: case
+                .branches
+                .iter()
+                .map(|branch| TermMatchBranch {
+                    constructor_id: branch.constructor_id,
+                    bound_vars: branch.bound_vars.clone(),
+                    body: go(&branch.body),
+                })
+                .collect(),
+        }),
+    }
+}
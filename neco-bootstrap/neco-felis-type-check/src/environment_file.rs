@@ -0,0 +1,367 @@
+use std::collections::HashMap;
+
+use neco_cic::{
+    id::Id,
+    primitive::Operation,
+    serialize::{Declaration, EnvironmentFile, FileItem, content_hash},
+};
+use neco_felis_rename::{phase_renamed::PhaseRenamed, rename_file};
+use neco_felis_syn::{File, FileId, Item, Parse, PhaseParse, token::Token};
+
+use crate::{TypeCheckError, TypeCheckErrorKind, TypeChecker, primitive::SPEC_BUILTINS};
+
+impl TypeChecker {
+    /// Type checks the items of `source`, loading them without checking from
+    /// `cache`, the environment file of a previous run, as long as their
+    /// source and the source of the items before them are unchanged. A cache
+    /// that cannot be read is ignored. Returns the renamed items.
+    pub fn check_source(
+        &mut self,
+        source: &str,
+        file_id: FileId,
+        cache: Option<&str>,
+    ) -> Result<File<PhaseRenamed>, TypeCheckError> {
+        let tokens = Token::try_lex(source, file_id)?;
+        let mut items = Vec::new();
+        let mut i = 0;
+        loop {
+            let start = i;
+            let item = Item::parse(&tokens, &mut i).map_err(|e| {
+                TypeCheckError::new(TypeCheckErrorKind::Parse(format!("{e:?}")), None)
+            })?;
+            let Some(item) = item else {
+                break;
+            };
+            items.push((item, start));
+        }
+        let renamed = rename_file(&File {
+            items: items.iter().map(|(item, _)| item.clone()).collect(),
+            ext: (),
+        })?;
+
+        // An item spans from its first token to the first token of the next one
+        let offsets: Vec<usize> = items
+            .iter()
+            .map(|(_, start)| offset(source, &tokens[*start]))
+            .chain(std::iter::once(source.len()))
+            .collect();
+        let cache = cache.and_then(|cache| EnvironmentFile::read(cache, &mut self.id_gen).ok());
+        let mut cached = cache.into_iter().flat_map(|cache| cache.items);
+        let mut reusing = true;
+        for (k, ((item, _), renamed)) in items.iter().zip(renamed.items()).enumerate() {
+            if !declares(item) {
+                self.process_item(item, renamed)?;
+                continue;
+            }
+            let hash = content_hash(source[offsets[k]..offsets[k + 1]].trim_end());
+            reusing = reusing
+                && cached
+                    .next()
+                    .is_some_and(|cached| cached.hash == hash && self.load_item(cached).is_ok());
+            if reusing {
+                if let Item::UseBuiltin(use_builtin) = item {
+                    self.procedures
+                        .add_builtin(use_builtin.name.s(), use_builtin.builtin_name.s());
+                }
+                continue;
+            }
+
+            let (declared, goals) = (self.declared.len(), self.goals.len());
+            self.process_item(item, renamed)?;
+            self.recording = self.recording && self.goals.len() == goals;
+            if self.recording {
+                let ids = &self.declared[declared..];
+                // A `#use_builtin` of a builtin declared before only names it
+                let named = match item {
+                    Item::UseBuiltin(use_builtin) if ids.is_empty() => {
+                        let builtin = use_builtin.builtin_name.s();
+                        self.builtins
+                            .get(builtin)
+                            .map(|id| (builtin, *id, use_builtin.name.s()))
+                    }
+                    _ => None,
+                };
+                let mut item = FileItem::new(
+                    &self.global_env,
+                    hash,
+                    ids,
+                    &self.names,
+                    &self.implicit_binders,
+                );
+                item.builtins = self
+                    .builtins
+                    .iter()
+                    .filter(|(_, id)| ids.contains(id))
+                    .map(|(builtin, id)| (builtin.to_string(), *id))
+                    .collect();
+                if let Some((builtin, id, name)) = named {
+                    item.names.push((id, name.to_string()));
+                    item.builtins.push((builtin.to_string(), id));
+                }
+                self.items.push(item);
+            }
+        }
+        Ok(renamed)
+    }
+
+    /// The declarations of the items checked by `check_source`, up to the
+    /// first one left with holes, to be written for the next runs
+    pub fn environment_file(&self) -> EnvironmentFile {
+        EnvironmentFile {
+            items: self.items.clone(),
+        }
+    }
+
+    /// Adds the declarations of a cached item with their names, as if its
+    /// source had been checked again. An item that names an id it does not
+    /// declare is not loaded.
+    fn load_item(&mut self, item: FileItem) -> Result<(), String> {
+        let names: HashMap<Id, &str> = item
+            .names
+            .iter()
+            .map(|(id, name)| (*id, name.as_str()))
+            .collect();
+        let name = |id: &Id| {
+            names
+                .get(id)
+                .map(|name| name.to_string())
+                .or_else(|| self.names.get(id).cloned())
+                .ok_or_else(|| format!("{id:?} has no name"))
+        };
+
+        let mut named = Vec::new();
+        let mut structures = Vec::new();
+        for declaration in &item.declarations {
+            match declaration {
+                Declaration::Inductives(defs) => {
+                    for def in defs {
+                        named.push((name(&def.name)?, def.name));
+                        for constructor in &def.constructors {
+                            named.push((name(&constructor.name)?, constructor.name));
+                        }
+                    }
+                }
+                Declaration::Constants(defs) => {
+                    for def in defs {
+                        named.push((name(&def.name)?, def.name));
+                    }
+                }
+                Declaration::Record {
+                    inductive,
+                    projections,
+                } => {
+                    // Projections are named after their structure
+                    let prefix = format!("{}_", name(inductive)?);
+                    let fields = projections
+                        .iter()
+                        .map(|projection| {
+                            let name = name(projection)?;
+                            let field = name.strip_prefix(&prefix).unwrap_or(&name);
+                            Ok((field.to_string(), *projection))
+                        })
+                        .collect::<Result<_, String>>()?;
+                    structures.push((*inductive, fields));
+                }
+            }
+        }
+        let mut builtins = Vec::new();
+        for (builtin, id) in &item.builtins {
+            let builtin = match builtin.as_str() {
+                "Acc" => "Acc",
+                "u64" => "u64",
+                builtin => match Operation::from_builtin(builtin) {
+                    Some(operation) => operation.builtin(),
+                    None => match SPEC_BUILTINS.iter().find(|spec| **spec == builtin) {
+                        Some(spec) => spec,
+                        None => continue,
+                    },
+                },
+            };
+            named.push((name(id)?, *id));
+            builtins.push((builtin, *id));
+        }
+
+        item.load(&mut self.global_env)?;
+        self.names.extend(item.names.iter().cloned());
+        self.implicit_binders
+            .extend(item.implicit_binders.iter().copied());
+        self.name_to_id.extend(named);
+        self.structures.extend(structures);
+        self.builtins.extend(builtins);
+        self.items.push(item);
+        Ok(())
+    }
+}
+
+/// Whether an item may add declarations to the environment
+fn declares(item: &Item<PhaseParse>) -> bool {
+    matches!(
+        item,
+        Item::Inductive(_)
+            | Item::Definition(_)
+            | Item::Theorem(_)
+            | Item::Axiom(_)
+            | Item::Mutual(_)
+            | Item::Structure(_)
+            | Item::UseBuiltin(_)
+    )
+}
+
+/// The byte offset of a token in the source it was lexed from
+fn offset(source: &str, token: &Token) -> usize {
+    let pos = token.pos();
+    let line_start: usize = source
+        .split_inclusive('\n')
+        .take(pos.line() - 1)
+        .map(str::len)
+        .sum();
+    source[line_start..]
+        .char_indices()
+        .nth(pos.column() - 1)
+        .map_or(source.len(), |(i, _)| line_start + i)
+}
+
+#[cfg(test)]
+mod tests {
+    use neco_cic::{
+        global_environment::GlobalEnvironment, id::IdGenerator, serialize::EnvironmentFile,
+    };
+    use neco_felis_syn::FileIdGenerator;
+
+    use crate::TypeChecker;
+
+    fn check(source: &str, cache: Option<&str>) -> Result<TypeChecker, crate::TypeCheckError> {
+        let file_id = FileIdGenerator::new().generate_file_id();
+        let mut type_checker = TypeChecker::new();
+        type_checker.check_source(source, file_id, cache)?;
+        Ok(type_checker)
+    }
+
+    fn testcase(name: &str) -> String {
+        std::fs::read_to_string(format!("../../testcases/felis/single/{name}")).unwrap()
+    }
+
+    #[test]
+    fn test_environment_files_are_rechecked_by_the_kernel() {
+        // well_founded.fe is left out: its definitions rely on the elaborator
+        // refining the types of the local variables in the branches of a
+        // match, such as `H : le n m` in `le_step`, which the kernel does not
+        for name in [
+            "eq_and_nat.fe",
+            "mutual.fe",
+            "structure.fe",
+            "tactics.fe",
+            "u64.fe",
+        ] {
+            let written = check(&testcase(name), None)
+                .unwrap()
+                .environment_file()
+                .write();
+            let file = EnvironmentFile::read(&written, &mut IdGenerator::new()).unwrap();
+            let mut env = GlobalEnvironment::new();
+            assert_eq!(file.recheck(&mut env), Ok(()), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_unchanged_items_are_loaded_from_the_environment_file() {
+        let source = testcase("eq_and_nat.fe");
+        let written = check(&source, None).unwrap().environment_file().write();
+
+        // Renaming `add` in the file shows which items are loaded from it
+        let add = written
+            .lines()
+            .find(|line| line.starts_with("name ") && line.ends_with(" add"))
+            .unwrap();
+        let cache = written.replace(add, &add.replace(" add", " plus"));
+        let changed = source.replace(
+            "eq nat (add O (S O)) (add (S O) O)",
+            "eq nat (plus O (S O)) (plus (S O) O)",
+        );
+        assert!(check(&changed, None).is_err());
+        let type_checker = check(&changed, Some(&cache)).unwrap();
+        assert_eq!(type_checker.environment_file().items.len(), 4);
+
+        // Items after a changed item are checked again
+        let changed = changed.replace("S p => S (add p m)", "S q => S (add q m)");
+        assert!(check(&changed, Some(&cache)).is_err());
+    }
+
+    #[test]
+    fn test_names_of_builtins_are_loaded_from_the_environment_file() {
+        let source = "#use_builtin \"u64_add\" #as add;
+#use_builtin \"u64\" #as nat64;
+#definition two : nat64 { add 1 1 }
+";
+        let written = check(source, None).unwrap().environment_file().write();
+        let file = EnvironmentFile::read(&written, &mut IdGenerator::new()).unwrap();
+        assert_eq!(file.recheck(&mut GlobalEnvironment::new()), Ok(()));
+
+        let changed = source.replace("add 1 1", "add 2 0");
+        let type_checker = check(&changed, Some(&written)).unwrap();
+        assert_eq!(type_checker.environment_file().items.len(), 3);
+        assert_eq!(type_checker.type_of("nat64").as_deref(), Some("Set"));
+        assert_eq!(type_checker.type_of("u64").as_deref(), Some("Set"));
+    }
+
+    #[test]
+    fn test_specification_of_u64_is_loaded_from_the_environment_file() {
+        let source = testcase("u64.fe");
+        let written = check(&source, None).unwrap().environment_file().write();
+        let items = EnvironmentFile::read(&written, &mut IdGenerator::new())
+            .unwrap()
+            .items
+            .len();
+        // Renaming the specification in the file shows that it is loaded
+        let cache = written.replace(" u64_add_spec\n", " cached_add_spec\n");
+
+        let changed = format!(
+            "{source}
+#theorem of_nat_succ : (n : nat) -> eq (u64_of_nat (S n)) (u64_add (u64_of_nat n) 1) {{
+    #by {{
+        intro n;
+        reflexivity;
+    }}
+}}
+"
+        );
+        let type_checker = check(&changed, Some(&cache)).unwrap();
+        assert_eq!(type_checker.environment_file().items.len(), items + 1);
+        assert_eq!(
+            type_checker.type_of("cached_add_spec").as_deref(),
+            Some(
+                "(n : nat) -> (m : nat) -> (P : u64 -> Prop) -> P (u64_add (u64_of_nat n) \
+                 (u64_of_nat m)) -> P (u64_of_nat (nat_add n m))"
+            )
+        );
+    }
+
+    #[test]
+    fn test_parameters_of_inductive_types_are_rechecked() {
+        let source = "#use_builtin \"Acc\" #as Acc;\n";
+        let written = check(source, None).unwrap().environment_file().write();
+        let file = EnvironmentFile::read(&written, &mut IdGenerator::new()).unwrap();
+        assert_eq!(file.recheck(&mut GlobalEnvironment::new()), Ok(()));
+    }
+
+    #[test]
+    fn test_unreadable_caches_are_ignored() {
+        let source = testcase("eq_and_nat.fe");
+        let type_checker = check(&source, Some("not an environment file")).unwrap();
+        assert_eq!(type_checker.environment_file().items.len(), 4);
+
+        // A file that reads but names none of its declarations
+        let written = check(&source, None).unwrap().environment_file().write();
+        let unnamed: String = written
+            .lines()
+            .filter(|line| !line.starts_with("name "))
+            .map(|line| format!("{line}\n"))
+            .collect();
+        let type_checker = check(&source, Some(&unnamed)).unwrap();
+        assert_eq!(type_checker.environment_file().items.len(), 4);
+        assert_eq!(
+            type_checker.type_of("add").as_deref(),
+            Some("nat -> nat -> nat")
+        );
+    }
+}
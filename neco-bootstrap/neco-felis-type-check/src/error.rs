@@ -0,0 +1,334 @@
+use neco_felis_rename::{RenameError, RenameErrorKind};
+use neco_felis_syn::{LexError, Pos};
+
+use crate::goal::Goal;
+
+/// What went wrong while checking a file. Terms are printed with their source
+/// names when the error is raised.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeCheckErrorKind {
+    Parse(String),
+    UnknownVariable(String),
+    UnknownConstant(String),
+    UnknownConstructor(String),
+    /// A field access on a term whose type is not a structure with the field
+    UnknownField {
+        field: String,
+        ty: String,
+    },
+    TypeMismatch {
+        expected: String,
+        found: String,
+    },
+    NotAFunction {
+        term: String,
+        ty: String,
+    },
+    NotAType {
+        term: String,
+        ty: String,
+    },
+    NotInductive {
+        scrutinee: String,
+        ty: String,
+    },
+    /// A match on a proof that returns data, see `check_elimination`
+    IllegalElimination {
+        inductive: String,
+        sort: String,
+    },
+    CannotInferMatch,
+    CannotInferHole(String),
+    CannotInferBy,
+    ConstructorArity {
+        constructor: String,
+        expected: usize,
+        found: usize,
+    },
+    CannotSolve {
+        lhs: String,
+        rhs: String,
+    },
+    UnsolvedMeta {
+        index: usize,
+        ty: String,
+    },
+    UnfilledHole {
+        name: String,
+        ty: String,
+    },
+    /// A type of an inductive block used to the left of an arrow in the
+    /// arguments of a constructor
+    NotPositive {
+        inductive: String,
+        constructor: String,
+    },
+    /// A `#structure` the kernel does not accept as a record, for `reason`
+    NotARecord {
+        name: String,
+        reason: &'static str,
+    },
+    /// Recursive calls not all on structurally smaller arguments
+    Termination(Vec<String>),
+    InvalidMutual,
+    /// An item, a parameter of a procedure or a field bound twice
+    DuplicateName(String),
+    /// An `#allow` of a lint that does not exist
+    UnknownLint(String),
+    /// A library constant used before its `#use_builtin` item
+    MissingBuiltin(&'static str),
+    /// A number literal that is not a 64-bit unsigned integer
+    InvalidLiteral(String),
+    /// A tactic that does not apply to the first goal, with the goals left
+    Tactic {
+        tactic: String,
+        message: String,
+        goals: Vec<Goal>,
+    },
+    /// Goals left open at the end of a `#by` block
+    UnsolvedGoals(Vec<Goal>),
+    /// An error of the kernel, described with source names
+    Kernel(String),
+    /// An item the global environment refused
+    Environment(String),
+    /// A definition called from a procedure that cannot be compiled
+    Extraction(String),
+    /// A type of a procedure signature or of a struct field that is neither
+    /// a machine type, a struct nor an array
+    UnknownType(String),
+    /// A struct value without one of the fields of its struct
+    MissingField {
+        field: String,
+        ty: String,
+    },
+    /// An array field accessed without an element index
+    MissingIndex(String),
+    /// A procedure, builtin or definition called with the wrong number of
+    /// arguments
+    Arity {
+        function: String,
+        expected: usize,
+        found: usize,
+    },
+    /// An assignment or a `.*` on a value that is not a reference
+    NotAReference {
+        term: String,
+        ty: String,
+    },
+    BreakOutsideLoop,
+    /// A `#call_ptx` of a procedure that is not a `#ptx` kernel
+    NotAPtxProc(String),
+    Unsupported(&'static str),
+}
+
+/// A type checking error, with the source position it concerns
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeCheckError {
+    // Boxed as goal listings make the kinds large
+    kind: Box<TypeCheckErrorKind>,
+    pos: Option<Pos>,
+    file_name: Option<String>,
+}
+
+impl TypeCheckError {
+    pub fn new(kind: TypeCheckErrorKind, pos: Option<Pos>) -> Self {
+        Self {
+            kind: Box::new(kind),
+            pos,
+            file_name: None,
+        }
+    }
+
+    pub fn kind(&self) -> &TypeCheckErrorKind {
+        &self.kind
+    }
+
+    pub fn pos(&self) -> Option<&Pos> {
+        self.pos.as_ref()
+    }
+
+    /// Positions an error that does not have a position yet
+    pub fn or_at(mut self, pos: &Pos) -> Self {
+        if self.pos.is_none() {
+            self.pos = Some(pos.clone());
+        }
+        self
+    }
+
+    /// Names the file of the position in the message
+    pub fn with_file_name(mut self, file_name: &str) -> Self {
+        self.file_name = Some(file_name.to_string());
+        self
+    }
+}
+
+impl std::fmt::Display for TypeCheckErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TypeCheckErrorKind::Parse(msg) => write!(f, "Parse error: {msg}"),
+            TypeCheckErrorKind::UnknownVariable(name) => write!(f, "Unknown variable: {name}"),
+            TypeCheckErrorKind::UnknownConstant(name) => write!(f, "Unknown constant: {name}"),
+            TypeCheckErrorKind::UnknownConstructor(name) => {
+                write!(f, "Unknown constructor: {name}")
+            }
+            TypeCheckErrorKind::UnknownField { field, ty } => {
+                write!(f, "No field {field} in type `{ty}`")
+            }
+            TypeCheckErrorKind::TypeMismatch { expected, found } => {
+                write!(f, "Type mismatch: expected `{expected}`, found `{found}`")
+            }
+            TypeCheckErrorKind::NotAFunction { term, ty } => {
+                write!(f, "Not a function: `{term}` of type `{ty}`")
+            }
+            TypeCheckErrorKind::NotAType { term, ty } => {
+                write!(f, "Expected a type, found `{term}` of type `{ty}`")
+            }
+            TypeCheckErrorKind::NotInductive { scrutinee, ty } => write!(
+                f,
+                "Cannot match on `{scrutinee}` of type `{ty}`, which is not an inductive type"
+            ),
+            TypeCheckErrorKind::IllegalElimination { inductive, sort } => write!(
+                f,
+                "Cannot match on a proof of {inductive} to build a value in {sort}: only empty and singleton propositions eliminate into Set and Type"
+            ),
+            TypeCheckErrorKind::CannotInferMatch => {
+                write!(f, "Cannot infer the type of a match expression")
+            }
+            TypeCheckErrorKind::CannotInferHole(name) => {
+                write!(f, "Cannot infer the type of the hole ?{name}")
+            }
+            TypeCheckErrorKind::CannotInferBy => {
+                write!(f, "Cannot infer the statement proved by a #by block")
+            }
+            TypeCheckErrorKind::ConstructorArity {
+                constructor,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Constructor {constructor} expects {expected} arguments, found {found}"
+            ),
+            TypeCheckErrorKind::CannotSolve { lhs, rhs } => {
+                write!(f, "Cannot solve `{lhs}` = `{rhs}`")
+            }
+            TypeCheckErrorKind::UnsolvedMeta { index, ty } => {
+                write!(f, "Unsolved metavariable ?{index} : `{ty}`")
+            }
+            TypeCheckErrorKind::UnfilledHole { name, ty } => {
+                write!(f, "Unfilled hole ?{name} : `{ty}`")
+            }
+            TypeCheckErrorKind::Tactic {
+                tactic,
+                message,
+                goals,
+            } => {
+                write!(f, "Tactic {tactic} failed: {message}")?;
+                goals.iter().try_for_each(|goal| write!(f, "\n{goal}"))
+            }
+            TypeCheckErrorKind::UnsolvedGoals(goals) => {
+                write!(f, "Unsolved goals")?;
+                goals.iter().try_for_each(|goal| write!(f, "\n{goal}"))
+            }
+            TypeCheckErrorKind::NotPositive {
+                inductive,
+                constructor,
+            } => write!(
+                f,
+                "Non strictly positive occurrence of {inductive} in constructor {constructor}"
+            ),
+            TypeCheckErrorKind::NotARecord { name, reason } => {
+                write!(f, "{name} is not a record: {reason}")
+            }
+            TypeCheckErrorKind::Termination(names) => write!(
+                f,
+                "Cannot show that the recursive calls of {} terminate: no argument decreases structurally",
+                names.join(", ")
+            ),
+            TypeCheckErrorKind::InvalidMutual => write!(
+                f,
+                "A #mutual block contains either only inductive types or only definitions"
+            ),
+            TypeCheckErrorKind::DuplicateName(name) => write!(f, "Duplicate name: {name}"),
+            TypeCheckErrorKind::UnknownLint(name) => write!(f, "Unknown lint: {name}"),
+            TypeCheckErrorKind::MissingBuiltin(name) => write!(
+                f,
+                "{name} is not declared: add #use_builtin \"{name}\" #as {name};"
+            ),
+            TypeCheckErrorKind::InvalidLiteral(literal) => write!(
+                f,
+                "Invalid literal {literal}: only 64-bit unsigned integers are supported"
+            ),
+            TypeCheckErrorKind::UnknownType(name) => write!(f, "Unknown type: {name}"),
+            TypeCheckErrorKind::MissingField { field, ty } => {
+                write!(f, "Missing field {field} of type `{ty}`")
+            }
+            TypeCheckErrorKind::MissingIndex(access) => {
+                write!(f, "Missing element index in the array access {access}")
+            }
+            TypeCheckErrorKind::Arity {
+                function,
+                expected,
+                found,
+            } => write!(f, "{function} expects {expected} arguments, found {found}"),
+            TypeCheckErrorKind::NotAReference { term, ty } => {
+                write!(f, "Not a reference: `{term}` of type `{ty}`")
+            }
+            TypeCheckErrorKind::BreakOutsideLoop => write!(f, "#break outside of a #loop"),
+            TypeCheckErrorKind::NotAPtxProc(name) => {
+                write!(f, "#call_ptx of {name}, which is not a #ptx procedure")
+            }
+            TypeCheckErrorKind::Kernel(msg) => write!(f, "{msg}"),
+            TypeCheckErrorKind::Environment(msg) => write!(f, "{msg}"),
+            TypeCheckErrorKind::Extraction(msg) => write!(f, "{msg}"),
+            TypeCheckErrorKind::Unsupported(what) => {
+                write!(f, "{what} are not supported in type checker yet")
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for TypeCheckError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The position ends the first line, before goal listings
+        let message = self.kind.to_string();
+        let (first, rest) = match message.split_once('\n') {
+            Some((first, rest)) => (first, Some(rest)),
+            None => (message.as_str(), None),
+        };
+        write!(f, "{first}")?;
+        match (&self.file_name, &self.pos) {
+            (Some(file_name), Some(pos)) => write!(f, " at {file_name}:{pos}")?,
+            (None, Some(pos)) => write!(f, " at {pos}")?,
+            (_, None) => {}
+        }
+        match rest {
+            Some(rest) => write!(f, "\n{rest}"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for TypeCheckError {}
+
+impl From<RenameError> for TypeCheckError {
+    fn from(error: RenameError) -> Self {
+        let kind = match error.kind {
+            RenameErrorKind::UnboundVariable(name) => TypeCheckErrorKind::UnknownVariable(name),
+            RenameErrorKind::DuplicateName(name) => TypeCheckErrorKind::DuplicateName(name),
+            RenameErrorKind::UnknownField { field, ty } => {
+                TypeCheckErrorKind::UnknownField { field, ty }
+            }
+            RenameErrorKind::UnknownLint(name) => TypeCheckErrorKind::UnknownLint(name),
+        };
+        TypeCheckError::new(kind, Some(error.pos))
+    }
+}
+
+impl From<LexError> for TypeCheckError {
+    fn from(error: LexError) -> Self {
+        TypeCheckError::new(
+            TypeCheckErrorKind::Parse(format!("unexpected character `{}`", error.c)),
+            Some(error.pos),
+        )
+    }
+}
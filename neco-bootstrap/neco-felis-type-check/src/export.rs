@@ -0,0 +1,44 @@
+use neco_cic::export::{self, ExportTarget};
+
+use crate::TypeChecker;
+
+impl TypeChecker {
+    /// Prints the declarations checked so far as a Coq or Lean 4 source file,
+    /// to check them again with another prover
+    pub fn export(&self, target: ExportTarget) -> String {
+        export::export(&self.global_env, &self.names, target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use neco_cic::export::ExportTarget;
+
+    use crate::TypeChecker;
+
+    fn check_testcase(name: &str) -> TypeChecker {
+        let path = format!("../../testcases/felis/single/{name}");
+        let source = std::fs::read_to_string(path).unwrap();
+        crate::tests::check_source(&source).unwrap()
+    }
+
+    #[test]
+    fn test_export_eq_and_nat_to_coq() {
+        let type_checker = check_testcase("eq_and_nat.fe");
+        insta::assert_snapshot!(type_checker.export(ExportTarget::Coq));
+    }
+
+    #[test]
+    fn test_export_eq_and_nat_to_lean() {
+        let type_checker = check_testcase("eq_and_nat.fe");
+        insta::assert_snapshot!(type_checker.export(ExportTarget::Lean));
+    }
+
+    #[test]
+    fn test_export_mutual_and_well_founded_definitions() {
+        let mutual = check_testcase("mutual.fe");
+        insta::assert_snapshot!("mutual_coq", mutual.export(ExportTarget::Coq));
+        let well_founded = check_testcase("well_founded.fe");
+        insta::assert_snapshot!("well_founded_lean", well_founded.export(ExportTarget::Lean));
+    }
+}
@@ -0,0 +1,188 @@
+use neco_cic::{extract, id::Id};
+
+pub use neco_cic::{
+    extract::{Branch, Code, Function},
+    primitive::Operation,
+};
+
+use crate::{TypeCheckError, TypeCheckErrorKind, TypeChecker};
+
+impl TypeChecker {
+    /// Extracts the definitions among `names`, and the ones they call, to
+    /// first-order functions with their types and proofs erased. Names that
+    /// are not definitions, such as procedures and primitive operations, are
+    /// skipped.
+    pub fn extract(&self, names: &[&str]) -> Result<Vec<Function>, TypeCheckError> {
+        let roots: Vec<Id> = names
+            .iter()
+            .filter_map(|name| self.name_to_id.get(*name).copied())
+            .filter(|id| {
+                self.global_env.get_constant(*id).is_some()
+                    && self.global_env.primitive_operation(*id).is_none()
+            })
+            .collect();
+        extract::extract(&self.global_env, &roots).map_err(|(function, e)| {
+            TypeCheckError::new(
+                TypeCheckErrorKind::Extraction(e.describe(function, &self.names)),
+                None,
+            )
+        })
+    }
+
+    /// The source name of a declaration
+    pub fn name(&self, id: Id) -> &str {
+        &self.names[&id]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Branch, Code, Operation};
+
+    const NAT: &str = "
+#inductive nat : Set {
+    O: nat,
+    S: nat -> nat,
+}
+";
+
+    #[test]
+    fn test_types_and_proofs_are_erased() {
+        let path = "../../testcases/felis/single/eq_and_nat.fe";
+        let source = std::fs::read_to_string(path).unwrap();
+        let source = format!(
+            "{source}
+#definition checked : (n : nat) -> (h : eq nat n n) -> nat {{
+    #match h {{
+        eq_refl A x => n,
+    }}
+}}
+
+#definition one : nat {{
+    checked (S O) (eq_refl nat (S O))
+}}
+"
+        );
+        let type_checker = crate::tests::check_source(&source).unwrap();
+        let functions = type_checker.extract(&["one"]).unwrap();
+        let names: Vec<_> = functions
+            .iter()
+            .map(|f| type_checker.name(f.name))
+            .collect();
+        assert_eq!(names, ["one", "checked"]);
+        // The proof is neither passed nor looked at
+        let Code::Call(_, args) = &functions[0].body else {
+            panic!("{:?}", functions[0].body);
+        };
+        assert_eq!(args.len(), 1);
+        assert_eq!((functions[1].params, functions[1].locals), (1, 1));
+        assert_eq!(functions[1].body, Code::Local(0));
+    }
+
+    #[test]
+    fn test_matches_become_switches() {
+        let source = format!(
+            "{NAT}
+#definition double : (n : nat) -> nat {{
+    #match n {{
+        O => O,
+        S p => S (S (double p)),
+    }}
+}}
+
+#definition four : nat {{
+    double (S (S O))
+}}
+"
+        );
+        let type_checker = crate::tests::check_source(&source).unwrap();
+        let functions = type_checker.extract(&["four", "main"]).unwrap();
+        let names: Vec<_> = functions
+            .iter()
+            .map(|f| type_checker.name(f.name))
+            .collect();
+        assert_eq!(names, ["four", "double"]);
+
+        let (zero, succ) = (
+            |fields| Code::Construct { tag: 0, fields },
+            |field| Code::Construct {
+                tag: 1,
+                fields: vec![field],
+            },
+        );
+        let double = functions[1].name;
+        assert_eq!(
+            functions[0].body,
+            Code::Call(double, vec![succ(succ(zero(vec![])))])
+        );
+        assert_eq!((functions[1].params, functions[1].locals), (1, 2));
+        assert_eq!(
+            functions[1].body,
+            Code::Switch {
+                scrutinee: Box::new(Code::Local(0)),
+                branches: vec![
+                    Branch {
+                        fields: vec![],
+                        body: zero(vec![]),
+                    },
+                    Branch {
+                        fields: vec![Some(1)],
+                        body: succ(succ(Code::Call(double, vec![Code::Local(1)]))),
+                    },
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn test_type_parameters_are_dropped_and_closures_rejected() {
+        let source = format!(
+            "{NAT}
+#definition id : (A : Set) -> (x : A) -> A {{
+    x
+}}
+
+#definition one : nat {{
+    id nat (S O)
+}}
+
+#definition apply : (f : nat -> nat) -> (n : nat) -> nat {{
+    f n
+}}
+
+#definition two : nat {{
+    apply S (S O)
+}}
+"
+        );
+        let type_checker = crate::tests::check_source(&source).unwrap();
+        let functions = type_checker.extract(&["one"]).unwrap();
+        assert_eq!(functions[1].params, 1);
+        assert!(matches!(&functions[0].body, Code::Call(_, args) if args.len() == 1));
+
+        let error = type_checker.extract(&["two"]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Cannot extract two: S is not applied to all its arguments"
+        );
+    }
+
+    #[test]
+    fn test_primitives_are_kept_as_operations() {
+        let source = "
+#use_builtin \"u64\" #as u64;
+#use_builtin \"u64_add\" #as u64_add;
+
+#definition succ : (x : u64) -> u64 {
+    u64_add x 1
+}
+";
+        let type_checker = crate::tests::check_source(source).unwrap();
+        let functions = type_checker.extract(&["succ", "u64_add"]).unwrap();
+        assert_eq!(functions.len(), 1);
+        assert_eq!(
+            functions[0].body,
+            Code::Primitive(Operation::Add, vec![Code::Local(0), Code::Literal(1)])
+        );
+    }
+}
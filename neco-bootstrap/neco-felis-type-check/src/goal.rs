@@ -0,0 +1,22 @@
+use neco_felis_syn::Pos;
+
+/// The expected type of a hole `?name` and the local variables around it,
+/// printed with their source names
+#[derive(Debug, Clone, PartialEq)]
+pub struct Goal {
+    pub name: String,
+    pub pos: Pos,
+    /// Named local variables with their types, outermost first
+    pub hypotheses: Vec<(String, String)>,
+    pub target: String,
+}
+
+impl std::fmt::Display for Goal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Goal ?{} at {}", self.name, self.pos)?;
+        for (name, ty) in &self.hypotheses {
+            writeln!(f, "  {name} : {ty}")?;
+        }
+        write!(f, "  ⊢ {}", self.target)
+    }
+}
@@ -1,29 +1,80 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 
 use neco_cic::{
+    conversion,
     global_environment::GlobalEnvironment,
     id::{Id, IdGenerator},
-    inductive::{ConstructorDefinition, InductiveDefinition},
+    inductive::{self, ConstructorDefinition, InductiveDefinition},
+    intern,
     local_context::LocalContext,
+    pretty::Printer,
     reduction,
-    term::{
-        Sort, Term, TermApplication, TermConstant, TermMatch, TermMatchBranch, TermProduct,
-        TermSort, TermVariable,
-    },
-    typechecker,
+    serialize::FileItem,
+    substitution::{abstract_variables, open},
+    term::{Term, TermLambda},
+    termination, typechecker,
 };
 
+use neco_felis_rename::{phase_renamed::PhaseRenamed, rename_file};
 use neco_felis_syn::{
-    File, FileIdGenerator, Item, ItemDefinition, ItemInductive, ItemTheorem, Parse, Pattern,
-    PhaseParse, Term as FTerm, TermMatch as FTermMatch, TermMatchBranch as FTermMatchBranch,
+    File, FileIdGenerator, Item, ItemAxiom, ItemCheck, ItemDefinition, ItemEval, ItemInductive,
+    ItemMutual, ItemPrint, ItemPrintAxioms, ItemTheorem, Parse, PhaseParse, Pos, Term as FTerm,
     token::Token,
 };
 
+mod builtin;
+mod contract;
+mod elaborate;
+mod environment_file;
+mod error;
+mod export;
+mod extract;
+mod goal;
+mod phase_typed;
+mod primitive;
+mod procedure;
+mod repl;
+mod structure;
+mod well_founded;
+
+pub use contract::Contract;
+use elaborate::Elaborator;
+pub use error::{TypeCheckError, TypeCheckErrorKind};
+pub use extract::{Branch, Code, Function, Operation};
+pub use goal::Goal;
+pub use neco_cic::export::ExportTarget;
+pub use phase_typed::{PhaseTyped, proc_term_type};
+use procedure::Procedures;
+pub use procedure::{ProcSignature, ProcType};
+pub use repl::Repl;
+
 pub struct TypeChecker {
     id_gen: IdGenerator,
     global_env: GlobalEnvironment,
     name_to_id: HashMap<String, Id>,
+    /// Source names of constants, binders and local variables, for printing
+    names: HashMap<Id, String>,
+    /// Binder names of the products introduced by implicit arrows `{x : A} -> B`
+    implicit_binders: HashSet<Id>,
+    /// Output of `#check`, `#eval`, `#print` and `#print_axioms`, one entry per item
+    diagnostics: Vec<String>,
+    /// Goals of the holes left in definitions and theorems
+    goals: Vec<Goal>,
+    /// Field names and projections of the `#structure` types
+    structures: HashMap<Id, Vec<(String, Id)>>,
+    /// Library constants declared by `#use_builtin`, by builtin name
+    builtins: HashMap<&'static str, Id>,
+    /// `#requires` and `#ensures` conditions, by procedure name
+    contracts: HashMap<String, Vec<Contract>>,
+    /// Signatures, structs and arrays of the procedures
+    procedures: Procedures,
+    /// Global ids in order of declaration
+    declared: Vec<Id>,
+    /// Items checked by `check_source`, up to the first one left with holes,
+    /// for the environment file
+    items: Vec<FileItem>,
+    recording: bool,
 }
 
 impl Default for TypeChecker {
@@ -38,330 +89,650 @@ impl TypeChecker {
             id_gen: IdGenerator::new(),
             global_env: GlobalEnvironment::new(),
             name_to_id: HashMap::new(),
+            names: HashMap::new(),
+            implicit_binders: HashSet::new(),
+            diagnostics: Vec::new(),
+            goals: Vec::new(),
+            structures: HashMap::new(),
+            builtins: HashMap::new(),
+            contracts: HashMap::new(),
+            procedures: Procedures::default(),
+            declared: Vec::new(),
+            items: Vec::new(),
+            recording: true,
         }
     }
 
-    pub fn check_file(&mut self, file: &File<PhaseParse>) -> Result<(), String> {
-        for item in file.items() {
-            self.process_item(item)?;
+    /// Messages of the inspection items checked so far
+    pub fn diagnostics(&self) -> &[String] {
+        &self.diagnostics
+    }
+
+    /// Goals of the holes left so far. An item with holes is assumed
+    /// without a body, like an axiom.
+    pub fn goals(&self) -> &[Goal] {
+        &self.goals
+    }
+
+    fn add_goals(&mut self, id: Id, ty: Term, goals: Vec<Goal>) -> Result<(), String> {
+        self.goals.extend(goals);
+        self.global_env.add_axiom(id, Rc::new(ty))
+    }
+
+    /// Declares a global name, returning the id of its constant
+    fn declare(&mut self, name: &str) -> Id {
+        let id = self.id_gen.generate_id();
+        self.name_to_id.insert(name.to_string(), id);
+        self.names.insert(id, name.to_string());
+        self.declared.push(id);
+        id
+    }
+
+    pub fn check_file(&mut self, file: &File<PhaseParse>) -> Result<(), TypeCheckError> {
+        let renamed = rename_file(file)?;
+        self.check_items(file, &renamed)
+    }
+
+    /// Checks the items of a file along with the renamed items, on which
+    /// the bodies of procedures are checked
+    fn check_items(
+        &mut self,
+        file: &File<PhaseParse>,
+        renamed: &File<PhaseRenamed>,
+    ) -> Result<(), TypeCheckError> {
+        for (item, renamed) in file.items().iter().zip(renamed.items()) {
+            self.process_item(item, renamed)?;
         }
         Ok(())
     }
 
-    fn process_item(&mut self, item: &Item<PhaseParse>) -> Result<(), String> {
+    fn process_item(
+        &mut self,
+        item: &Item<PhaseParse>,
+        renamed: &Item<PhaseRenamed>,
+    ) -> Result<(), TypeCheckError> {
         match item {
             Item::Inductive(inductive) => self.process_inductive(inductive),
             Item::Definition(definition) => self.process_definition(definition),
             Item::Theorem(theorem) => self.process_theorem(theorem),
+            Item::Axiom(axiom) => self.process_axiom(axiom),
+            Item::PrintAxioms(print_axioms) => self.process_print_axioms(print_axioms),
+            Item::Check(check) => self.process_check(check),
+            Item::Eval(eval) => self.process_eval(eval),
+            Item::Print(print) => self.process_print(print),
+            Item::Mutual(mutual) => self.process_mutual(mutual),
+            Item::Structure(structure) => self.process_structure(structure),
             Item::Entrypoint(_entrypoint) => {
                 // Entrypoint items are handled separately and don't need type checking
                 Ok(())
             }
-            Item::UseBuiltin(_use_builtin) => {
-                // Builtin items are handled separately and don't need type checking
-                Ok(())
-            }
-            Item::Proc(_item_proc) => Ok(()),
-            Item::Array(_item_array) => {
-                // TODO: Implement array type checking
-                Ok(())
-            }
-            Item::Struct(_item_struct) => {
-                // TODO: Implement struct type checking
+            Item::Allow(_allow) => {
+                // Lints are checked by the renamer
                 Ok(())
             }
+            Item::UseBuiltin(use_builtin) => self.process_use_builtin(use_builtin),
+            Item::Proc(item_proc) => match renamed {
+                Item::Proc(renamed) => self.process_proc(item_proc, renamed),
+                _ => unreachable!("the renamer keeps the items of a file in order"),
+            },
+            Item::Array(item_array) => self.process_array(item_array),
+            Item::Struct(item_struct) => self.process_struct(item_struct),
         }
     }
 
-    fn process_inductive(&mut self, inductive: &ItemInductive<PhaseParse>) -> Result<(), String> {
-        let name = inductive.name().s();
-        let id = self.id_gen.generate_id();
-        self.name_to_id.insert(name.to_string(), id);
+    fn process_inductive(
+        &mut self,
+        inductive: &ItemInductive<PhaseParse>,
+    ) -> Result<(), TypeCheckError> {
+        self.process_inductives(&[inductive])
+    }
+
+    fn process_definition(
+        &mut self,
+        definition: &ItemDefinition<PhaseParse>,
+    ) -> Result<(), TypeCheckError> {
+        match definition.decreasing_by() {
+            Some(decreasing_by) => self.process_well_founded_definition(definition, decreasing_by),
+            None => self.process_definitions(&[definition]),
+        }
+    }
 
-        // Convert type
-        let type_term = self.convert_term(inductive.ty())?;
-
-        // Convert constructors
-        let mut constructors = Vec::new();
-        for branch in inductive.branches() {
-            let constructor_id = self.id_gen.generate_id();
-            let constructor_name = branch.name().s();
-            self.name_to_id
-                .insert(constructor_name.to_string(), constructor_id);
-
-            let constructor_type = self.convert_term(branch.ty())?;
-            constructors.push(ConstructorDefinition {
-                name: constructor_id,
-                ty: Rc::new(constructor_type),
-                arity: 0, // TODO: compute actual arity
-            });
+    /// A `#mutual` block, checked as a whole so that its items can refer to
+    /// each other
+    fn process_mutual(&mut self, mutual: &ItemMutual<PhaseParse>) -> Result<(), TypeCheckError> {
+        let inductives: Option<Vec<_>> = mutual
+            .items()
+            .iter()
+            .map(|item| match item {
+                Item::Inductive(inductive) => Some(inductive),
+                _ => None,
+            })
+            .collect();
+        let definitions: Option<Vec<_>> = mutual
+            .items()
+            .iter()
+            .map(|item| match item {
+                Item::Definition(definition) => Some(definition),
+                _ => None,
+            })
+            .collect();
+        match (inductives, definitions) {
+            (Some(inductives), _) => self.process_inductives(&inductives),
+            (None, Some(definitions)) => self.process_definitions(&definitions),
+            (None, None) => Err(TypeCheckError::new(
+                TypeCheckErrorKind::InvalidMutual,
+                Some(mutual.keyword_mutual().pos().clone()),
+            )),
         }
+    }
 
-        let inductive_def = InductiveDefinition::new(
-            id,
-            Vec::new(), // No parameters for now
-            Rc::new(type_term),
-            constructors,
-        );
+    fn process_inductives(
+        &mut self,
+        inductives: &[&ItemInductive<PhaseParse>],
+    ) -> Result<(), TypeCheckError> {
+        let ids: Vec<Id> = inductives
+            .iter()
+            .map(|inductive| self.declare(inductive.name().s()))
+            .collect();
+        let constructor_ids: Vec<Vec<Id>> = inductives
+            .iter()
+            .map(|inductive| {
+                inductive
+                    .branches()
+                    .iter()
+                    .map(|branch| self.declare(branch.name().s()))
+                    .collect()
+            })
+            .collect();
+
+        // Elaborate the types, then the constructors, which refer to the
+        // inductive types of the block
+        let mut elab = Elaborator::new(self);
+        let mut type_terms = Vec::new();
+        for (inductive, id) in inductives.iter().zip(&ids) {
+            let type_term = elab.elaborate_type(inductive.ty())?;
+            elab.add_pending(*id, Rc::new(type_term.clone()));
+            type_terms.push(type_term);
+        }
+        let mut constructor_types = Vec::new();
+        for inductive in inductives {
+            let mut types = Vec::new();
+            for branch in inductive.branches() {
+                types.push(elab.elaborate_type(branch.ty())?);
+            }
+            constructor_types.push(types);
+        }
+        elab.finish()?;
+
+        let mut inductive_defs = Vec::new();
+        for ((id, type_term), (constructor_ids, types)) in ids
+            .iter()
+            .zip(&type_terms)
+            .zip(constructor_ids.into_iter().zip(&constructor_types))
+        {
+            let constructors = constructor_ids
+                .into_iter()
+                .zip(types)
+                .map(|(constructor_id, constructor_type)| {
+                    let constructor_type = elab.zonk(constructor_type);
+                    let arity = Self::product_arity(&constructor_type);
+                    ConstructorDefinition {
+                        name: constructor_id,
+                        ty: Rc::new(constructor_type),
+                        arity,
+                    }
+                })
+                .collect();
+            inductive_defs.push(InductiveDefinition::new(
+                *id,
+                Vec::new(), // No parameters for now
+                Rc::new(elab.zonk(type_term)),
+                constructors,
+            ));
+        }
 
+        let first = inductives[0].name().pos();
+        if let Err(error) = inductive::check_positivity(&inductive_defs) {
+            let pos = inductives
+                .iter()
+                .flat_map(|inductive| inductive.branches())
+                .find(|branch| self.name_to_id.get(branch.name().s()) == Some(&error.constructor))
+                .map_or(first, |branch| branch.name().pos());
+            return Err(TypeCheckError::new(
+                TypeCheckErrorKind::NotPositive {
+                    inductive: self.names[&error.inductive].clone(),
+                    constructor: self.names[&error.constructor].clone(),
+                },
+                Some(pos.clone()),
+            ));
+        }
         self.global_env
             .inductives
-            .add_inductive(inductive_def)
-            .map_err(|e| e.to_string())?;
+            .add_mutual_inductives(inductive_defs)
+            .map_err(|e| Self::environment_error(e, first))?;
         Ok(())
     }
 
-    fn process_definition(
+    fn process_definitions(
         &mut self,
-        definition: &ItemDefinition<PhaseParse>,
-    ) -> Result<(), String> {
-        let name = definition.name().s();
-        let id = self.id_gen.generate_id();
-        self.name_to_id.insert(name.to_string(), id);
-
-        let type_term = self.convert_term(definition.type_())?;
-        let body_term = self.convert_term(definition.body())?;
+        definitions: &[&ItemDefinition<PhaseParse>],
+    ) -> Result<(), TypeCheckError> {
+        if let Some(decreasing_by) = definitions
+            .iter()
+            .find_map(|definition| definition.decreasing_by())
+        {
+            return Err(TypeCheckError::new(
+                TypeCheckErrorKind::Unsupported(
+                    "Definitions by well-founded recursion in #mutual blocks",
+                ),
+                Some(decreasing_by.keyword_decreasing_by().pos().clone()),
+            ));
+        }
+        let ids: Vec<Id> = definitions
+            .iter()
+            .map(|definition| self.declare(definition.name().s()))
+            .collect();
+
+        // The definitions may call themselves and each other
+        let mut elab = Elaborator::new(self);
+        let mut type_terms = Vec::new();
+        for (definition, id) in definitions.iter().zip(&ids) {
+            let type_term = elab.elaborate_type(definition.type_())?;
+            elab.add_pending(*id, Rc::new(type_term.clone()));
+            type_terms.push(type_term);
+        }
 
-        // Convert type into lambda abstraction
-        let lambda_body = Self::create_lambda_from_product(&type_term, body_term)?;
+        let mut bodies = Vec::new();
+        for ((definition, id), type_term) in definitions.iter().zip(&ids).zip(&type_terms) {
+            elab.set_item(*id, Rc::new(type_term.clone()));
+
+            // The parameters named in the type are in scope in the body, which
+            // is checked against the rest of the type
+            let parameter_names = Self::parameter_names(definition.type_());
+            let mut parameter_ids = Vec::new();
+            let mut body_type = type_term.clone();
+            for name in &parameter_names {
+                let Term::Product(product) = &body_type else {
+                    break;
+                };
+                elab.push_local(name.as_deref(), product.var, product.source.clone());
+                parameter_ids.push(product.var);
+                body_type = open(&product.target, product.var);
+            }
+            let body_term = elab.check(definition.body(), &body_type);
+            elab.truncate_locals(0);
+            bodies.push((body_term?, parameter_ids));
+        }
+        let goals = elab.finish_with_goals()?;
+        let type_terms: Vec<Term> = type_terms.iter().map(|ty| elab.zonk(ty)).collect();
+        if !goals.is_empty() {
+            let mut goals = Some(goals);
+            for ((definition, id), type_term) in definitions.iter().zip(&ids).zip(type_terms) {
+                self.add_goals(*id, type_term, goals.take().unwrap_or_default())
+                    .map_err(|e| Self::environment_error(e, definition.name().pos()))?;
+            }
+            return Ok(());
+        }
 
-        println!("Processing definition: {name}");
+        // Convert the bodies into lambda abstractions over the parameters
+        let lambda_bodies: Vec<Term> = bodies
+            .into_iter()
+            .zip(&type_terms)
+            .map(|((body_term, parameter_ids), type_term)| {
+                let body_term = abstract_variables(&elab.zonk(&body_term), &parameter_ids);
+                Self::create_lambda_from_product(type_term, body_term, parameter_ids.len())
+            })
+            .collect();
+
+        let block: Vec<(Id, &Term)> = ids.iter().copied().zip(&lambda_bodies).collect();
+        self.check_termination(&block, definitions[0].name().pos())?;
+
+        for ((definition, id), (type_term, lambda_body)) in definitions
+            .iter()
+            .zip(ids)
+            .zip(type_terms.into_iter().zip(lambda_bodies))
+        {
+            let const_def = neco_cic::global_environment::ConstantDefinition {
+                name: id,
+                body: Some(Rc::new(lambda_body)),
+                ty: Rc::new(type_term),
+                opaque: false,
+            };
+            self.global_env
+                .add_constant(const_def)
+                .map_err(|e| Self::environment_error(e, definition.name().pos()))?;
+        }
+        Ok(())
+    }
 
-        let const_def = neco_cic::global_environment::ConstantDefinition {
-            name: id,
-            body: Some(Rc::new(lambda_body)),
-            ty: Rc::new(type_term),
-        };
-        self.global_env
-            .add_constant(const_def)
-            .map_err(|e| e.to_string())?;
+    /// Checks that the recursive calls of a block of constants are on
+    /// structurally smaller arguments
+    fn check_termination(&self, block: &[(Id, &Term)], pos: &Pos) -> Result<(), TypeCheckError> {
+        termination::check_termination(&self.global_env, block).map_err(|error| {
+            TypeCheckError::new(
+                TypeCheckErrorKind::Termination(
+                    error
+                        .constants
+                        .iter()
+                        .map(|id| self.names[id].clone())
+                        .collect(),
+                ),
+                Some(pos.clone()),
+            )
+        })?;
         Ok(())
     }
 
-    fn process_theorem(&mut self, theorem: &ItemTheorem<PhaseParse>) -> Result<(), String> {
+    fn process_theorem(&mut self, theorem: &ItemTheorem<PhaseParse>) -> Result<(), TypeCheckError> {
         let name = theorem.name().s();
-        let id = self.id_gen.generate_id();
-        self.name_to_id.insert(name.to_string(), id);
-
-        println!("Processing theorem: {name}");
+        let id = self.declare(name);
+
+        // Elaborate the theorem type and the proof against it
+        let mut elab = Elaborator::new(self);
+        let theorem_type = elab.elaborate_type(theorem.type_())?;
+        // Induction in a `#by` block calls the theorem for its hypotheses
+        elab.set_item(id, Rc::new(theorem_type.clone()));
+        let proof_term = elab.check(theorem.body(), &theorem_type)?;
+        let goals = elab.finish_with_goals()?;
+        let recursive = elab.is_recursive();
+        if !goals.is_empty() {
+            let theorem_type = elab.zonk(&theorem_type);
+            return self
+                .add_goals(id, theorem_type, goals)
+                .map_err(|e| Self::environment_error(e, theorem.name().pos()));
+        }
 
-        // Convert the theorem type and proof
-        let theorem_type = self.convert_term(theorem.type_())?;
-        let proof_term = self.convert_term(theorem.body())?;
+        // Share their subterms with the environment
+        let theorem_type = intern::intern(&elab.zonk(&theorem_type));
+        let proof_term = intern::intern(&elab.zonk(&proof_term));
 
-        // Type check the proof against the theorem type using CIC type checker
+        // Check the elaborated proof again with the CIC type checker, reporting
+        // its errors at the theorem name
+        let pos = theorem.name().pos();
         let local_ctx = LocalContext::new();
 
-        // Get the type of the proof term
-        let proof_type = typechecker::infer_type(&local_ctx, &self.global_env, &proof_term)
-            .map_err(|e| format!("Failed to infer proof type: {e}"))?;
-
-        // Check if the reduced theorem type equals the reduced proof type
-        let theorem_type_reduced = reduction::normalize_with_env(&theorem_type, &self.global_env);
-        let proof_type_reduced = reduction::normalize_with_env(&proof_type, &self.global_env);
+        // Induction hypotheses refer to the theorem on structurally smaller
+        // arguments, so the proof is checked with the theorem assumed
+        let mut recursive_env;
+        let global_env = if recursive {
+            self.check_termination(&[(id, &proof_term)], pos)?;
+            recursive_env = self.global_env.clone();
+            recursive_env
+                .add_axiom(id, theorem_type.clone())
+                .map_err(|e| Self::environment_error(e, pos))?;
+            &recursive_env
+        } else {
+            &self.global_env
+        };
 
-        if !typechecker::is_convertible(
-            &local_ctx,
-            &self.global_env,
-            &theorem_type_reduced,
-            &proof_type_reduced,
-        ) {
-            return Err(format!(
-                "Theorem type does not match proof type for: {name}\nTheorem type (reduced): {theorem_type_reduced:?}\nProof type (reduced): {proof_type_reduced:?}"
+        // Get the type of the proof term
+        let proof_type =
+            typechecker::infer_type(&local_ctx, global_env, &proof_term).map_err(|e| {
+                TypeCheckError::new(
+                    TypeCheckErrorKind::Kernel(e.describe(&self.names)),
+                    Some(pos.clone()),
+                )
+            })?;
+
+        // Check that the theorem type and the proof type are convertible.
+        // Normal forms are only computed to report a mismatch.
+        if !conversion::is_convertible(&local_ctx, global_env, &theorem_type, &proof_type) {
+            let printer = Printer::new(&self.names);
+            let theorem_type_reduced =
+                reduction::normalize_with_env(&theorem_type, &self.global_env);
+            let proof_type_reduced = reduction::normalize_with_env(&proof_type, &self.global_env);
+            return Err(TypeCheckError::new(
+                TypeCheckErrorKind::TypeMismatch {
+                    expected: printer.print(&theorem_type_reduced),
+                    found: printer.print(&proof_type_reduced),
+                },
+                Some(pos.clone()),
             ));
         }
 
-        let const_def = neco_cic::global_environment::ConstantDefinition {
-            name: id,
-            body: Some(Rc::new(proof_term)),
-            ty: Rc::new(theorem_type),
-        };
+        // Theorems are opaque: later conversions never unfold their proofs
         self.global_env
-            .add_constant(const_def)
-            .map_err(|e| e.to_string())?;
+            .add_theorem(id, theorem_type, proof_term)
+            .map_err(|e| Self::environment_error(e, pos))?;
         Ok(())
     }
 
-    fn convert_term(&mut self, term: &FTerm<PhaseParse>) -> Result<Term, String> {
-        match term {
-            FTerm::Variable(var) => {
-                let name = var.variable().s();
-
-                // Handle built-in types
-                match name {
-                    "Type" => Ok(Term::Sort(TermSort {
-                        sort: Sort::Type(0),
-                    })),
-                    "Set" => Ok(Term::Sort(TermSort { sort: Sort::Set })),
-                    "Prop" => Ok(Term::Sort(TermSort { sort: Sort::Prop })),
-                    _ => {
-                        if let Some(&id) = self.name_to_id.get(name) {
-                            Ok(Term::Constant(TermConstant { id }))
-                        } else {
-                            Err(format!("Unknown variable: {name}"))
-                        }
-                    }
-                }
-            }
-            FTerm::Apply(apply) => {
-                let f = self.convert_term(apply.f())?;
-                let mut args = Vec::new();
-                for arg in apply.args() {
-                    args.push(self.convert_term(arg)?);
-                }
-                Ok(Term::Application(TermApplication {
-                    f: Rc::new(f),
-                    args,
-                }))
-            }
-            FTerm::ArrowDep(arrow) => {
-                let var_name = arrow.from().variable().s();
-                let var_id = self.id_gen.generate_id();
-                self.name_to_id.insert(var_name.to_string(), var_id);
-
-                let source = self.convert_term(arrow.from_ty())?;
-                let target = self.convert_term(arrow.to())?;
-
-                Ok(Term::Product(TermProduct {
-                    var: var_id,
-                    source: Rc::new(source),
-                    target: Rc::new(target),
-                }))
-            }
-            FTerm::ArrowNodep(arrow) => {
-                let dummy_var = self.id_gen.generate_id();
-                let source = self.convert_term(arrow.from())?;
-                let target = self.convert_term(arrow.to())?;
-
-                Ok(Term::Product(TermProduct {
-                    var: dummy_var,
-                    source: Rc::new(source),
-                    target: Rc::new(target),
-                }))
-            }
-            FTerm::Paren(paren) => self.convert_term(paren.term()),
-            FTerm::Match(match_expr) => self.convert_match(match_expr),
-            FTerm::Unit(_term_unit) => todo!(),
-            FTerm::Number(_term_number) => todo!(),
-            FTerm::Struct(_item_struct) => {
-                // For now, struct expressions are not supported in the type checker
-                // They should be handled at the compilation level
-                Err("Struct expressions are not supported in type checker yet".to_string())
-            }
-        }
+    fn process_axiom(&mut self, axiom: &ItemAxiom<PhaseParse>) -> Result<(), TypeCheckError> {
+        let name = axiom.name().s();
+        let id = self.declare(name);
+
+        let mut elab = Elaborator::new(self);
+        let axiom_type = elab.elaborate_type(axiom.type_())?;
+        elab.finish()?;
+        let axiom_type = elab.zonk(&axiom_type);
+
+        self.global_env
+            .add_axiom(id, Rc::new(axiom_type))
+            .map_err(|e| Self::environment_error(e, axiom.name().pos()))?;
+        Ok(())
     }
 
-    fn convert_match(&mut self, match_expr: &FTermMatch<PhaseParse>) -> Result<Term, String> {
-        let scrutinee_name = match_expr.scrutinee().s();
-        let scrutinee_id = self
-            .name_to_id
-            .get(scrutinee_name)
-            .ok_or_else(|| format!("Unknown variable in match: {scrutinee_name}"))?;
+    fn process_print_axioms(
+        &mut self,
+        print_axioms: &ItemPrintAxioms<PhaseParse>,
+    ) -> Result<(), TypeCheckError> {
+        let name = print_axioms.name().s();
+        let axioms = self
+            .axioms_of(name)
+            .map_err(|e| e.or_at(print_axioms.name().pos()))?;
+        let message = if axioms.is_empty() {
+            format!("{name} does not depend on any axioms")
+        } else {
+            format!("{name} depends on axioms: {}", axioms.join(", "))
+        };
+        self.diagnostics.push(message);
+        Ok(())
+    }
 
-        let scrutinee = Term::Variable(TermVariable { id: *scrutinee_id });
+    fn process_check(&mut self, check: &ItemCheck<PhaseParse>) -> Result<(), TypeCheckError> {
+        let mut elab = Elaborator::new(self);
+        let (term, ty) = elab.infer_term(check.term())?;
+        elab.finish()?;
+        let term = elab.zonk(&term);
+        let ty = elab.zonk(&ty);
 
-        // For now, use a simple return type (we'd need more sophisticated inference)
-        let return_type = Term::Sort(TermSort { sort: Sort::Set });
+        let message = format!("{} : {}", self.print(&term), self.print(&ty));
+        self.diagnostics.push(message);
+        Ok(())
+    }
 
-        let mut branches = Vec::new();
-        for branch in match_expr.branches() {
-            let cic_branch = self.convert_match_branch(branch)?;
-            branches.push(cic_branch);
-        }
+    fn process_eval(&mut self, eval: &ItemEval<PhaseParse>) -> Result<(), TypeCheckError> {
+        let mut elab = Elaborator::new(self);
+        let (term, _ty) = elab.infer(eval.term())?;
+        elab.finish()?;
+        let term = elab.zonk(&term);
 
-        Ok(Term::Match(TermMatch {
-            scrutinee: Rc::new(scrutinee),
-            return_type: Rc::new(return_type),
-            branches,
-        }))
+        let normal_form = reduction::normalize_with_env(&term, &self.global_env);
+        let message = self.print(&normal_form);
+        self.diagnostics.push(message);
+        Ok(())
     }
 
-    fn convert_match_branch(
-        &mut self,
-        branch: &FTermMatchBranch<PhaseParse>,
-    ) -> Result<TermMatchBranch, String> {
-        match branch.pattern() {
-            Pattern::Variable(var) => {
-                let constructor_name = var.s();
-                let constructor_id = *self
-                    .name_to_id
-                    .get(constructor_name)
-                    .ok_or_else(|| format!("Unknown constructor: {constructor_name}"))?;
-
-                let body = self.convert_term(branch.body())?;
-
-                Ok(TermMatchBranch {
-                    constructor_id,
-                    bound_vars: Vec::new(),
-                    body: Rc::new(body),
-                })
+    fn process_print(&mut self, print: &ItemPrint<PhaseParse>) -> Result<(), TypeCheckError> {
+        let name = print.name().s();
+        let id = *self.name_to_id.get(name).ok_or_else(|| {
+            TypeCheckError::new(
+                TypeCheckErrorKind::UnknownConstant(name.to_string()),
+                Some(print.name().pos().clone()),
+            )
+        })?;
+
+        let env = &self.global_env;
+        let message = if let Some(inductive) = env.inductives.get_inductive(id) {
+            let mut message = format!(
+                "#inductive {name} : {} {{\n",
+                self.print(&inductive.get_type())
+            );
+            for constructor in &inductive.constructors {
+                message.push_str(&format!(
+                    "    {}: {},\n",
+                    self.names[&constructor.name],
+                    self.print(&constructor.ty)
+                ));
             }
-            Pattern::Constructor(constructor, args) => {
-                let constructor_name = constructor.s();
-                let constructor_id = *self
-                    .name_to_id
-                    .get(constructor_name)
-                    .ok_or_else(|| format!("Unknown constructor: {constructor_name}"))?;
-
-                let mut bound_vars = Vec::new();
-                for arg in args {
-                    let arg_id = self.id_gen.generate_id();
-                    self.name_to_id.insert(arg.s().to_string(), arg_id);
-                    bound_vars.push(arg_id);
+            message.push('}');
+            message
+        } else if let Some(constructor) = env.inductives.get_constructor(id) {
+            let inductive = env.inductives.get_inductive_for_constructor(id).unwrap();
+            format!(
+                "{name} : {} (constructor of {})",
+                self.print(&constructor.ty),
+                self.names[&inductive]
+            )
+        } else if let Some(constant) = env.get_constant(id) {
+            let ty = self.print(&constant.ty);
+            match &constant.body {
+                None => format!("#axiom {name} : {ty};"),
+                Some(body) => {
+                    let keyword = if constant.opaque {
+                        "theorem"
+                    } else {
+                        "definition"
+                    };
+                    format!("#{keyword} {name} : {ty} {{\n    {}\n}}", self.print(body))
                 }
+            }
+        } else {
+            return Err(TypeCheckError::new(
+                TypeCheckErrorKind::UnknownConstant(name.to_string()),
+                Some(print.name().pos().clone()),
+            ));
+        };
+        self.diagnostics.push(message);
+        Ok(())
+    }
 
-                let body = self.convert_term(branch.body())?;
+    /// Prints a term for the user, with implicit binders in braces
+    fn print(&self, term: &Term) -> String {
+        Printer::new(&self.names)
+            .with_implicit_binders(&self.implicit_binders)
+            .print(term)
+    }
 
-                Ok(TermMatchBranch {
-                    constructor_id,
-                    bound_vars,
-                    body: Rc::new(body),
-                })
+    /// Names of the axioms a global constant transitively depends on
+    pub fn axioms_of(&self, name: &str) -> Result<Vec<String>, TypeCheckError> {
+        let id = *self.name_to_id.get(name).ok_or_else(|| {
+            TypeCheckError::new(TypeCheckErrorKind::UnknownConstant(name.to_string()), None)
+        })?;
+        Ok(self
+            .global_env
+            .axioms_of(id)
+            .into_iter()
+            .map(|axiom| self.names[&axiom].clone())
+            .collect())
+    }
+
+    /// The type of a global constant, a constructor or an inductive type
+    /// checked so far, printed for the user
+    pub fn type_of(&self, name: &str) -> Option<String> {
+        let id = *self.name_to_id.get(name)?;
+        let env = &self.global_env;
+        let ty = if let Some(inductive) = env.inductives.get_inductive(id) {
+            inductive.get_type()
+        } else if let Some(constructor) = env.inductives.get_constructor(id) {
+            constructor.ty.clone()
+        } else {
+            env.get_constant(id)?.ty.clone()
+        };
+        Some(self.print(&ty))
+    }
+
+    /// Collects the parameter names of a function type, `None` for
+    /// non-dependent arrows
+    fn parameter_names(ty: &FTerm<PhaseParse>) -> Vec<Option<String>> {
+        match ty {
+            FTerm::ArrowDep(arrow) => {
+                let mut names = vec![Some(arrow.from().variable().s().to_string())];
+                names.extend(Self::parameter_names(arrow.to()));
+                names
+            }
+            FTerm::ArrowImplicit(arrow) => {
+                let mut names = vec![Some(arrow.from().variable().s().to_string())];
+                names.extend(Self::parameter_names(arrow.to()));
+                names
             }
+            FTerm::ArrowNodep(arrow) => {
+                let mut names = vec![None];
+                names.extend(Self::parameter_names(arrow.to()));
+                names
+            }
+            FTerm::Paren(paren) => Self::parameter_names(paren.term()),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Number of arguments of a constructor type
+    fn product_arity(ty: &Term) -> usize {
+        match ty {
+            Term::Product(product) => 1 + Self::product_arity(&product.target),
+            _ => 0,
         }
     }
 
-    /// Creates lambda abstractions from a product type
+    /// Creates `count` lambda abstractions from a product type
     /// For example: (n : nat) -> (m : nat) -> nat with body becomes
     /// λn:nat. λm:nat. body
-    fn create_lambda_from_product(product_type: &Term, body: Term) -> Result<Term, String> {
-        use neco_cic::term::TermLambda;
-
+    /// The body must already refer to the parameters as bound variables.
+    fn create_lambda_from_product(product_type: &Term, body: Term, count: usize) -> Term {
         match product_type {
-            Term::Product(product) => {
+            Term::Product(product) if count > 0 => {
                 // Create lambda for this parameter
-                let inner_lambda = Self::create_lambda_from_product(&product.target, body)?;
-                Ok(Term::Lambda(TermLambda {
+                let inner_lambda =
+                    Self::create_lambda_from_product(&product.target, body, count - 1);
+                Term::Lambda(TermLambda {
                     var: product.var,
                     source_ty: product.source.clone(),
                     target: Rc::new(inner_lambda),
-                }))
+                })
             }
             _ => {
-                // Base case: no more products, return the body
-                Ok(body)
+                // Base case: no more parameters, return the body
+                body
             }
         }
     }
+
+    fn environment_error(error: impl std::fmt::Display, pos: &Pos) -> TypeCheckError {
+        TypeCheckError::new(
+            TypeCheckErrorKind::Environment(error.to_string()),
+            Some(pos.clone()),
+        )
+    }
 }
 
-pub fn type_check_file(file_contents: &str) -> Result<(), String> {
+pub fn type_check_file(file_contents: &str) -> Result<(), TypeCheckError> {
     let mut file_id_generator = FileIdGenerator::new();
     let file_id = file_id_generator.generate_file_id();
-    let tokens = Token::lex(file_contents, file_id);
+    let tokens = Token::try_lex(file_contents, file_id)?;
 
     let mut i = 0;
+    let parse_error = |msg| TypeCheckError::new(TypeCheckErrorKind::Parse(msg), None);
     let file = File::parse(&tokens, &mut i)
-        .map_err(|e| format!("Parse error: {e:?}"))?
-        .ok_or("Failed to parse file")?;
+        .map_err(|e| parse_error(format!("{e:?}")))?
+        .ok_or_else(|| parse_error("no items".to_string()))?;
 
     let mut type_checker = TypeChecker::new();
     type_checker.check_file(&file)
 }
 
+/// Type checks a file, naming it in the positions of errors
+pub fn type_check_named_file(file_name: &str, file_contents: &str) -> Result<(), TypeCheckError> {
+    type_check_file(file_contents).map_err(|e| e.with_file_name(file_name))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use neco_cic::{
+        local_context::LocalContext,
+        term::{Sort, TermApplication, TermConstant, TermLambda, TermSort},
+        typechecker::{TypeError, check_type},
+    };
 
     #[test]
     fn test_type_check_eq_and_nat() {
@@ -375,11 +746,15 @@ mod tests {
     fn test_type_check_eq_and_nat_fail_1() {
         let file_contents =
             std::fs::read_to_string("../../testcases/felis/single/eq_and_nat_fail_1.fe").unwrap();
-        let result = type_check_file(&file_contents);
-        eprintln!("result = {result:?}");
-        assert!(
-            result.is_err(),
-            "Type checking should have failed but succeeded: {result:?}"
+        let result = type_check_named_file("eq_and_nat_fail_1.fe", &file_contents);
+        let error = result.unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            TypeCheckErrorKind::TypeMismatch { .. }
+        ));
+        assert_eq!(
+            error.to_string(),
+            "Type mismatch: expected `eq nat (add O (S O)) (add (S O) (S O))`, found `eq nat (S O) (S O)` at eq_and_nat_fail_1.fe:18:5"
         );
     }
 
@@ -434,6 +809,474 @@ mod tests {
         assert!(result.is_ok(), "Type checking failed: {result:?}");
     }
 
+    #[test]
+    fn test_type_check_eq_and_nat_implicit() {
+        let file_contents =
+            std::fs::read_to_string("../../testcases/felis/single/eq_and_nat_implicit.fe").unwrap();
+        let result = type_check_file(&file_contents);
+        assert!(result.is_ok(), "Type checking failed: {result:?}");
+    }
+
+    #[test]
+    fn test_implicit_arguments_are_inserted() {
+        let source =
+            std::fs::read_to_string("../../testcases/felis/single/eq_and_nat_implicit.fe").unwrap();
+        let type_checker = check_source(&source).unwrap();
+        let env = &type_checker.global_env;
+        let theorem = env
+            .get_constant(type_checker.name_to_id["add_1_1_eq_2"])
+            .unwrap();
+        let printer = Printer::new(&type_checker.names);
+        assert_eq!(
+            printer.print(&theorem.ty),
+            "eq nat (add (S O) (S O)) (id nat (S (S O)))"
+        );
+        assert_eq!(
+            printer.print(theorem.body.as_ref().unwrap()),
+            "eq_refl nat (S (S O))"
+        );
+    }
+
+    #[test]
+    fn test_unsolved_implicit_argument_is_reported() {
+        let source = "#inductive nat : Set {
+    O: nat,
+}
+
+#definition const : {A : Set} -> nat {
+    O
+}
+
+#definition zero : nat {
+    const
+}
+";
+        let result = check_source(source);
+        assert_eq!(
+            result.err().map(|e| e.to_string()),
+            Some("Unsolved metavariable ?0 : `Set` at 10:5".to_string())
+        );
+    }
+
+    #[test]
+    fn test_implicit_argument_mismatch_is_reported() {
+        let source = std::fs::read_to_string("../../testcases/felis/single/eq_and_nat_implicit.fe")
+            .unwrap()
+            + "
+#theorem wrong : eq (S O) O {
+    eq_refl O
+}
+";
+        let result = check_source(&source);
+        assert_eq!(
+            result.err().map(|e| e.to_string()),
+            Some(
+                "Type mismatch: expected `eq nat (S O) O`, found `eq nat O O` at 30:5".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_axioms_are_tracked_through_dependencies() {
+        let source = std::fs::read_to_string("../../testcases/felis/single/axiom.fe").unwrap();
+        let type_checker = check_source(&source).unwrap();
+        assert_eq!(
+            type_checker.axioms_of("uses_axiom_indirectly"),
+            Ok(vec!["zero_is_one".to_string()])
+        );
+        assert_eq!(type_checker.axioms_of("no_axiom"), Ok(vec![]));
+        assert_eq!(
+            type_checker.axioms_of("both_axioms"),
+            Ok(vec!["one".to_string()])
+        );
+        assert_eq!(type_checker.axioms_of("one"), Ok(vec!["one".to_string()]));
+        assert!(type_checker.axioms_of("missing").is_err());
+    }
+
+    #[test]
+    fn test_inspection_commands() {
+        let source = std::fs::read_to_string("../../testcases/felis/single/commands.fe").unwrap();
+        let type_checker = check_source(&source).unwrap();
+        insta::assert_snapshot!(type_checker.diagnostics().join("\n"));
+    }
+
+    #[test]
+    fn test_print_unknown_name_is_reported() {
+        let result = check_source("#print missing\n");
+        assert_eq!(
+            result.err().map(|e| e.to_string()),
+            Some("Unknown constant: missing at 1:8".to_string())
+        );
+    }
+
+    #[test]
+    fn test_holes_are_reported_as_goals() {
+        let source = std::fs::read_to_string("../../testcases/felis/single/holes.fe").unwrap();
+        let type_checker = check_source(&source).unwrap();
+        let goals: Vec<String> = type_checker
+            .goals()
+            .iter()
+            .map(|goal| goal.to_string())
+            .collect();
+        insta::assert_snapshot!(goals.join("\n"));
+
+        // Items with holes are assumed
+        assert_eq!(
+            type_checker.axioms_of("zero"),
+            Ok(vec!["add_zero".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_holes_need_an_expected_type() {
+        let source = "#inductive nat : Set {
+    O: nat,
+}
+
+#check ?x;
+";
+        assert_eq!(
+            check_source(source).err().map(|e| e.to_string()),
+            Some("Cannot infer the type of the hole ?x at 5:8".to_string())
+        );
+    }
+
+    #[test]
+    fn test_tactics_prove_theorems() {
+        let source = std::fs::read_to_string("../../testcases/felis/single/tactics.fe").unwrap();
+        let type_checker = check_source(&source).unwrap();
+        assert_eq!(type_checker.axioms_of("add_O_r"), Ok(Vec::new()));
+
+        // The binders the tactics introduce are named
+        let type_checker =
+            check_source(&format!("{source}\n#print S_cong\n#print add_O_r\n")).unwrap();
+        let printed = type_checker.diagnostics();
+        assert!(!printed[0].contains("Id("), "{}", printed[0]);
+        assert_eq!(
+            printed[1],
+            "#theorem add_O_r : (n : nat) -> eq nat (add n O) n {
+    fun (n : nat) => #match n { O => eq_refl nat O, S n0 => (fun (A : Set) => fun (x : A) => \
+             fun (i : A) => fun (e : eq A x i) => #match e { eq_refl A' x' => \
+             fun (P : A' -> Prop) => fun (H : P x') => H }) nat (add n0 O) n0 (add_O_r n0) \
+             (fun (t : nat) => eq nat (S t) (S n0)) (eq_refl nat (S n0)) }
+}"
+        );
+    }
+
+    #[test]
+    fn test_kernel_rejects_tactic_proofs_with_a_wrong_branch() {
+        let source = std::fs::read_to_string("../../testcases/felis/single/tactics.fe").unwrap();
+        let type_checker = check_source(&source).unwrap();
+        let id = |name: &str| type_checker.name_to_id[name];
+        let constant = |name: &str| Term::Constant(TermConstant { id: id(name) });
+        let env = &type_checker.global_env;
+        let add_o_r = env.get_constant(id("add_O_r")).unwrap();
+        let Some(Term::Lambda(lambda)) = add_o_r.body.as_deref() else {
+            panic!("induction builds a match under the introduced variable");
+        };
+        let Term::Match(case) = lambda.target.as_ref() else {
+            panic!("induction builds a match under the introduced variable");
+        };
+
+        // The branch of `O` proves `eq nat (S O) (S O)` instead of `eq nat (add O O) O`
+        let mut case = case.clone();
+        case.branches[0].body = Rc::new(Term::Application(TermApplication {
+            f: Rc::new(constant("eq_refl")),
+            args: vec![
+                constant("nat"),
+                Term::Application(TermApplication {
+                    f: Rc::new(constant("S")),
+                    args: vec![constant("O")],
+                }),
+            ],
+        }));
+        let tampered = Term::Lambda(TermLambda {
+            target: Rc::new(Term::Match(case)),
+            ..lambda.clone()
+        });
+        let ctx = LocalContext::new();
+        let body = add_o_r.body.as_deref().unwrap();
+        assert_eq!(check_type(&ctx, env, body, &add_o_r.ty), Ok(()));
+        assert!(matches!(
+            check_type(&ctx, env, &tampered, &add_o_r.ty),
+            Err(TypeError::TypeMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_failing_tactic_shows_the_remaining_goals() {
+        let source = "#inductive eq : {A : Set} -> (x : A) -> A -> Prop {
+    eq_refl: {A : Set} -> (x : A) -> eq x x,
+}
+
+#inductive nat : Set {
+    O: nat,
+    S: nat -> nat,
+}
+
+#theorem wrong : (n : nat) -> eq n O {
+    #by {
+        intro n;
+        induction n;
+        reflexivity;
+        reflexivity;
+    }
+}
+";
+        let result = check_source(source);
+        insta::assert_snapshot!(result.err().unwrap().to_string());
+    }
+
+    #[test]
+    fn test_mutual_blocks() {
+        let source = std::fs::read_to_string("../../testcases/felis/single/mutual.fe").unwrap();
+        let type_checker = check_source(&source).unwrap();
+        assert_eq!(
+            type_checker.diagnostics(),
+            ["S (S (S O))".to_string(), "true".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_non_positive_inductive_is_rejected() {
+        let source = "#inductive nat : Set {
+    O: nat,
+}
+
+#inductive bad : Set {
+    mk: (bad -> nat) -> bad,
+}
+";
+        assert_eq!(
+            check_source(source).err().map(|e| e.to_string()),
+            Some("Non strictly positive occurrence of bad in constructor mk at 6:5".to_string())
+        );
+    }
+
+    #[test]
+    fn test_non_positive_occurrence_in_a_mutual_block_names_the_occurring_type() {
+        let source = "#inductive nat : Set {
+    O: nat,
+}
+
+#mutual {
+    #inductive bad : Set {
+        mk_bad: (evil -> nat) -> bad,
+    }
+
+    #inductive evil : Set {
+        mk_evil: bad -> evil,
+    }
+}
+";
+        assert_eq!(
+            check_source(source).err().map(|e| e.to_string()),
+            Some(
+                "Non strictly positive occurrence of evil in constructor mk_bad at 7:9".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_non_structural_mutual_recursion_is_rejected() {
+        let source = "#inductive nat : Set {
+    O: nat,
+    S: nat -> nat,
+}
+
+#mutual {
+    #definition f : (n : nat) -> nat {
+        g (S n)
+    }
+
+    #definition g : (n : nat) -> nat {
+        #match n {
+            O => O,
+            S p => f p,
+        }
+    }
+}
+";
+        assert_eq!(
+            check_source(source).err().map(|e| e.to_string()),
+            Some(
+                "Cannot show that the recursive calls of f, g terminate: no argument decreases structurally at 7:17"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_mutual_block_mixing_items_is_rejected() {
+        let source = "#inductive nat : Set {
+    O: nat,
+}
+
+#mutual {
+    #inductive unit : Set {
+        tt: unit,
+    }
+
+    #definition zero : nat {
+        O
+    }
+}
+";
+        assert_eq!(
+            check_source(source).err().map(|e| e.to_string()),
+            Some(
+                "A #mutual block contains either only inductive types or only definitions at 5:1"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_well_founded_recursion() {
+        let source =
+            std::fs::read_to_string("../../testcases/felis/single/well_founded.fe").unwrap();
+        let type_checker = check_source(&source).unwrap();
+        assert_eq!(
+            type_checker.diagnostics(),
+            [
+                "S (S (S O))".to_string(),
+                "S (S O)".to_string(),
+                "div does not depend on any axioms".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_decreasing_by_needs_the_accessibility_builtin() {
+        let source = "#inductive nat : Set {
+    O: nat,
+}
+
+#definition f : (n : nat) -> nat #decreasing_by wf {
+    n
+}
+";
+        let error = check_source(source).err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "Acc is not declared: add #use_builtin \"Acc\" #as Acc; at 5:34"
+        );
+    }
+
+    #[test]
+    fn test_informative_proofs_do_not_eliminate_into_set() {
+        let source = "#inductive nat : Set {
+    O: nat,
+    S: nat -> nat,
+}
+
+#inductive either : Prop {
+    left: either,
+    right: either,
+}
+
+#definition choose : (h : either) -> nat {
+    #match h {
+        left => O,
+        right => S O,
+    }
+}
+";
+        assert_eq!(
+            check_source(source).err().map(|e| e.to_string()),
+            Some(
+                "Cannot match on a proof of either to build a value in Set: only empty and singleton propositions eliminate into Set and Type at 12:12"
+                    .to_string()
+            )
+        );
+
+        // Proving a proposition by cases is fine
+        let source = source.replace("(h : either) -> nat", "(h : either) -> either");
+        let source = source.replace("S O,", "left,").replace("O,", "right,");
+        assert!(check_source(&source).is_ok());
+    }
+
+    #[test]
+    fn test_proofs_are_irrelevant() {
+        let source = "#inductive eq : {A : Prop} -> (x : A) -> A -> Prop {
+    eq_refl: {A : Prop} -> (x : A) -> eq x x,
+}
+
+#inductive True : Prop {
+    I: True,
+}
+
+#axiom p : True;
+
+#theorem p_is_I : eq p I {
+    eq_refl p
+}
+";
+        assert!(check_source(source).is_ok());
+
+        let source =
+            std::fs::read_to_string("../../testcases/felis/single/proof_irrelevance.fe").unwrap();
+        assert!(check_source(&source).is_ok());
+    }
+
+    #[test]
+    fn test_theorems_are_opaque() {
+        let source = "#inductive eq : (A : Set) -> (x : A) -> A -> Prop {
+    eq_refl: (A : Set) -> (x : A) -> eq A x x,
+}
+
+#inductive nat : Set {
+    O: nat,
+}
+
+#definition zero : nat {
+    O
+}
+
+#theorem zero_thm : nat {
+    O
+}
+
+#theorem zero_unfolds : eq nat zero O {
+    eq_refl nat O
+}
+";
+        assert!(check_source(source).is_ok());
+
+        let opaque = source.replace(
+            "zero_unfolds : eq nat zero O",
+            "zero_unfolds : eq nat zero_thm O",
+        );
+        assert_eq!(
+            check_source(&opaque).err().map(|e| e.to_string()),
+            Some(
+                "Type mismatch: expected `eq nat zero_thm O`, found `eq nat O O` at 18:5"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_unknown_variable_is_reported_at_its_position() {
+        let source = "#inductive nat : Set {
+    O: nat,
+}
+
+#definition zero : nat {
+    Z
+}
+";
+        let error = check_source(source).err().unwrap();
+        assert_eq!(
+            error.kind(),
+            &TypeCheckErrorKind::UnknownVariable("Z".to_string())
+        );
+        assert_eq!(
+            error.with_file_name("zero.fe").to_string(),
+            "Unknown variable: Z at zero.fe:6:5"
+        );
+    }
+
     #[test]
     fn test_theorem_type_matches_proof_type() {
         let mut type_checker = TypeChecker::new();
@@ -447,6 +1290,7 @@ mod tests {
             name: x,
             body: None,
             ty: Rc::new(theorem_type.clone()),
+            opaque: false,
         };
         type_checker.global_env.add_constant(const_def).unwrap();
 
@@ -458,7 +1302,7 @@ mod tests {
         let theorem_reduced = reduction::normalize(&theorem_type);
         let proof_reduced = reduction::normalize(&proof_type);
 
-        assert!(typechecker::is_convertible(
+        assert!(conversion::is_convertible(
             &local_ctx,
             &type_checker.global_env,
             &theorem_reduced,
@@ -480,6 +1324,7 @@ mod tests {
             name: x,
             body: None,
             ty: Rc::new(proof_type_decl),
+            opaque: false,
         };
         type_checker.global_env.add_constant(const_def).unwrap();
 
@@ -491,11 +1336,147 @@ mod tests {
         let theorem_reduced = reduction::normalize(&theorem_type);
         let proof_reduced = reduction::normalize(&proof_type);
 
-        assert!(!typechecker::is_convertible(
+        assert!(!conversion::is_convertible(
             &local_ctx,
             &type_checker.global_env,
             &theorem_reduced,
             &proof_reduced
         ));
     }
+
+    #[test]
+    fn test_binders_are_not_global_names() {
+        let source = large_add_source(1, 1, 2).replace(
+            "#theorem add_large",
+            "#theorem leaked : eq nat p p {\n    eq_refl nat p\n}\n\n#theorem add_large",
+        );
+        let result = check_source(&source);
+        assert_eq!(
+            result.err().map(|e| e.kind().clone()),
+            Some(TypeCheckErrorKind::UnknownVariable("p".to_string()))
+        );
+    }
+
+    /// Number of nodes of a term seen as a tree
+    fn tree_size(term: &Term) -> usize {
+        1 + match term {
+            Term::Sort(_)
+            | Term::Variable(_)
+            | Term::Bound(_)
+            | Term::Constant(_)
+            | Term::Literal(_) => 0,
+            Term::Product(product) => tree_size(&product.source) + tree_size(&product.target),
+            Term::Lambda(lambda) => tree_size(&lambda.source_ty) + tree_size(&lambda.target),
+            Term::Application(app) => {
+                tree_size(&app.f) + app.args.iter().map(tree_size).sum::<usize>()
+            }
+            Term::LetIn(let_in) => {
+                tree_size(&let_in.term) + tree_size(&let_in.ty) + tree_size(&let_in.body)
+            }
+            Term::Match(case) => {
+                tree_size(&case.scrutinee)
+                    + tree_size(&case.return_type)
+                    + case
+                        .branches
+                        .iter()
+                        .map(|b| tree_size(&b.body))
+                        .sum::<usize>()
+            }
+        }
+    }
+
+    #[test]
+    fn test_test_theorems_are_hash_consed() {
+        for path in [
+            "../../testcases/felis/single/eq_and_nat.fe",
+            "../../testcases/felis/single/eq_and_nat_correct_2.fe",
+            "../../testcases/felis/single/eq_and_nat_correct_3.fe",
+        ] {
+            let before = intern::stats();
+            let type_checker = check_source(&std::fs::read_to_string(path).unwrap()).unwrap();
+            let after = intern::stats();
+
+            let env = &type_checker.global_env;
+            let mut tree_nodes = 0;
+            for def in env.constants.values() {
+                assert!(intern::is_interned(&def.ty));
+                tree_nodes += tree_size(&def.ty);
+                if let Some(body) = &def.body {
+                    assert!(intern::is_interned(body));
+                    tree_nodes += tree_size(body);
+                }
+            }
+            for inductive in env.inductives.list_inductives() {
+                for constructor in &inductive.constructors {
+                    tree_nodes += tree_size(&constructor.ty);
+                }
+            }
+
+            let shared_nodes = after.nodes - before.nodes;
+            assert!(shared_nodes < tree_nodes);
+        }
+    }
+
+    /// Unary numeral `S (S ... O)` in Felis syntax
+    fn numeral(n: usize) -> String {
+        (0..n).fold("O".to_string(), |acc, _| format!("(S {acc})"))
+    }
+
+    /// `add n m = add m n`, proved by reflexivity on the numeral `proof`
+    fn large_add_source(n: usize, m: usize, proof: usize) -> String {
+        format!(
+            "#inductive eq : (A : Set) -> (x : A) -> A -> Prop {{
+    eq_refl: (A : Set) -> (x : A) -> eq A x x,
+}}
+
+#inductive nat : Set {{
+    O: nat,
+    S: nat -> nat,
+}}
+
+#definition add : (n : nat) -> (m : nat) -> nat {{
+    #match n {{
+        O => m,
+        S p => S (add p m),
+    }}
+}}
+
+#theorem add_large : eq nat (add {} {}) (add {} {}) {{
+    eq_refl nat {}
+}}
+",
+            numeral(n),
+            numeral(m),
+            numeral(m),
+            numeral(n),
+            numeral(proof)
+        )
+    }
+
+    pub(crate) fn check_source(source: &str) -> Result<TypeChecker, TypeCheckError> {
+        let mut file_id_generator = FileIdGenerator::new();
+        let file_id = file_id_generator.generate_file_id();
+        let tokens = Token::lex(source, file_id);
+        let mut i = 0;
+        let file = File::parse(&tokens, &mut i).unwrap().unwrap();
+
+        let mut type_checker = TypeChecker::new();
+        type_checker.check_file(&file)?;
+        Ok(type_checker)
+    }
+
+    #[test]
+    fn test_type_check_large_add_theorem() {
+        let result = check_source(&large_add_source(40, 25, 65));
+        assert!(result.is_ok(), "Type checking failed: {:?}", result.err());
+    }
+
+    #[test]
+    fn test_type_check_large_add_theorem_fail() {
+        let result = check_source(&large_add_source(40, 25, 64));
+        assert!(
+            result.is_err(),
+            "Type checking should have failed but succeeded"
+        );
+    }
 }
@@ -0,0 +1,471 @@
+use neco_felis_rename::{VariableId, phase_renamed::PhaseRenamed};
+use neco_felis_syn::*;
+
+use crate::{ProcSignature, ProcType, TypeCheckError, TypeChecker};
+
+/// The phase after type checking, where the values of procedures carry
+/// their machine types, and names the ids the renamer gave them
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PhaseTyped();
+
+impl Phase for PhaseTyped {
+    type FileExt = ();
+    type ItemDefinitionExt = ();
+    type ItemInductiveExt = ();
+    type ItemInductiveBranchExt = ();
+    type ItemTheoremExt = ();
+    type ItemAxiomExt = ();
+    type ItemPrintAxiomsExt = ();
+    type ItemCheckExt = ();
+    type ItemEvalExt = ();
+    type ItemPrintExt = ();
+    type ItemMutualExt = ();
+    type ItemEntrypointExt = ();
+    type ItemAllowExt = ();
+    type ItemBuiltinExt = VariableId;
+    type TermApplyExt = ();
+    type TermArrowDepExt = ();
+    type TermArrowImplicitExt = ();
+    type TermArrowNodepExt = ();
+    type TermByExt = ();
+    type TermByTacticExt = ();
+    type TermHoleExt = ();
+    type TermMatchExt = ();
+    type TermMatchBranchExt = ();
+    type TermParenExt = ();
+    type TermVariableExt = VariableId;
+    type TermStringExt = ();
+    type StatementsExt = ();
+    type StatementsThenExt = ();
+    type ItemProcBlockExt = ();
+    type ItemProcExt = ProcSignature;
+    type TermUnitExt = ();
+    type TermNumberExt = ();
+    /// The fields of the items
+    type ItemArrayExt = Vec<(String, ProcType)>;
+    type ItemStructExt = Vec<(String, ProcType)>;
+    type ItemStructureExt = ();
+    type TermStructExt = ();
+    type TermFieldAccessExt = ();
+    type TermConstructorCallExt = ();
+    type StatementExt = ();
+    type StatementLoopExt = ();
+    type StatementBreakExt = ();
+    type StatementAssignExt = VariableId;
+    type StatementFieldAssignExt = ();
+    /// The bound variables and the type of the value
+    type StatementLetExt = (VariableId, ProcType);
+    type StatementLetMutExt = (VariableId, VariableId, ProcType);
+    type ProcTermExt = ();
+    type ProcTermApplyExt = ProcType;
+    type ProcTermVariableExt = (VariableId, ProcType);
+    type ProcTermParenExt = ProcType;
+    type ProcTermUnitExt = ProcType;
+    type ProcTermNumberExt = ProcType;
+    /// The object, and a reference for an element of an array
+    type ProcTermFieldAccessExt = (VariableId, ProcType);
+    type ProcTermConstructorCallExt = ProcType;
+    type ProcTermDereferenceExt = ProcType;
+    type ProcTermIfExt = ProcType;
+    type ProcTermStructValueExt = ProcType;
+    type StatementReturnExt = ();
+    type StatementCallPtxExt = ();
+}
+
+/// The type of the value of a procedure term
+pub fn proc_term_type(term: &ProcTerm<PhaseTyped>) -> ProcType {
+    match term {
+        ProcTerm::Paren(paren) => paren.ext.clone(),
+        ProcTerm::Apply(apply) => apply.ext.clone(),
+        ProcTerm::Variable(var) => var.ext.1.clone(),
+        ProcTerm::Unit(unit) => unit.ext.clone(),
+        ProcTerm::Number(number) => number.ext.clone(),
+        ProcTerm::FieldAccess(access) => access.ext.1.clone(),
+        ProcTerm::ConstructorCall(call) => call.ext.clone(),
+        ProcTerm::StructValue(value) => value.ext.clone(),
+        ProcTerm::If(if_expr) => if_expr.ext.clone(),
+        ProcTerm::Dereference(dereference) => dereference.ext.clone(),
+        ProcTerm::Struct(_) | ProcTerm::Ext(()) => ProcType::Unit,
+    }
+}
+
+impl TypeChecker {
+    /// Type checks `file`, returning its renamed items with the types of the
+    /// values of its procedures
+    pub fn type_file(
+        &mut self,
+        file: &File<PhaseParse>,
+        renamed: &File<PhaseRenamed>,
+    ) -> Result<File<PhaseTyped>, TypeCheckError> {
+        self.check_items(file, renamed)?;
+        let items = renamed
+            .items()
+            .iter()
+            .map(|item| self.typed_item(item))
+            .collect();
+        Ok(File { items, ext: () })
+    }
+
+    /// Type checks `source` as `type_file`, loading the unchanged items from
+    /// `cache` as `check_source` does
+    pub fn type_source(
+        &mut self,
+        source: &str,
+        file_id: FileId,
+        cache: Option<&str>,
+    ) -> Result<File<PhaseTyped>, TypeCheckError> {
+        let renamed = self.check_source(source, file_id, cache)?;
+        let items = renamed
+            .items()
+            .iter()
+            .map(|item| self.typed_item(item))
+            .collect();
+        Ok(File { items, ext: () })
+    }
+
+    fn typed_item(&self, item: &Item<PhaseRenamed>) -> Item<PhaseTyped> {
+        match item {
+            Item::Definition(definition) => Item::Definition(ItemDefinition {
+                keyword_definition: definition.keyword_definition.clone(),
+                name: definition.name.clone(),
+                colon: definition.colon.clone(),
+                type_: Box::new(typed_term(definition.type_())),
+                decreasing_by: definition.decreasing_by().map(|decreasing_by| {
+                    DefinitionDecreasingBy {
+                        keyword_decreasing_by: decreasing_by.keyword_decreasing_by.clone(),
+                        proof: Box::new(typed_term(decreasing_by.proof())),
+                    }
+                }),
+                brace_l: definition.brace_l.clone(),
+                body: Box::new(typed_term(definition.body())),
+                brace_r: definition.brace_r.clone(),
+                ext: (),
+            }),
+            Item::Inductive(inductive) => Item::Inductive(ItemInductive {
+                keyword_inductive: inductive.keyword_inductive.clone(),
+                name: inductive.name.clone(),
+                colon: inductive.colon.clone(),
+                ty: Box::new(typed_term(inductive.ty())),
+                brace_l: inductive.brace_l.clone(),
+                branches: inductive
+                    .branches()
+                    .iter()
+                    .map(|branch| ItemInductiveBranch {
+                        name: branch.name.clone(),
+                        colon: branch.colon.clone(),
+                        ty: Box::new(typed_term(branch.ty())),
+                        comma: branch.comma.clone(),
+                        ext: (),
+                    })
+                    .collect(),
+                brace_r: inductive.brace_r.clone(),
+                ext: (),
+            }),
+            Item::Theorem(theorem) => Item::Theorem(ItemTheorem {
+                keyword_theorem: theorem.keyword_theorem.clone(),
+                name: theorem.name.clone(),
+                colon: theorem.colon.clone(),
+                type_: Box::new(typed_term(theorem.type_())),
+                brace_l: theorem.brace_l.clone(),
+                body: Box::new(typed_term(theorem.body())),
+                brace_r: theorem.brace_r.clone(),
+                ext: (),
+            }),
+            Item::Axiom(axiom) => Item::Axiom(ItemAxiom {
+                keyword_axiom: axiom.keyword_axiom.clone(),
+                name: axiom.name.clone(),
+                colon: axiom.colon.clone(),
+                type_: Box::new(typed_term(axiom.type_())),
+                semicolon: axiom.semicolon.clone(),
+                ext: (),
+            }),
+            Item::PrintAxioms(print_axioms) => Item::PrintAxioms(ItemPrintAxioms {
+                keyword_print_axioms: print_axioms.keyword_print_axioms.clone(),
+                name: print_axioms.name.clone(),
+                ext: (),
+            }),
+            Item::Check(check) => Item::Check(ItemCheck {
+                keyword_check: check.keyword_check.clone(),
+                term: Box::new(typed_term(check.term())),
+                semicolon: check.semicolon.clone(),
+                ext: (),
+            }),
+            Item::Eval(eval) => Item::Eval(ItemEval {
+                keyword_eval: eval.keyword_eval.clone(),
+                term: Box::new(typed_term(eval.term())),
+                semicolon: eval.semicolon.clone(),
+                ext: (),
+            }),
+            Item::Print(print) => Item::Print(ItemPrint {
+                keyword_print: print.keyword_print.clone(),
+                name: print.name.clone(),
+                ext: (),
+            }),
+            Item::Mutual(mutual) => Item::Mutual(ItemMutual {
+                keyword_mutual: mutual.keyword_mutual.clone(),
+                brace_l: mutual.brace_l.clone(),
+                items: mutual
+                    .items()
+                    .iter()
+                    .map(|item| self.typed_item(item))
+                    .collect(),
+                brace_r: mutual.brace_r.clone(),
+                ext: (),
+            }),
+            Item::Structure(structure) => Item::Structure(ItemStructure {
+                keyword_structure: structure.keyword_structure.clone(),
+                name: structure.name.clone(),
+                colon: structure.colon.clone(),
+                ty: Box::new(typed_term(structure.ty())),
+                brace_l: structure.brace_l.clone(),
+                fields: structure.fields().iter().map(typed_struct_field).collect(),
+                brace_r: structure.brace_r.clone(),
+                ext: (),
+            }),
+            Item::Entrypoint(entrypoint) => Item::Entrypoint(ItemEntrypoint {
+                keyword_entrypoint: entrypoint.keyword_entrypoint.clone(),
+                name: entrypoint.name.clone(),
+                semicolon: entrypoint.semicolon.clone(),
+                ext: (),
+            }),
+            Item::Allow(allow) => Item::Allow(ItemAllow {
+                keyword_allow: allow.keyword_allow.clone(),
+                lint: allow.lint.clone(),
+                semicolon: allow.semicolon.clone(),
+                ext: (),
+            }),
+            Item::UseBuiltin(use_builtin) => Item::UseBuiltin(ItemUseBuiltin {
+                keyword_use_builtin: use_builtin.keyword_use_builtin.clone(),
+                builtin_name: use_builtin.builtin_name.clone(),
+                keyword_as: use_builtin.keyword_as.clone(),
+                name: use_builtin.name.clone(),
+                semicolon: use_builtin.semicolon.clone(),
+                ext: use_builtin.ext.clone(),
+            }),
+            Item::Proc(proc) => Item::Proc(Box::new(self.procedures.typed[proc.name.s()].clone())),
+            Item::Array(array) => Item::Array(ItemArray {
+                keyword_array: array.keyword_array.clone(),
+                name: array.name.clone(),
+                brace_l: array.brace_l.clone(),
+                fields: array
+                    .fields()
+                    .iter()
+                    .map(|field| ItemArrayField {
+                        keyword: field.keyword.clone(),
+                        colon: field.colon.clone(),
+                        value: Box::new(typed_term(&field.value)),
+                        comma: field.comma.clone(),
+                    })
+                    .collect(),
+                brace_r: array.brace_r.clone(),
+                ext: self.procedures.arrays[array.name().s()].clone(),
+            }),
+            Item::Struct(item_struct) => Item::Struct(ItemStruct {
+                keyword_struct: item_struct.keyword_struct.clone(),
+                name: item_struct.name.clone(),
+                brace_l: item_struct.brace_l.clone(),
+                fields: item_struct
+                    .fields()
+                    .iter()
+                    .map(typed_struct_field)
+                    .collect(),
+                brace_r: item_struct.brace_r.clone(),
+                ext: self.procedures.structs[item_struct.name().s()].clone(),
+            }),
+        }
+    }
+}
+
+fn typed_struct_field(field: &ItemStructField<PhaseRenamed>) -> ItemStructField<PhaseTyped> {
+    ItemStructField {
+        name: field.name.clone(),
+        colon: field.colon.clone(),
+        ty: Box::new(typed_term(&field.ty)),
+        comma: field.comma.clone(),
+    }
+}
+
+/// A Felis term in the typed phase. Felis terms carry no machine types.
+pub(crate) fn typed_term(term: &Term<PhaseRenamed>) -> Term<PhaseTyped> {
+    match term {
+        Term::Variable(var) => Term::Variable(typed_variable(var)),
+        Term::Apply(apply) => Term::Apply(TermApply {
+            f: Box::new(typed_term(apply.f())),
+            args: apply.args().iter().map(typed_term).collect(),
+            ext: (),
+        }),
+        Term::ArrowDep(arrow) => Term::ArrowDep(TermArrowDep {
+            paren_l: arrow.paren_l.clone(),
+            from: typed_variable(arrow.from()),
+            colon: arrow.colon.clone(),
+            from_ty: Box::new(typed_term(arrow.from_ty())),
+            paren_r: arrow.paren_r.clone(),
+            arrow: arrow.arrow.clone(),
+            to: Box::new(typed_term(arrow.to())),
+            ext: (),
+        }),
+        Term::ArrowImplicit(arrow) => Term::ArrowImplicit(TermArrowImplicit {
+            brace_l: arrow.brace_l.clone(),
+            from: typed_variable(arrow.from()),
+            colon: arrow.colon.clone(),
+            from_ty: Box::new(typed_term(arrow.from_ty())),
+            brace_r: arrow.brace_r.clone(),
+            arrow: arrow.arrow.clone(),
+            to: Box::new(typed_term(arrow.to())),
+            ext: (),
+        }),
+        Term::ArrowNodep(arrow) => Term::ArrowNodep(TermArrowNodep {
+            from: Box::new(typed_term(arrow.from())),
+            arrow: arrow.arrow.clone(),
+            to: Box::new(typed_term(arrow.to())),
+            ext: (),
+        }),
+        Term::Match(match_term) => Term::Match(TermMatch {
+            keyword_match: match_term.keyword_match.clone(),
+            scrutinee: match_term.scrutinee.clone(),
+            brace_l: match_term.brace_l.clone(),
+            branches: match_term
+                .branches()
+                .iter()
+                .map(|branch| TermMatchBranch {
+                    pattern: branch.pattern.clone(),
+                    arrow: branch.arrow.clone(),
+                    body: Box::new(typed_term(branch.body())),
+                    ext: (),
+                })
+                .collect(),
+            brace_r: match_term.brace_r.clone(),
+            ext: (),
+        }),
+        Term::Paren(paren) => Term::Paren(TermParen {
+            paren_l: paren.paren_l.clone(),
+            term: Box::new(typed_term(paren.term())),
+            paren_r: paren.paren_r.clone(),
+            ext: (),
+        }),
+        Term::Unit(unit) => Term::Unit(TermUnit {
+            paren_l: unit.paren_l.clone(),
+            paren_r: unit.paren_r.clone(),
+            ext: (),
+        }),
+        Term::Number(number) => Term::Number(TermNumber {
+            number: number.number.clone(),
+            ext: (),
+        }),
+        Term::By(by) => Term::By(TermBy {
+            keyword_by: by.keyword_by.clone(),
+            brace_l: by.brace_l.clone(),
+            tactics: by
+                .tactics()
+                .iter()
+                .map(|tactic| TermByTactic {
+                    name: tactic.name.clone(),
+                    arg: tactic.arg().map(|arg| Box::new(typed_term(arg))),
+                    semicolon: tactic.semicolon.clone(),
+                    ext: (),
+                })
+                .collect(),
+            brace_r: by.brace_r.clone(),
+            ext: (),
+        }),
+        Term::Hole(hole) => Term::Hole(TermHole {
+            hole: hole.hole.clone(),
+            ext: (),
+        }),
+        Term::FieldAccess(field_access) => Term::FieldAccess(TermFieldAccess {
+            object: Box::new(typed_term(field_access.object())),
+            dot: field_access.dot.clone(),
+            field: field_access.field.clone(),
+            ext: (),
+        }),
+        Term::Struct(term_struct) => Term::Struct(TermStruct {
+            keyword_struct: term_struct.keyword_struct.clone(),
+            brace_l: term_struct.brace_l.clone(),
+            fields: term_struct
+                .fields()
+                .iter()
+                .map(|field| TermStructField {
+                    name: field.name.clone(),
+                    colon: field.colon.clone(),
+                    ty: Box::new(typed_term(&field.ty)),
+                    comma: field.comma.clone(),
+                })
+                .collect(),
+            brace_r: term_struct.brace_r.clone(),
+            ext: (),
+        }),
+    }
+}
+
+fn typed_variable(var: &TermVariable<PhaseRenamed>) -> TermVariable<PhaseTyped> {
+    TermVariable {
+        variable: var.variable.clone(),
+        ext: var.ext.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use neco_felis_rename::rename_file;
+
+    use super::*;
+
+    #[test]
+    fn test_procedure_values_carry_their_types() {
+        let source = "
+#use_builtin \"u64\" #as __u64;
+#use_builtin \"f32\" #as __f32;
+
+#array Points {
+    #item: #struct {
+        x: __f32,
+        count: __u64,
+    },
+    #dimension: 1,
+}
+
+#proc f : (ps : Points) -> __f32 {
+    #let x = ps .x 1 .*;
+    #let n = ps .count 1 .*;
+    x
+}
+";
+        let mut file_id_generator = FileIdGenerator::new();
+        let file_id = file_id_generator.generate_file_id();
+        let tokens = Token::lex(source, file_id);
+        let mut i = 0;
+        let file = File::parse(&tokens, &mut i).unwrap().unwrap();
+
+        let renamed = rename_file(&file).unwrap();
+        let file = TypeChecker::new().type_file(&file, &renamed).unwrap();
+        let Some(Item::Array(array)) = file.items().get(2) else {
+            panic!("expected the array");
+        };
+        assert_eq!(
+            array.ext,
+            vec![
+                ("x".to_string(), ProcType::F32),
+                ("count".to_string(), ProcType::U64)
+            ]
+        );
+
+        let Some(Item::Proc(proc)) = file.items().get(3) else {
+            panic!("expected the procedure");
+        };
+        assert_eq!(proc.ext.result, ProcType::F32);
+        let Statements::Then(first) = &proc.proc_block.statements else {
+            panic!("expected a statement sequence");
+        };
+        let Statement::Let(x) = &*first.head else {
+            panic!("expected `#let x`");
+        };
+        assert_eq!(x.ext.1, ProcType::F32);
+        let Statements::Then(second) = &*first.tail else {
+            panic!("expected a statement sequence");
+        };
+        let Statement::Let(n) = &*second.head else {
+            panic!("expected `#let n`");
+        };
+        assert_eq!(n.ext.1, ProcType::U64);
+    }
+}
@@ -0,0 +1,294 @@
+use std::rc::Rc;
+
+use neco_cic::{
+    id::Id,
+    primitive::Operation,
+    u64_spec::{Nat, U64Spec},
+};
+use neco_felis_syn::Pos;
+
+use crate::{TypeCheckError, TypeChecker};
+
+const OPERATIONS: [Operation; 6] = [
+    Operation::Add,
+    Operation::Sub,
+    Operation::Mul,
+    Operation::Div,
+    Operation::Mod,
+    Operation::Eq,
+];
+
+/// The builtins specifying each operation, with the names of the binders of
+/// their types. They are named after the operation.
+type Specs = [(
+    Operation,
+    &'static [(&'static str, &'static [&'static str])],
+); 5];
+
+const SPECS: Specs = [
+    (Operation::Add, &[("u64_add_spec", &["n", "m", "P", "h"])]),
+    (Operation::Sub, &[("u64_sub_spec", &["n", "m", "P", "h"])]),
+    (Operation::Mul, &[("u64_mul_spec", &["n", "m", "P", "h"])]),
+    (Operation::Div, &[("u64_div_spec", &["x", "y", "P", "h"])]),
+    (
+        Operation::Eq,
+        &[
+            ("u64_eq_spec", &["x", "y", "h", "P", "p"]),
+            ("u64_eq_refl", &["x", "P", "h"]),
+        ],
+    ),
+];
+
+/// The builtins `declare_u64_spec` and `#use_builtin "nat"` declare, to be
+/// loaded from environment files
+pub(crate) const SPEC_BUILTINS: [&str; 14] = [
+    "nat",
+    "nat_zero",
+    "nat_succ",
+    "nat_add",
+    "nat_mul",
+    "u64_of_nat",
+    "u64_of_nat_wraps",
+    "u64_of_nat_surjective",
+    "u64_add_spec",
+    "u64_sub_spec",
+    "u64_mul_spec",
+    "u64_div_spec",
+    "u64_eq_spec",
+    "u64_eq_refl",
+];
+
+impl TypeChecker {
+    /// `#use_builtin "u64" #as u64;` declares the primitive type of the
+    /// number literals, and `#use_builtin "u64_add" #as add;` and the like
+    /// its operations, which compute on literals. Other builtins are left to
+    /// the compiler.
+    ///
+    /// An operation declared before the type declares it too, as
+    /// `#use_builtin "u64" #as u64;` would. A later `#use_builtin "u64"` then
+    /// names that type.
+    ///
+    /// The operations come with their specification, see `declare_u64_spec`.
+    pub(crate) fn process_primitive(
+        &mut self,
+        builtin: &str,
+        name: &str,
+        pos: &Pos,
+    ) -> Result<(), TypeCheckError> {
+        if builtin == "u64" {
+            match self.builtins.get("u64") {
+                Some(&id) => {
+                    self.name_to_id.insert(name.to_string(), id);
+                    self.names.insert(id, name.to_string());
+                }
+                None => self.add_u64(name, pos)?,
+            }
+        } else if let Some(operation) = Operation::from_builtin(builtin) {
+            if !self.builtins.contains_key("u64") {
+                self.add_u64("u64", pos)?;
+            }
+            let id = self.declare(name);
+            self.global_env
+                .add_u64_operation(id, operation, &mut self.id_gen)
+                .map_err(|e| Self::environment_error(e, pos))?;
+            self.builtins.insert(operation.builtin(), id);
+            self.declare_u64_spec(pos)?;
+        }
+        Ok(())
+    }
+
+    /// Declares the axioms specifying the operations declared so far whose
+    /// specification is not declared yet and can be stated, see `U64Spec`.
+    /// Most of it is stated with the natural numbers of `#use_builtin "nat"`
+    /// through `u64_of_nat`, declared once `nat` and `u64_add` are. The
+    /// axioms of `u64_add` imported as `add` are named `add_spec`, and so on,
+    /// and `u64` imported as `u64` comes with `u64_of_nat`, `u64_of_nat_wraps`
+    /// and `u64_of_nat_surjective`.
+    pub(crate) fn declare_u64_spec(&mut self, pos: &Pos) -> Result<(), TypeCheckError> {
+        let Some(&u64) = self.builtins.get("u64") else {
+            return Ok(());
+        };
+        let nat = match (
+            self.builtins.get("nat"),
+            self.builtins.get("nat_zero"),
+            self.builtins.get("nat_succ"),
+            self.builtins.get("nat_add"),
+            self.builtins.get("nat_mul"),
+        ) {
+            (Some(&nat), Some(&zero), Some(&succ), Some(&add), Some(&mul)) => Some(Nat {
+                nat,
+                zero,
+                succ,
+                add,
+                mul,
+            }),
+            _ => None,
+        };
+        let mut spec = U64Spec {
+            u64,
+            nat,
+            of_nat: self.builtins.get("u64_of_nat").copied(),
+            operations: OPERATIONS
+                .iter()
+                .filter_map(|operation| {
+                    let id = self.builtins.get(operation.builtin())?;
+                    Some((*operation, *id))
+                })
+                .collect(),
+        };
+
+        if spec.of_nat.is_none()
+            && spec.nat.is_some()
+            && spec.operations.contains_key(&Operation::Add)
+        {
+            let name = self.builtin_name(u64, "u64");
+            let ids = ["of_nat", "of_nat_wraps", "of_nat_surjective"]
+                .map(|suffix| self.declare(&format!("{name}_{suffix}")));
+            spec.add_of_nat(&mut self.global_env, ids, &mut self.id_gen)
+                .map_err(|e| Self::environment_error(e, pos))?;
+            for (builtin, id, names) in [
+                ("u64_of_nat", ids[0], &["n"][..]),
+                ("u64_of_nat_wraps", ids[1], &["P", "h"]),
+                ("u64_of_nat_surjective", ids[2], &["P", "h", "x"]),
+            ] {
+                let ty = self.global_env.get_constant(id).unwrap().ty.clone();
+                self.name_binders(&ty, names, 0);
+                self.builtins.insert(builtin, id);
+            }
+        }
+
+        for (operation, builtins) in SPECS {
+            if self.builtins.contains_key(builtins[0].0) {
+                continue;
+            }
+            let Some(axioms) = spec.axioms(operation, &mut self.id_gen) else {
+                continue;
+            };
+            let name = self.builtin_name(spec.operations[&operation], operation.builtin());
+            for ((builtin, names), axiom) in builtins.iter().zip(axioms) {
+                let suffix = builtin.strip_prefix(operation.builtin()).unwrap();
+                let id = self.declare(&format!("{name}{suffix}"));
+                let axiom = Rc::new(axiom);
+                self.global_env
+                    .add_axiom(id, axiom.clone())
+                    .map_err(|e| Self::environment_error(e, pos))?;
+                self.name_binders(&axiom, names, 0);
+                self.builtins.insert(builtin, id);
+            }
+        }
+        Ok(())
+    }
+
+    /// The name a builtin is imported as
+    fn builtin_name(&self, id: Id, builtin: &str) -> String {
+        self.names
+            .get(&id)
+            .cloned()
+            .unwrap_or_else(|| builtin.to_string())
+    }
+
+    fn add_u64(&mut self, name: &str, pos: &Pos) -> Result<(), TypeCheckError> {
+        let id = self.declare(name);
+        self.global_env
+            .add_u64(id)
+            .map_err(|e| Self::environment_error(e, pos))?;
+        self.builtins.insert("u64", id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::TypeCheckErrorKind;
+
+    const U64: &str = "
+#use_builtin \"u64\" #as u64;
+#use_builtin \"u64_add\" #as u64_add;
+
+#inductive eq : {A : Set} -> (x : A) -> A -> Prop {
+    eq_refl: {A : Set} -> (x : A) -> eq x x,
+}
+";
+
+    #[test]
+    fn test_u64_arithmetic_computes_and_is_specified() {
+        let source = std::fs::read_to_string("../../testcases/felis/single/u64.fe").unwrap();
+        crate::tests::check_source(&source).unwrap();
+    }
+
+    #[test]
+    fn test_wrong_arithmetic_is_rejected() {
+        let source = format!(
+            "{U64}
+#theorem wrong : eq (u64_add 40 2) 41 {{
+    eq_refl 41
+}}
+"
+        );
+        assert!(crate::tests::check_source(&source).is_err());
+    }
+
+    #[test]
+    fn test_operations_declare_u64() {
+        let source = "
+#use_builtin \"u64_add\" #as add;
+#check add 1 2;
+#use_builtin \"u64\" #as nat64;
+#use_builtin \"u64_mul\" #as mul;
+#definition six : nat64 { mul 2 3 }
+#check add;
+";
+        let type_checker = crate::tests::check_source(source).unwrap();
+        assert_eq!(
+            type_checker.diagnostics(),
+            ["add 1 2 : u64", "add : nat64 -> nat64 -> nat64"]
+        );
+    }
+
+    #[test]
+    fn test_operations_come_with_their_specification() {
+        let source = "
+#use_builtin \"u64_eq\" #as eq64;
+#check eq64_refl;
+#use_builtin \"nat\" #as nat;
+#use_builtin \"u64_mul\" #as mul;
+#use_builtin \"u64_add\" #as add;
+#check u64_of_nat;
+#check mul_spec;
+#use_builtin \"u64_div\" #as div;
+#use_builtin \"u64_mod\" #as mod;
+#check div_spec;
+";
+        let type_checker = crate::tests::check_source(source).unwrap();
+        assert_eq!(
+            type_checker.diagnostics(),
+            [
+                "eq64_refl : (x : u64) -> (P : u64 -> Prop) -> P (eq64 x x) -> P 1",
+                "u64_of_nat : nat -> u64",
+                "mul_spec : (n : nat) -> (m : nat) -> (P : u64 -> Prop) \
+                 -> P (mul (u64_of_nat n) (u64_of_nat m)) -> P (u64_of_nat (nat_mul n m))",
+                "div_spec : (x : u64) -> (y : u64) -> (P : u64 -> Prop) \
+                 -> P (add (mul (div x y) y) (mod x y)) -> P x",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_literals_need_the_u64_builtin() {
+        let result = crate::tests::check_source("#definition answer : Set { 42 }");
+        assert_eq!(
+            result.err().map(|e| e.kind().clone()),
+            Some(TypeCheckErrorKind::MissingBuiltin("u64"))
+        );
+
+        let source = format!("{U64}\n#definition big : u64 {{ 18446744073709551616 }}\n");
+        assert_eq!(
+            crate::tests::check_source(&source)
+                .err()
+                .map(|e| e.kind().clone()),
+            Some(TypeCheckErrorKind::InvalidLiteral(
+                "18446744073709551616".to_string()
+            ))
+        );
+    }
+}
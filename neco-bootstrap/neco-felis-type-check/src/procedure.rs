@@ -0,0 +1,1158 @@
+use std::collections::HashMap;
+
+use neco_cic::term::{Term, TermProduct};
+use neco_felis_rename::{VariableId, phase_renamed::PhaseRenamed};
+use neco_felis_syn::{
+    ItemArray, ItemProc, ItemProcBlock, ItemStruct, Phase, PhaseParse, Pos, ProcContract, ProcTerm,
+    ProcTermApply, ProcTermConstructorCall, ProcTermDereference, ProcTermFieldAccess, ProcTermIf,
+    ProcTermIfElse, ProcTermNumber, ProcTermParen, ProcTermStructField, ProcTermStructValue,
+    ProcTermUnit, ProcTermVariable, Statement, StatementAssign, StatementBreak, StatementCallPtx,
+    StatementFieldAssign, StatementLet, StatementLetMut, StatementLoop, StatementReturn,
+    Statements, StatementsThen, Term as FTerm,
+};
+
+use crate::{
+    TypeCheckError, TypeCheckErrorKind, TypeChecker,
+    phase_typed::{PhaseTyped, proc_term_type, typed_term},
+};
+
+// Type checking of procedures.
+//
+// Procedures are not elaborated to kernel terms: they are checked against the
+// machine types the compiler lays them out with. Every value is a 64-bit word,
+// a 32-bit float, a struct, an array of structs in structure-of-arrays layout,
+// or a reference to a mutable variable or to an element of an array. The
+// values of Felis types computed by definitions are words: the backend passes
+// them around as pointers, which procedures may read through with `.*`.
+
+/// The type of a value in a procedure
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ProcType {
+    U64,
+    F32,
+    Unit,
+    Struct(String),
+    Array(String),
+    /// The address of a `#let #mut` variable or of an array element
+    Reference(Box<ProcType>),
+}
+
+impl std::fmt::Display for ProcType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProcType::U64 => write!(f, "u64"),
+            ProcType::F32 => write!(f, "f32"),
+            ProcType::Unit => write!(f, "()"),
+            ProcType::Struct(name) | ProcType::Array(name) => write!(f, "{name}"),
+            ProcType::Reference(ty) => write!(f, "&{ty}"),
+        }
+    }
+}
+
+/// The parameters and result of a procedure
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ProcSignature {
+    pub params: Vec<ProcType>,
+    pub result: ProcType,
+    /// Whether the procedure is a `#ptx` kernel, launched by `#call_ptx`
+    pub ptx: bool,
+}
+
+impl std::fmt::Display for ProcSignature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.params.is_empty() {
+            write!(f, "() -> ")?;
+        }
+        for param in &self.params {
+            write!(f, "{param} -> ")?;
+        }
+        write!(f, "{}", self.result)
+    }
+}
+
+/// The signature of a library function or value used in procedures
+enum BuiltinSignature {
+    Function(Vec<ProcType>, ProcType),
+    /// A value, such as the thread index in a `#ptx` procedure
+    Value(ProcType),
+    /// `syscall number a1 .. a5`, whose arguments are words or references
+    Syscall,
+}
+
+fn builtin_signature(builtin: &str) -> Option<BuiltinSignature> {
+    use ProcType::{F32, U64};
+    let signature = match builtin {
+        "u64_add" | "u64_sub" | "u64_mul" | "u64_div" | "u64_mod" | "u64_eq" => {
+            BuiltinSignature::Function(vec![U64, U64], U64)
+        }
+        "f32_add" | "f32_sub" | "f32_mul" | "f32_div" => {
+            BuiltinSignature::Function(vec![F32, F32], F32)
+        }
+        "u64_to_f32" => BuiltinSignature::Function(vec![U64], F32),
+        "f32_to_u64" => BuiltinSignature::Function(vec![F32], U64),
+        // The types convert literals, as in `__f32 0.5`
+        "u64" => BuiltinSignature::Function(vec![U64], U64),
+        "f32" => BuiltinSignature::Function(vec![F32], F32),
+        "tid_x" | "ntid_x" | "ctaid_x" => BuiltinSignature::Value(U64),
+        "syscall" => BuiltinSignature::Syscall,
+        _ => return None,
+    };
+    Some(signature)
+}
+
+/// The procedures, structs and arrays declared so far, and the `#use_builtin`
+/// names they may use
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Procedures {
+    /// Builtin names, by the name they are used under
+    builtins: HashMap<String, String>,
+    pub(crate) structs: HashMap<String, Vec<(String, ProcType)>>,
+    /// Fields of the items of the arrays
+    pub(crate) arrays: HashMap<String, Vec<(String, ProcType)>>,
+    signatures: HashMap<String, ProcSignature>,
+    /// The checked procedures, with the types of their values
+    pub(crate) typed: HashMap<String, ItemProc<PhaseTyped>>,
+}
+
+impl Procedures {
+    pub(crate) fn add_builtin(&mut self, name: &str, builtin: &str) {
+        self.builtins.insert(name.to_string(), builtin.to_string());
+    }
+
+    /// Forgets the procedure, struct, array or builtin named `name`
+    pub(crate) fn remove(&mut self, name: &str) {
+        self.builtins.remove(name);
+        self.structs.remove(name);
+        self.arrays.remove(name);
+        self.signatures.remove(name);
+        self.typed.remove(name);
+    }
+}
+
+/// The scope of the body of a procedure, whose locals are known by the ids
+/// the renamer gave them
+struct ProcChecker<'a> {
+    checker: &'a TypeChecker,
+    locals: Vec<(VariableId, ProcType)>,
+    result: ProcType,
+    loops: usize,
+}
+
+impl TypeChecker {
+    /// Checks the body of a procedure against its signature, and the
+    /// signature against the types declared so far. A procedure may call
+    /// itself and the procedures declared before it. The body is checked on
+    /// `renamed`, the procedure as the renamer left it.
+    pub(crate) fn process_proc(
+        &mut self,
+        proc: &ItemProc<PhaseParse>,
+        renamed: &ItemProc<PhaseRenamed>,
+    ) -> Result<(), TypeCheckError> {
+        let mut params = Vec::new();
+        let mut ty = proc.ty.as_ref();
+        let mut renamed_ty = renamed.ty.as_ref();
+        loop {
+            match (ty, renamed_ty) {
+                (FTerm::ArrowDep(arrow), FTerm::ArrowDep(renamed_arrow)) => {
+                    let id = renamed_arrow.from().ext.clone();
+                    params.push((Some(id), self.proc_type(arrow.from_ty())?));
+                    (ty, renamed_ty) = (arrow.to(), renamed_arrow.to());
+                }
+                (FTerm::ArrowNodep(arrow), FTerm::ArrowNodep(renamed_arrow)) => {
+                    if !matches!(arrow.from(), FTerm::Unit(_)) {
+                        params.push((None, self.proc_type(arrow.from())?));
+                    }
+                    (ty, renamed_ty) = (arrow.to(), renamed_arrow.to());
+                }
+                (FTerm::Paren(paren), FTerm::Paren(renamed_paren)) => {
+                    (ty, renamed_ty) = (paren.term(), renamed_paren.term());
+                }
+                _ => break,
+            }
+        }
+        let signature = ProcSignature {
+            params: params.iter().map(|(_, ty)| ty.clone()).collect(),
+            result: self.proc_type(ty)?,
+            ptx: proc.ptx_modifier.is_some(),
+        };
+        self.procedures
+            .signatures
+            .insert(proc.name.s().to_string(), signature.clone());
+
+        let mut checker = ProcChecker {
+            checker: self,
+            locals: params
+                .into_iter()
+                .filter_map(|(id, ty)| Some((id?, ty)))
+                .collect(),
+            result: signature.result.clone(),
+            loops: 0,
+        };
+        let (statements, ty) = checker.check_statements(&renamed.proc_block.statements)?;
+        if ty != ProcType::Unit && signature.result != ProcType::Unit {
+            checker.expect(&signature.result, &ty, None)?;
+        }
+        self.process_contracts(proc)?;
+
+        let typed = ItemProc {
+            ptx_modifier: proc.ptx_modifier.clone(),
+            keyword_proc: proc.keyword_proc.clone(),
+            name: proc.name.clone(),
+            colon: proc.colon.clone(),
+            ty: Box::new(typed_term(&renamed.ty)),
+            contracts: renamed
+                .contracts
+                .iter()
+                .map(|contract| ProcContract {
+                    keyword: contract.keyword.clone(),
+                    condition: Box::new(typed_term(&contract.condition)),
+                })
+                .collect(),
+            proc_block: ItemProcBlock {
+                brace_l: proc.proc_block.brace_l.clone(),
+                statements,
+                brace_r: proc.proc_block.brace_r.clone(),
+                ext: (),
+            },
+            ext: signature,
+        };
+        self.procedures
+            .typed
+            .insert(proc.name.s().to_string(), typed);
+        Ok(())
+    }
+
+    pub(crate) fn process_struct(
+        &mut self,
+        item: &ItemStruct<PhaseParse>,
+    ) -> Result<(), TypeCheckError> {
+        let fields = item
+            .fields()
+            .iter()
+            .map(|field| Ok((field.name.s().to_string(), self.proc_type(&field.ty)?)))
+            .collect::<Result<_, TypeCheckError>>()?;
+        self.procedures
+            .structs
+            .insert(item.name().s().to_string(), fields);
+        Ok(())
+    }
+
+    /// `#array A { #item: #struct { .. }, #dimension: 1 }`
+    pub(crate) fn process_array(
+        &mut self,
+        item: &ItemArray<PhaseParse>,
+    ) -> Result<(), TypeCheckError> {
+        let mut fields = Vec::new();
+        for field in item.fields() {
+            if field.keyword.s() == "item" {
+                let FTerm::Struct(item_struct) = field.value.as_ref() else {
+                    return Err(TypeCheckError::new(
+                        TypeCheckErrorKind::Unsupported("Array items other than structs"),
+                        Some(field.keyword.pos().clone()),
+                    ));
+                };
+                for struct_field in item_struct.fields() {
+                    let ty = self.proc_type(&struct_field.ty)?;
+                    fields.push((struct_field.name.s().to_string(), ty));
+                }
+            }
+        }
+        self.procedures
+            .arrays
+            .insert(item.name().s().to_string(), fields);
+        Ok(())
+    }
+
+    /// The machine type a Felis type stands for in a procedure signature or
+    /// a struct field
+    fn proc_type(&self, ty: &FTerm<PhaseParse>) -> Result<ProcType, TypeCheckError> {
+        match ty {
+            FTerm::Unit(_) => Ok(ProcType::Unit),
+            FTerm::Paren(paren) => self.proc_type(paren.term()),
+            FTerm::Variable(var) => {
+                let name = var.variable().s();
+                match self.procedures.builtins.get(name).map(String::as_str) {
+                    Some("u64") => Ok(ProcType::U64),
+                    Some("f32") => Ok(ProcType::F32),
+                    _ if self.procedures.structs.contains_key(name) => {
+                        Ok(ProcType::Struct(name.to_string()))
+                    }
+                    _ if self.procedures.arrays.contains_key(name) => {
+                        Ok(ProcType::Array(name.to_string()))
+                    }
+                    _ => Err(TypeCheckError::new(
+                        TypeCheckErrorKind::UnknownType(name.to_string()),
+                        Some(var.variable().pos().clone()),
+                    )),
+                }
+            }
+            _ => Err(TypeCheckError::new(
+                TypeCheckErrorKind::Unsupported("Procedure types other than names"),
+                crate::elaborate::term_pos(ty),
+            )),
+        }
+    }
+
+    /// The parameters of a definition called from a procedure, which are
+    /// words once its type parameters are erased, or `None` if `name` is not
+    /// a definition
+    fn definition_signature(&self, name: &str) -> Option<ProcSignature> {
+        let id = self.name_to_id.get(name)?;
+        let constant = self.global_env.get_constant(*id)?;
+        let mut params = Vec::new();
+        let mut ty = constant.ty.as_ref();
+        while let Term::Product(TermProduct { source, target, .. }) = ty {
+            if !matches!(source.as_ref(), Term::Sort(_)) {
+                params.push(ProcType::U64);
+            }
+            ty = target;
+        }
+        Some(ProcSignature {
+            params,
+            result: ProcType::U64,
+            ptx: false,
+        })
+    }
+}
+
+impl ProcChecker<'_> {
+    fn check_statements(
+        &mut self,
+        statements: &Statements<PhaseRenamed>,
+    ) -> Result<(Statements<PhaseTyped>, ProcType), TypeCheckError> {
+        match statements {
+            Statements::Then(then) => {
+                let (head, _) = self.check_statement(&then.head)?;
+                let (tail, ty) = self.check_statements(&then.tail)?;
+                let then = StatementsThen {
+                    head: Box::new(head),
+                    semicolon: then.semicolon.clone(),
+                    tail: Box::new(tail),
+                    ext: (),
+                };
+                Ok((Statements::Then(then), ty))
+            }
+            Statements::Statement(statement) => {
+                let (statement, ty) = self.check_statement(statement)?;
+                Ok((Statements::Statement(Box::new(statement)), ty))
+            }
+            Statements::Nil => Ok((Statements::Nil, ProcType::Unit)),
+        }
+    }
+
+    /// Checks a block in its own scope, returning the type of its value
+    fn check_block(
+        &mut self,
+        statements: &Statements<PhaseRenamed>,
+    ) -> Result<(Statements<PhaseTyped>, ProcType), TypeCheckError> {
+        let len = self.locals.len();
+        let block = self.check_statements(statements);
+        self.locals.truncate(len);
+        block
+    }
+
+    /// Checks a statement, returning its value for an expression
+    fn check_statement(
+        &mut self,
+        statement: &Statement<PhaseRenamed>,
+    ) -> Result<(Statement<PhaseTyped>, ProcType), TypeCheckError> {
+        let statement = match statement {
+            Statement::Let(let_stmt) => {
+                let value = self.infer(&let_stmt.value)?;
+                let ty = proc_term_type(&value);
+                self.locals.push((let_stmt.ext.clone(), ty.clone()));
+                Statement::Let(StatementLet {
+                    let_keyword: let_stmt.let_keyword.clone(),
+                    variable: let_stmt.variable.clone(),
+                    equals: let_stmt.equals.clone(),
+                    value: Box::new(value),
+                    ext: (let_stmt.ext.clone(), ty),
+                })
+            }
+            Statement::LetMut(let_mut) => {
+                let value = self.infer(&let_mut.value)?;
+                let ty = proc_term_type(&value);
+                let (id, reference_id) = let_mut.ext.clone();
+                self.locals.push((
+                    reference_id.clone(),
+                    ProcType::Reference(Box::new(ty.clone())),
+                ));
+                self.locals.push((id.clone(), ty.clone()));
+                Statement::LetMut(StatementLetMut {
+                    let_keyword: let_mut.let_keyword.clone(),
+                    mut_keyword: let_mut.mut_keyword.clone(),
+                    variable: let_mut.variable.clone(),
+                    at_operator: let_mut.at_operator.clone(),
+                    reference_variable: let_mut.reference_variable.clone(),
+                    equals: let_mut.equals.clone(),
+                    value: Box::new(value),
+                    ext: (id, reference_id, ty),
+                })
+            }
+            Statement::Assign(assign) => {
+                let name = assign.variable.s();
+                let pos = assign.variable.pos();
+                let ty = self.variable(&assign.ext, name, pos)?;
+                let ProcType::Reference(target) = ty else {
+                    return Err(TypeCheckError::new(
+                        TypeCheckErrorKind::NotAReference {
+                            term: name.to_string(),
+                            ty: ty.to_string(),
+                        },
+                        Some(pos.clone()),
+                    ));
+                };
+                Statement::Assign(StatementAssign {
+                    variable: assign.variable.clone(),
+                    equals: assign.equals.clone(),
+                    value: Box::new(self.check(&assign.value, &target)?),
+                    ext: assign.ext.clone(),
+                })
+            }
+            Statement::FieldAssign(assign) => {
+                let field_access = self.field_access(&assign.field_access)?;
+                let ty = match &field_access.ext.1 {
+                    ProcType::Reference(ty) => ty.as_ref().clone(),
+                    ty => ty.clone(),
+                };
+                Statement::FieldAssign(StatementFieldAssign {
+                    field_access,
+                    equals: assign.equals.clone(),
+                    value: Box::new(self.check(&assign.value, &ty)?),
+                    ext: (),
+                })
+            }
+            Statement::Loop(loop_stmt) => {
+                self.loops += 1;
+                let body = self.check_block(loop_stmt.body());
+                self.loops -= 1;
+                let (body, _) = body?;
+                Statement::Loop(StatementLoop {
+                    keyword_loop: loop_stmt.keyword_loop.clone(),
+                    brace_l: loop_stmt.brace_l.clone(),
+                    body: Box::new(body),
+                    brace_r: loop_stmt.brace_r.clone(),
+                    ext: (),
+                })
+            }
+            Statement::Break(break_stmt) => {
+                if self.loops == 0 {
+                    return Err(TypeCheckError::new(
+                        TypeCheckErrorKind::BreakOutsideLoop,
+                        Some(break_stmt.keyword_break.pos().clone()),
+                    ));
+                }
+                Statement::Break(StatementBreak {
+                    keyword_break: break_stmt.keyword_break.clone(),
+                    semicolon: break_stmt.semicolon.clone(),
+                    ext: (),
+                })
+            }
+            Statement::Return(return_stmt) => {
+                let result = self.result.clone();
+                Statement::Return(StatementReturn {
+                    keyword_return: return_stmt.keyword_return.clone(),
+                    value: Box::new(self.check(&return_stmt.value, &result)?),
+                    semicolon: return_stmt.semicolon.clone(),
+                    ext: (),
+                })
+            }
+            Statement::CallPtx(call_ptx) => Statement::CallPtx(self.check_call_ptx(call_ptx)?),
+            Statement::Expr(term) => {
+                let term = self.infer(term)?;
+                let ty = proc_term_type(&term);
+                return Ok((Statement::Expr(term), ty));
+            }
+            Statement::Ext(()) => Statement::Ext(()),
+        };
+        Ok((statement, ProcType::Unit))
+    }
+
+    /// `#call_ptx f args grid block` launches the `#ptx` procedure `f`
+    fn check_call_ptx(
+        &mut self,
+        call_ptx: &StatementCallPtx<PhaseRenamed>,
+    ) -> Result<StatementCallPtx<PhaseTyped>, TypeCheckError> {
+        let name = call_ptx.function_name.s();
+        let pos = call_ptx.function_name.pos();
+        let signature = match self.checker.procedures.signatures.get(name) {
+            Some(signature) if signature.ptx => signature.clone(),
+            _ => {
+                return Err(TypeCheckError::new(
+                    TypeCheckErrorKind::NotAPtxProc(name.to_string()),
+                    Some(pos.clone()),
+                ));
+            }
+        };
+        Ok(StatementCallPtx {
+            keyword_call_ptx: call_ptx.keyword_call_ptx.clone(),
+            function_name: call_ptx.function_name.clone(),
+            args: self.check_arguments(name, pos, &signature.params, &call_ptx.args)?,
+            grid_dim_x: call_ptx.grid_dim_x.clone(),
+            grid_dim_y: call_ptx.grid_dim_y.clone(),
+            grid_dim_z: call_ptx.grid_dim_z.clone(),
+            block_dim_x: call_ptx.block_dim_x.clone(),
+            block_dim_y: call_ptx.block_dim_y.clone(),
+            block_dim_z: call_ptx.block_dim_z.clone(),
+            ext: (),
+        })
+    }
+
+    fn check(
+        &mut self,
+        term: &ProcTerm<PhaseRenamed>,
+        expected: &ProcType,
+    ) -> Result<ProcTerm<PhaseTyped>, TypeCheckError> {
+        let typed = self.infer(term)?;
+        self.expect(expected, &proc_term_type(&typed), proc_term_pos(term))?;
+        Ok(typed)
+    }
+
+    fn expect(
+        &self,
+        expected: &ProcType,
+        found: &ProcType,
+        pos: Option<Pos>,
+    ) -> Result<(), TypeCheckError> {
+        if expected == found {
+            return Ok(());
+        }
+        Err(TypeCheckError::new(
+            TypeCheckErrorKind::TypeMismatch {
+                expected: expected.to_string(),
+                found: found.to_string(),
+            },
+            pos,
+        ))
+    }
+
+    fn infer(
+        &mut self,
+        term: &ProcTerm<PhaseRenamed>,
+    ) -> Result<ProcTerm<PhaseTyped>, TypeCheckError> {
+        let typed = match term {
+            ProcTerm::Paren(paren) => {
+                let proc_term = self.infer(&paren.proc_term)?;
+                ProcTerm::Paren(ProcTermParen {
+                    paren_l: paren.paren_l.clone(),
+                    ext: proc_term_type(&proc_term),
+                    proc_term: Box::new(proc_term),
+                    paren_r: paren.paren_r.clone(),
+                })
+            }
+            ProcTerm::Apply(apply) => ProcTerm::Apply(self.infer_apply(apply)?),
+            ProcTerm::Variable(var) => {
+                let name = var.variable.s();
+                let pos = var.variable.pos();
+                let ty = match self.builtin(name) {
+                    Some(BuiltinSignature::Value(ty)) if self.local(&var.ext).is_none() => ty,
+                    _ => self.variable(&var.ext, name, pos)?,
+                };
+                ProcTerm::Variable(ProcTermVariable {
+                    variable: var.variable.clone(),
+                    ext: (var.ext.clone(), ty),
+                })
+            }
+            ProcTerm::Unit(unit) => ProcTerm::Unit(ProcTermUnit {
+                paren_l: unit.paren_l.clone(),
+                paren_r: unit.paren_r.clone(),
+                ext: ProcType::Unit,
+            }),
+            ProcTerm::Number(number) => ProcTerm::Number(ProcTermNumber {
+                number: number.number.clone(),
+                ext: infer_number(number)?,
+            }),
+            ProcTerm::FieldAccess(access) => ProcTerm::FieldAccess(self.field_access(access)?),
+            ProcTerm::ConstructorCall(call) => {
+                ProcTerm::ConstructorCall(self.infer_constructor_call(call)?)
+            }
+            ProcTerm::Struct(item) => {
+                return Err(TypeCheckError::new(
+                    TypeCheckErrorKind::Unsupported("Struct declarations in procedures"),
+                    Some(item.name().pos().clone()),
+                ));
+            }
+            ProcTerm::StructValue(value) => ProcTerm::StructValue(self.infer_struct_value(value)?),
+            ProcTerm::If(if_expr) => ProcTerm::If(self.infer_if(if_expr)?),
+            ProcTerm::Dereference(dereference) => {
+                let term = self.infer(&dereference.term)?;
+                let ty = match proc_term_type(&term) {
+                    ProcType::Reference(ty) => *ty,
+                    // A word computed by a definition is a pointer
+                    ProcType::U64 => ProcType::U64,
+                    ty => {
+                        return Err(TypeCheckError::new(
+                            TypeCheckErrorKind::NotAReference {
+                                term: describe(&dereference.term),
+                                ty: ty.to_string(),
+                            },
+                            proc_term_pos(&dereference.term),
+                        ));
+                    }
+                };
+                ProcTerm::Dereference(ProcTermDereference {
+                    term: Box::new(term),
+                    dot_star: dereference.dot_star.clone(),
+                    ext: ty,
+                })
+            }
+            ProcTerm::Ext(()) => ProcTerm::Ext(()),
+        };
+        Ok(typed)
+    }
+
+    fn local(&self, id: &VariableId) -> Option<&ProcType> {
+        self.locals
+            .iter()
+            .find(|(local, _)| local == id)
+            .map(|(_, ty)| ty)
+    }
+
+    fn variable(&self, id: &VariableId, name: &str, pos: &Pos) -> Result<ProcType, TypeCheckError> {
+        self.local(id).cloned().ok_or_else(|| {
+            TypeCheckError::new(
+                TypeCheckErrorKind::UnknownVariable(name.to_string()),
+                Some(pos.clone()),
+            )
+        })
+    }
+
+    fn builtin(&self, name: &str) -> Option<BuiltinSignature> {
+        builtin_signature(self.checker.procedures.builtins.get(name)?)
+    }
+
+    /// A call of a procedure, a builtin or a definition. The name of the
+    /// function has the type of the result.
+    fn infer_apply(
+        &mut self,
+        apply: &ProcTermApply<PhaseRenamed>,
+    ) -> Result<ProcTermApply<PhaseTyped>, TypeCheckError> {
+        let f = match apply.f() {
+            ProcTerm::Variable(var) if self.local(&var.ext).is_none() => var,
+            // `array.#len ()`
+            ProcTerm::FieldAccess(access) if access.field_name() == "#len" => {
+                let f = self.field_access(access)?;
+                let args = self.check_arguments("#len", access.field.pos(), &[], apply.args())?;
+                return Ok(ProcTermApply {
+                    ext: f.ext.1.clone(),
+                    f: Box::new(ProcTerm::FieldAccess(f)),
+                    args,
+                });
+            }
+            f => {
+                let ty = proc_term_type(&self.infer(f)?);
+                return Err(TypeCheckError::new(
+                    TypeCheckErrorKind::NotAFunction {
+                        term: describe(f),
+                        ty: ty.to_string(),
+                    },
+                    proc_term_pos(f),
+                ));
+            }
+        };
+        let (name, pos) = (f.variable.s(), f.variable.pos());
+        let (args, result) = if let Some(signature) =
+            self.checker.procedures.signatures.get(name).cloned()
+        {
+            let args = self.check_arguments(name, pos, &signature.params, apply.args())?;
+            (args, signature.result)
+        } else {
+            match self.builtin(name) {
+                Some(BuiltinSignature::Function(params, result)) => (
+                    self.check_arguments(name, pos, &params, apply.args())?,
+                    result,
+                ),
+                Some(BuiltinSignature::Syscall) => {
+                    if apply.args().len() != 6 {
+                        return Err(arity(name, pos, 6, apply.args().len()));
+                    }
+                    let mut args = Vec::new();
+                    for arg in apply.args() {
+                        let typed = self.infer(arg)?;
+                        match proc_term_type(&typed) {
+                            ProcType::U64 | ProcType::Reference(_) => args.push(typed),
+                            ty => {
+                                return Err(TypeCheckError::new(
+                                    TypeCheckErrorKind::TypeMismatch {
+                                        expected: "u64".to_string(),
+                                        found: ty.to_string(),
+                                    },
+                                    proc_term_pos(arg),
+                                ));
+                            }
+                        }
+                    }
+                    (args, ProcType::U64)
+                }
+                Some(BuiltinSignature::Value(ty)) => {
+                    return Err(TypeCheckError::new(
+                        TypeCheckErrorKind::NotAFunction {
+                            term: name.to_string(),
+                            ty: ty.to_string(),
+                        },
+                        Some(pos.clone()),
+                    ));
+                }
+                None => {
+                    let signature = self.checker.definition_signature(name).ok_or_else(|| {
+                        TypeCheckError::new(
+                            TypeCheckErrorKind::UnknownVariable(name.to_string()),
+                            Some(pos.clone()),
+                        )
+                    })?;
+                    let args = self.check_arguments(name, pos, &signature.params, apply.args())?;
+                    (args, signature.result)
+                }
+            }
+        };
+        Ok(ProcTermApply {
+            f: Box::new(ProcTerm::Variable(ProcTermVariable {
+                variable: f.variable.clone(),
+                ext: (f.ext.clone(), result.clone()),
+            })),
+            args,
+            ext: result,
+        })
+    }
+
+    /// Checks the arguments of a call. Functions without parameters are
+    /// called with `()`.
+    fn check_arguments(
+        &mut self,
+        name: &str,
+        pos: &Pos,
+        params: &[ProcType],
+        args: &[ProcTerm<PhaseRenamed>],
+    ) -> Result<Vec<ProcTerm<PhaseTyped>>, TypeCheckError> {
+        if params.is_empty() && matches!(args, [ProcTerm::Unit(_)]) {
+            return args.iter().map(|arg| self.infer(arg)).collect();
+        }
+        if params.len() != args.len() {
+            return Err(arity(name, pos, params.len(), args.len()));
+        }
+        params
+            .iter()
+            .zip(args)
+            .map(|(param, arg)| self.check(arg, param))
+            .collect()
+    }
+
+    /// `x.f` for a struct `x`, or `xs.f i`, a reference to the field `f` of
+    /// the element `i` of an array `xs`, or `xs.#len`
+    fn field_access(
+        &mut self,
+        access: &ProcTermFieldAccess<PhaseRenamed>,
+    ) -> Result<ProcTermFieldAccess<PhaseTyped>, TypeCheckError> {
+        let object = access.object_name();
+        let ty = self.variable(&access.ext, object, access.object.pos())?;
+        let pos = access.field.pos();
+        let unknown_field = |ty: &ProcType| {
+            TypeCheckError::new(
+                TypeCheckErrorKind::UnknownField {
+                    field: access.field_name().to_string(),
+                    ty: ty.to_string(),
+                },
+                Some(pos.clone()),
+            )
+        };
+        let mut index = None;
+        let field_ty = match &ty {
+            ProcType::Array(_) if access.field_name() == "#len" => ProcType::U64,
+            ProcType::Array(array) => {
+                let field = field_type(&self.checker.procedures.arrays[array], access.field_name())
+                    .ok_or_else(|| unknown_field(&ty))?;
+                let Some(index_term) = &access.index else {
+                    return Err(TypeCheckError::new(
+                        TypeCheckErrorKind::MissingIndex(format!(
+                            "{object}.{}",
+                            access.field_name()
+                        )),
+                        Some(pos.clone()),
+                    ));
+                };
+                index = Some(Box::new(self.check(index_term, &ProcType::U64)?));
+                ProcType::Reference(Box::new(field))
+            }
+            ProcType::Struct(name) if access.index.is_none() => {
+                field_type(&self.checker.procedures.structs[name], access.field_name())
+                    .ok_or_else(|| unknown_field(&ty))?
+            }
+            _ => return Err(unknown_field(&ty)),
+        };
+        Ok(ProcTermFieldAccess {
+            object: access.object.clone(),
+            dot: access.dot.clone(),
+            field: access.field.clone(),
+            index,
+            ext: (access.ext.clone(), field_ty),
+        })
+    }
+
+    /// `A::#new_with_size n` allocates an array of `n` elements
+    fn infer_constructor_call(
+        &mut self,
+        call: &ProcTermConstructorCall<PhaseRenamed>,
+    ) -> Result<ProcTermConstructorCall<PhaseTyped>, TypeCheckError> {
+        let name = call.type_name.s();
+        if !self.checker.procedures.arrays.contains_key(name) {
+            return Err(TypeCheckError::new(
+                TypeCheckErrorKind::UnknownType(name.to_string()),
+                Some(call.type_name.pos().clone()),
+            ));
+        }
+        if call.method.s() != "new_with_size" {
+            return Err(TypeCheckError::new(
+                TypeCheckErrorKind::UnknownField {
+                    field: format!("#{}", call.method.s()),
+                    ty: name.to_string(),
+                },
+                Some(call.method.pos().clone()),
+            ));
+        }
+        let args = self.check_arguments(
+            "#new_with_size",
+            call.method.pos(),
+            &[ProcType::U64],
+            &call.args,
+        )?;
+        Ok(ProcTermConstructorCall {
+            type_name: call.type_name.clone(),
+            colon2: call.colon2.clone(),
+            method: call.method.clone(),
+            args,
+            ext: ProcType::Array(name.to_string()),
+        })
+    }
+
+    /// `S { x: a, y: b }` gives every field of the struct `S` once
+    fn infer_struct_value(
+        &mut self,
+        value: &ProcTermStructValue<PhaseRenamed>,
+    ) -> Result<ProcTermStructValue<PhaseTyped>, TypeCheckError> {
+        let name = value.struct_name.s();
+        let fields = self
+            .checker
+            .procedures
+            .structs
+            .get(name)
+            .cloned()
+            .ok_or_else(|| {
+                TypeCheckError::new(
+                    TypeCheckErrorKind::UnknownType(name.to_string()),
+                    Some(value.struct_name.pos().clone()),
+                )
+            })?;
+        let ty = ProcType::Struct(name.to_string());
+        let mut given: Vec<&str> = Vec::new();
+        let mut typed_fields = Vec::new();
+        for field in &value.fields {
+            let field_name = field.name.s();
+            let expected = field_type(&fields, field_name)
+                .filter(|_| !given.contains(&field_name))
+                .ok_or_else(|| {
+                    TypeCheckError::new(
+                        TypeCheckErrorKind::UnknownField {
+                            field: field_name.to_string(),
+                            ty: ty.to_string(),
+                        },
+                        Some(field.name.pos().clone()),
+                    )
+                })?;
+            typed_fields.push(ProcTermStructField {
+                name: field.name.clone(),
+                colon: field.colon.clone(),
+                value: Box::new(self.check(&field.value, &expected)?),
+                comma: field.comma.clone(),
+            });
+            given.push(field_name);
+        }
+        if let Some((missing, _)) = fields
+            .iter()
+            .find(|(field, _)| !given.contains(&field.as_str()))
+        {
+            return Err(TypeCheckError::new(
+                TypeCheckErrorKind::MissingField {
+                    field: missing.clone(),
+                    ty: ty.to_string(),
+                },
+                Some(value.struct_name.pos().clone()),
+            ));
+        }
+        Ok(ProcTermStructValue {
+            struct_name: value.struct_name.clone(),
+            brace_l: value.brace_l.clone(),
+            fields: typed_fields,
+            brace_r: value.brace_r.clone(),
+            ext: ty,
+        })
+    }
+
+    /// `#if c { .. } #else { .. }` runs a branch as `c` is nonzero, and has a
+    /// value when both branches have one of the same type
+    fn infer_if(
+        &mut self,
+        if_expr: &ProcTermIf<PhaseRenamed>,
+    ) -> Result<ProcTermIf<PhaseTyped>, TypeCheckError> {
+        let (condition, condition_type) = self.check_block(&if_expr.condition)?;
+        self.expect(
+            &ProcType::U64,
+            &condition_type,
+            Some(if_expr.keyword_if.pos().clone()),
+        )?;
+        let (then_body, then_type) = self.check_block(&if_expr.then_body)?;
+        let mut typed = ProcTermIf {
+            keyword_if: if_expr.keyword_if.clone(),
+            condition: Box::new(condition),
+            brace_l: if_expr.brace_l.clone(),
+            then_body: Box::new(then_body),
+            brace_r: if_expr.brace_r.clone(),
+            else_clause: None,
+            ext: ProcType::Unit,
+        };
+        let Some(else_clause) = &if_expr.else_clause else {
+            return Ok(typed);
+        };
+        let (else_body, else_type) = self.check_block(&else_clause.else_body)?;
+        typed.else_clause = Some(ProcTermIfElse {
+            keyword_else: else_clause.keyword_else.clone(),
+            brace_l: else_clause.brace_l.clone(),
+            else_body: Box::new(else_body),
+            brace_r: else_clause.brace_r.clone(),
+        });
+        typed.ext = match (&then_type, &else_type) {
+            _ if then_type == else_type => then_type,
+            (ProcType::Unit, _) | (_, ProcType::Unit) => ProcType::Unit,
+            _ => {
+                self.expect(
+                    &then_type,
+                    &else_type,
+                    Some(else_clause.keyword_else.pos().clone()),
+                )?;
+                ProcType::Unit
+            }
+        };
+        Ok(typed)
+    }
+}
+
+/// Number literals are `u64`, or `f32` with a decimal point or the suffix
+fn infer_number(number: &ProcTermNumber<PhaseRenamed>) -> Result<ProcType, TypeCheckError> {
+    let literal = number.number.s();
+    if literal.ends_with("f32") || literal.contains('.') {
+        return Ok(ProcType::F32);
+    }
+    let digits = literal
+        .strip_suffix("u64")
+        .unwrap_or(literal)
+        .replace('_', "");
+    match digits.parse::<u64>() {
+        Ok(_) => Ok(ProcType::U64),
+        Err(_) => Err(TypeCheckError::new(
+            TypeCheckErrorKind::InvalidLiteral(literal.to_string()),
+            Some(number.number.pos().clone()),
+        )),
+    }
+}
+
+fn field_type(fields: &[(String, ProcType)], name: &str) -> Option<ProcType> {
+    fields
+        .iter()
+        .find(|(field, _)| field == name)
+        .map(|(_, ty)| ty.clone())
+}
+
+fn arity(name: &str, pos: &Pos, expected: usize, found: usize) -> TypeCheckError {
+    TypeCheckError::new(
+        TypeCheckErrorKind::Arity {
+            function: name.to_string(),
+            expected,
+            found,
+        },
+        Some(pos.clone()),
+    )
+}
+
+/// A short description of a term for error messages
+fn describe<P: Phase>(term: &ProcTerm<P>) -> String {
+    match term {
+        ProcTerm::Paren(paren) => describe(&paren.proc_term),
+        ProcTerm::Variable(var) => var.variable.s().to_string(),
+        ProcTerm::Number(number) => number.number.s().to_string(),
+        ProcTerm::FieldAccess(access) => {
+            format!("{}.{}", access.object_name(), access.field_name())
+        }
+        ProcTerm::Apply(apply) => format!("{} ..", describe(apply.f())),
+        ProcTerm::Unit(_) => "()".to_string(),
+        _ => "expression".to_string(),
+    }
+}
+
+/// The position of the first token of a term, if it has one
+fn proc_term_pos<P: Phase>(term: &ProcTerm<P>) -> Option<Pos> {
+    match term {
+        ProcTerm::Paren(paren) => proc_term_pos(&paren.proc_term),
+        ProcTerm::Apply(apply) => proc_term_pos(apply.f()),
+        ProcTerm::Variable(var) => Some(var.variable.pos().clone()),
+        ProcTerm::Number(number) => Some(number.number.pos().clone()),
+        ProcTerm::FieldAccess(access) => Some(access.object.pos().clone()),
+        ProcTerm::ConstructorCall(call) => Some(call.type_name.pos().clone()),
+        ProcTerm::Struct(item) => Some(item.name().pos().clone()),
+        ProcTerm::StructValue(value) => Some(value.struct_name.pos().clone()),
+        ProcTerm::If(if_expr) => Some(if_expr.keyword_if.pos().clone()),
+        ProcTerm::Dereference(dereference) => proc_term_pos(&dereference.term),
+        ProcTerm::Unit(_) | ProcTerm::Ext(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::TypeCheckErrorKind;
+
+    const PRELUDE: &str = "
+#use_builtin \"syscall\" #as __syscall;
+#use_builtin \"u64\" #as __u64;
+#use_builtin \"u64_add\" #as __u64_add;
+#use_builtin \"f32\" #as __f32;
+
+#struct Vec2 {
+    x: __u64,
+    y: __u64,
+}
+
+#array Points {
+    #item: #struct {
+        x: __f32,
+        y: __f32,
+    },
+    #dimension: 1,
+}
+
+#proc add : (a : __u64) -> (b : __u64) -> __u64 {
+    #let c = __u64_add a b;
+    #return c;
+}
+";
+
+    fn check(body: &str) -> Result<(), crate::TypeCheckError> {
+        let source = format!("{PRELUDE}\n#proc main : () -> () {{\n{body}\n}}\n");
+        crate::tests::check_source(&source).map(|_| ())
+    }
+
+    fn kind(body: &str) -> TypeCheckErrorKind {
+        check(body).unwrap_err().kind().clone()
+    }
+
+    #[test]
+    fn test_procedure_testcases_type_check() {
+        for name in [
+            "array.fe",
+            "array_4.fe",
+            "array_len.fe",
+            "contracts.fe",
+            "extract_nat.fe",
+            "if_2.fe",
+            "let_mut.fe",
+            "loop_break.fe",
+            "print_num3.fe",
+            "proc_call.fe",
+            "ptx_2.fe",
+            "struct_2.fe",
+        ] {
+            let source =
+                std::fs::read_to_string(format!("../../testcases/felis/single/{name}")).unwrap();
+            let result = crate::tests::check_source(&source);
+            assert!(result.is_ok(), "{name}: {:?}", result.err());
+        }
+    }
+
+    #[test]
+    fn test_well_typed_procedure() {
+        check(
+            "    #let v = Vec2 { x: 1, y: add 2 3 };
+    #let ps = Points::#new_with_size 2;
+    ps.x 1 <- 1.5f32;
+    #let x = ps .x 1 .*;
+    #let #mut n @ n_ref = ps.#len ();
+    #loop {
+        #if __u64_add n 0 {
+            n_ref <- 0;
+        } #else {
+            #break;
+        };
+    };
+    __syscall 231 n 0 0 0 0;",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_ill_typed_procedures_are_rejected() {
+        let error = check("    __u64_add 1.0f32 2;").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Type mismatch: expected `u64`, found `f32` at 26:15"
+        );
+
+        assert_eq!(
+            kind("    #let v = Vec2 { x: 1, y: 2 };\n    #let z = v.z;"),
+            TypeCheckErrorKind::UnknownField {
+                field: "z".to_string(),
+                ty: "Vec2".to_string()
+            }
+        );
+        assert_eq!(
+            kind("    #let v = Vec2 { x: 1 };"),
+            TypeCheckErrorKind::MissingField {
+                field: "y".to_string(),
+                ty: "Vec2".to_string()
+            }
+        );
+        assert_eq!(
+            kind("    add 1;"),
+            TypeCheckErrorKind::Arity {
+                function: "add".to_string(),
+                expected: 2,
+                found: 1
+            }
+        );
+        assert_eq!(
+            kind("    #let ps = Points::#new_with_size 2;\n    ps.x 0 <- 1;"),
+            TypeCheckErrorKind::TypeMismatch {
+                expected: "f32".to_string(),
+                found: "u64".to_string()
+            }
+        );
+        assert_eq!(
+            kind("    #let x = 1;\n    x <- 2;"),
+            TypeCheckErrorKind::NotAReference {
+                term: "x".to_string(),
+                ty: "u64".to_string()
+            }
+        );
+        assert_eq!(kind("    #break;"), TypeCheckErrorKind::BreakOutsideLoop);
+        assert_eq!(
+            kind("    #call_ptx add 1 1 1 1 1 1;"),
+            TypeCheckErrorKind::NotAPtxProc("add".to_string())
+        );
+        assert_eq!(
+            kind("    __syscall 60 0 0 0 0;"),
+            TypeCheckErrorKind::Arity {
+                function: "__syscall".to_string(),
+                expected: 6,
+                found: 5
+            }
+        );
+        assert_eq!(
+            kind("    #let y = x;"),
+            TypeCheckErrorKind::UnknownVariable("x".to_string())
+        );
+
+        let source = format!("{PRELUDE}\n#proc f : (v : Vec3) -> () {{ }}\n");
+        assert_eq!(
+            crate::tests::check_source(&source)
+                .err()
+                .map(|e| e.kind().clone()),
+            Some(TypeCheckErrorKind::UnknownType("Vec3".to_string()))
+        );
+    }
+}
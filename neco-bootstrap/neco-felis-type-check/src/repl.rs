@@ -0,0 +1,528 @@
+use std::io::{BufRead, Write};
+
+use neco_felis_rename::rename_file;
+use neco_felis_syn::{File, FileIdGenerator, Item, Parse, PhaseParse, token::Token};
+
+use crate::{TypeCheckError, TypeChecker};
+
+const HELP: &str = "\
+Enter items such as #inductive and #definition to add them to the session.
+  :type <term>    print the type of a term
+  :eval <term>    print the normal form of a term
+  :print <name>   print a constant, an inductive type or a constructor
+  :load <file>    check the items of a file
+  :undo           roll back the last item or file
+  :cancel         drop the lines of an unfinished item
+  :help           print this message
+  :quit           leave the session";
+
+/// An interactive session of the type checker. Each item entered is checked
+/// against the items before it. An input that fails to check leaves the
+/// session as it was.
+pub struct Repl {
+    checker: TypeChecker,
+    /// The items kept so far, renamed again with each input so that
+    /// procedures can refer to the items of the previous inputs
+    session: File<PhaseParse>,
+    /// The session before each item or file, for `:undo`
+    history: Vec<Checkpoint>,
+    file_id_generator: FileIdGenerator,
+}
+
+/// The state of a session before an input, as the lengths of what checking
+/// the input appends to
+#[derive(Debug, Clone, Copy)]
+struct Checkpoint {
+    items: usize,
+    declared: usize,
+    diagnostics: usize,
+    goals: usize,
+}
+
+impl Default for Repl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Repl {
+    pub fn new() -> Self {
+        Self {
+            checker: TypeChecker::new(),
+            session: File {
+                items: Vec::new(),
+                ext: (),
+            },
+            history: Vec::new(),
+            file_id_generator: FileIdGenerator::new(),
+        }
+    }
+
+    /// Handles a command or items, returning what to print
+    pub fn eval(&mut self, input: &str) -> Result<String, String> {
+        let input = input.trim();
+        let Some(command) = input.strip_prefix(':') else {
+            if input.is_empty() {
+                return Ok(String::new());
+            }
+            return self.add_items(input);
+        };
+        let (command, argument) = command
+            .split_once(char::is_whitespace)
+            .map_or((command, ""), |(command, argument)| {
+                (command, argument.trim())
+            });
+        match (command, argument) {
+            ("type", term) if !term.is_empty() => self.add_items(&format!("#check {term};")),
+            ("eval", term) if !term.is_empty() => self.add_items(&format!("#eval {term};")),
+            ("print", name) if !name.is_empty() => self.add_items(&format!("#print {name}")),
+            ("load", path) if !path.is_empty() => self.load(path),
+            ("undo", "") => {
+                let checkpoint = self.history.pop().ok_or("Nothing to undo")?;
+                self.restore(checkpoint);
+                Ok("Undid the last item".to_string())
+            }
+            ("cancel", "") => Err("Nothing to cancel".to_string()),
+            ("help", "") => Ok(HELP.to_string()),
+            ("type" | "eval" | "print" | "load", _) => {
+                Err(format!("Missing argument of :{command}, see :help"))
+            }
+            _ => Err(format!("Unknown command :{command}, see :help")),
+        }
+    }
+
+    fn add_items(&mut self, source: &str) -> Result<String, String> {
+        let file = self.parse(source)?;
+        let (output, names) = self.check(file, None)?;
+        if names.is_empty() {
+            return Ok(output);
+        }
+        let declared = format!("Declared {}", names.join(", "));
+        Ok([output, declared].join("\n").trim_start().to_string())
+    }
+
+    fn load(&mut self, path: &str) -> Result<String, String> {
+        let source = std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+        let file = self.parse(&source).map_err(|e| format!("{path}: {e}"))?;
+        let loaded = format!("Loaded {path}: {} items", file.items().len());
+        let (output, _) = self.check(file, Some(path))?;
+        Ok([output, loaded].join("\n").trim_start().to_string())
+    }
+
+    fn parse(&mut self, source: &str) -> Result<File<PhaseParse>, String> {
+        let file_id = self.file_id_generator.generate_file_id();
+        let tokens = Token::try_lex(source, file_id)
+            .map_err(|e| format!("Parse error: unexpected character `{}` at {}", e.c, e.pos))?;
+        let mut i = 0;
+        let file = File::parse(&tokens, &mut i)
+            .map_err(|e| e.to_string())?
+            .ok_or("Parse error: expected an item")?;
+        match tokens.get(i) {
+            None => Ok(file),
+            Some(token) => Err(format!("Parse error: unexpected token at {}", token.pos())),
+        }
+    }
+
+    /// Checks the items of a file after the items of the session, returning
+    /// the messages of its inspection items and the names it declares. The
+    /// items are kept, with a checkpoint in the history, unless they fail to
+    /// check or are all inspection items.
+    fn check(
+        &mut self,
+        file: File<PhaseParse>,
+        path: Option<&str>,
+    ) -> Result<(String, Vec<String>), String> {
+        let checkpoint = Checkpoint {
+            items: self.session.items.len(),
+            declared: self.checker.declared.len(),
+            diagnostics: self.checker.diagnostics.len(),
+            goals: self.checker.goals.len(),
+        };
+        let inspects = file.items.iter().all(is_inspection);
+        self.session.items.extend(file.items);
+        match self.check_from(checkpoint) {
+            Ok(checked) if !inspects => {
+                self.history.push(checkpoint);
+                Ok(checked)
+            }
+            Ok(checked) => {
+                self.restore(checkpoint);
+                Ok(checked)
+            }
+            Err(e) => {
+                self.restore(checkpoint);
+                Err(match path {
+                    Some(path) => e.with_file_name(path).to_string(),
+                    None => e.to_string(),
+                })
+            }
+        }
+    }
+
+    /// Checks the items of the session from `checkpoint` on, renamed along
+    /// with the items before them
+    fn check_from(
+        &mut self,
+        checkpoint: Checkpoint,
+    ) -> Result<(String, Vec<String>), TypeCheckError> {
+        let renamed = rename_file(&self.session)?;
+        let mut names = Vec::new();
+        for (item, renamed) in self.session.items[checkpoint.items..]
+            .iter()
+            .zip(&renamed.items()[checkpoint.items..])
+        {
+            let declared = self.checker.declared.len();
+            self.checker.process_item(item, renamed)?;
+            let declared = &self.checker.declared[declared..];
+            // The functions of the contracts of a procedure are not listed
+            match item_name(item) {
+                Some(name) if declared.is_empty() || matches!(item, Item::Proc(_)) => {
+                    names.push(name.to_string());
+                }
+                _ => names.extend(declared.iter().map(|id| self.checker.names[id].clone())),
+            }
+        }
+        let output = self.checker.diagnostics[checkpoint.diagnostics..].join("\n");
+        Ok((output, names))
+    }
+
+    /// Rolls the session back to `checkpoint`
+    fn restore(&mut self, checkpoint: Checkpoint) {
+        let items = self.session.items.split_off(checkpoint.items);
+        self.checker.roll_back(checkpoint, &items);
+    }
+
+    /// Reads inputs until the end of `input` or `:quit`. An item may span
+    /// several lines, up to a line that closes its braces and ends with `;`
+    /// or `}`. A command is handled as soon as it is entered, even in the
+    /// middle of an item, and `:cancel` drops the lines of the item.
+    pub fn run(&mut self, mut input: impl BufRead, mut output: impl Write) -> std::io::Result<()> {
+        let mut buffer = String::new();
+        loop {
+            let prompt = if buffer.is_empty() {
+                "neco> "
+            } else {
+                "....> "
+            };
+            write!(output, "{prompt}")?;
+            output.flush()?;
+
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                writeln!(output)?;
+                return Ok(());
+            }
+            let entered = match line.trim() {
+                ":quit" | ":q" => return Ok(()),
+                ":cancel" if !buffer.is_empty() => {
+                    buffer.clear();
+                    continue;
+                }
+                command if command.starts_with(':') => line,
+                _ => {
+                    buffer.push_str(&line);
+                    if !is_complete(&buffer) {
+                        continue;
+                    }
+                    std::mem::take(&mut buffer)
+                }
+            };
+            match self.eval(&entered) {
+                Ok(message) if message.is_empty() => {}
+                Ok(message) => writeln!(output, "{message}")?,
+                Err(message) => writeln!(output, "error: {message}")?,
+            }
+        }
+    }
+}
+
+impl TypeChecker {
+    /// Removes what checking the items after `checkpoint` added, whether they
+    /// were all checked or one of them failed
+    fn roll_back(&mut self, checkpoint: Checkpoint, items: &[Item<PhaseParse>]) {
+        self.diagnostics.truncate(checkpoint.diagnostics);
+        self.goals.truncate(checkpoint.goals);
+        for id in self.declared.split_off(checkpoint.declared) {
+            self.global_env.remove(id);
+            self.structures.remove(&id);
+            self.builtins.retain(|_, builtin| *builtin != id);
+            if let Some(name) = self.names.remove(&id)
+                && self.name_to_id.get(&name) == Some(&id)
+            {
+                self.name_to_id.remove(&name);
+            }
+        }
+        for name in items.iter().filter_map(item_name) {
+            self.procedures.remove(name);
+            self.contracts.remove(name);
+            // `#use_builtin "u64"` may have named the type declared before
+            if let Some(id) = self.name_to_id.remove(name)
+                && let Some(other) = self
+                    .name_to_id
+                    .iter()
+                    .find(|(_, other)| **other == id)
+                    .map(|(other, _)| other.clone())
+            {
+                self.names.insert(id, other);
+            }
+        }
+    }
+}
+
+/// Whether an item only prints, such as `#check`
+fn is_inspection(item: &Item<PhaseParse>) -> bool {
+    matches!(
+        item,
+        Item::Check(_) | Item::Eval(_) | Item::Print(_) | Item::PrintAxioms(_)
+    )
+}
+
+/// The name of an item the kernel may not know
+fn item_name(item: &Item<PhaseParse>) -> Option<&str> {
+    match item {
+        Item::UseBuiltin(use_builtin) => Some(use_builtin.name.s()),
+        Item::Proc(proc) => Some(proc.name.s()),
+        Item::Struct(item_struct) => Some(item_struct.name().s()),
+        Item::Array(item_array) => Some(item_array.name().s()),
+        _ => None,
+    }
+}
+
+/// Whether an input can be handled, or more lines are expected
+fn is_complete(input: &str) -> bool {
+    let input = input.trim();
+    if input.is_empty() {
+        return true;
+    }
+    let depth: i64 = input
+        .chars()
+        .map(|c| match c {
+            '(' | '{' | '[' => 1,
+            ')' | '}' | ']' => -1,
+            _ => 0,
+        })
+        .sum();
+    // `#print name` has no terminator
+    depth <= 0 && (input.ends_with(';') || input.ends_with('}') || input.starts_with("#print"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NAT: &str = "#inductive nat : Set {
+    O: nat,
+    S: nat -> nat,
+}";
+
+    const ADD: &str = "#definition add : (n : nat) -> (m : nat) -> nat {
+    #match n {
+        O => m,
+        S p => S (add p m),
+    }
+}";
+
+    #[test]
+    fn test_items_and_queries() {
+        let mut repl = Repl::new();
+        assert_eq!(repl.eval(NAT).unwrap(), "Declared nat, O, S");
+        assert_eq!(repl.eval(ADD).unwrap(), "Declared add");
+        assert_eq!(
+            repl.eval(":type add (S O)").unwrap(),
+            "add (S O) : nat -> nat"
+        );
+        assert_eq!(repl.eval(":eval add (S O) (S O)").unwrap(), "S (S O)");
+        assert_eq!(
+            repl.eval(":print S").unwrap(),
+            "S : nat -> nat (constructor of nat)"
+        );
+        assert_eq!(repl.eval("#check O;").unwrap(), "O : nat");
+    }
+
+    #[test]
+    fn test_errors_keep_the_session() {
+        let mut repl = Repl::new();
+        repl.eval(NAT).unwrap();
+
+        let error = repl
+            .eval("#definition two : nat { S (S zero) }")
+            .unwrap_err();
+        assert!(error.contains("zero"), "{error}");
+        assert!(repl.eval(":type two").is_err());
+        assert_eq!(repl.eval(":type S O").unwrap(), "S O : nat");
+
+        // A file stopping at an error adds none of its items
+        let error = repl
+            .eval("#definition one : nat { S O } #definition bad : nat { one one }")
+            .unwrap_err();
+        assert!(!error.is_empty());
+        assert!(repl.eval(":type one").is_err());
+
+        assert!(
+            repl.eval("#definition")
+                .unwrap_err()
+                .contains("Parse error")
+        );
+        assert!(repl.eval(":type").unwrap_err().contains("Missing argument"));
+        assert!(repl.eval(":nope").unwrap_err().contains("Unknown command"));
+
+        // Input that does not lex
+        assert_eq!(
+            repl.eval(":type !").unwrap_err(),
+            "Parse error: unexpected character `!` at 1:8"
+        );
+        assert!(repl.eval("#definition x : nat { λ }").is_err());
+        assert_eq!(repl.eval(":type S O").unwrap(), "S O : nat");
+    }
+
+    #[test]
+    fn test_undo() {
+        let mut repl = Repl::new();
+        repl.eval(NAT).unwrap();
+        repl.eval("#definition one : nat { S O }").unwrap();
+        // Queries are not undone
+        repl.eval(":type one").unwrap();
+        repl.eval("#check one;").unwrap();
+
+        assert_eq!(repl.eval(":undo").unwrap(), "Undid the last item");
+        assert!(repl.eval(":type one").is_err());
+        assert_eq!(repl.eval(":type O").unwrap(), "O : nat");
+        // The name can be declared again
+        assert_eq!(
+            repl.eval("#definition one : nat { S O }").unwrap(),
+            "Declared one"
+        );
+
+        repl.eval(":undo").unwrap();
+        repl.eval(":undo").unwrap();
+        assert!(repl.eval(":type O").is_err());
+        assert_eq!(repl.eval(":undo").unwrap_err(), "Nothing to undo");
+    }
+
+    #[test]
+    fn test_load() {
+        let mut repl = Repl::new();
+        let output = repl
+            .eval(":load ../../testcases/felis/single/commands.fe")
+            .unwrap();
+        assert!(
+            output.ends_with("Loaded ../../testcases/felis/single/commands.fe: 17 items"),
+            "{output}"
+        );
+        assert_eq!(
+            repl.eval(":type add_1_1_eq_2").unwrap(),
+            "add_1_1_eq_2 : eq nat (add (S O) (S O)) (S (S O))"
+        );
+
+        // A file is undone as a whole
+        repl.eval(":undo").unwrap();
+        assert!(repl.eval(":type add").is_err());
+
+        let error = repl.eval(":load missing.fe").unwrap_err();
+        assert!(error.starts_with("missing.fe: "), "{error}");
+    }
+
+    #[test]
+    fn test_run() {
+        let input = format!(
+            "{NAT}\n:type S\n\n#definition\n  two : nat {{\n  S (S O)\n}}\n:eval two\n:quit\n:type O\n"
+        );
+        let mut output = Vec::new();
+        Repl::new().run(input.as_bytes(), &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "neco> ....> ....> ....> Declared nat, O, S
+neco> S : nat -> nat
+neco> neco> ....> ....> ....> Declared two
+neco> S (S O)
+neco> "
+        );
+    }
+
+    #[test]
+    fn test_use_builtin_undo() {
+        let mut repl = Repl::new();
+        assert_eq!(
+            repl.eval("#use_builtin \"u64\" #as u64;").unwrap(),
+            "Declared u64"
+        );
+        // Builtins of the compiler declare no constant, and are undone too
+        assert_eq!(
+            repl.eval("#use_builtin \"syscall\" #as __syscall;")
+                .unwrap(),
+            "Declared __syscall"
+        );
+        assert_eq!(
+            repl.eval("#use_builtin \"u64\" #as word;").unwrap(),
+            "Declared word"
+        );
+        assert_eq!(repl.eval(":type 1u64").unwrap(), "1 : word");
+
+        repl.eval(":undo").unwrap();
+        assert!(repl.eval(":type word").is_err());
+        assert_eq!(repl.eval(":type 1u64").unwrap(), "1 : u64");
+        repl.eval(":undo").unwrap();
+        assert_eq!(
+            repl.eval("#use_builtin \"syscall\" #as __syscall;")
+                .unwrap(),
+            "Declared __syscall"
+        );
+        repl.eval(":undo").unwrap();
+        repl.eval(":undo").unwrap();
+        assert!(repl.eval(":type 1u64").is_err());
+        assert_eq!(
+            repl.eval("#use_builtin \"u64_add\" #as add;").unwrap(),
+            "Declared u64, add"
+        );
+    }
+
+    #[test]
+    fn test_procedures_use_earlier_inputs() {
+        let mut repl = Repl::new();
+        repl.eval("#use_builtin \"u64\" #as u64;").unwrap();
+        repl.eval("#use_builtin \"u64_add\" #as add;").unwrap();
+        assert_eq!(
+            repl.eval("#proc twice : (x : u64) -> u64 { #let y = add x x; #return y; }")
+                .unwrap(),
+            "Declared twice"
+        );
+        assert_eq!(
+            repl.eval("#proc four : () -> u64 { #let y = twice 2u64; #return y; }")
+                .unwrap(),
+            "Declared four"
+        );
+
+        // A failed procedure is forgotten with its signature
+        let error = repl
+            .eval("#proc bad : () -> u64 { #let y = missing 2u64; #return y; }")
+            .unwrap_err();
+        assert!(error.contains("missing"), "{error}");
+        assert_eq!(
+            repl.eval("#proc bad : () -> u64 { #let y = four (); #return y; }")
+                .unwrap(),
+            "Declared bad"
+        );
+
+        repl.eval(":undo").unwrap();
+        repl.eval(":undo").unwrap();
+        assert!(
+            repl.eval("#proc five : () -> u64 { #let y = four (); #return y; }")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_commands_and_cancel_in_an_item() {
+        let input = format!("{NAT}\n#check (\n:type O\n:cancel\n:type S O\n:cancel\n:quit\n");
+        let mut output = Vec::new();
+        Repl::new().run(input.as_bytes(), &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "neco> ....> ....> ....> Declared nat, O, S
+neco> ....> O : nat
+....> neco> S O : nat
+neco> error: Nothing to cancel
+neco> "
+        );
+    }
+}
@@ -0,0 +1,189 @@
+use std::rc::Rc;
+
+use neco_cic::{
+    global_environment::RecordError,
+    id::Id,
+    inductive::{ConstructorDefinition, InductiveDefinition},
+    substitution::{Substitution, abstract_variable, abstract_variables, substitute},
+    term::{
+        Term, TermApplication, TermBound, TermConstant, TermLambda, TermMatch, TermMatchBranch,
+        TermProduct, TermVariable,
+    },
+};
+use neco_felis_syn::{ItemStructure, PhaseParse};
+
+use crate::{Elaborator, TypeCheckError, TypeCheckErrorKind, TypeChecker};
+
+impl TypeChecker {
+    /// A `#structure S : Set { x_1 : T_1, .., x_n : T_n }` is the inductive
+    /// type `S` with the single constructor `S_mk : T_1 -> .. -> T_n -> S`,
+    /// and the projections `S_x_i : S -> T_i` defined by matching on it.
+    ///
+    /// The type of a field may mention the earlier fields, which become the
+    /// projections of the same value in the type of its projection.
+    pub(crate) fn process_structure(
+        &mut self,
+        structure: &ItemStructure<PhaseParse>,
+    ) -> Result<(), TypeCheckError> {
+        let name = structure.name().s();
+        let pos = structure.name().pos();
+        let id = self.declare(name);
+        let constructor_id = self.declare(&format!("{name}_mk"));
+        let fields = structure.fields();
+        let projection_ids: Vec<Id> = fields
+            .iter()
+            .map(|field| self.declare(&format!("{name}_{}", field.name.s())))
+            .collect();
+        let field_ids: Vec<Id> = fields.iter().map(|_| self.id_gen.generate_id()).collect();
+
+        // Each field is in scope in the types of the next ones
+        let mut elab = Elaborator::new(self);
+        let type_term = elab.elaborate_type(structure.ty())?;
+        elab.add_pending(id, Rc::new(type_term.clone()));
+        let mut field_types = Vec::new();
+        for (field, field_id) in fields.iter().zip(&field_ids) {
+            let field_type = elab.elaborate_type(&field.ty)?;
+            elab.push_local(Some(field.name.s()), *field_id, Rc::new(field_type.clone()));
+            field_types.push(field_type);
+        }
+        elab.finish()?;
+        elab.truncate_locals(0);
+        let type_term = elab.zonk(&type_term);
+        let field_types: Vec<Term> = field_types.iter().map(|ty| elab.zonk(ty)).collect();
+
+        let constructor_type = field_types.iter().enumerate().rev().fold(
+            Term::Constant(TermConstant { id }),
+            |target, (i, field_type)| {
+                Term::Product(TermProduct {
+                    var: field_ids[i],
+                    source: Rc::new(abstract_variables(field_type, &field_ids[..i])),
+                    target: Rc::new(target),
+                })
+            },
+        );
+        let inductive = InductiveDefinition::new(
+            id,
+            Vec::new(),
+            Rc::new(type_term),
+            vec![ConstructorDefinition {
+                name: constructor_id,
+                ty: Rc::new(constructor_type),
+                arity: fields.len(),
+            }],
+        );
+        self.global_env
+            .inductives
+            .add_inductive(inductive)
+            .map_err(|e| Self::environment_error(e, pos))?;
+
+        // S_x_i := fun p : S => match p { S_mk x_1 .. x_n => x_i }
+        let record = Rc::new(Term::Constant(TermConstant { id }));
+        let p = self.id_gen.generate_id();
+        self.names.insert(p, "p".to_string());
+        let mut projected = Substitution::new();
+        for (i, (field, field_type)) in fields.iter().zip(&field_types).enumerate() {
+            let field_type = substitute(field_type, &projected);
+            let target = Rc::new(abstract_variable(&field_type, p));
+            let ty = Term::Product(TermProduct {
+                var: p,
+                source: record.clone(),
+                target: target.clone(),
+            });
+            let body = Term::Lambda(TermLambda {
+                var: p,
+                source_ty: record.clone(),
+                target: Rc::new(Term::Match(TermMatch {
+                    scrutinee: Rc::new(Term::Bound(TermBound { index: 0 })),
+                    return_type: target,
+                    branches: vec![TermMatchBranch {
+                        constructor_id,
+                        bound_vars: field_ids.clone(),
+                        body: Rc::new(Term::Bound(TermBound {
+                            index: fields.len() - 1 - i,
+                        })),
+                    }],
+                })),
+            });
+            self.global_env
+                .add_definition(projection_ids[i], Rc::new(ty), Rc::new(body))
+                .map_err(|e| Self::environment_error(e, field.name.pos()))?;
+
+            projected.add(
+                field_ids[i],
+                Rc::new(Term::Application(TermApplication {
+                    f: Rc::new(Term::Constant(TermConstant {
+                        id: projection_ids[i],
+                    })),
+                    args: vec![Term::Variable(TermVariable { id: p })],
+                })),
+            );
+        }
+        self.global_env
+            .add_record(id, projection_ids.clone())
+            .map_err(|e| match e {
+                RecordError::NotARecord { reason, .. } => TypeCheckError::new(
+                    TypeCheckErrorKind::NotARecord {
+                        name: name.to_string(),
+                        reason,
+                    },
+                    Some(pos.clone()),
+                ),
+                e => Self::environment_error(e.describe(&self.names), pos),
+            })?;
+
+        let fields = fields
+            .iter()
+            .map(|field| field.name.s().to_string())
+            .zip(projection_ids)
+            .collect();
+        self.structures.insert(id, fields);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::check_source;
+
+    #[test]
+    fn test_structures() {
+        let source = std::fs::read_to_string("../../testcases/felis/single/structure.fe").unwrap();
+        let type_checker = check_source(&source).unwrap();
+        assert_eq!(
+            type_checker.diagnostics(),
+            [
+                "Refl_ok : (p : Refl) -> eq nat (Refl_val p) (Refl_val p)".to_string(),
+                "Point_mk (S O) O".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_field_is_reported() {
+        let source = "#inductive nat : Set {
+    O: nat,
+}
+
+#structure Point : Set {
+    x : nat,
+}
+
+#definition z : (p : Point) -> nat {
+    p.z
+}
+";
+        assert_eq!(
+            check_source(source).err().map(|e| e.to_string()),
+            Some("No field z in type `Point` at 10:7".to_string())
+        );
+    }
+
+    #[test]
+    fn test_recursive_structure_is_reported_with_its_name() {
+        let source = "#structure P : Set { x : P }";
+        assert_eq!(
+            check_source(source).err().map(|e| e.to_string()),
+            Some("P is not a record: it is recursive at 1:12".to_string())
+        );
+    }
+}
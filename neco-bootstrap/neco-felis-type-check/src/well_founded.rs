@@ -0,0 +1,257 @@
+use std::rc::Rc;
+
+use neco_cic::{
+    global_environment::ConstantDefinition,
+    id::Id,
+    local_context::LocalContext,
+    reduction::whnf_with_env,
+    substitution::{Substitution, abstract_variable, abstract_variables, open, substitute},
+    term::{
+        Term, TermApplication, TermConstant, TermLambda, TermMatch, TermMatchBranch, TermProduct,
+        TermVariable,
+    },
+    typechecker::{self, TypeError},
+};
+use neco_felis_syn::{DefinitionDecreasingBy, ItemDefinition, PhaseParse, Pos};
+
+use crate::{Elaborator, TypeCheckError, TypeCheckErrorKind, TypeChecker};
+
+impl TypeChecker {
+    /// `#definition f : (x : A) -> T #decreasing_by wf { body }`, where
+    /// `wf : well_founded R`. In the body, `f` is the local function
+    /// `(y : A) -> R y x -> T[y]`: a recursive call takes a proof that its
+    /// first argument decreased.
+    ///
+    /// `f` is defined by structural recursion on an accessibility proof, in
+    /// the helper `f_acc : (x : A) -> Acc R x -> T` whose recursive calls are
+    /// `f_acc y (h y r)` for the function `h` of the proof. Then
+    /// `f := fun x => f_acc x (wf x)`, which computes when `wf` reduces to
+    /// `Acc_intro` on closed arguments.
+    ///
+    /// Both terms are built here rather than elaborated, so the kernel checks
+    /// them before they are added: `f_acc` with its recursive calls to a
+    /// local of its type and the body abstracted, then `f` with `f_acc`
+    /// declared.
+    pub(crate) fn process_well_founded_definition(
+        &mut self,
+        definition: &ItemDefinition<PhaseParse>,
+        decreasing_by: &DefinitionDecreasingBy<PhaseParse>,
+    ) -> Result<(), TypeCheckError> {
+        let name = definition.name().s();
+        let pos = definition.name().pos();
+        let acc = self.builtin("Acc", decreasing_by.keyword_decreasing_by().pos())?;
+        let intro = self
+            .global_env
+            .inductives
+            .get_inductive(acc)
+            .unwrap()
+            .constructors[0]
+            .name;
+        let id = self.declare(name);
+        let helper = self.declare(&format!("{name}_acc"));
+        let [y, r, rec] = [(); 3].map(|_| self.id_gen.generate_id());
+        self.names.insert(y, "y".to_string());
+        self.names.insert(r, "r".to_string());
+
+        let mut elab = Elaborator::new(self);
+        let type_term = elab.elaborate_type(definition.type_())?;
+        let (wf, relation) =
+            elab.elaborate_well_founded(decreasing_by.proof(), name, &type_term, acc)?;
+        let Term::Product(first) = &type_term else {
+            unreachable!("the type of a definition by well-founded recursion is a product");
+        };
+        let x = first.var;
+        let parameter_names = Self::parameter_names(definition.type_());
+        let first_name = parameter_names.first().cloned().flatten();
+        elab.push_local(first_name.as_deref(), x, first.source.clone());
+
+        // f : (y : A) -> R y x -> T[y]
+        let decreased = apply(relation.clone(), vec![var(y), var(x)]);
+        let recursive_type = pi(
+            y,
+            first.source.as_ref().clone(),
+            pi(r, decreased, open(&first.target, y)),
+        );
+        elab.push_local(Some(name), rec, Rc::new(recursive_type));
+
+        // The other parameters are in scope as for other definitions
+        let mut parameter_ids = Vec::new();
+        let mut body_type = open(&first.target, x);
+        for parameter_name in parameter_names.iter().skip(1) {
+            let Term::Product(product) = &body_type else {
+                break;
+            };
+            elab.push_local(
+                parameter_name.as_deref(),
+                product.var,
+                product.source.clone(),
+            );
+            parameter_ids.push(product.var);
+            body_type = open(&product.target, product.var);
+        }
+        let body = elab.check(definition.body(), &body_type);
+        elab.truncate_locals(0);
+        let body = body?;
+        elab.finish()?;
+        let type_term = elab.zonk(&type_term);
+        let (wf, relation, body) = (elab.zonk(&wf), elab.zonk(&relation), elab.zonk(&body));
+
+        let Term::Product(first) = &type_term else {
+            unreachable!("the type of a definition by well-founded recursion is a product");
+        };
+        let domain = first.source.as_ref().clone();
+        let result_type = open(&first.target, x);
+        let accessible = |z: Id| {
+            apply(
+                constant(acc),
+                vec![domain.clone(), relation.clone(), var(z)],
+            )
+        };
+        let [proof, index, h, recursive, functional] = [(); 5].map(|_| self.id_gen.generate_id());
+        for (id, name) in [(proof, "acc"), (index, "x"), (h, "h"), (functional, "F")] {
+            self.names.insert(id, name.to_string());
+        }
+        self.names.insert(recursive, format!("{name}_acc"));
+
+        // fun x acc => match acc { Acc_intro x' h => F x' (fun y r => f_acc y (h y r)) }
+        // for the function F of the body. The kernel does not relate `x'` to
+        // `x`, so the branch is stated at `x'`.
+        let decreased = apply(relation.clone(), vec![var(y), var(index)]);
+        let call = apply(
+            var(recursive),
+            vec![var(y), apply(var(h), vec![var(y), var(r)])],
+        );
+        let calls = lambda(y, domain.clone(), lambda(r, decreased, call));
+        let helper_with = |branch: Term| {
+            let bound_vars = vec![index, h];
+            let case = Term::Match(TermMatch {
+                scrutinee: Rc::new(var(proof)),
+                return_type: Rc::new(result_type.clone()),
+                branches: vec![TermMatchBranch {
+                    constructor_id: intro,
+                    body: Rc::new(abstract_variables(&branch, &bound_vars)),
+                    bound_vars,
+                }],
+            });
+            lambda(x, domain.clone(), lambda(proof, accessible(x), case))
+        };
+        let helper_type = pi(
+            x,
+            domain.clone(),
+            pi(proof, accessible(x), result_type.clone()),
+        );
+
+        // The elaborator checked the body with dependent matches, which the
+        // kernel does not refine types by, so the kernel checks the helper
+        // with the body as a local `F : (x : A) -> ((y : A) -> R y x -> T[y]) -> T[x]`
+        let smaller = apply(relation.clone(), vec![var(y), var(x)]);
+        let recursive_type = pi(y, domain.clone(), pi(r, smaller, open(&first.target, y)));
+        let functional_type = pi(
+            x,
+            domain.clone(),
+            pi(rec, recursive_type, result_type.clone()),
+        );
+        let mut ctx = LocalContext::new();
+        for (id, ty) in [(recursive, &helper_type), (functional, &functional_type)] {
+            ctx.extend(id, Rc::new(ty.clone()))
+                .expect("the locals are fresh");
+        }
+        let skeleton = helper_with(apply(var(functional), vec![var(index), calls.clone()]));
+        self.check_constructed(&ctx, &helper_type, &skeleton, pos)?;
+
+        // F x' g := fun .. => body[x := x', f := g]
+        let mut substitution = Substitution::new();
+        substitution.add(x, Rc::new(var(index)));
+        substitution.add(rec, Rc::new(calls));
+        let body = abstract_variables(&substitute(&body, &substitution), &parameter_ids);
+        let body = Self::create_lambda_from_product(
+            &open(&first.target, index),
+            body,
+            parameter_ids.len(),
+        );
+        let helper_body = helper_with(body);
+        let mut recursive_calls = Substitution::new();
+        recursive_calls.add(recursive, Rc::new(constant(helper)));
+        let helper_body = substitute(&helper_body, &recursive_calls);
+        self.check_termination(&[(helper, &helper_body)], pos)?;
+        self.global_env
+            .add_constant(ConstantDefinition::new_definition(
+                helper,
+                Rc::new(helper_type),
+                Rc::new(helper_body),
+            ))
+            .map_err(|e| Self::environment_error(e, pos))?;
+
+        // f := fun x => f_acc x (wf x)
+        let body = lambda(
+            x,
+            domain,
+            apply(constant(helper), vec![var(x), apply(wf, vec![var(x)])]),
+        );
+        self.check_constructed(&LocalContext::new(), &type_term, &body, pos)?;
+        self.global_env
+            .add_constant(ConstantDefinition::new_definition(
+                id,
+                Rc::new(type_term),
+                Rc::new(body),
+            ))
+            .map_err(|e| Self::environment_error(e, pos))?;
+        Ok(())
+    }
+
+    /// Checks with the kernel that `ty` is a type and `body` a term of it
+    fn check_constructed(
+        &self,
+        ctx: &LocalContext,
+        ty: &Term,
+        body: &Term,
+        pos: &Pos,
+    ) -> Result<(), TypeCheckError> {
+        let kernel_error = |e: TypeError| {
+            TypeCheckError::new(
+                TypeCheckErrorKind::Kernel(e.describe(&self.names)),
+                Some(pos.clone()),
+            )
+        };
+        let sort = typechecker::infer_type(ctx, &self.global_env, ty).map_err(kernel_error)?;
+        if !matches!(whnf_with_env(&sort, &self.global_env), Term::Sort(_)) {
+            return Err(kernel_error(TypeError::NotAType {
+                term: Rc::new(ty.clone()),
+                ty: sort,
+            }));
+        }
+        typechecker::check_type(ctx, &self.global_env, body, ty).map_err(kernel_error)
+    }
+}
+
+fn var(id: Id) -> Term {
+    Term::Variable(TermVariable { id })
+}
+
+fn constant(id: Id) -> Term {
+    Term::Constant(TermConstant { id })
+}
+
+fn apply(f: Term, args: Vec<Term>) -> Term {
+    Term::Application(TermApplication {
+        f: Rc::new(f),
+        args,
+    })
+}
+
+/// The product binding the variable `var` in `target`
+fn pi(var: Id, source: Term, target: Term) -> Term {
+    Term::Product(TermProduct {
+        var,
+        source: Rc::new(source),
+        target: Rc::new(abstract_variable(&target, var)),
+    })
+}
+
+fn lambda(var: Id, source_ty: Term, body: Term) -> Term {
+    Term::Lambda(TermLambda {
+        var,
+        source_ty: Rc::new(source_ty),
+        target: Rc::new(abstract_variable(&body, var)),
+    })
+}
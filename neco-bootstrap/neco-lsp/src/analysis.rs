@@ -0,0 +1,395 @@
+use std::collections::HashMap;
+
+use neco_felis_rename::{Occurrence, rename_file_indexed};
+use neco_felis_syn::{
+    File, FileId, FileIdGenerator, Item, Parse, PhaseParse, Pos, ProcTerm, Statement, Statements,
+    Term,
+    token::{Token, TokenVariable},
+};
+use neco_felis_type_check::{PhaseTyped, ProcType, TypeChecker};
+
+/// A range of characters on a line. Lines and columns start at 1, as in `Pos`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    pub len: usize,
+}
+
+impl Span {
+    fn contains(&self, line: usize, column: usize) -> bool {
+        self.line == line && self.column <= column && column < self.column + self.len
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error = 1,
+    Warning = 2,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub span: Span,
+    pub severity: Severity,
+    /// The phase that reported it: `parse`, `rename` or `type`
+    pub source: &'static str,
+    pub message: String,
+}
+
+/// The kinds of the document symbols, numbered as in the protocol
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Function = 12,
+    Constant = 14,
+    Enum = 10,
+    EnumMember = 22,
+    Struct = 23,
+    Array = 18,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub span: Span,
+    /// The constructors of an inductive type
+    pub children: Vec<Symbol>,
+}
+
+/// What the server knows about a version of a document
+#[derive(Debug, Default)]
+pub struct Analysis {
+    pub diagnostics: Vec<Diagnostic>,
+    pub symbols: Vec<Symbol>,
+    occurrences: Vec<Occurrence>,
+    /// Hover texts, by the position of the declaration of the name
+    hovers: HashMap<Pos, String>,
+}
+
+impl Analysis {
+    /// Parses, renames and type checks `source`. Renaming and type checking
+    /// need the whole file to parse, and type checking needs it to rename.
+    pub fn new(source: &str) -> Analysis {
+        let mut analysis = Analysis::default();
+        let lines: Vec<&str> = source.lines().collect();
+        let file_id = FileIdGenerator::new().generate_file_id();
+
+        let tokens = match Token::try_lex(source, file_id) {
+            Ok(tokens) => tokens,
+            Err(error) => {
+                let message = format!("Parse error: unexpected character `{}`", error.c);
+                analysis.error(&lines, "parse", &error.pos, &message);
+                return analysis;
+            }
+        };
+        let (items, error) = parse_items(&tokens);
+        for item in &items {
+            item_symbols(item, &mut analysis.symbols);
+        }
+        if let Some((i, message)) = error {
+            let pos = tokens[i].pos().clone();
+            analysis.error(&lines, "parse", &pos, &message);
+            return analysis;
+        }
+
+        let file = File { items, ext: () };
+        analysis.check(&lines, file_id, &file);
+        analysis
+    }
+
+    fn check(&mut self, lines: &[&str], file_id: FileId, file: &File<PhaseParse>) {
+        let renamed = match rename_file_indexed(file) {
+            Ok(renamed) => renamed,
+            Err(error) => {
+                self.error(lines, "rename", &error.pos, &error.kind.to_string());
+                return;
+            }
+        };
+        for warning in &renamed.warnings {
+            self.diagnostics.push(Diagnostic {
+                span: span_at(lines, &warning.pos),
+                severity: Severity::Warning,
+                source: "rename",
+                message: format!("{} ({})", warning.message, warning.lint.name()),
+            });
+        }
+        self.occurrences = renamed.occurrences;
+
+        let mut checker = TypeChecker::new();
+        let typed = checker.type_file(file, &renamed.file);
+        // The types of the items checked before an error are still known
+        item_hovers(&checker, file.items(), &mut self.hovers);
+        match typed {
+            Ok(typed) => {
+                for item in typed.items() {
+                    if let Item::Proc(proc) = item {
+                        proc_hovers(proc, &mut self.hovers);
+                    }
+                }
+            }
+            Err(error) => {
+                // Errors of a whole file are shown on its first line
+                let pos = error
+                    .pos()
+                    .cloned()
+                    .unwrap_or_else(|| Pos::new(file_id, 1, 1));
+                self.error(lines, "type", &pos, &error.kind().to_string());
+            }
+        }
+    }
+
+    fn error(&mut self, lines: &[&str], source: &'static str, pos: &Pos, message: &str) {
+        self.diagnostics.push(Diagnostic {
+            span: span_at(lines, pos),
+            severity: Severity::Error,
+            source,
+            message: message.to_string(),
+        });
+    }
+
+    fn occurrence_at(&self, line: usize, column: usize) -> Option<&Occurrence> {
+        self.occurrences
+            .iter()
+            .find(|occurrence| occurrence_span(occurrence).contains(line, column))
+    }
+
+    fn declaration(&self, occurrence: &Occurrence) -> Option<&Occurrence> {
+        self.occurrences
+            .iter()
+            .find(|other| other.declaration && other.id == occurrence.id)
+    }
+
+    /// The declaration of the name at a position
+    pub fn definition(&self, line: usize, column: usize) -> Option<Span> {
+        let occurrence = self.occurrence_at(line, column)?;
+        self.declaration(occurrence).map(occurrence_span)
+    }
+
+    /// The uses of the name at a position, in the order of the source
+    pub fn references(&self, line: usize, column: usize, include_declaration: bool) -> Vec<Span> {
+        let Some(occurrence) = self.occurrence_at(line, column) else {
+            return Vec::new();
+        };
+        self.occurrences
+            .iter()
+            .filter(|other| other.id == occurrence.id)
+            .filter(|other| include_declaration || !other.declaration)
+            .map(occurrence_span)
+            .collect()
+    }
+
+    /// The type of the name at a position, with the span of the name
+    pub fn hover(&self, line: usize, column: usize) -> Option<(String, Span)> {
+        let occurrence = self.occurrence_at(line, column)?;
+        let declaration = self.declaration(occurrence)?;
+        let hover = self.hovers.get(&declaration.pos)?;
+        Some((hover.clone(), occurrence_span(occurrence)))
+    }
+}
+
+/// The items of a file up to the first that does not parse, with the index of
+/// the token it starts at and the error
+fn parse_items(tokens: &[Token]) -> (Vec<Item<PhaseParse>>, Option<(usize, String)>) {
+    let mut items = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        match Item::parse(tokens, &mut i) {
+            Ok(Some(item)) => items.push(item),
+            Ok(None) => {
+                return (
+                    items,
+                    Some((i, "Parse error: expected an item".to_string())),
+                );
+            }
+            Err(error) => return (items, Some((i, error.to_string()))),
+        }
+    }
+    (items, None)
+}
+
+fn occurrence_span(occurrence: &Occurrence) -> Span {
+    Span {
+        line: occurrence.pos.line(),
+        column: occurrence.pos.column(),
+        len: occurrence.name.chars().count(),
+    }
+}
+
+/// The span of the name or keyword at a position, at least one character long
+fn span_at(lines: &[&str], pos: &Pos) -> Span {
+    let len = lines.get(pos.line().wrapping_sub(1)).map_or(0, |line| {
+        line.chars()
+            .skip(pos.column().saturating_sub(1))
+            .take_while(|c| c.is_alphanumeric() || "_#'".contains(*c))
+            .count()
+    });
+    Span {
+        line: pos.line(),
+        column: pos.column(),
+        len: len.max(1),
+    }
+}
+
+fn name_span(name: &TokenVariable) -> Span {
+    Span {
+        line: name.pos().line(),
+        column: name.pos().column(),
+        len: name.s().chars().count(),
+    }
+}
+
+/// The symbols of an item. The items of a `#mutual` are listed as the others.
+fn item_symbols(item: &Item<PhaseParse>, symbols: &mut Vec<Symbol>) {
+    let symbol = |name: &TokenVariable, kind, children| Symbol {
+        name: name.s().to_string(),
+        kind,
+        span: name_span(name),
+        children,
+    };
+    let symbol = match item {
+        Item::Definition(definition) => symbol(&definition.name, SymbolKind::Function, vec![]),
+        Item::Theorem(theorem) => symbol(&theorem.name, SymbolKind::Function, vec![]),
+        Item::Axiom(axiom) => symbol(&axiom.name, SymbolKind::Constant, vec![]),
+        Item::Inductive(inductive) => {
+            let constructors = inductive
+                .branches()
+                .iter()
+                .map(|branch| symbol(&branch.name, SymbolKind::EnumMember, vec![]))
+                .collect();
+            symbol(&inductive.name, SymbolKind::Enum, constructors)
+        }
+        Item::Structure(structure) => symbol(&structure.name, SymbolKind::Struct, vec![]),
+        Item::UseBuiltin(use_builtin) => symbol(&use_builtin.name, SymbolKind::Constant, vec![]),
+        Item::Proc(proc) => symbol(&proc.name, SymbolKind::Function, vec![]),
+        Item::Array(array) => symbol(&array.name, SymbolKind::Array, vec![]),
+        Item::Struct(item_struct) => symbol(&item_struct.name, SymbolKind::Struct, vec![]),
+        Item::Mutual(mutual) => {
+            for item in &mutual.items {
+                item_symbols(item, symbols);
+            }
+            return;
+        }
+        Item::PrintAxioms(_)
+        | Item::Check(_)
+        | Item::Eval(_)
+        | Item::Print(_)
+        | Item::Entrypoint(_)
+        | Item::Allow(_) => return,
+    };
+    symbols.push(symbol);
+}
+
+/// The types of the global constants checked by `checker`, and the builtins
+fn item_hovers(
+    checker: &TypeChecker,
+    items: &[Item<PhaseParse>],
+    hovers: &mut HashMap<Pos, String>,
+) {
+    for item in items {
+        match item {
+            Item::Definition(definition) => add_type(checker, hovers, &definition.name),
+            Item::Theorem(theorem) => add_type(checker, hovers, &theorem.name),
+            Item::Axiom(axiom) => add_type(checker, hovers, &axiom.name),
+            Item::Structure(structure) => add_type(checker, hovers, &structure.name),
+            Item::Inductive(inductive) => {
+                add_type(checker, hovers, &inductive.name);
+                for branch in inductive.branches() {
+                    add_type(checker, hovers, &branch.name);
+                }
+            }
+            Item::Mutual(mutual) => item_hovers(checker, &mutual.items, hovers),
+            Item::UseBuiltin(use_builtin) => {
+                let builtin = use_builtin.builtin_name.s();
+                hovers.insert(
+                    use_builtin.name.pos().clone(),
+                    format!("#use_builtin \"{builtin}\" #as {}", use_builtin.name.s()),
+                );
+            }
+            _ => {}
+        }
+    }
+}
+
+fn add_type(checker: &TypeChecker, hovers: &mut HashMap<Pos, String>, name: &TokenVariable) {
+    if let Some(ty) = checker.type_of(name.s()) {
+        hovers.insert(name.pos().clone(), format!("{} : {ty}", name.s()));
+    }
+}
+
+/// The signature of a procedure and the types of its parameters and locals
+fn proc_hovers(proc: &neco_felis_syn::ItemProc<PhaseTyped>, hovers: &mut HashMap<Pos, String>) {
+    let signature = &proc.ext;
+    hovers.insert(
+        proc.name.pos().clone(),
+        format!("{} : {signature}", proc.name.s()),
+    );
+
+    // The parameters in the order of the signature, as `process_proc` reads them
+    let mut params = signature.params.iter();
+    let mut ty = proc.ty.as_ref();
+    loop {
+        match ty {
+            Term::ArrowDep(arrow) => {
+                if let Some(param_ty) = params.next() {
+                    add_local(hovers, arrow.from().variable(), param_ty);
+                }
+                ty = arrow.to();
+            }
+            Term::ArrowNodep(arrow) => {
+                if !matches!(arrow.from(), Term::Unit(_)) {
+                    params.next();
+                }
+                ty = arrow.to();
+            }
+            Term::Paren(paren) => ty = paren.term(),
+            _ => break,
+        }
+    }
+    statements_hovers(&proc.proc_block.statements, hovers);
+}
+
+fn add_local(hovers: &mut HashMap<Pos, String>, name: &TokenVariable, ty: &ProcType) {
+    hovers.insert(name.pos().clone(), format!("{} : {ty}", name.s()));
+}
+
+fn statements_hovers(statements: &Statements<PhaseTyped>, hovers: &mut HashMap<Pos, String>) {
+    match statements {
+        Statements::Then(then) => {
+            statement_hovers(&then.head, hovers);
+            statements_hovers(&then.tail, hovers);
+        }
+        Statements::Statement(statement) => statement_hovers(statement, hovers),
+        Statements::Nil => {}
+    }
+}
+
+fn statement_hovers(statement: &Statement<PhaseTyped>, hovers: &mut HashMap<Pos, String>) {
+    match statement {
+        Statement::Let(let_stmt) => {
+            add_local(hovers, &let_stmt.variable, &let_stmt.ext.1);
+            proc_term_hovers(&let_stmt.value, hovers);
+        }
+        Statement::LetMut(let_mut) => {
+            let (_, _, ty) = &let_mut.ext;
+            add_local(hovers, &let_mut.variable, ty);
+            let reference = ProcType::Reference(Box::new(ty.clone()));
+            add_local(hovers, &let_mut.reference_variable, &reference);
+            proc_term_hovers(&let_mut.value, hovers);
+        }
+        Statement::Loop(statement_loop) => statements_hovers(statement_loop.body(), hovers),
+        Statement::Expr(term) => proc_term_hovers(term, hovers),
+        _ => {}
+    }
+}
+
+/// The locals of the blocks of an `#if`
+fn proc_term_hovers(term: &ProcTerm<PhaseTyped>, hovers: &mut HashMap<Pos, String>) {
+    if let ProcTerm::If(if_expr) = term {
+        statements_hovers(&if_expr.condition, hovers);
+        statements_hovers(&if_expr.then_body, hovers);
+        if let Some(else_clause) = &if_expr.else_clause {
+            statements_hovers(&else_clause.else_body, hovers);
+        }
+    }
+}
@@ -0,0 +1,6 @@
+fn main() -> std::io::Result<()> {
+    let stdin = std::io::stdin();
+    let stdout = std::io::stdout();
+    let code = neco_lsp::serve(stdin.lock(), stdout.lock())?;
+    std::process::exit(code);
+}
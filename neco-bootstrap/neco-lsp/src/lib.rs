@@ -0,0 +1,17 @@
+//! A language server for Felis, speaking JSON-RPC over stdio.
+//!
+//! Each open document is parsed, renamed and type checked when it changes.
+//! The server publishes the errors and lint warnings of these phases, and
+//! answers go-to-definition, find-references, hover and document symbol
+//! requests from the `VariableId`s of the renamer and the types of the type
+//! checker.
+
+mod analysis;
+pub mod json;
+mod server;
+pub mod transport;
+
+pub use server::{Server, serve};
+
+#[cfg(test)]
+mod tests;
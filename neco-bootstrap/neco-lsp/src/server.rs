@@ -0,0 +1,324 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, Write},
+};
+
+use crate::{
+    analysis::{Analysis, Span, Symbol},
+    json::Json,
+    transport::{read_message, write_message},
+};
+
+// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// The open documents, analyzed each time they change. Documents are synced
+/// in full, and positions count characters rather than UTF-16 code units.
+#[derive(Debug, Default)]
+pub struct Server {
+    documents: HashMap<String, Analysis>,
+    shutdown: bool,
+    exited: bool,
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the client asked the server to exit
+    pub fn exited(&self) -> bool {
+        self.exited
+    }
+
+    /// The exit code of the process: 0 if the client asked for a shutdown
+    /// before the exit
+    pub fn exit_code(&self) -> i32 {
+        if self.shutdown { 0 } else { 1 }
+    }
+
+    /// Handles the content of a message, returning the messages to send back
+    pub fn handle_text(&mut self, text: &str) -> Vec<Json> {
+        match Json::parse(text) {
+            Ok(message) => self.handle(&message),
+            Err(error) => vec![error_response(Json::Null, PARSE_ERROR, &error.to_string())],
+        }
+    }
+
+    /// Handles a request or a notification. Notifications have no `id`.
+    pub fn handle(&mut self, message: &Json) -> Vec<Json> {
+        let method = message.get("method").and_then(Json::as_str).unwrap_or("");
+        let params = message.get("params").unwrap_or(&Json::Null);
+        let Some(id) = message.get("id").cloned() else {
+            return self.notification(method, params);
+        };
+
+        if self.shutdown {
+            return vec![error_response(
+                id,
+                INVALID_REQUEST,
+                "The server is shutting down",
+            )];
+        }
+        let result = match method {
+            "initialize" => Ok(initialize_result()),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Json::Null)
+            }
+            "textDocument/definition" => self.definition(params),
+            "textDocument/references" => self.references(params),
+            "textDocument/hover" => self.hover(params),
+            "textDocument/documentSymbol" => self.document_symbols(params),
+            _ => Err((METHOD_NOT_FOUND, format!("Unknown method: {method}"))),
+        };
+        match result {
+            Ok(result) => vec![Json::object([
+                ("jsonrpc", "2.0".into()),
+                ("id", id),
+                ("result", result),
+            ])],
+            Err((code, message)) => vec![error_response(id, code, &message)],
+        }
+    }
+
+    fn notification(&mut self, method: &str, params: &Json) -> Vec<Json> {
+        let uri = params
+            .get("textDocument")
+            .and_then(|document| document.get("uri"))
+            .and_then(Json::as_str);
+        match (method, uri) {
+            ("exit", _) => {
+                self.exited = true;
+                vec![]
+            }
+            ("textDocument/didOpen", Some(uri)) => {
+                let text = params
+                    .get("textDocument")
+                    .and_then(|document| document.get("text"))
+                    .and_then(Json::as_str)
+                    .unwrap_or("");
+                self.update(uri, text)
+            }
+            ("textDocument/didChange", Some(uri)) => {
+                // The last change has the whole text
+                let change = params
+                    .get("contentChanges")
+                    .and_then(Json::as_array)
+                    .and_then(|changes| changes.last());
+                match change
+                    .and_then(|change| change.get("text"))
+                    .and_then(Json::as_str)
+                {
+                    Some(text) => self.update(uri, text),
+                    None => vec![],
+                }
+            }
+            ("textDocument/didClose", Some(uri)) => {
+                self.documents.remove(uri);
+                vec![publish_diagnostics(uri, &Analysis::default())]
+            }
+            // `initialized`, `$/` notifications and the others are ignored
+            _ => vec![],
+        }
+    }
+
+    fn update(&mut self, uri: &str, text: &str) -> Vec<Json> {
+        let analysis = Analysis::new(text);
+        let notification = publish_diagnostics(uri, &analysis);
+        self.documents.insert(uri.to_string(), analysis);
+        vec![notification]
+    }
+
+    /// The analysis of the document and the position of a request, in the
+    /// lines and columns of `Pos`
+    fn position<'a>(
+        &'a self,
+        params: &'a Json,
+    ) -> Result<(&'a str, &'a Analysis, usize, usize), (i64, String)> {
+        let invalid = || {
+            (
+                INVALID_PARAMS,
+                "Expected a document and a position".to_string(),
+            )
+        };
+        let uri = params
+            .get("textDocument")
+            .and_then(|document| document.get("uri"))
+            .and_then(Json::as_str)
+            .ok_or_else(invalid)?;
+        let position = params.get("position").ok_or_else(invalid)?;
+        let line = position.get("line").and_then(Json::as_usize);
+        let character = position.get("character").and_then(Json::as_usize);
+        let (Some(line), Some(character)) = (line, character) else {
+            return Err(invalid());
+        };
+        let analysis = self
+            .documents
+            .get(uri)
+            .ok_or_else(|| (INVALID_PARAMS, format!("Unknown document: {uri}")))?;
+        Ok((uri, analysis, line + 1, character + 1))
+    }
+
+    fn definition(&self, params: &Json) -> Result<Json, (i64, String)> {
+        let (uri, analysis, line, column) = self.position(params)?;
+        Ok(analysis
+            .definition(line, column)
+            .map_or(Json::Null, |span| location(uri, span)))
+    }
+
+    fn references(&self, params: &Json) -> Result<Json, (i64, String)> {
+        let (uri, analysis, line, column) = self.position(params)?;
+        let include_declaration = params
+            .get("context")
+            .and_then(|context| context.get("includeDeclaration"))
+            .and_then(Json::as_bool)
+            .unwrap_or(true);
+        Ok(Json::Array(
+            analysis
+                .references(line, column, include_declaration)
+                .into_iter()
+                .map(|span| location(uri, span))
+                .collect(),
+        ))
+    }
+
+    fn hover(&self, params: &Json) -> Result<Json, (i64, String)> {
+        let (_, analysis, line, column) = self.position(params)?;
+        Ok(analysis
+            .hover(line, column)
+            .map_or(Json::Null, |(hover, span)| {
+                Json::object([
+                    (
+                        "contents",
+                        Json::object([("kind", "plaintext".into()), ("value", hover.into())]),
+                    ),
+                    ("range", range(span)),
+                ])
+            }))
+    }
+
+    fn document_symbols(&self, params: &Json) -> Result<Json, (i64, String)> {
+        let uri = params
+            .get("textDocument")
+            .and_then(|document| document.get("uri"))
+            .and_then(Json::as_str)
+            .ok_or_else(|| (INVALID_PARAMS, "Expected a document".to_string()))?;
+        let analysis = self
+            .documents
+            .get(uri)
+            .ok_or_else(|| (INVALID_PARAMS, format!("Unknown document: {uri}")))?;
+        Ok(Json::Array(analysis.symbols.iter().map(symbol).collect()))
+    }
+}
+
+/// Serves the messages of `reader` until the client asks to exit or closes
+/// the input, returning the exit code of the process
+pub fn serve(mut reader: impl BufRead, mut writer: impl Write) -> std::io::Result<i32> {
+    let mut server = Server::new();
+    while let Some(text) = read_message(&mut reader)? {
+        for message in server.handle_text(&text) {
+            write_message(&mut writer, &message)?;
+        }
+        if server.exited() {
+            break;
+        }
+    }
+    Ok(server.exit_code())
+}
+
+fn initialize_result() -> Json {
+    Json::object([
+        (
+            "capabilities",
+            Json::object([
+                // Full document sync
+                ("textDocumentSync", 1usize.into()),
+                ("definitionProvider", true.into()),
+                ("referencesProvider", true.into()),
+                ("hoverProvider", true.into()),
+                ("documentSymbolProvider", true.into()),
+            ]),
+        ),
+        (
+            "serverInfo",
+            Json::object([
+                ("name", "neco-lsp".into()),
+                ("version", env!("CARGO_PKG_VERSION").into()),
+            ]),
+        ),
+    ])
+}
+
+fn error_response(id: Json, code: i64, message: &str) -> Json {
+    Json::object([
+        ("jsonrpc", "2.0".into()),
+        ("id", id),
+        (
+            "error",
+            Json::object([("code", code.into()), ("message", message.into())]),
+        ),
+    ])
+}
+
+fn publish_diagnostics(uri: &str, analysis: &Analysis) -> Json {
+    let diagnostics = analysis
+        .diagnostics
+        .iter()
+        .map(|diagnostic| {
+            Json::object([
+                ("range", range(diagnostic.span)),
+                ("severity", (diagnostic.severity as usize).into()),
+                ("source", format!("neco-{}", diagnostic.source).into()),
+                ("message", diagnostic.message.clone().into()),
+            ])
+        })
+        .collect();
+    Json::object([
+        ("jsonrpc", "2.0".into()),
+        ("method", "textDocument/publishDiagnostics".into()),
+        (
+            "params",
+            Json::object([
+                ("uri", uri.into()),
+                ("diagnostics", Json::Array(diagnostics)),
+            ]),
+        ),
+    ])
+}
+
+fn position(line: usize, column: usize) -> Json {
+    Json::object([
+        ("line", (line - 1).into()),
+        ("character", (column - 1).into()),
+    ])
+}
+
+fn range(span: Span) -> Json {
+    Json::object([
+        ("start", position(span.line, span.column)),
+        ("end", position(span.line, span.column + span.len)),
+    ])
+}
+
+fn location(uri: &str, span: Span) -> Json {
+    Json::object([("uri", uri.into()), ("range", range(span))])
+}
+
+/// A `DocumentSymbol`. Items have no end position, so the range of a symbol
+/// is its name.
+fn symbol(symbol: &Symbol) -> Json {
+    Json::object([
+        ("name", symbol.name.clone().into()),
+        ("kind", (symbol.kind as usize).into()),
+        ("range", range(symbol.span)),
+        ("selectionRange", range(symbol.span)),
+        (
+            "children",
+            Json::Array(symbol.children.iter().map(self::symbol).collect()),
+        ),
+    ])
+}
@@ -0,0 +1,383 @@
+use crate::{json::Json, serve, transport::read_message};
+
+const URI: &str = "file:///test.fe";
+
+const SOURCE: &str = "#inductive nat : Set {
+    O: nat,
+    S: nat -> nat,
+}
+
+#definition two : nat {
+    S (S O)
+}
+
+#entrypoint main;
+
+#use_builtin \"syscall\" #as __syscall;
+#use_builtin \"u64\" #as __u64;
+#use_builtin \"u64_add\" #as __u64_add;
+
+#proc f : (x: __u64) -> (y: __u64) -> __u64 {
+    #let z = __u64_add x y;
+    #return z;
+}
+
+#proc main : () -> () {
+    #let syscall_id = 231u64;
+    #let error_code = f 40u64 2u64;
+    __syscall syscall_id error_code 0 0 0 0;
+}
+";
+
+fn request(id: usize, method: &str, params: Json) -> Json {
+    Json::object([
+        ("jsonrpc", "2.0".into()),
+        ("id", id.into()),
+        ("method", method.into()),
+        ("params", params),
+    ])
+}
+
+fn notification(method: &str, params: Json) -> Json {
+    Json::object([
+        ("jsonrpc", "2.0".into()),
+        ("method", method.into()),
+        ("params", params),
+    ])
+}
+
+fn did_open(text: &str) -> Json {
+    notification(
+        "textDocument/didOpen",
+        Json::object([(
+            "textDocument",
+            Json::object([
+                ("uri", URI.into()),
+                ("languageId", "felis".into()),
+                ("version", 1usize.into()),
+                ("text", text.into()),
+            ]),
+        )]),
+    )
+}
+
+/// The parameters of a request at a position, counted from 0 as in the
+/// protocol
+fn at(line: usize, character: usize) -> Json {
+    Json::object([
+        ("textDocument", Json::object([("uri", URI.into())])),
+        (
+            "position",
+            Json::object([("line", line.into()), ("character", character.into())]),
+        ),
+    ])
+}
+
+/// Runs the server on a script of messages, framed as a client sends them,
+/// returning the messages it sends back and its exit code
+fn run(script: &[Json]) -> (Vec<Json>, i32) {
+    let mut input = Vec::new();
+    for message in script {
+        crate::transport::write_message(&mut input, message).unwrap();
+    }
+    let mut output = Vec::new();
+    let code = serve(input.as_slice(), &mut output).unwrap();
+
+    let mut reader = output.as_slice();
+    let mut messages = Vec::new();
+    while let Some(text) = read_message(&mut reader).unwrap() {
+        messages.push(Json::parse(&text).unwrap());
+    }
+    (messages, code)
+}
+
+/// The result of the response to the request `id`
+fn result(messages: &[Json], id: usize) -> &Json {
+    let response = messages
+        .iter()
+        .find(|message| message.get("id").and_then(Json::as_usize) == Some(id))
+        .unwrap();
+    response.get("result").unwrap()
+}
+
+fn diagnostics(messages: &[Json]) -> Vec<String> {
+    let notification = messages
+        .iter()
+        .rfind(|message| {
+            message.get("method").and_then(Json::as_str) == Some("textDocument/publishDiagnostics")
+        })
+        .unwrap();
+    notification
+        .get("params")
+        .and_then(|params| params.get("diagnostics"))
+        .and_then(Json::as_array)
+        .unwrap()
+        .iter()
+        .map(|diagnostic| diagnostic.to_string())
+        .collect()
+}
+
+fn range(line: usize, start: usize, end: usize) -> String {
+    format!(
+        r#"{{"start":{{"line":{line},"character":{start}}},"end":{{"line":{line},"character":{end}}}}}"#
+    )
+}
+
+#[test]
+fn test_initialize_and_shutdown() {
+    let (messages, code) = run(&[
+        request(1, "initialize", Json::object([])),
+        notification("initialized", Json::object([])),
+        request(2, "shutdown", Json::Null),
+        notification("exit", Json::Null),
+    ]);
+    assert_eq!(messages.len(), 2);
+    let capabilities = result(&messages, 1).get("capabilities").unwrap();
+    assert_eq!(
+        capabilities.to_string(),
+        r#"{"textDocumentSync":1,"definitionProvider":true,"referencesProvider":true,"hoverProvider":true,"documentSymbolProvider":true}"#
+    );
+    assert_eq!(result(&messages, 2), &Json::Null);
+    assert_eq!(code, 0);
+
+    // An exit without a shutdown is an error
+    let (_, code) = run(&[notification("exit", Json::Null)]);
+    assert_eq!(code, 1);
+}
+
+#[test]
+fn test_errors() {
+    let (messages, _) = run(&[
+        request(1, "textDocument/unknown", Json::Null),
+        request(2, "textDocument/hover", Json::Null),
+    ]);
+    assert_eq!(
+        messages[0].get("error").unwrap().get("code"),
+        Some(&Json::from(-32601i64))
+    );
+    assert_eq!(
+        messages[1].get("error").unwrap().get("code"),
+        Some(&Json::from(-32602i64))
+    );
+
+    let mut server = crate::Server::new();
+    let responses = server.handle_text("{");
+    assert_eq!(
+        responses[0].to_string(),
+        r#"{"jsonrpc":"2.0","id":null,"error":{"code":-32700,"message":"unexpected end of input at byte 1"}}"#
+    );
+}
+
+#[test]
+fn test_no_diagnostics() {
+    let (messages, _) = run(&[did_open(SOURCE)]);
+    assert_eq!(diagnostics(&messages), Vec::<String>::new());
+}
+
+#[test]
+fn test_diagnostics() {
+    // A parse error
+    let source = SOURCE.replace("#return z;", "#return z");
+    let (messages, _) = run(&[did_open(&source)]);
+    assert_eq!(
+        diagnostics(&messages),
+        vec![format!(
+            r#"{{"range":{},"severity":1,"source":"neco-parse","message":"Parse error: expected ; after return expression"}}"#,
+            range(15, 0, 5)
+        )]
+    );
+
+    // An unbound name, and the warnings are published with it
+    let source = SOURCE.replace("__u64_add x y", "__u64_add x w");
+    let (messages, _) = run(&[did_open(&source)]);
+    assert_eq!(
+        diagnostics(&messages),
+        vec![format!(
+            r#"{{"range":{},"severity":1,"source":"neco-rename","message":"Unbound variable: w"}}"#,
+            range(16, 25, 26)
+        )]
+    );
+
+    // A type error
+    let source = SOURCE.replace("f 40u64 2u64", "f 40u64");
+    let (messages, _) = run(&[did_open(&source)]);
+    let published = diagnostics(&messages);
+    assert_eq!(published.len(), 1);
+    assert!(published[0].contains(r#""severity":1,"source":"neco-type""#));
+
+    // A lint warning, updated on change
+    let source = SOURCE.replace("#let z = __u64_add x y;", "#let z = __u64_add x x;");
+    let (messages, _) = run(&[
+        did_open(SOURCE),
+        notification(
+            "textDocument/didChange",
+            Json::object([
+                (
+                    "textDocument",
+                    Json::object([("uri", URI.into()), ("version", 2usize.into())]),
+                ),
+                (
+                    "contentChanges",
+                    Json::Array(vec![Json::object([("text", source.into())])]),
+                ),
+            ]),
+        ),
+    ]);
+    assert_eq!(
+        diagnostics(&messages),
+        vec![format!(
+            r#"{{"range":{},"severity":2,"source":"neco-rename","message":"Unused variable: y (unused_variables)"}}"#,
+            range(15, 25, 26)
+        )]
+    );
+}
+
+#[test]
+fn test_unfinished_item() {
+    let (messages, _) = run(&[did_open("#proc f : ")]);
+    let diagnostics = diagnostics(&messages);
+    assert_eq!(diagnostics.len(), 1);
+    assert!(diagnostics[0].contains(r#""source":"neco-parse""#));
+}
+
+#[test]
+fn test_unexpected_character() {
+    let source = "#definition x : nat { ! }";
+    let (messages, _) = run(&[
+        did_open(source),
+        request(1, "textDocument/hover", at(0, 22)),
+    ]);
+    assert_eq!(
+        diagnostics(&messages),
+        vec![format!(
+            r#"{{"range":{},"severity":1,"source":"neco-parse","message":"Parse error: unexpected character `!`"}}"#,
+            range(0, 22, 23)
+        )]
+    );
+    // The server is still running
+    assert_eq!(result(&messages, 1), &Json::Null);
+}
+
+#[test]
+fn test_definition_and_references() {
+    let (messages, _) = run(&[
+        did_open(SOURCE),
+        // `f` in `main`
+        request(1, "textDocument/definition", at(22, 22)),
+        // `x` in the body of `f`
+        request(2, "textDocument/definition", at(16, 23)),
+        request(3, "textDocument/references", at(15, 6)),
+        {
+            let mut params = at(16, 9);
+            if let Json::Object(members) = &mut params {
+                members.push((
+                    "context".to_string(),
+                    Json::object([("includeDeclaration", false.into())]),
+                ));
+            }
+            request(4, "textDocument/references", params)
+        },
+        // No name at the position
+        request(5, "textDocument/definition", at(0, 0)),
+    ]);
+    let location = |range| format!(r#"{{"uri":"{URI}","range":{range}}}"#);
+    assert_eq!(result(&messages, 1).to_string(), location(range(15, 6, 7)));
+    assert_eq!(
+        result(&messages, 2).to_string(),
+        location(range(15, 11, 12))
+    );
+    assert_eq!(
+        result(&messages, 3).to_string(),
+        format!(
+            "[{},{}]",
+            location(range(15, 6, 7)),
+            location(range(22, 22, 23))
+        )
+    );
+    assert_eq!(
+        result(&messages, 4).to_string(),
+        format!("[{}]", location(range(17, 12, 13)))
+    );
+    assert_eq!(result(&messages, 5), &Json::Null);
+}
+
+#[test]
+fn test_hover() {
+    let (messages, _) = run(&[
+        did_open(SOURCE),
+        request(1, "textDocument/hover", at(22, 22)),
+        request(2, "textDocument/hover", at(16, 25)),
+        request(3, "textDocument/hover", at(17, 12)),
+        request(4, "textDocument/hover", at(5, 12)),
+        request(5, "textDocument/hover", at(6, 7)),
+        request(6, "textDocument/hover", at(16, 15)),
+    ]);
+    let hover = |id| {
+        result(&messages, id)
+            .get("contents")
+            .and_then(|contents| contents.get("value"))
+            .and_then(Json::as_str)
+            .unwrap()
+            .to_string()
+    };
+    assert_eq!(hover(1), "f : u64 -> u64 -> u64");
+    assert_eq!(hover(2), "y : u64");
+    assert_eq!(hover(3), "z : u64");
+    assert_eq!(hover(4), "two : nat");
+    assert_eq!(hover(5), "S : nat -> nat");
+    assert_eq!(hover(6), "#use_builtin \"u64_add\" #as __u64_add");
+    assert_eq!(
+        result(&messages, 1).get("range").unwrap().to_string(),
+        range(22, 22, 23)
+    );
+}
+
+#[test]
+fn test_document_symbols() {
+    let (messages, _) = run(&[
+        did_open(SOURCE),
+        request(
+            1,
+            "textDocument/documentSymbol",
+            Json::object([("textDocument", Json::object([("uri", URI.into())]))]),
+        ),
+    ]);
+    let symbols: Vec<_> = result(&messages, 1)
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|symbol| {
+            let name = symbol.get("name").and_then(Json::as_str).unwrap();
+            let kind = symbol.get("kind").and_then(Json::as_usize).unwrap();
+            let children = symbol.get("children").and_then(Json::as_array).unwrap();
+            (name.to_string(), kind, children.len())
+        })
+        .collect();
+    let symbol = |name: &str, kind, children| (name.to_string(), kind, children);
+    assert_eq!(
+        symbols,
+        vec![
+            symbol("nat", 10, 2),
+            symbol("two", 12, 0),
+            symbol("__syscall", 14, 0),
+            symbol("__u64", 14, 0),
+            symbol("__u64_add", 14, 0),
+            symbol("f", 12, 0),
+            symbol("main", 12, 0),
+        ]
+    );
+}
+
+#[test]
+fn test_did_close() {
+    let (messages, _) = run(&[
+        did_open(&SOURCE.replace("x y", "x w")),
+        notification(
+            "textDocument/didClose",
+            Json::object([("textDocument", Json::object([("uri", URI.into())]))]),
+        ),
+        request(1, "textDocument/hover", at(22, 22)),
+    ]);
+    assert_eq!(diagnostics(&messages), Vec::<String>::new());
+    assert!(messages.last().unwrap().get("error").is_some());
+}
@@ -0,0 +1,68 @@
+use std::io::{BufRead, Write};
+
+use crate::json::Json;
+
+/// Reads the content of a message framed by a `Content-Length` header.
+/// Returns `None` at the end of the input.
+pub fn read_message(reader: &mut impl BufRead) -> std::io::Result<Option<String>> {
+    let mut content_length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            if content_length.is_some() {
+                break;
+            }
+            // Blank lines between messages
+            continue;
+        }
+        if let Some((name, value)) = line.split_once(':')
+            && name.eq_ignore_ascii_case("Content-Length")
+        {
+            content_length = value.trim().parse::<usize>().ok();
+        }
+    }
+
+    let mut content = vec![0; content_length.unwrap_or(0)];
+    reader.read_exact(&mut content)?;
+    String::from_utf8(content)
+        .map(Some)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+pub fn write_message(writer: &mut impl Write, message: &Json) -> std::io::Result<()> {
+    let content = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{content}", content.len())?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut buffer = Vec::new();
+        write_message(&mut buffer, &Json::object([("a", "é".into())])).unwrap();
+        write_message(&mut buffer, &Json::Null).unwrap();
+        assert!(buffer.starts_with(b"Content-Length: 10\r\n\r\n"));
+
+        let mut reader = buffer.as_slice();
+        assert_eq!(
+            read_message(&mut reader).unwrap().as_deref(),
+            Some(r#"{"a":"é"}"#)
+        );
+        assert_eq!(read_message(&mut reader).unwrap().as_deref(), Some("null"));
+        assert_eq!(read_message(&mut reader).unwrap(), None);
+    }
+
+    #[test]
+    fn test_other_headers() {
+        let input = "Content-Type: application/vscode-jsonrpc\r\ncontent-length: 2\r\n\r\n{}";
+        let mut reader = input.as_bytes();
+        assert_eq!(read_message(&mut reader).unwrap().as_deref(), Some("{}"));
+    }
+}
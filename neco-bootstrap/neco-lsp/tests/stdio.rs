@@ -0,0 +1,92 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use neco_lsp::{
+    json::Json,
+    transport::{read_message, write_message},
+};
+
+const SOURCE: &str = "#inductive eq : {A : Set} -> (x : A) -> A -> Prop {
+    eq_refl: {A : Set} -> (x : A) -> eq x x,
+}
+
+#inductive nat : Set {
+    O: nat,
+    S: nat -> nat,
+}
+
+#definition two : nat {
+    S (S O)
+}
+
+#theorem two_eq_two : eq two two {
+    eq_refl two
+}
+";
+
+fn message(id: Option<usize>, method: &str, params: Json) -> Json {
+    let mut members = vec![("jsonrpc".to_string(), "2.0".into())];
+    if let Some(id) = id {
+        members.push(("id".to_string(), id.into()));
+    }
+    members.push(("method".to_string(), method.into()));
+    members.push(("params".to_string(), params));
+    Json::Object(members)
+}
+
+/// Runs the binary as an editor would, and checks that everything it writes
+/// to stdout is a framed message
+#[test]
+fn test_stdout_has_only_messages() {
+    let did_open = Json::object([(
+        "textDocument",
+        Json::object([
+            ("uri", "file:///test.fe".into()),
+            ("languageId", "felis".into()),
+            ("version", 1usize.into()),
+            ("text", SOURCE.into()),
+        ]),
+    )]);
+    let mut input = Vec::new();
+    for message in [
+        message(Some(1), "initialize", Json::object([])),
+        message(None, "initialized", Json::object([])),
+        message(None, "textDocument/didOpen", did_open),
+        message(Some(2), "shutdown", Json::Null),
+        message(None, "exit", Json::Null),
+    ] {
+        write_message(&mut input, &message).unwrap();
+    }
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_neco-lsp"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(&input).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+
+    let mut reader = output.stdout.as_slice();
+    let mut reframed = Vec::new();
+    let mut messages = Vec::new();
+    while let Some(text) = read_message(&mut reader).unwrap() {
+        let message = Json::parse(&text).unwrap();
+        write_message(&mut reframed, &message).unwrap();
+        messages.push(message);
+    }
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&reframed)
+    );
+    // The responses to the two requests and the diagnostics of the document
+    assert_eq!(messages.len(), 3);
+    let diagnostics = messages[1]
+        .get("params")
+        .and_then(|params| params.get("diagnostics"))
+        .and_then(Json::as_array)
+        .unwrap();
+    assert_eq!(diagnostics, &[]);
+}
@@ -1,47 +1,100 @@
-pub struct Scope<K, V> {
+/// Where a name was declared, and how many times it was used since
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Declaration<P> {
+    pub declared_at: Option<P>,
+    pub uses: usize,
+}
+
+pub struct Scope<K, V, P = ()> {
     binds: Vec<(K, V)>,
+    /// The declaration of each of `binds`
+    declarations: Vec<Declaration<P>>,
 }
 
 impl<K, V> Scope<K, V> {
+    pub fn new() -> Scope<K, V> {
+        Scope::default()
+    }
+}
+
+impl<K, V, P> Default for Scope<K, V, P> {
+    fn default() -> Self {
+        Scope {
+            binds: Vec::new(),
+            declarations: Vec::new(),
+        }
+    }
+}
+
+impl<K, V, P> Scope<K, V, P> {
     pub fn set(&mut self, key: K, value: V) {
         self.binds.push((key, value));
+        self.declarations.push(Declaration {
+            declared_at: None,
+            uses: 0,
+        });
+    }
+
+    /// Binds `key`, declared at `pos`
+    pub fn declare(&mut self, key: K, value: V, pos: P) {
+        self.binds.push((key, value));
+        self.declarations.push(Declaration {
+            declared_at: Some(pos),
+            uses: 0,
+        });
     }
 
     pub fn get(&self, key: &K) -> Option<&V>
     where
         K: PartialEq,
     {
-        self.binds
-            .iter()
-            .rev()
-            .find_map(|(k, v)| if k == key { Some(v) } else { None })
+        self.position(key).map(|index| &self.binds[index].1)
+    }
+
+    /// The index of the most recent binding of `key`
+    fn position(&self, key: &K) -> Option<usize>
+    where
+        K: PartialEq,
+    {
+        self.binds.iter().rposition(|(k, _)| k == key)
     }
 
     pub fn binds(&self) -> &[(K, V)] {
         &self.binds
     }
+
+    /// The bindings of the scope with their declarations, in the order they
+    /// were bound
+    pub fn bindings(&self) -> impl Iterator<Item = (&K, &V, &Declaration<P>)> {
+        self.binds
+            .iter()
+            .zip(&self.declarations)
+            .map(|((k, v), declaration)| (k, v, declaration))
+    }
 }
 
-pub struct ScopeStack<K, V> {
-    scopes: Vec<Scope<K, V>>,
+pub struct ScopeStack<K, V, P = ()> {
+    scopes: Vec<Scope<K, V, P>>,
 }
 
-impl<K, V> Default for ScopeStack<K, V> {
+impl<K, V, P> Default for ScopeStack<K, V, P> {
     fn default() -> Self {
-        Self::new()
+        ScopeStack { scopes: Vec::new() }
     }
 }
 
 impl<K, V> ScopeStack<K, V> {
     pub fn new() -> ScopeStack<K, V> {
-        ScopeStack { scopes: Vec::new() }
+        ScopeStack::default()
     }
+}
 
+impl<K, V, P> ScopeStack<K, V, P> {
     pub fn enter_scope(&mut self) {
-        self.scopes.push(Scope { binds: Vec::new() });
+        self.scopes.push(Scope::default());
     }
 
-    pub fn leave_scope(&mut self) -> Scope<K, V> {
+    pub fn leave_scope(&mut self) -> Scope<K, V, P> {
         self.scopes
             .pop()
             .expect("Cannot leave scope: no scopes to leave")
@@ -53,6 +106,13 @@ impl<K, V> ScopeStack<K, V> {
         }
     }
 
+    /// Binds `key` in the current scope, declared at `pos`
+    pub fn declare(&mut self, key: K, value: V, pos: P) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.declare(key, value, pos);
+        }
+    }
+
     pub fn get(&self, key: &K) -> Option<&V>
     where
         K: PartialEq,
@@ -64,6 +124,37 @@ impl<K, V> ScopeStack<K, V> {
         }
         None
     }
+
+    /// Looks `key` up as `get` does, counting a use of its binding
+    pub fn use_key(&mut self, key: &K) -> Option<&V>
+    where
+        K: PartialEq,
+    {
+        for scope in self.scopes.iter_mut().rev() {
+            if let Some(index) = scope.position(key) {
+                scope.declarations[index].uses += 1;
+                return Some(&scope.binds[index].1);
+            }
+        }
+        None
+    }
+
+    /// The declaration that binding `key` in the current scope would shadow:
+    /// that of its binding in an enclosing scope. Binding a name again in
+    /// the same scope shadows nothing.
+    pub fn shadowed(&self, key: &K) -> Option<&Declaration<P>>
+    where
+        K: PartialEq,
+    {
+        let (current, enclosing) = self.scopes.split_last()?;
+        if current.position(key).is_some() {
+            return None;
+        }
+        enclosing
+            .iter()
+            .rev()
+            .find_map(|scope| scope.position(key).map(|index| &scope.declarations[index]))
+    }
 }
 
 #[cfg(test)]
@@ -72,27 +163,27 @@ mod tests {
 
     #[test]
     fn scope_set_adds_binding() {
-        let mut scope = Scope { binds: Vec::new() };
+        let mut scope = Scope::new();
         scope.set("key1", "value1");
         assert_eq!(scope.binds.len(), 1);
     }
 
     #[test]
     fn scope_get_finds_existing_key() {
-        let mut scope = Scope { binds: Vec::new() };
+        let mut scope = Scope::new();
         scope.set("key1", "value1");
         assert_eq!(scope.get(&"key1"), Some(&"value1"));
     }
 
     #[test]
     fn scope_get_returns_none_for_missing_key() {
-        let scope = Scope::<&str, &str> { binds: Vec::new() };
+        let scope = Scope::<&str, &str>::new();
         assert_eq!(scope.get(&"missing"), None);
     }
 
     #[test]
     fn scope_get_returns_most_recent_binding() {
-        let mut scope = Scope { binds: Vec::new() };
+        let mut scope = Scope::new();
         scope.set("key1", "value1");
         scope.set("key1", "value2");
         assert_eq!(scope.get(&"key1"), Some(&"value2"));
@@ -100,7 +191,7 @@ mod tests {
 
     #[test]
     fn scope_binds_returns_all_bindings() {
-        let mut scope = Scope { binds: Vec::new() };
+        let mut scope = Scope::new();
         scope.set("key1", "value1");
         scope.set("key2", "value2");
         assert_eq!(scope.binds(), &[("key1", "value1"), ("key2", "value2")]);
@@ -202,4 +293,52 @@ mod tests {
         stack.leave_scope();
         assert_eq!(stack.get(&"x"), Some(&1));
     }
+
+    #[test]
+    fn scope_stack_use_key_counts_uses_of_the_binding_found() {
+        let mut stack: ScopeStack<&str, i32, i32> = ScopeStack::default();
+        stack.enter_scope();
+        stack.declare("x", 1, 10);
+        stack.enter_scope();
+        stack.declare("x", 2, 20);
+        assert_eq!(stack.use_key(&"x"), Some(&2));
+        assert_eq!(stack.use_key(&"x"), Some(&2));
+        assert_eq!(stack.use_key(&"missing"), None);
+
+        let inner = stack.leave_scope();
+        let bindings: Vec<_> = inner.bindings().collect();
+        assert_eq!(
+            bindings,
+            vec![(
+                &"x",
+                &2,
+                &Declaration {
+                    declared_at: Some(20),
+                    uses: 2
+                }
+            )]
+        );
+        let outer = stack.leave_scope();
+        let (_, _, declaration) = outer.bindings().next().unwrap();
+        assert_eq!(declaration.uses, 0);
+    }
+
+    #[test]
+    fn scope_stack_shadowed_finds_bindings_of_enclosing_scopes() {
+        let mut stack: ScopeStack<&str, i32, i32> = ScopeStack::default();
+        stack.enter_scope();
+        stack.declare("x", 1, 10);
+        assert_eq!(stack.shadowed(&"x"), None);
+        stack.enter_scope();
+        assert_eq!(
+            stack.shadowed(&"x"),
+            Some(&Declaration {
+                declared_at: Some(10),
+                uses: 0
+            })
+        );
+        stack.declare("x", 2, 20);
+        assert_eq!(stack.shadowed(&"x"), None);
+        assert_eq!(stack.shadowed(&"y"), None);
+    }
 }